@@ -0,0 +1,127 @@
+//! CI status (GitHub checks and commit statuses, GitLab pipelines) of the
+//! commit a branch or revision points to, for badges in the branch and commit
+//! lists.
+
+use super::hosting::{self, HostingProvider};
+use super::{github, gitlab};
+use git2::Repository;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckState {
+    Pass,
+    Fail,
+    Pending,
+    Neutral, // skipped or informational; does not affect the overall state
+}
+
+/// One check run, status context or pipeline job
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub state: CheckState,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckStatus {
+    pub commit: String,
+    pub provider: HostingProvider,
+    pub state: Option<CheckState>, // None when no checks ran on the commit
+    pub checks: Vec<Check>,
+}
+
+/// Any failure fails the commit, then any pending check keeps it pending;
+/// neutral checks only count when they are all there is
+fn overall_state(checks: &[Check]) -> Option<CheckState> {
+    let has = |state| checks.iter().any(|check| check.state == state);
+    if has(CheckState::Fail) {
+        Some(CheckState::Fail)
+    } else if has(CheckState::Pending) {
+        Some(CheckState::Pending)
+    } else if has(CheckState::Pass) {
+        Some(CheckState::Pass)
+    } else if checks.is_empty() {
+        None
+    } else {
+        Some(CheckState::Neutral)
+    }
+}
+
+/// CI status of the commit `reference` (a branch or any revision) points to.
+/// The checks are read from `remote`, else from the remote a local branch
+/// tracks, else from the repository's default remote.
+pub fn get_check_status(
+    repo: &Repository,
+    reference: &str,
+    remote: Option<&str>,
+) -> Result<CheckStatus, String> {
+    let commit = repo
+        .revparse_single(reference)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| e.message().to_string())?
+        .id()
+        .to_string();
+    let remote = match remote {
+        Some(remote) => remote.to_string(),
+        None => match hosting::branch_upstream(repo, reference) {
+            Some((remote, _)) => remote,
+            None => hosting::default_hosted_remote(repo)?.0,
+        },
+    };
+    let hosted = hosting::hosted_remote(repo, &remote)?;
+    let token = hosting::token_for_remote(repo, &remote, &hosted);
+
+    let checks = match hosted.provider {
+        HostingProvider::GitHub => {
+            github::get_commit_checks(&hosted.path, &commit, token.as_ref())?
+        }
+        HostingProvider::GitLab => {
+            gitlab::get_commit_checks(&hosted.host, &hosted.path, &commit, token.as_ref())?
+        }
+        HostingProvider::Bitbucket => {
+            return Err("CI status is not available for Bitbucket repositories".to_string())
+        }
+    };
+    Ok(CheckStatus {
+        commit,
+        provider: hosted.provider,
+        state: overall_state(&checks),
+        checks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(state: CheckState) -> Check {
+        Check {
+            name: "ci".to_string(),
+            state,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn test_overall_state() {
+        assert_eq!(overall_state(&[]), None);
+        assert_eq!(
+            overall_state(&[check(CheckState::Pass), check(CheckState::Neutral)]),
+            Some(CheckState::Pass)
+        );
+        assert_eq!(
+            overall_state(&[check(CheckState::Pending), check(CheckState::Pass)]),
+            Some(CheckState::Pending)
+        );
+        assert_eq!(
+            overall_state(&[check(CheckState::Pending), check(CheckState::Fail)]),
+            Some(CheckState::Fail)
+        );
+        assert_eq!(
+            overall_state(&[check(CheckState::Neutral)]),
+            Some(CheckState::Neutral)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use super::checks::{self, CheckStatus};
+use super::github::{self, PullRequest};
+use super::hosting::{self, CreatePullRequestOptions, CreatedPullRequest, RemotePageKind};
+use crate::git::commands::run_blocking;
+use crate::git::repository::GitOperationResult;
+use crate::git::validation::{open_validated_repo, validate_repo_path_string};
+
+/// Open pull requests of the repository's GitHub remote
+#[tauri::command]
+pub async fn list_pull_requests(repo_path: String) -> Result<Vec<PullRequest>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        github::list_pull_requests(&repo)
+    })
+    .await
+}
+
+/// Fetch a pull request into the local branch `pr/<number>` and check it out
+#[tauri::command]
+pub async fn checkout_pull_request(
+    repo_path: String,
+    number: u64,
+) -> Result<GitOperationResult, String> {
+    run_blocking(move || {
+        let canonical = validate_repo_path_string(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        github::checkout_pull_request(&repo, &canonical.to_string_lossy(), number)
+    })
+    .await
+}
+
+/// Open a pull request for the current branch: created through the GitHub API
+/// when possible, otherwise the provider's "new pull request" page is opened
+/// in the browser
+#[tauri::command]
+pub async fn create_pull_request(
+    repo_path: String,
+    options: Option<CreatePullRequestOptions>,
+) -> Result<CreatedPullRequest, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let created = hosting::create_pull_request(&repo, &options.unwrap_or_default())?;
+        if !created.created {
+            tauri_plugin_opener::open_url(&created.url, None::<&str>)
+                .map_err(|e| format!("Failed to open {}: {}", created.url, e))?;
+        }
+        Ok(created)
+    })
+    .await
+}
+
+/// Open the web page of the repository, a branch, a commit or a file on the
+/// remote's hosting provider in the default browser; returns the URL
+#[tauri::command]
+pub async fn open_remote_url(
+    repo_path: String,
+    kind: RemotePageKind,
+    id: Option<String>,
+    path: Option<String>,
+    remote: Option<String>,
+) -> Result<String, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let url = hosting::remote_page_url(
+            &repo,
+            remote.as_deref(),
+            kind,
+            id.as_deref(),
+            path.as_deref(),
+        )?;
+        tauri_plugin_opener::open_url(&url, None::<&str>)
+            .map_err(|e| format!("Failed to open {}: {}", url, e))?;
+        Ok(url)
+    })
+    .await
+}
+
+/// Permanent web link to a file at a commit, optionally to a line range
+/// (`start_line`..=`end_line`, 1-based), for copying or sharing
+#[tauri::command]
+pub async fn get_permalink(
+    repo_path: String,
+    commit: String,
+    path: String,
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+    remote: Option<String>,
+) -> Result<String, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let lines = start_line.map(|start| (start, end_line.unwrap_or(start)));
+        hosting::permalink(&repo, remote.as_deref(), &commit, &path, lines)
+    })
+    .await
+}
+
+/// CI checks (GitHub checks, GitLab pipeline jobs) of the commit `reference`
+/// points to, on the branch's upstream remote unless `remote` is given
+#[tauri::command]
+pub async fn get_check_status(
+    repo_path: String,
+    reference: String,
+    remote: Option<String>,
+) -> Result<CheckStatus, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        checks::get_check_status(&repo, &reference, remote.as_deref())
+    })
+    .await
+}
@@ -0,0 +1,487 @@
+//! Pull requests of repositories hosted on GitHub.
+//!
+//! The GitHub remote is found among the repository's remotes (`upstream` first,
+//! so a fork lists the pull requests of the project it was forked from). API
+//! requests use the personal access token attached to that remote, if any;
+//! public repositories can be listed without one, within GitHub's rate limit.
+
+use super::checks::{Check, CheckState};
+use super::hosting::{parse_remote_url, HostingProvider};
+use crate::git::environment::git_command;
+use crate::git::remote_tokens::{self, RemoteToken};
+use crate::git::repository::{
+    create_error_result, create_success_result, git_checkout, GitOperationResult,
+};
+use git2::{BranchType, Repository};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const API_URL: &str = "https://api.github.com";
+
+/// A repository on github.com
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitHubRepository {
+    pub owner: String,
+    pub name: String,
+}
+
+/// An open pull request
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub author: String,
+    pub head_branch: String,
+    pub head_repository: Option<String>, // "owner/name"; None if the fork was deleted
+    pub base_branch: String,
+    pub draft: bool,
+    pub url: String,
+    pub updated_at: String,
+    pub local_branch: Option<String>, // the branch it was checked out into, if it exists
+}
+
+// The parts of the GitHub API response that are used
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    number: u64,
+    title: String,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    updated_at: String,
+    user: Option<ApiUser>,
+    head: ApiBranch,
+    base: ApiBranch,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct ApiBranch {
+    #[serde(rename = "ref")]
+    branch: String,
+    repo: Option<ApiRepository>,
+}
+
+#[derive(Deserialize)]
+struct ApiRepository {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+    #[serde(default)]
+    errors: Vec<ApiErrorDetail>, // validation failures, such as an existing pull request
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetail {
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiCheckRuns {
+    check_runs: Vec<ApiCheckRun>,
+}
+
+#[derive(Deserialize)]
+struct ApiCheckRun {
+    name: String,
+    status: String,             // queued, in_progress, completed
+    conclusion: Option<String>, // set once completed
+    html_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiCombinedStatus {
+    statuses: Vec<ApiStatus>,
+}
+
+#[derive(Deserialize)]
+struct ApiStatus {
+    context: String,
+    state: String, // success, failure, error, pending
+    target_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiRepositoryInfo {
+    default_branch: String,
+}
+
+/// A pull request to open through the API
+#[derive(Serialize)]
+pub struct NewPullRequest<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub head: &'a str,         // "branch", or "owner:branch" for a branch of a fork
+    pub base: Option<&'a str>, // the repository's default branch when None
+    pub draft: bool,
+}
+
+/// A pull request opened through the API
+pub struct OpenedPullRequest {
+    pub number: u64,
+    pub url: String,
+    pub base_branch: String,
+}
+
+/// The GitHub repository a remote URL points to, in any of the forms
+/// `hosting::parse_remote_url` reads
+fn parse_github_url(url: &str) -> Option<GitHubRepository> {
+    let hosted = parse_remote_url(url).filter(|r| r.provider == HostingProvider::GitHub)?;
+    let (owner, name) = hosted.path.split_once('/')?;
+    Some(GitHubRepository {
+        owner: owner.to_string(),
+        name: name.to_string(),
+    })
+}
+
+/// The remote pointing to GitHub: `upstream`, then `origin`, then any other
+pub fn find_github_remote(repo: &Repository) -> Result<(String, GitHubRepository), String> {
+    let remotes = repo.remotes().map_err(|e| e.message().to_string())?;
+    let mut found: Vec<(String, GitHubRepository)> = remotes
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let remote = repo.find_remote(name).ok()?;
+            let github = parse_github_url(remote.url()?)?;
+            Some((name.to_string(), github))
+        })
+        .collect();
+    found.sort_by_key(|(name, _)| match name.as_str() {
+        "upstream" => 0,
+        "origin" => 1,
+        _ => 2,
+    });
+    found
+        .into_iter()
+        .next()
+        .ok_or_else(|| "This repository has no GitHub remote".to_string())
+}
+
+/// The token attached to the remote, or to the HTTPS URL of the repository
+/// when the remote uses SSH
+fn token_for(repo: &Repository, remote: &str, github: &GitHubRepository) -> Option<RemoteToken> {
+    let remote_url = repo
+        .find_remote(remote)
+        .ok()
+        .and_then(|r| r.url().map(String::from));
+    let https_url = format!("https://github.com/{}/{}", github.owner, github.name);
+    remote_url
+        .into_iter()
+        .chain([format!("{}.git", https_url), https_url])
+        .find_map(|url| remote_tokens::token_for_url(&url))
+}
+
+fn api_agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(30)))
+        .http_status_as_error(false)
+        .user_agent("Forky")
+        .build()
+        .into()
+}
+
+/// Read a successful (2xx) response as JSON, turning error statuses into
+/// readable messages
+fn read_response<T: serde::de::DeserializeOwned>(
+    mut response: ureq::http::Response<ureq::Body>,
+    token: Option<&RemoteToken>,
+) -> Result<T, String> {
+    let status = response.status().as_u16();
+    if (200..300).contains(&status) {
+        return response
+            .body_mut()
+            .read_json()
+            .map_err(|e| format!("Unexpected response from GitHub: {}", e));
+    }
+
+    let error = response.body_mut().read_json::<ApiError>().ok();
+    let detail = error
+        .map(|e| match e.errors.into_iter().find_map(|d| d.message) {
+            Some(reason) => format!("{} ({})", e.message, reason),
+            None => e.message,
+        })
+        .unwrap_or_default();
+    Err(match status {
+        401 => "GitHub rejected the access token attached to this remote".to_string(),
+        403 | 429 if detail.contains("rate limit") => {
+            "GitHub API rate limit exceeded; attach an access token to the remote to raise it"
+                .to_string()
+        }
+        404 if token.is_none() => {
+            "Repository not found on GitHub; private repositories need an access token".to_string()
+        }
+        _ => format!("GitHub returned {}: {}", status, detail),
+    })
+}
+
+fn with_headers<B>(
+    mut request: ureq::RequestBuilder<B>,
+    token: Option<&RemoteToken>,
+) -> ureq::RequestBuilder<B> {
+    request = request
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token.token));
+    }
+    request
+}
+
+/// GET a GitHub API path
+fn api_get<T: serde::de::DeserializeOwned>(
+    path: &str,
+    token: Option<&RemoteToken>,
+) -> Result<T, String> {
+    let request = with_headers(api_agent().get(format!("{}{}", API_URL, path)), token);
+    let response = request
+        .call()
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    read_response(response, token)
+}
+
+/// POST a JSON body to a GitHub API path
+fn api_post<B: Serialize, T: serde::de::DeserializeOwned>(
+    path: &str,
+    token: &RemoteToken,
+    body: &B,
+) -> Result<T, String> {
+    let request = with_headers(
+        api_agent().post(format!("{}{}", API_URL, path)),
+        Some(token),
+    );
+    let response = request
+        .send_json(body)
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    read_response(response, Some(token))
+}
+
+/// The token attached to a remote of the repository, if it points to GitHub
+pub fn token_for_remote(repo: &Repository, remote: &str) -> Option<RemoteToken> {
+    let url = repo.find_remote(remote).ok()?.url().map(String::from)?;
+    let github = parse_github_url(&url)?;
+    token_for(repo, remote, &github)
+}
+
+/// Open a pull request in the repository at `path` ("owner/name")
+pub fn create_pull_request(
+    path: &str,
+    token: &RemoteToken,
+    pull: NewPullRequest,
+) -> Result<OpenedPullRequest, String> {
+    let default_branch;
+    let base = match pull.base {
+        Some(base) => base,
+        None => {
+            let info: ApiRepositoryInfo = api_get(&format!("/repos/{}", path), Some(token))?;
+            default_branch = info.default_branch;
+            &default_branch
+        }
+    };
+    let created: ApiPullRequest = api_post(
+        &format!("/repos/{}/pulls", path),
+        token,
+        &NewPullRequest {
+            base: Some(base),
+            ..pull
+        },
+    )?;
+    Ok(OpenedPullRequest {
+        number: created.number,
+        url: created.html_url,
+        base_branch: created.base.branch,
+    })
+}
+
+fn check_run_state(run: &ApiCheckRun) -> CheckState {
+    if run.status != "completed" {
+        return CheckState::Pending;
+    }
+    match run.conclusion.as_deref() {
+        Some("success") => CheckState::Pass,
+        Some("neutral") | Some("skipped") | Some("stale") => CheckState::Neutral,
+        Some(_) => CheckState::Fail,
+        None => CheckState::Pending,
+    }
+}
+
+/// Check runs (GitHub Actions and other apps) and commit statuses (older CI
+/// integrations) of a commit of the repository at `path` ("owner/name")
+pub fn get_commit_checks(
+    path: &str,
+    sha: &str,
+    token: Option<&RemoteToken>,
+) -> Result<Vec<Check>, String> {
+    let runs: ApiCheckRuns = api_get(
+        &format!("/repos/{}/commits/{}/check-runs?per_page=100", path, sha),
+        token,
+    )?;
+    let combined: ApiCombinedStatus =
+        api_get(&format!("/repos/{}/commits/{}/status", path, sha), token)?;
+
+    let mut checks: Vec<Check> = runs
+        .check_runs
+        .iter()
+        .map(|run| Check {
+            name: run.name.clone(),
+            state: check_run_state(run),
+            url: run.html_url.clone(),
+        })
+        .collect();
+    checks.extend(combined.statuses.into_iter().map(|status| Check {
+        name: status.context,
+        state: match status.state.as_str() {
+            "success" => CheckState::Pass,
+            "pending" => CheckState::Pending,
+            _ => CheckState::Fail,
+        },
+        url: status.target_url,
+    }));
+    Ok(checks)
+}
+
+/// Branch a pull request is checked out into
+fn local_branch_name(number: u64) -> String {
+    format!("pr/{}", number)
+}
+
+/// Open pull requests of the repository's GitHub remote, most recently updated
+/// first (the first 100)
+pub fn list_pull_requests(repo: &Repository) -> Result<Vec<PullRequest>, String> {
+    let (remote, github) = find_github_remote(repo)?;
+    let token = token_for(repo, &remote, &github);
+    let pulls: Vec<ApiPullRequest> = api_get(
+        &format!(
+            "/repos/{}/{}/pulls?state=open&sort=updated&direction=desc&per_page=100",
+            github.owner, github.name
+        ),
+        token.as_ref(),
+    )?;
+
+    Ok(pulls
+        .into_iter()
+        .map(|pull| {
+            let local_branch = local_branch_name(pull.number);
+            PullRequest {
+                number: pull.number,
+                title: pull.title,
+                author: pull.user.map(|u| u.login).unwrap_or_default(),
+                head_branch: pull.head.branch,
+                head_repository: pull.head.repo.map(|r| r.full_name),
+                base_branch: pull.base.branch,
+                draft: pull.draft,
+                url: pull.html_url,
+                updated_at: pull.updated_at,
+                local_branch: repo
+                    .find_branch(&local_branch, BranchType::Local)
+                    .is_ok()
+                    .then_some(local_branch),
+            }
+        })
+        .collect())
+}
+
+fn run_git(repo_path: &str, args: &[&str]) -> Result<std::process::Output, String> {
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path);
+    remote_tokens::configure_command(&mut cmd, repo_path);
+    cmd.args(args)
+        .output()
+        .map_err(|e| format!("Failed to execute git {}: {}", args[0], e))
+}
+
+/// Fetch `refs/pull/<number>/head` into the local branch `pr/<number>` and check
+/// it out. An existing branch is fast-forwarded; if the pull request was
+/// force-pushed the fetch is rejected rather than discarding local commits.
+pub fn checkout_pull_request(
+    repo: &Repository,
+    repo_path: &str,
+    number: u64,
+) -> Result<GitOperationResult, String> {
+    let (remote, _) = find_github_remote(repo)?;
+    let branch = local_branch_name(number);
+    let pull_ref = format!("refs/pull/{}/head", number);
+    let current = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(String::from));
+
+    if current.as_deref() == Some(branch.as_str()) {
+        // git refuses to fetch into the checked-out branch; fetch then fast-forward
+        let output = run_git(repo_path, &["fetch", &remote, &pull_ref])?;
+        if !output.status.success() {
+            return Ok(error_result(&output));
+        }
+        let output = run_git(repo_path, &["merge", "--ff-only", "FETCH_HEAD"])?;
+        if !output.status.success() {
+            return Ok(error_result(&output));
+        }
+        return Ok(create_success_result(format!(
+            "Updated '{}' with pull request #{}",
+            branch, number
+        )));
+    }
+
+    let refspec = format!("{}:refs/heads/{}", pull_ref, branch);
+    let output = run_git(repo_path, &["fetch", &remote, &refspec])?;
+    if !output.status.success() {
+        return Ok(error_result(&output));
+    }
+    git_checkout(repo_path, &branch)
+}
+
+fn error_result(output: &std::process::Output) -> GitOperationResult {
+    create_error_result(
+        &String::from_utf8_lossy(&output.stderr),
+        &String::from_utf8_lossy(&output.stdout),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_url_forms() {
+        let expected = Some(GitHubRepository {
+            owner: "calambrenet".to_string(),
+            name: "forky".to_string(),
+        });
+        for url in [
+            "https://github.com/calambrenet/forky.git",
+            "https://user@GitHub.com/calambrenet/forky/",
+            "git@github.com:calambrenet/forky.git",
+            "ssh://git@github.com:22/calambrenet/forky",
+        ] {
+            assert_eq!(parse_github_url(url), expected, "{}", url);
+        }
+        assert_eq!(
+            parse_github_url("git@gitlab.com:calambrenet/forky.git"),
+            None
+        );
+        assert_eq!(parse_github_url("https://github.com/calambrenet"), None);
+    }
+
+    #[test]
+    fn test_find_github_remote_prefers_upstream() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.remote("origin", "git@github.com:me/forky.git")
+            .unwrap();
+        repo.remote("mirror", "https://gitlab.com/me/forky.git")
+            .unwrap();
+        assert_eq!(find_github_remote(&repo).unwrap().0, "origin");
+
+        repo.remote("upstream", "https://github.com/calambrenet/forky.git")
+            .unwrap();
+        let (remote, github) = find_github_remote(&repo).unwrap();
+        assert_eq!(remote, "upstream");
+        assert_eq!(github.owner, "calambrenet");
+    }
+}
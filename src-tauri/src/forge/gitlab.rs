@@ -0,0 +1,98 @@
+//! GitLab API access (gitlab.com and self-hosted instances).
+//!
+//! Requests use the personal access token attached to the remote, if any;
+//! public projects can be read without one.
+
+use super::checks::{Check, CheckState};
+use super::hosting::encode;
+use crate::git::remote_tokens::RemoteToken;
+use serde::Deserialize;
+use std::time::Duration;
+
+// The parts of a commit status (pipeline job) that are used
+#[derive(Deserialize)]
+struct ApiCommitStatus {
+    name: String,
+    status: String, // created, pending, running, success, failed, canceled, skipped, manual
+    target_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: Option<serde_json::Value>,
+    error: Option<String>,
+}
+
+/// GET a path of the API of the GitLab instance at `host`
+fn api_get<T: serde::de::DeserializeOwned>(
+    host: &str,
+    path: &str,
+    token: Option<&RemoteToken>,
+) -> Result<T, String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(30)))
+        .http_status_as_error(false)
+        .user_agent("Forky")
+        .build()
+        .into();
+    let mut request = agent.get(format!("https://{}/api/v4{}", host, path));
+    if let Some(token) = token {
+        request = request.header("PRIVATE-TOKEN", &token.token);
+    }
+    let mut response = request
+        .call()
+        .map_err(|e| format!("Failed to reach {}: {}", host, e))?;
+
+    let status = response.status().as_u16();
+    if status == 200 {
+        return response
+            .body_mut()
+            .read_json()
+            .map_err(|e| format!("Unexpected response from {}: {}", host, e));
+    }
+    let detail = response
+        .body_mut()
+        .read_json::<ApiError>()
+        .ok()
+        .and_then(|e| e.error.or_else(|| e.message.map(|m| m.to_string())))
+        .unwrap_or_default();
+    Err(match status {
+        401 => "GitLab rejected the access token attached to this remote".to_string(),
+        404 if token.is_none() => {
+            "Project not found on GitLab; private projects need an access token".to_string()
+        }
+        _ => format!("GitLab returned {}: {}", status, detail),
+    })
+}
+
+/// Pipeline jobs that ran on a commit of the project at `path`, latest run of
+/// each job only
+pub fn get_commit_checks(
+    host: &str,
+    path: &str,
+    sha: &str,
+    token: Option<&RemoteToken>,
+) -> Result<Vec<Check>, String> {
+    let statuses: Vec<ApiCommitStatus> = api_get(
+        host,
+        &format!(
+            "/projects/{}/repository/commits/{}/statuses?per_page=100",
+            encode(path, false),
+            sha
+        ),
+        token,
+    )?;
+    Ok(statuses
+        .into_iter()
+        .map(|status| Check {
+            name: status.name,
+            state: match status.status.as_str() {
+                "success" => CheckState::Pass,
+                "failed" | "canceled" => CheckState::Fail,
+                "skipped" | "manual" => CheckState::Neutral,
+                _ => CheckState::Pending,
+            },
+            url: status.target_url,
+        })
+        .collect())
+}
@@ -0,0 +1,586 @@
+//! Hosting providers (GitHub, GitLab, Bitbucket) recognized from remote URLs.
+//!
+//! A remote URL in any of its forms (HTTPS, SCP-like `git@host:path`, `ssh://`)
+//! is reduced to the host and repository path, which is enough to build the
+//! pages of the provider's web interface, such as the one that opens a new pull
+//! request (merge request on GitLab) for a pushed branch.
+
+use super::github::{self, NewPullRequest};
+use crate::git::remote_tokens::{self, RemoteToken};
+use crate::git::repository::get_default_remote;
+use git2::Repository;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostingProvider {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+/// A repository on a hosting provider
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostedRepository {
+    pub provider: HostingProvider,
+    pub host: String,
+    pub path: String, // "owner/name"; GitLab groups can nest ("group/sub/name")
+}
+
+impl HostedRepository {
+    /// The repository's home page
+    pub fn web_url(&self) -> String {
+        format!("https://{}/{}", self.host, self.path)
+    }
+
+    /// The account or group that owns the repository
+    pub fn owner(&self) -> &str {
+        self.path.rsplit_once('/').map_or("", |(owner, _)| owner)
+    }
+}
+
+/// The provider a host belongs to; self-hosted GitLab instances usually have
+/// "gitlab" in their name
+fn provider_for_host(host: &str) -> Option<HostingProvider> {
+    let host = host.to_lowercase();
+    if host == "github.com" || host == "www.github.com" {
+        Some(HostingProvider::GitHub)
+    } else if host == "bitbucket.org" || host == "www.bitbucket.org" {
+        Some(HostingProvider::Bitbucket)
+    } else if host == "gitlab.com" || host.split('.').any(|part| part == "gitlab") {
+        Some(HostingProvider::GitLab)
+    } else {
+        None
+    }
+}
+
+/// The hosted repository a remote URL points to, or None for other hosts and
+/// local paths
+pub fn parse_remote_url(url: &str) -> Option<HostedRepository> {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        None => url.split_once(':')?,
+    };
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split(':').next().unwrap_or(host).to_lowercase();
+    let provider = provider_for_host(&host)?;
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let segments: Vec<&str> = path.split('/').collect();
+    let nested_ok = provider == HostingProvider::GitLab || segments.len() == 2;
+    if segments.len() < 2 || !nested_ok || segments.iter().any(|s| s.is_empty()) {
+        return None;
+    }
+    Some(HostedRepository {
+        provider,
+        host: host.trim_start_matches("www.").to_string(),
+        path: path.to_string(),
+    })
+}
+
+/// The hosted repository a remote of `repo` points to
+pub fn hosted_remote(repo: &Repository, remote: &str) -> Result<HostedRepository, String> {
+    let remote_handle = repo
+        .find_remote(remote)
+        .map_err(|e| e.message().to_string())?;
+    let url = remote_handle
+        .url()
+        .ok_or_else(|| "The remote URL is not valid UTF-8".to_string())?;
+    parse_remote_url(url).ok_or_else(|| {
+        format!(
+            "Remote '{}' is not hosted on GitHub, GitLab or Bitbucket",
+            remote
+        )
+    })
+}
+
+/// The token attached to the remote, or to the HTTPS URL of the repository
+/// when the remote uses SSH
+pub fn token_for_remote(
+    repo: &Repository,
+    remote: &str,
+    hosted: &HostedRepository,
+) -> Option<RemoteToken> {
+    let remote_url = repo
+        .find_remote(remote)
+        .ok()
+        .and_then(|r| r.url().map(String::from));
+    let https_url = hosted.web_url();
+    remote_url
+        .into_iter()
+        .chain([format!("{}.git", https_url), https_url])
+        .find_map(|url| remote_tokens::token_for_url(&url))
+}
+
+/// Percent-encode everything but unreserved characters (and `/` when
+/// `keep_slash`, for branch names in URL paths)
+pub fn encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn query(params: &[(&str, Option<&str>)]) -> String {
+    params
+        .iter()
+        .filter_map(|(key, value)| {
+            Some(format!(
+                "{}={}",
+                encode(key, false),
+                encode((*value)?, false)
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The page that opens a pull request from `head_branch` of `head` into
+/// `base_branch` of `base` (the default branch when None), prefilled with the
+/// title and description where the provider supports it
+pub fn compare_url(
+    base: &HostedRepository,
+    head: &HostedRepository,
+    head_branch: &str,
+    base_branch: Option<&str>,
+    title: Option<&str>,
+    body: Option<&str>,
+) -> String {
+    match base.provider {
+        HostingProvider::GitHub => {
+            let head_ref = if head.path == base.path {
+                encode(head_branch, true)
+            } else {
+                format!(
+                    "{}:{}",
+                    encode(head.owner(), false),
+                    encode(head_branch, true)
+                )
+            };
+            let range = match base_branch {
+                Some(base_branch) => format!("{}...{}", encode(base_branch, true), head_ref),
+                None => head_ref,
+            };
+            let params = query(&[("expand", Some("1")), ("title", title), ("body", body)]);
+            format!("{}/compare/{}?{}", base.web_url(), range, params)
+        }
+        HostingProvider::GitLab => {
+            let params = query(&[
+                ("merge_request[source_branch]", Some(head_branch)),
+                ("merge_request[target_branch]", base_branch),
+                ("merge_request[title]", title),
+                ("merge_request[description]", body),
+            ]);
+            format!("{}/-/merge_requests/new?{}", base.web_url(), params)
+        }
+        HostingProvider::Bitbucket => {
+            let params = query(&[("source", Some(head_branch)), ("dest", base_branch)]);
+            format!("{}/pull-requests/new?{}", base.web_url(), params)
+        }
+    }
+}
+
+/// A page of a repository's web interface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemotePage<'a> {
+    Repository,
+    Branch(&'a str),
+    Commit(&'a str),
+    File { reference: &'a str, path: &'a str }, // a file at a branch, tag or commit
+}
+
+/// The URL of `page` on the provider hosting `repository`
+pub fn page_url(repository: &HostedRepository, page: RemotePage) -> String {
+    let base = repository.web_url();
+    // GitLab puts repository pages under "/-/"
+    let section = |github: &str, gitlab: &str, bitbucket: &str| match repository.provider {
+        HostingProvider::GitHub => github.to_string(),
+        HostingProvider::GitLab => format!("-/{}", gitlab),
+        HostingProvider::Bitbucket => bitbucket.to_string(),
+    };
+    match page {
+        RemotePage::Repository => base,
+        RemotePage::Branch(branch) => {
+            format!(
+                "{}/{}/{}",
+                base,
+                section("tree", "tree", "src"),
+                encode(branch, true)
+            )
+        }
+        RemotePage::Commit(sha) => {
+            format!(
+                "{}/{}/{}",
+                base,
+                section("commit", "commit", "commits"),
+                encode(sha, false)
+            )
+        }
+        RemotePage::File { reference, path } => format!(
+            "{}/{}/{}/{}",
+            base,
+            section("blob", "blob", "src"),
+            encode(reference, true),
+            encode(path.trim_start_matches('/'), true)
+        ),
+    }
+}
+
+/// The remote whose web pages are opened: the one the current branch tracks,
+/// then the repository's default remote, then the first on a known provider
+pub fn default_hosted_remote(repo: &Repository) -> Result<(String, HostedRepository), String> {
+    let tracked = current_branch(repo)
+        .and_then(|branch| branch_upstream(repo, &branch))
+        .map(|(remote, _)| remote);
+    let remotes = repo.remotes().map_err(|e| e.message().to_string())?;
+    let names = tracked
+        .into_iter()
+        .chain(get_default_remote(repo)?)
+        .chain(remotes.iter().flatten().map(String::from));
+    for name in names {
+        if let Ok(hosted) = hosted_remote(repo, &name) {
+            return Ok((name, hosted));
+        }
+    }
+    Err("This repository has no remote on GitHub, GitLab or Bitbucket".to_string())
+}
+
+/// The fragment that highlights lines `start`..=`end` of a file page
+fn line_anchor(provider: HostingProvider, start: u32, end: u32) -> String {
+    match (provider, start == end) {
+        (HostingProvider::GitHub, true) | (HostingProvider::GitLab, true) => format!("#L{}", start),
+        (HostingProvider::GitHub, false) => format!("#L{}-L{}", start, end),
+        (HostingProvider::GitLab, false) => format!("#L{}-{}", start, end),
+        (HostingProvider::Bitbucket, true) => format!("#lines-{}", start),
+        (HostingProvider::Bitbucket, false) => format!("#lines-{}:{}", start, end),
+    }
+}
+
+/// A permanent link to `path` at `commit` (resolved to its full id, so the
+/// link keeps pointing at the same content), optionally to a line range
+pub fn permalink(
+    repo: &Repository,
+    remote: Option<&str>,
+    commit: &str,
+    path: &str,
+    lines: Option<(u32, u32)>,
+) -> Result<String, String> {
+    let hosted = match remote {
+        Some(remote) => hosted_remote(repo, remote)?,
+        None => default_hosted_remote(repo)?.1,
+    };
+    let commit = repo
+        .revparse_single(commit)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| e.message().to_string())?;
+    let sha = commit.id().to_string();
+    let mut url = page_url(
+        &hosted,
+        RemotePage::File {
+            reference: &sha,
+            path,
+        },
+    );
+    if let Some((start, end)) = lines {
+        let (start, end) = (start.min(end).max(1), start.max(end).max(1));
+        url.push_str(&line_anchor(hosted.provider, start, end));
+    }
+    Ok(url)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemotePageKind {
+    Repository,
+    Branch,
+    Commit,
+    File,
+}
+
+/// The web URL of a repository page. `id` is the branch (a local branch is
+/// mapped to the remote branch it tracks) or the commit; for files it is the
+/// branch, tag or commit to show the file at (HEAD's commit when None).
+/// `remote` defaults to [`default_hosted_remote`].
+pub fn remote_page_url(
+    repo: &Repository,
+    remote: Option<&str>,
+    kind: RemotePageKind,
+    id: Option<&str>,
+    path: Option<&str>,
+) -> Result<String, String> {
+    let hosted = match remote {
+        Some(remote) => hosted_remote(repo, remote)?,
+        None => default_hosted_remote(repo)?.1,
+    };
+    let required = |what: &str| id.ok_or_else(|| format!("A {} is required", what));
+
+    let url = match kind {
+        RemotePageKind::Repository => page_url(&hosted, RemotePage::Repository),
+        RemotePageKind::Branch => {
+            let branch = required("branch")?;
+            let remote_branch = branch_upstream(repo, branch).map(|(_, upstream)| upstream);
+            let branch = remote_branch.as_deref().unwrap_or(branch);
+            page_url(&hosted, RemotePage::Branch(branch))
+        }
+        RemotePageKind::Commit => {
+            let commit = repo
+                .revparse_single(required("commit")?)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| e.message().to_string())?;
+            page_url(&hosted, RemotePage::Commit(&commit.id().to_string()))
+        }
+        RemotePageKind::File => {
+            let path = path.ok_or_else(|| "A file path is required".to_string())?;
+            let head_commit;
+            let reference = match id {
+                Some(reference) => reference,
+                None => {
+                    head_commit = repo
+                        .head()
+                        .and_then(|head| head.peel_to_commit())
+                        .map_err(|e| e.message().to_string())?
+                        .id()
+                        .to_string();
+                    &head_commit
+                }
+            };
+            page_url(&hosted, RemotePage::File { reference, path })
+        }
+    };
+    Ok(url)
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct CreatePullRequestOptions {
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub base_branch: Option<String>, // the target repository's default branch when None
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// A pull request opened through the provider's API, or the page to open one
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedPullRequest {
+    pub provider: HostingProvider,
+    pub url: String,
+    pub created: bool, // false: `url` is the provider's "new pull request" page
+    pub number: Option<u64>,
+    pub head_branch: String,
+    pub base_branch: Option<String>,
+}
+
+/// The remote and remote branch a local branch tracks, if it has an upstream
+pub fn branch_upstream(repo: &Repository, branch: &str) -> Option<(String, String)> {
+    let refname = format!("refs/heads/{}", branch);
+    let remote = repo.branch_upstream_remote(&refname).ok()?;
+    let merge = repo
+        .config()
+        .and_then(|config| config.get_string(&format!("branch.{}.merge", branch)))
+        .ok()?;
+    let upstream_branch = merge.strip_prefix("refs/heads/")?.to_string();
+    Some((remote.as_str()?.to_string(), upstream_branch))
+}
+
+/// The current branch, or None when HEAD is detached or unborn
+fn current_branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    head.name()?.strip_prefix("refs/heads/").map(String::from)
+}
+
+/// The remote and branch the current branch is pushed to
+fn pushed_head(repo: &Repository) -> Result<(String, String), String> {
+    let branch = current_branch(repo)
+        .ok_or_else(|| "Check out a branch to create a pull request".to_string())?;
+    branch_upstream(repo, &branch)
+        .ok_or_else(|| format!("Push '{}' before creating a pull request", branch))
+}
+
+/// Open a pull request for the current (pushed) branch. On GitHub, with a title
+/// and an access token attached to the remote, it is created through the API;
+/// otherwise the provider's "new pull request" page is returned, prefilled, for
+/// the user to finish in the browser. On GitHub the pull request targets the
+/// `upstream` remote when there is one, so forks propose to the original project.
+pub fn create_pull_request(
+    repo: &Repository,
+    options: &CreatePullRequestOptions,
+) -> Result<CreatedPullRequest, String> {
+    let (head_remote, head_branch) = pushed_head(repo)?;
+    let head = hosted_remote(repo, &head_remote)?;
+    let title = options
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+    let body = options.body.as_deref().filter(|b| !b.trim().is_empty());
+    let base_branch = options
+        .base_branch
+        .as_deref()
+        .filter(|b| !b.trim().is_empty());
+
+    let (base_remote, base) = match head.provider {
+        HostingProvider::GitHub => match github::find_github_remote(repo) {
+            Ok((remote, _)) => (remote.clone(), hosted_remote(repo, &remote)?),
+            Err(_) => (head_remote.clone(), head.clone()),
+        },
+        _ => (head_remote.clone(), head.clone()),
+    };
+
+    if base.provider == HostingProvider::GitHub {
+        if let (Some(title), Some(token)) = (title, github::token_for_remote(repo, &base_remote)) {
+            let head_ref = if head.path == base.path {
+                head_branch.clone()
+            } else {
+                format!("{}:{}", head.owner(), head_branch)
+            };
+            let created = github::create_pull_request(
+                &base.path,
+                &token,
+                NewPullRequest {
+                    title,
+                    body: body.unwrap_or_default(),
+                    head: &head_ref,
+                    base: base_branch,
+                    draft: options.draft,
+                },
+            )?;
+            return Ok(CreatedPullRequest {
+                provider: base.provider,
+                url: created.url,
+                created: true,
+                number: Some(created.number),
+                head_branch,
+                base_branch: Some(created.base_branch),
+            });
+        }
+    }
+
+    Ok(CreatedPullRequest {
+        provider: base.provider,
+        url: compare_url(&base, &head, &head_branch, base_branch, title, body),
+        created: false,
+        number: None,
+        head_branch,
+        base_branch: base_branch.map(String::from),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosted(provider: HostingProvider, host: &str, path: &str) -> HostedRepository {
+        HostedRepository {
+            provider,
+            host: host.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_remote_url_providers() {
+        assert_eq!(
+            parse_remote_url("git@github.com:calambrenet/forky.git"),
+            Some(hosted(
+                HostingProvider::GitHub,
+                "github.com",
+                "calambrenet/forky"
+            ))
+        );
+        assert_eq!(
+            parse_remote_url("https://gitlab.example.com/group/sub/forky.git"),
+            Some(hosted(
+                HostingProvider::GitLab,
+                "gitlab.example.com",
+                "group/sub/forky"
+            ))
+        );
+        assert_eq!(
+            parse_remote_url("ssh://git@bitbucket.org/team/forky.git"),
+            Some(hosted(
+                HostingProvider::Bitbucket,
+                "bitbucket.org",
+                "team/forky"
+            ))
+        );
+        assert_eq!(parse_remote_url("https://github.com/a/b/c"), None);
+        assert_eq!(parse_remote_url("git@example.com:team/forky.git"), None);
+        assert_eq!(parse_remote_url("/srv/git/forky.git"), None);
+    }
+
+    #[test]
+    fn test_page_urls() {
+        let github = hosted(HostingProvider::GitHub, "github.com", "calambrenet/forky");
+        let gitlab = hosted(HostingProvider::GitLab, "gitlab.com", "group/forky");
+        let bitbucket = hosted(HostingProvider::Bitbucket, "bitbucket.org", "team/forky");
+        assert_eq!(
+            page_url(&github, RemotePage::Branch("feature/x")),
+            "https://github.com/calambrenet/forky/tree/feature/x"
+        );
+        assert_eq!(
+            page_url(&gitlab, RemotePage::Commit("abc123")),
+            "https://gitlab.com/group/forky/-/commit/abc123"
+        );
+        assert_eq!(
+            page_url(
+                &bitbucket,
+                RemotePage::File {
+                    reference: "main",
+                    path: "src/my file.rs"
+                }
+            ),
+            "https://bitbucket.org/team/forky/src/main/src/my%20file.rs"
+        );
+        assert_eq!(
+            page_url(&github, RemotePage::Repository),
+            "https://github.com/calambrenet/forky"
+        );
+    }
+
+    #[test]
+    fn test_line_anchors() {
+        assert_eq!(line_anchor(HostingProvider::GitHub, 10, 20), "#L10-L20");
+        assert_eq!(line_anchor(HostingProvider::GitHub, 7, 7), "#L7");
+        assert_eq!(line_anchor(HostingProvider::GitLab, 10, 20), "#L10-20");
+        assert_eq!(
+            line_anchor(HostingProvider::Bitbucket, 10, 20),
+            "#lines-10:20"
+        );
+    }
+
+    #[test]
+    fn test_compare_urls() {
+        let github = hosted(HostingProvider::GitHub, "github.com", "calambrenet/forky");
+        let fork = hosted(HostingProvider::GitHub, "github.com", "me/forky");
+        assert_eq!(
+            compare_url(&github, &fork, "feature/x", Some("main"), Some("Add x"), None),
+            "https://github.com/calambrenet/forky/compare/main...me:feature/x?expand=1&title=Add%20x"
+        );
+        assert_eq!(
+            compare_url(&github, &github, "fix", None, None, None),
+            "https://github.com/calambrenet/forky/compare/fix?expand=1"
+        );
+
+        let gitlab = hosted(HostingProvider::GitLab, "gitlab.com", "group/forky");
+        assert_eq!(
+            compare_url(&gitlab, &gitlab, "feature/x", Some("main"), None, None),
+            "https://gitlab.com/group/forky/-/merge_requests/new?\
+             merge_request%5Bsource_branch%5D=feature%2Fx&merge_request%5Btarget_branch%5D=main"
+        );
+
+        let bitbucket = hosted(HostingProvider::Bitbucket, "bitbucket.org", "team/forky");
+        assert_eq!(
+            compare_url(&bitbucket, &bitbucket, "fix", None, Some("ignored"), None),
+            "https://bitbucket.org/team/forky/pull-requests/new?source=fix"
+        );
+    }
+}
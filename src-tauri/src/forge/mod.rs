@@ -0,0 +1,5 @@
+pub mod checks;
+pub mod commands;
+pub mod github;
+pub mod gitlab;
+pub mod hosting;
@@ -0,0 +1,430 @@
+//! Catalog of the git actions the backend exposes, for the command palette.
+//!
+//! Each action names the Tauri command that performs it and the conditions it
+//! needs (e.g. `git_rebase_continue` only while a rebase is in progress). The
+//! conditions are checked against an [`ActionContext`] read from the repository,
+//! so the palette can grey out actions and say why instead of hard-coding rules.
+
+use serde::{Deserialize, Serialize};
+
+/// Facts about the repository that decide which actions apply
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionContext {
+    pub state: String, // same values as RepositoryState::state
+    pub has_commits: bool,
+    pub on_branch: bool, // false when HEAD is detached or unborn
+    pub has_remotes: bool,
+    pub has_upstream: bool,
+    pub has_changes: bool,
+    pub has_staged_changes: bool,
+    pub has_stashes: bool,
+    pub has_previous_branch: bool,
+    pub gitflow_initialized: bool,
+    pub on_gitflow_branch: bool, // on a feature/release/hotfix branch
+    pub flow_finish_in_progress: bool, // a finish stopped on a conflict
+    pub has_undoable_operation: bool, // in the operation history
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Requirement {
+    HasCommits,
+    OnBranch,
+    HasRemotes,
+    HasUpstream,
+    HasChanges,
+    HasStagedChanges,
+    HasStashes,
+    HasPreviousBranch,
+    HasUndoableOperation,
+    NoOperationInProgress,
+    Merging,
+    Rebasing,
+    CherryPicking,
+    Reverting,
+    GitFlowInitialized,
+    GitFlowNotInitialized,
+    OnGitFlowBranch,
+    FinishingGitFlow,
+}
+
+impl Requirement {
+    /// None when met, otherwise the reason the action is unavailable
+    fn unmet_reason(self, context: &ActionContext) -> Option<&'static str> {
+        let rebasing = context.state.starts_with("rebase");
+        let (met, reason) = match self {
+            Requirement::HasCommits => (context.has_commits, "The repository has no commits"),
+            Requirement::OnBranch => (context.on_branch, "HEAD is not on a branch"),
+            Requirement::HasRemotes => (context.has_remotes, "No remotes configured"),
+            Requirement::HasUpstream => (context.has_upstream, "The branch has no upstream"),
+            Requirement::HasChanges => (context.has_changes, "No local changes"),
+            Requirement::HasStagedChanges => (context.has_staged_changes, "No staged changes"),
+            Requirement::HasStashes => (context.has_stashes, "No stashes"),
+            Requirement::HasPreviousBranch => (
+                context.has_previous_branch,
+                "No previously checked out branch",
+            ),
+            Requirement::HasUndoableOperation => {
+                (context.has_undoable_operation, "Nothing to undo")
+            }
+            Requirement::NoOperationInProgress => (
+                context.state == "clean",
+                "Another operation (merge, rebase...) is in progress",
+            ),
+            Requirement::Merging => (context.state == "merge", "No merge in progress"),
+            Requirement::Rebasing => (rebasing, "No rebase in progress"),
+            Requirement::CherryPicking => {
+                (context.state == "cherry_pick", "No cherry-pick in progress")
+            }
+            Requirement::Reverting => (context.state == "revert", "No revert in progress"),
+            Requirement::GitFlowInitialized => {
+                (context.gitflow_initialized, "Git flow is not initialized")
+            }
+            Requirement::GitFlowNotInitialized => (
+                !context.gitflow_initialized,
+                "Git flow is already initialized",
+            ),
+            Requirement::OnGitFlowBranch => (
+                context.on_gitflow_branch,
+                "Not on a feature, release or hotfix branch",
+            ),
+            Requirement::FinishingGitFlow => (
+                context.flow_finish_in_progress,
+                "No git flow finish in progress",
+            ),
+        };
+        (!met).then_some(reason)
+    }
+}
+
+struct ActionSpec {
+    id: &'static str, // Tauri command that performs the action
+    label: &'static str,
+    category: &'static str,
+    requires: &'static [Requirement],
+}
+
+const fn action(
+    id: &'static str,
+    label: &'static str,
+    category: &'static str,
+    requires: &'static [Requirement],
+) -> ActionSpec {
+    ActionSpec {
+        id,
+        label,
+        category,
+        requires,
+    }
+}
+
+use Requirement::*;
+
+const ACTIONS: &[ActionSpec] = &[
+    action("git_fetch", "Fetch", "remote", &[HasRemotes]),
+    action(
+        "git_pull",
+        "Pull",
+        "remote",
+        &[NoOperationInProgress, OnBranch, HasUpstream],
+    ),
+    action(
+        "git_push",
+        "Push",
+        "remote",
+        &[HasCommits, OnBranch, HasRemotes],
+    ),
+    action(
+        "git_fast_forward",
+        "Fast-forward to upstream",
+        "remote",
+        &[NoOperationInProgress, OnBranch, HasUpstream],
+    ),
+    action("git_add_remote", "Add Remote...", "remote", &[]),
+    action("git_commit", "Commit", "changes", &[HasStagedChanges]),
+    action(
+        "git_stash_save",
+        "Stash Changes",
+        "stash",
+        &[HasCommits, HasChanges],
+    ),
+    action("git_stash_pop", "Pop Stash", "stash", &[HasStashes]),
+    action("git_stash_apply", "Apply Stash", "stash", &[HasStashes]),
+    action(
+        "git_create_branch",
+        "New Branch...",
+        "branch",
+        &[HasCommits],
+    ),
+    action(
+        "restack_branches",
+        "Restack Current Branch",
+        "branch",
+        &[NoOperationInProgress, OnBranch],
+    ),
+    action(
+        "git_checkout_previous",
+        "Switch to Previous Branch",
+        "branch",
+        &[NoOperationInProgress, HasPreviousBranch],
+    ),
+    action("git_create_tag", "New Tag...", "branch", &[HasCommits]),
+    action(
+        "git_reset",
+        "Reset Current Branch...",
+        "branch",
+        &[NoOperationInProgress, HasCommits, OnBranch],
+    ),
+    action(
+        "git_describe",
+        "Copy Version (describe)",
+        "branch",
+        &[HasCommits],
+    ),
+    action(
+        "git_merge",
+        "Merge...",
+        "merge",
+        &[NoOperationInProgress, OnBranch],
+    ),
+    action("git_merge_continue", "Continue Merge", "merge", &[Merging]),
+    action("git_merge_abort", "Abort Merge", "merge", &[Merging]),
+    action(
+        "git_rebase",
+        "Rebase...",
+        "rebase",
+        &[NoOperationInProgress, OnBranch],
+    ),
+    action(
+        "git_interactive_rebase",
+        "Interactive Rebase...",
+        "rebase",
+        &[NoOperationInProgress, OnBranch],
+    ),
+    action(
+        "git_rebase_continue",
+        "Continue Rebase",
+        "rebase",
+        &[Rebasing],
+    ),
+    action("git_rebase_abort", "Abort Rebase", "rebase", &[Rebasing]),
+    action(
+        "git_cherry_pick_continue",
+        "Continue Cherry-pick",
+        "cherry_pick",
+        &[CherryPicking],
+    ),
+    action(
+        "git_cherry_pick_abort",
+        "Abort Cherry-pick",
+        "cherry_pick",
+        &[CherryPicking],
+    ),
+    action(
+        "git_revert",
+        "Revert Commit...",
+        "revert",
+        &[NoOperationInProgress, HasCommits, OnBranch],
+    ),
+    action(
+        "git_revert_continue",
+        "Continue Revert",
+        "revert",
+        &[Reverting],
+    ),
+    action("git_revert_abort", "Abort Revert", "revert", &[Reverting]),
+    action(
+        "git_flow_init",
+        "Initialize Git Flow",
+        "gitflow",
+        &[HasCommits, GitFlowNotInitialized],
+    ),
+    action(
+        "git_flow_start",
+        "Start Feature/Release/Hotfix...",
+        "gitflow",
+        &[NoOperationInProgress, GitFlowInitialized],
+    ),
+    action(
+        "git_flow_finish",
+        "Finish Current Flow Branch",
+        "gitflow",
+        &[NoOperationInProgress, GitFlowInitialized, OnGitFlowBranch],
+    ),
+    action(
+        "git_flow_finish_continue",
+        "Continue Flow Finish",
+        "gitflow",
+        &[FinishingGitFlow],
+    ),
+    action(
+        "git_flow_finish_abort",
+        "Abort Flow Finish",
+        "gitflow",
+        &[FinishingGitFlow],
+    ),
+    action(
+        "undo_last_operation",
+        "Undo Last Operation",
+        "repository",
+        &[NoOperationInProgress, HasUndoableOperation],
+    ),
+    action("open_in_terminal", "Open in Terminal", "repository", &[]),
+];
+
+/// An action as shown in the command palette
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailableAction {
+    pub id: String,
+    pub label: String,
+    pub category: String,
+    pub enabled: bool,
+    pub reason: Option<String>, // why the action is disabled
+}
+
+/// Every known action, in palette order, with its availability in `context`
+pub fn available_actions(context: &ActionContext) -> Vec<AvailableAction> {
+    ACTIONS
+        .iter()
+        .map(|action| {
+            let reason = action
+                .requires
+                .iter()
+                .find_map(|requirement| requirement.unmet_reason(context));
+            AvailableAction {
+                id: action.id.to_string(),
+                label: action.label.to_string(),
+                category: action.category.to_string(),
+                enabled: reason.is_none(),
+                reason: reason.map(String::from),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(actions: &'a [AvailableAction], id: &str) -> &'a AvailableAction {
+        actions.iter().find(|a| a.id == id).expect("action exists")
+    }
+
+    #[test]
+    fn test_rebase_actions_follow_state() {
+        let mut context = ActionContext {
+            state: "clean".into(),
+            has_commits: true,
+            on_branch: true,
+            ..Default::default()
+        };
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_rebase").enabled);
+        assert!(!find(&actions, "git_rebase_continue").enabled);
+
+        context.state = "rebase_interactive".into();
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_rebase_continue").enabled);
+        assert!(find(&actions, "git_rebase_abort").enabled);
+        assert!(!find(&actions, "git_merge_abort").enabled);
+        assert!(!find(&actions, "git_merge_continue").enabled);
+        assert_eq!(
+            find(&actions, "git_rebase").reason.as_deref(),
+            Some("Another operation (merge, rebase...) is in progress")
+        );
+
+        context.state = "merge".into();
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_merge_continue").enabled);
+        assert!(find(&actions, "git_merge_abort").enabled);
+        assert!(!find(&actions, "git_rebase_continue").enabled);
+    }
+
+    #[test]
+    fn test_cherry_pick_actions_follow_state() {
+        let mut context = ActionContext {
+            state: "clean".into(),
+            has_commits: true,
+            on_branch: true,
+            ..Default::default()
+        };
+        let actions = available_actions(&context);
+        assert!(!find(&actions, "git_cherry_pick_continue").enabled);
+        assert_eq!(
+            find(&actions, "git_cherry_pick_abort").reason.as_deref(),
+            Some("No cherry-pick in progress")
+        );
+
+        context.state = "cherry_pick".into();
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_cherry_pick_continue").enabled);
+        assert!(find(&actions, "git_cherry_pick_abort").enabled);
+        assert!(!find(&actions, "git_rebase_continue").enabled);
+        assert!(!find(&actions, "git_merge").enabled);
+    }
+
+    #[test]
+    fn test_revert_actions_follow_state() {
+        let mut context = ActionContext {
+            state: "clean".into(),
+            has_commits: true,
+            on_branch: true,
+            ..Default::default()
+        };
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_revert").enabled);
+        assert!(!find(&actions, "git_revert_continue").enabled);
+
+        context.state = "revert".into();
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_revert_continue").enabled);
+        assert!(find(&actions, "git_revert_abort").enabled);
+        assert!(!find(&actions, "git_cherry_pick_abort").enabled);
+        assert_eq!(
+            find(&actions, "git_revert").reason.as_deref(),
+            Some("Another operation (merge, rebase...) is in progress")
+        );
+    }
+
+    #[test]
+    fn test_flow_finish_actions_follow_saved_state() {
+        let mut context = ActionContext {
+            state: "merge".into(),
+            ..Default::default()
+        };
+        let actions = available_actions(&context);
+        let continue_finish = find(&actions, "git_flow_finish_continue");
+        assert!(!continue_finish.enabled);
+        assert_eq!(
+            continue_finish.reason.as_deref(),
+            Some("No git flow finish in progress")
+        );
+
+        context.flow_finish_in_progress = true;
+        let actions = available_actions(&context);
+        assert!(find(&actions, "git_flow_finish_continue").enabled);
+        assert!(find(&actions, "git_flow_finish_abort").enabled);
+    }
+
+    #[test]
+    fn test_first_unmet_requirement_is_reported() {
+        let context = ActionContext {
+            state: "clean".into(),
+            ..Default::default()
+        };
+        let actions = available_actions(&context);
+        let pull = find(&actions, "git_pull");
+        assert!(!pull.enabled);
+        assert_eq!(pull.reason.as_deref(), Some("HEAD is not on a branch"));
+        assert!(!find(&actions, "restack_branches").enabled);
+        assert_eq!(
+            find(&actions, "git_reset").reason.as_deref(),
+            Some("The repository has no commits")
+        );
+        assert_eq!(
+            find(&actions, "undo_last_operation").reason.as_deref(),
+            Some("Nothing to undo")
+        );
+        assert!(find(&actions, "open_in_terminal").enabled);
+        assert!(find(&actions, "open_in_terminal").reason.is_none());
+    }
+}
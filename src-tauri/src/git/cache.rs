@@ -0,0 +1,202 @@
+//! Reuse of `git2::Repository` handles for the watched repository.
+//!
+//! Opening a repository re-reads its config and discovers its layout, which every
+//! IPC command used to pay again. Only the watched repository is cached, because
+//! the file watcher is what tells us when a handle may be stale: each change
+//! event bumps a generation counter and idle handles from older generations are
+//! dropped. A `Repository` cannot be shared between threads, so the cache is a
+//! small pool: a command takes a handle out, and it goes back when the command
+//! is done. Concurrent commands simply open extra handles.
+
+use crate::locks::LockExt;
+use git2::Repository;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Idle handles kept per repository
+const MAX_IDLE: usize = 4;
+
+#[derive(Default)]
+struct CacheState {
+    path: Option<PathBuf>, // canonical path of the watched repository
+    generation: u64,
+    idle: Vec<Repository>,
+}
+
+#[derive(Default)]
+pub struct RepoCache {
+    state: Mutex<CacheState>,
+}
+
+/// Cache used by `open_validated_repo`
+pub static REPO_CACHE: RepoCache = RepoCache {
+    state: Mutex::new(CacheState {
+        path: None,
+        generation: 0,
+        idle: Vec::new(),
+    }),
+};
+
+impl RepoCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock_recover("repository cache")
+    }
+
+    /// Start caching handles for `path` (the repository the watcher now follows)
+    pub fn watch(&self, path: &Path) {
+        let path = dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut state = self.lock();
+        state.path = Some(path);
+        state.generation += 1;
+        state.idle.clear();
+    }
+
+    /// Stop caching (the watcher was stopped)
+    pub fn unwatch(&self) {
+        let mut state = self.lock();
+        state.path = None;
+        state.generation += 1;
+        state.idle.clear();
+    }
+
+    /// Drop idle handles after a change in the watched repository
+    pub fn invalidate(&self) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.idle.clear();
+    }
+
+    /// Take a cached handle for `canonical`, or open one with `open`
+    pub fn get(
+        &self,
+        canonical: &Path,
+        open: impl FnOnce() -> Result<Repository, String>,
+    ) -> Result<CachedRepository<'_>, String> {
+        // Only what is cached is read under the lock; opening a repository can
+        // be slow and must not hold up other commands
+        let cached = {
+            let mut state = self.lock();
+            (state.path.as_deref() == Some(canonical)).then(|| (state.idle.pop(), state.generation))
+        };
+        let Some((cached, generation)) = cached else {
+            return Ok(CachedRepository {
+                repo: Some(open()?),
+                cache: None,
+            });
+        };
+
+        let repo = match cached {
+            Some(repo) => {
+                // The index object lives in the handle; pick up writes made by the
+                // git CLI before the watcher's debounced event arrives
+                if let Ok(mut index) = repo.index() {
+                    let _ = index.read(false);
+                }
+                repo
+            }
+            None => open()?,
+        };
+        Ok(CachedRepository {
+            repo: Some(repo),
+            cache: Some((self, canonical.to_path_buf(), generation)),
+        })
+    }
+
+    fn put_back(&self, path: &Path, generation: u64, repo: Repository) {
+        let mut state = self.lock();
+        if state.path.as_deref() == Some(path)
+            && state.generation == generation
+            && state.idle.len() < MAX_IDLE
+        {
+            state.idle.push(repo);
+        }
+    }
+}
+
+/// A repository handle that goes back to the cache when dropped
+pub struct CachedRepository<'a> {
+    repo: Option<Repository>,
+    cache: Option<(&'a RepoCache, PathBuf, u64)>, // None when the path is not cached
+}
+
+impl Deref for CachedRepository<'_> {
+    type Target = Repository;
+
+    fn deref(&self) -> &Repository {
+        self.repo.as_ref().expect("repository present until drop")
+    }
+}
+
+impl DerefMut for CachedRepository<'_> {
+    fn deref_mut(&mut self) -> &mut Repository {
+        self.repo.as_mut().expect("repository present until drop")
+    }
+}
+
+impl Drop for CachedRepository<'_> {
+    fn drop(&mut self) {
+        if let (Some(repo), Some((cache, path, generation))) = (self.repo.take(), self.cache.take())
+        {
+            cache.put_back(&path, generation, repo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        Repository::init(dir.path()).expect("init repo");
+        let canonical = dunce::canonicalize(dir.path()).expect("canonicalize");
+        (dir, canonical)
+    }
+
+    fn open(path: &Path) -> impl FnOnce() -> Result<Repository, String> + '_ {
+        move || Repository::open(path).map_err(|e| e.message().to_string())
+    }
+
+    #[test]
+    fn test_handles_are_reused_for_the_watched_path() {
+        let (_dir, path) = temp_repo();
+        let (_other_dir, other) = temp_repo();
+        let cache = RepoCache::default();
+        cache.watch(&path);
+
+        drop(cache.get(&path, open(&path)).unwrap());
+        // The idle handle is reused, so the opener is not called again
+        let reused = cache.get(&path, || Err("opened again".to_string()));
+        assert!(reused.is_ok());
+        drop(reused);
+
+        // Other repositories are opened every time and never kept
+        drop(cache.get(&other, open(&other)).unwrap());
+        assert!(cache
+            .get(&other, || Err("opened again".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalidate_drops_idle_and_in_flight_handles() {
+        let (_dir, path) = temp_repo();
+        let cache = RepoCache::default();
+        cache.watch(&path);
+
+        let in_flight = cache.get(&path, open(&path)).unwrap();
+        drop(cache.get(&path, open(&path)).unwrap());
+        cache.invalidate();
+        // Neither the idle handle nor the one returned after the change survive
+        drop(in_flight);
+        assert!(cache
+            .get(&path, || Err("opened again".to_string()))
+            .is_err());
+
+        drop(cache.get(&path, open(&path)).unwrap());
+        cache.unwatch();
+        assert!(cache
+            .get(&path, || Err("opened again".to_string()))
+            .is_err());
+    }
+}
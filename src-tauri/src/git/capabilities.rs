@@ -0,0 +1,310 @@
+//! Commands that depend on the version of the installed git.
+//!
+//! `git checkout` does two unrelated jobs: switching branches and restoring
+//! files. It guesses which one is meant, so a branch named like a file is
+//! ambiguous. Git 2.23 split it into `git switch` and `git restore`, which are
+//! used when the installed git has them, with the `checkout` forms kept for
+//! older versions. The version is the one `check_git_installed` reports,
+//! detected on first use otherwise. Features an old git lacks come with a
+//! warning the settings show.
+
+use crate::git::environment::git_command;
+use crate::locks::RwLockExt;
+use git2::{BranchType, Repository};
+use serde::Serialize;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl GitVersion {
+    /// Parse the version of `git --version` output, with or without the "git
+    /// version" prefix: "2.39.3 (Apple Git-145)", "2.43.0.windows.1"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix("git version ").unwrap_or(text);
+        let mut numbers = text
+            .split(|c: char| !c.is_ascii_digit())
+            .map(|part| part.parse::<u32>().ok());
+        Some(GitVersion {
+            major: numbers.next()??,
+            minor: numbers.next()??,
+            patch: numbers.next().flatten().unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitFeature {
+    Switch,
+    Restore,
+    SparseCheckout,
+}
+
+impl GitFeature {
+    const ALL: [GitFeature; 3] = [
+        GitFeature::Switch,
+        GitFeature::Restore,
+        GitFeature::SparseCheckout,
+    ];
+
+    pub fn min_version(self) -> GitVersion {
+        let (major, minor) = match self {
+            GitFeature::Switch | GitFeature::Restore => (2, 23),
+            GitFeature::SparseCheckout => (2, 25),
+        };
+        GitVersion {
+            major,
+            minor,
+            patch: 0,
+        }
+    }
+
+    /// What is used instead, or lost, without the feature
+    fn fallback(self) -> &'static str {
+        match self {
+            GitFeature::Switch => "branches are switched with git checkout",
+            GitFeature::Restore => "files are restored with git checkout",
+            GitFeature::SparseCheckout => "sparse checkouts cannot be set up",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitCapabilities {
+    pub version: Option<String>, // None when git could not be run
+    pub switch: bool,
+    pub restore: bool,
+    pub sparse_checkout: bool,
+    pub warnings: Vec<String>, // one per missing feature, with the version needed
+}
+
+/// Version of the installed git: None until detected, Some(None) when git
+/// could not be run or its version not parsed
+static DETECTED: RwLock<Option<Option<GitVersion>>> = RwLock::new(None);
+
+/// Record the version `check_git_installed` found
+pub fn set_version(version: Option<&str>) {
+    *DETECTED.write_recover("git version") = Some(version.and_then(GitVersion::parse));
+}
+
+/// Forget the detected version, e.g. when PATH changes which git is run
+pub fn reset() {
+    *DETECTED.write_recover("git version") = None;
+}
+
+fn detect() -> Option<GitVersion> {
+    let output = git_command().arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    GitVersion::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+pub fn version() -> Option<GitVersion> {
+    if let Some(version) = *DETECTED.read_recover("git version") {
+        return version;
+    }
+    let version = detect();
+    *DETECTED.write_recover("git version") = Some(version);
+    version
+}
+
+fn has(version: Option<GitVersion>, feature: GitFeature) -> bool {
+    version.is_some_and(|version| version >= feature.min_version())
+}
+
+pub fn supports(feature: GitFeature) -> bool {
+    has(version(), feature)
+}
+
+fn capabilities_for(version: Option<GitVersion>) -> GitCapabilities {
+    let warnings = match version {
+        None => vec!["The git version could not be detected".to_string()],
+        Some(version) => GitFeature::ALL
+            .iter()
+            .filter(|feature| !has(Some(version), **feature))
+            .map(|feature| {
+                let min = feature.min_version();
+                format!(
+                    "Git {}.{}.{} is older than {}.{}: {}",
+                    version.major,
+                    version.minor,
+                    version.patch,
+                    min.major,
+                    min.minor,
+                    feature.fallback()
+                )
+            })
+            .collect(),
+    };
+    GitCapabilities {
+        version: version.map(|v| format!("{}.{}.{}", v.major, v.minor, v.patch)),
+        switch: has(version, GitFeature::Switch),
+        restore: has(version, GitFeature::Restore),
+        sparse_checkout: has(version, GitFeature::SparseCheckout),
+        warnings,
+    }
+}
+
+pub fn get_capabilities() -> GitCapabilities {
+    capabilities_for(version())
+}
+
+/// Whether `target` names a branch to switch to: a local branch, or a remote
+/// branch git creates a local branch for. Anything else (a commit, a tag) is
+/// checked out detached.
+fn is_branch_target(repo: &Repository, target: &str) -> bool {
+    if repo.find_branch(target, BranchType::Local).is_ok() {
+        return true;
+    }
+    repo.branches(Some(BranchType::Remote))
+        .map(|branches| {
+            branches.flatten().any(|(branch, _)| {
+                branch
+                    .name()
+                    .ok()
+                    .flatten()
+                    .and_then(|name| name.split_once('/'))
+                    .is_some_and(|(_, name)| name == target)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn switch_args_for(version: Option<GitVersion>, is_branch: bool, target: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    if !has(version, GitFeature::Switch) {
+        args.push("checkout".to_string());
+    } else if is_branch {
+        args.push("switch".to_string());
+    } else {
+        args.extend(["switch".to_string(), "--detach".to_string()]);
+    }
+    args.push(target.to_string());
+    args
+}
+
+/// Arguments switching the work tree to `target`, a branch, or a commit or
+/// tag to check out detached
+pub fn switch_args(repo: &Repository, target: &str) -> Vec<String> {
+    switch_args_for(version(), is_branch_target(repo, target), target)
+}
+
+fn create_branch_args_for(
+    version: Option<GitVersion>,
+    branch_name: &str,
+    start_point: &str,
+    track: bool,
+) -> Vec<String> {
+    let mut args: Vec<String> = if has(version, GitFeature::Switch) {
+        vec!["switch".into(), "-c".into()]
+    } else {
+        vec!["checkout".into(), "-b".into()]
+    };
+    args.push(branch_name.to_string());
+    if track {
+        args.push("--track".to_string());
+    }
+    args.push(start_point.to_string());
+    args
+}
+
+/// Arguments creating `branch_name` at `start_point` and switching to it,
+/// tracking `start_point` when `track`
+pub fn create_branch_args(branch_name: &str, start_point: &str, track: bool) -> Vec<String> {
+    create_branch_args_for(version(), branch_name, start_point, track)
+}
+
+/// Where `restore_args` takes the files from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreSource<'a> {
+    Commit(&'a str), // both the index and the work tree
+    Ours,            // our side of a conflict, in the work tree
+    Theirs,          // their side of a conflict, in the work tree
+}
+
+fn restore_args_for(version: Option<GitVersion>, source: RestoreSource) -> Vec<String> {
+    let modern = has(version, GitFeature::Restore);
+    match (source, modern) {
+        (RestoreSource::Commit(commit), true) => vec![
+            "restore".into(),
+            format!("--source={}", commit),
+            "--staged".into(),
+            "--worktree".into(),
+        ],
+        (RestoreSource::Commit(commit), false) => vec!["checkout".into(), commit.into()],
+        (RestoreSource::Ours, true) => vec!["restore".into(), "--ours".into()],
+        (RestoreSource::Ours, false) => vec!["checkout".into(), "--ours".into()],
+        (RestoreSource::Theirs, true) => vec!["restore".into(), "--theirs".into()],
+        (RestoreSource::Theirs, false) => vec!["checkout".into(), "--theirs".into()],
+    }
+}
+
+/// Arguments restoring files from `source`; the paths go after a "--"
+pub fn restore_args(source: RestoreSource) -> Vec<String> {
+    restore_args_for(version(), source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: Option<GitVersion> = Some(GitVersion {
+        major: 2,
+        minor: 20,
+        patch: 1,
+    });
+    const NEW: Option<GitVersion> = Some(GitVersion {
+        major: 2,
+        minor: 43,
+        patch: 0,
+    });
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(GitVersion::parse("git version 2.43.0.windows.1\n"), NEW);
+        assert_eq!(
+            GitVersion::parse("2.39.3 (Apple Git-145)"),
+            Some(GitVersion {
+                major: 2,
+                minor: 39,
+                patch: 3
+            })
+        );
+        assert_eq!(GitVersion::parse("git version 3.0").unwrap().major, 3);
+        assert_eq!(GitVersion::parse("not git"), None);
+    }
+
+    #[test]
+    fn test_commands_follow_the_version() {
+        assert_eq!(switch_args_for(NEW, true, "main"), ["switch", "main"]);
+        assert_eq!(
+            switch_args_for(NEW, false, "v1.0"),
+            ["switch", "--detach", "v1.0"]
+        );
+        assert_eq!(switch_args_for(OLD, false, "v1.0"), ["checkout", "v1.0"]);
+        assert_eq!(
+            create_branch_args_for(OLD, "fix", "origin/fix", true),
+            ["checkout", "-b", "fix", "--track", "origin/fix"]
+        );
+        assert_eq!(
+            restore_args_for(NEW, RestoreSource::Commit("HEAD")),
+            ["restore", "--source=HEAD", "--staged", "--worktree"]
+        );
+        assert_eq!(
+            restore_args_for(OLD, RestoreSource::Theirs),
+            ["checkout", "--theirs"]
+        );
+
+        let old = capabilities_for(OLD);
+        assert!(!old.switch && !old.sparse_checkout);
+        assert_eq!(old.warnings.len(), 3);
+        assert!(capabilities_for(NEW).warnings.is_empty());
+    }
+}
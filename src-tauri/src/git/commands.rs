@@ -1,333 +1,1478 @@
+use crate::git::actions::{self, AvailableAction};
+use crate::git::capabilities;
+use crate::git::commit_lint::{self, CommitLintRules, CommitLintWarning};
+use crate::git::conflicts::{self, ConflictSide, ConflictVersions, ConflictedFile};
+use crate::git::environment::{self, GitEnvironment};
+use crate::git::flow_finish::{self, FlowFinishState};
+use crate::git::history_export::{self, HistoryExport, HistoryFormat};
+use crate::git::hook_frameworks::{self, HookFrameworkStatus};
+use crate::git::index_flags::{self, FlaggedFile, IndexFlag};
+use crate::git::issue_keys::{self, IssueKey};
+use crate::git::known_hosts;
+use crate::git::network::{
+    self, FetchOptions, NetworkOperation, NetworkResult, PullOptions, PushOptions,
+    SuppliedCredential,
+};
+use crate::git::onboarding::{self, BundleImportSummary, OnboardingBundle};
+use crate::git::operation_history::{self, OperationKind, OperationRecord};
+use crate::git::progress::GitProgress;
+use crate::git::refresh::{self, RepoRefresh};
+use crate::git::remote_tokens::{self, RemoteTokenInfo};
 use crate::git::repository::{
-    self, BranchHead, BranchInfo, CommitInfo, CommitMessage, DiffInfo, FetchOptions, FileStatus,
-    GitIdentity, GitOperationResult, HunkData, ImageContent, InteractiveRebaseEntry, PullOptions,
-    PushOptions, RepositoryInfo, StashInfo, TagInfo,
+    self, BranchCommitSet, BranchHead, BranchInfo, CommitCount, CommitFiles, CommitFilter,
+    CommitInfo, CommitMessage, CommitScope, ContentSearchMatch, DescribeOptions, DiffInfo,
+    FileStatus, FileThreeWay, GitIdentity, GitOperationResult, HistoryStats, HunkData,
+    HunkPatchPreview, ImageContent, InteractiveRebaseEntry, LineRange, MediaDiffInfo, RecentBranch,
+    RefContainment, RefDecoration, RefMatch, RefsDiff, RefsDiffMode, RepositoryInfo,
+    RepositoryState, RepositorySummary, StashInfo, TagInfo, WorkingDiffStat,
 };
-use crate::git::validation::open_validated_repo;
+use crate::git::scratch::{self, ScratchCleanup};
+use crate::git::stack::{self, RestackState, StackBranch};
+use crate::git::status_tree::{self, FileStatusTree};
+use crate::git::trace::{self, PerformanceReport};
+use crate::git::validation::{ensure_writable, open_validated_repo, validate_repo_path_string};
+use crate::git::verification::{self, VerificationResult, VerificationSettings};
+use crate::locks;
+use crate::recent;
+use crate::watcher;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Run libgit2 / git CLI work on the blocking thread pool, so a slow command
+/// (fetch, large diff) does not hold up the IPC thread or other commands. The
+/// work is timed under the calling command's name when the trace is enabled.
+/// A panic in the work is returned as an error naming the command.
+pub async fn run_blocking<T, F>(task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    let name = trace::task_name::<F>();
+    tauri::async_runtime::spawn_blocking(move || {
+        std::panic::catch_unwind(AssertUnwindSafe(|| trace::measure(name, task))).unwrap_or_else(
+            |payload| {
+                Err(format!(
+                    "Internal error in {}: {}",
+                    name,
+                    locks::panic_message(&*payload)
+                ))
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Git task failed: {}", e))?
+}
 
 #[tauri::command]
-pub fn open_repository(path: String) -> Result<RepositoryInfo, String> {
-    let repo = repository::open_repository(&path)?;
-    let info = repository::get_repository_info(&repo)?;
-    Ok(info)
+pub async fn open_repository(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<RepositoryInfo, String> {
+    run_blocking(move || {
+        let repo = repository::open_repository(&path)?;
+        let info = repository::get_repository_info(&repo)?;
+        recent::record_opened(&app_handle, &info);
+        Ok(info)
+    })
+    .await
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct CloneProgressEvent {
+    pub url: String,
+    pub path: String,
+    #[serde(flatten)]
+    pub progress: GitProgress,
 }
 
+#[derive(serde::Serialize)]
+pub struct CloneResult {
+    pub result: GitOperationResult,
+    pub repository: Option<RepositoryInfo>, // the cloned repository, ready to open as a tab
+}
+
+/// Clone a remote repository into `path`, emitting `clone-progress` events, and
+/// add the clone to the recent repositories
 #[tauri::command]
-pub fn get_branches(repo_path: String) -> Result<Vec<BranchInfo>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_branches(&repo)
+pub async fn git_clone(
+    app_handle: AppHandle,
+    url: String,
+    path: String,
+) -> Result<CloneResult, String> {
+    run_blocking(move || {
+        let result = repository::git_clone(&url, &path, |progress| {
+            let event = CloneProgressEvent {
+                url: url.clone(),
+                path: path.clone(),
+                progress,
+            };
+            if let Err(e) = app_handle.emit("clone-progress", event) {
+                eprintln!("Failed to emit clone progress event: {}", e);
+            }
+        })?;
+
+        let repository = if result.success {
+            let repo = repository::open_repository(&path)?;
+            let info = repository::get_repository_info(&repo)?;
+            recent::record_opened(&app_handle, &info);
+            Some(info)
+        } else {
+            None
+        };
+
+        Ok(CloneResult { result, repository })
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_branch_heads(repo_path: String) -> Result<Vec<BranchHead>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_branch_heads(&repo)
+pub async fn get_branches(repo_path: String) -> Result<Vec<BranchInfo>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_branches(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_branch_heads(repo_path: String) -> Result<Vec<BranchHead>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_branch_heads(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_ref_decorations(
+    repo_path: String,
+) -> Result<HashMap<String, Vec<RefDecoration>>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_ref_decorations(&repo)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_commits(
+pub async fn get_commits(
     repo_path: String,
     limit: Option<usize>,
+    skip: Option<usize>,
+    cursor: Option<String>,
+    scope: Option<CommitScope>,
+    filter: Option<CommitFilter>,
 ) -> Result<Vec<CommitInfo>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_commits(&repo, limit.unwrap_or(100))
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_commits(
+            &repo,
+            limit.unwrap_or(100),
+            skip.unwrap_or(0),
+            cursor.as_deref(),
+            &scope.unwrap_or_default(),
+            &filter.unwrap_or_default(),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_commit_count(
+    repo_path: String,
+    scope: Option<CommitScope>,
+    filter: Option<CommitFilter>,
+) -> Result<CommitCount, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_commit_count(
+            &repo,
+            &scope.unwrap_or_default(),
+            &filter.unwrap_or_default(),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_history_stats(
+    repo_path: String,
+    refs: Option<Vec<String>>,
+) -> Result<HistoryStats, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_history_stats(&repo, refs)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_file_status(repo_path: String) -> Result<Vec<FileStatus>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_file_status(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_tags(repo_path: String) -> Result<Vec<TagInfo>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_tags(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_branch_commit_set(
+    repo_path: String,
+    branch: String,
+    base: Option<String>,
+) -> Result<BranchCommitSet, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_branch_commit_set(&repo, &branch, base.as_deref())
+    })
+    .await
+}
+
+/// Default branch of `remote`; may ask the remote when it is not known locally
+#[tauri::command]
+pub async fn get_default_branch(
+    repo_path: String,
+    remote: String,
+) -> Result<Option<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_default_branch(&repo, &remote)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_default_branch_local(
+    repo_path: String,
+    remote: String,
+    branch: String,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::set_default_branch_local(&repo, &remote, &branch)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_recent_branches(
+    repo_path: String,
+    limit: Option<usize>,
+) -> Result<Vec<RecentBranch>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_recent_branches(&repo, limit.unwrap_or(10))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn search_refs(
+    repo_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<RefMatch>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::search_refs(&repo, &query, limit.unwrap_or(50))
+    })
+    .await
+}
+
+/// Commits that added or removed `term` (`git log -S`, or `-G` when `regex`)
+#[tauri::command]
+pub async fn search_commits_by_content(
+    repo_path: String,
+    term: String,
+    regex: bool,
+    limit: Option<usize>,
+) -> Result<Vec<ContentSearchMatch>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::search_commits_by_content(&repo, &term, regex, limit.unwrap_or(200))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_branches_containing(
+    repo_path: String,
+    commit: String,
+) -> Result<RefContainment, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_branches_containing(&repo, &commit)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_tag_containment(repo_path: String, tag: String) -> Result<RefContainment, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_tag_containment(&repo, &tag)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_describe(
+    repo_path: String,
+    reference: Option<String>,
+    tags: Option<bool>,
+    dirty: Option<bool>,
+    long: Option<bool>,
+    always: Option<bool>,
+    abbrev: Option<u32>,
+) -> Result<String, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::git_describe(
+            &repo,
+            reference.as_deref(),
+            DescribeOptions {
+                tags: tags.unwrap_or(true),
+                dirty: dirty.unwrap_or(true),
+                long: long.unwrap_or(false),
+                always: always.unwrap_or(false),
+                abbrev,
+            },
+        )
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_file_status(repo_path: String) -> Result<Vec<FileStatus>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_file_status(&repo)
+pub async fn get_remotes(repo_path: String) -> Result<Vec<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_remotes(&repo)
+    })
+    .await
 }
 
+/// The remote used when none is given and the branch has no upstream
 #[tauri::command]
-pub fn get_tags(repo_path: String) -> Result<Vec<TagInfo>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_tags(&repo)
+pub async fn get_default_remote(repo_path: String) -> Result<Option<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_default_remote(&repo)
+    })
+    .await
 }
 
+/// Choose the repository's default remote (None clears the choice)
 #[tauri::command]
-pub fn get_remotes(repo_path: String) -> Result<Vec<String>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_remotes(&repo)
+pub async fn set_default_remote(repo_path: String, remote: Option<String>) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::set_default_remote(&repo, remote.as_deref())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_repository_info(repo_path: String) -> Result<RepositoryInfo, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_repository_info(&repo)
+pub async fn get_repository_info(repo_path: String) -> Result<RepositoryInfo, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_repository_info(&repo)
+    })
+    .await
+}
+
+/// Summaries for several repositories, read in parallel. A repository that
+/// cannot be read gets a summary with `error` set instead of failing the call.
+#[tauri::command]
+pub async fn get_multi_repo_summary(paths: Vec<String>) -> Result<Vec<RepositorySummary>, String> {
+    run_blocking(move || {
+        let summaries = std::thread::scope(|scope| {
+            let handles: Vec<_> = paths
+                .iter()
+                .map(|path| {
+                    scope.spawn(move || {
+                        open_validated_repo(path)
+                            .and_then(|repo| repository::get_repository_summary(&repo))
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .zip(&paths)
+                .map(|(handle, path)| match handle.join() {
+                    Ok(Ok(summary)) => summary,
+                    Ok(Err(e)) => RepositorySummary::failed(path, e),
+                    Err(_) => {
+                        RepositorySummary::failed(path, "Failed to read repository".to_string())
+                    }
+                })
+                .collect()
+        });
+        Ok(summaries)
+    })
+    .await
+}
+
+/// Every git action the backend offers, with whether it applies to the
+/// repository's current state (for the command palette)
+#[tauri::command]
+pub async fn list_available_actions(repo_path: String) -> Result<Vec<AvailableAction>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let context = repository::get_action_context(&repo)?;
+        Ok(actions::available_actions(&context))
+    })
+    .await
+}
+
+/// Repository info, branches, tags, stashes and status in one call, with the
+/// repository's version; only the version when it is still `since_version`
+#[tauri::command]
+pub async fn refresh(repo_path: String, since_version: Option<u64>) -> Result<RepoRefresh, String> {
+    run_blocking(move || {
+        let canonical = validate_repo_path_string(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        refresh::refresh(&repo, &canonical, since_version)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_repository_state(repo_path: String) -> Result<RepositoryState, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_repository_state(&repo)
+    })
+    .await
 }
 
 #[derive(serde::Serialize)]
 pub struct FileStatusSeparated {
     pub unstaged: Vec<FileStatus>,
     pub staged: Vec<FileStatus>,
+    pub flagged: Vec<FlaggedFile>, // assume-unchanged / skip-worktree files, hidden from the lists
 }
 
 #[tauri::command]
-pub fn get_file_status_separated(
+pub async fn get_focused_paths(repo_path: String) -> Result<Vec<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_focused_paths(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_focused_paths(
+    app_handle: AppHandle,
+    repo_path: String,
+    paths: Vec<String>,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::set_focused_paths(&repo, &paths)?;
+
+        // The watcher scope depends on the focused paths, restart it if it covers this repo
+        if watcher::get_watched_path(&app_handle).as_deref() == Some(repo_path.as_str()) {
+            watcher::start_watching(app_handle, repo_path)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_file_status_separated(repo_path: String) -> Result<FileStatusSeparated, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let (unstaged, staged) = repository::get_file_status_separated(&repo)?;
+        let flagged = index_flags::get_flagged_files(&repo)?;
+        Ok(FileStatusSeparated {
+            unstaged,
+            staged,
+            flagged,
+        })
+    })
+    .await
+}
+
+/// The unstaged and staged files as directory trees with per-directory
+/// counts; `compact` merges chains of single-directory folders
+#[tauri::command]
+pub async fn get_file_status_tree(
+    repo_path: String,
+    compact: bool,
+) -> Result<FileStatusTree, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        status_tree::get_file_status_tree(&repo, compact)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_flagged_files(repo_path: String) -> Result<Vec<FlaggedFile>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        index_flags::get_flagged_files(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_conflicts(repo_path: String) -> Result<Vec<ConflictedFile>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        conflicts::get_conflicts(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_conflict_versions(
+    repo_path: String,
+    file_path: String,
+) -> Result<ConflictVersions, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        conflicts::get_conflict_versions(&repo, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn resolve_conflict_take(
     repo_path: String,
-) -> Result<FileStatusSeparated, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    let (unstaged, staged) = repository::get_file_status_separated(&repo)?;
-    Ok(FileStatusSeparated { unstaged, staged })
+    file_path: String,
+    side: ConflictSide,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        conflicts::resolve_conflict_take(&repo, &file_path, side)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_working_diff(
+pub async fn mark_conflict_resolved(
+    repo_path: String,
+    file_path: String,
+    content: String,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        conflicts::mark_conflict_resolved(&repo, &file_path, &content)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_index_flag(
+    repo_path: String,
+    paths: Vec<String>,
+    flag: IndexFlag,
+    enabled: bool,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        index_flags::set_index_flag(&repo, &paths, flag, enabled)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn clear_index_flags(repo_path: String, paths: Vec<String>) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        index_flags::clear_index_flags(&repo, &paths)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_working_diff(
     repo_path: String,
     file_path: String,
     staged: bool,
     file_status: String,
+    line_range: Option<LineRange>,
 ) -> Result<DiffInfo, String> {
-    let repo = open_validated_repo(&repo_path)?;
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
 
-    // Handle untracked files - read the file content directly
-    if file_status == "untracked" {
-        return repository::get_untracked_file_diff(&repo, &file_path);
-    }
+        // Handle untracked files - read the file content directly
+        if file_status == "untracked" {
+            return repository::get_untracked_file_diff(&repo, &file_path, line_range);
+        }
 
-    // Handle deleted files - get content from HEAD
-    if file_status == "deleted" && !staged {
-        return repository::get_deleted_file_diff(&repo, &file_path);
-    }
+        // Handle deleted files - get content from HEAD
+        if file_status == "deleted" && !staged {
+            return repository::get_deleted_file_diff(&repo, &file_path);
+        }
 
-    // Normal diff for modified files
-    let diff = repository::get_working_diff(&repo, &file_path, staged)?;
+        // Normal diff for modified files
+        let diff = repository::get_working_diff(&repo, &file_path, staged)?;
 
-    // If no hunks and status indicates a new or deleted file, try special handling
-    if diff.hunks.is_empty() {
-        if file_status == "new" {
-            // Staged new file
-            return repository::get_untracked_file_diff(&repo, &file_path);
+        // If no hunks and status indicates a new or deleted file, try special handling
+        if diff.hunks.is_empty() {
+            if file_status == "new" {
+                // Staged new file
+                return repository::get_untracked_file_diff(&repo, &file_path, line_range);
+            }
         }
-    }
 
-    Ok(diff)
+        Ok(diff)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_working_diffstat(repo_path: String) -> Result<WorkingDiffStat, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_working_diffstat(&repo)
+    })
+    .await
+}
+
+/// Staged and unstaged changes as a patch, written to `output_path` or
+/// returned as text
+#[tauri::command]
+pub async fn export_working_diff(
+    repo_path: String,
+    output_path: Option<String>,
+) -> Result<repository::WorkingPatch, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::export_working_diff(&repo, output_path.as_deref())
+    })
+    .await
+}
+
+/// Commit graph of a range as DOT, JSON or SVG, written to `output_path` or
+/// returned as text
+#[tauri::command]
+pub async fn export_history(
+    repo_path: String,
+    format: HistoryFormat,
+    range: Option<String>,
+    max_commits: Option<usize>,
+    output_path: Option<String>,
+) -> Result<HistoryExport, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        history_export::export_history(
+            &repo,
+            format,
+            range.as_deref(),
+            max_commits,
+            output_path.as_deref(),
+        )
+    })
+    .await
 }
 
+/// HEAD, index and working tree versions of a file in one call
 #[tauri::command]
-pub fn get_commit_diff(
+pub async fn get_file_three_way(
+    repo_path: String,
+    file_path: String,
+) -> Result<FileThreeWay, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_file_three_way(&repo, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_commit_diff(
     repo_path: String,
     commit_id: String,
     file_path: String,
 ) -> Result<DiffInfo, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_commit_diff(&repo, &commit_id, &file_path)
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_commit_diff(&repo, &commit_id, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_commit_files(repo_path: String, commit_id: String) -> Result<CommitFiles, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_commit_files(&repo, &commit_id)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_commit_files(
+pub async fn get_refs_diff(
     repo_path: String,
-    commit_id: String,
-) -> Result<Vec<FileStatus>, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_commit_files(&repo, &commit_id)
+    base: String,
+    target: String,
+    mode: Option<RefsDiffMode>,
+) -> Result<RefsDiff, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_refs_diff(&repo, &base, &target, mode.unwrap_or_default())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_refs_file_diff(
+    repo_path: String,
+    base: String,
+    target: String,
+    mode: Option<RefsDiffMode>,
+    file_path: String,
+) -> Result<DiffInfo, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_refs_file_diff(&repo, &base, &target, mode.unwrap_or_default(), &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn stage_file(repo_path: String, file_path: String) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::stage_file(&repo, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn stage_mode_change(repo_path: String, file_path: String) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::stage_mode_change(&repo, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unstage_file(repo_path: String, file_path: String) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::unstage_file(&repo, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn unstage_files(repo_path: String, file_paths: Vec<String>) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::unstage_files(&repo, &file_paths)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn stage_file(repo_path: String, file_path: String) -> Result<(), String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::stage_file(&repo, &file_path)
+pub async fn stage_directory(repo_path: String, dir_path: String) -> Result<Vec<String>, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::stage_directory(&repo, &dir_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn unstage_file(repo_path: String, file_path: String) -> Result<(), String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::unstage_file(&repo, &file_path)
+pub async fn unstage_directory(repo_path: String, dir_path: String) -> Result<Vec<String>, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::unstage_directory(&repo, &dir_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn discard_file(
+pub async fn discard_file(
     repo_path: String,
     file_path: String,
     is_untracked: bool,
 ) -> Result<(), String> {
-    repository::discard_file(&repo_path, &file_path, is_untracked)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::discard_file(&repo_path, &file_path, is_untracked)
+    })
+    .await
 }
 
+#[derive(serde::Serialize, Clone)]
+pub struct GitProgressEvent {
+    pub operation_id: String,
+    pub operation: String, // "fetch", "pull", "push" or "cherry_pick"
+    pub repo_path: String,
+    #[serde(flatten)]
+    pub progress: GitProgress,
+}
+
+/// Progress callback emitting `git-progress` events for one operation, keyed by
+/// the `operation_id` the caller chose to match events to the request it made
+fn progress_emitter(
+    app_handle: AppHandle,
+    operation_id: String,
+    operation: &str,
+    repo_path: &str,
+) -> impl FnMut(GitProgress) {
+    let operation = operation.to_string();
+    let repo_path = repo_path.to_string();
+    move |progress| {
+        let event = GitProgressEvent {
+            operation_id: operation_id.clone(),
+            operation: operation.clone(),
+            repo_path: repo_path.clone(),
+            progress,
+        };
+        if let Err(e) = app_handle.emit("git-progress", event) {
+            eprintln!("Failed to emit git progress event: {}", e);
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct VerificationOutputEvent {
+    pub operation_id: String,
+    pub repo_path: String,
+    pub line: String,
+}
+
+/// Run the verification command, emitting a `verification-output` event for
+/// every line it prints
+fn run_verification_with_events<T>(
+    app_handle: &AppHandle,
+    operation_id: &str,
+    repo_path: &str,
+    run: impl FnOnce(&mut dyn FnMut(&str)) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut on_line = |line: &str| {
+        let event = VerificationOutputEvent {
+            operation_id: operation_id.to_string(),
+            repo_path: repo_path.to_string(),
+            line: line.to_string(),
+        };
+        if let Err(e) = app_handle.emit("verification-output", event) {
+            eprintln!("Failed to emit verification output event: {}", e);
+        }
+    };
+    run(&mut on_line)
+}
+
+/// Run the verification command when it is enabled for pushes; the failed
+/// result to return instead of pushing when it fails
+fn verify_before_push(
+    app_handle: &AppHandle,
+    operation_id: &str,
+    repo_path: &str,
+) -> Result<Option<GitOperationResult>, String> {
+    run_verification_with_events(app_handle, operation_id, repo_path, |on_line| {
+        verification::blocked_push(repo_path, on_line)
+    })
+}
+
+/// Pull the current branch, emitting `git-progress` events
 #[tauri::command]
-pub fn git_pull(repo_path: String) -> Result<GitOperationResult, String> {
-    repository::git_pull(&repo_path)
+pub async fn git_pull(
+    app_handle: AppHandle,
+    repo_path: String,
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let on_progress = progress_emitter(app_handle, operation_id, "pull", &repo_path);
+        network::pull(&repo_path, on_progress)
+    })
+    .await
 }
 
+/// Push the current branch, emitting `git-progress` events
 #[tauri::command]
-pub fn git_push(repo_path: String) -> Result<GitOperationResult, String> {
-    repository::git_push(&repo_path)
+pub async fn git_push(
+    app_handle: AppHandle,
+    repo_path: String,
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        if let Some(result) = verify_before_push(&app_handle, &operation_id, &repo_path)? {
+            return Ok(NetworkResult {
+                result,
+                updated_refs: Vec::new(),
+            });
+        }
+        let on_progress = progress_emitter(app_handle, operation_id, "push", &repo_path);
+        network::push(&repo_path, on_progress)
+    })
+    .await
+}
+
+/// Fetch all remotes, emitting `git-progress` events
+#[tauri::command]
+pub async fn git_fetch(
+    app_handle: AppHandle,
+    repo_path: String,
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let on_progress = progress_emitter(app_handle, operation_id, "fetch", &repo_path);
+        network::fetch(&repo_path, on_progress)
+    })
+    .await
 }
 
+/// Re-run a fetch, pull or push that returned `credential_required`, with the
+/// options it was started with and the credential the user typed. The
+/// credential is not kept afterwards unless `credential.save` asks for it to be
+/// stored with the credential helper.
 #[tauri::command]
-pub fn git_fetch(repo_path: String) -> Result<GitOperationResult, String> {
-    repository::git_fetch(&repo_path)
+pub async fn retry_with_credential(
+    app_handle: AppHandle,
+    repo_path: String,
+    operation: NetworkOperation,
+    credential: SuppliedCredential,
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let name = match operation {
+            NetworkOperation::Fetch(_) => "fetch",
+            NetworkOperation::Pull(_) => "pull",
+            NetworkOperation::Push(_) => "push",
+        };
+        let on_progress =
+            progress_emitter(app_handle.clone(), operation_id.clone(), name, &repo_path);
+        // A push runs the verification command first, as `git_push` does
+        run_verification_with_events(&app_handle, &operation_id, &repo_path, |on_line| {
+            network::retry_with_credential(&repo_path, operation, credential, on_line, on_progress)
+        })
+    })
+    .await
 }
 
+/// Fetch the remotes chosen in the fetch dialog, emitting `git-progress` events
 #[tauri::command]
-pub fn git_fetch_with_options(
+pub async fn git_fetch_with_options(
+    app_handle: AppHandle,
     repo_path: String,
     remote: Option<String>,
     all: bool,
-) -> Result<GitOperationResult, String> {
-    repository::git_fetch_with_options(&repo_path, FetchOptions { remote, all })
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let on_progress = progress_emitter(app_handle, operation_id, "fetch", &repo_path);
+        network::fetch_with_options(&repo_path, FetchOptions { remote, all }, on_progress)
+    })
+    .await
 }
 
+/// Pull as chosen in the pull dialog, emitting `git-progress` events
 #[tauri::command]
-pub fn git_pull_with_options(
+pub async fn git_pull_with_options(
+    app_handle: AppHandle,
     repo_path: String,
     remote: String,
     branch: String,
     rebase: bool,
     autostash: bool,
-) -> Result<GitOperationResult, String> {
-    repository::git_pull_with_options(
-        &repo_path,
-        PullOptions {
-            remote,
-            branch,
-            rebase,
-            autostash,
-        },
-    )
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let on_progress = progress_emitter(app_handle, operation_id, "pull", &repo_path);
+        network::pull_with_options(
+            &repo_path,
+            PullOptions {
+                remote,
+                branch,
+                rebase,
+                autostash,
+            },
+            on_progress,
+        )
+    })
+    .await
 }
 
+/// Push as chosen in the push dialog, emitting `git-progress` events.
+// Arguments mirror the fields of the push dialog
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
-pub fn git_push_with_options(
+pub async fn git_push_with_options(
+    app_handle: AppHandle,
     repo_path: String,
     branch: String,
     remote: String,
     remote_branch: String,
     push_tags: bool,
     force_with_lease: bool,
+    operation_id: String,
+) -> Result<NetworkResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        if let Some(result) = verify_before_push(&app_handle, &operation_id, &repo_path)? {
+            return Ok(NetworkResult {
+                result,
+                updated_refs: Vec::new(),
+            });
+        }
+        let on_progress = progress_emitter(app_handle, operation_id, "push", &repo_path);
+        network::push_with_options(
+            &repo_path,
+            PushOptions {
+                branch,
+                remote,
+                remote_branch,
+                push_tags,
+                force_with_lease,
+            },
+            on_progress,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_verification_settings(repo_path: String) -> Result<VerificationSettings, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        verification::get_verification_settings(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_verification_settings(
+    repo_path: String,
+    settings: VerificationSettings,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        verification::set_verification_settings(&repo, &settings)
+    })
+    .await
+}
+
+/// Run the configured verification command now, emitting `verification-output`
+/// events; None when no command is configured
+#[tauri::command]
+pub async fn run_verification_command(
+    app_handle: AppHandle,
+    repo_path: String,
+    operation_id: String,
+) -> Result<Option<VerificationResult>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let settings = verification::get_verification_settings(&repo)?;
+        let Some(workdir) = repo.workdir().filter(|_| !settings.command.is_empty()) else {
+            return Ok(None);
+        };
+        let workdir = workdir.to_string_lossy().to_string();
+        run_verification_with_events(&app_handle, &operation_id, &repo_path, |on_line| {
+            verification::run_verification(&workdir, &settings.command, on_line).map(Some)
+        })
+    })
+    .await
+}
+
+/// Scan the keys an SSH host presents, so the user can compare them before trusting it
+#[tauri::command]
+pub async fn scan_ssh_host_keys(host: String) -> Result<Vec<known_hosts::ScannedHostKey>, String> {
+    run_blocking(move || known_hosts::scan_ssh_host_keys(&host)).await
+}
+
+/// Add the host key with the fingerprint the user confirmed to known_hosts
+#[tauri::command]
+pub async fn add_ssh_known_host(
+    host: String,
+    fingerprint: String,
 ) -> Result<GitOperationResult, String> {
-    repository::git_push_with_options(
-        &repo_path,
-        PushOptions {
-            branch,
-            remote,
-            remote_branch,
-            push_tags,
-            force_with_lease,
-        },
-    )
+    run_blocking(move || known_hosts::add_ssh_known_host(&host, &fingerprint)).await
+}
+
+/// Remotes of a repository and whether an access token is attached to them
+#[tauri::command]
+pub async fn get_remote_tokens(repo_path: String) -> Result<Vec<RemoteTokenInfo>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        remote_tokens::get_remote_tokens(&repo)
+    })
+    .await
+}
+
+/// Attach a personal access token to an HTTPS remote, stored in the OS keychain
+#[tauri::command]
+pub async fn set_remote_token(
+    repo_path: String,
+    remote: String,
+    username: Option<String>,
+    token: String,
+) -> Result<(), String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        remote_tokens::set_remote_token(&repo, &remote, username.as_deref(), &token)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn add_ssh_known_host(host: String) -> Result<GitOperationResult, String> {
-    repository::add_ssh_known_host(&host)
+pub async fn remove_remote_token(repo_path: String, remote: String) -> Result<(), String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        remote_tokens::remove_remote_token(&repo, &remote)
+    })
+    .await
 }
 
+/// Hook frameworks configured in the repository, with warnings when their
+/// hooks are not installed or their runner is missing
 #[tauri::command]
-pub fn git_commit(
+pub async fn get_hook_framework_status(
+    repo_path: String,
+) -> Result<Vec<HookFrameworkStatus>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        Ok(hook_frameworks::get_hook_framework_status(&repo))
+    })
+    .await
+}
+
+/// Issue key (JIRA-123, #456) in the current branch name, with the commit
+/// message prefix built from it
+#[tauri::command]
+pub async fn get_branch_issue_key(repo_path: String) -> Result<Option<IssueKey>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        issue_keys::get_branch_issue_key(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_issue_patterns(repo_path: String) -> Result<Vec<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        issue_keys::get_issue_patterns(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_issue_patterns(repo_path: String, patterns: Vec<String>) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        issue_keys::set_issue_patterns(&repo, &patterns)
+    })
+    .await
+}
+
+/// Warnings for a commit message under the repository's lint rules
+#[tauri::command]
+pub async fn lint_commit_message(
+    repo_path: String,
+    message: String,
+) -> Result<Vec<CommitLintWarning>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        let rules = commit_lint::get_commit_lint_rules(&repo)?;
+        Ok(commit_lint::lint_commit_message(&message, &rules))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_commit_lint_rules(repo_path: String) -> Result<CommitLintRules, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        commit_lint::get_commit_lint_rules(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_commit_lint_rules(
+    repo_path: String,
+    rules: CommitLintRules,
+) -> Result<(), String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        commit_lint::set_commit_lint_rules(&repo, &rules)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_commit(
     repo_path: String,
     message: String,
     amend: bool,
 ) -> Result<GitOperationResult, String> {
-    repository::git_commit(&repo_path, &message, amend)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let subject = message.lines().next().unwrap_or_default().to_string();
+        let summary = if amend {
+            format!("Amend commit: {}", subject)
+        } else {
+            format!("Commit: {}", subject)
+        };
+        operation_history::record(&repo_path, OperationKind::Commit, summary, || {
+            repository::git_commit(&repo_path, &message, amend)
+        })
+    })
+    .await
 }
 
+/// Operations performed through Forky in the repository, newest first
 #[tauri::command]
-pub fn get_last_commit_message(repo_path: String) -> Result<CommitMessage, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_last_commit_message(&repo)
+pub async fn get_operation_history(
+    repo_path: String,
+    limit: Option<usize>,
+) -> Result<Vec<OperationRecord>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        operation_history::get_operation_history(&repo, limit.unwrap_or(100))
+    })
+    .await
 }
 
+/// Undo the latest operation of the history, putting back the references,
+/// index and work tree it changed
 #[tauri::command]
-pub fn git_add_remote(
+pub async fn undo_last_operation(repo_path: String) -> Result<GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        operation_history::undo_last_operation(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_last_commit_message(repo_path: String) -> Result<CommitMessage, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_last_commit_message(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_add_remote(
     repo_path: String,
     name: String,
     url: String,
 ) -> Result<GitOperationResult, String> {
-    repository::git_add_remote(&repo_path, &name, &url)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_add_remote(&repo_path, &name, &url)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_test_remote_connection(url: String) -> Result<GitOperationResult, String> {
-    repository::git_test_remote_connection(&url)
+pub async fn git_test_remote_connection(url: String) -> Result<GitOperationResult, String> {
+    run_blocking(move || repository::git_test_remote_connection(&url)).await
 }
 
 #[tauri::command]
-pub fn git_checkout(
+pub async fn git_checkout(
     repo_path: String,
     branch_name: String,
 ) -> Result<GitOperationResult, String> {
-    repository::git_checkout(&repo_path, &branch_name)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_checkout(&repo_path, &branch_name)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_checkout_with_stash(
+pub async fn git_checkout_with_stash(
     repo_path: String,
     branch_name: String,
     restore_changes: bool,
 ) -> Result<GitOperationResult, String> {
-    repository::git_checkout_with_stash(&repo_path, &branch_name, restore_changes)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_checkout_with_stash(&repo_path, &branch_name, restore_changes)
+    })
+    .await
+}
+
+/// Whether switching to a branch would conflict with local changes, and
+/// whether carrying them over with a stash would apply cleanly
+#[tauri::command]
+pub async fn checkout_preflight(
+    repo_path: String,
+    branch_name: String,
+) -> Result<repository::CheckoutPreflight, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::checkout_preflight(&repo, &branch_name)
+    })
+    .await
+}
+
+/// Commits an amend, rebase or force push would rewrite that are already on a
+/// remote, with a warning to show before going ahead
+#[tauri::command]
+pub async fn rewrite_preflight(
+    repo_path: String,
+    operation: repository::RewriteOperation,
+) -> Result<repository::RewritePreflight, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::rewrite_preflight(&repo, &operation)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_previous_branch(repo_path: String) -> Result<Option<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_previous_branch(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_checkout_previous(
+    repo_path: String,
+    restore_changes: Option<bool>,
+) -> Result<GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::git_checkout_previous(&repo, &repo_path, restore_changes)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_checkout_track(
+pub async fn git_checkout_track(
     repo_path: String,
     local_branch: String,
     remote_branch: String,
 ) -> Result<GitOperationResult, String> {
-    repository::git_checkout_track(&repo_path, &local_branch, &remote_branch)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_checkout_track(&repo_path, &local_branch, &remote_branch)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_create_branch(
+pub async fn git_create_branch(
     repo_path: String,
     branch_name: String,
     start_point: String,
     checkout: bool,
 ) -> Result<GitOperationResult, String> {
-    repository::git_create_branch(&repo_path, &branch_name, &start_point, checkout)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_create_branch(&repo_path, &branch_name, &start_point, checkout)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_create_tag(
+pub async fn git_create_tag(
     repo_path: String,
     tag_name: String,
     start_point: String,
     message: Option<String>,
     push_to_remotes: bool,
 ) -> Result<GitOperationResult, String> {
-    repository::git_create_tag(
-        &repo_path,
-        &tag_name,
-        &start_point,
-        message.as_deref(),
-        push_to_remotes,
-    )
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_create_tag(
+            &repo_path,
+            &tag_name,
+            &start_point,
+            message.as_deref(),
+            push_to_remotes,
+        )
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_rename_branch(
+pub async fn git_rename_branch(
     repo_path: String,
     old_name: String,
     new_name: String,
     rename_remote: bool,
     remote_name: Option<String>,
 ) -> Result<GitOperationResult, String> {
-    repository::git_rename_branch(
-        &repo_path,
-        &old_name,
-        &new_name,
-        rename_remote,
-        remote_name.as_deref(),
-    )
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_rename_branch(
+            &repo_path,
+            &old_name,
+            &new_name,
+            rename_remote,
+            remote_name.as_deref(),
+        )
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_delete_branch(
+pub async fn git_delete_branch(
     repo_path: String,
     branch_name: String,
     force: bool,
     delete_remote: bool,
     remote_name: Option<String>,
 ) -> Result<GitOperationResult, String> {
-    repository::git_delete_branch(
-        &repo_path,
-        &branch_name,
-        force,
-        delete_remote,
-        remote_name.as_deref(),
-    )
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Delete branch {}", branch_name);
+        operation_history::record(&repo_path, OperationKind::DeleteBranch, summary, || {
+            repository::git_delete_branch(
+                &repo_path,
+                &branch_name,
+                force,
+                delete_remote,
+                remote_name.as_deref(),
+            )
+        })
+    })
+    .await
 }
 
 // ============================================================================
@@ -335,42 +1480,80 @@ pub fn git_delete_branch(
 // ============================================================================
 
 #[tauri::command]
-pub fn get_stashes(repo_path: String) -> Result<Vec<StashInfo>, String> {
-    repository::get_stashes(&repo_path)
+pub async fn get_stashes(repo_path: String) -> Result<Vec<StashInfo>, String> {
+    run_blocking(move || repository::get_stashes(&repo_path)).await
 }
 
 #[tauri::command]
-pub fn git_stash_save(
+pub async fn git_stash_save(
     repo_path: String,
     message: Option<String>,
     include_untracked: bool,
     keep_index: bool,
 ) -> Result<GitOperationResult, String> {
-    repository::git_stash_save(&repo_path, message.as_deref(), include_untracked, keep_index)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_stash_save(
+            &repo_path,
+            message.as_deref(),
+            include_untracked,
+            keep_index,
+        )
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_stash_apply(
+pub async fn git_stash_apply(
     repo_path: String,
     stash_index: usize,
 ) -> Result<GitOperationResult, String> {
-    repository::git_stash_apply(&repo_path, stash_index)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_stash_apply(&repo_path, stash_index)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_stash_pop(
+pub async fn git_stash_pop(
     repo_path: String,
     stash_index: usize,
 ) -> Result<GitOperationResult, String> {
-    repository::git_stash_pop(&repo_path, stash_index)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_stash_pop(&repo_path, stash_index)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_stash_drop(
+pub async fn git_stash_drop(
     repo_path: String,
     stash_index: usize,
 ) -> Result<GitOperationResult, String> {
-    repository::git_stash_drop(&repo_path, stash_index)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        operation_history::record_stash_drop(&repo_path, stash_index, || {
+            repository::git_stash_drop(&repo_path, stash_index)
+        })
+    })
+    .await
+}
+
+/// A stash, untracked files included, as a patch written to `output_path` or
+/// returned as text
+#[tauri::command]
+pub async fn export_stash(
+    repo_path: String,
+    stash_index: usize,
+    output_path: Option<String>,
+) -> Result<repository::WorkingPatch, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::export_stash(&repo, stash_index, output_path.as_deref())
+    })
+    .await
 }
 
 // ============================================================================
@@ -378,30 +1561,53 @@ pub fn git_stash_drop(
 // ============================================================================
 
 #[tauri::command]
-pub fn get_image_content(
+pub async fn get_image_content(
     repo_path: String,
     file_path: String,
 ) -> Result<ImageContent, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_image_content(&repo, &file_path)
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_image_content(&repo, &file_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_image_from_head(
+pub async fn get_image_from_head(
     repo_path: String,
     file_path: String,
 ) -> Result<ImageContent, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_image_from_head(&repo, &file_path)
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_image_from_head(&repo, &file_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_image_from_index(
+pub async fn get_image_from_index(
     repo_path: String,
     file_path: String,
 ) -> Result<ImageContent, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_image_from_index(&repo, &file_path)
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_image_from_index(&repo, &file_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_media_diff_info(
+    repo_path: String,
+    file_path: String,
+    staged: bool,
+    commit_id: Option<String>,
+) -> Result<MediaDiffInfo, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_media_diff_info(&repo, &file_path, staged, commit_id.as_deref())
+    })
+    .await
 }
 
 // ============================================================================
@@ -409,30 +1615,104 @@ pub fn get_image_from_index(
 // ============================================================================
 
 #[tauri::command]
-pub fn stage_hunk(
+pub async fn stage_hunk(
     repo_path: String,
     file_path: String,
     hunk: HunkData,
 ) -> Result<(), String> {
-    repository::stage_hunk(&repo_path, &file_path, hunk)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::stage_hunk(&repo, &file_path, hunk)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn unstage_hunk(
+pub async fn unstage_hunk(
     repo_path: String,
     file_path: String,
     hunk: HunkData,
 ) -> Result<(), String> {
-    repository::unstage_hunk(&repo_path, &file_path, hunk)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        repository::unstage_hunk(&repo, &file_path, hunk)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn discard_hunk(
+pub async fn discard_hunk(
     repo_path: String,
     file_path: String,
     hunk: HunkData,
 ) -> Result<(), String> {
-    repository::discard_hunk(&repo_path, &file_path, hunk)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::discard_hunk(&repo_path, &file_path, hunk)
+    })
+    .await
+}
+
+/// Apply a .patch/.diff file to the repository (working tree, plus index with `to_index`)
+#[tauri::command]
+pub async fn apply_patch_file(
+    repo_path: String,
+    patch_path: String,
+    to_index: Option<bool>,
+    check_only: Option<bool>,
+) -> Result<GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let canonical = validate_repo_path_string(&repo_path)?;
+        repository::apply_patch_file(
+            &canonical.to_string_lossy(),
+            &patch_path,
+            to_index.unwrap_or(false),
+            check_only.unwrap_or(false),
+        )
+    })
+    .await
+}
+
+/// Apply a shared patch to the working tree, with a three-way merge fallback
+/// or `.rej` files for the hunks that do not apply
+#[tauri::command]
+pub async fn apply_working_diff(
+    repo_path: String,
+    patch: String,
+    three_way: bool,
+) -> Result<repository::PatchApplyResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let canonical = validate_repo_path_string(&repo_path)?;
+        repository::apply_working_diff(&canonical.to_string_lossy(), &patch, three_way)
+    })
+    .await
+}
+
+/// Classify paths dropped onto the window and forward them to the frontend as a
+/// `files-dropped` event (patches to apply, folders to open as repositories)
+pub fn handle_dropped_paths(app_handle: &AppHandle, paths: &[PathBuf]) {
+    let dropped = repository::classify_dropped_paths(paths);
+    if let Err(e) = app_handle.emit("files-dropped", dropped) {
+        eprintln!("Failed to emit files dropped event: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn preview_hunk_patch(
+    repo_path: String,
+    file_path: String,
+    hunk: HunkData,
+    operation: String,
+) -> Result<HunkPatchPreview, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::preview_hunk_patch(&repo, &file_path, hunk, &operation)
+    })
+    .await
 }
 
 // ============================================================================
@@ -440,25 +1720,75 @@ pub fn discard_hunk(
 // ============================================================================
 
 #[tauri::command]
-pub fn get_merge_preview(
+pub async fn get_merge_preview(
     repo_path: String,
     source_branch: String,
 ) -> Result<repository::MergePreview, String> {
-    repository::get_merge_preview(&repo_path, &source_branch)
+    run_blocking(move || repository::get_merge_preview(&repo_path, &source_branch)).await
+}
+
+#[tauri::command]
+pub async fn get_merge_changed_files(
+    repo_path: String,
+    source_branch: String,
+) -> Result<Vec<FileStatus>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_merge_changed_files(&repo, &source_branch)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_merge(
+pub async fn git_merge(
     repo_path: String,
     source_branch: String,
     merge_type: String,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_merge(&repo_path, &source_branch, &merge_type)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Merge {}", source_branch);
+        operation_history::record(&repo_path, OperationKind::Merge, summary, || {
+            repository::git_merge(&repo_path, &source_branch, &merge_type)
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_merge_abort(repo_path: String) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_merge_abort(&repo_path)
+    })
+    .await
+}
+
+/// Message prepared for the merge in progress, None when there is none
+#[tauri::command]
+pub async fn get_merge_message(repo_path: String) -> Result<Option<String>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_merge_message(&repo)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_merge_abort(repo_path: String) -> Result<repository::GitOperationResult, String> {
-    repository::git_merge_abort(&repo_path)
+pub async fn git_merge_continue(
+    repo_path: String,
+    message: Option<String>,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        operation_history::record(
+            &repo_path,
+            OperationKind::Merge,
+            "Commit merge".to_string(),
+            || repository::git_merge_continue(&repo_path, message.as_deref()),
+        )
+    })
+    .await
 }
 
 // ============================================================================
@@ -466,75 +1796,281 @@ pub fn git_merge_abort(repo_path: String) -> Result<repository::GitOperationResu
 // ============================================================================
 
 #[tauri::command]
-pub fn get_rebase_preview(
+pub async fn get_rebase_preview(
     repo_path: String,
     target_branch: String,
 ) -> Result<repository::RebasePreview, String> {
-    repository::get_rebase_preview(&repo_path, &target_branch)
+    run_blocking(move || repository::get_rebase_preview(&repo_path, &target_branch)).await
 }
 
 #[tauri::command]
-pub fn git_rebase(
+pub async fn get_rebase_changed_files(
+    repo_path: String,
+    target_branch: String,
+) -> Result<Vec<FileStatus>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_rebase_changed_files(&repo, &target_branch)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_rebase(
     repo_path: String,
     target_branch: String,
     preserve_merges: bool,
     autostash: bool,
 ) -> Result<repository::GitOperationResult, String> {
-    let options = repository::RebaseOptions {
-        preserve_merges,
-        autostash,
-    };
-    repository::git_rebase(&repo_path, &target_branch, options)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let options = repository::RebaseOptions {
+            preserve_merges,
+            autostash,
+        };
+        let summary = format!("Rebase onto {}", target_branch);
+        operation_history::record(&repo_path, OperationKind::Rebase, summary, || {
+            repository::git_rebase(&repo_path, &target_branch, options)
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_cherry_pick(
+    repo_path: String,
+    commit_id: String,
+    no_commit: bool,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Cherry-pick {}", commit_id.get(..7).unwrap_or(&commit_id));
+        operation_history::record(&repo_path, OperationKind::CherryPick, summary, || {
+            repository::git_cherry_pick(&repo_path, &commit_id, no_commit)
+        })
+    })
+    .await
+}
+
+/// Cherry-pick commits and ranges (`A..B`) in order, emitting a `git-progress`
+/// event after each commit
+#[tauri::command]
+pub async fn git_cherry_pick_range(
+    app_handle: AppHandle,
+    repo_path: String,
+    commits: Vec<String>,
+    operation_id: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let on_progress = progress_emitter(app_handle, operation_id, "cherry_pick", &repo_path);
+        let summary = format!("Cherry-pick {}", commits.join(", "));
+        operation_history::record(&repo_path, OperationKind::CherryPick, summary, || {
+            repository::git_cherry_pick_range(&repo_path, &commits, on_progress)
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_cherry_pick_abort(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_cherry_pick_abort(&repo_path)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_rebase_abort(repo_path: String) -> Result<repository::GitOperationResult, String> {
-    repository::git_rebase_abort(&repo_path)
+pub async fn git_cherry_pick_continue(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        operation_history::record(
+            &repo_path,
+            OperationKind::CherryPick,
+            "Continue cherry-pick".to_string(),
+            || repository::git_cherry_pick_continue(&repo_path),
+        )
+    })
+    .await
 }
 
+/// Revert a commit; `mainline` (1-based parent to keep) is required for merge
+/// commits
 #[tauri::command]
-pub fn git_rebase_continue(
+pub async fn git_revert(
     repo_path: String,
+    commit_id: String,
+    mainline: Option<u32>,
+    no_commit: bool,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_rebase_continue(&repo_path)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Revert {}", commit_id.get(..7).unwrap_or(&commit_id));
+        operation_history::record(&repo_path, OperationKind::Revert, summary, || {
+            repository::git_revert(&repo_path, &commit_id, mainline, no_commit)
+        })
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_interactive_rebase_commits(
+pub async fn git_revert_abort(repo_path: String) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_revert_abort(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_revert_continue(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        operation_history::record(
+            &repo_path,
+            OperationKind::Revert,
+            "Continue revert".to_string(),
+            || repository::git_revert_continue(&repo_path),
+        )
+    })
+    .await
+}
+
+/// Commits and local changes a reset to `commit_id` would lose, for its
+/// confirmation
+#[tauri::command]
+pub async fn reset_preview(
+    repo_path: String,
+    commit_id: String,
+    mode: repository::ResetMode,
+) -> Result<repository::ResetPreview, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::reset_preview(&repo, &commit_id, mode)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_reset(
+    repo_path: String,
+    commit_id: String,
+    mode: repository::ResetMode,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Reset to {}", commit_id.get(..7).unwrap_or(&commit_id));
+        operation_history::record(&repo_path, OperationKind::Reset, summary, || {
+            repository::git_reset(&repo_path, &commit_id, mode)
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_rebase_abort(repo_path: String) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_rebase_abort(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_rebase_continue(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        operation_history::record(
+            &repo_path,
+            OperationKind::Rebase,
+            "Continue rebase".to_string(),
+            || repository::git_rebase_continue(&repo_path),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_interactive_rebase_commits(
     repo_path: String,
     target_branch: String,
 ) -> Result<Vec<InteractiveRebaseEntry>, String> {
-    repository::get_interactive_rebase_commits(&repo_path, &target_branch)
+    run_blocking(move || repository::get_interactive_rebase_commits(&repo_path, &target_branch))
+        .await
 }
 
 #[tauri::command]
-pub fn git_interactive_rebase(
+pub async fn git_interactive_rebase(
     repo_path: String,
     target_branch: String,
     entries: Vec<InteractiveRebaseEntry>,
     autostash: bool,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_interactive_rebase(&repo_path, &target_branch, entries, autostash)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Interactive rebase onto {}", target_branch);
+        operation_history::record(&repo_path, OperationKind::Rebase, summary, || {
+            repository::git_interactive_rebase(&repo_path, &target_branch, entries, autostash)
+        })
+    })
+    .await
 }
 
 // ==================== Git Flow Commands ====================
 
 #[tauri::command]
-pub fn get_gitflow_config(repo_path: String) -> Result<repository::GitFlowConfig, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_gitflow_config(&repo)
+pub async fn get_gitflow_config(repo_path: String) -> Result<repository::GitFlowConfig, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_gitflow_config(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn validate_gitflow_config(
+    repo_path: String,
+) -> Result<Vec<repository::GitFlowIssue>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::validate_gitflow_config(&repo)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_current_branch_flow_info(
+pub async fn repair_gitflow_config(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::repair_gitflow_config(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_current_branch_flow_info(
     repo_path: String,
 ) -> Result<repository::CurrentBranchFlowInfo, String> {
-    let repo = open_validated_repo(&repo_path)?;
-    repository::get_current_branch_flow_info(&repo)
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        repository::get_current_branch_flow_info(&repo)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_flow_init(
+pub async fn git_flow_init(
     repo_path: String,
     master_branch: String,
     develop_branch: String,
@@ -543,57 +2079,275 @@ pub fn git_flow_init(
     hotfix_prefix: String,
     version_tag_prefix: String,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_flow_init(
-        &repo_path,
-        &master_branch,
-        &develop_branch,
-        &feature_prefix,
-        &release_prefix,
-        &hotfix_prefix,
-        &version_tag_prefix,
-    )
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_flow_init(
+            &repo_path,
+            &master_branch,
+            &develop_branch,
+            &feature_prefix,
+            &release_prefix,
+            &hotfix_prefix,
+            &version_tag_prefix,
+        )
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_flow_start(
+pub async fn git_flow_start(
     repo_path: String,
     flow_type: String,
     name: String,
     base_branch: Option<String>,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_flow_start(&repo_path, &flow_type, &name, base_branch.as_deref())
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_flow_start(&repo_path, &flow_type, &name, base_branch.as_deref())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_flow_finish(
+pub async fn git_flow_finish(
     repo_path: String,
     flow_type: String,
     name: String,
     delete_branch: bool,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_flow_finish(&repo_path, &flow_type, &name, delete_branch)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        flow_finish::git_flow_finish(&repo_path, &flow_type, &name, delete_branch)
+    })
+    .await
+}
+
+/// The git flow finish interrupted by a conflict, if any
+#[tauri::command]
+pub async fn get_flow_finish_state(repo_path: String) -> Result<Option<FlowFinishState>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        Ok(flow_finish::get_flow_finish_state(&repo))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_flow_finish_continue(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        flow_finish::git_flow_finish_continue(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_flow_finish_abort(
+    repo_path: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        flow_finish::git_flow_finish_abort(&repo_path)
+    })
+    .await
+}
+
+// ==================== Stacked Branch Commands ====================
+
+/// The stack `branch` belongs to, its base first
+#[tauri::command]
+pub async fn get_branch_stack(
+    repo_path: String,
+    branch: String,
+) -> Result<Vec<StackBranch>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        stack::get_branch_stack(&repo, &branch)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn restack_branches(
+    repo_path: String,
+    branch: String,
+) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let summary = format!("Restack {}", branch);
+        operation_history::record(&repo_path, OperationKind::Rebase, summary, || {
+            stack::restack_branches(&repo_path, &branch)
+        })
+    })
+    .await
+}
+
+/// The restack interrupted by a conflict, if any
+#[tauri::command]
+pub async fn get_restack_state(repo_path: String) -> Result<Option<RestackState>, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        Ok(stack::get_restack_state(&repo))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn restack_continue(repo_path: String) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        stack::restack_continue(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn restack_abort(repo_path: String) -> Result<repository::GitOperationResult, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        stack::restack_abort(&repo_path)
+    })
+    .await
 }
 
 // ==================== Global Git Identity Commands ====================
 
 #[tauri::command]
-pub fn git_get_global_identity() -> Result<GitIdentity, String> {
-    repository::git_get_global_identity()
+pub async fn git_get_global_identity() -> Result<GitIdentity, String> {
+    run_blocking(repository::git_get_global_identity).await
+}
+
+/// The identity used for commits in a repository, honoring conditional includes
+#[tauri::command]
+pub async fn git_get_repository_identity(
+    repo_path: String,
+) -> Result<repository::RepositoryIdentity, String> {
+    run_blocking(move || {
+        let canonical = validate_repo_path_string(&repo_path)?;
+        repository::git_get_repository_identity(&canonical.to_string_lossy())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn git_set_global_identity(
+pub async fn git_set_global_identity(
     name: String,
     email: String,
 ) -> Result<GitOperationResult, String> {
-    repository::git_set_global_identity(&name, &email)
+    run_blocking(move || repository::git_set_global_identity(&name, &email)).await
 }
 
 #[tauri::command]
-pub fn git_fast_forward(
+pub async fn git_fast_forward(
     repo_path: String,
     branch: String,
     remote: String,
 ) -> Result<repository::GitOperationResult, String> {
-    repository::git_fast_forward(&repo_path, &branch, &remote)
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        repository::git_fast_forward(&repo_path, &branch, &remote)
+    })
+    .await
+}
+
+fn environment_file(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    Ok(dir.join(environment::ENVIRONMENT_FILE))
+}
+
+#[tauri::command]
+pub fn get_performance_report() -> PerformanceReport {
+    trace::get_performance_report()
+}
+
+/// Turn the performance trace on (with new totals) or off
+#[tauri::command]
+pub fn set_performance_trace(enabled: bool) -> PerformanceReport {
+    trace::set_enabled(enabled);
+    trace::get_performance_report()
+}
+
+#[tauri::command]
+pub fn reset_performance_report() -> PerformanceReport {
+    trace::reset();
+    trace::get_performance_report()
+}
+
+/// Keep the scratch files in the app data dir and remove those left over by
+/// earlier runs; called once at startup
+pub fn init_scratch(app_handle: &AppHandle) {
+    match app_handle.path().app_data_dir() {
+        Ok(dir) => scratch::set_root(dir.join(scratch::SCRATCH_DIR)),
+        Err(e) => eprintln!("Failed to resolve app data directory: {}", e),
+    }
+    scratch::clean(scratch::STALE_AFTER);
+}
+
+/// Remove the scratch files (rebase todo lists, editor scripts, textconv
+/// input) older than `max_age_secs` (one hour by default, five minutes at
+/// least) left behind by crashed or closed instances
+#[tauri::command]
+pub async fn clean_scratch(max_age_secs: Option<u64>) -> Result<ScratchCleanup, String> {
+    run_blocking(move || {
+        let max_age = max_age_secs.map_or(scratch::STALE_AFTER, Duration::from_secs);
+        Ok(scratch::clean(max_age))
+    })
+    .await
+}
+
+/// Apply the saved git environment settings; called once at startup
+pub fn load_git_environment(app_handle: &AppHandle) {
+    match environment_file(app_handle).and_then(|path| environment::load(&path)) {
+        Ok(settings) => environment::set_current(settings),
+        Err(e) => eprintln!("Failed to load git environment settings: {}", e),
+    }
+}
+
+/// Extra PATH entries and variables passed to the git commands Forky runs
+#[tauri::command]
+pub fn get_git_environment() -> GitEnvironment {
+    environment::current()
+}
+
+/// Save the extra PATH entries and variables and apply them to git commands
+/// started from now on (hooks run by commit, push, etc. inherit them)
+#[tauri::command]
+pub fn set_git_environment(
+    app_handle: AppHandle,
+    settings: GitEnvironment,
+) -> Result<GitEnvironment, String> {
+    settings.validate()?;
+    environment::save(&environment_file(&app_handle)?, &settings)?;
+    environment::set_current(settings.clone());
+    // PATH may now lead to another git
+    capabilities::reset();
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn export_onboarding_bundle(repo_path: String) -> Result<OnboardingBundle, String> {
+    run_blocking(move || {
+        let repo = open_validated_repo(&repo_path)?;
+        onboarding::export_onboarding_bundle(&repo)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn import_onboarding_bundle(
+    repo_path: String,
+    bundle: OnboardingBundle,
+    allow_commands: bool,
+) -> Result<BundleImportSummary, String> {
+    run_blocking(move || {
+        ensure_writable(&repo_path)?;
+        let repo = open_validated_repo(&repo_path)?;
+        onboarding::import_onboarding_bundle(&repo, &bundle, allow_commands)
+    })
+    .await
 }
@@ -0,0 +1,369 @@
+//! Commit message lint.
+//!
+//! Checks a message against the usual conventions (short subject in the
+//! imperative mood, blank line before a wrapped body, no trailing whitespace)
+//! and returns warnings for the commit dialog; nothing here blocks a commit.
+//! The rules are stored under `forky.commitLint.*` in the git config, so a
+//! team can tune them per repository.
+
+use git2::{Config, Repository};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitLintRules {
+    pub subject_max_length: usize,   // 0 disables the check
+    pub body_max_line_length: usize, // 0 disables the check
+    pub imperative_subject: bool,
+    pub subject_period: bool, // warn on a subject ending with "."
+    pub blank_line_after_subject: bool,
+    pub trailing_whitespace: bool,
+}
+
+impl Default for CommitLintRules {
+    fn default() -> Self {
+        Self {
+            subject_max_length: 72,
+            body_max_line_length: 72,
+            imperative_subject: true,
+            subject_period: true,
+            blank_line_after_subject: true,
+            trailing_whitespace: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitLintWarning {
+    pub rule: String, // field of CommitLintRules that raised it
+    pub line: usize,  // 1-based line of the message
+    pub message: String,
+}
+
+/// Verbs commonly starting a subject; their past, gerund and third person
+/// forms are what a non-imperative subject usually starts with
+const COMMON_VERBS: &[&str] = &[
+    "add",
+    "adjust",
+    "allow",
+    "avoid",
+    "bump",
+    "change",
+    "clean",
+    "convert",
+    "create",
+    "delete",
+    "deprecate",
+    "disable",
+    "document",
+    "drop",
+    "enable",
+    "ensure",
+    "extract",
+    "fix",
+    "handle",
+    "hide",
+    "implement",
+    "improve",
+    "introduce",
+    "make",
+    "merge",
+    "migrate",
+    "move",
+    "optimize",
+    "prevent",
+    "refactor",
+    "remove",
+    "rename",
+    "reorder",
+    "replace",
+    "restore",
+    "revert",
+    "rewrite",
+    "show",
+    "simplify",
+    "support",
+    "switch",
+    "update",
+    "upgrade",
+    "use",
+];
+
+fn rule_key(name: &str) -> String {
+    format!("forky.commitLint.{}", name)
+}
+
+fn read_length(config: &Config, name: &str, default: usize) -> usize {
+    config
+        .get_i64(&rule_key(name))
+        .ok()
+        .and_then(|value| usize::try_from(value).ok())
+        .unwrap_or(default)
+}
+
+fn read_flag(config: &Config, name: &str, default: bool) -> bool {
+    config.get_bool(&rule_key(name)).unwrap_or(default)
+}
+
+/// The rules configured for the repository (local, then global config), with
+/// the defaults for anything not set
+pub fn get_commit_lint_rules(repo: &Repository) -> Result<CommitLintRules, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("Failed to read config: {}", e))?;
+    let defaults = CommitLintRules::default();
+    Ok(CommitLintRules {
+        subject_max_length: read_length(&config, "subjectMaxLength", defaults.subject_max_length),
+        body_max_line_length: read_length(
+            &config,
+            "bodyMaxLineLength",
+            defaults.body_max_line_length,
+        ),
+        imperative_subject: read_flag(&config, "imperativeSubject", defaults.imperative_subject),
+        subject_period: read_flag(&config, "subjectPeriod", defaults.subject_period),
+        blank_line_after_subject: read_flag(
+            &config,
+            "blankLineAfterSubject",
+            defaults.blank_line_after_subject,
+        ),
+        trailing_whitespace: read_flag(&config, "trailingWhitespace", defaults.trailing_whitespace),
+    })
+}
+
+/// Store the rules in the repository config
+pub fn set_commit_lint_rules(repo: &Repository, rules: &CommitLintRules) -> Result<(), String> {
+    let mut config = repo
+        .config()
+        .and_then(|config| config.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| format!("Failed to open config: {}", e))?;
+
+    let lengths = [
+        ("subjectMaxLength", rules.subject_max_length),
+        ("bodyMaxLineLength", rules.body_max_line_length),
+    ];
+    for (name, value) in lengths {
+        config
+            .set_i64(&rule_key(name), value as i64)
+            .map_err(|e| format!("Failed to set {}: {}", rule_key(name), e))?;
+    }
+    let flags = [
+        ("imperativeSubject", rules.imperative_subject),
+        ("subjectPeriod", rules.subject_period),
+        ("blankLineAfterSubject", rules.blank_line_after_subject),
+        ("trailingWhitespace", rules.trailing_whitespace),
+    ];
+    for (name, value) in flags {
+        config
+            .set_bool(&rule_key(name), value)
+            .map_err(|e| format!("Failed to set {}: {}", rule_key(name), e))?;
+    }
+    Ok(())
+}
+
+/// The first word of the subject after a conventional commit type
+/// (`feat(ui): `) or an issue key (`PROJ-42: `, `[PROJ-42] `, `#12 `)
+fn first_word(subject: &str) -> Option<&str> {
+    let mut rest = subject.trim();
+    loop {
+        if let Some(inner) = rest.strip_prefix('[') {
+            match inner.split_once(']') {
+                Some((_, after)) => rest = after.trim_start(),
+                None => break,
+            }
+        } else if rest.starts_with('#') {
+            rest = rest
+                .split_once(' ')
+                .map_or("", |(_, after)| after.trim_start());
+        } else {
+            match rest.split_once(": ") {
+                Some((prefix, after)) if !prefix.contains(' ') => rest = after.trim_start(),
+                _ => break,
+            }
+        }
+    }
+    rest.split_whitespace().next()
+}
+
+/// Whether `word` looks like a past, gerund or third person form ("Added",
+/// "fixing", "Updates") of a verb commonly used in subjects
+fn is_non_imperative(word: &str) -> bool {
+    let word = word.to_lowercase();
+    COMMON_VERBS.iter().any(|verb| {
+        let stem = verb.strip_suffix('e').unwrap_or(verb);
+        let last = verb.chars().last().unwrap_or_default();
+        let mut forms = vec![
+            format!("{}ed", stem),
+            format!("{}ing", stem),
+            format!("{}s", verb),
+            format!("{}es", verb),
+        ];
+        // bump -> bumped, drop -> dropped
+        if verb.len() <= 4 && !"aeiouwxy".contains(last) {
+            forms.push(format!("{}{}ed", verb, last));
+            forms.push(format!("{}{}ing", verb, last));
+        }
+        forms.contains(&word)
+    })
+}
+
+fn warning(rule: &str, line: usize, message: String) -> CommitLintWarning {
+    CommitLintWarning {
+        rule: rule.to_string(),
+        line,
+        message,
+    }
+}
+
+/// Warnings for `message` under `rules`, in line order
+pub fn lint_commit_message(message: &str, rules: &CommitLintRules) -> Vec<CommitLintWarning> {
+    let lines: Vec<(usize, &str)> = message
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .collect();
+    let mut warnings = Vec::new();
+    let Some(&(subject_line, subject)) = lines.first() else {
+        return warnings;
+    };
+
+    let length = subject.trim_end().chars().count();
+    if rules.subject_max_length > 0 && length > rules.subject_max_length {
+        warnings.push(warning(
+            "subject_max_length",
+            subject_line,
+            format!(
+                "Subject is {} characters long; keep it under {}",
+                length, rules.subject_max_length
+            ),
+        ));
+    }
+    if rules.subject_period && subject.trim_end().ends_with('.') && !subject.ends_with("...") {
+        warnings.push(warning(
+            "subject_period",
+            subject_line,
+            "Subject should not end with a period".to_string(),
+        ));
+    }
+    if rules.imperative_subject {
+        if let Some(word) = first_word(subject).filter(|word| is_non_imperative(word)) {
+            warnings.push(warning(
+                "imperative_subject",
+                subject_line,
+                format!(
+                    "Write the subject in the imperative mood (\"Fix bug\", not \"{} bug\")",
+                    word
+                ),
+            ));
+        }
+    }
+    if let Some(&(line, text)) = lines.get(1) {
+        if rules.blank_line_after_subject && !text.trim().is_empty() {
+            warnings.push(warning(
+                "blank_line_after_subject",
+                line,
+                "Separate the subject from the body with a blank line".to_string(),
+            ));
+        }
+    }
+
+    for &(line, text) in &lines {
+        if rules.trailing_whitespace && text != text.trim_end() {
+            warnings.push(warning(
+                "trailing_whitespace",
+                line,
+                "Line has trailing whitespace".to_string(),
+            ));
+        }
+        let length = text.trim_end().chars().count();
+        // Lines without spaces (URLs, paths) cannot be wrapped
+        if line != subject_line
+            && rules.body_max_line_length > 0
+            && length > rules.body_max_line_length
+            && text.trim().contains(char::is_whitespace)
+        {
+            warnings.push(warning(
+                "body_max_line_length",
+                line,
+                format!(
+                    "Line is {} characters long; wrap the body at {}",
+                    length, rules.body_max_line_length
+                ),
+            ));
+        }
+    }
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_raised(message: &str) -> Vec<String> {
+        lint_commit_message(message, &CommitLintRules::default())
+            .into_iter()
+            .map(|warning| warning.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_commit_message_rules() {
+        assert!(rules_raised("Fix crash when opening a bare repository").is_empty());
+        assert!(rules_raised("feat(ui): add dark theme\n\nWith a toggle.\n").is_empty());
+        assert!(rules_raised("").is_empty());
+
+        assert_eq!(
+            rules_raised("Fixed the crash."),
+            ["subject_period", "imperative_subject"]
+        );
+        assert_eq!(
+            rules_raised("PROJ-42: Adding dark theme"),
+            ["imperative_subject"]
+        );
+        assert_eq!(
+            rules_raised("[PROJ-42] Bumped version"),
+            ["imperative_subject"]
+        );
+        assert_eq!(rules_raised("Updates docs"), ["imperative_subject"]);
+        assert_eq!(rules_raised("#12 Fixing typo"), ["imperative_subject"]);
+        assert_eq!(
+            rules_raised("Add theme\nsecond line "),
+            ["blank_line_after_subject", "trailing_whitespace"]
+        );
+
+        let long_body = format!(
+            "Add theme\n\n{}\n{}",
+            "word ".repeat(20).trim(),
+            "x".repeat(90)
+        );
+        let warnings = lint_commit_message(&long_body, &CommitLintRules::default());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].rule, "body_max_line_length");
+        assert_eq!(warnings[0].line, 3);
+
+        let subject = "A".repeat(80);
+        assert_eq!(rules_raised(&subject), ["subject_max_length"]);
+        let relaxed = CommitLintRules {
+            subject_max_length: 0,
+            ..CommitLintRules::default()
+        };
+        assert!(lint_commit_message(&subject, &relaxed).is_empty());
+    }
+
+    #[test]
+    fn test_rules_round_trip_through_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        assert_eq!(
+            get_commit_lint_rules(&repo).unwrap(),
+            CommitLintRules::default()
+        );
+
+        let rules = CommitLintRules {
+            subject_max_length: 50,
+            imperative_subject: false,
+            ..CommitLintRules::default()
+        };
+        set_commit_lint_rules(&repo, &rules).unwrap();
+        assert_eq!(get_commit_lint_rules(&repo).unwrap(), rules);
+    }
+}
@@ -0,0 +1,302 @@
+//! Conflicted files of a merge, rebase, cherry-pick or stash in progress.
+//!
+//! A conflicted path has up to three entries in the index, one per stage: the
+//! common ancestor (1), our side (2) and their side (3). Which stages are
+//! present tells what kind of conflict it is, e.g. a path with an ancestor and
+//! their version but none of ours was deleted by us and modified by them.
+//!
+//! A conflict is resolved by taking one side whole, or by writing the merged
+//! content, and staging the result, which replaces the stages with one entry.
+
+use crate::git::capabilities::{self, RestoreSource};
+use crate::git::environment::git_command;
+use crate::git::repository::FileVersion;
+use git2::{IndexConflict, IndexEntry, Repository};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    BothModified,
+    BothAdded,
+    DeletedByUs,
+    DeletedByThem,
+    AddedByUs,
+    AddedByThem,
+    BothDeleted,
+}
+
+impl ConflictKind {
+    fn from_stages(ancestor: bool, ours: bool, theirs: bool) -> Self {
+        match (ancestor, ours, theirs) {
+            (true, true, true) => ConflictKind::BothModified,
+            (false, true, true) => ConflictKind::BothAdded,
+            (true, false, true) => ConflictKind::DeletedByUs,
+            (true, true, false) => ConflictKind::DeletedByThem,
+            (false, true, false) => ConflictKind::AddedByUs,
+            (false, false, true) => ConflictKind::AddedByThem,
+            _ => ConflictKind::BothDeleted,
+        }
+    }
+}
+
+/// Side of a conflict to take. During a rebase "ours" is the branch being
+/// rebased onto and "theirs" the commit being replayed, as in git.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictSide {
+    Ours,
+    Theirs,
+}
+
+/// One stage of a conflicted path
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConflictStage {
+    pub id: String, // blob id
+    pub mode: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConflictedFile {
+    pub path: String,
+    pub kind: ConflictKind,
+    pub ancestor: Option<ConflictStage>, // stage 1
+    pub ours: Option<ConflictStage>,     // stage 2
+    pub theirs: Option<ConflictStage>,   // stage 3
+}
+
+fn stage(entry: &Option<IndexEntry>) -> Option<ConflictStage> {
+    entry.as_ref().map(|entry| ConflictStage {
+        id: entry.id.to_string(),
+        mode: entry.mode,
+    })
+}
+
+/// Path of a conflict, taken from the first stage present
+fn conflicted_file_path(conflict: &IndexConflict) -> String {
+    [&conflict.our, &conflict.their, &conflict.ancestor]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .unwrap_or_default()
+}
+
+fn conflicted_file(conflict: &IndexConflict) -> ConflictedFile {
+    ConflictedFile {
+        path: conflicted_file_path(conflict),
+        kind: ConflictKind::from_stages(
+            conflict.ancestor.is_some(),
+            conflict.our.is_some(),
+            conflict.their.is_some(),
+        ),
+        ancestor: stage(&conflict.ancestor),
+        ours: stage(&conflict.our),
+        theirs: stage(&conflict.their),
+    }
+}
+
+/// The three versions of a conflicted file and the working tree file git
+/// merged them into, to resolve the conflict side by side
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictVersions {
+    pub file_path: String,
+    pub kind: ConflictKind,
+    pub ancestor: FileVersion,
+    pub ours: FileVersion,
+    pub theirs: FileVersion,
+    pub merged: FileVersion, // the working tree file, with conflict markers
+}
+
+fn index_conflicts(repo: &Repository) -> Result<Vec<IndexConflict>, String> {
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    // The index is cached by the repository; pick up what git changed on disk
+    index.read(false).map_err(|e| e.message().to_string())?;
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+    let conflicts = index
+        .conflicts()
+        .map_err(|e| e.message().to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.message().to_string());
+    conflicts
+}
+
+/// Conflicted paths of the index, sorted by path
+pub fn get_conflicts(repo: &Repository) -> Result<Vec<ConflictedFile>, String> {
+    let mut files: Vec<ConflictedFile> =
+        index_conflicts(repo)?.iter().map(conflicted_file).collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn stage_version(repo: &Repository, entry: &Option<IndexEntry>) -> Result<FileVersion, String> {
+    match entry {
+        Some(entry) => {
+            let blob = repo
+                .find_blob(entry.id)
+                .map_err(|e| e.message().to_string())?;
+            Ok(FileVersion::from_bytes(Some(blob.content())))
+        }
+        None => Ok(FileVersion::from_bytes(None)),
+    }
+}
+
+pub fn get_conflict_versions(
+    repo: &Repository,
+    file_path: &str,
+) -> Result<ConflictVersions, String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("Repository has no working tree")?
+        .to_path_buf();
+    let conflict = find_conflict(repo, file_path)?;
+    let merged = std::fs::read(workdir.join(file_path)).ok();
+
+    Ok(ConflictVersions {
+        file_path: file_path.to_string(),
+        kind: conflicted_file(&conflict).kind,
+        ancestor: stage_version(repo, &conflict.ancestor)?,
+        ours: stage_version(repo, &conflict.our)?,
+        theirs: stage_version(repo, &conflict.their)?,
+        merged: FileVersion::from_bytes(merged.as_deref()),
+    })
+}
+
+fn find_conflict(repo: &Repository, file_path: &str) -> Result<IndexConflict, String> {
+    index_conflicts(repo)?
+        .into_iter()
+        .find(|conflict| conflicted_file_path(conflict) == file_path)
+        .ok_or_else(|| format!("'{}' is not conflicted", file_path))
+}
+
+fn run_git<S: AsRef<str>>(workdir: &Path, args: &[S], file_path: &str) -> Result<(), String> {
+    let command = args[0].as_ref();
+    let output = git_command()
+        .arg("-C")
+        .arg(workdir)
+        .args(args.iter().map(|arg| arg.as_ref()))
+        .arg("--")
+        .arg(file_path)
+        .output()
+        .map_err(|e| format!("Failed to execute git {}: {}", command, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("git {} failed: {}", command, stderr.trim()))
+    }
+}
+
+/// Resolve the conflict on `file_path` with the version of `side` and stage
+/// it. When that side deleted the file, the file is removed.
+pub fn resolve_conflict_take(
+    repo: &Repository,
+    file_path: &str,
+    side: ConflictSide,
+) -> Result<(), String> {
+    let workdir = repo.workdir().ok_or("Repository has no working tree")?;
+    let conflict = find_conflict(repo, file_path)?;
+    let (entry, source) = match side {
+        ConflictSide::Ours => (&conflict.our, RestoreSource::Ours),
+        ConflictSide::Theirs => (&conflict.their, RestoreSource::Theirs),
+    };
+    if entry.is_none() {
+        return run_git(workdir, &["rm", "--quiet"], file_path);
+    }
+    run_git(workdir, &capabilities::restore_args(source), file_path)?;
+    run_git(workdir, &["add"], file_path)
+}
+
+/// Whether `content` still has a conflict block: a line opening one and a
+/// line closing one
+fn has_conflict_markers(content: &str) -> bool {
+    content.lines().any(|line| line.starts_with("<<<<<<< "))
+        && content.lines().any(|line| line.starts_with(">>>>>>> "))
+}
+
+/// Resolve the conflict on `file_path` with `content`, edited from the
+/// versions of both sides, and stage it
+pub fn mark_conflict_resolved(
+    repo: &Repository,
+    file_path: &str,
+    content: &str,
+) -> Result<(), String> {
+    let workdir = repo.workdir().ok_or("Repository has no working tree")?;
+    find_conflict(repo, file_path)?;
+    if has_conflict_markers(content) {
+        return Err(format!("'{}' still has conflict markers", file_path));
+    }
+    let path = workdir.join(file_path);
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    run_git(workdir, &["add"], file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::environment::git_command;
+    use crate::git::test_support::{commit_file, git, init_repo};
+
+    #[test]
+    fn test_get_conflicts_kinds() {
+        let dir = init_repo();
+        let path = dir.path();
+        std::fs::write(path.join("both.txt"), "base\n").unwrap();
+        git(path, &["add", "both.txt"]);
+        commit_file(path, "gone.txt", "base\n", "base");
+
+        git(path, &["checkout", "-qb", "topic"]);
+        std::fs::write(path.join("both.txt"), "theirs\n").unwrap();
+        git(path, &["add", "both.txt"]);
+        commit_file(path, "gone.txt", "changed\n", "topic");
+
+        git(path, &["checkout", "-q", "main"]);
+        git(path, &["rm", "-q", "gone.txt"]);
+        commit_file(path, "both.txt", "ours\n", "main");
+        let merge = git_command()
+            .args(["merge", "-q", "topic"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        assert!(!merge.status.success());
+
+        let repo = Repository::open(path).unwrap();
+        let conflicts = get_conflicts(&repo).unwrap();
+        let kinds: Vec<_> = conflicts
+            .iter()
+            .map(|file| (file.path.as_str(), file.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("both.txt", ConflictKind::BothModified),
+                ("gone.txt", ConflictKind::DeletedByUs),
+            ]
+        );
+        assert!(conflicts[1].ours.is_none() && conflicts[1].theirs.is_some());
+
+        let versions = get_conflict_versions(&repo, "both.txt").unwrap();
+        assert_eq!(versions.ancestor.content.as_deref(), Some("base\n"));
+        assert_eq!(versions.ours.content.as_deref(), Some("ours\n"));
+        assert_eq!(versions.theirs.content.as_deref(), Some("theirs\n"));
+        assert!(versions.merged.content.unwrap().contains("<<<<<<<"));
+        let versions = get_conflict_versions(&repo, "gone.txt").unwrap();
+        assert!(!versions.ours.exists && versions.theirs.exists);
+        assert!(get_conflict_versions(&repo, "missing.txt").is_err());
+
+        let merged = std::fs::read_to_string(path.join("both.txt")).unwrap();
+        assert!(mark_conflict_resolved(&repo, "both.txt", &merged).is_err());
+        mark_conflict_resolved(&repo, "both.txt", "ours and theirs\n").unwrap();
+        resolve_conflict_take(&repo, "gone.txt", ConflictSide::Ours).unwrap();
+        assert!(get_conflicts(&repo).unwrap().is_empty());
+        assert!(!path.join("gone.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(path.join("both.txt")).unwrap(),
+            "ours and theirs\n"
+        );
+    }
+}
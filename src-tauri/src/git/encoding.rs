@@ -0,0 +1,145 @@
+//! Character encoding of text files shown in diffs.
+//!
+//! Diff lines used to be read as UTF-8, which turns Latin-1 or Shift-JIS text
+//! into replacement characters and UTF-16 text (full of NUL bytes) into a
+//! "binary" file. The encoding is taken from the `working-tree-encoding`
+//! attribute when the file has one, otherwise guessed from the content, and
+//! the text is converted to UTF-8 before it is shown.
+
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use git2::{AttrCheckFlags, Repository};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Bytes looked at to recognize UTF-16 without a byte order mark
+const UTF16_SAMPLE_LEN: usize = 4096;
+
+/// Encoding of the work tree copy of `file_path` declared with the
+/// `working-tree-encoding` attribute. Git stores such files as UTF-8, so it
+/// only applies to the work tree side of a diff.
+pub fn working_tree_encoding(repo: &Repository, file_path: &str) -> Option<&'static Encoding> {
+    let value = repo
+        .get_attr(
+            Path::new(file_path),
+            "working-tree-encoding",
+            AttrCheckFlags::FILE_THEN_INDEX,
+        )
+        .ok()
+        .flatten()?;
+    Encoding::for_label(value.trim().as_bytes()).filter(|encoding| *encoding != UTF_8)
+}
+
+/// UTF-16 recognized by its byte order mark, or by the NUL bytes that fill
+/// every other byte of mostly ASCII text. Checked before the binary check,
+/// which UTF-16 text would fail.
+pub fn detect_utf16(content: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(content) {
+        return (encoding != UTF_8).then_some(encoding);
+    }
+    let sample = &content[..content.len().min(UTF16_SAMPLE_LEN) & !1];
+    if sample.is_empty() {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let nul_count = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let (even_nuls, odd_nuls) = (nul_count(0), nul_count(1));
+    // Nearly all high bytes zero and no zero low byte
+    if odd_nuls * 10 >= pairs * 9 && even_nuls == 0 {
+        Some(UTF_16LE)
+    } else if even_nuls * 10 >= pairs * 9 && odd_nuls == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Guess the encoding of text that is not valid UTF-8. Shift-JIS is tried
+/// first because its two byte sequences rarely all decode by chance, while any
+/// byte sequence is valid windows-1252 (the superset of Latin-1).
+fn detect_legacy(content: &[u8]) -> &'static Encoding {
+    let (_, had_errors) = SHIFT_JIS.decode_without_bom_handling(content);
+    if had_errors {
+        WINDOWS_1252
+    } else {
+        SHIFT_JIS
+    }
+}
+
+/// `content` as UTF-8 text, decoded with `known` when given, as is when it is
+/// UTF-8 and with a guessed encoding otherwise. Also returns the encoding the
+/// text was converted from, None for UTF-8.
+pub fn decode<'a>(
+    content: &'a [u8],
+    known: Option<&'static Encoding>,
+) -> (Cow<'a, str>, Option<&'static Encoding>) {
+    let encoding = match known {
+        Some(encoding) => encoding,
+        None => match std::str::from_utf8(content) {
+            Ok(text) => return (Cow::Borrowed(text), None),
+            Err(_) => detect_legacy(content),
+        },
+    };
+    let (text, _) = encoding.decode_with_bom_removal(content);
+    (text, (encoding != UTF_8).then_some(encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_detect_utf16() {
+        let mut with_bom = vec![0xFF, 0xFE];
+        with_bom.extend(utf16le("héllo\n"));
+        assert_eq!(detect_utf16(&with_bom), Some(UTF_16LE));
+        assert_eq!(detect_utf16(&utf16le("plain ascii text\n")), Some(UTF_16LE));
+        let big_endian: Vec<u8> = "text\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(detect_utf16(&big_endian), Some(UTF_16BE));
+        assert_eq!(detect_utf16(b"plain ascii text\n"), None);
+        assert_eq!(detect_utf16(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode("déjà vu".as_bytes(), None),
+            (Cow::Borrowed("déjà vu"), None)
+        );
+        assert_eq!(
+            decode(b"caf\xe9 cr\xe8me\n", None),
+            (Cow::Owned("café crème\n".to_string()), Some(WINDOWS_1252))
+        );
+        // "日本語" in Shift-JIS
+        assert_eq!(
+            decode(b"\x93\xfa\x96\x7b\x8c\xea\n", None),
+            (Cow::Owned("日本語\n".to_string()), Some(SHIFT_JIS))
+        );
+        let mut with_bom = vec![0xFF, 0xFE];
+        with_bom.extend(utf16le("héllo\n"));
+        assert_eq!(decode(&with_bom, Some(UTF_16LE)).0, "héllo\n");
+    }
+
+    #[test]
+    fn test_working_tree_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        std::fs::write(
+            dir.path().join(".gitattributes"),
+            "*.txt working-tree-encoding=UTF-16LE\n",
+        )
+        .unwrap();
+        assert_eq!(working_tree_encoding(&repo, "a.txt"), Some(UTF_16LE));
+        assert_eq!(working_tree_encoding(&repo, "a.rs"), None);
+    }
+}
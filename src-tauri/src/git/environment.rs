@@ -0,0 +1,202 @@
+//! Extra environment for the git processes Forky spawns.
+//!
+//! An app launched from the Dock, Start menu or a desktop launcher does not
+//! inherit the shell profile, so hooks that call node (husky, lint-staged via
+//! nvm) or other tools installed in the user's shell PATH fail from the GUI
+//! while working in a terminal. The user can list directories to prepend to
+//! PATH and variables to set; every git command is built with `git_command`,
+//! which applies them. The settings are persisted as JSON in the app data dir.
+
+use crate::json_store;
+use crate::locks::RwLockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::RwLock;
+
+/// File (in the app data dir) where the git environment settings are persisted
+pub const ENVIRONMENT_FILE: &str = "git_environment.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GitEnvironment {
+    #[serde(default)]
+    pub path_entries: Vec<String>, // prepended to PATH, in order
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Settings applied to spawned git commands, loaded at startup
+static CURRENT: RwLock<GitEnvironment> = RwLock::new(GitEnvironment {
+    path_entries: Vec::new(),
+    variables: BTreeMap::new(),
+});
+
+impl GitEnvironment {
+    /// Reject settings that would break every git command
+    pub fn validate(&self) -> Result<(), String> {
+        for name in self.variables.keys() {
+            if name.is_empty() || name.contains('=') || name.contains('\0') {
+                return Err(format!("Invalid environment variable name '{}'", name));
+            }
+            if name.eq_ignore_ascii_case("PATH") {
+                return Err("Add PATH entries as path entries, not as a variable".to_string());
+            }
+        }
+        if let Some(entry) = self.path_entries.iter().find(|e| e.trim().is_empty()) {
+            return Err(format!("Invalid PATH entry '{}'", entry));
+        }
+        Ok(())
+    }
+
+    /// PATH with the configured entries in front of `inherited`, or None when
+    /// there is nothing to add
+    fn path_value(&self, inherited: Option<OsString>) -> Option<OsString> {
+        if self.path_entries.is_empty() {
+            return None;
+        }
+        let inherited = inherited.unwrap_or_default();
+        let paths = self
+            .path_entries
+            .iter()
+            .map(|entry| expand_home(entry))
+            .map(PathBuf::from)
+            .chain(std::env::split_paths(&inherited));
+        std::env::join_paths(paths).ok()
+    }
+
+    /// Set the configured PATH and variables on `cmd`
+    pub fn apply(&self, cmd: &mut Command) {
+        if let Some(path) = self.path_value(std::env::var_os("PATH")) {
+            cmd.env("PATH", path);
+        }
+        cmd.envs(&self.variables);
+    }
+}
+
+/// Where `program` is found on the PATH git commands (and their hooks) get,
+/// i.e. the inherited PATH with the configured entries in front
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let inherited = std::env::var_os("PATH");
+    let path = current()
+        .path_value(inherited.clone())
+        .or(inherited)
+        .unwrap_or_default();
+    let names: Vec<String> = if cfg!(windows) {
+        ["", ".exe", ".cmd", ".bat"]
+            .iter()
+            .map(|ext| format!("{}{}", program, ext))
+            .collect()
+    } else {
+        vec![program.to_string()]
+    };
+    std::env::split_paths(&path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `~/bin` -> `<home>/bin`, since the entries are not run through a shell
+fn expand_home(entry: &str) -> String {
+    let rest = match entry.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return entry.to_string(),
+    };
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => format!("{}{}", home.to_string_lossy(), rest),
+        None => entry.to_string(),
+    }
+}
+
+/// The settings currently applied to git commands
+pub fn current() -> GitEnvironment {
+    CURRENT.read_recover("git environment").clone()
+}
+
+/// Replace the settings applied to git commands started from now on
+pub fn set_current(environment: GitEnvironment) {
+    *CURRENT.write_recover("git environment") = environment;
+}
+
+/// A `git` command with the configured PATH entries and variables applied
+pub fn git_command() -> Command {
+    let mut cmd = Command::new("git");
+    CURRENT.read_recover("git environment").apply(&mut cmd);
+    cmd
+}
+
+/// `command` run by the platform shell (`sh -c`, `cmd /C`) with the configured
+/// PATH entries and variables applied, as for the commands git itself runs
+pub fn shell_command(command: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    CURRENT.read_recover("git environment").apply(&mut cmd);
+    cmd
+}
+
+/// Read the settings from `path`; a missing file means no extra environment
+pub fn load(path: &Path) -> Result<GitEnvironment, String> {
+    Ok(json_store::read(path)?.unwrap_or_default())
+}
+
+/// Write the settings to `path`
+pub fn save(path: &Path, environment: &GitEnvironment) -> Result<(), String> {
+    json_store::write(path, environment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_entries_prepended_to_inherited_path() {
+        let environment = GitEnvironment {
+            path_entries: vec!["/opt/node/bin".to_string(), "/usr/local/bin".to_string()],
+            variables: BTreeMap::new(),
+        };
+        let inherited = std::env::join_paths(["/usr/bin", "/bin"]).unwrap();
+        let path = environment.path_value(Some(inherited)).unwrap();
+        let entries: Vec<_> = std::env::split_paths(&path).collect();
+        assert_eq!(
+            entries,
+            ["/opt/node/bin", "/usr/local/bin", "/usr/bin", "/bin"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(GitEnvironment::default().path_value(None), None);
+    }
+
+    #[test]
+    fn test_validate_rejects_unusable_settings() {
+        let mut environment = GitEnvironment::default();
+        environment
+            .variables
+            .insert("NVM_DIR".to_string(), "/home/me/.nvm".to_string());
+        assert!(environment.validate().is_ok());
+
+        environment
+            .variables
+            .insert("Path".to_string(), "/bin".to_string());
+        assert!(environment.validate().is_err());
+
+        let bad_name = GitEnvironment {
+            path_entries: Vec::new(),
+            variables: BTreeMap::from([("A=B".to_string(), String::new())]),
+        };
+        assert!(bad_name.validate().is_err());
+
+        let blank_entry = GitEnvironment {
+            path_entries: vec![" ".to_string()],
+            variables: BTreeMap::new(),
+        };
+        assert!(blank_entry.validate().is_err());
+    }
+}
@@ -0,0 +1,464 @@
+//! Finishing git flow branches.
+//!
+//! Finishing a release or hotfix is several steps: merge into the production
+//! branch, merge into the development branch, tag, delete the branch. When a
+//! step stops halfway (typically a conflict merging into develop after master
+//! already got the merge) the progress is saved in the git dir, so the finish
+//! can be continued once the conflicts are resolved, or aborted, which puts
+//! the merged branches back where they were.
+
+use crate::git::environment::git_command;
+use crate::git::repository::{
+    create_error_result, create_success_result, get_gitflow_config, open_repository,
+    GitOperationResult,
+};
+use crate::json_store;
+use git2::{BranchType, Repository};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// File (in the git dir) holding the state of an interrupted finish
+pub const FINISH_STATE_FILE: &str = "forky-flow-finish.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlowFinishState {
+    pub flow_type: String,                  // "feature", "release" or "hotfix"
+    pub name: String,                       // without prefix; also the tag of a release or hotfix
+    pub branch: String,                     // e.g. "hotfix/1.2.1"
+    pub targets: Vec<String>,               // branches it is merged into, in order
+    pub original_tips: Vec<Option<String>>, // commit of each target before the finish
+    pub merged: usize,                      // targets merged so far
+    pub create_tag: bool,
+    pub delete_branch: bool,
+    pub master_branch: String,
+    pub develop_branch: String,
+    pub start_branch: Option<String>, // checked out when the finish started
+    pub messages: Vec<String>,        // steps done so far
+}
+
+fn state_path(repo: &Repository) -> PathBuf {
+    repo.path().join(FINISH_STATE_FILE)
+}
+
+/// The finish waiting to be continued or aborted, if any
+pub fn get_flow_finish_state(repo: &Repository) -> Option<FlowFinishState> {
+    json_store::read(&state_path(repo)).ok().flatten()
+}
+
+fn save_state(repo: &Repository, state: &FlowFinishState) -> Result<(), String> {
+    json_store::write(&state_path(repo), state)
+}
+
+fn clear_state(repo: &Repository) {
+    let _ = std::fs::remove_file(state_path(repo));
+}
+
+fn branch_tip(repo: &Repository, branch: &str) -> Option<git2::Oid> {
+    repo.find_branch(branch, BranchType::Local)
+        .ok()?
+        .get()
+        .target()
+}
+
+fn run_git(repo_path: &str, args: &[&str]) -> Result<std::process::Output, String> {
+    git_command()
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .map_err(|e| format!("Failed to execute git {}: {}", args[0], e))
+}
+
+/// Finish a git flow branch (feature, release, or hotfix)
+pub fn git_flow_finish(
+    repo_path: &str,
+    flow_type: &str,
+    name: &str,
+    delete_branch: bool,
+) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    if let Some(state) = get_flow_finish_state(&repo) {
+        return Ok(create_error_result(
+            &format!(
+                "Finishing '{}' was interrupted; continue or abort it first",
+                state.branch
+            ),
+            "",
+        ));
+    }
+    let config = get_gitflow_config(&repo)?;
+
+    let (prefix, targets, create_tag) = match flow_type {
+        "feature" => (
+            config.feature_prefix.clone(),
+            vec![config.develop_branch.clone()],
+            false,
+        ),
+        "release" => (
+            config.release_prefix.clone(),
+            vec![config.master_branch.clone(), config.develop_branch.clone()],
+            true,
+        ),
+        "hotfix" => (
+            config.hotfix_prefix.clone(),
+            vec![config.master_branch.clone(), config.develop_branch.clone()],
+            true,
+        ),
+        _ => {
+            return Ok(create_error_result(
+                &format!("Unknown flow type: {}", flow_type),
+                "",
+            ))
+        }
+    };
+
+    let state = FlowFinishState {
+        flow_type: flow_type.to_string(),
+        name: name.to_string(),
+        branch: format!("{}{}", prefix, name),
+        original_tips: targets
+            .iter()
+            .map(|target| branch_tip(&repo, target).map(|oid| oid.to_string()))
+            .collect(),
+        targets,
+        merged: 0,
+        create_tag,
+        delete_branch,
+        master_branch: config.master_branch,
+        develop_branch: config.develop_branch,
+        start_branch: repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| head.shorthand().map(String::from)),
+        messages: Vec::new(),
+    };
+    run_finish(repo_path, &repo, state)
+}
+
+/// Run the steps of `state` that are left. A merge that stops saves the state
+/// for git_flow_finish_continue and git_flow_finish_abort.
+fn run_finish(
+    repo_path: &str,
+    repo: &Repository,
+    mut state: FlowFinishState,
+) -> Result<GitOperationResult, String> {
+    // Merge into each target branch
+    while state.merged < state.targets.len() {
+        let target = state.targets[state.merged].clone();
+        // Nothing to undo yet when the first checkout fails
+        let interrupted = |state: &FlowFinishState| {
+            if state.merged > 0 {
+                save_state(repo, state)
+            } else {
+                Ok(())
+            }
+        };
+
+        let output = run_git(repo_path, &["checkout", &target])?;
+        if !output.status.success() {
+            interrupted(&state)?;
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Ok(create_error_result(
+                &format!("Failed to checkout '{}': {}", target, stderr),
+                "",
+            ));
+        }
+
+        // Merge with --no-ff
+        let merge_message = format!("Merge {} '{}' into {}", state.flow_type, state.name, target);
+        let output = run_git(
+            repo_path,
+            &["merge", "--no-ff", "-m", &merge_message, &state.branch],
+        )?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+            // Check for merge conflicts
+            if repo.state() == git2::RepositoryState::Merge
+                || stdout.contains("CONFLICT")
+                || stderr.contains("Automatic merge failed")
+            {
+                save_state(repo, &state)?;
+                return Ok(GitOperationResult {
+                    success: false,
+                    message: format!(
+                        "Merge conflict while merging into '{}'. Resolve the conflicts, then continue the finish (or abort it).",
+                        target
+                    ),
+                    requires_ssh_verification: None,
+                    requires_credential: None,
+                    error_type: Some("merge_conflict".to_string()),
+                    conflicting_files: None,
+                });
+            }
+
+            interrupted(&state)?;
+            return Ok(create_error_result(
+                &format!("Failed to merge into '{}': {}", target, stderr),
+                "",
+            ));
+        }
+
+        state.messages.push(format!("Merged into '{}'", target));
+        state.merged += 1;
+    }
+
+    // Create tag for release/hotfix (on master branch)
+    if state.create_tag {
+        // Make sure we're on master for tagging
+        let _ = run_git(repo_path, &["checkout", &state.master_branch]);
+
+        let tag_message = format!(
+            "{} {}",
+            if state.flow_type == "release" {
+                "Release"
+            } else {
+                "Hotfix"
+            },
+            state.name
+        );
+        let output = run_git(repo_path, &["tag", "-a", &state.name, "-m", &tag_message])?;
+
+        if output.status.success() {
+            state.messages.push(format!("Created tag '{}'", state.name));
+        } else {
+            // Tag might already exist, not a fatal error
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            if !stderr.contains("already exists") {
+                state
+                    .messages
+                    .push(format!("Warning: Could not create tag: {}", stderr));
+            }
+        }
+    }
+
+    // Delete branch if requested
+    if state.delete_branch {
+        let output = run_git(repo_path, &["branch", "-d", &state.branch])?;
+
+        if output.status.success() {
+            state
+                .messages
+                .push(format!("Deleted branch '{}'", state.branch));
+        } else {
+            // Try force delete if normal delete fails
+            if let Ok(output) = run_git(repo_path, &["branch", "-D", &state.branch]) {
+                if output.status.success() {
+                    state
+                        .messages
+                        .push(format!("Deleted branch '{}' (force)", state.branch));
+                }
+            }
+        }
+    }
+
+    // Checkout back to develop
+    let _ = run_git(repo_path, &["checkout", &state.develop_branch]);
+
+    clear_state(repo);
+    Ok(create_success_result(state.messages.join(". ")))
+}
+
+/// Resume an interrupted finish: commit the merge whose conflicts were
+/// resolved (or accept a merge the user committed), then run the steps left
+pub fn git_flow_finish_continue(repo_path: &str) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    let Some(mut state) = get_flow_finish_state(&repo) else {
+        return Ok(create_error_result("No git flow finish to continue", ""));
+    };
+    // The state file is ours, but it may have been edited or truncated
+    let Some(target) = state.targets.get(state.merged).cloned() else {
+        return Err("The saved git flow finish state is invalid; abort it instead".to_string());
+    };
+
+    if repo.state() == git2::RepositoryState::Merge {
+        let mut index = repo.index().map_err(|e| e.message().to_string())?;
+        index.read(false).map_err(|e| e.message().to_string())?;
+        if index.has_conflicts() {
+            return Ok(create_error_result(
+                &format!(
+                    "There are still conflicts merging into '{}'; resolve and stage them first",
+                    target
+                ),
+                "",
+            ));
+        }
+        // Commits with the message prepared by the merge
+        let output = run_git(repo_path, &["commit", "--no-edit"])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            return Ok(create_error_result(&stderr, &stdout));
+        }
+        state.messages.push(format!("Merged into '{}'", target));
+        state.merged += 1;
+    } else if let (Some(target_tip), Some(branch_tip)) =
+        (branch_tip(&repo, &target), branch_tip(&repo, &state.branch))
+    {
+        if target_tip == branch_tip
+            || repo
+                .graph_descendant_of(target_tip, branch_tip)
+                .unwrap_or(false)
+        {
+            state.messages.push(format!("Merged into '{}'", target));
+            state.merged += 1;
+        }
+        // Otherwise the merge is retried
+    }
+
+    run_finish(repo_path, &repo, state)
+}
+
+/// Give up an interrupted finish: abort the merge in progress, put the target
+/// branches already merged back on their previous commits and check out the
+/// branch the finish started from
+pub fn git_flow_finish_abort(repo_path: &str) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    let Some(state) = get_flow_finish_state(&repo) else {
+        return Ok(create_error_result("No git flow finish to abort", ""));
+    };
+
+    if repo.state() == git2::RepositoryState::Merge {
+        let output = run_git(repo_path, &["merge", "--abort"])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            return Ok(create_error_result(&stderr, &stdout));
+        }
+    }
+
+    let checked_out = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(String::from));
+    let mut restored = Vec::new();
+    for (target, original) in state.targets.iter().zip(&state.original_tips) {
+        let Some(original) = original else {
+            continue;
+        };
+        if branch_tip(&repo, target)
+            .map(|oid| oid.to_string())
+            .as_ref()
+            == Some(original)
+        {
+            continue;
+        }
+        if checked_out.as_deref() == Some(target.as_str()) {
+            // Keeps local changes, and refuses rather than lose them
+            let output = run_git(repo_path, &["reset", "--keep", original])?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                return Ok(create_error_result(
+                    &format!("Failed to reset '{}': {}", target, stderr),
+                    "",
+                ));
+            }
+        } else {
+            let oid = git2::Oid::from_str(original).map_err(|e| e.message().to_string())?;
+            repo.reference(
+                &format!("refs/heads/{}", target),
+                oid,
+                true,
+                "flow finish: abort",
+            )
+            .map_err(|e| format!("Failed to reset '{}': {}", target, e.message()))?;
+        }
+        restored.push(target.clone());
+    }
+
+    if let Some(start_branch) = &state.start_branch {
+        let _ = run_git(repo_path, &["checkout", start_branch]);
+    }
+    clear_state(&repo);
+
+    let mut message = format!("Aborted finishing '{}'", state.branch);
+    if !restored.is_empty() {
+        message.push_str(&format!(
+            "; {} put back on the previous commit",
+            restored.join(", ")
+        ));
+    }
+    Ok(create_success_result(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{git, init_repo};
+
+    /// A repository with git flow set up and a hotfix that conflicts with develop
+    fn conflicting_hotfix() -> tempfile::TempDir {
+        let dir = init_repo();
+        let path = dir.path();
+        for (key, value) in [
+            ("gitflow.branch.master", "main"),
+            ("gitflow.branch.develop", "develop"),
+            ("gitflow.prefix.feature", "feature/"),
+            ("gitflow.prefix.release", "release/"),
+            ("gitflow.prefix.hotfix", "hotfix/"),
+        ] {
+            git(path, &["config", key, value]);
+        }
+        std::fs::write(path.join("version.txt"), "1.0\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-qm", "init"]);
+        git(path, &["branch", "develop"]);
+        git(path, &["checkout", "-q", "-b", "hotfix/1.0.1"]);
+        std::fs::write(path.join("version.txt"), "1.0.1\n").unwrap();
+        git(path, &["commit", "-qam", "hotfix"]);
+        git(path, &["checkout", "-q", "develop"]);
+        std::fs::write(path.join("version.txt"), "1.1-dev\n").unwrap();
+        git(path, &["commit", "-qam", "develop"]);
+        git(path, &["checkout", "-q", "hotfix/1.0.1"]);
+        dir
+    }
+
+    #[test]
+    fn test_finish_continues_after_conflict() {
+        let dir = conflicting_hotfix();
+        let path = dir.path().to_str().unwrap();
+
+        let result = git_flow_finish(path, "hotfix", "1.0.1", true).unwrap();
+        assert_eq!(result.error_type.as_deref(), Some("merge_conflict"));
+        let repo = Repository::open(path).unwrap();
+        let state = get_flow_finish_state(&repo).unwrap();
+        assert_eq!((state.merged, state.targets.len()), (1, 2));
+        // A new finish is refused meanwhile
+        assert!(
+            !git_flow_finish(path, "hotfix", "1.0.1", true)
+                .unwrap()
+                .success
+        );
+        // Still conflicted
+        assert!(!git_flow_finish_continue(path).unwrap().success);
+
+        std::fs::write(dir.path().join("version.txt"), "1.1-dev\n").unwrap();
+        git(dir.path(), &["add", "version.txt"]);
+        let result = git_flow_finish_continue(path).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert!(get_flow_finish_state(&repo).is_none());
+        assert!(repo.find_reference("refs/tags/1.0.1").is_ok());
+        assert!(repo.find_branch("hotfix/1.0.1", BranchType::Local).is_err());
+    }
+
+    #[test]
+    fn test_abort_restores_merged_targets() {
+        let dir = conflicting_hotfix();
+        let path = dir.path().to_str().unwrap();
+        let repo = Repository::open(path).unwrap();
+        let main_before = branch_tip(&repo, "main").unwrap();
+
+        git_flow_finish(path, "hotfix", "1.0.1", true).unwrap();
+        assert_ne!(branch_tip(&repo, "main").unwrap(), main_before);
+
+        let result = git_flow_finish_abort(path).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(branch_tip(&repo, "main").unwrap(), main_before);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert_eq!(repo.head().unwrap().shorthand(), Some("hotfix/1.0.1"));
+        assert!(get_flow_finish_state(&repo).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Fuzzy matching for ref names typed into the branch switcher.
+//!
+//! The query has to appear in the candidate as a subsequence (case-insensitive).
+//! Matches are ranked higher when characters are consecutive or start a path
+//! segment / word (`feature/login-form` matches `flf` well), and shorter
+//! candidates win ties.
+
+/// A successful match: higher scores rank first
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub indices: Vec<usize>, // char positions of the matched characters
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '-' | '_' | '.' | ' ')
+}
+
+/// Match `query` against `candidate`. An empty query matches everything with score 0.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            indices: Vec::new(),
+        });
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = candidate.to_lowercase().chars().collect();
+    if lower.len() != chars.len() {
+        // Lowercasing changed the length (rare unicode); fall back to a substring test
+        let needle: String = query.iter().collect();
+        let pos = candidate.to_lowercase().find(&needle)?;
+        let start = candidate.to_lowercase()[..pos].chars().count();
+        return Some(FuzzyMatch {
+            score: 10,
+            indices: (start..start + query.len()).collect(),
+        });
+    }
+
+    let mut indices = Vec::with_capacity(query.len());
+    let mut score = 0i64;
+    let mut qi = 0;
+    let mut prev: Option<usize> = None;
+
+    for (i, c) in lower.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if *c != query[qi] {
+            continue;
+        }
+
+        score += 1;
+        if prev == Some(i.wrapping_sub(1)) {
+            score += 5; // consecutive run
+        }
+        if i == 0 || is_separator(chars[i - 1]) {
+            score += 8; // start of the name or of a segment/word
+        } else if chars[i].is_uppercase() && chars[i - 1].is_lowercase() {
+            score += 4; // camelCase boundary
+        }
+
+        indices.push(i);
+        prev = Some(i);
+        qi += 1;
+    }
+
+    if qi < query.len() {
+        return None;
+    }
+
+    // Exact and prefix matches rank first; long names with scattered matches last
+    let query_str: String = query.iter().collect();
+    let lower_str: String = lower.iter().collect();
+    if lower_str == query_str {
+        score += 100;
+    } else if lower_str.starts_with(&query_str) {
+        score += 50;
+    } else if lower_str
+        .rsplit('/')
+        .next()
+        .is_some_and(|leaf| leaf.starts_with(&query_str))
+    {
+        score += 30;
+    }
+    score -= (chars.len() as i64 - query.len() as i64) / 4;
+
+    Some(FuzzyMatch { score, indices })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(fuzzy_match("flf", "feature/login-form").is_some());
+        assert!(fuzzy_match("xyz", "feature/login-form").is_none());
+        assert_eq!(
+            fuzzy_match("LOG", "feature/login").unwrap().indices,
+            vec![8, 9, 10]
+        );
+    }
+
+    #[test]
+    fn test_ranking_prefers_exact_prefix_and_boundaries() {
+        let score = |candidate| fuzzy_match("main", candidate).unwrap().score;
+        assert!(score("main") > score("maintenance"));
+        assert!(score("maintenance") > score("origin/main"));
+        assert!(score("origin/main") > score("feature/my-animation"));
+    }
+}
@@ -0,0 +1,424 @@
+//! Export of the commit graph for documentation and architecture reviews.
+//!
+//! The commits of a range are laid out in lanes the way the commit list draws
+//! them and written as Graphviz DOT, as JSON (commits, parents, refs and
+//! lanes) for other tools, or as a standalone SVG drawn here so no Graphviz
+//! install is needed.
+
+use crate::git::repository;
+use chrono::{TimeZone, Utc};
+use git2::{Oid, Repository, Sort};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Commits exported when no limit is given
+pub const DEFAULT_MAX_COMMITS: usize = 1000;
+
+const ROW_HEIGHT: usize = 24;
+const LANE_WIDTH: usize = 16;
+const MARGIN: usize = 12;
+const LANE_COLORS: [&str; 8] = [
+    "#4c8bf5", "#e8710a", "#1e8e3e", "#d93025", "#9334e6", "#12a4af", "#e52592", "#80868b",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryFormat {
+    Dot,
+    Json,
+    Svg,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryNode {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub author: String,
+    pub date: String,
+    pub parents: Vec<String>, // all parents, including those outside the export
+    pub refs: Vec<String>,    // branches and tags pointing here
+    pub lane: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryGraph {
+    pub range: String,
+    pub nodes: Vec<HistoryNode>, // newest first
+    pub truncated: bool,         // the range has more commits than were exported
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryExport {
+    pub format: HistoryFormat,
+    pub commit_count: usize,
+    pub truncated: bool,
+    pub path: Option<String>,    // file the export was written to
+    pub content: Option<String>, // the export itself when it was not written to a file
+}
+
+/// The commits of `range` (a revision, or `A..B` for the commits of B not in
+/// A; HEAD when None), newest first, at most `max_commits`
+pub fn history_graph(
+    repo: &Repository,
+    range: Option<&str>,
+    max_commits: usize,
+) -> Result<HistoryGraph, String> {
+    let range = range
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .unwrap_or("HEAD");
+    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+    revwalk
+        .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(|e| e.message().to_string())?;
+    if range.contains("..") {
+        revwalk.push_range(range)
+    } else {
+        repo.revparse_single(range)
+            .and_then(|object| object.peel_to_commit())
+            .and_then(|commit| revwalk.push(commit.id()))
+    }
+    .map_err(|e| format!("Cannot resolve '{}': {}", range, e.message()))?;
+
+    let mut oids = Vec::new();
+    let mut truncated = false;
+    for oid in revwalk {
+        let oid = oid.map_err(|e| e.message().to_string())?;
+        if oids.len() == max_commits {
+            truncated = true;
+            break;
+        }
+        oids.push(oid);
+    }
+
+    let decorations = repository::get_ref_decorations(repo)?;
+    let mut lanes = Lanes::default();
+    let mut nodes = Vec::with_capacity(oids.len());
+    for oid in oids {
+        let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+        let parents: Vec<Oid> = commit.parent_ids().collect();
+        let date = Utc
+            .timestamp_opt(commit.time().seconds(), 0)
+            .single()
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        nodes.push(HistoryNode {
+            id: oid.to_string(),
+            short_id: oid.to_string()[..7].to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            author: commit.author().name().unwrap_or("Unknown").to_string(),
+            date,
+            parents: parents.iter().map(Oid::to_string).collect(),
+            refs: decorations
+                .get(&oid.to_string())
+                .map(|refs| refs.iter().map(|r| r.name.clone()).collect())
+                .unwrap_or_default(),
+            lane: lanes.place(oid, &parents),
+        });
+    }
+
+    Ok(HistoryGraph {
+        range: range.to_string(),
+        nodes,
+        truncated,
+    })
+}
+
+/// Lane assignment, newest commit first: each lane waits for the next commit
+/// of a line of history, which continues in the lane of its first child
+#[derive(Default)]
+struct Lanes {
+    expected: Vec<Option<Oid>>,
+}
+
+impl Lanes {
+    fn place(&mut self, oid: Oid, parents: &[Oid]) -> usize {
+        let lane = match self.expected.iter().position(|e| *e == Some(oid)) {
+            Some(lane) => lane,
+            None => self.free_lane(),
+        };
+        // Other lanes that ended in this commit (branches merged into it) are free now
+        for expected in self.expected.iter_mut() {
+            if *expected == Some(oid) {
+                *expected = None;
+            }
+        }
+        self.expected[lane] = parents.first().copied();
+        for parent in parents.iter().skip(1) {
+            if !self.expected.contains(&Some(*parent)) {
+                let free = self.free_lane();
+                self.expected[free] = Some(*parent);
+            }
+        }
+        lane
+    }
+
+    fn free_lane(&mut self) -> usize {
+        match self.expected.iter().position(Option::is_none) {
+            Some(lane) => lane,
+            None => {
+                self.expected.push(None);
+                self.expected.len() - 1
+            }
+        }
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Graphviz digraph with the newest commit at the top and an edge from every
+/// commit to its parents in the export
+pub fn to_dot(graph: &HistoryGraph) -> String {
+    let mut dot = String::from("digraph history {\n");
+    dot.push_str("    rankdir=TB;\n");
+    dot.push_str("    node [shape=box, style=rounded, fontname=\"monospace\", fontsize=10];\n");
+    for node in &graph.nodes {
+        let mut label = format!("{} {}", node.short_id, escape_dot(&node.summary));
+        if !node.refs.is_empty() {
+            label.push_str(&format!("\\n[{}]", escape_dot(&node.refs.join(", "))));
+        }
+        dot.push_str(&format!(
+            "    \"{}\" [label=\"{}\", color=\"{}\"];\n",
+            node.id,
+            label,
+            LANE_COLORS[node.lane % LANE_COLORS.len()]
+        ));
+    }
+    let exported: HashSet<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+    for node in &graph.nodes {
+        for parent in node
+            .parents
+            .iter()
+            .filter(|p| exported.contains(p.as_str()))
+        {
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", node.id, parent));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Standalone SVG: one row per commit, lanes on the left, the short id,
+/// refs and summary on the right
+pub fn to_svg(graph: &HistoryGraph) -> String {
+    let lane_count = graph.nodes.iter().map(|n| n.lane + 1).max().unwrap_or(1);
+    let text_x = MARGIN + lane_count * LANE_WIDTH + MARGIN;
+    let longest = graph
+        .nodes
+        .iter()
+        .map(|n| n.summary.chars().count() + n.refs.join(", ").chars().count() + 12)
+        .max()
+        .unwrap_or(0);
+    let width = text_x + longest * 7 + MARGIN;
+    let height = MARGIN * 2 + graph.nodes.len().max(1) * ROW_HEIGHT;
+    let position = |lane: usize, row: usize| {
+        (
+            MARGIN + lane * LANE_WIDTH + LANE_WIDTH / 2,
+            MARGIN + row * ROW_HEIGHT + ROW_HEIGHT / 2,
+        )
+    };
+    let rows: HashMap<&str, usize> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(row, n)| (n.id.as_str(), row))
+        .collect();
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"12\">\n",
+        w = width,
+        h = height
+    );
+    svg.push_str(&format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>\n",
+        width, height
+    ));
+
+    // Edges first so the commit dots are drawn over them
+    for (row, node) in graph.nodes.iter().enumerate() {
+        let (x, y) = position(node.lane, row);
+        for parent in &node.parents {
+            let Some(&parent_row) = rows.get(parent.as_str()) else {
+                continue;
+            };
+            let parent_node = &graph.nodes[parent_row];
+            let (px, py) = position(parent_node.lane, parent_row);
+            // A line keeps the color of the lane it runs down: the child's
+            // towards its first parent, the merged branch's otherwise
+            let lane = if parent == &node.parents[0] {
+                node.lane
+            } else {
+                parent_node.lane
+            };
+            let color = LANE_COLORS[lane % LANE_COLORS.len()];
+            let path = if x == px {
+                format!("M{} {} L{} {}", x, y, px, py)
+            } else if parent == &node.parents[0] {
+                // Fork: the line runs in the child's lane, then bends into the parent
+                format!("M{} {} L{} {} L{} {}", x, y, x, py - ROW_HEIGHT / 2, px, py)
+            } else {
+                // Merge: the merged line leaves the child and runs down its own lane
+                format!("M{} {} L{} {} L{} {}", x, y, px, y + ROW_HEIGHT / 2, px, py)
+            };
+            svg.push_str(&format!(
+                "<path d=\"{}\" stroke=\"{}\" stroke-width=\"2\" fill=\"none\"/>\n",
+                path, color
+            ));
+        }
+    }
+
+    for (row, node) in graph.nodes.iter().enumerate() {
+        let (x, y) = position(node.lane, row);
+        let color = LANE_COLORS[node.lane % LANE_COLORS.len()];
+        svg.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"{}\"><title>{}</title></circle>\n",
+            x,
+            y,
+            color,
+            escape_xml(&format!("{} {} {}", node.id, node.author, node.date))
+        ));
+        let refs = if node.refs.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<tspan font-weight=\"bold\">({})</tspan> ",
+                escape_xml(&node.refs.join(", "))
+            )
+        };
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\"><tspan fill=\"#80868b\">{}</tspan> {}{}</text>\n",
+            text_x,
+            y + 4,
+            node.short_id,
+            refs,
+            escape_xml(&node.summary)
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Export the commit graph of `range` in `format`, written to `output_path`
+/// when given, otherwise returned as text
+pub fn export_history(
+    repo: &Repository,
+    format: HistoryFormat,
+    range: Option<&str>,
+    max_commits: Option<usize>,
+    output_path: Option<&str>,
+) -> Result<HistoryExport, String> {
+    let graph = history_graph(repo, range, max_commits.unwrap_or(DEFAULT_MAX_COMMITS))?;
+    let content = match format {
+        HistoryFormat::Dot => to_dot(&graph),
+        HistoryFormat::Json => serde_json::to_string_pretty(&graph)
+            .map_err(|e| format!("Failed to serialize the history: {}", e))?,
+        HistoryFormat::Svg => to_svg(&graph),
+    };
+
+    let mut export = HistoryExport {
+        format,
+        commit_count: graph.nodes.len(),
+        truncated: graph.truncated,
+        path: None,
+        content: None,
+    };
+    match output_path {
+        Some(path) => {
+            std::fs::write(path, content)
+                .map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+            export.path = Some(path.to_string());
+        }
+        None => export.content = Some(content),
+    }
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    /// main: a - b - m, with c on a side branch merged by m
+    fn merge_repo() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let commit = |message: &str, parents: &[Oid]| {
+            let parents: Vec<git2::Commit> = parents
+                .iter()
+                .map(|p| repo.find_commit(*p).unwrap())
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(None, &sig, &sig, message, &tree, &parents)
+                .unwrap()
+        };
+        let a = commit("a", &[]);
+        let b = commit("b <main>", &[a]);
+        let c = commit("c", &[a]);
+        let m = commit("merge", &[b, c]);
+        repo.reference("refs/heads/main", m, true, "test").unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        drop(tree);
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_history_graph_lanes() {
+        let (_dir, repo) = merge_repo();
+        let graph = history_graph(&repo, None, DEFAULT_MAX_COMMITS).unwrap();
+        let summaries: Vec<(&str, usize)> = graph
+            .nodes
+            .iter()
+            .map(|n| (n.summary.as_str(), n.lane))
+            .collect();
+        assert_eq!(summaries[0], ("merge", 0));
+        assert_eq!(summaries[3], ("a", 0));
+        // The merged side branch gets a lane of its own
+        let c = summaries.iter().find(|(s, _)| *s == "c").unwrap();
+        assert_eq!(c.1, 1);
+        assert_eq!(graph.nodes[0].refs, ["main"]);
+
+        let limited = history_graph(&repo, Some("HEAD"), 2).unwrap();
+        assert!(limited.truncated);
+        assert_eq!(limited.nodes.len(), 2);
+        let range = history_graph(&repo, Some("HEAD~1..HEAD"), 10).unwrap();
+        assert_eq!(range.nodes.len(), 2); // the merge and c
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let (_dir, repo) = merge_repo();
+        let export = |format| {
+            export_history(&repo, format, None, None, None)
+                .unwrap()
+                .content
+                .unwrap()
+        };
+
+        let dot = export(HistoryFormat::Dot);
+        assert!(dot.starts_with("digraph history {"));
+        assert_eq!(dot.matches(" -> ").count(), 4);
+        assert!(dot.contains("b <main>"));
+
+        let svg = export(HistoryFormat::Svg);
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(svg.matches("<path").count(), 4);
+        assert!(svg.contains("b &lt;main&gt;"));
+
+        let json: serde_json::Value = serde_json::from_str(&export(HistoryFormat::Json)).unwrap();
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,176 @@
+//! Hook frameworks (husky, pre-commit) configured in a repository.
+//!
+//! Both keep their hooks in the repository but only take effect once installed
+//! into git (husky sets `core.hooksPath`, pre-commit writes `.git/hooks/*`), and
+//! both need a runner (node, the pre-commit tool) on the PATH the hooks get.
+//! When either is missing, commits made from Forky silently skip checks that
+//! run in the terminal, so the status is reported with readable warnings.
+
+use crate::git::environment;
+use git2::Repository;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookFrameworkStatus {
+    pub framework: String,   // "husky" or "pre-commit"
+    pub config_path: String, // relative to the work tree
+    pub hooks_installed: bool,
+    pub runner: String,              // program the hooks need
+    pub runner_path: Option<String>, // where it was found, None if missing
+    pub warnings: Vec<String>,
+}
+
+/// The directory git runs hooks from: `core.hooksPath` (relative to the work
+/// tree) or the `hooks` dir of the common git dir, which linked worktrees name
+/// in their `commondir` file
+fn hooks_dir(repo: &Repository, workdir: &Path) -> PathBuf {
+    let configured = repo
+        .config()
+        .and_then(|config| config.get_string("core.hooksPath"))
+        .ok()
+        .filter(|path| !path.trim().is_empty());
+    match configured {
+        Some(path) => workdir.join(path),
+        None => std::fs::read_to_string(repo.path().join("commondir"))
+            .map(|common| repo.path().join(common.trim()))
+            .unwrap_or_else(|_| repo.path().to_path_buf())
+            .join("hooks"),
+    }
+}
+
+fn read_hook(dir: &Path, hook: &str) -> Option<String> {
+    std::fs::read_to_string(dir.join(hook)).ok()
+}
+
+fn husky_status(repo: &Repository, workdir: &Path) -> HookFrameworkStatus {
+    let dir = hooks_dir(repo, workdir);
+    let husky_dir = workdir.join(".husky");
+    // husky 9 points core.hooksPath at .husky/_, older versions at .husky or
+    // write their own scripts into .git/hooks
+    let hooks_installed = dir.starts_with(&husky_dir)
+        || read_hook(&dir, "pre-commit").is_some_and(|script| script.contains("husky"));
+    let runner_path = environment::find_program("node");
+
+    let mut warnings = Vec::new();
+    if !hooks_installed {
+        warnings.push(
+            "Husky hooks are not installed in git; run `npm install` (or `npx husky`) in the repository"
+                .to_string(),
+        );
+    }
+    if runner_path.is_none() {
+        warnings.push(
+            "node is not on the PATH Forky gives git, so Husky hooks will fail; add its directory in Settings > Git"
+                .to_string(),
+        );
+    }
+    HookFrameworkStatus {
+        framework: "husky".to_string(),
+        config_path: ".husky".to_string(),
+        hooks_installed,
+        runner: "node".to_string(),
+        runner_path: runner_path.map(|p| p.to_string_lossy().to_string()),
+        warnings,
+    }
+}
+
+fn pre_commit_status(repo: &Repository, workdir: &Path) -> HookFrameworkStatus {
+    let dir = hooks_dir(repo, workdir);
+    let script = read_hook(&dir, "pre-commit")
+        .filter(|script| script.contains("File generated by pre-commit"));
+    let hooks_installed = script.is_some();
+    // The generated hook runs the Python it was installed with, and falls back
+    // to `pre-commit` on the PATH
+    let install_python = script.as_deref().and_then(|script| {
+        script
+            .lines()
+            .find_map(|line| line.strip_prefix("INSTALL_PYTHON="))
+            .map(|python| PathBuf::from(python.trim().trim_matches(['\'', '"'])))
+            .filter(|python| python.is_file())
+    });
+    let runner_path = environment::find_program("pre-commit").or(install_python);
+
+    let mut warnings = Vec::new();
+    if !hooks_installed {
+        warnings.push(
+            "pre-commit hooks are not installed in git; run `pre-commit install` in the repository"
+                .to_string(),
+        );
+    }
+    if runner_path.is_none() {
+        warnings.push(
+            "pre-commit is not on the PATH Forky gives git, so its hooks will fail; add its directory in Settings > Git"
+                .to_string(),
+        );
+    }
+    HookFrameworkStatus {
+        framework: "pre-commit".to_string(),
+        config_path: ".pre-commit-config.yaml".to_string(),
+        hooks_installed,
+        runner: "pre-commit".to_string(),
+        runner_path: runner_path.map(|p| p.to_string_lossy().to_string()),
+        warnings,
+    }
+}
+
+/// The hook frameworks configured in the repository and whether they will run
+/// from Forky. Empty when the repository uses none (or is bare).
+pub fn get_hook_framework_status(repo: &Repository) -> Vec<HookFrameworkStatus> {
+    let Some(workdir) = repo.workdir() else {
+        return Vec::new();
+    };
+    let mut frameworks = Vec::new();
+    if workdir.join(".husky").is_dir() {
+        frameworks.push(husky_status(repo, workdir));
+    }
+    if workdir.join(".pre-commit-config.yaml").is_file() {
+        frameworks.push(pre_commit_status(repo, workdir));
+    }
+    frameworks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_husky_installed_through_hooks_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        assert!(get_hook_framework_status(&repo).is_empty());
+
+        fs::create_dir_all(dir.path().join(".husky/_")).unwrap();
+        let status = get_hook_framework_status(&repo);
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].framework, "husky");
+        assert!(!status[0].hooks_installed);
+        assert!(status[0].warnings[0].contains("not installed"));
+
+        repo.config()
+            .unwrap()
+            .set_str("core.hooksPath", ".husky/_")
+            .unwrap();
+        assert!(get_hook_framework_status(&repo)[0].hooks_installed);
+    }
+
+    #[test]
+    fn test_pre_commit_installed_hook_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".pre-commit-config.yaml"), "repos: []\n").unwrap();
+        assert!(!get_hook_framework_status(&repo)[0].hooks_installed);
+
+        let hooks = repo.path().join("hooks");
+        fs::create_dir_all(&hooks).unwrap();
+        fs::write(
+            hooks.join("pre-commit"),
+            "#!/usr/bin/env bash\n# File generated by pre-commit: https://pre-commit.com\n",
+        )
+        .unwrap();
+        let status = get_hook_framework_status(&repo);
+        assert_eq!(status[0].framework, "pre-commit");
+        assert!(status[0].hooks_installed);
+    }
+}
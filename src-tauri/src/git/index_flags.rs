@@ -0,0 +1,260 @@
+//! Assume-unchanged and skip-worktree bits of index entries.
+//!
+//! Both bits make git stop looking at the work tree file, so changes to it no
+//! longer show up in the status: a common source of "why doesn't my change
+//! show up". The flagged files are listed next to the status so they can be
+//! spotted, and the bits are set and cleared with `git update-index`.
+
+use crate::git::environment::git_command;
+use git2::{IndexEntryExtendedFlag, IndexEntryFlag, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexFlag {
+    AssumeUnchanged,
+    SkipWorktree,
+}
+
+impl IndexFlag {
+    fn option(self, enabled: bool) -> &'static str {
+        match (self, enabled) {
+            (IndexFlag::AssumeUnchanged, true) => "--assume-unchanged",
+            (IndexFlag::AssumeUnchanged, false) => "--no-assume-unchanged",
+            (IndexFlag::SkipWorktree, true) => "--skip-worktree",
+            (IndexFlag::SkipWorktree, false) => "--no-skip-worktree",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlaggedFile {
+    pub path: String,
+    pub assume_unchanged: bool,
+    pub skip_worktree: bool,
+    pub worktree_status: Option<String>, // "modified" or "deleted": the change git is not showing
+}
+
+/// Blob ids of the work tree files `paths` as git would store them, i.e.
+/// through the clean filters and end-of-line conversion of each path
+pub(crate) fn hash_workdir_files(workdir: &Path, paths: &[String]) -> Result<Vec<Oid>, String> {
+    let mut child = git_command()
+        .arg("-C")
+        .arg(workdir)
+        .args(["hash-object", "--stdin-paths"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git hash-object: {}", e))?;
+
+    // Written from a thread so git is never blocked on a full stdout pipe
+    let input: String = paths.iter().map(|path| format!("{}\n", path)).collect();
+    let mut stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to execute git hash-object: {}", e))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to hash the work tree files: {}",
+            stderr.trim()
+        ));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| Oid::from_str(line.trim()).map_err(|e| e.message().to_string()))
+        .collect()
+}
+
+/// Files of the index with the assume-unchanged or skip-worktree bit set,
+/// sorted by path
+pub fn get_flagged_files(repo: &Repository) -> Result<Vec<FlaggedFile>, String> {
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    // The index is cached by the repository; pick up what git changed on disk
+    index.read(false).map_err(|e| e.message().to_string())?;
+
+    let mut files = Vec::new();
+    let mut index_ids = Vec::new();
+    for entry in index.iter() {
+        let assume_unchanged = IndexEntryFlag::from_bits_truncate(entry.flags).is_valid();
+        let skip_worktree =
+            IndexEntryExtendedFlag::from_bits_truncate(entry.flags_extended).is_skip_worktree();
+        if !assume_unchanged && !skip_worktree {
+            continue;
+        }
+        files.push(FlaggedFile {
+            path: String::from_utf8_lossy(&entry.path).to_string(),
+            assume_unchanged,
+            skip_worktree,
+            worktree_status: None,
+        });
+        index_ids.push(entry.id);
+    }
+
+    if let Some(workdir) = repo.workdir() {
+        let mut present = Vec::new();
+        for (position, file) in files.iter_mut().enumerate() {
+            if workdir.join(&file.path).exists() {
+                // One path per line for hash-object
+                if !file.path.contains('\n') {
+                    present.push(position);
+                }
+            } else if !file.skip_worktree {
+                // Skip-worktree files are expected to be missing: a sparse
+                // checkout marks everything it does not check out that way
+                file.worktree_status = Some("deleted".to_string());
+            }
+        }
+        let paths: Vec<String> = present.iter().map(|&i| files[i].path.clone()).collect();
+        if !paths.is_empty() {
+            let hashes = hash_workdir_files(workdir, &paths)?;
+            for (&position, hash) in present.iter().zip(hashes) {
+                if hash != index_ids[position] {
+                    files[position].worktree_status = Some("modified".to_string());
+                }
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Set or clear `flag` on the index entries of `paths`
+pub fn set_index_flag(
+    repo: &Repository,
+    paths: &[String],
+    flag: IndexFlag,
+    enabled: bool,
+) -> Result<(), String> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or("Cannot change index flags in a bare repository")?;
+
+    let output = git_command()
+        .arg("-C")
+        .arg(workdir)
+        .arg("update-index")
+        .arg(flag.option(enabled))
+        .arg("--")
+        .args(paths)
+        .output()
+        .map_err(|e| format!("Failed to execute git update-index: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("Failed to update the index: {}", stderr.trim()))
+    }
+}
+
+/// Clear both bits on `paths`
+pub fn clear_index_flags(repo: &Repository, paths: &[String]) -> Result<(), String> {
+    set_index_flag(repo, paths, IndexFlag::AssumeUnchanged, false)?;
+    set_index_flag(repo, paths, IndexFlag::SkipWorktree, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_list_and_clear_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        for name in ["a.txt", "b.txt"] {
+            std::fs::write(dir.path().join(name), "one\n").unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.add_path(Path::new("b.txt")).unwrap();
+        index.write().unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        assert!(get_flagged_files(&repo).unwrap().is_empty());
+
+        let a = vec!["a.txt".to_string()];
+        let b = vec!["b.txt".to_string()];
+        set_index_flag(&repo, &a, IndexFlag::AssumeUnchanged, true).unwrap();
+        set_index_flag(&repo, &b, IndexFlag::SkipWorktree, true).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "two\n").unwrap();
+        // The change is hidden from the status
+        assert!(repo.statuses(None).unwrap().is_empty());
+
+        let flagged = get_flagged_files(&repo).unwrap();
+        assert_eq!(
+            flagged,
+            vec![
+                FlaggedFile {
+                    path: "a.txt".to_string(),
+                    assume_unchanged: true,
+                    skip_worktree: false,
+                    worktree_status: Some("modified".to_string()),
+                },
+                FlaggedFile {
+                    path: "b.txt".to_string(),
+                    assume_unchanged: false,
+                    skip_worktree: true,
+                    worktree_status: None,
+                },
+            ]
+        );
+
+        clear_index_flags(&repo, &[a[0].clone(), b[0].clone()]).unwrap();
+        assert!(get_flagged_files(&repo).unwrap().is_empty());
+        assert!(set_index_flag(
+            &repo,
+            &["missing.txt".to_string()],
+            IndexFlag::SkipWorktree,
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sparse_and_filtered_files_are_not_reported_as_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("core.autocrlf", "true")
+            .unwrap();
+        std::fs::write(dir.path().join("crlf.txt"), "one\r\ntwo\r\n").unwrap();
+        std::fs::write(dir.path().join("sparse.txt"), "one\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("crlf.txt")).unwrap();
+        index.add_path(Path::new("sparse.txt")).unwrap();
+        index.write().unwrap();
+
+        let crlf = vec!["crlf.txt".to_string()];
+        let sparse = vec!["sparse.txt".to_string()];
+        set_index_flag(&repo, &crlf, IndexFlag::AssumeUnchanged, true).unwrap();
+        set_index_flag(&repo, &sparse, IndexFlag::SkipWorktree, true).unwrap();
+        // What a sparse checkout does with the files outside of it
+        std::fs::remove_file(dir.path().join("sparse.txt")).unwrap();
+
+        let flagged = get_flagged_files(&repo).unwrap();
+        assert_eq!(flagged.len(), 2);
+        assert!(flagged.iter().all(|file| file.worktree_status.is_none()));
+
+        std::fs::write(dir.path().join("crlf.txt"), "one\r\nthree\r\n").unwrap();
+        let flagged = get_flagged_files(&repo).unwrap();
+        assert_eq!(flagged[0].worktree_status.as_deref(), Some("modified"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Issue keys (JIRA-123, #456) in branch names.
+//!
+//! Branches are often named after the ticket they implement
+//! (`feature/PROJ-42-login`, `fix/456-crash`); the key found in the current
+//! branch is offered as a prefix for the commit message. The patterns are
+//! regular expressions stored as `forky.issuePattern` entries in the git config
+//! and the prefix format as `forky.issuePrefix`, so both can be set per
+//! repository or globally.
+
+use crate::git::environment::git_command;
+use git2::Repository;
+use regex::Regex;
+use serde::Serialize;
+
+/// Patterns used when none are configured: a JIRA-style key anywhere in the
+/// name, or a number at the start of a path component (GitHub/GitLab issues)
+pub const DEFAULT_ISSUE_PATTERNS: &[&str] =
+    &[r"\b([A-Z][A-Z0-9]+-\d+)\b", r"(?:^|/)#?(\d+)(?:[-_]|$)"];
+
+/// Prefix format used when `forky.issuePrefix` is not set; `{key}` is replaced
+/// with the key
+pub const DEFAULT_ISSUE_PREFIX: &str = "{key}: ";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IssueKey {
+    pub key: String, // "PROJ-42", or "#456" for a bare number
+    pub branch: String,
+    pub prefix: String, // commit message prefix built from forky.issuePrefix
+}
+
+/// The configured patterns, or the defaults when there are none
+pub fn get_issue_patterns(repo: &Repository) -> Result<Vec<String>, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("Failed to read config: {}", e))?;
+
+    let mut patterns = Vec::new();
+    if let Ok(mut entries) = config.multivar("forky.issuePattern", None) {
+        while let Some(Ok(entry)) = entries.next() {
+            if let Some(value) = entry.value().map(str::trim) {
+                if !value.is_empty() && !patterns.iter().any(|p| p == value) {
+                    patterns.push(value.to_string());
+                }
+            }
+        }
+    }
+    if patterns.is_empty() {
+        patterns = DEFAULT_ISSUE_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+    }
+    Ok(patterns)
+}
+
+/// Replace the patterns of a repository; an empty list restores the defaults
+pub fn set_issue_patterns(repo: &Repository, patterns: &[String]) -> Result<(), String> {
+    let patterns: Vec<&str> = patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    for pattern in &patterns {
+        Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+    }
+
+    // Written with git itself: libgit2 does not escape the backslashes regular
+    // expressions are full of, and then fails to read the value back
+    let config = |args: &[&str]| {
+        git_command()
+            .arg("--git-dir")
+            .arg(repo.path())
+            .args(["config", "--local"])
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to execute git config: {}", e))
+    };
+    let output = config(&["--unset-all", "forky.issuePattern"])?;
+    // Exit code 5: there was nothing to unset
+    if !output.status.success() && output.status.code() != Some(5) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to clear forky.issuePattern: {}",
+            stderr.trim()
+        ));
+    }
+    for pattern in patterns {
+        let output = config(&["--add", "forky.issuePattern", pattern])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "Failed to set forky.issuePattern: {}",
+                stderr.trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The first key any of `patterns` finds in `branch`, tried in order. A
+/// pattern's first capture group is the key (the whole match without one), and
+/// bare numbers get a `#`. Invalid patterns are skipped.
+pub fn extract_issue_key(branch: &str, patterns: &[String]) -> Option<String> {
+    patterns
+        .iter()
+        .filter_map(|pattern| Regex::new(pattern).ok())
+        .find_map(|regex| {
+            let captures = regex.captures(branch)?;
+            let key = captures.get(1).or_else(|| captures.get(0))?.as_str();
+            let key = key.trim_start_matches('#');
+            if key.is_empty() {
+                None
+            } else if key.chars().all(|c| c.is_ascii_digit()) {
+                Some(format!("#{}", key))
+            } else {
+                Some(key.to_string())
+            }
+        })
+}
+
+/// The issue key of the checked out branch, None on a detached HEAD or when no
+/// pattern matches
+pub fn get_branch_issue_key(repo: &Repository) -> Result<Option<IssueKey>, String> {
+    let head = match repo.head() {
+        Ok(head) if head.is_branch() => head,
+        _ => return Ok(None),
+    };
+    let Some(branch) = head.shorthand() else {
+        return Ok(None);
+    };
+    let patterns = get_issue_patterns(repo)?;
+    let Some(key) = extract_issue_key(branch, &patterns) else {
+        return Ok(None);
+    };
+
+    let format = repo
+        .config()
+        .and_then(|config| config.get_string("forky.issuePrefix"))
+        .unwrap_or_else(|_| DEFAULT_ISSUE_PREFIX.to_string());
+    Ok(Some(IssueKey {
+        prefix: format.replace("{key}", &key),
+        key,
+        branch: branch.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<String> {
+        DEFAULT_ISSUE_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_issue_key_with_default_patterns() {
+        let patterns = defaults();
+        assert_eq!(
+            extract_issue_key("feature/PROJ-42-login", &patterns).as_deref(),
+            Some("PROJ-42")
+        );
+        assert_eq!(
+            extract_issue_key("fix/456-crash-on-start", &patterns).as_deref(),
+            Some("#456")
+        );
+        assert_eq!(
+            extract_issue_key("#789-typo", &patterns).as_deref(),
+            Some("#789")
+        );
+        assert_eq!(extract_issue_key("release/v2", &patterns), None);
+        assert_eq!(extract_issue_key("release/2.0", &patterns), None);
+        assert_eq!(extract_issue_key("main", &patterns), None);
+    }
+
+    #[test]
+    fn test_configured_patterns_and_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        let commit = repo.find_commit(commit).unwrap();
+        repo.branch("ticket-77_cleanup", &commit, false).unwrap();
+        repo.set_head("refs/heads/ticket-77_cleanup").unwrap();
+
+        assert!(set_issue_patterns(&repo, &["(".to_string()]).is_err());
+        set_issue_patterns(&repo, &[r"ticket-(\d+)".to_string()]).unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("forky.issuePrefix", "[{key}] ")
+            .unwrap();
+
+        let issue = get_branch_issue_key(&repo).unwrap().unwrap();
+        assert_eq!(issue.key, "#77");
+        assert_eq!(issue.prefix, "[#77] ");
+
+        set_issue_patterns(&repo, &[]).unwrap();
+        assert_eq!(get_issue_patterns(&repo).unwrap(), defaults());
+    }
+}
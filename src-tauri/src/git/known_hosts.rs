@@ -0,0 +1,247 @@
+//! Adding SSH hosts to `~/.ssh/known_hosts`.
+//!
+//! The verification prompt shows the fingerprint seen while connecting. Before
+//! anything is written, the host's keys are scanned and their fingerprints
+//! returned (checked against the ones GitHub and GitLab publish), and only the
+//! key matching the fingerprint the user confirmed is added.
+
+use crate::git::repository::{create_success_result, GitOperationResult};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+
+/// SHA256 host key fingerprints published by well-known hosts
+/// (docs.github.com "GitHub's SSH key fingerprints", docs.gitlab.com "SSH host keys fingerprints")
+const PUBLISHED_FINGERPRINTS: &[(&str, &[&str])] = &[
+    (
+        "github.com",
+        &[
+            "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU",
+            "SHA256:p2QAMXNIC1TJYWeIOttrVc98/R1BUFWu3/LiyKgUfQM",
+            "SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s",
+        ],
+    ),
+    (
+        "gitlab.com",
+        &[
+            "SHA256:eUXGGm1YGsMAS7vkcx6JOJdOGHPem5gQp4taiCfCLB8",
+            "SHA256:HbW3g8zUjNSksFbqTiUWPWg2Bq1x8xdGUrliXFzSnUw",
+            "SHA256:ROQFvPThGrW4RuWLoL9tq9I9zJ42fK4XywyRtbOz/EQ",
+        ],
+    ),
+];
+
+/// A host key returned by `ssh-keyscan`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScannedHostKey {
+    pub key_type: String,        // e.g. "ssh-ed25519"
+    pub fingerprint: String,     // "SHA256:...", as shown by ssh
+    pub published: Option<bool>, // matches the host's published fingerprints; None if it publishes none
+}
+
+/// SHA256 fingerprint of a base64 public key, in ssh's format
+fn fingerprint(public_key: &str) -> Option<String> {
+    let blob = base64::engine::general_purpose::STANDARD
+        .decode(public_key)
+        .ok()?;
+    let hash = Sha256::digest(blob);
+    Some(format!(
+        "SHA256:{}",
+        base64::engine::general_purpose::STANDARD_NO_PAD.encode(hash)
+    ))
+}
+
+/// Whether `fingerprint` is one `host` publishes, or None if it publishes none
+fn is_published(host: &str, fingerprint: &str) -> Option<bool> {
+    let host = host.to_lowercase();
+    PUBLISHED_FINGERPRINTS
+        .iter()
+        .find(|(name, _)| *name == host)
+        .map(|(_, fingerprints)| fingerprints.contains(&fingerprint))
+}
+
+/// Parse `ssh-keyscan` output ("host key-type base64-key" per line, comments
+/// start with '#') into the known_hosts lines and their keys
+fn parse_keyscan(host: &str, output: &str) -> Vec<(String, ScannedHostKey)> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let (_, key_type, public_key) = (parts.next()?, parts.next()?, parts.next()?);
+            let fingerprint = fingerprint(public_key)?;
+            let key = ScannedHostKey {
+                key_type: key_type.to_string(),
+                published: is_published(host, &fingerprint),
+                fingerprint,
+            };
+            Some((line.to_string(), key))
+        })
+        .collect()
+}
+
+/// Whether `host` can only be read by ssh-keyscan as a host name or address:
+/// a leading '-' would be taken as an option, whitespace would split it
+fn is_valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && !host.starts_with('-')
+        && host
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '[' | ']'))
+}
+
+/// Run `ssh-keyscan` for `host`, returning its keys with their known_hosts lines
+fn keyscan(host: &str) -> Result<Vec<(String, ScannedHostKey)>, String> {
+    if !is_valid_host(host) {
+        return Err(format!("'{}' is not a valid host name", host));
+    }
+    let output = Command::new("ssh-keyscan")
+        .arg("-t")
+        .arg("ed25519,rsa,ecdsa")
+        .arg("--")
+        .arg(host)
+        .output()
+        .map_err(|e| format!("Failed to execute ssh-keyscan: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to scan host keys: {}", stderr.trim()));
+    }
+
+    Ok(parse_keyscan(
+        host,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// The keys `host` presents, for the user to compare before trusting it
+pub fn scan_ssh_host_keys(host: &str) -> Result<Vec<ScannedHostKey>, String> {
+    let keys = keyscan(host)?;
+    if keys.is_empty() {
+        return Err("No host keys found for this host".to_string());
+    }
+    Ok(keys.into_iter().map(|(_, key)| key).collect())
+}
+
+fn failure(message: String, error_type: &str) -> GitOperationResult {
+    GitOperationResult {
+        success: false,
+        message,
+        requires_ssh_verification: None,
+        requires_credential: None,
+        error_type: Some(error_type.to_string()),
+        conflicting_files: None,
+    }
+}
+
+/// Add the key of `host` whose fingerprint the user confirmed to known_hosts.
+/// The host is scanned again and nothing is written if it no longer presents
+/// that key.
+pub fn add_ssh_known_host(host: &str, fingerprint: &str) -> Result<GitOperationResult, String> {
+    let keys = match keyscan(host) {
+        Ok(keys) => keys,
+        Err(message) => return Ok(failure(message, "ssh_keyscan_failed")),
+    };
+    if keys.is_empty() {
+        return Ok(failure(
+            "No host keys found for this host".to_string(),
+            "no_host_keys",
+        ));
+    }
+    let Some((line, _)) = keys.iter().find(|(_, key)| key.fingerprint == fingerprint) else {
+        return Ok(failure(
+            format!(
+                "'{}' no longer presents the key {}; it was not added to known hosts",
+                host, fingerprint
+            ),
+            "host_key_mismatch",
+        ));
+    };
+
+    // Get the path to known_hosts
+    let home = std::env::var("HOME").map_err(|_| "Could not determine home directory")?;
+    let ssh_dir = format!("{}/.ssh", home);
+    let known_hosts_path = format!("{}/known_hosts", ssh_dir);
+
+    // Create .ssh directory if it doesn't exist
+    std::fs::create_dir_all(&ssh_dir)
+        .map_err(|e| format!("Failed to create .ssh directory: {}", e))?;
+
+    let existing = std::fs::read_to_string(&known_hosts_path).unwrap_or_default();
+    if !existing.lines().any(|known| known.trim() == line) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&known_hosts_path)
+            .map_err(|e| format!("Failed to open known_hosts: {}", e))?;
+
+        let separator = if existing.is_empty() || existing.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        writeln!(file, "{}{}", separator, line)
+            .map_err(|e| format!("Failed to write to known_hosts: {}", e))?;
+    }
+
+    Ok(create_success_result(format!(
+        "Host '{}' added to known hosts",
+        host
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // github.com's published ed25519 key
+    const GITHUB_ED25519: &str =
+        "AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+
+    #[test]
+    fn test_parse_keyscan_computes_fingerprints() {
+        let output = format!(
+            "# github.com:22 SSH-2.0-babeld\n\
+             github.com ssh-ed25519 {}\n\
+             github.com ssh-rsa not-base64!\n",
+            GITHUB_ED25519
+        );
+        let keys = parse_keyscan("github.com", &output);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            keys[0].0,
+            format!("github.com ssh-ed25519 {}", GITHUB_ED25519)
+        );
+        assert_eq!(
+            keys[0].1,
+            ScannedHostKey {
+                key_type: "ssh-ed25519".to_string(),
+                fingerprint: "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU".to_string(),
+                published: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn test_hosts_that_look_like_options_are_refused() {
+        assert!(is_valid_host("github.com"));
+        assert!(is_valid_host("[::1]"));
+        assert!(is_valid_host("git-server_01.local"));
+        assert!(!is_valid_host("-oProxyCommand=touch /tmp/x"));
+        assert!(!is_valid_host("host other"));
+        assert!(!is_valid_host(""));
+        assert!(keyscan("-f/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_published_fingerprints_only_known_for_listed_hosts() {
+        let github = "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU";
+        assert_eq!(is_published("GitHub.com", github), Some(true));
+        assert_eq!(is_published("gitlab.com", github), Some(false));
+        assert_eq!(is_published("git.example.com", github), None);
+    }
+}
@@ -0,0 +1,536 @@
+//! Lightweight metadata extraction for audio/video files shown in diffs.
+//!
+//! Media files are binary, so the diff panel cannot show a textual diff for
+//! them. Instead we parse just enough of the container headers (WAV, FLAC,
+//! MP3, Ogg, MP4/MOV, AVI, Matroska/WebM) to report duration, codec and
+//! stream parameters for the old and new versions of the file. Parsing is
+//! best-effort: unknown or truncated files still report their size.
+//!
+//! Git LFS pointer files are detected as well, since media-heavy repositories
+//! usually store these instead of the real content.
+
+use serde::{Deserialize, Serialize};
+
+const AUDIO_EXTENSIONS: &[&str] = &[
+    ".mp3", ".wav", ".flac", ".ogg", ".oga", ".opus", ".m4a", ".aac", ".aif", ".aiff", ".wma",
+];
+
+const VIDEO_EXTENSIONS: &[&str] = &[
+    ".mp4", ".m4v", ".mov", ".avi", ".mkv", ".webm", ".wmv", ".ogv", ".mpg", ".mpeg", ".3gp",
+    ".flv",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MediaMetadata {
+    pub media_type: String, // "audio", "video"
+    pub mime_type: String,
+    pub file_size: u64,
+    pub duration_seconds: Option<f64>,
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub is_lfs_pointer: bool,
+}
+
+/// Return "audio" or "video" for known media extensions
+pub fn media_kind(file_path: &str) -> Option<&'static str> {
+    let lower = file_path.to_lowercase();
+    if AUDIO_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) {
+        Some("audio")
+    } else if VIDEO_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) {
+        Some("video")
+    } else {
+        None
+    }
+}
+
+/// Get MIME type for a media file from its extension
+fn media_mime_type(file_path: &str) -> String {
+    let lower = file_path.to_lowercase();
+    let ext = lower.rsplit('.').next().unwrap_or("");
+    match ext {
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "aif" | "aiff" => "audio/aiff",
+        "wma" => "audio/x-ms-wma",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "avi" => "video/x-msvideo",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "wmv" => "video/x-ms-wmv",
+        "ogv" => "video/ogg",
+        "mpg" | "mpeg" => "video/mpeg",
+        "3gp" => "video/3gpp",
+        "flv" => "video/x-flv",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// Extract metadata from the raw content of a media file.
+/// Returns None if the path does not have a known media extension.
+pub fn extract_metadata(file_path: &str, content: &[u8]) -> Option<MediaMetadata> {
+    let media_type = media_kind(file_path)?;
+
+    let mut meta = MediaMetadata {
+        media_type: media_type.to_string(),
+        mime_type: media_mime_type(file_path),
+        file_size: content.len() as u64,
+        ..Default::default()
+    };
+
+    if let Some(size) = parse_lfs_pointer_size(content) {
+        meta.file_size = size;
+        meta.is_lfs_pointer = true;
+        return Some(meta);
+    }
+
+    if content.starts_with(b"RIFF") && content.get(8..12) == Some(b"WAVE") {
+        parse_wav(content, &mut meta);
+    } else if content.starts_with(b"RIFF") && content.get(8..12) == Some(b"AVI ") {
+        parse_avi(content, &mut meta);
+    } else if content.starts_with(b"fLaC") {
+        parse_flac(content, &mut meta);
+    } else if content.starts_with(b"OggS") {
+        parse_ogg(content, &mut meta);
+    } else if content.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        parse_matroska(content, &mut meta);
+    } else if content
+        .get(4..8)
+        .is_some_and(|t| t == b"ftyp" || t == b"moov")
+    {
+        parse_mp4(content, &mut meta);
+    } else if content.starts_with(b"ID3") || is_mpeg_audio_sync(content, 0) {
+        parse_mp3(content, &mut meta);
+    }
+
+    Some(meta)
+}
+
+/// Return the real object size if the content is a Git LFS pointer file
+fn parse_lfs_pointer_size(content: &[u8]) -> Option<u64> {
+    // Pointer files are tiny text files; anything bigger is real content
+    if content.len() > 1024 || !content.starts_with(b"version https://git-lfs.github.com/spec/") {
+        return None;
+    }
+    let text = std::str::from_utf8(content).ok()?;
+    text.lines()
+        .find_map(|line| line.strip_prefix("size "))
+        .and_then(|size| size.trim().parse().ok())
+}
+
+fn read_u16_le(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u16_be(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_u32_be(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_u64_le(data: &[u8], pos: usize) -> Option<u64> {
+    data.get(pos..pos + 8).map(|b| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(b);
+        u64::from_le_bytes(buf)
+    })
+}
+
+fn read_u64_be(data: &[u8], pos: usize) -> Option<u64> {
+    data.get(pos..pos + 8).map(|b| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(b);
+        u64::from_be_bytes(buf)
+    })
+}
+
+fn find_bytes(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+/// WAV: RIFF chunks "fmt " (format, channels, rate) and "data" (payload size)
+fn parse_wav(content: &[u8], meta: &mut MediaMetadata) {
+    let mut pos = 12;
+    let mut byte_rate = None;
+
+    while let (Some(id), Some(size)) = (content.get(pos..pos + 4), read_u32_le(content, pos + 4)) {
+        let body = pos + 8;
+        match id {
+            b"fmt " => {
+                let codec = match read_u16_le(content, body) {
+                    Some(1) => "pcm",
+                    Some(3) => "pcm_float",
+                    Some(6) => "alaw",
+                    Some(7) => "mulaw",
+                    Some(0x55) => "mp3",
+                    Some(0xFFFE) => "pcm_extensible",
+                    _ => "unknown",
+                };
+                meta.codec = Some(codec.to_string());
+                meta.channels = read_u16_le(content, body + 2);
+                meta.sample_rate = read_u32_le(content, body + 4);
+                byte_rate = read_u32_le(content, body + 8);
+            }
+            b"data" => {
+                if let Some(rate) = byte_rate.filter(|r| *r > 0) {
+                    meta.duration_seconds = Some(size as f64 / rate as f64);
+                }
+                break;
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even number of bytes
+        pos = body + size as usize + (size as usize & 1);
+    }
+}
+
+/// AVI: main header (avih) holds frame timing, first video stream header (strh) the codec
+fn parse_avi(content: &[u8], meta: &mut MediaMetadata) {
+    if let Some(pos) = find_bytes(content, b"avih") {
+        let micros_per_frame = read_u32_le(content, pos + 8).unwrap_or(0);
+        let total_frames = read_u32_le(content, pos + 8 + 16).unwrap_or(0);
+        if micros_per_frame > 0 && total_frames > 0 {
+            meta.duration_seconds = Some(micros_per_frame as f64 * total_frames as f64 / 1e6);
+        }
+    }
+    // strh chunk: id (4) + size (4), then fccType and fccHandler
+    let video_header = content
+        .windows(4)
+        .enumerate()
+        .filter(|(_, w)| *w == b"strh")
+        .map(|(pos, _)| pos)
+        .find(|pos| content.get(pos + 8..pos + 12) == Some(b"vids"));
+    if let Some(pos) = video_header {
+        if let Some(handler) = content.get(pos + 12..pos + 16) {
+            let handler = String::from_utf8_lossy(handler).trim().to_string();
+            if !handler.is_empty() {
+                meta.codec = Some(handler);
+            }
+        }
+    }
+}
+
+/// FLAC: the mandatory STREAMINFO block follows the "fLaC" marker
+fn parse_flac(content: &[u8], meta: &mut MediaMetadata) {
+    meta.codec = Some("flac".to_string());
+    // 4 bytes marker + 4 bytes block header, then STREAMINFO
+    let Some(info) = content.get(8..8 + 18) else {
+        return;
+    };
+    let sample_rate = ((info[10] as u32) << 12) | ((info[11] as u32) << 4) | (info[12] as u32 >> 4);
+    let channels = ((info[12] >> 1) & 0x07) as u16 + 1;
+    let total_samples = (((info[13] & 0x0F) as u64) << 32)
+        | ((info[14] as u64) << 24)
+        | ((info[15] as u64) << 16)
+        | ((info[16] as u64) << 8)
+        | info[17] as u64;
+
+    meta.channels = Some(channels);
+    if sample_rate > 0 {
+        meta.sample_rate = Some(sample_rate);
+        if total_samples > 0 {
+            meta.duration_seconds = Some(total_samples as f64 / sample_rate as f64);
+        }
+    }
+}
+
+/// Ogg: codec from the first page, duration from the granule position of the last page
+fn parse_ogg(content: &[u8], meta: &mut MediaMetadata) {
+    // The first packet starts right after the page header and segment table
+    let segments = content.get(26).copied().unwrap_or(0) as usize;
+    let packet_start = 27 + segments;
+    let packet = content.get(packet_start..).unwrap_or(&[]);
+
+    let mut pre_skip = 0u64;
+    let mut granule_rate = None;
+    if packet.starts_with(b"\x01vorbis") {
+        meta.codec = Some("vorbis".to_string());
+        meta.channels = packet.get(11).map(|c| *c as u16);
+        meta.sample_rate = read_u32_le(packet, 12);
+        granule_rate = meta.sample_rate;
+    } else if packet.starts_with(b"OpusHead") {
+        meta.codec = Some("opus".to_string());
+        meta.channels = packet.get(9).map(|c| *c as u16);
+        pre_skip = read_u16_le(packet, 10).unwrap_or(0) as u64;
+        meta.sample_rate = read_u32_le(packet, 12);
+        // Opus granule positions always count 48 kHz samples
+        granule_rate = Some(48_000);
+    } else if packet.starts_with(b"\x80theora") {
+        meta.codec = Some("theora".to_string());
+    } else if packet.starts_with(b"\x7fFLAC") {
+        meta.codec = Some("flac".to_string());
+    }
+
+    let Some(rate) = granule_rate.filter(|r| *r > 0) else {
+        return;
+    };
+    let search_from = content.len().saturating_sub(64 * 1024);
+    let last_page = content[search_from..]
+        .windows(4)
+        .rposition(|w| w == b"OggS")
+        .map(|p| p + search_from);
+    if let Some(granule) = last_page.and_then(|p| read_u64_le(content, p + 6)) {
+        if granule != u64::MAX && granule > pre_skip {
+            meta.duration_seconds = Some((granule - pre_skip) as f64 / rate as f64);
+        }
+    }
+}
+
+/// Matroska/WebM: scan the header area for CodecID strings and the Duration element
+fn parse_matroska(content: &[u8], meta: &mut MediaMetadata) {
+    let head = &content[..content.len().min(256 * 1024)];
+
+    let known_codecs: &[(&[u8], &str)] = &[
+        (b"V_VP9", "vp9"),
+        (b"V_VP8", "vp8"),
+        (b"V_AV1", "av1"),
+        (b"V_MPEG4/ISO/AVC", "h264"),
+        (b"V_MPEGH/ISO/HEVC", "hevc"),
+        (b"A_OPUS", "opus"),
+        (b"A_VORBIS", "vorbis"),
+        (b"A_AAC", "aac"),
+        (b"A_FLAC", "flac"),
+    ];
+    let codecs: Vec<&str> = known_codecs
+        .iter()
+        .filter(|(id, _)| find_bytes(head, id).is_some())
+        .map(|(_, name)| *name)
+        .collect();
+    if !codecs.is_empty() {
+        meta.codec = Some(codecs.join(", "));
+    }
+
+    // TimecodeScale (0x2AD7B1) defaults to 1ms; Duration (0x4489) is a float in those units
+    let mut timecode_scale = 1_000_000f64;
+    if let Some(pos) = find_bytes(head, &[0x2A, 0xD7, 0xB1]) {
+        if let Some(&size_byte) = head.get(pos + 3) {
+            let len = (size_byte & 0x7F) as usize;
+            if (1..=8).contains(&len) {
+                if let Some(bytes) = head.get(pos + 4..pos + 4 + len) {
+                    timecode_scale =
+                        bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64) as f64;
+                }
+            }
+        }
+    }
+    if let Some(pos) = find_bytes(head, &[0x44, 0x89]) {
+        let duration = match head.get(pos + 2) {
+            Some(0x84) => read_u32_be(head, pos + 3).map(|bits| f32::from_bits(bits) as f64),
+            Some(0x88) => read_u64_be(head, pos + 3).map(f64::from_bits),
+            _ => None,
+        };
+        if let Some(duration) = duration.filter(|d| d.is_finite() && *d > 0.0) {
+            meta.duration_seconds = Some(duration * timecode_scale / 1e9);
+        }
+    }
+}
+
+/// MP4/MOV: walk the box tree for mvhd (duration) and stsd (sample entry codecs)
+fn parse_mp4(content: &[u8], meta: &mut MediaMetadata) {
+    let mut codecs = Vec::new();
+    walk_mp4_boxes(content, 0, content.len(), meta, &mut codecs, 0);
+    if !codecs.is_empty() {
+        meta.codec = Some(codecs.join(", "));
+    }
+}
+
+fn walk_mp4_boxes(
+    content: &[u8],
+    start: usize,
+    end: usize,
+    meta: &mut MediaMetadata,
+    codecs: &mut Vec<String>,
+    depth: usize,
+) {
+    let mut pos = start;
+    while pos + 8 <= end && depth < 8 {
+        let Some(size32) = read_u32_be(content, pos) else {
+            return;
+        };
+        let box_type = &content[pos + 4..pos + 8];
+        let (header_len, box_size) = match size32 {
+            0 => (8, end - pos),
+            1 => match read_u64_be(content, pos + 8) {
+                Some(size) => (16, size as usize),
+                None => return,
+            },
+            size => (8, size as usize),
+        };
+        if box_size < header_len {
+            return;
+        }
+        let box_end = pos.saturating_add(box_size).min(end);
+        let body = pos + header_len;
+
+        match box_type {
+            b"moov" | b"trak" | b"mdia" | b"minf" | b"stbl" => {
+                walk_mp4_boxes(content, body, box_end, meta, codecs, depth + 1);
+            }
+            b"mvhd" => {
+                let version = content.get(body).copied().unwrap_or(0);
+                let (timescale, duration) = if version == 1 {
+                    (
+                        read_u32_be(content, body + 20),
+                        read_u64_be(content, body + 24),
+                    )
+                } else {
+                    (
+                        read_u32_be(content, body + 12),
+                        read_u32_be(content, body + 16).map(|d| d as u64),
+                    )
+                };
+                if let (Some(timescale), Some(duration)) = (timescale, duration) {
+                    if timescale > 0 {
+                        meta.duration_seconds = Some(duration as f64 / timescale as f64);
+                    }
+                }
+            }
+            b"stsd" => {
+                // version/flags (4) + entry count (4), then the first sample entry
+                if let Some(format) = content.get(body + 12..body + 16) {
+                    let format = String::from_utf8_lossy(format).trim().to_string();
+                    if !format.is_empty() && !codecs.contains(&format) {
+                        codecs.push(format);
+                    }
+                }
+                // Audio sample entries carry channel count and a 16.16 sample rate
+                let entry = body + 8;
+                if content.get(entry + 4..entry + 8) == Some(b"mp4a") {
+                    meta.channels = read_u16_be(content, entry + 24);
+                    meta.sample_rate = read_u32_be(content, entry + 32).map(|rate| rate >> 16);
+                }
+            }
+            _ => {}
+        }
+
+        pos = box_end;
+    }
+}
+
+fn is_mpeg_audio_sync(content: &[u8], pos: usize) -> bool {
+    matches!(content.get(pos..pos + 2), Some([0xFF, b]) if b & 0xE0 == 0xE0)
+}
+
+/// MP3: skip ID3v2, read the first frame header, estimate duration assuming CBR
+fn parse_mp3(content: &[u8], meta: &mut MediaMetadata) {
+    meta.codec = Some("mp3".to_string());
+
+    let mut pos = 0;
+    if content.starts_with(b"ID3") {
+        if let Some(size) = content.get(6..10) {
+            // Synchsafe integer: 7 bits per byte
+            let tag_size = size
+                .iter()
+                .fold(0usize, |acc, b| (acc << 7) | (*b & 0x7F) as usize);
+            pos = 10 + tag_size;
+        }
+    }
+    while pos + 4 <= content.len() && !is_mpeg_audio_sync(content, pos) {
+        pos += 1;
+    }
+    let Some(header) = content.get(pos..pos + 4) else {
+        return;
+    };
+
+    let version_bits = (header[1] >> 3) & 0x03; // 3 = MPEG1, 2 = MPEG2, 0 = MPEG2.5
+    let bitrate_index = (header[2] >> 4) as usize;
+    let rate_index = ((header[2] >> 2) & 0x03) as usize;
+    let channel_mode = header[3] >> 6;
+
+    const MPEG1_L3: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const MPEG2_L3: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    let (bitrates, rates): (&[u32; 15], [u32; 3]) = match version_bits {
+        3 => (&MPEG1_L3, [44_100, 48_000, 32_000]),
+        2 => (&MPEG2_L3, [22_050, 24_000, 16_000]),
+        0 => (&MPEG2_L3, [11_025, 12_000, 8_000]),
+        _ => return,
+    };
+
+    meta.channels = Some(if channel_mode == 3 { 1 } else { 2 });
+    meta.sample_rate = rates.get(rate_index).copied();
+    if let Some(kbps) = bitrates.get(bitrate_index).filter(|b| **b > 0) {
+        let audio_bytes = (content.len() - pos) as f64;
+        meta.duration_seconds = Some(audio_bytes * 8.0 / (*kbps as f64 * 1000.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_bytes(sample_rate: u32, channels: u16, data_len: u32) -> Vec<u8> {
+        let byte_rate = sample_rate * channels as u32 * 2;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&byte_rate.to_le_bytes());
+        out.extend_from_slice(&(channels * 2).to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.resize(out.len() + data_len as usize, 0);
+        out
+    }
+
+    #[test]
+    fn test_media_kind_by_extension() {
+        assert_eq!(media_kind("sfx/Explosion.WAV"), Some("audio"));
+        assert_eq!(media_kind("cutscenes/intro.mp4"), Some("video"));
+        assert_eq!(media_kind("textures/wall.png"), None);
+    }
+
+    #[test]
+    fn test_wav_duration_and_format() {
+        // 1 second of 16-bit stereo at 8 kHz
+        let content = wav_bytes(8_000, 2, 32_000);
+        let meta = extract_metadata("a.wav", &content).expect("media file");
+        assert_eq!(meta.codec.as_deref(), Some("pcm"));
+        assert_eq!(meta.sample_rate, Some(8_000));
+        assert_eq!(meta.channels, Some(2));
+        assert_eq!(meta.duration_seconds, Some(1.0));
+    }
+
+    #[test]
+    fn test_lfs_pointer_reports_real_size() {
+        let pointer = b"version https://git-lfs.github.com/spec/v1\n\
+oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+size 12345678\n";
+        let meta = extract_metadata("music/theme.ogg", pointer).expect("media file");
+        assert!(meta.is_lfs_pointer);
+        assert_eq!(meta.file_size, 12_345_678);
+        assert!(meta.duration_seconds.is_none());
+    }
+
+    #[test]
+    fn test_unknown_content_still_reports_size() {
+        let meta = extract_metadata("clip.mkv", &[0u8; 10]).expect("media file");
+        assert_eq!(meta.media_type, "video");
+        assert_eq!(meta.file_size, 10);
+        assert!(meta.codec.is_none());
+    }
+}
@@ -1,3 +1,32 @@
+pub mod actions;
+pub mod cache;
+pub mod capabilities;
 pub mod commands;
+pub mod commit_lint;
+pub mod conflicts;
+pub mod encoding;
+pub mod environment;
+pub mod flow_finish;
+pub mod fuzzy;
+pub mod history_export;
+pub mod hook_frameworks;
+pub mod index_flags;
+pub mod issue_keys;
+pub mod known_hosts;
+pub mod media;
+pub mod network;
+pub mod notebook;
+pub mod onboarding;
+pub mod operation_history;
+pub mod progress;
+pub mod refresh;
+pub mod remote_tokens;
 pub mod repository;
+pub mod scratch;
+pub mod stack;
+pub mod status_tree;
+#[cfg(test)]
+mod test_support;
+pub mod trace;
 pub mod validation;
+pub mod verification;
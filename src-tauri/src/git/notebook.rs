@@ -0,0 +1,85 @@
+//! Built-in text conversion for Jupyter notebooks (`.ipynb`).
+//!
+//! Raw notebook JSON is unreadable in a line diff: every cell source is a JSON
+//! array of strings and outputs (images, tracebacks, execution counts) change
+//! on every run. The converter renders only the cell sources, one after the
+//! other, separated by `# %%` markers (the jupytext "percent" format), so the
+//! diff panel shows cell-by-cell source changes.
+
+use serde_json::Value;
+
+/// Render a notebook as plain text, dropping outputs and execution counts.
+/// Returns None if the content is not a valid notebook.
+pub fn notebook_to_text(content: &[u8]) -> Option<String> {
+    let notebook: Value = serde_json::from_slice(content).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+
+    let mut text = String::new();
+    for cell in cells {
+        let cell_type = cell
+            .get("cell_type")
+            .and_then(Value::as_str)
+            .unwrap_or("code");
+
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        match cell_type {
+            "code" => text.push_str("# %%\n"),
+            other => text.push_str(&format!("# %% [{}]\n", other)),
+        }
+
+        let source = cell_source(cell);
+        text.push_str(&source);
+        if !source.is_empty() && !source.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+
+    Some(text)
+}
+
+/// Cell sources are either a single string or an array of lines
+fn cell_source(cell: &Value) -> String {
+    match cell.get("source") {
+        Some(Value::String(source)) => source.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notebook_outputs_are_stripped() {
+        let notebook = br##"{
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": ["# Title\n", "Intro"]},
+                {
+                    "cell_type": "code",
+                    "execution_count": 42,
+                    "metadata": {},
+                    "outputs": [{"output_type": "stream", "name": "stdout", "text": ["hi\n"]}],
+                    "source": "print('hi')"
+                }
+            ],
+            "metadata": {},
+            "nbformat": 4,
+            "nbformat_minor": 5
+        }"##;
+
+        let text = notebook_to_text(notebook).expect("valid notebook");
+        assert_eq!(
+            text,
+            "# %% [markdown]\n# Title\nIntro\n\n# %%\nprint('hi')\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_notebook_returns_none() {
+        assert!(notebook_to_text(b"not json").is_none());
+        assert!(notebook_to_text(b"{\"metadata\": {}}").is_none());
+    }
+}
@@ -0,0 +1,494 @@
+//! History of the operations performed through Forky in a repository.
+//!
+//! Commits, merges, rebases, resets, branch deletions and stash drops are
+//! recorded with the references they moved (old and new ids), where HEAD
+//! pointed and the tree of the index before the operation, so the Activity
+//! panel can list them and undo_last_operation can put the repository back.
+//! The history is a JSON lines file in the git dir, next to git's own state,
+//! and keeps the latest `MAX_ENTRIES` operations.
+
+use crate::git::environment::git_command;
+use crate::git::network::RefUpdate;
+use crate::git::repository::{create_success_result, GitOperationResult};
+use crate::json_store;
+use crate::locks::LockExt;
+use git2::{Oid, Repository, RepositoryState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// File (in the git dir) holding the history
+pub const HISTORY_FILE: &str = "forky-operations.jsonl";
+
+/// Operations kept per repository
+const MAX_ENTRIES: usize = 500;
+
+/// Serializes writes to history files, and undos from reading the record to
+/// marking it undone
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Commit,
+    Merge,
+    Rebase,
+    DeleteBranch,
+    CherryPick,
+    Revert,
+    Reset,
+    StashDrop,
+}
+
+/// Stash entry removed by a stash drop, enough to store it again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroppedStash {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationRecord {
+    pub kind: OperationKind,
+    pub summary: String,      // e.g. "Merge feature/login into main"
+    pub timestamp: i64,       // unix timestamp (seconds)
+    pub head: Option<String>, // commit HEAD pointed to afterwards
+    pub refs: Vec<RefUpdate>, // local branches (and a detached HEAD) it moved
+    #[serde(default)]
+    pub head_ref: Option<String>, // branch HEAD was on before, None when detached
+    #[serde(default)]
+    pub index_tree: Option<String>, // tree of the index before, None when it had conflicts
+    #[serde(default)]
+    pub stash: Option<DroppedStash>,
+    #[serde(default)]
+    pub undone: bool,
+}
+
+fn history_path(repo: &Repository) -> PathBuf {
+    repo.path().join(HISTORY_FILE)
+}
+
+/// Ids of the local branches, and of HEAD, to compare before and after an
+/// operation
+pub fn snapshot_refs(repo: &Repository) -> BTreeMap<String, String> {
+    let mut refs = BTreeMap::new();
+    if let Ok(references) = repo.references_glob("refs/heads/*") {
+        for reference in references.flatten() {
+            if let (Some(name), Some(id)) = (reference.name(), reference.target()) {
+                refs.insert(name.to_string(), id.to_string());
+            }
+        }
+    }
+    if let Ok(head) = repo.head() {
+        if let Some(id) = head.target() {
+            refs.insert("HEAD".to_string(), id.to_string());
+        }
+    }
+    refs
+}
+
+/// Branch HEAD points to, None when it is detached
+fn head_ref(repo: &Repository) -> Option<String> {
+    repo.find_reference("HEAD")
+        .ok()
+        .and_then(|head| head.symbolic_target().map(String::from))
+}
+
+/// Tree of the current index, written to the object database so the staged
+/// state can be restored; None when the index has conflicts
+fn index_tree(repo: &Repository) -> Option<String> {
+    let mut index = repo.index().ok()?;
+    index.read(false).ok()?;
+    index.write_tree().ok().map(|tree| tree.to_string())
+}
+
+/// References that differ between two snapshots. HEAD is only listed when it
+/// is detached, since otherwise its branch already is.
+pub fn changed_refs(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+    head_detached: bool,
+) -> Vec<RefUpdate> {
+    let names: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    names
+        .into_iter()
+        .filter(|name| head_detached || name.as_str() != "HEAD")
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| RefUpdate {
+            name: name.clone(),
+            old_id: before.get(name).cloned(),
+            new_id: after.get(name).cloned(),
+        })
+        .collect()
+}
+
+/// Replace the whole history with `records`
+fn write_records(repo: &Repository, records: &[OperationRecord]) -> Result<(), String> {
+    let path = history_path(repo);
+    let mut content = String::new();
+    for record in records {
+        content.push_str(
+            &serde_json::to_string(record)
+                .map_err(|e| format!("Failed to serialize operation: {}", e))?,
+        );
+        content.push('\n');
+    }
+    json_store::write_content(&path, &content)
+}
+
+/// Append an operation to the repository's history
+pub fn append(repo: &Repository, record: &OperationRecord) -> Result<(), String> {
+    let _guard = WRITE_LOCK.lock_recover("operation history");
+    let path = history_path(repo);
+    let line = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize operation: {}", e))?;
+
+    let mut records = read_records(repo)?;
+    if records.len() >= MAX_ENTRIES {
+        // Rewrite without the oldest entries rather than growing forever
+        records.drain(..records.len() + 1 - MAX_ENTRIES);
+        records.push(record.clone());
+        return write_records(repo, &records);
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// All recorded operations, oldest first; lines that do not parse are skipped
+fn read_records(repo: &Repository) -> Result<Vec<OperationRecord>, String> {
+    let path = history_path(repo);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// The latest `limit` operations, newest first
+pub fn get_operation_history(
+    repo: &Repository,
+    limit: usize,
+) -> Result<Vec<OperationRecord>, String> {
+    let mut records = read_records(repo)?;
+    records.reverse();
+    records.truncate(limit);
+    Ok(records)
+}
+
+/// Whether the history holds an operation that was not undone yet
+pub fn has_undoable_operation(repo: &Repository) -> bool {
+    read_records(repo).is_ok_and(|records| records.iter().any(|record| !record.undone))
+}
+
+/// Run an operation on the repository at `repo_path` and, when it succeeds and
+/// moved any reference, record it. A history that cannot be written does not
+/// fail the operation.
+pub fn record(
+    repo_path: &str,
+    kind: OperationKind,
+    summary: String,
+    run: impl FnOnce() -> Result<GitOperationResult, String>,
+) -> Result<GitOperationResult, String> {
+    let before = Repository::open(repo_path)
+        .map(|repo| (snapshot_refs(&repo), head_ref(&repo), index_tree(&repo)))
+        .ok();
+    let result = run()?;
+    let (Some((before, head_ref, index_tree)), true) = (before, result.success) else {
+        return Ok(result);
+    };
+
+    if let Ok(repo) = Repository::open(repo_path) {
+        let after = snapshot_refs(&repo);
+        let refs = changed_refs(&before, &after, repo.head_detached().unwrap_or(false));
+        if !refs.is_empty() {
+            let record = OperationRecord {
+                kind,
+                summary,
+                timestamp: chrono::Utc::now().timestamp(),
+                head: after.get("HEAD").cloned(),
+                refs,
+                head_ref,
+                index_tree,
+                stash: None,
+                undone: false,
+            };
+            if let Err(e) = append(&repo, &record) {
+                eprintln!("Failed to record operation: {}", e);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Run a drop of `stash@{stash_index}` and, when it succeeds, record the
+/// dropped entry so it can be stored again
+pub fn record_stash_drop(
+    repo_path: &str,
+    stash_index: usize,
+    run: impl FnOnce() -> Result<GitOperationResult, String>,
+) -> Result<GitOperationResult, String> {
+    let stash = Repository::open(repo_path).ok().and_then(|repo| {
+        let reflog = repo.reflog("refs/stash").ok()?;
+        let entry = reflog.get(stash_index)?;
+        Some(DroppedStash {
+            id: entry.id_new().to_string(),
+            message: entry.message().unwrap_or("").to_string(),
+        })
+    });
+    let result = run()?;
+    let (Some(stash), true) = (stash, result.success) else {
+        return Ok(result);
+    };
+
+    if let Ok(repo) = Repository::open(repo_path) {
+        let record = OperationRecord {
+            kind: OperationKind::StashDrop,
+            summary: format!("Drop stash@{{{}}}: {}", stash_index, stash.message),
+            timestamp: chrono::Utc::now().timestamp(),
+            head: snapshot_refs(&repo).remove("HEAD"),
+            refs: Vec::new(),
+            head_ref: None,
+            index_tree: None,
+            stash: Some(stash),
+            undone: false,
+        };
+        if let Err(e) = append(&repo, &record) {
+            eprintln!("Failed to record operation: {}", e);
+        }
+    }
+    Ok(result)
+}
+
+fn parse_oid(id: &str) -> Result<Oid, String> {
+    Oid::from_str(id).map_err(|e| e.message().to_string())
+}
+
+/// Undo the latest operation of the history that was not undone yet: its
+/// references are put back, HEAD goes back to the branch it was on and the
+/// index and the work tree go back to the state before the operation (files
+/// with local changes are left alone, or the undo is refused when it would
+/// overwrite them). A dropped stash is stored again as stash@{0}. Refused
+/// when a reference has moved since, since putting it back would lose what
+/// happened afterwards.
+pub fn undo_last_operation(repo_path: &str) -> Result<GitOperationResult, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.message().to_string())?;
+    if repo.state() != RepositoryState::Clean {
+        return Err(
+            "Finish or abort the merge, rebase or cherry-pick in progress before undoing"
+                .to_string(),
+        );
+    }
+    // Held until the record is marked, so two undos cannot both pick the
+    // same record and apply it twice
+    let _guard = WRITE_LOCK.lock_recover("operation history");
+    let record = read_records(&repo)?
+        .into_iter()
+        .rev()
+        .find(|record| !record.undone)
+        .ok_or("There is no operation to undo")?;
+
+    let current = snapshot_refs(&repo);
+    for update in &record.refs {
+        if current.get(&update.name) != update.new_id.as_ref() {
+            let name = update
+                .name
+                .strip_prefix("refs/heads/")
+                .unwrap_or(&update.name);
+            return Err(format!(
+                "'{}' cannot be undone: {} has changed since",
+                record.summary, name
+            ));
+        }
+    }
+
+    if let Some(stash) = &record.stash {
+        let output = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .args(["stash", "store", "-m", &stash.message, &stash.id])
+            .output()
+            .map_err(|e| format!("Failed to execute git stash store: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to restore the stash: {}", stderr.trim()));
+        }
+    }
+
+    // Work tree and index first, while HEAD still is the checkout baseline
+    if let Some(tree_id) = &record.index_tree {
+        let tree = repo
+            .find_tree(parse_oid(tree_id)?)
+            .map_err(|e| format!("The index before '{}' is gone: {}", record.summary, e))?;
+        repo.checkout_tree(
+            tree.as_object(),
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )
+        .map_err(|e| {
+            format!(
+                "Undoing would overwrite local changes; commit or stash them first ({})",
+                e.message()
+            )
+        })?;
+        let mut index = repo.index().map_err(|e| e.message().to_string())?;
+        index
+            .read_tree(&tree)
+            .and_then(|_| index.write())
+            .map_err(|e| e.message().to_string())?;
+    }
+
+    let message = format!("forky: undo {}", record.summary);
+    for update in &record.refs {
+        let result = match (&update.old_id, update.name.as_str()) {
+            (Some(old_id), "HEAD") => repo.set_head_detached(parse_oid(old_id)?),
+            (Some(old_id), name) => repo
+                .reference(name, parse_oid(old_id)?, true, &message)
+                .map(|_| ()),
+            (None, name) => repo
+                .find_reference(name)
+                .and_then(|mut reference| reference.delete()),
+        };
+        result.map_err(|e| format!("Failed to restore {}: {}", update.name, e.message()))?;
+    }
+    if let Some(branch) = &record.head_ref {
+        if head_ref(&repo).as_ref() != Some(branch) {
+            repo.set_head(branch)
+                .map_err(|e| format!("Failed to switch back to {}: {}", branch, e.message()))?;
+        }
+    }
+
+    let marked = read_records(&repo).and_then(|mut records| {
+        if let Some(undone) = records.iter_mut().rev().find(|r| **r == record) {
+            undone.undone = true;
+        }
+        write_records(&repo, &records)
+    });
+    if let Err(e) = marked {
+        eprintln!("Failed to record the undo: {}", e);
+    }
+    let restored = if record.stash.is_some() {
+        " (the stash is back as stash@{0})"
+    } else {
+        ""
+    };
+    Ok(create_success_result(format!(
+        "Undid '{}'{}",
+        record.summary, restored
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::commit_index;
+
+    #[test]
+    fn test_record_successful_operations_with_moved_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let repo_path = dir.path().to_string_lossy().to_string();
+        let first = commit_index(&repo, "first");
+        let branch = repo.head().unwrap().name().unwrap().to_string();
+
+        let mut second = None;
+        record(
+            &repo_path,
+            OperationKind::Commit,
+            "second".to_string(),
+            || {
+                second = Some(commit_index(&repo, "second"));
+                Ok(create_success_result(String::new()))
+            },
+        )
+        .unwrap();
+        // Failed operations and ones that move nothing are not recorded
+        record(&repo_path, OperationKind::Merge, "noop".to_string(), || {
+            Ok(create_success_result(String::new()))
+        })
+        .unwrap();
+        record(
+            &repo_path,
+            OperationKind::Commit,
+            "failed".to_string(),
+            || {
+                commit_index(&repo, "failed");
+                Ok(GitOperationResult {
+                    success: false,
+                    ..create_success_result(String::new())
+                })
+            },
+        )
+        .unwrap();
+
+        let history = get_operation_history(&repo, 10).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].kind, OperationKind::Commit);
+        assert_eq!(history[0].head, second.map(|id| id.to_string()));
+        assert_eq!(
+            history[0].refs,
+            vec![RefUpdate {
+                name: branch,
+                old_id: Some(first.to_string()),
+                new_id: second.map(|id| id.to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_undo_last_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let repo_path = dir.path().to_string_lossy().to_string();
+        let first = commit_index(&repo, "first");
+        std::fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("new.txt")).unwrap();
+        index.write().unwrap();
+
+        record(
+            &repo_path,
+            OperationKind::Commit,
+            "second".to_string(),
+            || {
+                commit_index(&repo, "second");
+                Ok(create_success_result(String::new()))
+            },
+        )
+        .unwrap();
+        undo_last_operation(&repo_path).unwrap();
+
+        // The branch is back and the committed file is staged again
+        assert_eq!(repo.head().unwrap().target(), Some(first));
+        let mut index = repo.index().unwrap();
+        index.read(true).unwrap();
+        assert!(index.get_path(std::path::Path::new("new.txt"), 0).is_some());
+        assert!(dir.path().join("new.txt").exists());
+        assert!(get_operation_history(&repo, 10).unwrap()[0].undone);
+        assert!(undo_last_operation(&repo_path).is_err());
+
+        // Not when the branch moved on since the operation
+        record(
+            &repo_path,
+            OperationKind::Commit,
+            "third".to_string(),
+            || {
+                commit_index(&repo, "third");
+                Ok(create_success_result(String::new()))
+            },
+        )
+        .unwrap();
+        let fourth = commit_index(&repo, "fourth");
+        assert!(undo_last_operation(&repo_path).is_err());
+        assert_eq!(repo.head().unwrap().target(), Some(fourth));
+    }
+}
@@ -0,0 +1,155 @@
+//! Parsing of the progress lines git prints on stderr with `--progress`.
+//!
+//! Long-running network commands (clone, fetch, push) report their phases as
+//! lines such as `Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s`,
+//! rewritten in place with `\r`. These helpers split the stream into lines and
+//! turn the known phases into structured progress for the frontend.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitProgress {
+    pub stage: String, // "counting", "compressing", "receiving", "resolving", "checkout", "writing"
+    pub percent: Option<u32>,
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+    pub bytes: Option<u64>, // data transferred so far ("1.20 MiB" while receiving/writing)
+}
+
+const STAGES: &[(&str, &str)] = &[
+    ("Enumerating objects", "counting"),
+    ("Counting objects", "counting"),
+    ("Compressing objects", "compressing"),
+    ("Receiving objects", "receiving"),
+    ("Resolving deltas", "resolving"),
+    ("Updating files", "checkout"),
+    ("Checking out files", "checkout"),
+    ("Writing objects", "writing"),
+];
+
+/// Parse a single progress line; returns None for anything that is not progress
+pub fn parse_progress_line(line: &str) -> Option<GitProgress> {
+    let line = line.trim();
+    let line = line.strip_prefix("remote:").map(str::trim).unwrap_or(line);
+
+    let (label, rest) = line.split_once(':')?;
+    let stage = STAGES
+        .iter()
+        .find(|(name, _)| *name == label.trim())
+        .map(|(_, stage)| stage.to_string())?;
+
+    let rest = rest.trim();
+    let percent = rest
+        .split_once('%')
+        .and_then(|(value, _)| value.trim().parse().ok());
+
+    // "(450/1000)" when a total is known, otherwise just a running count ("1234, done.")
+    let (current, total) = match rest.split_once('(').and_then(|(_, r)| r.split_once(')')) {
+        Some((counts, _)) => match counts.split_once('/') {
+            Some((current, total)) => (current.trim().parse().ok(), total.trim().parse().ok()),
+            None => (counts.trim().parse().ok(), None),
+        },
+        None => (
+            rest.split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|n| n.parse().ok()),
+            None,
+        ),
+    };
+
+    Some(GitProgress {
+        stage,
+        percent,
+        current,
+        total,
+        bytes: parse_bytes(rest),
+    })
+}
+
+/// Parse the transferred size in e.g. "45% (450/1000), 1.20 MiB | 2.00 MiB/s"
+fn parse_bytes(rest: &str) -> Option<u64> {
+    let (_, size) = rest.split_once("),")?;
+    let size = size.split('|').next()?.trim();
+    let (value, unit) = size.split_once(' ')?;
+    let value: f64 = value.parse().ok()?;
+    let multiplier = match unit.trim_end_matches(',') {
+        "bytes" | "byte" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((value * multiplier) as u64)
+}
+
+/// Read a stderr stream, calling `on_line` for every line. Git redraws progress
+/// with `\r`, so both `\r` and `\n` end a line.
+pub fn read_lines<R: Read>(mut reader: R, mut on_line: impl FnMut(&str)) {
+    let mut buffer = [0u8; 4096];
+    let mut line = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        for &byte in &buffer[..read] {
+            if byte == b'\r' || byte == b'\n' {
+                if !line.is_empty() {
+                    on_line(&String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            } else {
+                line.push(byte);
+            }
+        }
+    }
+
+    if !line.is_empty() {
+        on_line(&String::from_utf8_lossy(&line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress_line() {
+        let progress =
+            parse_progress_line("Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s")
+                .expect("progress line");
+        assert_eq!(progress.stage, "receiving");
+        assert_eq!(progress.percent, Some(45));
+        assert_eq!(progress.current, Some(450));
+        assert_eq!(progress.total, Some(1000));
+        assert_eq!(progress.bytes, Some(1_258_291));
+
+        let remote =
+            parse_progress_line("remote: Enumerating objects: 1234, done.").expect("progress line");
+        assert_eq!(remote.stage, "counting");
+        assert_eq!(remote.current, Some(1234));
+        assert_eq!(remote.total, None);
+        assert_eq!(remote.bytes, None);
+
+        assert!(parse_progress_line("Cloning into 'forky'...").is_none());
+        assert!(parse_progress_line("fatal: repository not found").is_none());
+    }
+
+    #[test]
+    fn test_read_lines_splits_carriage_returns() {
+        let input =
+            b"Receiving objects:  10% (1/10)\rReceiving objects: 100% (10/10), done.\nfatal: x";
+        let mut lines = Vec::new();
+        read_lines(&input[..], |line| lines.push(line.to_string()));
+        assert_eq!(
+            lines,
+            vec![
+                "Receiving objects:  10% (1/10)",
+                "Receiving objects: 100% (10/10), done.",
+                "fatal: x"
+            ]
+        );
+    }
+}
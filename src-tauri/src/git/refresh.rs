@@ -0,0 +1,198 @@
+//! One call refreshing everything the main window shows about a repository.
+//!
+//! After each change event the frontend used to ask separately for the
+//! repository info, branches, tags, stashes and status, even when the event
+//! came from a file git ignores. `refresh` returns them together with a
+//! version, and nothing else when the version is the one the frontend already
+//! has. The version is bumped whenever the repository's fingerprint changes:
+//! HEAD, the refs, the branches' upstreams, the index and stash files, the
+//! operation in progress, whether the repository is read-only and the status
+//! of the files, which is read on every call anyway. Watcher
+//! events are not used: they also come for ignored files, and repositories
+//! that are not watched have none.
+
+use crate::git::index_flags::{self, FlaggedFile};
+use crate::git::repository::{self, BranchInfo, FileStatus, RepositoryInfo, StashInfo, TagInfo};
+use crate::git::validation;
+use crate::locks::LockExt;
+use git2::Repository;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoRefresh {
+    pub version: u64,
+    pub changed: bool, // false: same version as `since_version`, the rest is None
+    pub info: Option<RepositoryInfo>,
+    pub branches: Option<Vec<BranchInfo>>,
+    pub tags: Option<Vec<TagInfo>>,
+    pub stashes: Option<Vec<StashInfo>>,
+    pub unstaged: Option<Vec<FileStatus>>,
+    pub staged: Option<Vec<FileStatus>>,
+    pub flagged: Option<Vec<FlaggedFile>>,
+}
+
+struct Versions {
+    last: u64,
+    repos: HashMap<PathBuf, (u64, u64)>, // canonical path -> (fingerprint, version)
+}
+
+static VERSIONS: Mutex<Option<Versions>> = Mutex::new(None);
+
+/// Modification time and size of a file in the git dir
+fn hash_file(hasher: &mut DefaultHasher, path: &Path) {
+    let metadata = std::fs::metadata(path).ok();
+    metadata.as_ref().map(|m| m.len()).hash(hasher);
+    metadata.and_then(|m| m.modified().ok()).hash(hasher);
+}
+
+/// Hash of what the refreshed data depends on, the file status being given
+fn fingerprint(repo: &Repository, status: &[&[FileStatus]]) -> Result<u64, String> {
+    let mut hasher = DefaultHasher::new();
+
+    let head = repo.find_reference("HEAD").ok();
+    head.as_ref()
+        .and_then(|head| head.symbolic_target().map(String::from))
+        .hash(&mut hasher);
+    repo.head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|oid| oid.to_string())
+        .hash(&mut hasher);
+
+    let references = repo.references().map_err(|e| e.message().to_string())?;
+    for reference in references.flatten() {
+        reference.name_bytes().hash(&mut hasher);
+        reference
+            .target()
+            .map(|oid| oid.to_string())
+            .hash(&mut hasher);
+    }
+
+    // Upstreams show in the branch list (tracking and ahead/behind counts)
+    let config = repo.config().map_err(|e| e.message().to_string())?;
+    let tracking = config
+        .entries(Some(r"^branch\..*\.(remote|merge)$"))
+        .map_err(|e| e.message().to_string())?;
+    tracking
+        .for_each(|entry| {
+            (entry.name_bytes(), entry.value_bytes()).hash(&mut hasher);
+        })
+        .map_err(|e| e.message().to_string())?;
+
+    // The stash reflog changes on drop even when refs/stash does not
+    for file in ["index", "logs/refs/stash"] {
+        hash_file(&mut hasher, &repo.path().join(file));
+    }
+    (repo.state() as i32).hash(&mut hasher);
+    validation::is_read_only(repo).hash(&mut hasher);
+    for file in status.iter().flat_map(|files| files.iter()) {
+        (&file.path, &file.status, file.staged, &file.old_path).hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Version of the repository at `canonical`, bumped when its fingerprint
+/// changed since the last call. Versions only grow, across repositories too.
+fn version(repo: &Repository, canonical: &Path, status: &[&[FileStatus]]) -> Result<u64, String> {
+    let fingerprint = fingerprint(repo, status)?;
+    let mut versions = VERSIONS.lock_recover("repository versions");
+    let versions = versions.get_or_insert_with(|| Versions {
+        last: 0,
+        repos: HashMap::new(),
+    });
+    match versions.repos.get(canonical) {
+        Some((known, version)) if *known == fingerprint => Ok(*version),
+        _ => {
+            versions.last += 1;
+            let version = versions.last;
+            versions
+                .repos
+                .insert(canonical.to_path_buf(), (fingerprint, version));
+            Ok(version)
+        }
+    }
+}
+
+/// Repository info, branches, tags, stashes and status of the repository at
+/// `canonical`, unless its version is still `since_version`
+pub fn refresh(
+    repo: &Repository,
+    canonical: &Path,
+    since_version: Option<u64>,
+) -> Result<RepoRefresh, String> {
+    let (unstaged, staged) = repository::get_file_status_separated(repo)?;
+    let version = version(repo, canonical, &[&unstaged, &staged])?;
+    if since_version == Some(version) {
+        return Ok(RepoRefresh {
+            version,
+            changed: false,
+            info: None,
+            branches: None,
+            tags: None,
+            stashes: None,
+            unstaged: None,
+            staged: None,
+            flagged: None,
+        });
+    }
+
+    Ok(RepoRefresh {
+        version,
+        changed: true,
+        info: Some(repository::get_repository_info(repo)?),
+        branches: Some(repository::get_branches(repo)?),
+        tags: Some(repository::get_tags(repo)?),
+        stashes: Some(repository::get_stashes(&canonical.to_string_lossy())?),
+        unstaged: Some(unstaged),
+        staged: Some(staged),
+        flagged: Some(index_flags::get_flagged_files(repo)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_changes_with_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let canonical = dunce::canonicalize(dir.path()).unwrap();
+
+        let first = refresh(&repo, &canonical, None).unwrap();
+        assert!(first.changed);
+        let same = refresh(&repo, &canonical, Some(first.version)).unwrap();
+        assert_eq!(same.version, first.version);
+        assert!(!same.changed && same.branches.is_none());
+
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+            .unwrap();
+        let after = refresh(&repo, &canonical, Some(first.version)).unwrap();
+        assert!(after.version > first.version);
+        assert!(after.changed);
+        assert_eq!(after.branches.unwrap().len(), 1);
+
+        // A new upstream changes the branch list, though no ref moved
+        let mut config = repo.config().unwrap();
+        config.set_str("branch.main.remote", "origin").unwrap();
+        config
+            .set_str("branch.main.merge", "refs/heads/main")
+            .unwrap();
+        let tracked = refresh(&repo, &canonical, Some(after.version)).unwrap();
+        assert!(tracked.changed);
+
+        // A new file in a repository nobody watches
+        std::fs::write(dir.path().join("new.txt"), "new").unwrap();
+        let edited = refresh(&repo, &canonical, Some(tracked.version)).unwrap();
+        assert!(edited.changed);
+        assert_eq!(edited.unstaged.unwrap().len(), 1);
+    }
+}
@@ -1,6 +1,24 @@
+use crate::git::actions::ActionContext;
+use crate::git::capabilities::{self, RestoreSource};
+use crate::git::conflicts::{self, ConflictKind};
+use crate::git::encoding;
+use crate::git::environment::{self, git_command};
+use crate::git::flow_finish;
+use crate::git::fuzzy;
+use crate::git::index_flags;
+use crate::git::media::{self, MediaMetadata};
+use crate::git::notebook;
+use crate::git::operation_history;
+use crate::git::progress::{self, GitProgress};
+use crate::git::scratch::ScratchFile;
+use crate::git::validation;
+use crate::locks::LockExt;
 use chrono::{DateTime, TimeZone, Utc};
-use git2::{BranchType, Repository, StatusOptions};
+use git2::{BranchType, Repository, Status, StatusOptions};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CommitInfo {
@@ -21,6 +39,7 @@ pub struct BranchInfo {
     pub upstream: Option<String>,
     pub ahead: Option<u32>,
     pub behind: Option<u32>,
+    pub ahead_behind_approximate: bool, // counted in a shallow history that misses the fork point
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,8 +67,12 @@ pub struct StashInfo {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileStatus {
     pub path: String,
-    pub status: String,
+    pub status: String, // "conflicted" for unmerged paths, with their `conflict`
     pub staged: bool,
+    #[serde(default)]
+    pub conflict: Option<ConflictKind>,
+    #[serde(default)]
+    pub old_path: Option<String>, // path before a staged rename, set when status is "renamed"
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -59,6 +82,7 @@ pub struct RepositoryInfo {
     pub current_branch: Option<String>,
     pub is_bare: bool,
     pub is_empty: bool,
+    pub read_only: bool, // read-only media or no write permission; changes are refused
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -68,8 +92,36 @@ pub struct DiffInfo {
     pub new_content: Option<String>,
     pub hunks: Vec<DiffHunk>,
     pub is_binary: bool,
-    pub binary_type: Option<String>, // "image", "other"
+    pub binary_type: Option<String>, // "image", "pdf", "audio", "video", "other"
     pub file_size: Option<u64>,
+    pub diff_driver: Option<String>, // set when a textconv driver produced the diff
+    pub page: Option<DiffPage>,      // set when only some lines of a new file are included
+    pub encoding: Option<String>,    // set when the text was converted to UTF-8 from this encoding
+    #[serde(default)]
+    pub old_mode: Option<u32>, // old and new file mode, set when the mode changed
+    #[serde(default)]
+    pub new_mode: Option<u32>,
+    #[serde(default)]
+    pub old_path: Option<String>, // path before a rename, set for renamed files
+}
+
+/// Lines of a new file included in a DiffInfo page by default
+pub const NEW_FILE_PAGE_LINES: usize = 2000;
+
+/// Lines requested from a new file (0-based first line and number of lines)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct LineRange {
+    pub start: usize,
+    pub count: usize,
+}
+
+/// Part of a new file a paged DiffInfo holds; its hunk covers lines
+/// `start + 1 ..= start + count`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffPage {
+    pub start: usize,
+    pub count: usize,
+    pub total_lines: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -87,6 +139,7 @@ pub struct DiffLine {
     pub line_type: String, // "add", "delete", "context"
     pub old_line_no: Option<u32>,
     pub new_line_no: Option<u32>,
+    pub eol_only: bool, // changed only in line endings / trailing whitespace
 }
 
 // Git Flow types
@@ -111,18 +164,45 @@ pub enum GitFlowBranchType {
     Other,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GitFlowIssueKind {
+    MissingMasterBranch,
+    MissingDevelopBranch,
+    SameMasterAndDevelop,
+    EmptyPrefix,
+    PrefixCollision,
+}
+
+/// A problem with the git flow configuration found by validate_gitflow_config
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GitFlowIssue {
+    pub kind: GitFlowIssueKind,
+    pub message: String,
+    pub key: String,                     // gitflow.* key at fault
+    pub suggested_value: Option<String>, // what repair_gitflow_config writes; None when it cannot be fixed automatically
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CurrentBranchFlowInfo {
     pub branch_type: GitFlowBranchType,
     pub name: String, // nombre sin prefijo (ej: "my-feature" de "feature/my-feature")
 }
 
+/// Open the repository containing `path`, which may be the root of the working
+/// tree or any folder inside it
 pub fn open_repository(path: &str) -> Result<Repository, String> {
-    Repository::open(path).map_err(|e| e.message().to_string())
+    Repository::discover(path).map_err(|e| e.message().to_string())
 }
 
 pub fn get_repository_info(repo: &Repository) -> Result<RepositoryInfo, String> {
-    let path = repo.path().parent().unwrap_or(repo.path());
+    // The working tree root (also right for linked worktrees, whose git dir lives
+    // under the main repository's .git/worktrees); rebuilt from its components to
+    // drop the trailing separator libgit2 adds
+    let path: std::path::PathBuf = match repo.workdir() {
+        Some(workdir) => workdir.components().collect(),
+        None => repo.path().parent().unwrap_or(repo.path()).to_path_buf(),
+    };
     let name = path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -139,28 +219,319 @@ pub fn get_repository_info(repo: &Repository) -> Result<RepositoryInfo, String>
         current_branch,
         is_bare: repo.is_bare(),
         is_empty: repo.is_empty().unwrap_or(true),
+        read_only: validation::is_read_only(repo),
+    })
+}
+
+/// Special heads written by git during merges, rebases, cherry-picks and reverts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepositoryState {
+    pub state: String, // "clean", "merge", "rebase", "rebase_interactive", "cherry_pick", ...
+    pub head: Option<String>,
+    pub orig_head: Option<String>,
+    pub merge_heads: Vec<String>, // more than one for octopus merges
+    pub rebase_head: Option<String>,
+    pub rebase_onto: Option<String>,
+    pub cherry_pick_head: Option<String>,
+    pub revert_head: Option<String>,
+    pub rebase_branch: Option<String>, // branch being rebased, None when it was detached
+    pub rebase_step: Option<u32>,      // 1-based number of the commit being applied
+    pub rebase_total: Option<u32>,
+    pub sequence_remaining: Option<usize>, // commits left, current included, of a cherry-pick/revert
+    pub bisect_bad: Option<String>,
+    pub bisect_good: Vec<String>,
+}
+
+/// Trimmed content of a file in the .git directory
+fn read_git_dir_text(repo: &Repository, file: &str) -> Option<String> {
+    std::fs::read_to_string(repo.path().join(file))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+/// Read a file in the .git directory that holds one commit id per line
+fn read_git_dir_oids(repo: &Repository, file: &str) -> Vec<String> {
+    std::fs::read_to_string(repo.path().join(file))
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| line.split_whitespace().next())
+                .filter(|oid| git2::Oid::from_str(oid).is_ok())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Current operation state plus the ORIG_HEAD/MERGE_HEAD/REBASE_HEAD commits, so the
+/// graph can tell the "current" and "incoming" sides apart, and the progress of a
+/// rebase, cherry-pick sequence or bisect, read from the state files git leaves in
+/// .git (so an operation started before a restart is still shown)
+pub fn get_repository_state(repo: &Repository) -> Result<RepositoryState, String> {
+    use git2::RepositoryState as State;
+
+    let state = match repo.state() {
+        State::Clean => "clean",
+        State::Merge => "merge",
+        State::Revert | State::RevertSequence => "revert",
+        State::CherryPick | State::CherryPickSequence => "cherry_pick",
+        State::Bisect => "bisect",
+        State::Rebase | State::RebaseMerge => "rebase",
+        State::RebaseInteractive => "rebase_interactive",
+        State::ApplyMailbox | State::ApplyMailboxOrRebase => "apply_mailbox",
+    };
+
+    let first = |file: &str| read_git_dir_oids(repo, file).into_iter().next();
+    let rebase_onto = first("rebase-merge/onto").or_else(|| first("rebase-apply/onto"));
+
+    // Interactive and merge rebases keep their state in rebase-merge, patch
+    // based ones (and `git am`) in rebase-apply, with other file names
+    let rebase_file = |merge_file: &str, apply_file: &str| {
+        read_git_dir_text(repo, &format!("rebase-merge/{}", merge_file))
+            .or_else(|| read_git_dir_text(repo, &format!("rebase-apply/{}", apply_file)))
+    };
+    let rebase_branch = rebase_file("head-name", "head-name")
+        .filter(|name| name != "detached HEAD")
+        .map(|name| {
+            name.strip_prefix("refs/heads/")
+                .map(String::from)
+                .unwrap_or(name)
+        });
+    let rebase_step = rebase_file("msgnum", "next").and_then(|n| n.parse().ok());
+    let rebase_total = rebase_file("end", "last").and_then(|n| n.parse().ok());
+
+    let sequence_remaining = read_git_dir_text(repo, "sequencer/todo").map(|todo| {
+        todo.lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .count()
+    });
+
+    let bisect_ref = |name: &str| {
+        repo.find_reference(name)
+            .ok()
+            .and_then(|reference| reference.target())
+            .map(|oid| oid.to_string())
+    };
+    let bisect_good = repo
+        .references_glob("refs/bisect/good-*")
+        .map(|references| {
+            references
+                .flatten()
+                .filter_map(|reference| reference.target())
+                .map(|oid| oid.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(RepositoryState {
+        state: state.to_string(),
+        head: repo
+            .head()
+            .ok()
+            .and_then(|head| head.target())
+            .map(|oid| oid.to_string()),
+        orig_head: first("ORIG_HEAD"),
+        merge_heads: read_git_dir_oids(repo, "MERGE_HEAD"),
+        rebase_head: first("REBASE_HEAD"),
+        rebase_onto,
+        cherry_pick_head: first("CHERRY_PICK_HEAD"),
+        revert_head: first("REVERT_HEAD"),
+        rebase_branch,
+        rebase_step,
+        rebase_total,
+        sequence_remaining,
+        bisect_bad: bisect_ref("refs/bisect/bad"),
+        bisect_good,
+    })
+}
+
+/// At-a-glance state of a repository for the workspace dashboard
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepositorySummary {
+    pub path: String,
+    pub name: String,
+    pub branch: Option<String>, // None when HEAD is detached or unborn
+    pub upstream: Option<String>,
+    pub changed_files: usize, // staged, unstaged and untracked
+    pub conflicted_files: usize,
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+    pub ahead_behind_approximate: bool,
+    pub state: String,         // same values as RepositoryState::state
+    pub error: Option<String>, // set when the repository could not be read
+}
+
+impl RepositorySummary {
+    /// Summary for a path that could not be opened or read
+    pub fn failed(path: &str, error: String) -> Self {
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+
+        RepositorySummary {
+            path: path.to_string(),
+            name,
+            branch: None,
+            upstream: None,
+            changed_files: 0,
+            conflicted_files: 0,
+            ahead: None,
+            behind: None,
+            ahead_behind_approximate: false,
+            state: "clean".to_string(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Branch, change counts and ahead/behind for one repository
+pub fn get_repository_summary(repo: &Repository) -> Result<RepositorySummary, String> {
+    let info = get_repository_info(repo)?;
+    let state = get_repository_state(repo)?.state;
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false);
+    apply_focused_paths(repo, &mut opts);
+
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    let conflicted_files = statuses
+        .iter()
+        .filter(|entry| entry.status().is_conflicted())
+        .count();
+
+    let mut branch_name = None;
+    let mut upstream = None;
+    let (mut ahead, mut behind, mut ahead_behind_approximate) = (None, None, false);
+    if let Ok(head) = repo.head() {
+        if head.is_branch() {
+            let branch = git2::Branch::wrap(head);
+            branch_name = branch.name().ok().flatten().map(String::from);
+            if let Ok(upstream_branch) = branch.upstream() {
+                upstream = upstream_branch.name().ok().flatten().map(String::from);
+                (ahead, behind, ahead_behind_approximate) =
+                    calculate_ahead_behind(repo, &branch, &upstream_branch);
+            }
+        }
+    }
+
+    Ok(RepositorySummary {
+        path: info.path,
+        name: info.name,
+        branch: branch_name,
+        upstream,
+        changed_files: statuses.len(),
+        conflicted_files,
+        ahead,
+        behind,
+        ahead_behind_approximate,
+        state,
+        error: None,
+    })
+}
+
+/// Read the facts the command palette needs to decide which actions apply
+pub fn get_action_context(repo: &Repository) -> Result<ActionContext, String> {
+    let state = get_repository_state(repo)?.state;
+
+    let head = repo.head().ok();
+    let has_commits = head.as_ref().is_some_and(|head| head.target().is_some());
+    let on_branch = head.as_ref().is_some_and(|head| head.is_branch());
+    let has_upstream = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .is_some_and(|head| git2::Branch::wrap(head).upstream().is_ok());
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    let index_changes = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    let has_staged_changes = statuses
+        .iter()
+        .any(|entry| entry.status().intersects(index_changes));
+
+    let on_gitflow_branch = on_branch
+        && get_current_branch_flow_info(repo).is_ok_and(|info| {
+            matches!(
+                info.branch_type,
+                GitFlowBranchType::Feature | GitFlowBranchType::Release | GitFlowBranchType::Hotfix
+            )
+        });
+
+    Ok(ActionContext {
+        state,
+        has_commits,
+        on_branch,
+        has_remotes: repo.remotes().is_ok_and(|remotes| !remotes.is_empty()),
+        has_upstream,
+        has_changes: !statuses.is_empty(),
+        has_staged_changes,
+        has_stashes: repo.find_reference("refs/stash").is_ok(),
+        has_previous_branch: get_previous_branch(repo).ok().flatten().is_some(),
+        gitflow_initialized: get_gitflow_config(repo).is_ok_and(|config| config.initialized),
+        on_gitflow_branch,
+        flow_finish_in_progress: flow_finish::get_flow_finish_state(repo).is_some(),
+        has_undoable_operation: operation_history::has_undoable_operation(repo),
     })
 }
 
-/// Helper function to calculate ahead/behind counts for a branch relative to its upstream
+/// Commits whose parents are cut off from the history: the boundary of a
+/// shallow clone (`.git/shallow`) and commits given other parents in the
+/// legacy `info/grafts` file
+pub fn grafted_commits(repo: &Repository) -> HashSet<git2::Oid> {
+    let mut commits = HashSet::new();
+    for file in [repo.path().join("shallow"), repo.path().join("info/grafts")] {
+        let Ok(content) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        commits.extend(
+            content
+                .lines()
+                .filter_map(|line| line.split_whitespace().next())
+                .filter_map(|sha| git2::Oid::from_str(sha).ok()),
+        );
+    }
+    commits
+}
+
+/// Helper function to calculate ahead/behind counts for a branch relative to its upstream.
+/// The third value is true when the counts are approximate: in a shallow or
+/// grafted history without the commit the two branches forked from, every
+/// commit down to the cut counts as ahead or behind.
 fn calculate_ahead_behind(
     repo: &Repository,
     local_branch: &git2::Branch,
     upstream_branch: &git2::Branch,
-) -> (Option<u32>, Option<u32>) {
+) -> (Option<u32>, Option<u32>, bool) {
     let local_oid = match local_branch.get().peel_to_commit() {
         Ok(commit) => commit.id(),
-        Err(_) => return (None, None),
+        Err(_) => return (None, None, false),
     };
 
     let upstream_oid = match upstream_branch.get().peel_to_commit() {
         Ok(commit) => commit.id(),
-        Err(_) => return (None, None),
+        Err(_) => return (None, None, false),
     };
 
+    let approximate = (repo.is_shallow() || repo.path().join("info/grafts").exists())
+        && repo.merge_base(local_oid, upstream_oid).is_err();
     match repo.graph_ahead_behind(local_oid, upstream_oid) {
-        Ok((ahead, behind)) => (Some(ahead as u32), Some(behind as u32)),
-        Err(_) => (None, None),
+        Ok((ahead, behind)) => (Some(ahead as u32), Some(behind as u32), approximate),
+        Err(_) => (None, None, approximate),
     }
 }
 
@@ -176,16 +547,16 @@ pub fn get_branches(repo: &Repository) -> Result<Vec<BranchInfo>, String> {
                 let is_head = branch.is_head();
 
                 // Get upstream and calculate ahead/behind
-                let (upstream, ahead, behind) = match branch.upstream() {
+                let (upstream, ahead, behind, ahead_behind_approximate) = match branch.upstream() {
                     Ok(upstream_branch) => {
                         let upstream_name =
                             upstream_branch.name().ok().flatten().map(|s| s.to_string());
 
-                        let (ahead, behind) =
+                        let (ahead, behind, approximate) =
                             calculate_ahead_behind(repo, &branch, &upstream_branch);
-                        (upstream_name, ahead, behind)
+                        (upstream_name, ahead, behind, approximate)
                     }
-                    Err(_) => (None, None, None),
+                    Err(_) => (None, None, None, false),
                 };
 
                 branches.push(BranchInfo {
@@ -195,6 +566,7 @@ pub fn get_branches(repo: &Repository) -> Result<Vec<BranchInfo>, String> {
                     upstream,
                     ahead,
                     behind,
+                    ahead_behind_approximate,
                 });
             }
         }
@@ -213,6 +585,7 @@ pub fn get_branches(repo: &Repository) -> Result<Vec<BranchInfo>, String> {
                     upstream: None,
                     ahead: None,
                     behind: None,
+                    ahead_behind_approximate: false,
                 });
             }
         }
@@ -245,2261 +618,5625 @@ pub fn get_branch_heads(repo: &Repository) -> Result<Vec<BranchHead>, String> {
     Ok(heads)
 }
 
-pub fn get_commits(repo: &Repository, limit: usize) -> Result<Vec<CommitInfo>, String> {
-    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    Head, // HEAD itself, listed when it is detached
+    LocalBranch,
+    RemoteBranch,
+    Tag,
+    Stash,
+}
 
-    // Push all local branches to include all commits in the graph
-    let mut has_branches = false;
-    if let Ok(local_branches) = repo.branches(Some(BranchType::Local)) {
-        for branch in local_branches.flatten() {
-            let (branch, _) = branch;
-            if let Ok(reference) = branch.get().peel_to_commit() {
-                let _ = revwalk.push(reference.id());
-                has_branches = true;
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefDecoration {
+    pub name: String, // short name: "main", "origin/main", "v1.0", "stash@{0}"
+    pub kind: RefKind,
+    pub is_head: bool, // the local branch HEAD points to, or the detached HEAD
+}
+
+/// Refs pointing at each commit, keyed by commit SHA, so the commit list can
+/// draw its branch and tag chips from a single call. Each commit's refs are
+/// ordered HEAD, local branches, remote branches, tags, stashes.
+pub fn get_ref_decorations(
+    repo: &Repository,
+) -> Result<HashMap<String, Vec<RefDecoration>>, String> {
+    let mut decorations: HashMap<String, Vec<RefDecoration>> = HashMap::new();
+    let mut add = |oid: git2::Oid, name: String, kind: RefKind, is_head: bool| {
+        decorations
+            .entry(oid.to_string())
+            .or_default()
+            .push(RefDecoration {
+                name,
+                kind,
+                is_head,
+            });
+    };
+
+    if repo.head_detached().unwrap_or(false) {
+        if let Ok(commit) = repo.head().and_then(|head| head.peel_to_commit()) {
+            add(commit.id(), "HEAD".to_string(), RefKind::Head, true);
+        }
+    }
+
+    for (branch_type, kind) in [
+        (BranchType::Local, RefKind::LocalBranch),
+        (BranchType::Remote, RefKind::RemoteBranch),
+    ] {
+        let branches = repo
+            .branches(Some(branch_type))
+            .map_err(|e| e.message().to_string())?;
+        for (branch, _) in branches.flatten() {
+            // Symbolic refs such as origin/HEAD only repeat another branch
+            if branch.get().kind() == Some(git2::ReferenceType::Symbolic) {
+                continue;
             }
+            let (Ok(Some(name)), Ok(commit)) = (branch.name(), branch.get().peel_to_commit())
+            else {
+                continue;
+            };
+            add(commit.id(), name.to_string(), kind, branch.is_head());
         }
     }
 
-    // Fallback to HEAD if no branches were pushed
-    if !has_branches {
-        revwalk.push_head().map_err(|e| e.message().to_string())?;
+    for tag in get_tags(repo)? {
+        if let Ok(oid) = git2::Oid::from_str(&tag.commit_sha) {
+            add(oid, tag.name, RefKind::Tag, false);
+        }
     }
 
-    // Sort by topological order with time
-    revwalk
-        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
-        .map_err(|e| e.message().to_string())?;
+    // Every stash entry, not only the latest one refs/stash points to
+    if let Ok(reflog) = repo.reflog("refs/stash") {
+        for (index, entry) in reflog.iter().enumerate() {
+            add(
+                entry.id_new(),
+                format!("stash@{{{}}}", index),
+                RefKind::Stash,
+                false,
+            );
+        }
+    }
 
-    let commits: Vec<CommitInfo> = revwalk
-        .take(limit)
-        .filter_map(|oid| oid.ok())
-        .filter_map(|oid| repo.find_commit(oid).ok())
-        .map(|commit| {
-            let time = commit.time();
-            let datetime: DateTime<Utc> = Utc.timestamp_opt(time.seconds(), 0).unwrap();
-
-            CommitInfo {
-                id: commit.id().to_string(),
-                short_id: commit.id().to_string()[..7].to_string(),
-                message: commit.message().unwrap_or("").trim().to_string(),
-                author: commit.author().name().unwrap_or("Unknown").to_string(),
-                author_email: commit.author().email().unwrap_or("").to_string(),
-                date: datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
-                parent_ids: commit.parent_ids().map(|id| id.to_string()).collect(),
-            }
-        })
-        .collect();
+    Ok(decorations)
+}
 
-    Ok(commits)
+/// Restricts the commits returned by get_commits; every field is optional and
+/// the default matches everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommitFilter {
+    pub message: Option<String>, // substring of the message, case-insensitive
+    pub author: Option<String>,  // substring of the author name or email, case-insensitive
+    pub since: Option<i64>,      // unix timestamp (seconds) of the commit time, inclusive
+    pub until: Option<i64>,      // unix timestamp (seconds) of the commit time, inclusive
 }
 
-pub fn get_file_status(repo: &Repository) -> Result<Vec<FileStatus>, String> {
-    let mut opts = StatusOptions::new();
-    opts.include_untracked(true)
-        .recurse_untracked_dirs(true)
-        .include_ignored(false);
+impl CommitFilter {
+    fn is_empty(&self) -> bool {
+        self.message.is_none()
+            && self.author.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
 
-    let statuses = repo
-        .statuses(Some(&mut opts))
-        .map_err(|e| e.message().to_string())?;
-    let mut files = Vec::new();
+    fn matches(&self, commit: &git2::Commit) -> bool {
+        let time = commit.time().seconds();
+        if self.since.is_some_and(|since| time < since)
+            || self.until.is_some_and(|until| time > until)
+        {
+            return false;
+        }
+        let contains = |haystack: Option<&str>, needle: &str| {
+            haystack.is_some_and(|haystack| haystack.to_lowercase().contains(needle))
+        };
+        if let Some(author) = self.author.as_deref().map(str::to_lowercase) {
+            let signature = commit.author();
+            if !contains(signature.name(), &author) && !contains(signature.email(), &author) {
+                return false;
+            }
+        }
+        match self.message.as_deref().map(str::to_lowercase) {
+            Some(message) => contains(commit.message(), &message),
+            None => true,
+        }
+    }
+}
 
-    for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("").to_string();
-        let status = entry.status();
+/// Which part of the history get_commits walks; the default is all local
+/// branches and every path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommitScope {
+    pub reference: Option<String>, // branch, tag or commit to walk instead of the local branches
+    #[serde(default)]
+    pub paths: Vec<String>, // pathspecs; only commits touching them
+}
 
-        let (status_str, staged) = if status.is_index_new() {
-            ("new".to_string(), true)
-        } else if status.is_index_modified() {
-            ("modified".to_string(), true)
-        } else if status.is_index_deleted() {
-            ("deleted".to_string(), true)
-        } else if status.is_wt_new() {
-            ("untracked".to_string(), false)
-        } else if status.is_wt_modified() {
-            ("modified".to_string(), false)
-        } else if status.is_wt_deleted() {
-            ("deleted".to_string(), false)
-        } else {
-            ("unknown".to_string(), false)
+impl CommitScope {
+    /// Whether `commit` changes the scope's paths. Like git log, a merge only
+    /// counts when it differs from every parent there, so merges bringing in
+    /// a change made on a branch are left out.
+    fn touches_paths(&self, repo: &Repository, commit: &git2::Commit) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+        let Ok(tree) = commit.tree() else {
+            return false;
+        };
+        let mut opts = git2::DiffOptions::new();
+        for path in &self.paths {
+            opts.pathspec(path);
+        }
+        let mut differs = |parent_tree: Option<&git2::Tree>| {
+            repo.diff_tree_to_tree(parent_tree, Some(&tree), Some(&mut opts))
+                .is_ok_and(|diff| diff.deltas().len() > 0)
         };
+        if commit.parent_count() == 0 {
+            return differs(None);
+        }
+        commit.parents().all(|parent| {
+            parent
+                .tree()
+                .is_ok_and(|parent_tree| differs(Some(&parent_tree)))
+        })
+    }
+}
 
-        files.push(FileStatus {
-            path,
-            status: status_str,
-            staged,
-        });
+/// Where a history walk starts: `scope.reference`, or all local branches (HEAD
+/// when there are none)
+fn history_tips(repo: &Repository, scope: &CommitScope) -> Result<Vec<git2::Oid>, String> {
+    if let Some(reference) = scope.reference.as_deref() {
+        let commit = repo
+            .revparse_single(reference)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("Cannot resolve '{}': {}", reference, e.message()))?;
+        return Ok(vec![commit.id()]);
     }
 
-    Ok(files)
-}
+    // All local branches, to include all commits in the graph
+    let mut tips: Vec<git2::Oid> = repo
+        .branches(Some(BranchType::Local))
+        .map(|branches| {
+            branches
+                .flatten()
+                .filter_map(|(branch, _)| branch.get().peel_to_commit().ok())
+                .map(|commit| commit.id())
+                .collect()
+        })
+        .unwrap_or_default();
 
-pub fn get_tags(repo: &Repository) -> Result<Vec<TagInfo>, String> {
-    let tags = repo.tag_names(None).map_err(|e| e.message().to_string())?;
-    let mut result = Vec::new();
+    // Fallback to HEAD if there are no branches
+    if tips.is_empty() {
+        let head = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(|e| e.message().to_string())?;
+        tips.push(head.id());
+    }
+    tips.sort();
+    tips.dedup();
+    Ok(tips)
+}
 
-    for tag_name in tags.iter().flatten() {
-        // Try to resolve the tag to a commit
-        let refname = format!("refs/tags/{}", tag_name);
-        if let Ok(reference) = repo.find_reference(&refname) {
-            // Peel to commit to handle both lightweight and annotated tags
-            if let Ok(commit) = reference.peel_to_commit() {
-                result.push(TagInfo {
-                    name: tag_name.to_string(),
-                    commit_sha: commit.id().to_string(),
-                });
-            }
-        }
+/// Walk over the commits from `tips`, in the order get_commits returns them
+fn history_revwalk<'r>(
+    repo: &'r Repository,
+    tips: &[git2::Oid],
+) -> Result<git2::Revwalk<'r>, String> {
+    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+    for tip in tips {
+        revwalk.push(*tip).map_err(|e| e.message().to_string())?;
     }
 
-    Ok(result)
+    // Sort by topological order with time
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .map_err(|e| e.message().to_string())?;
+    Ok(revwalk)
 }
 
-pub fn get_remotes(repo: &Repository) -> Result<Vec<String>, String> {
-    let remotes = repo.remotes().map_err(|e| e.message().to_string())?;
-    Ok(remotes.iter().filter_map(|r| r.map(String::from)).collect())
+/// Order of the last history walk, for the pages that follow it. Commits never
+/// change, so the order stays valid as long as the walk starts from the same
+/// tips; a topological walk has to visit the whole history before returning
+/// its first commit, so walking again for every page is what this avoids.
+struct HistoryWalk {
+    git_dir: PathBuf,
+    tips: Vec<git2::Oid>,
+    oids: Arc<Vec<git2::Oid>>,
 }
 
-/// Get diff for a file in the working directory (unstaged changes)
-pub fn get_working_diff(
-    repo: &Repository,
-    file_path: &str,
-    staged: bool,
-) -> Result<DiffInfo, String> {
-    use git2::DiffOptions;
-
-    let mut diff_opts = DiffOptions::new();
-    diff_opts.pathspec(file_path);
-    diff_opts.context_lines(3);
-
-    let diff = if staged {
-        // Staged changes: compare HEAD to index
-        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+static LAST_HISTORY_WALK: Mutex<Option<HistoryWalk>> = Mutex::new(None);
 
-        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_opts))
-            .map_err(|e| e.message().to_string())?
-    } else {
-        // Unstaged changes: compare index to working directory
-        repo.diff_index_to_workdir(None, Some(&mut diff_opts))
-            .map_err(|e| e.message().to_string())?
-    };
+/// The commits from `tips` in history order, reusing the last walk when
+/// `reuse` is set and it started from the same tips
+fn history_order(
+    repo: &Repository,
+    tips: Vec<git2::Oid>,
+    reuse: bool,
+) -> Result<Arc<Vec<git2::Oid>>, String> {
+    if reuse {
+        let last = LAST_HISTORY_WALK.lock_recover("history walk");
+        if let Some(walk) = last
+            .as_ref()
+            .filter(|walk| walk.git_dir == repo.path() && walk.tips == tips)
+        {
+            return Ok(walk.oids.clone());
+        }
+    }
 
-    parse_diff(&diff, file_path)
+    let oids: Arc<Vec<git2::Oid>> = Arc::new(
+        history_revwalk(repo, &tips)?
+            .filter_map(|oid| oid.ok())
+            .collect(),
+    );
+    *LAST_HISTORY_WALK.lock_recover("history walk") = Some(HistoryWalk {
+        git_dir: repo.path().to_path_buf(),
+        tips,
+        oids: oids.clone(),
+    });
+    Ok(oids)
 }
 
-/// Get diff for a file in a specific commit
-pub fn get_commit_diff(
+/// A page of the commits in `scope` (all local branches by default), newest
+/// first, that match `filter`. The page starts after the commit `cursor` (the
+/// last one of the previous page) when given, then skips `skip` matching
+/// commits. The walk that produced the previous page is reused for a cursor,
+/// so following pages do not walk the history again. Only the commits of the
+/// page are loaded: skipped ones are passed over by id when nothing is
+/// filtered, and the filter and paths are applied while walking.
+pub fn get_commits(
     repo: &Repository,
-    commit_id: &str,
-    file_path: &str,
-) -> Result<DiffInfo, String> {
-    use git2::{DiffOptions, Oid};
+    limit: usize,
+    skip: usize,
+    cursor: Option<&str>,
+    scope: &CommitScope,
+    filter: &CommitFilter,
+) -> Result<Vec<CommitInfo>, String> {
+    let order = history_order(repo, history_tips(repo, scope)?, cursor.is_some())?;
+    let start = match cursor {
+        Some(cursor) => {
+            let cursor = git2::Oid::from_str(cursor).map_err(|e| e.message().to_string())?;
+            match order.iter().position(|oid| *oid == cursor) {
+                Some(position) => position + 1,
+                None => {
+                    return Err(format!(
+                        "Commit {} is no longer in the history; reload the commits",
+                        cursor
+                    ))
+                }
+            }
+        }
+        None => 0,
+    };
+    let oids = order[start..].iter().copied();
 
-    let oid = Oid::from_str(commit_id).map_err(|e| e.message().to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
-    let commit_tree = commit.tree().map_err(|e| e.message().to_string())?;
+    let find = |oid| repo.find_commit(oid).ok();
+    let page: Box<dyn Iterator<Item = git2::Commit>> =
+        if filter.is_empty() && scope.paths.is_empty() {
+            Box::new(oids.skip(skip).filter_map(find))
+        } else {
+            Box::new(
+                oids.filter_map(find)
+                    .filter(|commit| filter.matches(commit) && scope.touches_paths(repo, commit))
+                    .skip(skip),
+            )
+        };
+    let commits: Vec<CommitInfo> = page
+        .take(limit)
+        .map(|commit| commit_info(&commit))
+        .collect();
 
-    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    Ok(commits)
+}
 
-    let mut diff_opts = DiffOptions::new();
-    diff_opts.pathspec(file_path);
-    diff_opts.context_lines(3);
+fn commit_info(commit: &git2::Commit) -> CommitInfo {
+    let time = commit.time();
+    let datetime: DateTime<Utc> = Utc.timestamp_opt(time.seconds(), 0).unwrap();
+
+    CommitInfo {
+        id: commit.id().to_string(),
+        short_id: commit.id().to_string()[..7].to_string(),
+        message: commit.message().unwrap_or("").trim().to_string(),
+        author: commit.author().name().unwrap_or("Unknown").to_string(),
+        author_email: commit.author().email().unwrap_or("").to_string(),
+        date: datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+        parent_ids: commit.parent_ids().map(|id| id.to_string()).collect(),
+    }
+}
 
-    let diff = repo
-        .diff_tree_to_tree(
-            parent_tree.as_ref(),
-            Some(&commit_tree),
-            Some(&mut diff_opts),
-        )
-        .map_err(|e| e.message().to_string())?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitCount {
+    pub count: usize,
+    pub is_estimate: bool, // true when counting stopped at the limit ("at least count")
+    pub history_limited: bool, // the history reaches a shallow or grafted cut; older commits are missing
+}
 
-    parse_diff(&diff, file_path)
+/// Number of commits get_commits can page through with `scope` and `filter`,
+/// for sizing an infinitely scrolled list
+pub fn get_commit_count(
+    repo: &Repository,
+    scope: &CommitScope,
+    filter: &CommitFilter,
+) -> Result<CommitCount, String> {
+    let revwalk = match history_tips(repo, scope).and_then(|tips| history_revwalk(repo, &tips)) {
+        Ok(revwalk) => revwalk,
+        // Unborn HEAD has no history at all
+        Err(_) if scope.reference.is_none() && repo.head().is_err() => {
+            return Ok(CommitCount {
+                count: 0,
+                is_estimate: false,
+                history_limited: false,
+            })
+        }
+        Err(e) => return Err(e),
+    };
+    let unfiltered = filter.is_empty() && scope.paths.is_empty();
+    let counts = |oid| {
+        unfiltered
+            || repo
+                .find_commit(oid)
+                .is_ok_and(|commit| filter.matches(&commit) && scope.touches_paths(repo, &commit))
+    };
+    count_history(repo, revwalk, counts, |_, _| {})
 }
 
-/// Get files changed in a specific commit
-pub fn get_commit_files(repo: &Repository, commit_id: &str) -> Result<Vec<FileStatus>, String> {
-    use git2::{DiffOptions, Oid};
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryTip {
+    pub name: String,
+    pub commit_sha: String,
+    pub position: Option<usize>, // row in the commit list, None if past the counting limit
+}
 
-    let oid = Oid::from_str(commit_id).map_err(|e| e.message().to_string())?;
-    let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
-    let commit_tree = commit.tree().map_err(|e| e.message().to_string())?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryStats {
+    pub total_count: usize,
+    pub is_estimate: bool, // true when counting stopped at the limit ("at least total_count")
+    pub history_limited: bool, // the history reaches a shallow or grafted cut
+    pub tips: Vec<HistoryTip>,
+}
 
-    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+/// Upper bound on commits walked by get_history_stats and get_commit_count before
+/// reporting an estimate
+const HISTORY_STATS_LIMIT: usize = 200_000;
 
-    let mut diff_opts = DiffOptions::new();
+/// Walk at most HISTORY_STATS_LIMIT commits of `revwalk`, counting those `counts` accepts;
+/// `visit` sees each counted commit with its position among them
+fn count_history(
+    repo: &Repository,
+    revwalk: git2::Revwalk,
+    mut counts: impl FnMut(git2::Oid) -> bool,
+    mut visit: impl FnMut(git2::Oid, usize),
+) -> Result<CommitCount, String> {
+    let grafted = grafted_commits(repo);
+    let mut counted = CommitCount {
+        count: 0,
+        is_estimate: false,
+        history_limited: false,
+    };
+    for (walked, oid) in revwalk.enumerate() {
+        let oid = oid.map_err(|e| e.message().to_string())?;
+        if walked == HISTORY_STATS_LIMIT {
+            counted.is_estimate = true;
+            break;
+        }
+        counted.history_limited |= grafted.contains(&oid);
+        if counts(oid) {
+            visit(oid, counted.count);
+            counted.count += 1;
+        }
+    }
+    Ok(counted)
+}
 
-    let diff = repo
-        .diff_tree_to_tree(
-            parent_tree.as_ref(),
-            Some(&commit_tree),
-            Some(&mut diff_opts),
-        )
+/// Count the commits reachable from the given refs (all local branches by default) and
+/// locate each tip in the same order get_commits returns them
+pub fn get_history_stats(
+    repo: &Repository,
+    refs: Option<Vec<String>>,
+) -> Result<HistoryStats, String> {
+    let mut tips: Vec<(String, git2::Oid)> = Vec::new();
+    match refs {
+        Some(refs) => {
+            for name in refs {
+                let commit = repo
+                    .revparse_single(&name)
+                    .and_then(|obj| obj.peel_to_commit())
+                    .map_err(|e| e.message().to_string())?;
+                tips.push((name, commit.id()));
+            }
+        }
+        None => {
+            for head in get_branch_heads(repo)? {
+                if let Ok(oid) = git2::Oid::from_str(&head.commit_sha) {
+                    tips.push((head.name, oid));
+                }
+            }
+        }
+    }
+
+    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+    if tips.is_empty() {
+        // Unborn HEAD has no history at all
+        if revwalk.push_head().is_err() {
+            return Ok(HistoryStats {
+                total_count: 0,
+                is_estimate: false,
+                history_limited: false,
+                tips: Vec::new(),
+            });
+        }
+    }
+    for (_, oid) in &tips {
+        revwalk.push(*oid).map_err(|e| e.message().to_string())?;
+    }
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
         .map_err(|e| e.message().to_string())?;
 
-    let mut files = Vec::new();
+    let mut positions: std::collections::HashMap<git2::Oid, Option<usize>> =
+        tips.iter().map(|(_, oid)| (*oid, None)).collect();
+    let counted = count_history(
+        repo,
+        revwalk,
+        |_| true,
+        |oid, position| {
+            if let Some(tip) = positions.get_mut(&oid) {
+                *tip = Some(position);
+            }
+        },
+    )?;
+
+    Ok(HistoryStats {
+        total_count: counted.count,
+        is_estimate: counted.is_estimate,
+        history_limited: counted.history_limited,
+        tips: tips
+            .into_iter()
+            .map(|(name, oid)| HistoryTip {
+                name,
+                commit_sha: oid.to_string(),
+                position: positions.get(&oid).copied().flatten(),
+            })
+            .collect(),
+    })
+}
 
-    diff.foreach(
-        &mut |delta, _| {
-            let path = delta
-                .new_file()
-                .path()
-                .or_else(|| delta.old_file().path())
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_default();
+// ============================================================================
+// Content Search (pickaxe)
+// ============================================================================
 
-            let status = match delta.status() {
-                git2::Delta::Added => "new",
-                git2::Delta::Deleted => "deleted",
-                git2::Delta::Modified => "modified",
-                git2::Delta::Renamed => "renamed",
-                git2::Delta::Copied => "copied",
-                _ => "unknown",
-            };
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContentSearchMatch {
+    pub commit: CommitInfo,
+    pub files: Vec<String>, // files where the term was added or removed
+}
 
-            files.push(FileStatus {
-                path,
-                status: status.to_string(),
-                staged: false,
-            });
+/// Commits (on local branches, newest first) that added or removed `term`:
+/// `git log -S` counts occurrences of the literal string, `git log -G` (when
+/// `regex` is set) matches added or removed lines against a regular expression
+pub fn search_commits_by_content(
+    repo: &Repository,
+    term: &str,
+    regex: bool,
+    limit: usize,
+) -> Result<Vec<ContentSearchMatch>, String> {
+    if term.is_empty() {
+        return Err("Search term cannot be empty".to_string());
+    }
+    let repo_path = repo
+        .workdir()
+        .unwrap_or_else(|| repo.path())
+        .to_string_lossy()
+        .to_string();
+    let pickaxe = if regex {
+        format!("-G{}", term)
+    } else {
+        format!("-S{}", term)
+    };
 
-            true
-        },
-        None,
-        None,
-        None,
-    )
-    .map_err(|e| e.message().to_string())?;
+    // Each commit starts with \x1e and its fields are separated by \x1f; the
+    // files matched by the pickaxe follow the message, one per line
+    let output = git_command()
+        .arg("-C")
+        .arg(&repo_path)
+        .args(["-c", "core.quotePath=false", "log", "--branches", "HEAD"])
+        .arg(pickaxe)
+        .arg(format!("--max-count={}", limit))
+        .args([
+            "--name-only",
+            "--no-ext-diff",
+            "--format=%x1e%H%x1f%an%x1f%ae%x1f%at%x1f%P%x1f%B%x1f",
+            "--",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute git log: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // An unborn HEAD has no history to search
+        if repo.head().is_err() {
+            return Ok(Vec::new());
+        }
+        return Err(format!("Failed to search commits: {}", stderr.trim()));
+    }
 
-    Ok(files)
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let matches = stdout
+        .split('\x1e')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.splitn(7, '\x1f').collect();
+            let [id, author, email, time, parents, message, files] = fields[..] else {
+                return None;
+            };
+            let seconds: i64 = time.parse().ok()?;
+            let datetime: DateTime<Utc> = Utc.timestamp_opt(seconds, 0).single()?;
+            Some(ContentSearchMatch {
+                commit: CommitInfo {
+                    id: id.to_string(),
+                    short_id: id.get(..7).unwrap_or(id).to_string(),
+                    message: message.trim().to_string(),
+                    author: author.to_string(),
+                    author_email: email.to_string(),
+                    date: datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    parent_ids: parents.split_whitespace().map(String::from).collect(),
+                },
+                files: files
+                    .lines()
+                    .map(str::trim_end)
+                    .filter(|line| !line.is_empty())
+                    .map(String::from)
+                    .collect(),
+            })
+        })
+        .collect();
+    Ok(matches)
 }
 
-/// Check if a file is binary based on content
-fn is_binary_content(content: &[u8]) -> bool {
-    // Check for null bytes in the first 8000 bytes (git's approach)
-    let check_len = std::cmp::min(content.len(), 8000);
-    content[..check_len].contains(&0)
+/// Branch `remote`'s HEAD points to in the remote-tracking refs, e.g. "main"
+/// for origin/HEAD -> origin/main. Set by clone, or by set_default_branch_local.
+fn remote_head_branch(repo: &Repository, remote: &str) -> Option<String> {
+    let reference = repo
+        .find_reference(&format!("refs/remotes/{}/HEAD", remote))
+        .ok()?;
+    let target = reference.symbolic_target()?;
+    target
+        .strip_prefix(&format!("refs/remotes/{}/", remote))
+        .map(String::from)
 }
 
-/// Get the binary type based on file extension
-fn get_binary_type(file_path: &str) -> Option<String> {
-    let lower = file_path.to_lowercase();
-    if lower.ends_with(".png")
-        || lower.ends_with(".jpg")
-        || lower.ends_with(".jpeg")
-        || lower.ends_with(".gif")
-        || lower.ends_with(".bmp")
-        || lower.ends_with(".webp")
-        || lower.ends_with(".svg")
-        || lower.ends_with(".ico")
-    {
-        Some("image".to_string())
-    } else if lower.ends_with(".pdf") {
-        Some("pdf".to_string())
-    } else {
-        Some("other".to_string())
+/// Best guess at the repository's main line: the branch origin/HEAD (or the
+/// HEAD of another remote) points to, otherwise a local "main" or "master"
+pub(crate) fn find_default_branch(repo: &Repository) -> Option<String> {
+    let mut remotes: Vec<String> = repo
+        .remotes()
+        .map(|names| names.iter().flatten().map(String::from).collect())
+        .unwrap_or_default();
+    remotes.sort_by_key(|name| name != "origin");
+    for remote in remotes {
+        if let Some(branch) = remote_head_branch(repo, &remote) {
+            return Some(format!("{}/{}", remote, branch));
+        }
     }
-}
 
-/// Parse a git2 Diff into our DiffInfo structure
-fn parse_diff(diff: &git2::Diff, file_path: &str) -> Result<DiffInfo, String> {
-    use std::cell::RefCell;
+    ["main", "master"]
+        .into_iter()
+        .find(|name| repo.find_branch(name, BranchType::Local).is_ok())
+        .map(String::from)
+}
 
-    let hunks: RefCell<Vec<DiffHunk>> = RefCell::new(Vec::new());
-    let is_binary: RefCell<bool> = RefCell::new(false);
+/// Default branch of `remote` ("main", without the remote name). Read from
+/// refs/remotes/<remote>/HEAD, or asked to the remote with
+/// `git ls-remote --symref` when that ref was never set (a repository that was
+/// not cloned, or a default branch changed on the server). None when neither
+/// tells.
+pub fn get_default_branch(repo: &Repository, remote: &str) -> Result<Option<String>, String> {
+    repo.find_remote(remote)
+        .map_err(|_| format!("Remote '{}' does not exist", remote))?;
+    if let Some(branch) = remote_head_branch(repo, remote) {
+        return Ok(Some(branch));
+    }
 
-    diff.foreach(
-        &mut |delta, _| {
-            // Check if the file is binary
-            if delta.flags().is_binary() {
-                *is_binary.borrow_mut() = true;
-            }
-            true
-        },
-        Some(&mut |_, _binary| {
-            *is_binary.borrow_mut() = true;
-            true
-        }),
-        Some(&mut |_, hunk| {
-            let diff_hunk = DiffHunk {
-                old_start: hunk.old_start(),
-                old_lines: hunk.old_lines(),
-                new_start: hunk.new_start(),
-                new_lines: hunk.new_lines(),
-                lines: Vec::new(),
-            };
-            hunks.borrow_mut().push(diff_hunk);
-            true
-        }),
-        Some(&mut |_, _hunk, line| {
-            let mut hunks_mut = hunks.borrow_mut();
-            if let Some(current_hunk) = hunks_mut.last_mut() {
-                let line_type = match line.origin() {
-                    '+' => "add",
-                    '-' => "delete",
-                    ' ' => "context",
-                    _ => "context",
-                };
+    let output = git_command()
+        .arg("-C")
+        .arg(repo.workdir().unwrap_or(repo.path()))
+        .args(["ls-remote", "--symref", remote, "HEAD"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env(
+            "GIT_SSH_COMMAND",
+            "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
+        )
+        .output()
+        .map_err(|e| format!("Failed to execute git ls-remote: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to query the default branch of '{}': {}",
+            remote,
+            stderr.lines().next().unwrap_or_default().trim()
+        ));
+    }
 
-                let content = String::from_utf8_lossy(line.content()).to_string();
+    // "ref: refs/heads/main\tHEAD"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("ref:"))
+        .filter_map(|line| line.trim().split_once('\t'))
+        .find(|(_, name)| *name == "HEAD")
+        .and_then(|(target, _)| target.strip_prefix("refs/heads/"))
+        .map(String::from))
+}
 
-                current_hunk.lines.push(DiffLine {
-                    content,
-                    line_type: line_type.to_string(),
-                    old_line_no: line.old_lineno(),
-                    new_line_no: line.new_lineno(),
-                });
-            }
-            true
-        }),
+/// Point refs/remotes/<remote>/HEAD at `branch`, like `git remote set-head`,
+/// without contacting the remote. The remote-tracking branch must exist.
+pub fn set_default_branch_local(
+    repo: &Repository,
+    remote: &str,
+    branch: &str,
+) -> Result<(), String> {
+    repo.find_remote(remote)
+        .map_err(|_| format!("Remote '{}' does not exist", remote))?;
+    let target = format!("refs/remotes/{}/{}", remote, branch);
+    if repo.find_reference(&target).is_err() {
+        return Err(format!(
+            "'{}/{}' does not exist; fetch it from the remote first",
+            remote, branch
+        ));
+    }
+    repo.reference_symbolic(
+        &format!("refs/remotes/{}/HEAD", remote),
+        &target,
+        true,
+        "remote set-head",
     )
-    .map_err(|e| e.message().to_string())?;
+    .map_err(|e| format!("Failed to set the default branch: {}", e.message()))?;
+    Ok(())
+}
 
-    let binary = *is_binary.borrow();
-    let binary_type = if binary {
-        get_binary_type(file_path)
-    } else {
-        None
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BranchCommitSet {
+    pub branch: String,
+    pub base: Option<String>, // branch the commits are unique against
+    pub commits: Vec<String>,
+}
+
+/// Commits reachable from `branch` but not from `base` (the default branch when not
+/// given), i.e. the commits that selecting the branch should highlight in the graph
+pub fn get_branch_commit_set(
+    repo: &Repository,
+    branch: &str,
+    base: Option<&str>,
+) -> Result<BranchCommitSet, String> {
+    let tip = repo
+        .revparse_single(branch)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| e.message().to_string())?;
+
+    let base = base.map(String::from).or_else(|| find_default_branch(repo));
+    let base_commit = match &base {
+        Some(base) => Some(
+            repo.revparse_single(base)
+                .and_then(|obj| obj.peel_to_commit())
+                .map_err(|e| e.message().to_string())?,
+        ),
+        None => None,
     };
 
-    Ok(DiffInfo {
-        file_path: file_path.to_string(),
-        old_content: None,
-        new_content: None,
-        hunks: hunks.into_inner(),
-        is_binary: binary,
-        binary_type,
-        file_size: None,
+    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+    revwalk
+        .push(tip.id())
+        .map_err(|e| e.message().to_string())?;
+    if let Some(base_commit) = &base_commit {
+        revwalk
+            .hide(base_commit.id())
+            .map_err(|e| e.message().to_string())?;
+    }
+
+    let commits = revwalk
+        .filter_map(|oid| oid.ok())
+        .map(|oid| oid.to_string())
+        .collect();
+
+    Ok(BranchCommitSet {
+        branch: branch.to_string(),
+        base,
+        commits,
     })
 }
 
-/// Read content of an untracked file and create diff info showing all lines as additions
-pub fn get_untracked_file_diff(repo: &Repository, file_path: &str) -> Result<DiffInfo, String> {
-    let workdir = repo.workdir().ok_or("No working directory")?;
-    let full_path = workdir.join(file_path);
+// ============================================================================
+// Focused Paths (limit status and watching to parts of a monorepo)
+// ============================================================================
 
-    // Read the file
-    let content = std::fs::read(&full_path).map_err(|e| e.to_string())?;
-    let file_size = content.len() as u64;
+/// Directories the user focuses on in this repository, stored as `forky.focusPath`
+/// entries in the repository config. Empty means the whole repository.
+pub fn get_focused_paths(repo: &Repository) -> Result<Vec<String>, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("Failed to read config: {}", e))?;
 
-    // Check if binary
-    if is_binary_content(&content) {
-        return Ok(DiffInfo {
-            file_path: file_path.to_string(),
-            old_content: None,
-            new_content: None,
-            hunks: Vec::new(),
-            is_binary: true,
-            binary_type: get_binary_type(file_path),
-            file_size: Some(file_size),
-        });
+    let mut paths = Vec::new();
+    if let Ok(mut entries) = config.multivar("forky.focusPath", None) {
+        while let Some(Ok(entry)) = entries.next() {
+            if let Some(value) = entry.value() {
+                let value = value.trim().trim_matches('/');
+                if !value.is_empty() && !paths.iter().any(|p| p == value) {
+                    paths.push(value.to_string());
+                }
+            }
+        }
     }
 
-    // Convert to string and create diff lines
-    let text = String::from_utf8_lossy(&content);
-    let lines: Vec<&str> = text.lines().collect();
+    Ok(paths)
+}
 
-    let diff_lines: Vec<DiffLine> = lines
-        .iter()
-        .enumerate()
-        .map(|(i, line)| DiffLine {
-            content: format!("{}\n", line),
-            line_type: "add".to_string(),
-            old_line_no: None,
-            new_line_no: Some((i + 1) as u32),
-        })
-        .collect();
+/// Replace the focused paths of a repository; an empty list removes the focus
+pub fn set_focused_paths(repo: &Repository, paths: &[String]) -> Result<(), String> {
+    let mut config = repo
+        .config()
+        .and_then(|config| config.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| format!("Failed to open config: {}", e))?;
 
-    let hunk = DiffHunk {
-        old_start: 0,
-        old_lines: 0,
-        new_start: 1,
-        new_lines: lines.len() as u32,
-        lines: diff_lines,
+    if let Err(e) = config.remove_multivar("forky.focusPath", ".*") {
+        if e.code() != git2::ErrorCode::NotFound {
+            return Err(format!("Failed to clear forky.focusPath: {}", e));
+        }
+    }
+
+    let workdir = repo.workdir().ok_or("No working directory")?;
+    let workdir = dunce::canonicalize(workdir).unwrap_or_else(|_| workdir.to_path_buf());
+    for path in paths {
+        let path = path.trim().trim_matches('/');
+        if path.is_empty() {
+            continue;
+        }
+        let path = focused_path_in_workdir(&workdir, path)?;
+        // "^$" never matches an existing value, so every call appends a new entry
+        config
+            .set_multivar("forky.focusPath", "^$", &path)
+            .map_err(|e| format!("Failed to set forky.focusPath: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// `path` relative to the canonical `workdir`, refused when it resolves
+/// outside of it. Symlinks and ".." are resolved for paths that exist; a path
+/// that does not exist yet may not contain "..".
+fn focused_path_in_workdir(workdir: &std::path::Path, path: &str) -> Result<String, String> {
+    use std::path::Component;
+
+    let outside = || format!("'{}' is outside the repository", path);
+    let joined = workdir.join(path);
+    let resolved = match dunce::canonicalize(&joined) {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            if std::path::Path::new(path)
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(outside());
+            }
+            joined
+        }
     };
+    let relative = resolved.strip_prefix(workdir).map_err(|_| outside())?;
+    let relative: Vec<String> = relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    if relative.is_empty() {
+        return Err(format!(
+            "'{}' is the repository root, not a subfolder",
+            path
+        ));
+    }
+    Ok(relative.join("/"))
+}
 
-    Ok(DiffInfo {
-        file_path: file_path.to_string(),
-        old_content: None,
-        new_content: Some(text.to_string()),
-        hunks: vec![hunk],
-        is_binary: false,
-        binary_type: None,
-        file_size: Some(file_size),
-    })
+/// Restrict a status scan to the focused paths, if any
+fn apply_focused_paths(repo: &Repository, opts: &mut StatusOptions) {
+    for path in get_focused_paths(repo).unwrap_or_default() {
+        opts.pathspec(path);
+    }
 }
 
-/// Get diff for a deleted file from the index, showing all lines as deletions
-pub fn get_deleted_file_diff(repo: &Repository, file_path: &str) -> Result<DiffInfo, String> {
-    // Get the file content from HEAD
-    let head = repo.head().map_err(|e| e.message().to_string())?;
-    let tree = head.peel_to_tree().map_err(|e| e.message().to_string())?;
+pub fn get_file_status(repo: &Repository) -> Result<Vec<FileStatus>, String> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .renames_head_to_index(true);
+    // Only staged renames are paired, as `git status` does: until it is
+    // staged, a moved file is a deleted file and an untracked one
+    apply_focused_paths(repo, &mut opts);
 
-    let entry = tree
-        .get_path(std::path::Path::new(file_path))
+    let statuses = repo
+        .statuses(Some(&mut opts))
         .map_err(|e| e.message().to_string())?;
+    let conflicts = conflict_kinds(repo, &statuses)?;
+    let mut files = Vec::new();
 
-    let blob = repo
-        .find_blob(entry.id())
-        .map_err(|e| e.message().to_string())?;
-    let content = blob.content();
-    let file_size = content.len() as u64;
+    for entry in statuses.iter() {
+        let (path, old_path) = status_entry_paths(&entry);
+        let status = entry.status();
 
-    // Check if binary
-    if blob.is_binary() || is_binary_content(content) {
-        return Ok(DiffInfo {
-            file_path: file_path.to_string(),
-            old_content: None,
-            new_content: None,
-            hunks: Vec::new(),
-            is_binary: true,
-            binary_type: get_binary_type(file_path),
-            file_size: Some(file_size),
+        if status.is_conflicted() {
+            files.push(conflicted_status(path, &conflicts));
+            continue;
+        }
+
+        let (status_str, staged) = if status.is_index_renamed() {
+            ("renamed".to_string(), true)
+        } else if status.is_index_new() {
+            ("new".to_string(), true)
+        } else if status.is_index_modified() {
+            ("modified".to_string(), true)
+        } else if status.is_index_deleted() {
+            ("deleted".to_string(), true)
+        } else if status.is_wt_new() {
+            ("untracked".to_string(), false)
+        } else if status.is_wt_modified() {
+            ("modified".to_string(), false)
+        } else if status.is_wt_deleted() {
+            ("deleted".to_string(), false)
+        } else {
+            ("unknown".to_string(), false)
+        };
+
+        files.push(FileStatus {
+            path,
+            status: status_str,
+            staged,
+            conflict: None,
+            old_path,
         });
     }
 
-    // Convert to string and create diff lines
-    let text = String::from_utf8_lossy(content);
-    let lines: Vec<&str> = text.lines().collect();
+    Ok(files)
+}
 
-    let diff_lines: Vec<DiffLine> = lines
-        .iter()
-        .enumerate()
-        .map(|(i, line)| DiffLine {
-            content: format!("{}\n", line),
-            line_type: "delete".to_string(),
-            old_line_no: Some((i + 1) as u32),
-            new_line_no: None,
-        })
-        .collect();
+pub fn get_tags(repo: &Repository) -> Result<Vec<TagInfo>, String> {
+    let tags = repo.tag_names(None).map_err(|e| e.message().to_string())?;
+    let mut result = Vec::new();
 
-    let hunk = DiffHunk {
-        old_start: 1,
-        old_lines: lines.len() as u32,
-        new_start: 0,
-        new_lines: 0,
-        lines: diff_lines,
-    };
+    for tag_name in tags.iter().flatten() {
+        // Try to resolve the tag to a commit
+        let refname = format!("refs/tags/{}", tag_name);
+        if let Ok(reference) = repo.find_reference(&refname) {
+            // Peel to commit to handle both lightweight and annotated tags
+            if let Ok(commit) = reference.peel_to_commit() {
+                result.push(TagInfo {
+                    name: tag_name.to_string(),
+                    commit_sha: commit.id().to_string(),
+                });
+            }
+        }
+    }
 
-    Ok(DiffInfo {
-        file_path: file_path.to_string(),
-        old_content: Some(text.to_string()),
-        new_content: None,
-        hunks: vec![hunk],
-        is_binary: false,
-        binary_type: None,
-        file_size: Some(file_size),
-    })
+    Ok(result)
 }
 
-/// Stage a file
-pub fn stage_file(repo: &Repository, file_path: &str) -> Result<(), String> {
-    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecentBranch {
+    pub name: String,
+    pub last_checkout: i64, // unix timestamp of the last checkout of this branch
+}
 
-    // Check if file exists or was deleted
-    let workdir = repo.workdir().ok_or("No working directory")?;
-    let full_path = workdir.join(file_path);
+/// Parse "checkout: moving from <old> to <new>" reflog messages
+fn parse_checkout_reflog_message(message: &str) -> Option<(&str, &str)> {
+    let rest = message.strip_prefix("checkout: moving from ")?;
+    rest.split_once(" to ")
+}
 
-    if full_path.exists() {
-        index
-            .add_path(std::path::Path::new(file_path))
-            .map_err(|e| e.message().to_string())?;
-    } else {
-        // File was deleted, remove from index
-        index
-            .remove_path(std::path::Path::new(file_path))
-            .map_err(|e| e.message().to_string())?;
+/// Local branches most recently checked out, newest first, read from the HEAD reflog.
+/// The current branch and branches that no longer exist are skipped.
+pub fn get_recent_branches(repo: &Repository, limit: usize) -> Result<Vec<RecentBranch>, String> {
+    let reflog = repo.reflog("HEAD").map_err(|e| e.message().to_string())?;
+    let current = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(String::from));
+
+    let mut recent: Vec<RecentBranch> = Vec::new();
+    for entry in reflog.iter() {
+        if recent.len() >= limit {
+            break;
+        }
+        let Some((from, to)) = entry.message().and_then(parse_checkout_reflog_message) else {
+            continue;
+        };
+        // The branch we left is as relevant as the one we moved to, it just happened earlier
+        for name in [to, from] {
+            if current.as_deref() == Some(name)
+                || recent.iter().any(|branch| branch.name == name)
+                || repo.find_branch(name, BranchType::Local).is_err()
+            {
+                continue;
+            }
+            recent.push(RecentBranch {
+                name: name.to_string(),
+                last_checkout: entry.committer().when().seconds(),
+            });
+        }
     }
+    recent.truncate(limit);
 
-    index.write().map_err(|e| e.message().to_string())?;
-    Ok(())
+    Ok(recent)
 }
 
-/// Unstage a file
-pub fn unstage_file(repo: &Repository, file_path: &str) -> Result<(), String> {
-    let head = repo.head().map_err(|e| e.message().to_string())?;
-    let head_commit = head.peel_to_commit().map_err(|e| e.message().to_string())?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefMatch {
+    pub name: String,     // short name, e.g. "main", "origin/main", "v1.0"
+    pub ref_type: String, // "local", "remote", "tag"
+    pub commit_sha: String,
+    pub score: i64,
+    pub matched_indices: Vec<usize>, // char positions in name to highlight
+}
 
-    repo.reset_default(
-        Some(&head_commit.as_object()),
-        &[std::path::Path::new(file_path)],
-    )
-    .map_err(|e| e.message().to_string())?;
+/// Fuzzy-search local branches, remote branches and tags, best matches first
+pub fn search_refs(repo: &Repository, query: &str, limit: usize) -> Result<Vec<RefMatch>, String> {
+    let references = repo.references().map_err(|e| e.message().to_string())?;
+    let query = query.trim();
+    let mut matches = Vec::new();
 
-    Ok(())
+    for reference in references.flatten() {
+        let Some(full_name) = reference.name() else {
+            continue;
+        };
+        let (ref_type, name) = if let Some(name) = full_name.strip_prefix("refs/heads/") {
+            ("local", name)
+        } else if let Some(name) = full_name.strip_prefix("refs/remotes/") {
+            // Skip symbolic remote HEADs (origin/HEAD)
+            if name.ends_with("/HEAD") {
+                continue;
+            }
+            ("remote", name)
+        } else if let Some(name) = full_name.strip_prefix("refs/tags/") {
+            ("tag", name)
+        } else {
+            continue;
+        };
+
+        let Some(found) = fuzzy::fuzzy_match(query, name) else {
+            continue;
+        };
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+
+        matches.push(RefMatch {
+            name: name.to_string(),
+            ref_type: ref_type.to_string(),
+            commit_sha: commit.id().to_string(),
+            score: found.score,
+            matched_indices: found.indices,
+        });
+    }
+
+    // Ties (and the empty query) list local branches first, then remotes, then tags
+    let type_rank = |ref_type: &str| match ref_type {
+        "local" => 0,
+        "remote" => 1,
+        _ => 2,
+    };
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| type_rank(&a.ref_type).cmp(&type_rank(&b.ref_type)))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    matches.truncate(limit);
+
+    Ok(matches)
 }
 
-/// Discard changes in a file (restore from HEAD or delete if untracked)
-pub fn discard_file(repo_path: &str, file_path: &str, is_untracked: bool) -> Result<(), String> {
-    use std::process::Command;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefContainment {
+    pub commit_sha: String,
+    pub local_branches: Vec<String>,
+    pub remote_branches: Vec<String>,
+    pub tags: Vec<String>,
+}
 
-    if is_untracked {
-        // For untracked files, simply delete them
-        let full_path = std::path::Path::new(repo_path).join(file_path);
-        if full_path.is_dir() {
-            std::fs::remove_dir_all(&full_path)
-                .map_err(|e| format!("Failed to remove directory: {}", e))?;
-        } else {
-            std::fs::remove_file(&full_path)
-                .map_err(|e| format!("Failed to remove file: {}", e))?;
+/// Branches and tags whose history includes a commit, like
+/// `git branch -a --contains` and `git tag --contains`
+pub fn get_branches_containing(repo: &Repository, commit: &str) -> Result<RefContainment, String> {
+    let target = repo
+        .revparse_single(commit)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| e.message().to_string())?
+        .id();
+
+    let contains =
+        |tip: git2::Oid| tip == target || repo.graph_descendant_of(tip, target).unwrap_or(false);
+
+    let mut result = RefContainment {
+        commit_sha: target.to_string(),
+        local_branches: Vec::new(),
+        remote_branches: Vec::new(),
+        tags: Vec::new(),
+    };
+
+    let references = repo.references().map_err(|e| e.message().to_string())?;
+    for reference in references.flatten() {
+        let Some(full_name) = reference.name().map(String::from) else {
+            continue;
+        };
+        let Ok(tip) = reference.peel_to_commit() else {
+            continue;
+        };
+        if !contains(tip.id()) {
+            continue;
         }
-    } else {
-        // For tracked files, use git checkout to restore from HEAD
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("checkout")
-            .arg("HEAD")
-            .arg("--")
-            .arg(file_path)
-            .output()
-            .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Failed to discard changes: {}", stderr.trim()));
+        if let Some(name) = full_name.strip_prefix("refs/heads/") {
+            result.local_branches.push(name.to_string());
+        } else if let Some(name) = full_name.strip_prefix("refs/remotes/") {
+            if !name.ends_with("/HEAD") {
+                result.remote_branches.push(name.to_string());
+            }
+        } else if let Some(name) = full_name.strip_prefix("refs/tags/") {
+            result.tags.push(name.to_string());
         }
     }
 
-    Ok(())
+    Ok(result)
 }
 
-// ============================================================================
-// Hunk Operations (Stage/Unstage/Discard individual hunks)
-// ============================================================================
-
-/// Structure to receive hunk data from frontend
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct HunkData {
-    pub old_start: u32,
-    pub old_lines: u32,
-    pub new_start: u32,
-    pub new_lines: u32,
-    pub lines: Vec<HunkLineData>,
+/// Which branches and later tags include a tag ("is this fix in the release?")
+pub fn get_tag_containment(repo: &Repository, tag: &str) -> Result<RefContainment, String> {
+    get_branches_containing(repo, &format!("refs/tags/{}", tag))
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct HunkLineData {
-    pub content: String,
-    pub line_type: String, // "add", "delete", "context"
+pub struct DescribeOptions {
+    pub tags: bool,   // --tags: also use lightweight tags
+    pub dirty: bool,  // --dirty: append "-dirty" when the working tree has changes (HEAD only)
+    pub long: bool,   // --long: always print the "-<n>-g<sha>" suffix
+    pub always: bool, // --always: fall back to the abbreviated commit id without tags
+    pub abbrev: Option<u32>,
 }
 
-/// Generate a unified diff patch from hunk data
-fn generate_patch(file_path: &str, hunk: &HunkData) -> String {
-    let mut patch = String::new();
+/// `git describe` for a ref (HEAD when not given), e.g. "v1.4.2-3-gd3adb33-dirty"
+pub fn git_describe(
+    repo: &Repository,
+    reference: Option<&str>,
+    options: DescribeOptions,
+) -> Result<String, String> {
+    let mut describe_options = git2::DescribeOptions::new();
+    if options.tags {
+        describe_options.describe_tags();
+    }
+    describe_options.show_commit_oid_as_fallback(options.always);
 
-    // Header
-    patch.push_str(&format!("diff --git a/{} b/{}\n", file_path, file_path));
-    patch.push_str(&format!("--- a/{}\n", file_path));
-    patch.push_str(&format!("+++ b/{}\n", file_path));
-
-    // Hunk header
-    patch.push_str(&format!(
-        "@@ -{},{} +{},{} @@\n",
-        hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
-    ));
-
-    // Lines - content might already have newline, so we need to handle both cases
-    for line in &hunk.lines {
-        let prefix = match line.line_type.as_str() {
-            "add" => "+",
-            "delete" => "-",
-            "context" => " ",
-            _ => " ",
-        };
+    let mut format_options = git2::DescribeFormatOptions::new();
+    format_options.always_use_long_format(options.long);
+    if let Some(abbrev) = options.abbrev {
+        format_options.abbreviated_size(abbrev);
+    }
 
-        // Remove trailing newline/carriage return if present, then add our own
-        let content = line.content.trim_end_matches(|c| c == '\n' || c == '\r');
-        patch.push_str(&format!("{}{}\n", prefix, content));
+    // Only the working tree can be dirty, so --dirty applies to HEAD alone
+    let commit;
+    let describe = match reference {
+        None | Some("HEAD") => {
+            if options.dirty {
+                format_options.dirty_suffix("-dirty");
+            }
+            repo.describe(&describe_options)
+        }
+        Some(reference) => {
+            commit = repo
+                .revparse_single(reference)
+                .and_then(|obj| obj.peel(git2::ObjectType::Commit))
+                .map_err(|e| e.message().to_string())?;
+            commit.describe(&describe_options)
+        }
     }
+    .map_err(|e| e.message().to_string())?;
 
-    patch
+    describe
+        .format(Some(&format_options))
+        .map_err(|e| e.message().to_string())
 }
 
-/// Stage a single hunk from unstaged changes
-pub fn stage_hunk(repo_path: &str, file_path: &str, hunk: HunkData) -> Result<(), String> {
-    use std::io::Write;
-    use std::process::{Command, Stdio};
+pub fn get_remotes(repo: &Repository) -> Result<Vec<String>, String> {
+    let remotes = repo.remotes().map_err(|e| e.message().to_string())?;
+    Ok(remotes.iter().filter_map(|r| r.map(String::from)).collect())
+}
 
-    let patch = generate_patch(file_path, &hunk);
+/// The remote used when none is given and the branch has no upstream: the one
+/// chosen for the repository (stored as git's `remote.pushDefault`, which the
+/// git CLI honors too), then `origin`, then the only remote
+pub fn get_default_remote(repo: &Repository) -> Result<Option<String>, String> {
+    let remotes = get_remotes(repo)?;
+    let configured = repo
+        .config()
+        .and_then(|config| config.get_string("remote.pushDefault"))
+        .ok()
+        .filter(|name| remotes.contains(name));
+    Ok(configured.or_else(|| match remotes.as_slice() {
+        [only] => Some(only.clone()),
+        _ => remotes.iter().find(|name| *name == "origin").cloned(),
+    }))
+}
 
-    // Debug: print the generated patch
-    eprintln!("=== STAGE HUNK PATCH ===");
-    eprintln!("repo_path: {}", repo_path);
-    eprintln!("file_path: {}", file_path);
-    eprintln!("patch:\n{}", patch);
-    eprintln!("=== END PATCH ===");
+/// Choose the repository's default remote, or clear the choice with None
+pub fn set_default_remote(repo: &Repository, remote: Option<&str>) -> Result<(), String> {
+    let mut config = repo
+        .config()
+        .and_then(|config| config.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| e.message().to_string())?;
+    match remote {
+        Some(remote) => {
+            repo.find_remote(remote)
+                .map_err(|_| format!("Remote '{}' does not exist", remote))?;
+            config.set_str("remote.pushDefault", remote)
+        }
+        None => match config.remove("remote.pushDefault") {
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(()),
+            result => result,
+        },
+    }
+    .map_err(|e| e.message().to_string())
+}
 
-    // Use git apply --cached to stage the hunk
-    let mut child = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("apply")
-        .arg("--cached")
-        .arg("--unidiff-zero")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to execute git apply: {}", e))?;
+/// Get diff for a file in the working directory (unstaged changes)
+pub fn get_working_diff(
+    repo: &Repository,
+    file_path: &str,
+    staged: bool,
+) -> Result<DiffInfo, String> {
+    use git2::DiffOptions;
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin
-            .write_all(patch.as_bytes())
-            .map_err(|e| format!("Failed to write patch to stdin: {}", e))?;
+    let rename_source = if staged {
+        staged_rename_source(repo, file_path)
+    } else {
+        None
+    };
+    let read_sides = || {
+        if staged {
+            (
+                read_head_blob(repo, rename_source.as_deref().unwrap_or(file_path)),
+                read_index_blob(repo, file_path),
+            )
+        } else {
+            (
+                read_index_blob(repo, file_path),
+                read_workdir_file(repo, file_path),
+            )
+        }
+    };
+
+    if let Some(driver) = get_textconv_driver(repo, file_path) {
+        let (old, new) = read_sides();
+        return get_textconv_diff(repo, file_path, &driver, old, new).map(|info| DiffInfo {
+            old_path: rename_source.clone(),
+            ..info
+        });
     }
 
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for git apply: {}", e))?;
+    // The index holds such a file as UTF-8, the work tree in the declared encoding
+    let declared_encoding = if staged {
+        None
+    } else {
+        encoding::working_tree_encoding(repo, file_path)
+    };
+    if declared_encoding.is_some() {
+        let (old, new) = read_sides();
+        if let Some(info) = get_encoded_diff(file_path, old, new, declared_encoding)? {
+            return Ok(info);
+        }
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        eprintln!("git apply stderr: {}", stderr);
-        return Err(format!("Failed to stage hunk: {}", stderr.trim()));
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.pathspec(file_path);
+    if let Some(old_path) = &rename_source {
+        diff_opts.pathspec(old_path);
     }
+    diff_opts.context_lines(3);
 
-    eprintln!("Stage hunk successful!");
-    Ok(())
-}
+    let mut diff = if staged {
+        // Staged changes: compare HEAD to index
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
 
-/// Unstage a single hunk from staged changes
-pub fn unstage_hunk(repo_path: &str, file_path: &str, hunk: HunkData) -> Result<(), String> {
-    use std::io::Write;
-    use std::process::{Command, Stdio};
+        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_opts))
+            .map_err(|e| e.message().to_string())?
+    } else {
+        // Unstaged changes: compare index to working directory
+        repo.diff_index_to_workdir(None, Some(&mut diff_opts))
+            .map_err(|e| e.message().to_string())?
+    };
+    if rename_source.is_some() {
+        find_renames(&mut diff)?;
+    }
 
-    let patch = generate_patch(file_path, &hunk);
+    let info = parse_diff(&diff, file_path)?;
+    if info.is_binary {
+        let (old, new) = read_sides();
+        if let Some(encoded) = get_encoded_diff(file_path, old, new, None)? {
+            return Ok(DiffInfo {
+                old_mode: info.old_mode,
+                new_mode: info.new_mode,
+                old_path: info.old_path,
+                ..encoded
+            });
+        }
+    }
+    Ok(info)
+}
 
-    // Use git apply --cached -R to unstage the hunk (reverse apply to index)
-    let mut child = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("apply")
-        .arg("--cached")
-        .arg("--reverse")
-        .arg("--unidiff-zero")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to execute git apply: {}", e))?;
+/// Pair the deleted and added files of `diff` that are renames of each other
+fn find_renames(diff: &mut git2::Diff) -> Result<(), String> {
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))
+        .map_err(|e| e.message().to_string())
+}
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin
-            .write_all(patch.as_bytes())
-            .map_err(|e| format!("Failed to write patch to stdin: {}", e))?;
+/// Staged renames, from the new path to the path in HEAD
+fn staged_renames(repo: &Repository) -> HashMap<String, String> {
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().to_string());
+    let Some(head_tree) = repo.head().ok().and_then(|head| head.peel_to_tree().ok()) else {
+        return HashMap::new();
+    };
+    let Ok(mut diff) = repo.diff_tree_to_index(Some(&head_tree), None, None) else {
+        return HashMap::new();
+    };
+    if find_renames(&mut diff).is_err() {
+        return HashMap::new();
     }
+    diff.deltas()
+        .filter(|delta| delta.status() == git2::Delta::Renamed)
+        .filter_map(|delta| Some((path_of(delta.new_file())?, path_of(delta.old_file())?)))
+        .collect()
+}
 
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for git apply: {}", e))?;
+/// Path `file_path` had in HEAD, when the index holds it as a rename of that
+/// file
+fn staged_rename_source(repo: &Repository, file_path: &str) -> Option<String> {
+    let head_tree = repo.head().ok()?.peel_to_tree().ok()?;
+    // A path already in HEAD was not renamed to
+    if head_tree.get_path(std::path::Path::new(file_path)).is_ok() {
+        return None;
+    }
+    staged_renames(repo).remove(file_path)
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to unstage hunk: {}", stderr.trim()));
+/// Stage only the mode change of `file_path` (its executable bit), leaving
+/// its content changes unstaged
+pub fn stage_mode_change(repo: &Repository, file_path: &str) -> Result<(), String> {
+    let mut opts = git2::DiffOptions::new();
+    opts.pathspec(file_path).disable_pathspec_match(true);
+    let diff = repo
+        .diff_index_to_workdir(None, Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    let new_mode = diff
+        .deltas()
+        .find_map(|delta| delta_mode_change(&delta))
+        .map(|(_, new_mode)| new_mode)
+        .ok_or_else(|| format!("'{}' has no mode change to stage", file_path))?;
+    if new_mode != u32::from(git2::FileMode::BlobExecutable)
+        && new_mode != u32::from(git2::FileMode::Blob)
+    {
+        return Err(format!(
+            "Only executable bit changes can be staged alone, '{}' changed type",
+            file_path
+        ));
     }
 
-    Ok(())
+    // The entry keeps its blob, so content changes stay unstaged
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    let mut entry = index
+        .get_path(std::path::Path::new(file_path), 0)
+        .ok_or_else(|| format!("'{}' is not in the index", file_path))?;
+    entry.mode = new_mode;
+    index.add(&entry).map_err(|e| e.message().to_string())?;
+    index.write().map_err(|e| e.message().to_string())
 }
 
-/// Discard a single hunk from unstaged changes (restore from index or HEAD)
-pub fn discard_hunk(repo_path: &str, file_path: &str, hunk: HunkData) -> Result<(), String> {
-    use std::io::Write;
-    use std::process::{Command, Stdio};
+/// Line counts of the staged and of the unstaged changes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkingDiffStat {
+    pub staged: Vec<FileDiffStat>,
+    pub unstaged: Vec<FileDiffStat>, // untracked files count all their lines as added
+    pub staged_summary: DiffStatSummary,
+    pub unstaged_summary: DiffStatSummary,
+}
 
-    let patch = generate_patch(file_path, &hunk);
+/// Lines added and removed in every changed file of the working directory, in
+/// one call instead of a get_working_diff per file. Restricted to the focused
+/// paths like the status.
+pub fn get_working_diffstat(repo: &Repository) -> Result<WorkingDiffStat, String> {
+    use git2::DiffOptions;
 
-    // Use git apply -R to discard the hunk from working directory
-    let mut child = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("apply")
-        .arg("--reverse")
-        .arg("--unidiff-zero")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to execute git apply: {}", e))?;
+    let focused_paths = get_focused_paths(repo).unwrap_or_default();
+    let diff_options = |untracked: bool| {
+        let mut opts = DiffOptions::new();
+        for path in &focused_paths {
+            opts.pathspec(path);
+        }
+        opts.include_untracked(untracked)
+            .recurse_untracked_dirs(untracked)
+            .show_untracked_content(untracked);
+        opts
+    };
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin
-            .write_all(patch.as_bytes())
-            .map_err(|e| format!("Failed to write patch to stdin: {}", e))?;
-    }
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let staged_diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_options(false)))
+        .map_err(|e| e.message().to_string())?;
+    let unstaged_diff = repo
+        .diff_index_to_workdir(None, Some(&mut diff_options(true)))
+        .map_err(|e| e.message().to_string())?;
 
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for git apply: {}", e))?;
+    let (staged, staged_summary) = diff_file_stats(&staged_diff)?;
+    let (unstaged, unstaged_summary) = diff_file_stats(&unstaged_diff)?;
+    Ok(WorkingDiffStat {
+        staged,
+        unstaged,
+        staged_summary,
+        unstaged_summary,
+    })
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to discard hunk: {}", stderr.trim()));
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkingPatch {
+    pub summary: DiffStatSummary,
+    pub path: Option<String>,  // file the patch was written to
+    pub patch: Option<String>, // the patch text when it was not written to a file
+}
+
+/// The staged and unstaged changes, untracked files included, as one patch
+/// against HEAD that `git apply` (binary files too) recreates on another
+/// clone. Written to `output_path` when given, otherwise returned as text.
+/// Unlike the status it ignores the focused paths, so nothing is left out.
+pub fn export_working_diff(
+    repo: &Repository,
+    output_path: Option<&str>,
+) -> Result<WorkingPatch, String> {
+    use git2::DiffOptions;
+
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .show_binary(true);
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let diff = repo
+        .diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    if diff.deltas().len() == 0 {
+        return Err("There are no local changes to export".to_string());
     }
+    write_patch(&diff, output_path)
+}
 
-    Ok(())
+/// `diff` as a patch, written to `output_path` when given, otherwise returned
+/// as text
+fn write_patch(diff: &git2::Diff, output_path: Option<&str>) -> Result<WorkingPatch, String> {
+    use git2::DiffFormat;
+
+    let (_, summary) = diff_file_stats(diff)?;
+    let mut patch = Vec::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin() as u8);
+        }
+        patch.extend_from_slice(line.content());
+        true
+    })
+    .map_err(|e| e.message().to_string())?;
+
+    match output_path {
+        Some(path) => {
+            std::fs::write(path, &patch)
+                .map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+            Ok(WorkingPatch {
+                summary,
+                path: Some(path.to_string()),
+                patch: None,
+            })
+        }
+        None => Ok(WorkingPatch {
+            summary,
+            path: None,
+            patch: Some(String::from_utf8_lossy(&patch).to_string()),
+        }),
+    }
 }
 
+/// One version of a file in a FileThreeWay
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct GitOperationResult {
-    pub success: bool,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub requires_ssh_verification: Option<SshHostVerification>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub requires_credential: Option<CredentialRequest>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error_type: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub conflicting_files: Option<Vec<String>>,
+pub struct FileVersion {
+    pub exists: bool,
+    pub content: Option<String>, // None when the file is missing or binary
+    pub is_binary: bool,
+    pub size: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SshHostVerification {
-    pub host: String,
-    pub key_type: String,
-    pub fingerprint: String,
+impl FileVersion {
+    pub(crate) fn from_bytes(bytes: Option<&[u8]>) -> Self {
+        match bytes {
+            Some(bytes) => {
+                let is_binary = is_binary_content(bytes);
+                FileVersion {
+                    exists: true,
+                    content: (!is_binary).then(|| String::from_utf8_lossy(bytes).to_string()),
+                    is_binary,
+                    size: Some(bytes.len() as u64),
+                }
+            }
+            None => FileVersion {
+                exists: false,
+                content: None,
+                is_binary: false,
+                size: None,
+            },
+        }
+    }
 }
 
+/// The HEAD, index (staged) and working tree versions of a file, to inspect
+/// what is staged when only part of the changes are
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CredentialRequest {
-    pub credential_type: String, // "username", "password", "passphrase"
-    pub prompt: String,
-    pub host: Option<String>,
+pub struct FileThreeWay {
+    pub file_path: String,
+    pub head: FileVersion,
+    pub index: FileVersion,
+    pub workdir: FileVersion,
+    pub partially_staged: bool, // the index differs from both HEAD and the working tree
 }
 
-/// Detect the type of error from stderr
-fn detect_error_type(stderr: &str) -> Option<String> {
-    let lower = stderr.to_lowercase();
-
-    if lower.contains("host key verification failed") {
-        return Some("ssh_host_verification_failed".to_string());
-    }
-    if lower.contains("permission denied") || lower.contains("publickey") {
-        return Some("authentication_failed".to_string());
-    }
-    if lower.contains("could not read from remote")
-        || lower.contains("no se pudo leer del repositorio remoto")
-    {
-        return Some("remote_access_failed".to_string());
-    }
-    if lower.contains("connection refused") || lower.contains("conexión rechazada") {
-        return Some("connection_refused".to_string());
-    }
-    if lower.contains("connection timed out") || lower.contains("tiempo de espera agotado") {
-        return Some("connection_timeout".to_string());
-    }
-    if lower.contains("could not resolve host") || lower.contains("no se pudo resolver") {
-        return Some("host_not_found".to_string());
-    }
-    // Detect checkout conflicts (local changes would be overwritten)
-    if lower.contains("would be overwritten by checkout")
-        || lower.contains("serían sobrescritos por checkout")
-        || lower.contains("serán sobrescritos por checkout")
-    {
-        return Some("checkout_would_overwrite".to_string());
-    }
-    // Detect divergent branches (need to specify merge or rebase)
-    if lower.contains("divergent branches")
-        || lower.contains("need to specify how to reconcile")
-        || lower.contains("ramas divergentes")
-    {
-        return Some("divergent_branches".to_string());
+pub fn get_file_three_way(repo: &Repository, file_path: &str) -> Result<FileThreeWay, String> {
+    if repo.workdir().is_none() {
+        return Err("Repository has no working tree".to_string());
     }
-    // Detect missing Git identity (user.name / user.email not configured)
-    if lower.contains("author identity unknown")
-        || lower.contains("identidad del autor desconocido")
-        || lower.contains("please tell me who you are")
-        || lower.contains("empty ident name")
-        || lower.contains("no es posible auto-detectar")
-        || lower.contains("unable to auto-detect")
-    {
-        return Some("missing_identity".to_string());
-    }
-    if lower.contains("fatal:") {
-        return Some("git_error".to_string());
+    let head = read_head_blob(repo, file_path);
+    let index = read_index_blob(repo, file_path);
+    let workdir = read_workdir_file(repo, file_path);
+    if head.is_none() && index.is_none() && workdir.is_none() {
+        return Err(format!("File not found: {}", file_path));
     }
-    None
+
+    // From the status rather than the raw bytes, which differ from the blobs
+    // whenever filters (autocrlf, eol, clean/smudge) rewrite the working copy
+    let index_changes = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    let workdir_changes =
+        Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+    let partially_staged = repo
+        .status_file(std::path::Path::new(file_path))
+        .is_ok_and(|status| status.intersects(index_changes) && status.intersects(workdir_changes));
+
+    Ok(FileThreeWay {
+        file_path: file_path.to_string(),
+        partially_staged,
+        head: FileVersion::from_bytes(head.as_deref()),
+        index: FileVersion::from_bytes(index.as_deref()),
+        workdir: FileVersion::from_bytes(workdir.as_deref()),
+    })
 }
 
-/// Extract list of conflicting files from git checkout error output
-fn extract_conflicting_files(stderr: &str) -> Vec<String> {
-    let mut files = Vec::new();
-    let mut in_file_list = false;
+/// Diff of `file_path` from `old_tree` (None for an empty tree) to `new_tree`
+fn tree_file_diff(
+    repo: &Repository,
+    old_tree: Option<&git2::Tree>,
+    new_tree: &git2::Tree,
+    file_path: &str,
+) -> Result<DiffInfo, String> {
+    if let Some(driver) = get_textconv_driver(repo, file_path) {
+        let old = old_tree.and_then(|tree| read_tree_blob(repo, tree, file_path));
+        let new = read_tree_blob(repo, new_tree, file_path);
+        return get_textconv_diff(repo, file_path, &driver, old, new);
+    }
 
-    for line in stderr.lines() {
-        let trimmed = line.trim();
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.pathspec(file_path);
+    diff_opts.context_lines(3);
 
-        // Start capturing after "would be overwritten" line
-        if trimmed.contains("would be overwritten")
-            || trimmed.contains("serían sobrescritos")
-            || trimmed.contains("serán sobrescritos")
-        {
-            in_file_list = true;
-            continue;
-        }
+    let diff = repo
+        .diff_tree_to_tree(old_tree, Some(new_tree), Some(&mut diff_opts))
+        .map_err(|e| e.message().to_string())?;
 
-        // Stop at "Please commit" or "Aborting" lines
-        if trimmed.starts_with("Please")
-            || trimmed.starts_with("Por favor")
-            || trimmed.contains("Aborting")
-            || trimmed.contains("Abortando")
-        {
-            break;
+    let info = parse_diff(&diff, file_path)?;
+    if info.is_binary {
+        let old = old_tree.and_then(|tree| read_tree_blob(repo, tree, file_path));
+        let new = read_tree_blob(repo, new_tree, file_path);
+        if let Some(info) = get_encoded_diff(file_path, old, new, None)? {
+            return Ok(info);
         }
+    }
+    Ok(info)
+}
 
-        // Capture file names (non-empty lines that don't start with error)
-        if in_file_list && !trimmed.is_empty() && !trimmed.starts_with("error") {
-            files.push(trimmed.to_string());
-        }
+/// Files changed in a tree to tree diff
+fn diff_files(diff: &git2::Diff) -> Result<Vec<FileStatus>, String> {
+    let mut files = Vec::new();
+
+    diff.foreach(
+        &mut |delta, _| {
+            files.push(FileStatus {
+                path: delta_path(&delta),
+                status: delta_status(delta.status()).to_string(),
+                staged: false,
+                conflict: None,
+                old_path: None,
+            });
+
+            true
+        },
+        None,
+        None,
+        None,
+    )
+    .map_err(|e| e.message().to_string())?;
+
+    Ok(files)
+}
+
+/// Path of the file a diff delta is about, the old path for a deletion
+fn delta_path(delta: &git2::DiffDelta) -> String {
+    delta
+        .new_file()
+        .path()
+        .or_else(|| delta.old_file().path())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn delta_status(status: git2::Delta) -> &'static str {
+    match status {
+        git2::Delta::Added => "new",
+        git2::Delta::Deleted => "deleted",
+        git2::Delta::Modified => "modified",
+        git2::Delta::Renamed => "renamed",
+        git2::Delta::Copied => "copied",
+        git2::Delta::Untracked => "untracked",
+        _ => "unknown",
     }
+}
 
-    files
+/// Lines added and removed in one file of a diff
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileDiffStat {
+    pub path: String,
+    pub status: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub binary: bool, // no line counts for binary files
 }
 
-/// Parse credential prompt request from output
-fn parse_credential_request(output: &str) -> Option<CredentialRequest> {
-    let lower = output.to_lowercase();
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DiffStatSummary {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
 
-    // Check for username prompts
-    if lower.contains("username for") || lower.contains("usuario para") {
-        // Try to extract host
-        let host = if let Some(start) = output.find('\'') {
-            let rest = &output[start + 1..];
-            rest.find('\'').map(|end| rest[..end].to_string())
-        } else {
-            None
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitFiles {
+    pub files: Vec<FileDiffStat>,
+    pub summary: DiffStatSummary,
+}
+
+/// Per file line counts of `diff`, like `git diff --numstat`, and their totals
+fn diff_file_stats(diff: &git2::Diff) -> Result<(Vec<FileDiffStat>, DiffStatSummary), String> {
+    let mut files = Vec::new();
+    let mut summary = DiffStatSummary::default();
+
+    for idx in 0..diff.deltas().len() {
+        let patch = git2::Patch::from_diff(diff, idx).map_err(|e| e.message().to_string())?;
+        // No patch is made for a binary file; the binary flag is only known
+        // once the contents were loaded for the patch
+        let (delta, line_stats) = match &patch {
+            Some(patch) => (
+                patch.delta(),
+                Some(patch.line_stats().map_err(|e| e.message().to_string())?),
+            ),
+            None => match diff.get_delta(idx) {
+                Some(delta) => (delta, None),
+                None => continue,
+            },
+        };
+        let binary = line_stats.is_none() || delta.flags().is_binary();
+        let (insertions, deletions) = match line_stats {
+            Some((_, additions, deletions)) if !binary => (additions, deletions),
+            _ => (0, 0),
         };
 
-        return Some(CredentialRequest {
-            credential_type: "username".to_string(),
-            prompt: output.trim().to_string(),
-            host,
+        summary.files_changed += 1;
+        summary.insertions += insertions;
+        summary.deletions += deletions;
+        files.push(FileDiffStat {
+            path: delta_path(&delta),
+            status: delta_status(delta.status()).to_string(),
+            insertions,
+            deletions,
+            binary,
         });
     }
 
-    // Check for password prompts
-    if lower.contains("password for") || lower.contains("contraseña para") {
-        let host = if let Some(start) = output.find('\'') {
-            let rest = &output[start + 1..];
-            rest.find('\'').map(|end| rest[..end].to_string())
-        } else {
-            None
-        };
+    Ok((files, summary))
+}
 
-        return Some(CredentialRequest {
-            credential_type: "password".to_string(),
-            prompt: output.trim().to_string(),
-            host,
-        });
-    }
+/// Get diff for a file in a specific commit
+pub fn get_commit_diff(
+    repo: &Repository,
+    commit_id: &str,
+    file_path: &str,
+) -> Result<DiffInfo, String> {
+    use git2::Oid;
 
-    // Check for passphrase prompts (SSH key)
-    if lower.contains("enter passphrase") || lower.contains("introduzca la contraseña") {
-        return Some(CredentialRequest {
-            credential_type: "passphrase".to_string(),
-            prompt: output.trim().to_string(),
-            host: None,
-        });
-    }
+    let oid = Oid::from_str(commit_id).map_err(|e| e.message().to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+    let commit_tree = commit.tree().map_err(|e| e.message().to_string())?;
 
-    None
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+
+    tree_file_diff(repo, parent_tree.as_ref(), &commit_tree, file_path)
 }
 
-/// Create a git operation result for errors
-fn create_error_result(stderr: &str, stdout: &str) -> GitOperationResult {
-    // Check for SSH host verification
-    if let Some(ssh_verification) = parse_ssh_host_verification(stderr) {
-        return GitOperationResult {
-            success: false,
-            message: "SSH host verification required".to_string(),
-            requires_ssh_verification: Some(ssh_verification),
-            requires_credential: None,
-            error_type: Some("ssh_host_verification".to_string()),
-            conflicting_files: None,
-        };
-    }
+/// Get files changed in a specific commit, with the lines added and removed
+pub fn get_commit_files(repo: &Repository, commit_id: &str) -> Result<CommitFiles, String> {
+    use git2::{DiffOptions, Oid};
 
-    // Check for credential request
-    let combined = format!("{}\n{}", stdout, stderr);
-    if let Some(credential) = parse_credential_request(&combined) {
-        return GitOperationResult {
-            success: false,
-            message: credential.prompt.clone(),
-            requires_ssh_verification: None,
-            requires_credential: Some(credential),
-            error_type: Some("credential_required".to_string()),
-            conflicting_files: None,
-        };
-    }
+    let oid = Oid::from_str(commit_id).map_err(|e| e.message().to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+    let commit_tree = commit.tree().map_err(|e| e.message().to_string())?;
 
-    // Detect error type
-    let error_type = detect_error_type(stderr);
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
 
-    // If it's a checkout conflict, extract the conflicting files
-    let conflicting_files = if error_type.as_deref() == Some("checkout_would_overwrite") {
-        let files = extract_conflicting_files(stderr);
-        if files.is_empty() {
-            None
-        } else {
-            Some(files)
-        }
-    } else {
-        None
-    };
+    let mut diff_opts = DiffOptions::new();
 
-    GitOperationResult {
-        success: false,
-        message: stderr.trim().to_string(),
-        requires_ssh_verification: None,
-        requires_credential: None,
-        error_type,
-        conflicting_files,
-    }
+    let diff = repo
+        .diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&commit_tree),
+            Some(&mut diff_opts),
+        )
+        .map_err(|e| e.message().to_string())?;
+
+    let (files, summary) = diff_file_stats(&diff)?;
+    Ok(CommitFiles { files, summary })
 }
 
-/// Create a git operation result for success
-fn create_success_result(message: String) -> GitOperationResult {
-    GitOperationResult {
-        success: true,
-        message,
-        requires_ssh_verification: None,
-        requires_credential: None,
-        error_type: None,
-        conflicting_files: None,
-    }
+/// How get_refs_diff compares two refs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefsDiffMode {
+    /// `base..target`: the trees of the two refs
+    #[default]
+    TwoDot,
+    /// `base...target`: the merge base of the two refs against `target`, only
+    /// the changes made on `target` since it forked, as a pull request shows them
+    ThreeDot,
 }
 
-/// Parse SSH host verification prompt from stderr
-fn parse_ssh_host_verification(stderr: &str) -> Option<SshHostVerification> {
-    // Look for patterns like:
-    // "The authenticity of host 'bitbucket.org (185.166.143.49)' can't be established."
-    // "ED25519 key fingerprint is SHA256:ybgmFkzwOSotHTHLJgHO0QN8L0xErw6vd0VhFA9m3SM."
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefsDiff {
+    pub base_sha: String,
+    pub target_sha: String,
+    pub merge_base: Option<String>, // the commit compared against target in ThreeDot mode
+    pub files: Vec<FileStatus>,     // status of each file in `target` relative to the base
+}
 
-    if !stderr.contains("authenticity of host") || !stderr.contains("can't be established") {
-        return None;
-    }
+/// Commit `spec` (a branch, tag, SHA or any revision) points to
+fn resolve_commit<'r>(repo: &'r Repository, spec: &str) -> Result<git2::Commit<'r>, String> {
+    repo.revparse_single(spec)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| format!("Cannot resolve '{}': {}", spec, e.message()))
+}
 
-    // Extract host
-    let host = if let Some(start) = stderr.find("host '") {
-        let after_host = &stderr[start + 6..];
-        if let Some(end) = after_host.find('\'') {
-            // Handle "host 'hostname (ip)'" format
-            let host_part = &after_host[..end];
-            if let Some(space_pos) = host_part.find(' ') {
-                host_part[..space_pos].to_string()
-            } else {
-                host_part.to_string()
-            }
-        } else {
-            return None;
+/// The base and target commits of a refs comparison, plus the merge base the
+/// comparison starts from in ThreeDot mode
+fn resolve_refs_diff<'r>(
+    repo: &'r Repository,
+    base: &str,
+    target: &str,
+    mode: RefsDiffMode,
+) -> Result<(git2::Commit<'r>, git2::Commit<'r>, Option<git2::Commit<'r>>), String> {
+    let base_commit = resolve_commit(repo, base)?;
+    let target_commit = resolve_commit(repo, target)?;
+    let merge_base = match mode {
+        RefsDiffMode::TwoDot => None,
+        RefsDiffMode::ThreeDot => {
+            let oid = repo
+                .merge_base(base_commit.id(), target_commit.id())
+                .map_err(|_| format!("'{}' and '{}' have no common history", base, target))?;
+            Some(repo.find_commit(oid).map_err(|e| e.message().to_string())?)
         }
-    } else {
-        return None;
     };
+    Ok((base_commit, target_commit, merge_base))
+}
 
-    // Extract key type and fingerprint
-    // Look for lines like "ED25519 key fingerprint is SHA256:..."
-    let mut key_type = String::new();
-    let mut fingerprint = String::new();
+/// Files that differ between two branches or commits, like `git diff base target`
+/// (or `git diff base...target` in ThreeDot mode), e.g. to review `release/1.2`
+/// against `develop` before merging
+pub fn get_refs_diff(
+    repo: &Repository,
+    base: &str,
+    target: &str,
+    mode: RefsDiffMode,
+) -> Result<RefsDiff, String> {
+    let (base_commit, target_commit, merge_base) = resolve_refs_diff(repo, base, target, mode)?;
+    let from_tree = merge_base
+        .as_ref()
+        .unwrap_or(&base_commit)
+        .tree()
+        .map_err(|e| e.message().to_string())?;
+    let target_tree = target_commit.tree().map_err(|e| e.message().to_string())?;
 
-    for line in stderr.lines() {
-        if line.contains("key fingerprint") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                key_type = parts[0].to_string();
-                // Find "SHA256:" or similar
-                for part in &parts {
-                    if part.starts_with("SHA256:") || part.starts_with("MD5:") {
-                        fingerprint = part.trim_end_matches('.').to_string();
-                        break;
-                    }
-                }
-            }
-            break;
-        }
-    }
+    let diff = repo
+        .diff_tree_to_tree(Some(&from_tree), Some(&target_tree), None)
+        .map_err(|e| e.message().to_string())?;
 
-    if !host.is_empty() && !fingerprint.is_empty() {
-        Some(SshHostVerification {
-            host,
-            key_type,
-            fingerprint,
-        })
-    } else {
-        None
-    }
+    Ok(RefsDiff {
+        base_sha: base_commit.id().to_string(),
+        target_sha: target_commit.id().to_string(),
+        merge_base: merge_base.map(|commit| commit.id().to_string()),
+        files: diff_files(&diff)?,
+    })
 }
 
-/// Add a host to SSH known_hosts using ssh-keyscan
-pub fn add_ssh_known_host(host: &str) -> Result<GitOperationResult, String> {
-    use std::fs::OpenOptions;
-    use std::io::Write;
-    use std::process::Command;
+/// Diff of one file between two branches or commits, see get_refs_diff
+pub fn get_refs_file_diff(
+    repo: &Repository,
+    base: &str,
+    target: &str,
+    mode: RefsDiffMode,
+    file_path: &str,
+) -> Result<DiffInfo, String> {
+    let (base_commit, target_commit, merge_base) = resolve_refs_diff(repo, base, target, mode)?;
+    let from_tree = merge_base
+        .as_ref()
+        .unwrap_or(&base_commit)
+        .tree()
+        .map_err(|e| e.message().to_string())?;
+    let target_tree = target_commit.tree().map_err(|e| e.message().to_string())?;
 
-    // Run ssh-keyscan to get the host key
-    let output = Command::new("ssh-keyscan")
-        .arg("-t")
-        .arg("ed25519,rsa,ecdsa")
-        .arg(host)
-        .output()
-        .map_err(|e| format!("Failed to execute ssh-keyscan: {}", e))?;
+    tree_file_diff(repo, Some(&from_tree), &target_tree, file_path)
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Ok(GitOperationResult {
-            success: false,
-            message: format!("Failed to scan host keys: {}", stderr),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: Some("ssh_keyscan_failed".to_string()),
-            conflicting_files: None,
-        });
-    }
+/// Check if a file is binary based on content
+fn is_binary_content(content: &[u8]) -> bool {
+    // Check for null bytes in the first 8000 bytes (git's approach)
+    let check_len = std::cmp::min(content.len(), 8000);
+    content[..check_len].contains(&0)
+}
 
-    let host_keys = String::from_utf8_lossy(&output.stdout);
-    if host_keys.trim().is_empty() {
-        return Ok(GitOperationResult {
-            success: false,
-            message: "No host keys found for this host".to_string(),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: Some("no_host_keys".to_string()),
-            conflicting_files: None,
-        });
+/// Get the binary type based on file extension
+fn get_binary_type(file_path: &str) -> Option<String> {
+    let lower = file_path.to_lowercase();
+    if lower.ends_with(".png")
+        || lower.ends_with(".jpg")
+        || lower.ends_with(".jpeg")
+        || lower.ends_with(".gif")
+        || lower.ends_with(".bmp")
+        || lower.ends_with(".webp")
+        || lower.ends_with(".svg")
+        || lower.ends_with(".ico")
+    {
+        Some("image".to_string())
+    } else if lower.ends_with(".pdf") {
+        Some("pdf".to_string())
+    } else if let Some(kind) = media::media_kind(&lower) {
+        Some(kind.to_string())
+    } else {
+        Some("other".to_string())
     }
-
-    // Get the path to known_hosts
-    let home = std::env::var("HOME").map_err(|_| "Could not determine home directory")?;
-    let ssh_dir = format!("{}/.ssh", home);
-    let known_hosts_path = format!("{}/known_hosts", ssh_dir);
-
-    // Create .ssh directory if it doesn't exist
-    std::fs::create_dir_all(&ssh_dir)
-        .map_err(|e| format!("Failed to create .ssh directory: {}", e))?;
-
-    // Append to known_hosts
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&known_hosts_path)
-        .map_err(|e| format!("Failed to open known_hosts: {}", e))?;
-
-    file.write_all(host_keys.as_bytes())
-        .map_err(|e| format!("Failed to write to known_hosts: {}", e))?;
-
-    Ok(create_success_result(format!(
-        "Host '{}' added to known hosts",
-        host
-    )))
 }
 
-/// Execute git pull using the git command line (handles authentication properly)
-pub fn git_pull(repo_path: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("pull")
-        .env("GIT_TERMINAL_PROMPT", "0")
-        .env(
-            "GIT_SSH_COMMAND",
-            "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
-        )
-        .output()
-        .map_err(|e| format!("Failed to execute git pull: {}", e))?;
+/// Flag delete/add line pairs that differ only in line endings or trailing whitespace,
+/// so an autocrlf or editor change does not read as a full rewrite of the file.
+/// Within each run of deletions followed by the same number of additions, lines are
+/// compared pairwise.
+fn mark_eol_only_changes(hunks: &mut [DiffHunk]) {
+    for hunk in hunks.iter_mut() {
+        let lines = &mut hunk.lines;
+        let mut i = 0;
+        while i < lines.len() {
+            if lines[i].line_type != "delete" {
+                i += 1;
+                continue;
+            }
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let del_start = i;
+            while i < lines.len() && lines[i].line_type == "delete" {
+                i += 1;
+            }
+            let add_start = i;
+            while i < lines.len() && lines[i].line_type == "add" {
+                i += 1;
+            }
 
-    if output.status.success() {
-        let message =
-            if stdout.contains("Already up to date") || stdout.contains("Ya está actualizado") {
-                "Already up to date".to_string()
-            } else {
-                stdout.trim().to_string()
-            };
-        Ok(create_success_result(message))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
+            let count = add_start - del_start;
+            if count != i - add_start {
+                continue;
+            }
+            for k in 0..count {
+                let (del, add) = (del_start + k, add_start + k);
+                if lines[del].content.trim_end() == lines[add].content.trim_end() {
+                    lines[del].eol_only = true;
+                    lines[add].eol_only = true;
+                }
+            }
+        }
     }
 }
 
-/// Execute git push using the git command line (handles authentication properly)
-pub fn git_push(repo_path: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("push")
-        .env("GIT_TERMINAL_PROMPT", "0")
-        .env(
-            "GIT_SSH_COMMAND",
-            "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
-        )
-        .output()
-        .map_err(|e| format!("Failed to execute git push: {}", e))?;
+/// Parse a git2 Diff into our DiffInfo structure
+fn parse_diff(diff: &git2::Diff, file_path: &str) -> Result<DiffInfo, String> {
+    use std::cell::RefCell;
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let hunks: RefCell<Vec<DiffHunk>> = RefCell::new(Vec::new());
+    let is_binary: RefCell<bool> = RefCell::new(false);
+    let mode_change: RefCell<Option<(u32, u32)>> = RefCell::new(None);
+    let old_path: RefCell<Option<String>> = RefCell::new(None);
+    // Lines that are not UTF-8 (hunk, line, bytes), decoded once the encoding is known
+    let undecoded: RefCell<Vec<(usize, usize, Vec<u8>)>> = RefCell::new(Vec::new());
 
-    // Git push outputs to stderr even on success
-    if output.status.success() {
-        let message =
-            if stderr.contains("Everything up-to-date") || stderr.contains("Todo actualizado") {
-                "Everything up-to-date".to_string()
-            } else if stdout.is_empty() && stderr.is_empty() {
-                "Push completed successfully".to_string()
-            } else {
-                format!("{}{}", stdout, stderr).trim().to_string()
+    diff.foreach(
+        &mut |delta, _| {
+            // Check if the file is binary
+            if delta.flags().is_binary() {
+                *is_binary.borrow_mut() = true;
+            }
+            *mode_change.borrow_mut() = delta_mode_change(&delta);
+            if delta.status() == git2::Delta::Renamed {
+                *old_path.borrow_mut() = delta
+                    .old_file()
+                    .path()
+                    .map(|p| p.to_string_lossy().to_string());
+            }
+            true
+        },
+        Some(&mut |_, _binary| {
+            *is_binary.borrow_mut() = true;
+            true
+        }),
+        Some(&mut |_, hunk| {
+            let diff_hunk = DiffHunk {
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                new_start: hunk.new_start(),
+                new_lines: hunk.new_lines(),
+                lines: Vec::new(),
             };
-        Ok(create_success_result(message))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
-    }
-}
-
-/// Execute git fetch using the git command line
-pub fn git_fetch(repo_path: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+            hunks.borrow_mut().push(diff_hunk);
+            true
+        }),
+        Some(&mut |_, _hunk, line| {
+            let mut hunks_mut = hunks.borrow_mut();
+            let hunk_index = hunks_mut.len().saturating_sub(1);
+            if let Some(current_hunk) = hunks_mut.last_mut() {
+                let line_type = match line.origin() {
+                    '+' => "add",
+                    '-' => "delete",
+                    ' ' => "context",
+                    _ => "context",
+                };
 
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("fetch")
-        .arg("--all")
-        .env("GIT_TERMINAL_PROMPT", "0")
-        .env(
-            "GIT_SSH_COMMAND",
-            "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
-        )
-        .output()
-        .map_err(|e| format!("Failed to execute git fetch: {}", e))?;
+                let content = match std::str::from_utf8(line.content()) {
+                    Ok(text) => text.to_string(),
+                    Err(_) => {
+                        undecoded.borrow_mut().push((
+                            hunk_index,
+                            current_hunk.lines.len(),
+                            line.content().to_vec(),
+                        ));
+                        String::new()
+                    }
+                };
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                current_hunk.lines.push(DiffLine {
+                    content,
+                    line_type: line_type.to_string(),
+                    old_line_no: line.old_lineno(),
+                    new_line_no: line.new_lineno(),
+                    eol_only: false,
+                });
+            }
+            true
+        }),
+    )
+    .map_err(|e| e.message().to_string())?;
 
-    if output.status.success() {
-        let message = if stdout.is_empty() && stderr.is_empty() {
-            "Fetch completed".to_string()
-        } else {
-            format!("{}{}", stdout, stderr).trim().to_string()
-        };
-        Ok(create_success_result(message))
+    let binary = *is_binary.borrow();
+    let binary_type = if binary {
+        get_binary_type(file_path)
     } else {
-        Ok(create_error_result(&stderr, &stdout))
+        None
+    };
+    let mode_change = mode_change.into_inner();
+
+    let mut hunks = hunks.into_inner();
+    // The lines that are not UTF-8 together tell the file's encoding best
+    let undecoded = undecoded.into_inner();
+    let mut text_encoding = None;
+    if !undecoded.is_empty() {
+        let sample: Vec<u8> = undecoded
+            .iter()
+            .flat_map(|(_, _, bytes)| bytes.iter().copied())
+            .collect();
+        text_encoding = encoding::decode(&sample, None).1;
+        for (hunk_index, line_index, bytes) in &undecoded {
+            hunks[*hunk_index].lines[*line_index].content =
+                encoding::decode(bytes, text_encoding).0.into_owned();
+        }
     }
+    mark_eol_only_changes(&mut hunks);
+
+    Ok(DiffInfo {
+        file_path: file_path.to_string(),
+        old_content: None,
+        new_content: None,
+        hunks,
+        is_binary: binary,
+        binary_type,
+        file_size: None,
+        diff_driver: None,
+        page: None,
+        encoding: text_encoding.map(|e| e.name().to_string()),
+        old_mode: mode_change.map(|(old, _)| old),
+        new_mode: mode_change.map(|(_, new)| new),
+        old_path: old_path.into_inner(),
+    })
 }
 
 // ============================================================================
-// Git Operations with Options
+// Diff Drivers (textconv)
 // ============================================================================
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FetchOptions {
-    pub remote: Option<String>,
-    pub all: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PullOptions {
-    pub remote: String,
-    pub branch: String,
-    pub rebase: bool,
-    pub autostash: bool,
+/// A `diff.<driver>.textconv` converter selected through the file's `diff` attribute,
+/// or one of the converters built into Forky
+struct TextconvDriver {
+    name: String,
+    command: TextconvCommand,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PushOptions {
-    pub branch: String,
-    pub remote: String,
-    pub remote_branch: String,
-    pub push_tags: bool,
-    pub force_with_lease: bool,
+enum TextconvCommand {
+    External(String),
+    Notebook,
 }
 
-/// Execute git fetch with options
-pub fn git_fetch_with_options(
-    repo_path: &str,
-    options: FetchOptions,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let mut cmd = Command::new("git");
-    cmd.arg("-C").arg(repo_path).arg("fetch");
-    cmd.env("GIT_TERMINAL_PROMPT", "0");
-    cmd.env(
-        "GIT_SSH_COMMAND",
-        "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
-    );
+/// Look up the textconv driver for a file, following `.gitattributes` (`*.docx diff=word`)
+/// and the matching `diff.<driver>.textconv` config entry
+fn get_textconv_driver(repo: &Repository, file_path: &str) -> Option<TextconvDriver> {
+    let config = repo.config().ok()?;
 
-    if options.all {
-        cmd.arg("--all");
-    } else if let Some(remote) = &options.remote {
-        cmd.arg(remote);
-    } else {
-        cmd.arg("origin");
+    let value = repo
+        .get_attr(
+            std::path::Path::new(file_path),
+            "diff",
+            git2::AttrCheckFlags::default(),
+        )
+        .ok()
+        .flatten();
+
+    // `diff` / `-diff` are plain booleans; only `diff=<name>` selects a driver
+    if let git2::AttrValue::String(name) = git2::AttrValue::from_string(value) {
+        if let Ok(command) = config.get_string(&format!("diff.{}.textconv", name)) {
+            if !command.trim().is_empty() {
+                return Some(TextconvDriver {
+                    name: name.to_string(),
+                    command: TextconvCommand::External(command),
+                });
+            }
+        }
     }
 
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to execute git fetch: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-    if output.status.success() {
-        let message = if stdout.is_empty() && stderr.is_empty() {
-            "Fetch completed".to_string()
-        } else {
-            format!("{}{}", stdout, stderr).trim().to_string()
-        };
-        Ok(create_success_result(message))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
+    // Notebooks get the built-in converter unless `forky.notebookDiff` is set to false
+    if file_path.to_lowercase().ends_with(".ipynb")
+        && config.get_bool("forky.notebookDiff").unwrap_or(true)
+    {
+        return Some(TextconvDriver {
+            name: "notebook".to_string(),
+            command: TextconvCommand::Notebook,
+        });
     }
-}
 
-/// Execute git pull with options
-pub fn git_pull_with_options(
-    repo_path: &str,
-    options: PullOptions,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+    None
+}
 
-    let mut cmd = Command::new("git");
-    cmd.arg("-C").arg(repo_path).arg("pull");
-    cmd.env("GIT_TERMINAL_PROMPT", "0");
-    cmd.env(
-        "GIT_SSH_COMMAND",
-        "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
-    );
+/// Run a textconv command on content, the same way git does: the content is written
+/// to a scratch file whose path is appended to the command line
+fn run_textconv(
+    repo: &Repository,
+    driver: &TextconvDriver,
+    file_path: &str,
+    content: &[u8],
+) -> Result<Vec<u8>, String> {
+    let command = match &driver.command {
+        TextconvCommand::External(command) => command,
+        // Content that does not parse as a notebook is diffed as-is
+        TextconvCommand::Notebook => {
+            return Ok(notebook::notebook_to_text(content)
+                .map(String::into_bytes)
+                .unwrap_or_else(|| content.to_vec()));
+        }
+    };
 
-    if options.rebase {
-        cmd.arg("--rebase");
-    } else {
-        // Explicitly tell git to merge (not rebase) when divergent branches exist
-        cmd.arg("--no-rebase");
-    }
+    // Keep the original extension, some converters dispatch on it
+    let extension = std::path::Path::new(file_path)
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let input = ScratchFile::create("textconv", &extension, content)
+        .map_err(|e| format!("Failed to write textconv input: {}", e))?;
 
-    if options.autostash {
-        cmd.arg("--autostash");
+    let command_line = format!("{} \"{}\"", command, input.path().to_string_lossy());
+    let mut cmd = environment::shell_command(&command_line);
+    if let Some(workdir) = repo.workdir() {
+        cmd.current_dir(workdir);
     }
 
-    // Add remote and branch
-    cmd.arg(&options.remote);
-    cmd.arg(&options.branch);
-
     let output = cmd
         .output()
-        .map_err(|e| format!("Failed to execute git pull: {}", e))?;
+        .map_err(|e| format!("Failed to run textconv '{}': {}", driver.name, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Textconv driver '{}' failed: {}",
+            driver.name,
+            stderr.trim()
+        ));
+    }
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    Ok(output.stdout)
+}
 
-    if output.status.success() {
-        let message =
-            if stdout.contains("Already up to date") || stdout.contains("Ya está actualizado") {
-                "Already up to date".to_string()
-            } else {
-                stdout.trim().to_string()
+/// Convert hunks of an in-memory patch into our DiffHunk structure
+fn parse_patch_hunks(patch: &git2::Patch) -> Result<Vec<DiffHunk>, String> {
+    let mut hunks = Vec::new();
+
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx).map_err(|e| e.message().to_string())?;
+        let mut lines = Vec::with_capacity(line_count);
+
+        for line_idx in 0..line_count {
+            let line = patch
+                .line_in_hunk(hunk_idx, line_idx)
+                .map_err(|e| e.message().to_string())?;
+            let line_type = match line.origin() {
+                '+' => "add",
+                '-' => "delete",
+                _ => "context",
             };
-        Ok(create_success_result(message))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
+            lines.push(DiffLine {
+                content: String::from_utf8_lossy(line.content()).to_string(),
+                line_type: line_type.to_string(),
+                old_line_no: line.old_lineno(),
+                new_line_no: line.new_lineno(),
+                eol_only: false,
+            });
+        }
+
+        hunks.push(DiffHunk {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
     }
+
+    mark_eol_only_changes(&mut hunks);
+    Ok(hunks)
 }
 
-/// Execute git push with options
-pub fn git_push_with_options(
-    repo_path: &str,
-    options: PushOptions,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Diff the textconv output of the old and new versions of a file.
+/// A missing side (added or deleted file) is treated as empty.
+fn get_textconv_diff(
+    repo: &Repository,
+    file_path: &str,
+    driver: &TextconvDriver,
+    old: Option<Vec<u8>>,
+    new: Option<Vec<u8>>,
+) -> Result<DiffInfo, String> {
+    let convert = |content: Option<Vec<u8>>| -> Result<Option<String>, String> {
+        content
+            .map(|c| run_textconv(repo, driver, file_path, &c))
+            .transpose()
+            .map(|out| out.map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+    };
 
-    let mut cmd = Command::new("git");
-    cmd.arg("-C").arg(repo_path).arg("push");
-    cmd.env("GIT_TERMINAL_PROMPT", "0");
-    cmd.env(
-        "GIT_SSH_COMMAND",
-        "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
-    );
+    let file_size = new.as_ref().or(old.as_ref()).map(|c| c.len() as u64);
+    let old_text = convert(old)?;
+    let new_text = convert(new)?;
+    let hunks = diff_texts(file_path, old_text.as_deref(), new_text.as_deref())?;
 
-    if options.force_with_lease {
-        cmd.arg("--force-with-lease");
-    }
+    Ok(DiffInfo {
+        file_path: file_path.to_string(),
+        old_content: old_text,
+        new_content: new_text,
+        hunks,
+        is_binary: false,
+        binary_type: None,
+        file_size,
+        diff_driver: Some(driver.name.clone()),
+        page: None,
+        encoding: None,
+        old_mode: None,
+        new_mode: None,
+        old_path: None,
+    })
+}
+
+/// Diff two texts in memory. A missing side (added or deleted file) is treated
+/// as empty.
+fn diff_texts(
+    file_path: &str,
+    old_text: Option<&str>,
+    new_text: Option<&str>,
+) -> Result<Vec<DiffHunk>, String> {
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts.context_lines(3);
+    let path = std::path::Path::new(file_path);
+    let patch = git2::Patch::from_buffers(
+        old_text.unwrap_or("").as_bytes(),
+        Some(path),
+        new_text.unwrap_or("").as_bytes(),
+        Some(path),
+        Some(&mut diff_opts),
+    )
+    .map_err(|e| e.message().to_string())?;
+    parse_patch_hunks(&patch)
+}
 
-    if options.push_tags {
-        cmd.arg("--tags");
+/// Diff of a file libgit2 cannot show as text: UTF-16 content, which looks
+/// binary, or a work tree copy in the encoding its `working-tree-encoding`
+/// attribute declares (`new_encoding`). Both versions are converted to UTF-8
+/// and diffed in memory; None when neither needs converting.
+fn get_encoded_diff(
+    file_path: &str,
+    old: Option<Vec<u8>>,
+    new: Option<Vec<u8>>,
+    new_encoding: Option<&'static encoding_rs::Encoding>,
+) -> Result<Option<DiffInfo>, String> {
+    let old_encoding = old.as_deref().and_then(encoding::detect_utf16);
+    let new_encoding = new_encoding.or_else(|| new.as_deref().and_then(encoding::detect_utf16));
+    if old_encoding.is_none() && new_encoding.is_none() {
+        return Ok(None);
     }
 
-    // Add remote
-    cmd.arg(&options.remote);
+    let file_size = new.as_ref().or(old.as_ref()).map(|c| c.len() as u64);
+    let old_text = old
+        .as_deref()
+        .map(|c| encoding::decode(c, old_encoding).0.into_owned());
+    let new_text = new
+        .as_deref()
+        .map(|c| encoding::decode(c, new_encoding).0.into_owned());
+    let hunks = diff_texts(file_path, old_text.as_deref(), new_text.as_deref())?;
 
-    // Add branch:remote_branch refspec
-    let refspec = format!("{}:{}", options.branch, options.remote_branch);
-    cmd.arg(&refspec);
+    Ok(Some(DiffInfo {
+        file_path: file_path.to_string(),
+        old_content: old_text,
+        new_content: new_text,
+        hunks,
+        is_binary: false,
+        binary_type: None,
+        file_size,
+        diff_driver: None,
+        page: None,
+        encoding: new_encoding.or(old_encoding).map(|e| e.name().to_string()),
+        old_mode: None,
+        new_mode: None,
+        old_path: None,
+    }))
+}
 
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to execute git push: {}", e))?;
+/// Read content of an untracked file and create diff info showing all lines as
+/// additions. A file longer than NEW_FILE_PAGE_LINES is returned a page at a
+/// time, `range` (the first page by default), so previewing a huge generated
+/// file stays fast; the whole file is still staged by stage_file.
+pub fn get_untracked_file_diff(
+    repo: &Repository,
+    file_path: &str,
+    range: Option<LineRange>,
+) -> Result<DiffInfo, String> {
+    let workdir = repo.workdir().ok_or("No working directory")?;
+    let full_path = workdir.join(file_path);
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    // Read the file
+    let content = std::fs::read(&full_path).map_err(|e| e.to_string())?;
+    let file_size = content.len() as u64;
 
-    // Git push outputs to stderr even on success
-    if output.status.success() {
-        let message =
-            if stderr.contains("Everything up-to-date") || stderr.contains("Todo actualizado") {
-                "Everything up-to-date".to_string()
-            } else if stdout.is_empty() && stderr.is_empty() {
-                "Push completed successfully".to_string()
-            } else {
-                format!("{}{}", stdout, stderr).trim().to_string()
-            };
-        Ok(create_success_result(message))
+    if let Some(driver) = get_textconv_driver(repo, file_path) {
+        return get_textconv_diff(repo, file_path, &driver, None, Some(content));
+    }
+
+    // Known before the binary check, which UTF-16 text would fail
+    let known_encoding = encoding::working_tree_encoding(repo, file_path)
+        .or_else(|| encoding::detect_utf16(&content));
+
+    // Check if binary
+    if known_encoding.is_none() && is_binary_content(&content) {
+        return Ok(DiffInfo {
+            file_path: file_path.to_string(),
+            old_content: None,
+            new_content: None,
+            hunks: Vec::new(),
+            is_binary: true,
+            binary_type: get_binary_type(file_path),
+            file_size: Some(file_size),
+            diff_driver: None,
+            page: None,
+            encoding: None,
+            old_mode: None,
+            new_mode: None,
+            old_path: None,
+        });
+    }
+
+    // Convert to string and create diff lines
+    let (text, text_encoding) = encoding::decode(&content, known_encoding);
+    let total_lines = text.lines().count();
+    let range = range.unwrap_or(LineRange {
+        start: 0,
+        count: NEW_FILE_PAGE_LINES,
+    });
+    let start = range.start.min(total_lines);
+    let count = range.count.min(total_lines - start);
+    let page = (start > 0 || count < total_lines).then_some(DiffPage {
+        start,
+        count,
+        total_lines,
+    });
+
+    let diff_lines: Vec<DiffLine> = text
+        .lines()
+        .enumerate()
+        .skip(start)
+        .take(count)
+        .map(|(i, line)| DiffLine {
+            content: format!("{}\n", line),
+            line_type: "add".to_string(),
+            old_line_no: None,
+            new_line_no: Some((i + 1) as u32),
+            eol_only: false,
+        })
+        .collect();
+
+    let hunk = DiffHunk {
+        old_start: 0,
+        old_lines: 0,
+        new_start: (start + 1) as u32,
+        new_lines: count as u32,
+        lines: diff_lines,
+    };
+
+    Ok(DiffInfo {
+        file_path: file_path.to_string(),
+        old_content: None,
+        // The full text is only sent along with the whole file
+        new_content: page.is_none().then(|| text.to_string()),
+        hunks: vec![hunk],
+        is_binary: false,
+        binary_type: None,
+        file_size: Some(file_size),
+        diff_driver: None,
+        page,
+        encoding: text_encoding.map(|e| e.name().to_string()),
+        old_mode: None,
+        new_mode: None,
+        old_path: None,
+    })
+}
+
+/// Get diff for a deleted file from the index, showing all lines as deletions
+pub fn get_deleted_file_diff(repo: &Repository, file_path: &str) -> Result<DiffInfo, String> {
+    // Get the file content from HEAD
+    let head = repo.head().map_err(|e| e.message().to_string())?;
+    let tree = head.peel_to_tree().map_err(|e| e.message().to_string())?;
+
+    let entry = tree
+        .get_path(std::path::Path::new(file_path))
+        .map_err(|e| e.message().to_string())?;
+
+    let blob = repo
+        .find_blob(entry.id())
+        .map_err(|e| e.message().to_string())?;
+    let content = blob.content();
+    let file_size = content.len() as u64;
+
+    if let Some(driver) = get_textconv_driver(repo, file_path) {
+        return get_textconv_diff(repo, file_path, &driver, Some(content.to_vec()), None);
+    }
+
+    // Known before the binary check, which UTF-16 text would fail
+    let known_encoding = encoding::detect_utf16(content);
+
+    // Check if binary
+    if known_encoding.is_none() && (blob.is_binary() || is_binary_content(content)) {
+        return Ok(DiffInfo {
+            file_path: file_path.to_string(),
+            old_content: None,
+            new_content: None,
+            hunks: Vec::new(),
+            is_binary: true,
+            binary_type: get_binary_type(file_path),
+            file_size: Some(file_size),
+            diff_driver: None,
+            page: None,
+            encoding: None,
+            old_mode: None,
+            new_mode: None,
+            old_path: None,
+        });
+    }
+
+    // Convert to string and create diff lines
+    let (text, text_encoding) = encoding::decode(content, known_encoding);
+    let lines: Vec<&str> = text.lines().collect();
+
+    let diff_lines: Vec<DiffLine> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| DiffLine {
+            content: format!("{}\n", line),
+            line_type: "delete".to_string(),
+            old_line_no: Some((i + 1) as u32),
+            new_line_no: None,
+            eol_only: false,
+        })
+        .collect();
+
+    let hunk = DiffHunk {
+        old_start: 1,
+        old_lines: lines.len() as u32,
+        new_start: 0,
+        new_lines: 0,
+        lines: diff_lines,
+    };
+
+    Ok(DiffInfo {
+        file_path: file_path.to_string(),
+        old_content: Some(text.to_string()),
+        new_content: None,
+        hunks: vec![hunk],
+        is_binary: false,
+        binary_type: None,
+        file_size: Some(file_size),
+        diff_driver: None,
+        page: None,
+        encoding: text_encoding.map(|e| e.name().to_string()),
+        old_mode: None,
+        new_mode: None,
+        old_path: None,
+    })
+}
+
+/// Stage a file
+pub fn stage_file(repo: &Repository, file_path: &str) -> Result<(), String> {
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+
+    // Check if file exists or was deleted
+    let workdir = repo.workdir().ok_or("No working directory")?;
+    let full_path = workdir.join(file_path);
+
+    if full_path.exists() {
+        index
+            .add_path(std::path::Path::new(file_path))
+            .map_err(|e| e.message().to_string())?;
     } else {
-        Ok(create_error_result(&stderr, &stdout))
+        // File was deleted, remove from index
+        index
+            .remove_path(std::path::Path::new(file_path))
+            .map_err(|e| e.message().to_string())?;
     }
+
+    index.write().map_err(|e| e.message().to_string())?;
+    Ok(())
 }
 
-/// Get separated unstaged and staged files
-pub fn get_file_status_separated(
+/// Unstage a file
+pub fn unstage_file(repo: &Repository, file_path: &str) -> Result<(), String> {
+    unstage_files(repo, &[file_path.to_string()])
+}
+
+/// Unstage several files at once
+pub fn unstage_files(repo: &Repository, file_paths: &[String]) -> Result<(), String> {
+    let head = match repo.head() {
+        Ok(head) => head,
+        // Nothing is committed yet, so there is no version to reset to: the
+        // files leave the index and become untracked again
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+            let mut index = repo.index().map_err(|e| e.message().to_string())?;
+            for path in file_paths {
+                index
+                    .remove_path(std::path::Path::new(path))
+                    .map_err(|e| e.message().to_string())?;
+            }
+            return index.write().map_err(|e| e.message().to_string());
+        }
+        Err(e) => return Err(e.message().to_string()),
+    };
+    let head_commit = head.peel_to_commit().map_err(|e| e.message().to_string())?;
+    let head_tree = head_commit.tree().map_err(|e| e.message().to_string())?;
+
+    // A staged rename is unstaged as a whole, the old path included. Only a
+    // path missing from HEAD can be the new path of a rename.
+    let renames = if file_paths
+        .iter()
+        .any(|path| head_tree.get_path(std::path::Path::new(path)).is_err())
+    {
+        staged_renames(repo)
+    } else {
+        HashMap::new()
+    };
+    let mut paths = file_paths.to_vec();
+    paths.extend(
+        file_paths
+            .iter()
+            .filter_map(|path| renames.get(path).cloned()),
+    );
+    repo.reset_default(Some(head_commit.as_object()), paths)
+        .map_err(|e| e.message().to_string())?;
+
+    Ok(())
+}
+
+/// Working tree changes inside a directory, with untracked directories expanded
+/// to the files they contain (ignored files are skipped)
+fn get_directory_changes(
     repo: &Repository,
-) -> Result<(Vec<FileStatus>, Vec<FileStatus>), String> {
+    dir_path: &str,
+    staged: bool,
+) -> Result<Vec<(String, git2::Status)>, String> {
+    let dir = dir_path.trim_end_matches('/');
+    // "" and "." are the repository root: every change is inside it
+    let is_root = dir.is_empty() || dir == ".";
     let mut opts = StatusOptions::new();
     opts.include_untracked(true)
         .recurse_untracked_dirs(true)
         .include_ignored(false);
+    if !is_root {
+        opts.pathspec(dir);
+    }
 
     let statuses = repo
         .statuses(Some(&mut opts))
         .map_err(|e| e.message().to_string())?;
-    let mut unstaged = Vec::new();
-    let mut staged = Vec::new();
 
-    for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("").to_string();
-        let status = entry.status();
+    let prefix = if is_root {
+        String::new()
+    } else {
+        format!("{}/", dir)
+    };
+    let wanted = if staged {
+        Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE
+    } else {
+        Status::WT_NEW
+            | Status::WT_MODIFIED
+            | Status::WT_DELETED
+            | Status::WT_RENAMED
+            | Status::WT_TYPECHANGE
+    };
 
-        // Check for staged changes (index changes)
-        if status.is_index_new() {
-            staged.push(FileStatus {
-                path: path.clone(),
-                status: "new".to_string(),
-                staged: true,
-            });
-        } else if status.is_index_modified() {
-            staged.push(FileStatus {
-                path: path.clone(),
-                status: "modified".to_string(),
-                staged: true,
-            });
-        } else if status.is_index_deleted() {
-            staged.push(FileStatus {
-                path: path.clone(),
-                status: "deleted".to_string(),
-                staged: true,
-            });
-        } else if status.is_index_renamed() {
-            staged.push(FileStatus {
-                path: path.clone(),
-                status: "renamed".to_string(),
-                staged: true,
-            });
-        }
+    Ok(statuses
+        .iter()
+        .filter(|entry| entry.status().intersects(wanted))
+        .filter_map(|entry| entry.path().map(|path| (path.to_string(), entry.status())))
+        .filter(|(path, _)| path.starts_with(&prefix))
+        .collect())
+}
 
-        // Check for unstaged changes (working tree changes)
-        if status.is_wt_new() {
-            unstaged.push(FileStatus {
-                path: path.clone(),
-                status: "untracked".to_string(),
-                staged: false,
-            });
-        } else if status.is_wt_modified() {
-            unstaged.push(FileStatus {
-                path: path.clone(),
-                status: "modified".to_string(),
-                staged: false,
-            });
-        } else if status.is_wt_deleted() {
-            unstaged.push(FileStatus {
-                path: path.clone(),
-                status: "deleted".to_string(),
-                staged: false,
-            });
-        } else if status.is_wt_renamed() {
-            unstaged.push(FileStatus {
-                path: path.clone(),
-                status: "renamed".to_string(),
-                staged: false,
-            });
+/// Stage every changed file inside a directory individually, returning the staged paths
+pub fn stage_directory(repo: &Repository, dir_path: &str) -> Result<Vec<String>, String> {
+    let changes = get_directory_changes(repo, dir_path, false)?;
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+
+    for (path, status) in &changes {
+        if status.is_wt_deleted() {
+            index
+                .remove_path(std::path::Path::new(path))
+                .map_err(|e| e.message().to_string())?;
+        } else {
+            index
+                .add_path(std::path::Path::new(path))
+                .map_err(|e| e.message().to_string())?;
         }
     }
 
-    Ok((unstaged, staged))
+    index.write().map_err(|e| e.message().to_string())?;
+    Ok(changes.into_iter().map(|(path, _)| path).collect())
 }
 
-/// Commit message with subject and body separated
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CommitMessage {
-    pub subject: String,
-    pub body: String,
+/// Unstage every staged file inside a directory, returning the unstaged paths
+pub fn unstage_directory(repo: &Repository, dir_path: &str) -> Result<Vec<String>, String> {
+    let paths: Vec<String> = get_directory_changes(repo, dir_path, true)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    if paths.is_empty() {
+        return Ok(paths);
+    }
+
+    // Goes through unstage_files so that a file renamed into the directory
+    // takes its old path, wherever that was, with it
+    unstage_files(repo, &paths)?;
+    Ok(paths)
 }
 
-/// Get the last commit message (subject and body)
-pub fn get_last_commit_message(repo: &Repository) -> Result<CommitMessage, String> {
-    let head = repo.head().map_err(|e| e.message().to_string())?;
-    let commit = head.peel_to_commit().map_err(|e| e.message().to_string())?;
-    let message = commit.message().unwrap_or("");
+/// Discard changes in a file (restore from HEAD or delete if untracked)
+pub fn discard_file(repo_path: &str, file_path: &str, is_untracked: bool) -> Result<(), String> {
+    if is_untracked {
+        // For untracked files, simply delete them
+        let full_path = std::path::Path::new(repo_path).join(file_path);
+        if full_path.is_dir() {
+            std::fs::remove_dir_all(&full_path)
+                .map_err(|e| format!("Failed to remove directory: {}", e))?;
+        } else {
+            std::fs::remove_file(&full_path)
+                .map_err(|e| format!("Failed to remove file: {}", e))?;
+        }
+    } else {
+        // For tracked files, restore the index and work tree from HEAD
+        let output = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .args(capabilities::restore_args(RestoreSource::Commit("HEAD")))
+            .arg("--")
+            .arg(file_path)
+            .output()
+            .map_err(|e| format!("Failed to restore {}: {}", file_path, e))?;
 
-    // Split into subject (first line) and body (rest)
-    let parts: Vec<&str> = message.splitn(2, '\n').collect();
-    let subject = parts[0].trim().to_string();
-    let body = parts
-        .get(1)
-        .map(|s| s.trim().to_string())
-        .unwrap_or_default();
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to discard changes: {}", stderr.trim()));
+        }
+    }
 
-    Ok(CommitMessage { subject, body })
+    Ok(())
 }
 
-/// Execute git checkout to switch branches
-pub fn git_checkout(repo_path: &str, branch_name: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+// ============================================================================
+// Hunk Operations (Stage/Unstage/Discard individual hunks)
+// ============================================================================
 
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("checkout")
-        .arg(branch_name)
-        .output()
-        .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
+/// Structure to receive hunk data from frontend
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HunkData {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<HunkLineData>,
+    // Renames and mode changes; when absent both sides use the file path / unchanged mode
+    #[serde(default)]
+    pub old_path: Option<String>,
+    #[serde(default)]
+    pub new_path: Option<String>,
+    #[serde(default)]
+    pub old_mode: Option<u32>,
+    #[serde(default)]
+    pub new_mode: Option<u32>,
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HunkLineData {
+    pub content: String,
+    pub line_type: String, // "add", "delete", "context"
+}
 
-    if output.status.success() {
-        // Git checkout success messages often go to stderr
-        let message = if stderr.contains("Switched to branch") || stderr.contains("Cambiado a rama")
-        {
-            stderr.trim().to_string()
-        } else if !stdout.is_empty() {
-            stdout.trim().to_string()
-        } else {
-            format!("Switched to branch '{}'", branch_name)
-        };
-        Ok(create_success_result(message))
-    } else {
-        // Common errors: uncommitted changes, branch doesn't exist
-        Ok(create_error_result(&stderr, &stdout))
+/// The line a diff adds after a line with no final newline ("\\ No newline at end
+/// of file"), which reaches hunk operations as a context line
+fn is_missing_newline_marker(line: &HunkLineData) -> bool {
+    line.content.starts_with("\n\\")
+}
+
+/// The `similarity index` and `index` values of the file a hunk belongs to, as
+/// libgit2 prints them in the file's diff. A patch needs them to carry a rename
+/// or a mode change.
+#[derive(Debug, Default, Clone)]
+struct FileHeader {
+    similarity: Option<String>,      // "87%"
+    index: Option<(String, String)>, // abbreviated blob ids of both sides
+}
+
+impl FileHeader {
+    /// The header of the inverse change
+    fn reversed(self) -> Self {
+        FileHeader {
+            similarity: self.similarity,
+            index: self.index.map(|(old, new)| (new, old)),
+        }
     }
 }
 
-/// Execute git checkout with automatic stash/pop for handling uncommitted changes
-/// This function:
-/// 1. Stashes current changes (including untracked files)
-/// 2. Checks out the target branch
-/// 3. Optionally pops the stash to restore changes
-pub fn git_checkout_with_stash(
-    repo_path: &str,
-    branch_name: &str,
-    restore_changes: bool,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Read the file header of a hunk's rename or mode change from the staged
+/// (HEAD -> index) or unstaged (index -> workdir) diff it comes from
+fn file_header(
+    repo: &Repository,
+    file_path: &str,
+    hunk: &HunkData,
+    staged: bool,
+) -> Result<FileHeader, String> {
+    let old_path = hunk.old_path.as_deref().unwrap_or(file_path);
+    let new_path = hunk.new_path.as_deref().unwrap_or(file_path);
+    let mode_change =
+        matches!((hunk.old_mode, hunk.new_mode), (Some(old), Some(new)) if old != new);
+    if old_path == new_path && !mode_change {
+        return Ok(FileHeader::default());
+    }
 
-    // Step 1: Stash all changes including untracked files
-    let stash_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("stash")
-        .arg("push")
-        .arg("-u")
-        .arg("-m")
-        .arg(format!("Auto-stash before switching to {}", branch_name))
-        .output()
-        .map_err(|e| format!("Failed to execute git stash: {}", e))?;
+    let mut opts = git2::DiffOptions::new();
+    opts.pathspec(old_path)
+        .pathspec(new_path)
+        .include_untracked(!staged)
+        .show_untracked_content(!staged);
+    let mut diff = if staged {
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))
+    } else {
+        repo.diff_index_to_workdir(None, Some(&mut opts))
+    }
+    .map_err(|e| e.message().to_string())?;
+    if old_path != new_path {
+        diff.find_similar(Some(
+            git2::DiffFindOptions::new()
+                .renames(true)
+                .for_untracked(true),
+        ))
+        .map_err(|e| e.message().to_string())?;
+    }
 
-    if !stash_output.status.success() {
-        let stderr = String::from_utf8_lossy(&stash_output.stderr).to_string();
-        return Ok(GitOperationResult {
-            success: false,
-            message: format!("Failed to stash changes: {}", stderr.trim()),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: Some("stash_failed".to_string()),
-            conflicting_files: None,
-        });
+    let path_is =
+        |file: git2::DiffFile, path: &str| file.path() == Some(std::path::Path::new(path));
+    let position = diff
+        .deltas()
+        .position(|delta| {
+            path_is(delta.old_file(), old_path) && path_is(delta.new_file(), new_path)
+        })
+        .ok_or_else(|| {
+            if old_path == new_path {
+                format!("'{}' has no mode change to apply", new_path)
+            } else {
+                format!("'{}' is not a rename of '{}'", new_path, old_path)
+            }
+        })?;
+    let mut patch = git2::Patch::from_diff(&diff, position)
+        .map_err(|e| e.message().to_string())?
+        .ok_or_else(|| format!("No diff for '{}'", new_path))?;
+    let text = patch.to_buf().map_err(|e| e.message().to_string())?;
+
+    let mut header = FileHeader::default();
+    for line in String::from_utf8_lossy(&text)
+        .lines()
+        .take_while(|line| !line.starts_with("--- ") && !line.starts_with("@@ "))
+    {
+        if let Some(similarity) = line.strip_prefix("similarity index ") {
+            header.similarity = Some(similarity.to_string());
+        } else if let Some(ids) = line.strip_prefix("index ") {
+            // Drop the mode that follows the ids when both sides share it
+            let ids = ids.split(' ').next().unwrap_or(ids);
+            header.index = ids
+                .split_once("..")
+                .map(|(old, new)| (old.to_string(), new.to_string()));
+        }
     }
+    Ok(header)
+}
 
-    // Step 2: Checkout the target branch
-    let checkout_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("checkout")
-        .arg(branch_name)
-        .output()
-        .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
+/// Generate a unified diff patch from hunk data
+fn generate_patch(file_path: &str, hunk: &HunkData, header: &FileHeader) -> String {
+    let mut patch = String::new();
+    let old_path = hunk.old_path.as_deref().unwrap_or(file_path);
+    let new_path = hunk.new_path.as_deref().unwrap_or(file_path);
 
-    if !checkout_output.status.success() {
-        let stderr = String::from_utf8_lossy(&checkout_output.stderr).to_string();
+    // Header
+    patch.push_str(&format!("diff --git a/{} b/{}\n", old_path, new_path));
+    let mode_change = match (hunk.old_mode, hunk.new_mode) {
+        (Some(old_mode), Some(new_mode)) if old_mode != new_mode => {
+            patch.push_str(&format!("old mode {:o}\n", old_mode));
+            patch.push_str(&format!("new mode {:o}\n", new_mode));
+            true
+        }
+        _ => false,
+    };
+    if old_path != new_path {
+        // libgit2 only parses renames that carry a similarity score
+        if let Some(similarity) = &header.similarity {
+            patch.push_str(&format!("similarity index {}\n", similarity));
+        }
+        patch.push_str(&format!("rename from {}\n", old_path));
+        patch.push_str(&format!("rename to {}\n", new_path));
+    }
 
-        // Checkout failed, try to restore the stash
-        let _ = Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("stash")
-            .arg("pop")
-            .output();
+    // A mode-only change or pure rename has no hunk body
+    if hunk.lines.is_empty() {
+        return patch;
+    }
 
-        return Ok(GitOperationResult {
-            success: false,
-            message: format!("Checkout failed (stash restored): {}", stderr.trim()),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: Some("checkout_failed".to_string()),
-            conflicting_files: None,
-        });
+    // libgit2 expects an index line between extended headers and the file headers
+    if let Some((old_id, new_id)) = &header.index {
+        if mode_change || old_path != new_path {
+            patch.push_str(&format!("index {}..{}\n", old_id, new_id));
+        }
     }
+    patch.push_str(&format!("--- a/{}\n", old_path));
+    patch.push_str(&format!("+++ b/{}\n", new_path));
 
-    // Step 3: Optionally pop the stash to restore changes
-    if restore_changes {
-        let pop_output = Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("stash")
-            .arg("pop")
-            .output()
-            .map_err(|e| format!("Failed to execute git stash pop: {}", e))?;
+    // Hunk header
+    patch.push_str(&format!(
+        "@@ -{},{} +{},{} @@\n",
+        hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+    ));
 
-        if !pop_output.status.success() {
-            let stderr = String::from_utf8_lossy(&pop_output.stderr).to_string();
-            // Checkout succeeded but pop failed - likely conflicts
-            return Ok(GitOperationResult {
-                success: true,
-                message: format!(
-                    "Switched to '{}' but failed to restore changes. Your changes are in stash. Error: {}",
-                    branch_name,
-                    stderr.trim()
-                ),
-                requires_ssh_verification: None,
-                requires_credential: None,
-                error_type: Some("stash_pop_conflict".to_string()),
-                conflicting_files: None,
-            });
+    // Lines - content might already have newline, so we need to handle both cases
+    for line in &hunk.lines {
+        // Rebuilt below from the line that lacks the newline
+        if is_missing_newline_marker(line) {
+            continue;
         }
+        let prefix = match line.line_type.as_str() {
+            "add" => "+",
+            "delete" => "-",
+            "context" => " ",
+            _ => " ",
+        };
 
-        Ok(GitOperationResult {
-            success: true,
-            message: format!("Switched to '{}' and restored changes", branch_name),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: None,
-            conflicting_files: None,
-        })
-    } else {
-        Ok(GitOperationResult {
-            success: true,
-            message: format!("Switched to '{}' (changes saved in stash)", branch_name),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: None,
-            conflicting_files: None,
-        })
+        // Content might already end with a newline; keep any CR so CRLF files still match
+        match line.content.strip_suffix('\n') {
+            Some(content) => patch.push_str(&format!("{}{}\n", prefix, content)),
+            None => patch.push_str(&format!(
+                "{}{}\n\\ No newline at end of file\n",
+                prefix, line.content
+            )),
+        }
     }
+
+    patch
 }
 
-/// Create a local branch that tracks a remote branch and switch to it
-pub fn git_checkout_track(
+/// Pipe a patch into `git apply` with the given flags. Messages are kept in
+/// English: apply_working_diff reads the outcome of each file from them.
+fn run_git_apply(
     repo_path: &str,
-    local_branch: &str,
-    remote_branch: &str,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+    patch: &str,
+    args: &[&str],
+) -> Result<std::process::Output, String> {
+    use std::io::Write;
+    use std::process::Stdio;
 
-    // git checkout -b <local_branch> --track <remote_branch>
-    let output = Command::new("git")
+    let mut child = git_command()
+        .env("LC_ALL", "C")
         .arg("-C")
         .arg(repo_path)
-        .arg("checkout")
-        .arg("-b")
-        .arg(local_branch)
-        .arg("--track")
-        .arg(remote_branch)
-        .output()
-        .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        .arg("apply")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git apply: {}", e))?;
 
-    if output.status.success() {
-        let message = if stderr.contains("Switched to") || stderr.contains("Cambiado a") {
-            stderr.trim().to_string()
-        } else if !stdout.is_empty() {
-            stdout.trim().to_string()
-        } else {
-            format!(
-                "Branch '{}' set up to track remote branch '{}'",
-                local_branch, remote_branch
-            )
-        };
-        Ok(create_success_result(message))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(patch.as_bytes())
+            .map_err(|e| format!("Failed to write patch to stdin: {}", e))?;
     }
+
+    child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for git apply: {}", e))
 }
 
-/// Execute git commit with message and optional amend
-pub fn git_commit(
-    repo_path: &str,
-    message: &str,
-    amend: bool,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Swap the sides of a hunk, turning an index -> workdir change into its inverse.
+/// Deletions stay ahead of the additions next to them: libgit2 only accepts a
+/// missing final newline on an addition once no deletions follow.
+fn reverse_hunk(hunk: &HunkData) -> HunkData {
+    let mut lines = Vec::new();
+    let mut added = Vec::new();
+    for line in &hunk.lines {
+        if is_missing_newline_marker(line) {
+            continue;
+        }
+        let reversed = |line_type: &str| HunkLineData {
+            content: line.content.clone(),
+            line_type: line_type.to_string(),
+        };
+        match line.line_type.as_str() {
+            "add" => lines.push(reversed("delete")),
+            "delete" => added.push(reversed("add")),
+            other => {
+                lines.append(&mut added);
+                lines.push(reversed(other));
+            }
+        }
+    }
+    lines.append(&mut added);
+
+    HunkData {
+        old_start: hunk.new_start,
+        old_lines: hunk.new_lines,
+        new_start: hunk.old_start,
+        new_lines: hunk.old_lines,
+        lines,
+        old_path: hunk.new_path.clone(),
+        new_path: hunk.old_path.clone(),
+        old_mode: hunk.new_mode,
+        new_mode: hunk.old_mode,
+    }
+}
 
-    let mut cmd = Command::new("git");
-    cmd.arg("-C").arg(repo_path).arg("commit");
-    cmd.arg("-m").arg(message);
+/// Apply a single hunk to the index with libgit2, optionally as a dry run
+fn apply_hunk_to_index(
+    repo: &Repository,
+    file_path: &str,
+    hunk: &HunkData,
+    header: &FileHeader,
+    check_only: bool,
+) -> Result<(), String> {
+    let patch = generate_patch(file_path, hunk, header);
+    let diff = git2::Diff::from_buffer(patch.as_bytes()).map_err(|e| e.message().to_string())?;
+
+    let mut options = git2::ApplyOptions::new();
+    options.check(check_only);
+    repo.apply(&diff, git2::ApplyLocation::Index, Some(&mut options))
+        .map_err(|e| e.message().to_string())
+}
 
-    if amend {
-        cmd.arg("--amend");
+/// Refuse hunk operations on files whose diff was converted to UTF-8 for
+/// display: the hunk lines no longer match the bytes of either side.
+fn ensure_not_transcoded(repo: &Repository, file_path: &str) -> Result<(), String> {
+    let workdir_content = repo
+        .workdir()
+        .and_then(|workdir| std::fs::read(workdir.join(file_path)).ok());
+    let index_content = repo
+        .index()
+        .ok()
+        .and_then(|index| index.get_path(std::path::Path::new(file_path), 0))
+        .and_then(|entry| repo.find_blob(entry.id).ok())
+        .map(|blob| blob.content().to_vec());
+    let transcoded = encoding::working_tree_encoding(repo, file_path).is_some()
+        || [workdir_content, index_content]
+            .iter()
+            .flatten()
+            .any(|content| {
+                encoding::detect_utf16(content).is_some()
+                    || (!content.contains(&0) && std::str::from_utf8(content).is_err())
+            });
+    if transcoded {
+        return Err(
+            "Files converted from another encoding can only be staged or discarded whole"
+                .to_string(),
+        );
     }
+    Ok(())
+}
 
-    cmd.env("GIT_TERMINAL_PROMPT", "0");
+/// Refuse hunk operations on files diffed through a textconv driver: the hunks
+/// describe the converter's output, not the file's bytes.
+fn ensure_not_textconv(repo: &Repository, file_path: &str) -> Result<(), String> {
+    match get_textconv_driver(repo, file_path) {
+        Some(driver) => Err(format!(
+            "Files shown through the '{}' diff driver can only be staged or discarded whole",
+            driver.name
+        )),
+        None => Ok(()),
+    }
+}
 
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to execute git commit: {}", e))?;
+/// Stage a single hunk from unstaged changes
+pub fn stage_hunk(repo: &Repository, file_path: &str, hunk: HunkData) -> Result<(), String> {
+    ensure_not_textconv(repo, file_path)?;
+    ensure_not_transcoded(repo, file_path)?;
+    let header = file_header(repo, file_path, &hunk, false)?;
+    apply_hunk_to_index(repo, file_path, &hunk, &header, false)
+        .map_err(|e| format!("Failed to stage hunk: {}", e))
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+/// Unstage a single hunk from staged changes
+pub fn unstage_hunk(repo: &Repository, file_path: &str, hunk: HunkData) -> Result<(), String> {
+    ensure_not_textconv(repo, file_path)?;
+    ensure_not_transcoded(repo, file_path)?;
+    // Applying the reversed hunk to the index restores the HEAD side of it
+    let header = file_header(repo, file_path, &hunk, true)?.reversed();
+    apply_hunk_to_index(repo, file_path, &reverse_hunk(&hunk), &header, false)
+        .map_err(|e| format!("Failed to unstage hunk: {}", e))
+}
 
-    if output.status.success() {
-        // Parse commit info from output
-        let message = if stdout.contains("create mode") || stdout.contains("delete mode") {
-            // Get first line which usually contains the commit summary
-            stdout
-                .lines()
-                .next()
-                .unwrap_or("Commit created")
-                .to_string()
-        } else if !stdout.trim().is_empty() {
-            stdout.trim().to_string()
-        } else {
-            "Commit created successfully".to_string()
-        };
-        Ok(create_success_result(message))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
+/// Discard a single hunk from unstaged changes (restore from index or HEAD)
+pub fn discard_hunk(repo_path: &str, file_path: &str, hunk: HunkData) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.message().to_string())?;
+    ensure_not_textconv(&repo, file_path)?;
+    ensure_not_transcoded(&repo, file_path)?;
+    let header = file_header(&repo, file_path, &hunk, false)?;
+    let patch = generate_patch(file_path, &hunk, &header);
+
+    // Use git apply -R to discard the hunk from working directory. The hunks
+    // generated here may have no context lines around them.
+    let output = run_git_apply(repo_path, &patch, &["--reverse", "--unidiff-zero"])?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to discard hunk: {}", stderr.trim()));
     }
+
+    Ok(())
 }
 
-/// Add a new remote to the repository
-pub fn git_add_remote(
-    repo_path: &str,
-    name: &str,
-    url: &str,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Result of previewing a hunk operation without touching the index or working tree
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HunkPatchPreview {
+    pub patch: String,
+    pub can_apply: bool,
+    pub check_output: String, // why the patch does not apply, empty when it does
+}
 
-    let mut cmd = Command::new("git");
-    cmd.arg("-C")
-        .arg(repo_path)
-        .arg("remote")
-        .arg("add")
-        .arg(name)
-        .arg(url);
+/// Generate the patch a hunk operation ("stage", "unstage", "discard") would apply
+/// and dry-run it the same way the operation itself applies it
+pub fn preview_hunk_patch(
+    repo: &Repository,
+    file_path: &str,
+    hunk: HunkData,
+    operation: &str,
+) -> Result<HunkPatchPreview, String> {
+    let header = file_header(repo, file_path, &hunk, operation == "unstage")?;
+    let check = match operation {
+        "stage" => apply_hunk_to_index(repo, file_path, &hunk, &header, true),
+        "unstage" => apply_hunk_to_index(
+            repo,
+            file_path,
+            &reverse_hunk(&hunk),
+            &header.clone().reversed(),
+            true,
+        ),
+        "discard" => {
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| "Repository has no working directory".to_string())?;
+            let patch = generate_patch(file_path, &hunk, &header);
+            let output = run_git_apply(
+                &workdir.to_string_lossy(),
+                &patch,
+                &["--check", "--reverse", "--unidiff-zero"],
+            )?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+        }
+        _ => return Err(format!("Unknown hunk operation: {}", operation)),
+    };
+    let check = ensure_not_textconv(repo, file_path)
+        .and_then(|()| ensure_not_transcoded(repo, file_path))
+        .and(check);
+
+    Ok(HunkPatchPreview {
+        patch: generate_patch(file_path, &hunk, &header),
+        can_apply: check.is_ok(),
+        check_output: check.err().unwrap_or_default(),
+    })
+}
 
-    cmd.env("GIT_TERMINAL_PROMPT", "0");
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitOperationResult {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requires_ssh_verification: Option<SshHostVerification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requires_credential: Option<CredentialRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflicting_files: Option<Vec<String>>,
+}
 
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to execute git remote add: {}", e))?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SshHostVerification {
+    pub host: String,
+    pub key_type: String,
+    pub fingerprint: String,
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CredentialRequest {
+    pub credential_type: String, // "username", "password", "passphrase"
+    pub prompt: String,
+    pub host: Option<String>,
+}
 
-    if output.status.success() {
-        Ok(create_success_result(format!(
-            "Remote '{}' added successfully",
-            name
-        )))
-    } else {
-        Ok(create_error_result(&stderr, &stdout))
+/// Detect the type of error from stderr
+fn detect_error_type(stderr: &str) -> Option<String> {
+    let lower = stderr.to_lowercase();
+
+    if lower.contains("host key verification failed") {
+        return Some("ssh_host_verification_failed".to_string());
+    }
+    if lower.contains("permission denied") || lower.contains("publickey") {
+        return Some("authentication_failed".to_string());
+    }
+    if lower.contains("could not read from remote")
+        || lower.contains("no se pudo leer del repositorio remoto")
+    {
+        return Some("remote_access_failed".to_string());
+    }
+    if lower.contains("connection refused") || lower.contains("conexión rechazada") {
+        return Some("connection_refused".to_string());
+    }
+    if lower.contains("connection timed out") || lower.contains("tiempo de espera agotado") {
+        return Some("connection_timeout".to_string());
+    }
+    if lower.contains("could not resolve host") || lower.contains("no se pudo resolver") {
+        return Some("host_not_found".to_string());
+    }
+    // Detect checkout conflicts (local changes would be overwritten)
+    if lower.contains("would be overwritten by checkout")
+        || lower.contains("serían sobrescritos por checkout")
+        || lower.contains("serán sobrescritos por checkout")
+    {
+        return Some("checkout_would_overwrite".to_string());
+    }
+    // Detect divergent branches (need to specify merge or rebase)
+    if lower.contains("divergent branches")
+        || lower.contains("need to specify how to reconcile")
+        || lower.contains("ramas divergentes")
+    {
+        return Some("divergent_branches".to_string());
+    }
+    // Detect missing Git identity (user.name / user.email not configured)
+    if lower.contains("author identity unknown")
+        || lower.contains("identidad del autor desconocido")
+        || lower.contains("please tell me who you are")
+        || lower.contains("empty ident name")
+        || lower.contains("no es posible auto-detectar")
+        || lower.contains("unable to auto-detect")
+    {
+        return Some("missing_identity".to_string());
+    }
+    if lower.contains("fatal:") {
+        return Some("git_error".to_string());
     }
+    None
 }
 
-/// Test connection to a remote URL using git ls-remote
-pub fn git_test_remote_connection(url: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let mut cmd = Command::new("git");
-    cmd.arg("ls-remote")
-        .arg("--exit-code")
-        .arg("--heads")
-        .arg(url);
+/// Extract list of conflicting files from git checkout error output
+fn extract_conflicting_files(stderr: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut in_file_list = false;
 
-    cmd.env("GIT_TERMINAL_PROMPT", "0");
+    for line in stderr.lines() {
+        let trimmed = line.trim();
 
-    // Set a timeout-like behavior by limiting refs
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to test connection: {}", e))?;
+        // Start capturing after "would be overwritten" line
+        if trimmed.contains("would be overwritten")
+            || trimmed.contains("serían sobrescritos")
+            || trimmed.contains("serán sobrescritos")
+        {
+            in_file_list = true;
+            continue;
+        }
 
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        // Stop at "Please commit" or "Aborting" lines
+        if trimmed.starts_with("Please")
+            || trimmed.starts_with("Por favor")
+            || trimmed.contains("Aborting")
+            || trimmed.contains("Abortando")
+        {
+            break;
+        }
 
-    if output.status.success() {
-        Ok(create_success_result("Connection successful".to_string()))
-    } else {
-        // Check for common errors
-        if stderr.contains("Host key verification failed") {
-            Ok(create_error_result(
-                "SSH host key verification failed. Add the host to known_hosts first.",
-                &stderr,
-            ))
-        } else if stderr.contains("Permission denied") || stderr.contains("Authentication failed") {
-            Ok(create_error_result(
-                "Authentication failed. Check your credentials.",
-                &stderr,
-            ))
-        } else if stderr.contains("Could not resolve host") {
-            Ok(create_error_result(
-                "Could not resolve host. Check the URL.",
-                &stderr,
-            ))
-        } else if stderr.contains("Connection refused") {
-            Ok(create_error_result(
-                "Connection refused. Check if the server is accessible.",
-                &stderr,
-            ))
-        } else {
-            Ok(create_error_result("Could not connect to remote", &stderr))
+        // Capture file names (non-empty lines that don't start with error)
+        if in_file_list && !trimmed.is_empty() && !trimmed.starts_with("error") {
+            files.push(trimmed.to_string());
         }
     }
+
+    files
 }
 
-/// Create a new branch at a specific starting point
-pub fn git_create_branch(
-    repo_path: &str,
-    branch_name: &str,
-    start_point: &str,
-    checkout: bool,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Parse credential prompt request from output
+fn parse_credential_request(output: &str) -> Option<CredentialRequest> {
+    let lower = output.to_lowercase();
 
-    if checkout {
-        // git checkout -b <branch_name> <start_point>
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("checkout")
-            .arg("-b")
-            .arg(branch_name)
-            .arg(start_point)
-            .output()
-            .map_err(|e| format!("Failed to execute git checkout -b: {}", e))?;
+    // Check for username prompts
+    if lower.contains("username for") || lower.contains("usuario para") {
+        // Try to extract host
+        let host = if let Some(start) = output.find('\'') {
+            let rest = &output[start + 1..];
+            rest.find('\'').map(|end| rest[..end].to_string())
+        } else {
+            None
+        };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Some(CredentialRequest {
+            credential_type: "username".to_string(),
+            prompt: output.trim().to_string(),
+            host,
+        });
+    }
 
-        if output.status.success() {
-            let message = if stderr.contains("Switched to") || stderr.contains("Cambiado a") {
-                stderr.trim().to_string()
-            } else if !stdout.is_empty() {
-                stdout.trim().to_string()
-            } else {
-                format!("Switched to a new branch '{}'", branch_name)
-            };
-            Ok(create_success_result(message))
+    // Check for password prompts
+    if lower.contains("password for") || lower.contains("contraseña para") {
+        let host = if let Some(start) = output.find('\'') {
+            let rest = &output[start + 1..];
+            rest.find('\'').map(|end| rest[..end].to_string())
         } else {
-            Ok(create_error_result(&stderr, &stdout))
-        }
-    } else {
-        // git branch <branch_name> <start_point>
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("branch")
-            .arg(branch_name)
-            .arg(start_point)
-            .output()
-            .map_err(|e| format!("Failed to execute git branch: {}", e))?;
+            None
+        };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Some(CredentialRequest {
+            credential_type: "password".to_string(),
+            prompt: output.trim().to_string(),
+            host,
+        });
+    }
 
-        if output.status.success() {
-            Ok(create_success_result(format!(
-                "Branch '{}' created",
-                branch_name
-            )))
-        } else {
-            Ok(create_error_result(&stderr, &stdout))
-        }
+    // Check for passphrase prompts (SSH key)
+    if lower.contains("enter passphrase") || lower.contains("introduzca la contraseña") {
+        return Some(CredentialRequest {
+            credential_type: "passphrase".to_string(),
+            prompt: output.trim().to_string(),
+            host: None,
+        });
     }
+
+    None
 }
 
-/// Creates a new tag at the specified commit/branch
-/// If message is provided, creates an annotated tag; otherwise creates a lightweight tag
-/// If push_to_remotes is true, pushes the tag to all remotes
-pub fn git_create_tag(
-    repo_path: &str,
-    tag_name: &str,
-    start_point: &str,
-    message: Option<&str>,
-    push_to_remotes: bool,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Create a git operation result for errors
+pub(crate) fn create_error_result(stderr: &str, stdout: &str) -> GitOperationResult {
+    // Check for SSH host verification
+    if let Some(ssh_verification) = parse_ssh_host_verification(stderr) {
+        return GitOperationResult {
+            success: false,
+            message: "SSH host verification required".to_string(),
+            requires_ssh_verification: Some(ssh_verification),
+            requires_credential: None,
+            error_type: Some("ssh_host_verification".to_string()),
+            conflicting_files: None,
+        };
+    }
 
-    // Create the tag
-    let output = if let Some(msg) = message {
-        if msg.trim().is_empty() {
-            // Lightweight tag
-            Command::new("git")
-                .arg("-C")
-                .arg(repo_path)
-                .arg("tag")
-                .arg(tag_name)
-                .arg(start_point)
-                .output()
-                .map_err(|e| format!("Failed to execute git tag: {}", e))?
+    // Check for credential request
+    let combined = format!("{}\n{}", stdout, stderr);
+    if let Some(credential) = parse_credential_request(&combined) {
+        return GitOperationResult {
+            success: false,
+            message: credential.prompt.clone(),
+            requires_ssh_verification: None,
+            requires_credential: Some(credential),
+            error_type: Some("credential_required".to_string()),
+            conflicting_files: None,
+        };
+    }
+
+    // Detect error type
+    let error_type = detect_error_type(stderr);
+
+    // If it's a checkout conflict, extract the conflicting files
+    let conflicting_files = if error_type.as_deref() == Some("checkout_would_overwrite") {
+        let files = extract_conflicting_files(stderr);
+        if files.is_empty() {
+            None
         } else {
-            // Annotated tag with message
-            Command::new("git")
-                .arg("-C")
-                .arg(repo_path)
-                .arg("tag")
-                .arg("-a")
-                .arg(tag_name)
-                .arg(start_point)
-                .arg("-m")
-                .arg(msg)
-                .output()
-                .map_err(|e| format!("Failed to execute git tag -a: {}", e))?
+            Some(files)
         }
     } else {
-        // Lightweight tag
-        Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("tag")
-            .arg(tag_name)
-            .arg(start_point)
-            .output()
-            .map_err(|e| format!("Failed to execute git tag: {}", e))?
+        None
     };
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    GitOperationResult {
+        success: false,
+        message: stderr.trim().to_string(),
+        requires_ssh_verification: None,
+        requires_credential: None,
+        error_type,
+        conflicting_files,
+    }
+}
 
-    if !output.status.success() {
-        return Ok(create_error_result(&stderr, &stdout));
+/// Create a git operation result for success
+pub(crate) fn create_success_result(message: String) -> GitOperationResult {
+    GitOperationResult {
+        success: true,
+        message,
+        requires_ssh_verification: None,
+        requires_credential: None,
+        error_type: None,
+        conflicting_files: None,
     }
+}
 
-    // If push_to_remotes is true, push the tag to all remotes
-    if push_to_remotes {
-        let push_output = Command::new("git")
-            .arg("-C")
-            .arg(repo_path)
-            .arg("push")
-            .arg("--tags")
-            .output()
-            .map_err(|e| format!("Failed to execute git push --tags: {}", e))?;
+/// Parse SSH host verification prompt from stderr
+fn parse_ssh_host_verification(stderr: &str) -> Option<SshHostVerification> {
+    // Look for patterns like:
+    // "The authenticity of host 'bitbucket.org (185.166.143.49)' can't be established."
+    // "ED25519 key fingerprint is SHA256:ybgmFkzwOSotHTHLJgHO0QN8L0xErw6vd0VhFA9m3SM."
 
-        let _push_stdout = String::from_utf8_lossy(&push_output.stdout).to_string();
-        let push_stderr = String::from_utf8_lossy(&push_output.stderr).to_string();
+    if !stderr.contains("authenticity of host") || !stderr.contains("can't be established") {
+        return None;
+    }
 
-        if push_output.status.success() {
-            Ok(create_success_result(format!(
-                "Tag '{}' created and pushed",
-                tag_name
-            )))
+    // Extract host
+    let host = if let Some(start) = stderr.find("host '") {
+        let after_host = &stderr[start + 6..];
+        if let Some(end) = after_host.find('\'') {
+            // Handle "host 'hostname (ip)'" format
+            let host_part = &after_host[..end];
+            if let Some(space_pos) = host_part.find(' ') {
+                host_part[..space_pos].to_string()
+            } else {
+                host_part.to_string()
+            }
         } else {
-            // Tag was created but push failed
-            Ok(GitOperationResult {
-                success: false,
-                message: format!(
-                    "Tag '{}' created but push failed: {}",
-                    tag_name,
-                    push_stderr.trim()
-                ),
-                requires_ssh_verification: None,
-                requires_credential: None,
-                error_type: Some("push_failed".to_string()),
-                conflicting_files: None,
-            })
+            return None;
         }
     } else {
-        Ok(create_success_result(format!("Tag '{}' created", tag_name)))
+        return None;
+    };
+
+    // Extract key type and fingerprint
+    // Look for lines like "ED25519 key fingerprint is SHA256:..."
+    let mut key_type = String::new();
+    let mut fingerprint = String::new();
+
+    for line in stderr.lines() {
+        if line.contains("key fingerprint") {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 4 {
+                key_type = parts[0].to_string();
+                // Find "SHA256:" or similar
+                for part in &parts {
+                    if part.starts_with("SHA256:") || part.starts_with("MD5:") {
+                        fingerprint = part.trim_end_matches('.').to_string();
+                        break;
+                    }
+                }
+            }
+            break;
+        }
+    }
+
+    if !host.is_empty() && !fingerprint.is_empty() {
+        Some(SshHostVerification {
+            host,
+            key_type,
+            fingerprint,
+        })
+    } else {
+        None
     }
 }
 
-/// Renames a local branch
-/// If rename_remote is true and the branch has an upstream, also renames the remote branch
-pub fn git_rename_branch(
-    repo_path: &str,
-    old_name: &str,
-    new_name: &str,
-    rename_remote: bool,
-    remote_name: Option<&str>,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+/// Run a network git command with `--progress` (already added by the caller),
+/// reporting progress parsed from stderr. Returns whether it succeeded, its
+/// stdout, and the stderr lines that were not progress.
+fn run_with_progress(
+    mut cmd: std::process::Command,
+    action: &str,
+    mut on_progress: impl FnMut(GitProgress),
+) -> Result<(bool, String, String), String> {
+    use std::process::Stdio;
+
+    let mut child = cmd
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env(
+            "GIT_SSH_COMMAND",
+            "ssh -o BatchMode=yes -o StrictHostKeyChecking=ask",
+        )
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git {}: {}", action, e))?;
+
+    // Drain stdout on its own thread: a full pipe would block git while we are
+    // still waiting for stderr to close
+    let stdout_reader = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = std::io::Read::read_to_end(&mut stdout, &mut buffer);
+            buffer
+        })
+    });
 
-    // Rename local branch: git branch -m old_name new_name
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("branch")
-        .arg("-m")
-        .arg(old_name)
-        .arg(new_name)
-        .output()
-        .map_err(|e| format!("Failed to execute git branch -m: {}", e))?;
+    // Progress lines are reported as they arrive; everything else is kept for the result
+    let mut messages = Vec::new();
+    if let Some(stderr) = child.stderr.take() {
+        progress::read_lines(stderr, |line| match progress::parse_progress_line(line) {
+            Some(progress) => on_progress(progress),
+            None => messages.push(line.to_string()),
+        });
+    }
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for git {}: {}", action, e))?;
+    let stdout = stdout_reader
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    let stdout = String::from_utf8_lossy(&stdout).to_string();
+    Ok((status.success(), stdout, messages.join("\n")))
+}
 
-    if !output.status.success() {
-        return Ok(create_error_result(&stderr, &stdout));
+// ============================================================================
+// Clone
+// ============================================================================
+
+/// Clone `url` into `destination`, reporting progress parsed from git's stderr
+pub fn git_clone(
+    url: &str,
+    destination: &str,
+    on_progress: impl FnMut(GitProgress),
+) -> Result<GitOperationResult, String> {
+    let mut cmd = git_command();
+    cmd.arg("clone")
+        .arg("--progress")
+        .arg("--")
+        .arg(url)
+        .arg(destination);
+    let (success, stdout, stderr) = run_with_progress(cmd, "clone", on_progress)?;
+
+    if success {
+        Ok(create_success_result(format!(
+            "Cloned {} into {}",
+            url, destination
+        )))
+    } else {
+        Ok(create_error_result(&stderr, &stdout))
     }
+}
 
-    // If rename_remote is true and we have a remote name, also rename on remote
-    if rename_remote {
-        if let Some(remote) = remote_name {
-            // Push the new branch name to remote
-            let push_output = Command::new("git")
-                .arg("-C")
-                .arg(repo_path)
-                .arg("push")
-                .arg(remote)
-                .arg(new_name)
-                .output()
-                .map_err(|e| format!("Failed to push new branch: {}", e))?;
+// ============================================================================
+// Patch Files and Dropped Paths
+// ============================================================================
 
-            if !push_output.status.success() {
-                let push_stderr = String::from_utf8_lossy(&push_output.stderr).to_string();
-                return Ok(GitOperationResult {
-                    success: false,
-                    message: format!(
-                        "Local branch renamed but failed to push to remote: {}",
-                        push_stderr.trim()
-                    ),
-                    requires_ssh_verification: None,
-                    requires_credential: None,
-                    error_type: Some("push_failed".to_string()),
-                    conflicting_files: None,
-                });
-            }
+/// Whether a path looks like a patch file (`git diff` / `git format-patch` output)
+fn is_patch_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("patch") || ext.eq_ignore_ascii_case("diff"))
+}
 
-            // Delete the old branch from remote
-            let delete_output = Command::new("git")
-                .arg("-C")
-                .arg(repo_path)
-                .arg("push")
-                .arg(remote)
-                .arg("--delete")
-                .arg(old_name)
-                .output()
-                .map_err(|e| format!("Failed to delete old remote branch: {}", e))?;
+/// Apply a .patch/.diff file to the working tree, and to the index as well when
+/// `to_index` is set. With `check_only` nothing is modified.
+pub fn apply_patch_file(
+    repo_path: &str,
+    patch_file: &str,
+    to_index: bool,
+    check_only: bool,
+) -> Result<GitOperationResult, String> {
+    let patch = std::fs::read_to_string(patch_file)
+        .map_err(|e| format!("Failed to read patch file: {}", e))?;
 
-            if !delete_output.status.success() {
-                let delete_stderr = String::from_utf8_lossy(&delete_output.stderr).to_string();
-                return Ok(GitOperationResult {
-                    success: false,
-                    message: format!(
-                        "Branch renamed and pushed, but failed to delete old remote branch: {}",
-                        delete_stderr.trim()
-                    ),
-                    requires_ssh_verification: None,
-                    requires_credential: None,
-                    error_type: Some("delete_remote_failed".to_string()),
-                    conflicting_files: None,
-                });
-            }
+    let mut args = vec!["--whitespace=nowarn"];
+    if to_index {
+        args.push("--index");
+    }
+    if check_only {
+        args.push("--check");
+    }
 
-            // Set upstream for the new branch
-            let upstream_output = Command::new("git")
-                .arg("-C")
-                .arg(repo_path)
-                .arg("branch")
-                .arg("-u")
-                .arg(format!("{}/{}", remote, new_name))
-                .arg(new_name)
-                .output()
-                .map_err(|e| format!("Failed to set upstream: {}", e))?;
+    let output = run_git_apply(repo_path, &patch, &args)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
 
-            if upstream_output.status.success() {
-                Ok(create_success_result(format!(
-                    "Branch renamed from '{}' to '{}' (local and remote)",
-                    old_name, new_name
-                )))
-            } else {
-                // Upstream setting failed but main operation succeeded
-                Ok(create_success_result(format!(
-                    "Branch renamed from '{}' to '{}' (upstream may need manual setup)",
-                    old_name, new_name
-                )))
-            }
+    if output.status.success() {
+        let name = std::path::Path::new(patch_file)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| patch_file.to_string());
+        let message = if check_only {
+            format!("{} applies cleanly", name)
         } else {
-            Ok(create_success_result(format!(
-                "Branch renamed from '{}' to '{}'",
-                old_name, new_name
-            )))
-        }
+            format!("Applied {}", name)
+        };
+        Ok(create_success_result(message))
     } else {
-        Ok(create_success_result(format!(
-            "Branch renamed from '{}' to '{}'",
-            old_name, new_name
-        )))
+        Ok(create_error_result(&stderr, &stdout))
     }
 }
 
-/// Deletes a local branch
-/// If force is true, uses -D (force delete), otherwise uses -d (safe delete)
-/// If delete_remote is true and remote_name is provided, also deletes the remote branch
-pub fn git_delete_branch(
-    repo_path: &str,
-    branch_name: &str,
-    force: bool,
-    delete_remote: bool,
-    remote_name: Option<&str>,
-) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RejectedFile {
+    pub path: String,
+    pub hunks: Vec<u32>, // 1-based numbers of the hunks left in `<path>.rej`
+}
 
-    // Delete local branch: git branch -d/-D branch_name
-    let delete_flag = if force { "-D" } else { "-d" };
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("branch")
-        .arg(delete_flag)
-        .arg(branch_name)
-        .output()
-        .map_err(|e| format!("Failed to execute git branch {}: {}", delete_flag, e))?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PatchApplyResult {
+    pub success: bool, // every file applied without conflicts or rejects
+    pub message: String,
+    pub applied_files: Vec<String>,
+    pub conflicted_files: Vec<String>, // merged with conflict markers (three-way)
+    pub rejected_files: Vec<RejectedFile>,
+}
 
+/// Apply a patch shared by someone else (e.g. from export_working_diff) to
+/// the working tree. With `three_way`, files whose hunks do not apply are
+/// merged from the blobs the patch was made against, leaving conflicts to
+/// resolve; this stages the result and needs those blobs in the repository.
+/// Otherwise the hunks that apply are applied and the others are left in
+/// `.rej` files next to the file.
+pub fn apply_working_diff(
+    repo_path: &str,
+    patch: &str,
+    three_way: bool,
+) -> Result<PatchApplyResult, String> {
+    if patch.trim().is_empty() {
+        return Err("The patch is empty".to_string());
+    }
+    let mode = if three_way { "--3way" } else { "--reject" };
+    let output = run_git_apply(
+        repo_path,
+        patch,
+        &["--verbose", "--whitespace=nowarn", mode],
+    )?;
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    if !output.status.success() {
-        // Check if it's an unmerged branch error
-        if stderr.contains("not fully merged") {
-            return Ok(GitOperationResult {
-                success: false,
-                message: format!(
-                    "Branch '{}' is not fully merged. Use force delete to remove it anyway.",
-                    branch_name
-                ),
-                requires_ssh_verification: None,
-                requires_credential: None,
-                error_type: Some("not_merged".to_string()),
-                conflicting_files: None,
+    // --verbose reports the outcome of every file on stderr
+    let mut applied_files = Vec::new();
+    let mut conflicted_files = Vec::new();
+    let mut rejected_files: Vec<RejectedFile> = Vec::new();
+    for line in stderr.lines() {
+        if let Some(rest) = line.strip_prefix("Applied patch to '") {
+            if let Some(path) = rest.strip_suffix("' cleanly.") {
+                applied_files.push(path.to_string());
+            } else if let Some(path) = rest.strip_suffix("' with conflicts.") {
+                conflicted_files.push(path.to_string());
+            }
+        } else if let Some(path) = line
+            .strip_prefix("Applied patch ")
+            .and_then(|rest| rest.strip_suffix(" cleanly."))
+        {
+            applied_files.push(path.to_string());
+        } else if let Some((path, _)) = line
+            .strip_prefix("Applying patch ")
+            .and_then(|rest| rest.rsplit_once(" with "))
+        {
+            rejected_files.push(RejectedFile {
+                path: path.to_string(),
+                hunks: Vec::new(),
             });
+        } else if let Some(number) = line
+            .strip_prefix("Rejected hunk #")
+            .and_then(|rest| rest.strip_suffix('.'))
+            .and_then(|number| number.parse().ok())
+        {
+            if let Some(file) = rejected_files.last_mut() {
+                file.hunks.push(number);
+            }
         }
-        return Ok(create_error_result(&stderr, &stdout));
     }
 
-    // If delete_remote is true and we have a remote name, also delete on remote
-    if delete_remote {
-        if let Some(remote) = remote_name {
-            let push_output = Command::new("git")
-                .arg("-C")
-                .arg(repo_path)
-                .arg("push")
-                .arg(remote)
-                .arg("--delete")
-                .arg(branch_name)
-                .output()
-                .map_err(|e| format!("Failed to delete remote branch: {}", e))?;
+    // A three-way apply reports files both when checking and when merging them
+    applied_files.retain(|path| {
+        !conflicted_files.contains(path) && !rejected_files.iter().any(|file| file.path == *path)
+    });
+    applied_files.sort();
+    applied_files.dedup();
+
+    if !output.status.success() && conflicted_files.is_empty() && rejected_files.is_empty() {
+        // Refused as a whole, e.g. a file it creates already exists
+        let result = create_error_result(&stderr, &stdout);
+        return Ok(PatchApplyResult {
+            success: false,
+            message: result.message,
+            applied_files,
+            conflicted_files,
+            rejected_files,
+        });
+    }
 
-            if !push_output.status.success() {
-                let push_stderr = String::from_utf8_lossy(&push_output.stderr).to_string();
-                return Ok(GitOperationResult {
-                    success: false,
-                    message: format!(
-                        "Local branch deleted but failed to delete remote branch: {}",
-                        push_stderr.trim()
-                    ),
-                    requires_ssh_verification: None,
-                    requires_credential: None,
-                    error_type: Some("delete_remote_failed".to_string()),
-                    conflicting_files: None,
-                });
-            }
+    let message = if !conflicted_files.is_empty() {
+        format!(
+            "Patch applied; {} file{} with conflicts to resolve.",
+            conflicted_files.len(),
+            if conflicted_files.len() == 1 { "" } else { "s" }
+        )
+    } else if !rejected_files.is_empty() {
+        let hunks: usize = rejected_files.iter().map(|file| file.hunks.len()).sum();
+        format!(
+            "Patch partly applied; {} hunk{} rejected, see the .rej files.",
+            hunks,
+            if hunks == 1 { "" } else { "s" }
+        )
+    } else {
+        format!(
+            "Patch applied to {} file{}.",
+            applied_files.len(),
+            if applied_files.len() == 1 { "" } else { "s" }
+        )
+    };
+    Ok(PatchApplyResult {
+        success: conflicted_files.is_empty() && rejected_files.is_empty(),
+        message,
+        applied_files,
+        conflicted_files,
+        rejected_files,
+    })
+}
 
-            Ok(create_success_result(format!(
-                "Branch '{}' deleted (local and remote)",
-                branch_name
-            )))
+/// Paths dropped onto the window, sorted by how they can be handled
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DroppedPaths {
+    pub patches: Vec<String>,              // .patch/.diff files to apply
+    pub repositories: Vec<RepositoryInfo>, // folders that can be opened as repositories
+    pub rejected: Vec<String>,             // anything else
+}
+
+/// Sort dropped paths into patch files, openable repositories and the rest
+pub fn classify_dropped_paths(paths: &[std::path::PathBuf]) -> DroppedPaths {
+    let mut dropped = DroppedPaths::default();
+
+    for path in paths {
+        let display = path.to_string_lossy().to_string();
+        if path.is_file() && is_patch_file(path) {
+            dropped.patches.push(display);
+        } else if path.is_dir() {
+            let info = crate::git::validation::validate_repo_path_string(&display)
+                .and_then(|canonical| open_repository(&canonical.to_string_lossy()))
+                .and_then(|repo| get_repository_info(&repo));
+            match info {
+                Ok(info) if !info.is_bare => dropped.repositories.push(info),
+                _ => dropped.rejected.push(display),
+            }
         } else {
-            Ok(create_success_result(format!(
-                "Branch '{}' deleted",
-                branch_name
-            )))
+            dropped.rejected.push(display);
+        }
+    }
+
+    dropped
+}
+
+/// Get separated unstaged and staged files
+pub fn get_file_status_separated(
+    repo: &Repository,
+) -> Result<(Vec<FileStatus>, Vec<FileStatus>), String> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .renames_head_to_index(true);
+    // Only staged renames are paired, as `git status` does: until it is
+    // staged, a moved file is a deleted file and an untracked one
+    apply_focused_paths(repo, &mut opts);
+
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    let conflicts = conflict_kinds(repo, &statuses)?;
+    let mut unstaged = Vec::new();
+    let mut staged = Vec::new();
+
+    for entry in statuses.iter() {
+        let (path, old_path) = status_entry_paths(&entry);
+        let status = entry.status();
+
+        // Unmerged paths have nothing staged until they are resolved
+        if status.is_conflicted() {
+            unstaged.push(conflicted_status(path, &conflicts));
+            continue;
+        }
+
+        // Check for staged changes (index changes)
+        if status.is_index_renamed() {
+            staged.push(FileStatus {
+                path: path.clone(),
+                status: "renamed".to_string(),
+                staged: true,
+                conflict: None,
+                old_path,
+            });
+        } else if status.is_index_new() {
+            staged.push(FileStatus {
+                path: path.clone(),
+                status: "new".to_string(),
+                staged: true,
+                conflict: None,
+                old_path: None,
+            });
+        } else if status.is_index_modified() {
+            staged.push(FileStatus {
+                path: path.clone(),
+                status: modified_status(repo, entry.head_to_index()).to_string(),
+                staged: true,
+                conflict: None,
+                old_path: None,
+            });
+        } else if status.is_index_deleted() {
+            staged.push(FileStatus {
+                path: path.clone(),
+                status: "deleted".to_string(),
+                staged: true,
+                conflict: None,
+                old_path: None,
+            });
+        }
+
+        // Check for unstaged changes (working tree changes)
+        if status.is_wt_new() {
+            unstaged.push(FileStatus {
+                path: path.clone(),
+                status: "untracked".to_string(),
+                staged: false,
+                conflict: None,
+                old_path: None,
+            });
+        } else if status.is_wt_modified() {
+            unstaged.push(FileStatus {
+                path: path.clone(),
+                status: modified_status(repo, entry.index_to_workdir()).to_string(),
+                staged: false,
+                conflict: None,
+                old_path: None,
+            });
+        } else if status.is_wt_deleted() {
+            unstaged.push(FileStatus {
+                path: path.clone(),
+                status: "deleted".to_string(),
+                staged: false,
+                conflict: None,
+                old_path: None,
+            });
+        } else if status.is_wt_renamed() {
+            unstaged.push(FileStatus {
+                path: path.clone(),
+                status: "renamed".to_string(),
+                staged: false,
+                conflict: None,
+                old_path: None,
+            });
+        }
+    }
+
+    Ok((unstaged, staged))
+}
+
+/// Path of a status entry and, for a staged rename, the path it had in HEAD.
+/// The entry's own path is the old one for renames.
+fn status_entry_paths(entry: &git2::StatusEntry) -> (String, Option<String>) {
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().to_string());
+    match entry.head_to_index() {
+        Some(delta) if delta.status() == git2::Delta::Renamed => (
+            path_of(delta.new_file()).unwrap_or_default(),
+            path_of(delta.old_file()),
+        ),
+        _ => (entry.path().unwrap_or("").to_string(), None),
+    }
+}
+
+/// Kind of each unmerged path of `statuses`, looked up in the index only when
+/// there are some
+fn conflict_kinds(
+    repo: &Repository,
+    statuses: &git2::Statuses,
+) -> Result<HashMap<String, ConflictKind>, String> {
+    if !statuses.iter().any(|entry| entry.status().is_conflicted()) {
+        return Ok(HashMap::new());
+    }
+    Ok(conflicts::get_conflicts(repo)?
+        .into_iter()
+        .map(|file| (file.path, file.kind))
+        .collect())
+}
+
+fn conflicted_status(path: String, conflicts: &HashMap<String, ConflictKind>) -> FileStatus {
+    FileStatus {
+        conflict: conflicts.get(&path).copied(),
+        path,
+        status: "conflicted".to_string(),
+        staged: false,
+        old_path: None,
+    }
+}
+
+/// Old and new mode of a delta, when they differ (e.g. the executable bit)
+fn delta_mode_change(delta: &git2::DiffDelta) -> Option<(u32, u32)> {
+    let old_mode = u32::from(delta.old_file().mode());
+    let new_mode = u32::from(delta.new_file().mode());
+    (old_mode != new_mode).then_some((old_mode, new_mode))
+}
+
+/// "mode_changed" for a modified file whose content is unchanged, so only its
+/// mode differs, otherwise "modified"
+fn modified_status(repo: &Repository, delta: Option<git2::DiffDelta>) -> &'static str {
+    let Some(delta) = delta.filter(|delta| delta_mode_change(delta).is_some()) else {
+        return "modified";
+    };
+    // The work tree side of a status delta is not always hashed. It is hashed
+    // through the clean filters and end-of-line conversion, as git stores it.
+    let new_id = if delta.new_file().id().is_zero() {
+        repo.workdir()
+            .zip(delta.new_file().path())
+            .and_then(|(workdir, path)| {
+                let path = path.to_string_lossy().to_string();
+                index_flags::hash_workdir_files(workdir, &[path]).ok()
+            })
+            .and_then(|ids| ids.into_iter().next())
+    } else {
+        Some(delta.new_file().id())
+    };
+    if new_id == Some(delta.old_file().id()) {
+        "mode_changed"
+    } else {
+        "modified"
+    }
+}
+
+/// Commit message with subject and body separated
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitMessage {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Get the last commit message (subject and body)
+pub fn get_last_commit_message(repo: &Repository) -> Result<CommitMessage, String> {
+    let head = repo.head().map_err(|e| e.message().to_string())?;
+    let commit = head.peel_to_commit().map_err(|e| e.message().to_string())?;
+    let message = commit.message().unwrap_or("");
+
+    // Split into subject (first line) and body (rest)
+    let parts: Vec<&str> = message.splitn(2, '\n').collect();
+    let subject = parts[0].trim().to_string();
+    let body = parts
+        .get(1)
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default();
+
+    Ok(CommitMessage { subject, body })
+}
+
+/// Switch to a branch, or check out a commit or tag detached
+pub fn git_checkout(repo_path: &str, branch_name: &str) -> Result<GitOperationResult, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.message().to_string())?;
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .args(capabilities::switch_args(&repo, branch_name))
+        .output()
+        .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        // Git checkout success messages often go to stderr
+        let message = if stderr.contains("Switched to branch") || stderr.contains("Cambiado a rama")
+        {
+            stderr.trim().to_string()
+        } else if !stdout.is_empty() {
+            stdout.trim().to_string()
+        } else {
+            format!("Switched to branch '{}'", branch_name)
+        };
+        Ok(create_success_result(message))
+    } else {
+        // Common errors: uncommitted changes, branch doesn't exist
+        Ok(create_error_result(&stderr, &stdout))
+    }
+}
+
+/// The branch (or commit, if it was detached) checked out before the current one,
+/// i.e. what `git checkout -` would switch to
+pub fn get_previous_branch(repo: &Repository) -> Result<Option<String>, String> {
+    let reflog = repo.reflog("HEAD").map_err(|e| e.message().to_string())?;
+    let current = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(String::from));
+
+    let previous = reflog
+        .iter()
+        .find_map(|entry| {
+            entry
+                .message()
+                .and_then(parse_checkout_reflog_message)
+                .map(|(from, _)| from.to_string())
+        })
+        // Checking out the branch we are already on leaves nothing to go back to
+        .filter(|from| current.as_deref() != Some(from.as_str()));
+
+    Ok(previous)
+}
+
+/// `git checkout -`: switch back to the previously checked-out branch.
+/// With `restore_changes` set, local changes are carried over the same way as
+/// git_checkout_with_stash; without it a plain checkout is attempted.
+pub fn git_checkout_previous(
+    repo: &Repository,
+    repo_path: &str,
+    restore_changes: Option<bool>,
+) -> Result<GitOperationResult, String> {
+    let Some(previous) = get_previous_branch(repo)? else {
+        return Ok(GitOperationResult {
+            success: false,
+            message: "No previous branch to switch to".to_string(),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: Some("no_previous_branch".to_string()),
+            conflicting_files: None,
+        });
+    };
+
+    match restore_changes {
+        Some(restore_changes) => git_checkout_with_stash(repo_path, &previous, restore_changes),
+        None => git_checkout(repo_path, &previous),
+    }
+}
+
+/// Execute git checkout with automatic stash/pop for handling uncommitted changes
+/// This function:
+/// 1. Stashes current changes (including untracked files)
+/// 2. Checks out the target branch
+/// 3. Optionally pops the stash to restore changes
+pub fn git_checkout_with_stash(
+    repo_path: &str,
+    branch_name: &str,
+    restore_changes: bool,
+) -> Result<GitOperationResult, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.message().to_string())?;
+
+    // Step 1: Stash all changes including untracked files
+    let stash_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("stash")
+        .arg("push")
+        .arg("-u")
+        .arg("-m")
+        .arg(format!("Auto-stash before switching to {}", branch_name))
+        .output()
+        .map_err(|e| format!("Failed to execute git stash: {}", e))?;
+
+    if !stash_output.status.success() {
+        let stderr = String::from_utf8_lossy(&stash_output.stderr).to_string();
+        return Ok(GitOperationResult {
+            success: false,
+            message: format!("Failed to stash changes: {}", stderr.trim()),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: Some("stash_failed".to_string()),
+            conflicting_files: None,
+        });
+    }
+
+    // Step 2: Checkout the target branch
+    let checkout_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .args(capabilities::switch_args(&repo, branch_name))
+        .output()
+        .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
+
+    if !checkout_output.status.success() {
+        let stderr = String::from_utf8_lossy(&checkout_output.stderr).to_string();
+
+        // Checkout failed, try to restore the stash
+        let _ = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .arg("stash")
+            .arg("pop")
+            .output();
+
+        return Ok(GitOperationResult {
+            success: false,
+            message: format!("Checkout failed (stash restored): {}", stderr.trim()),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: Some("checkout_failed".to_string()),
+            conflicting_files: None,
+        });
+    }
+
+    // Step 3: Optionally pop the stash to restore changes
+    if restore_changes {
+        let pop_output = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .arg("stash")
+            .arg("pop")
+            .output()
+            .map_err(|e| format!("Failed to execute git stash pop: {}", e))?;
+
+        if !pop_output.status.success() {
+            let stderr = String::from_utf8_lossy(&pop_output.stderr).to_string();
+            // Checkout succeeded but pop failed - likely conflicts
+            return Ok(GitOperationResult {
+                success: true,
+                message: format!(
+                    "Switched to '{}' but failed to restore changes. Your changes are in stash. Error: {}",
+                    branch_name,
+                    stderr.trim()
+                ),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("stash_pop_conflict".to_string()),
+                conflicting_files: None,
+            });
+        }
+
+        Ok(GitOperationResult {
+            success: true,
+            message: format!("Switched to '{}' and restored changes", branch_name),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: None,
+            conflicting_files: None,
+        })
+    } else {
+        Ok(GitOperationResult {
+            success: true,
+            message: format!("Switched to '{}' (changes saved in stash)", branch_name),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: None,
+            conflicting_files: None,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckoutPreflight {
+    pub branch_name: String,
+    pub is_current: bool,
+    pub staged_files: usize,
+    pub unstaged_files: usize,
+    pub untracked_files: usize,
+    pub stash_count: usize,
+    pub will_conflict: bool, // a plain checkout would refuse to switch
+    pub blocking_files: Vec<String>, // local changes a plain checkout would overwrite
+    pub autostash_clean: Option<bool>, // None when there is nothing to stash
+    pub autostash_conflicts: Vec<String>, // files that would conflict when restoring the stash
+}
+
+/// The commit `git checkout <name>` would switch to: a local branch, any
+/// revision, or a remote branch of that name (which checkout would track)
+fn resolve_checkout_target<'r>(repo: &'r Repository, name: &str) -> Option<git2::Commit<'r>> {
+    if let Ok(branch) = repo.find_branch(name, BranchType::Local) {
+        return branch.get().peel_to_commit().ok();
+    }
+    if let Ok(commit) = repo.revparse_single(name).and_then(|o| o.peel_to_commit()) {
+        return Some(commit);
+    }
+    let suffix = format!("/{}", name);
+    let mut remote_branches = repo.branches(Some(BranchType::Remote)).ok()?.flatten();
+    remote_branches
+        .find(|(branch, _)| {
+            branch
+                .name()
+                .ok()
+                .flatten()
+                .is_some_and(|n| n.ends_with(&suffix))
+        })
+        .and_then(|(branch, _)| branch.get().peel_to_commit().ok())
+}
+
+/// What switching to `branch_name` would do with the local changes: whether a
+/// plain checkout would be refused (and which files block it) and whether
+/// stashing, switching and restoring the stash (git_checkout_with_stash) would
+/// restore them without conflicts. Nothing in the repository is modified.
+pub fn checkout_preflight(
+    repo: &Repository,
+    branch_name: &str,
+) -> Result<CheckoutPreflight, String> {
+    let target = resolve_checkout_target(repo, branch_name)
+        .ok_or_else(|| format!("'{}' does not name a branch or commit", branch_name))?;
+    let head = repo.head().ok();
+    let head_commit = head.as_ref().and_then(|h| h.peel_to_commit().ok());
+    let is_current = head.as_ref().and_then(|h| h.shorthand()) == Some(branch_name);
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    let count = |flags: Status| {
+        statuses
+            .iter()
+            .filter(|entry| entry.status().intersects(flags))
+            .count()
+    };
+    let staged_files = count(
+        Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE,
+    );
+    let unstaged_files = count(
+        Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE,
+    );
+    let untracked_files = count(Status::WT_NEW);
+    let stash_count = repo.reflog("refs/stash").map(|r| r.len()).unwrap_or(0);
+    let dirty = statuses
+        .iter()
+        .any(|entry| entry.status() != Status::CURRENT);
+
+    let mut preflight = CheckoutPreflight {
+        branch_name: branch_name.to_string(),
+        is_current,
+        staged_files,
+        unstaged_files,
+        untracked_files,
+        stash_count,
+        will_conflict: false,
+        blocking_files: Vec::new(),
+        autostash_clean: None,
+        autostash_conflicts: Vec::new(),
+    };
+    if !dirty || is_current {
+        return Ok(preflight);
+    }
+
+    // Plain checkout: a dry run reports the local changes it would overwrite
+    let target_tree = target.tree().map_err(|e| e.message().to_string())?;
+    let blocking = std::cell::RefCell::new(Vec::new());
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout
+        .safe()
+        .dry_run()
+        .notify_on(git2::CheckoutNotificationType::CONFLICT)
+        .notify(|_, path, _, _, _| {
+            if let Some(path) = path {
+                blocking
+                    .borrow_mut()
+                    .push(path.to_string_lossy().to_string());
+            }
+            true
+        });
+    let dry_run = repo.checkout_tree(target_tree.as_object(), Some(&mut checkout));
+    drop(checkout);
+    preflight.blocking_files = blocking.into_inner();
+    preflight.will_conflict = dry_run.is_err() || !preflight.blocking_files.is_empty();
+
+    // Autostash: restoring the stash on the target is a three-way merge of the
+    // working tree onto the target, based on HEAD. The working tree is written
+    // as a tree through a separate handle whose objects stay in memory.
+    let scratch = in_memory_handle(repo)?;
+    let mut index = scratch.index().map_err(|e| e.message().to_string())?;
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .and_then(|_| index.update_all(["*"], None))
+        .map_err(|e| e.message().to_string())?;
+    let worktree_tree = index
+        .write_tree()
+        .and_then(|id| scratch.find_tree(id))
+        .map_err(|e| e.message().to_string())?;
+    let base_tree = match &head_commit {
+        Some(commit) => Some(commit.tree().map_err(|e| e.message().to_string())?),
+        None => None,
+    };
+    let target_tree = scratch
+        .find_tree(target_tree.id())
+        .map_err(|e| e.message().to_string())?;
+    let empty_tree = scratch
+        .treebuilder(None)
+        .and_then(|builder| builder.write())
+        .and_then(|id| scratch.find_tree(id))
+        .map_err(|e| e.message().to_string())?;
+    let base_tree = match base_tree {
+        Some(tree) => scratch
+            .find_tree(tree.id())
+            .map_err(|e| e.message().to_string())?,
+        None => empty_tree,
+    };
+    let merged = scratch
+        .merge_trees(&base_tree, &target_tree, &worktree_tree, None)
+        .map_err(|e| e.message().to_string())?;
+    preflight.autostash_conflicts = merged
+        .conflicts()
+        .map_err(|e| e.message().to_string())?
+        .flatten()
+        .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    preflight.autostash_clean = Some(preflight.autostash_conflicts.is_empty());
+
+    Ok(preflight)
+}
+
+/// History rewrite checked by rewrite_preflight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RewriteOperation {
+    /// Amending the HEAD commit
+    Amend,
+    /// Rebasing the current branch onto `onto`, plain or interactive
+    Rebase { onto: String },
+    /// Force pushing `branch` to `remote_branch` on `remote`
+    ForcePush {
+        branch: String,
+        remote: String,
+        remote_branch: String,
+    },
+}
+
+/// Commits listed at most in a RewritePreflight
+const REWRITE_PREFLIGHT_LIMIT: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RewritePreflight {
+    pub published_count: usize,
+    pub published_commits: Vec<CommitInfo>, // newest first, at most REWRITE_PREFLIGHT_LIMIT
+    pub remote_refs: Vec<String>,           // remote branches holding them, e.g. "origin/main"
+    pub warning: Option<String>,            // None when no published commit is affected
+}
+
+/// Remote-tracking branches and the commits they point to, without the
+/// symbolic `<remote>/HEAD`
+fn remote_branch_tips(repo: &Repository) -> Vec<(String, git2::Oid)> {
+    let Ok(branches) = repo.branches(Some(BranchType::Remote)) else {
+        return Vec::new();
+    };
+    branches
+        .flatten()
+        .filter_map(|(branch, _)| {
+            let oid = branch.get().target()?;
+            let name = branch.name().ok().flatten()?.to_string();
+            Some((name, oid))
+        })
+        .collect()
+}
+
+/// Commits reachable from `tip` but not from any of `hidden`, newest first
+fn commits_between(
+    repo: &Repository,
+    tip: git2::Oid,
+    hidden: impl IntoIterator<Item = git2::Oid>,
+) -> Result<Vec<git2::Oid>, String> {
+    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+    revwalk.push(tip).map_err(|e| e.message().to_string())?;
+    for oid in hidden {
+        revwalk.hide(oid).map_err(|e| e.message().to_string())?;
+    }
+    revwalk
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.message().to_string())
+}
+
+/// Like commits_between, but only the first `limit` commits are kept; the
+/// others are counted. Also returns the number of commits in the range.
+fn commits_between_capped(
+    repo: &Repository,
+    tip: git2::Oid,
+    hidden: impl IntoIterator<Item = git2::Oid>,
+    limit: usize,
+) -> Result<(Vec<git2::Oid>, usize), String> {
+    let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+    revwalk.push(tip).map_err(|e| e.message().to_string())?;
+    for oid in hidden {
+        revwalk.hide(oid).map_err(|e| e.message().to_string())?;
+    }
+    let mut commits = Vec::new();
+    let mut count = 0;
+    for oid in revwalk {
+        let oid = oid.map_err(|e| e.message().to_string())?;
+        if commits.len() < limit {
+            commits.push(oid);
+        }
+        count += 1;
+    }
+    Ok((commits, count))
+}
+
+/// Which of the commits an amend, rebase or force push would rewrite or
+/// discard are already on a remote, so the user is warned before changing
+/// published history. Nothing in the repository is modified.
+pub fn rewrite_preflight(
+    repo: &Repository,
+    operation: &RewriteOperation,
+) -> Result<RewritePreflight, String> {
+    let tips = remote_branch_tips(repo);
+
+    let (published, remote_refs) = match operation {
+        RewriteOperation::ForcePush {
+            branch,
+            remote,
+            remote_branch,
+        } => {
+            // Everything on the remote branch that the pushed branch does not
+            // contain is discarded
+            let remote_ref = format!("{}/{}", remote, remote_branch);
+            let Some((_, remote_tip)) = tips.iter().find(|(name, _)| *name == remote_ref) else {
+                return Ok(RewritePreflight::default());
+            };
+            let local = resolve_commit(repo, branch)?;
+            let discarded = commits_between(repo, *remote_tip, [local.id()])?;
+            (discarded, vec![remote_ref])
+        }
+        RewriteOperation::Amend | RewriteOperation::Rebase { .. } => {
+            let head = repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .map_err(|e| e.message().to_string())?;
+            let rewritten = match operation {
+                RewriteOperation::Rebase { onto } => {
+                    commits_between(repo, head.id(), [resolve_commit(repo, onto)?.id()])?
+                }
+                _ => vec![head.id()],
+            };
+            let unpublished: HashSet<git2::Oid> =
+                commits_between(repo, head.id(), tips.iter().map(|(_, oid)| *oid))?
+                    .into_iter()
+                    .collect();
+            let published: Vec<git2::Oid> = rewritten
+                .into_iter()
+                .filter(|oid| !unpublished.contains(oid))
+                .collect();
+            let remote_refs = tips
+                .iter()
+                .filter(|(_, tip)| {
+                    published.iter().take(REWRITE_PREFLIGHT_LIMIT).any(|oid| {
+                        tip == oid || repo.graph_descendant_of(*tip, *oid).unwrap_or(false)
+                    })
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            (published, remote_refs)
+        }
+    };
+
+    if published.is_empty() {
+        return Ok(RewritePreflight::default());
+    }
+    let count = published.len();
+    let commits = if count == 1 { "commit" } else { "commits" };
+    let refs = remote_refs.join(", ");
+    let warning = match operation {
+        RewriteOperation::Amend => format!(
+            "The last commit is already on {}. Amending it rewrites published history; pushing it will need a force push.",
+            refs
+        ),
+        RewriteOperation::Rebase { .. } => format!(
+            "Rebasing rewrites {} {} already on {}; pushing the result will need a force push.",
+            count, commits, refs
+        ),
+        RewriteOperation::ForcePush { branch, .. } => format!(
+            "The force push removes {} {} of {} that '{}' does not contain.",
+            count, commits, refs, branch
+        ),
+    };
+
+    Ok(RewritePreflight {
+        published_count: count,
+        published_commits: published
+            .iter()
+            .take(REWRITE_PREFLIGHT_LIMIT)
+            .filter_map(|oid| repo.find_commit(*oid).ok())
+            .map(|commit| commit_info(&commit))
+            .collect(),
+        remote_refs,
+        warning: Some(warning),
+    })
+}
+
+/// Create a local branch that tracks a remote branch and switch to it
+pub fn git_checkout_track(
+    repo_path: &str,
+    local_branch: &str,
+    remote_branch: &str,
+) -> Result<GitOperationResult, String> {
+    // git switch -c <local_branch> --track <remote_branch>
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .args(capabilities::create_branch_args(
+            local_branch,
+            remote_branch,
+            true,
+        ))
+        .output()
+        .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        let message = if stderr.contains("Switched to") || stderr.contains("Cambiado a") {
+            stderr.trim().to_string()
+        } else if !stdout.is_empty() {
+            stdout.trim().to_string()
+        } else {
+            format!(
+                "Branch '{}' set up to track remote branch '{}'",
+                local_branch, remote_branch
+            )
+        };
+        Ok(create_success_result(message))
+    } else {
+        Ok(create_error_result(&stderr, &stdout))
+    }
+}
+
+/// Execute git commit with message and optional amend
+pub fn git_commit(
+    repo_path: &str,
+    message: &str,
+    amend: bool,
+) -> Result<GitOperationResult, String> {
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path).arg("commit");
+    cmd.arg("-m").arg(message);
+
+    if amend {
+        cmd.arg("--amend");
+    }
+
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to execute git commit: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        // Parse commit info from output
+        let message = if stdout.contains("create mode") || stdout.contains("delete mode") {
+            // Get first line which usually contains the commit summary
+            stdout
+                .lines()
+                .next()
+                .unwrap_or("Commit created")
+                .to_string()
+        } else if !stdout.trim().is_empty() {
+            stdout.trim().to_string()
+        } else {
+            "Commit created successfully".to_string()
+        };
+        Ok(create_success_result(message))
+    } else {
+        Ok(create_error_result(&stderr, &stdout))
+    }
+}
+
+/// Add a new remote to the repository
+pub fn git_add_remote(
+    repo_path: &str,
+    name: &str,
+    url: &str,
+) -> Result<GitOperationResult, String> {
+    let mut cmd = git_command();
+    cmd.arg("-C")
+        .arg(repo_path)
+        .arg("remote")
+        .arg("add")
+        .arg(name)
+        .arg(url);
+
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to execute git remote add: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        Ok(create_success_result(format!(
+            "Remote '{}' added successfully",
+            name
+        )))
+    } else {
+        Ok(create_error_result(&stderr, &stdout))
+    }
+}
+
+/// Test connection to a remote URL using git ls-remote
+pub fn git_test_remote_connection(url: &str) -> Result<GitOperationResult, String> {
+    let mut cmd = git_command();
+    cmd.arg("ls-remote")
+        .arg("--exit-code")
+        .arg("--heads")
+        .arg(url);
+
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+
+    // Set a timeout-like behavior by limiting refs
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to test connection: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        Ok(create_success_result("Connection successful".to_string()))
+    } else {
+        // Check for common errors
+        if stderr.contains("Host key verification failed") {
+            Ok(create_error_result(
+                "SSH host key verification failed. Add the host to known_hosts first.",
+                &stderr,
+            ))
+        } else if stderr.contains("Permission denied") || stderr.contains("Authentication failed") {
+            Ok(create_error_result(
+                "Authentication failed. Check your credentials.",
+                &stderr,
+            ))
+        } else if stderr.contains("Could not resolve host") {
+            Ok(create_error_result(
+                "Could not resolve host. Check the URL.",
+                &stderr,
+            ))
+        } else if stderr.contains("Connection refused") {
+            Ok(create_error_result(
+                "Connection refused. Check if the server is accessible.",
+                &stderr,
+            ))
+        } else {
+            Ok(create_error_result("Could not connect to remote", &stderr))
+        }
+    }
+}
+
+/// Create a new branch at a specific starting point
+pub fn git_create_branch(
+    repo_path: &str,
+    branch_name: &str,
+    start_point: &str,
+    checkout: bool,
+) -> Result<GitOperationResult, String> {
+    if checkout {
+        // git switch -c <branch_name> <start_point>
+        let output = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .args(capabilities::create_branch_args(
+                branch_name,
+                start_point,
+                false,
+            ))
+            .output()
+            .map_err(|e| format!("Failed to execute git checkout -b: {}", e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        if output.status.success() {
+            let message = if stderr.contains("Switched to") || stderr.contains("Cambiado a") {
+                stderr.trim().to_string()
+            } else if !stdout.is_empty() {
+                stdout.trim().to_string()
+            } else {
+                format!("Switched to a new branch '{}'", branch_name)
+            };
+            Ok(create_success_result(message))
+        } else {
+            Ok(create_error_result(&stderr, &stdout))
+        }
+    } else {
+        // git branch <branch_name> <start_point>
+        let output = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .arg("branch")
+            .arg(branch_name)
+            .arg(start_point)
+            .output()
+            .map_err(|e| format!("Failed to execute git branch: {}", e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        if output.status.success() {
+            Ok(create_success_result(format!(
+                "Branch '{}' created",
+                branch_name
+            )))
+        } else {
+            Ok(create_error_result(&stderr, &stdout))
+        }
+    }
+}
+
+/// Creates a new tag at the specified commit/branch
+/// If message is provided, creates an annotated tag; otherwise creates a lightweight tag
+/// If push_to_remotes is true, pushes the tag to all remotes
+pub fn git_create_tag(
+    repo_path: &str,
+    tag_name: &str,
+    start_point: &str,
+    message: Option<&str>,
+    push_to_remotes: bool,
+) -> Result<GitOperationResult, String> {
+    // Create the tag
+    let output = if let Some(msg) = message {
+        if msg.trim().is_empty() {
+            // Lightweight tag
+            git_command()
+                .arg("-C")
+                .arg(repo_path)
+                .arg("tag")
+                .arg(tag_name)
+                .arg(start_point)
+                .output()
+                .map_err(|e| format!("Failed to execute git tag: {}", e))?
+        } else {
+            // Annotated tag with message
+            git_command()
+                .arg("-C")
+                .arg(repo_path)
+                .arg("tag")
+                .arg("-a")
+                .arg(tag_name)
+                .arg(start_point)
+                .arg("-m")
+                .arg(msg)
+                .output()
+                .map_err(|e| format!("Failed to execute git tag -a: {}", e))?
+        }
+    } else {
+        // Lightweight tag
+        git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .arg("tag")
+            .arg(tag_name)
+            .arg(start_point)
+            .output()
+            .map_err(|e| format!("Failed to execute git tag: {}", e))?
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    // If push_to_remotes is true, push the tag to all remotes
+    if push_to_remotes {
+        let push_output = git_command()
+            .arg("-C")
+            .arg(repo_path)
+            .arg("push")
+            .arg("--tags")
+            .output()
+            .map_err(|e| format!("Failed to execute git push --tags: {}", e))?;
+
+        let _push_stdout = String::from_utf8_lossy(&push_output.stdout).to_string();
+        let push_stderr = String::from_utf8_lossy(&push_output.stderr).to_string();
+
+        if push_output.status.success() {
+            Ok(create_success_result(format!(
+                "Tag '{}' created and pushed",
+                tag_name
+            )))
+        } else {
+            // Tag was created but push failed
+            Ok(GitOperationResult {
+                success: false,
+                message: format!(
+                    "Tag '{}' created but push failed: {}",
+                    tag_name,
+                    push_stderr.trim()
+                ),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("push_failed".to_string()),
+                conflicting_files: None,
+            })
+        }
+    } else {
+        Ok(create_success_result(format!("Tag '{}' created", tag_name)))
+    }
+}
+
+/// Renames a local branch
+/// If rename_remote is true and the branch has an upstream, also renames the remote branch
+pub fn git_rename_branch(
+    repo_path: &str,
+    old_name: &str,
+    new_name: &str,
+    rename_remote: bool,
+    remote_name: Option<&str>,
+) -> Result<GitOperationResult, String> {
+    // Rename local branch: git branch -m old_name new_name
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("branch")
+        .arg("-m")
+        .arg(old_name)
+        .arg(new_name)
+        .output()
+        .map_err(|e| format!("Failed to execute git branch -m: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    // If rename_remote is true and we have a remote name, also rename on remote
+    if rename_remote {
+        if let Some(remote) = remote_name {
+            // Push the new branch name to remote
+            let push_output = git_command()
+                .arg("-C")
+                .arg(repo_path)
+                .arg("push")
+                .arg(remote)
+                .arg(new_name)
+                .output()
+                .map_err(|e| format!("Failed to push new branch: {}", e))?;
+
+            if !push_output.status.success() {
+                let push_stderr = String::from_utf8_lossy(&push_output.stderr).to_string();
+                return Ok(GitOperationResult {
+                    success: false,
+                    message: format!(
+                        "Local branch renamed but failed to push to remote: {}",
+                        push_stderr.trim()
+                    ),
+                    requires_ssh_verification: None,
+                    requires_credential: None,
+                    error_type: Some("push_failed".to_string()),
+                    conflicting_files: None,
+                });
+            }
+
+            // Delete the old branch from remote
+            let delete_output = git_command()
+                .arg("-C")
+                .arg(repo_path)
+                .arg("push")
+                .arg(remote)
+                .arg("--delete")
+                .arg(old_name)
+                .output()
+                .map_err(|e| format!("Failed to delete old remote branch: {}", e))?;
+
+            if !delete_output.status.success() {
+                let delete_stderr = String::from_utf8_lossy(&delete_output.stderr).to_string();
+                return Ok(GitOperationResult {
+                    success: false,
+                    message: format!(
+                        "Branch renamed and pushed, but failed to delete old remote branch: {}",
+                        delete_stderr.trim()
+                    ),
+                    requires_ssh_verification: None,
+                    requires_credential: None,
+                    error_type: Some("delete_remote_failed".to_string()),
+                    conflicting_files: None,
+                });
+            }
+
+            // Set upstream for the new branch
+            let upstream_output = git_command()
+                .arg("-C")
+                .arg(repo_path)
+                .arg("branch")
+                .arg("-u")
+                .arg(format!("{}/{}", remote, new_name))
+                .arg(new_name)
+                .output()
+                .map_err(|e| format!("Failed to set upstream: {}", e))?;
+
+            if upstream_output.status.success() {
+                Ok(create_success_result(format!(
+                    "Branch renamed from '{}' to '{}' (local and remote)",
+                    old_name, new_name
+                )))
+            } else {
+                // Upstream setting failed but main operation succeeded
+                Ok(create_success_result(format!(
+                    "Branch renamed from '{}' to '{}' (upstream may need manual setup)",
+                    old_name, new_name
+                )))
+            }
+        } else {
+            Ok(create_success_result(format!(
+                "Branch renamed from '{}' to '{}'",
+                old_name, new_name
+            )))
+        }
+    } else {
+        Ok(create_success_result(format!(
+            "Branch renamed from '{}' to '{}'",
+            old_name, new_name
+        )))
+    }
+}
+
+/// Deletes a local branch
+/// If force is true, uses -D (force delete), otherwise uses -d (safe delete)
+/// If delete_remote is true and remote_name is provided, also deletes the remote branch
+pub fn git_delete_branch(
+    repo_path: &str,
+    branch_name: &str,
+    force: bool,
+    delete_remote: bool,
+    remote_name: Option<&str>,
+) -> Result<GitOperationResult, String> {
+    // Delete local branch: git branch -d/-D branch_name
+    let delete_flag = if force { "-D" } else { "-d" };
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("branch")
+        .arg(delete_flag)
+        .arg(branch_name)
+        .output()
+        .map_err(|e| format!("Failed to execute git branch {}: {}", delete_flag, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        // Check if it's an unmerged branch error
+        if stderr.contains("not fully merged") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: format!(
+                    "Branch '{}' is not fully merged. Use force delete to remove it anyway.",
+                    branch_name
+                ),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("not_merged".to_string()),
+                conflicting_files: None,
+            });
+        }
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    // If delete_remote is true and we have a remote name, also delete on remote
+    if delete_remote {
+        if let Some(remote) = remote_name {
+            let push_output = git_command()
+                .arg("-C")
+                .arg(repo_path)
+                .arg("push")
+                .arg(remote)
+                .arg("--delete")
+                .arg(branch_name)
+                .output()
+                .map_err(|e| format!("Failed to delete remote branch: {}", e))?;
+
+            if !push_output.status.success() {
+                let push_stderr = String::from_utf8_lossy(&push_output.stderr).to_string();
+                return Ok(GitOperationResult {
+                    success: false,
+                    message: format!(
+                        "Local branch deleted but failed to delete remote branch: {}",
+                        push_stderr.trim()
+                    ),
+                    requires_ssh_verification: None,
+                    requires_credential: None,
+                    error_type: Some("delete_remote_failed".to_string()),
+                    conflicting_files: None,
+                });
+            }
+
+            Ok(create_success_result(format!(
+                "Branch '{}' deleted (local and remote)",
+                branch_name
+            )))
+        } else {
+            Ok(create_success_result(format!(
+                "Branch '{}' deleted",
+                branch_name
+            )))
+        }
+    } else {
+        Ok(create_success_result(format!(
+            "Branch '{}' deleted",
+            branch_name
+        )))
+    }
+}
+
+// ============================================================================
+// Stash Operations
+// ============================================================================
+
+pub fn get_stashes(repo_path: &str) -> Result<Vec<StashInfo>, String> {
+    // Use git stash list with custom format to get structured data
+    // Format: index|ref|message|timestamp
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("stash")
+        .arg("list")
+        .arg("--format=%gd|%gs|%ct")
+        .output()
+        .map_err(|e| format!("Failed to list stashes: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list stashes: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut stashes = Vec::new();
+
+    for (index, line) in stdout.lines().enumerate() {
+        let parts: Vec<&str> = line.split('|').collect();
+        if parts.len() >= 3 {
+            let id = parts[0].to_string(); // e.g., "stash@{0}"
+            let message = parts[1].to_string(); // e.g., "WIP on main: abc1234 commit message"
+            let timestamp: i64 = parts[2].parse().unwrap_or(0);
+
+            // Extract branch name from message (format: "WIP on branch: ..." or "On branch: ...")
+            let branch = extract_branch_from_stash_message(&message);
+
+            stashes.push(StashInfo {
+                index,
+                id,
+                message,
+                branch,
+                timestamp,
+            });
+        }
+    }
+
+    Ok(stashes)
+}
+
+fn extract_branch_from_stash_message(message: &str) -> String {
+    // Stash messages typically look like:
+    // "WIP on main: abc1234 commit message"
+    // "On main: custom message"
+    // "index on main: abc1234 commit message"
+    if let Some(rest) = message.strip_prefix("WIP on ") {
+        if let Some(colon_pos) = rest.find(':') {
+            return rest[..colon_pos].to_string();
+        }
+    }
+    if let Some(rest) = message.strip_prefix("On ") {
+        if let Some(colon_pos) = rest.find(':') {
+            return rest[..colon_pos].to_string();
+        }
+    }
+    if let Some(rest) = message.strip_prefix("index on ") {
+        if let Some(colon_pos) = rest.find(':') {
+            return rest[..colon_pos].to_string();
+        }
+    }
+    String::new()
+}
+
+pub fn git_stash_save(
+    repo_path: &str,
+    message: Option<&str>,
+    include_untracked: bool,
+    keep_index: bool,
+) -> Result<GitOperationResult, String> {
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path).arg("stash").arg("push");
+
+    if include_untracked {
+        cmd.arg("-u");
+    }
+
+    if keep_index {
+        cmd.arg("--keep-index");
+    }
+
+    if let Some(msg) = message {
+        if !msg.trim().is_empty() {
+            cmd.arg("-m").arg(msg);
+        }
+    }
+
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to save stash: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        // Check if there are no changes to stash
+        if stderr.contains("No local changes to save")
+            || stdout.contains("No local changes to save")
+        {
+            return Ok(GitOperationResult {
+                success: false,
+                message: "No local changes to save".to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("no_changes".to_string()),
+                conflicting_files: None,
+            });
+        }
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    Ok(create_success_result(
+        "Stash saved successfully".to_string(),
+    ))
+}
+
+pub fn git_stash_apply(repo_path: &str, stash_index: usize) -> Result<GitOperationResult, String> {
+    let stash_ref = format!("stash@{{{}}}", stash_index);
+
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("stash")
+        .arg("apply")
+        .arg(&stash_ref)
+        .output()
+        .map_err(|e| format!("Failed to apply stash: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        // Check for conflicts
+        if stderr.contains("CONFLICT") || stdout.contains("CONFLICT") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: format!(
+                    "Stash applied with conflicts. Resolve conflicts and commit.\n{}",
+                    stderr.trim()
+                ),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("conflicts".to_string()),
+                conflicting_files: None,
+            });
+        }
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    Ok(create_success_result(
+        "Stash applied successfully".to_string(),
+    ))
+}
+
+pub fn git_stash_pop(repo_path: &str, stash_index: usize) -> Result<GitOperationResult, String> {
+    let stash_ref = format!("stash@{{{}}}", stash_index);
+
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("stash")
+        .arg("pop")
+        .arg(&stash_ref)
+        .output()
+        .map_err(|e| format!("Failed to pop stash: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        // Check for conflicts
+        if stderr.contains("CONFLICT") || stdout.contains("CONFLICT") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: format!("Stash popped with conflicts. Resolve conflicts and commit. The stash was not dropped.\n{}", stderr.trim()),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("conflicts".to_string()),
+                conflicting_files: None,
+            });
+        }
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    Ok(create_success_result(
+        "Stash popped successfully".to_string(),
+    ))
+}
+
+pub fn git_stash_drop(repo_path: &str, stash_index: usize) -> Result<GitOperationResult, String> {
+    let stash_ref = format!("stash@{{{}}}", stash_index);
+
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("stash")
+        .arg("drop")
+        .arg(&stash_ref)
+        .output()
+        .map_err(|e| format!("Failed to drop stash: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    Ok(create_success_result(format!(
+        "Stash {} dropped",
+        stash_ref
+    )))
+}
+
+/// A stash as one patch against the commit it was made on, its untracked files
+/// included, that `git apply` recreates on another clone. Written to
+/// `output_path` when given, otherwise returned as text.
+pub fn export_stash(
+    repo: &Repository,
+    stash_index: usize,
+    output_path: Option<&str>,
+) -> Result<WorkingPatch, String> {
+    use git2::DiffOptions;
+
+    let stash_ref = format!("stash@{{{}}}", stash_index);
+    let stash = repo
+        .revparse_single(&stash_ref)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| format!("Stash {} not found", stash_ref))?;
+    let base_tree = stash
+        .parent(0)
+        .and_then(|base| base.tree())
+        .map_err(|e| e.message().to_string())?;
+    let stash_tree = stash.tree().map_err(|e| e.message().to_string())?;
+
+    let mut opts = DiffOptions::new();
+    opts.show_binary(true);
+    let mut diff = repo
+        .diff_tree_to_tree(Some(&base_tree), Some(&stash_tree), Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    // Stashes made with --include-untracked keep the untracked files in a
+    // third parent, a root commit holding only them
+    if stash.parent_count() > 2 {
+        let untracked_tree = stash
+            .parent(2)
+            .and_then(|untracked| untracked.tree())
+            .map_err(|e| e.message().to_string())?;
+        let untracked = repo
+            .diff_tree_to_tree(None, Some(&untracked_tree), Some(&mut opts))
+            .map_err(|e| e.message().to_string())?;
+        diff.merge(&untracked)
+            .map_err(|e| e.message().to_string())?;
+    }
+    if diff.deltas().len() == 0 {
+        return Err(format!("Stash {} has no changes to export", stash_ref));
+    }
+    write_patch(&diff, output_path)
+}
+
+// ============================================================================
+// Image Content Functions
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageContent {
+    pub base64: String,
+    pub mime_type: String,
+    pub file_size: u64,
+}
+
+/// Get MIME type from file extension
+fn get_mime_type(file_path: &str) -> String {
+    let lower = file_path.to_lowercase();
+    if lower.ends_with(".png") {
+        "image/png".to_string()
+    } else if lower.ends_with(".jpg") || lower.ends_with(".jpeg") {
+        "image/jpeg".to_string()
+    } else if lower.ends_with(".gif") {
+        "image/gif".to_string()
+    } else if lower.ends_with(".bmp") {
+        "image/bmp".to_string()
+    } else if lower.ends_with(".webp") {
+        "image/webp".to_string()
+    } else if lower.ends_with(".svg") {
+        "image/svg+xml".to_string()
+    } else if lower.ends_with(".ico") {
+        "image/x-icon".to_string()
+    } else {
+        "application/octet-stream".to_string()
+    }
+}
+
+/// Get current image content from working directory as base64
+pub fn get_image_content(repo: &Repository, file_path: &str) -> Result<ImageContent, String> {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    let workdir = repo.workdir().ok_or("No working directory")?;
+    let full_path = workdir.join(file_path);
+
+    if !full_path.exists() {
+        return Err(format!("File does not exist: {}", file_path));
+    }
+
+    let content = std::fs::read(&full_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let file_size = content.len() as u64;
+    let base64_content = STANDARD.encode(&content);
+    let mime_type = get_mime_type(file_path);
+
+    Ok(ImageContent {
+        base64: base64_content,
+        mime_type,
+        file_size,
+    })
+}
+
+/// Get image content from HEAD commit as base64
+pub fn get_image_from_head(repo: &Repository, file_path: &str) -> Result<ImageContent, String> {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    let head = repo.head().map_err(|e| e.message().to_string())?;
+    let tree = head.peel_to_tree().map_err(|e| e.message().to_string())?;
+
+    let entry = tree
+        .get_path(std::path::Path::new(file_path))
+        .map_err(|e| format!("File not found in HEAD: {}", e.message()))?;
+
+    let blob = repo
+        .find_blob(entry.id())
+        .map_err(|e| format!("Failed to get blob: {}", e.message()))?;
+
+    let content = blob.content();
+    let file_size = content.len() as u64;
+    let base64_content = STANDARD.encode(content);
+    let mime_type = get_mime_type(file_path);
+
+    Ok(ImageContent {
+        base64: base64_content,
+        mime_type,
+        file_size,
+    })
+}
+
+/// Get image content from index (staged) as base64
+pub fn get_image_from_index(repo: &Repository, file_path: &str) -> Result<ImageContent, String> {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    let index = repo.index().map_err(|e| e.message().to_string())?;
+
+    let entry = index
+        .get_path(std::path::Path::new(file_path), 0)
+        .ok_or_else(|| format!("File not found in index: {}", file_path))?;
+
+    let blob = repo
+        .find_blob(entry.id)
+        .map_err(|e| format!("Failed to get blob: {}", e.message()))?;
+
+    let content = blob.content();
+    let file_size = content.len() as u64;
+    let base64_content = STANDARD.encode(content);
+    let mime_type = get_mime_type(file_path);
+
+    Ok(ImageContent {
+        base64: base64_content,
+        mime_type,
+        file_size,
+    })
+}
+
+// ============================================================================
+// Media Metadata Functions
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaDiffInfo {
+    pub file_path: String,
+    pub old: Option<MediaMetadata>,
+    pub new: Option<MediaMetadata>,
+}
+
+/// Read a file's blob content from a tree. Returns None if the path is not in the tree.
+fn read_tree_blob(repo: &Repository, tree: &git2::Tree, file_path: &str) -> Option<Vec<u8>> {
+    let entry = tree.get_path(std::path::Path::new(file_path)).ok()?;
+    let blob = repo.find_blob(entry.id()).ok()?;
+    Some(blob.content().to_vec())
+}
+
+/// Read a file's blob content from HEAD. Returns None if there is no HEAD or the path is not in it.
+fn read_head_blob(repo: &Repository, file_path: &str) -> Option<Vec<u8>> {
+    let tree = repo.head().ok()?.peel_to_tree().ok()?;
+    read_tree_blob(repo, &tree, file_path)
+}
+
+/// Read a file's staged blob content. Returns None if the path is not in the index.
+fn read_index_blob(repo: &Repository, file_path: &str) -> Option<Vec<u8>> {
+    let index = repo.index().ok()?;
+    let entry = index.get_path(std::path::Path::new(file_path), 0)?;
+    let blob = repo.find_blob(entry.id).ok()?;
+    Some(blob.content().to_vec())
+}
+
+/// Read a file from the working directory. Returns None if it does not exist.
+fn read_workdir_file(repo: &Repository, file_path: &str) -> Option<Vec<u8>> {
+    let workdir = repo.workdir()?;
+    std::fs::read(workdir.join(file_path)).ok()
+}
+
+/// Get audio/video metadata (duration, codec, size) for the old and new versions of a file.
+///
+/// The sides follow the same conventions as the text diffs:
+/// - `commit_id` given: parent commit vs commit
+/// - `staged`: HEAD vs index
+/// - otherwise: index vs working directory
+pub fn get_media_diff_info(
+    repo: &Repository,
+    file_path: &str,
+    staged: bool,
+    commit_id: Option<&str>,
+) -> Result<MediaDiffInfo, String> {
+    if media::media_kind(file_path).is_none() {
+        return Err(format!("Not an audio or video file: {}", file_path));
+    }
+
+    let (old_content, new_content) = if let Some(commit_id) = commit_id {
+        let oid = git2::Oid::from_str(commit_id).map_err(|e| e.message().to_string())?;
+        let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+        let commit_tree = commit.tree().map_err(|e| e.message().to_string())?;
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+
+        (
+            parent_tree.and_then(|tree| read_tree_blob(repo, &tree, file_path)),
+            read_tree_blob(repo, &commit_tree, file_path),
+        )
+    } else if staged {
+        (
+            read_head_blob(repo, file_path),
+            read_index_blob(repo, file_path),
+        )
+    } else {
+        (
+            read_index_blob(repo, file_path),
+            read_workdir_file(repo, file_path),
+        )
+    };
+
+    Ok(MediaDiffInfo {
+        file_path: file_path.to_string(),
+        old: old_content.and_then(|c| media::extract_metadata(file_path, &c)),
+        new: new_content.and_then(|c| media::extract_metadata(file_path, &c)),
+    })
+}
+
+// ============================================================================
+// Merge Functions
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergePreview {
+    pub source_branch: String,
+    pub target_branch: String,
+    pub commits_ahead: usize,
+    pub can_fast_forward: bool,
+    pub has_conflicts: bool,
+    pub conflicting_files: Vec<String>,
+}
+
+/// Get a preview of what a merge would look like without actually performing it
+pub fn get_merge_preview(repo_path: &str, source_branch: &str) -> Result<MergePreview, String> {
+    // Get current branch name
+    let head_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("rev-parse")
+        .arg("--abbrev-ref")
+        .arg("HEAD")
+        .output()
+        .map_err(|e| format!("Failed to get current branch: {}", e))?;
+
+    let target_branch = String::from_utf8_lossy(&head_output.stdout)
+        .trim()
+        .to_string();
+
+    // Get merge base (common ancestor)
+    let merge_base_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("merge-base")
+        .arg("HEAD")
+        .arg(source_branch)
+        .output()
+        .map_err(|e| format!("Failed to find merge base: {}", e))?;
+
+    if !merge_base_output.status.success() {
+        return Err(format!(
+            "Cannot find common ancestor between HEAD and '{}'. Are they related?",
+            source_branch
+        ));
+    }
+
+    let merge_base = String::from_utf8_lossy(&merge_base_output.stdout)
+        .trim()
+        .to_string();
+
+    // Count commits ahead (commits in source_branch not in HEAD)
+    let ahead_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("rev-list")
+        .arg("--count")
+        .arg(format!("{}..{}", merge_base, source_branch))
+        .output()
+        .map_err(|e| format!("Failed to count commits: {}", e))?;
+
+    let commits_ahead: usize = String::from_utf8_lossy(&ahead_output.stdout)
+        .trim()
+        .parse()
+        .unwrap_or(0);
+
+    // Check if can fast-forward (HEAD is at merge base)
+    let head_sha_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .map_err(|e| format!("Failed to get HEAD: {}", e))?;
+
+    let head_sha = String::from_utf8_lossy(&head_sha_output.stdout)
+        .trim()
+        .to_string();
+    let can_fast_forward = head_sha == merge_base;
+
+    // Check for conflicts using git merge-tree (doesn't modify working directory)
+    let source_sha_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("rev-parse")
+        .arg(source_branch)
+        .output()
+        .map_err(|e| format!("Failed to get source branch SHA: {}", e))?;
+
+    let source_sha = String::from_utf8_lossy(&source_sha_output.stdout)
+        .trim()
+        .to_string();
+
+    // Use git merge-tree to detect conflicts without modifying working tree
+    let merge_tree_output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("merge-tree")
+        .arg(&merge_base)
+        .arg(&head_sha)
+        .arg(&source_sha)
+        .output()
+        .map_err(|e| format!("Failed to run merge-tree: {}", e))?;
+
+    let merge_tree_result = String::from_utf8_lossy(&merge_tree_output.stdout).to_string();
+
+    // Parse conflicts from merge-tree output
+    let mut conflicting_files = Vec::new();
+    let has_conflicts = merge_tree_result.contains("<<<<<<<")
+        || merge_tree_result.contains("changed in both")
+        || merge_tree_result.contains("added in both");
+
+    if has_conflicts {
+        // Extract file paths from merge-tree output
+        // When there's a conflict, merge-tree outputs markers followed by file info
+        let mut in_conflict_section = false;
+        for line in merge_tree_result.lines() {
+            if line.contains("changed in both") || line.contains("added in both") {
+                in_conflict_section = true;
+                continue;
+            }
+            if in_conflict_section && line.starts_with("  ") {
+                // Lines with file info have format: "  base   100644 <sha> <path>"
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() >= 4 {
+                    let path = parts[3..].join(" ");
+                    if !path.is_empty() && !conflicting_files.contains(&path) {
+                        conflicting_files.push(path);
+                    }
+                }
+            }
+            if line.is_empty() {
+                in_conflict_section = false;
+            }
         }
-    } else {
-        Ok(create_success_result(format!(
-            "Branch '{}' deleted",
-            branch_name
-        )))
     }
-}
 
-// ============================================================================
-// Stash Operations
-// ============================================================================
-
-pub fn get_stashes(repo_path: &str) -> Result<Vec<StashInfo>, String> {
-    use std::process::Command;
-
-    // Use git stash list with custom format to get structured data
-    // Format: index|ref|message|timestamp
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("stash")
-        .arg("list")
-        .arg("--format=%gd|%gs|%ct")
-        .output()
-        .map_err(|e| format!("Failed to list stashes: {}", e))?;
+    Ok(MergePreview {
+        source_branch: source_branch.to_string(),
+        target_branch,
+        commits_ahead,
+        can_fast_forward,
+        has_conflicts,
+        conflicting_files,
+    })
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to list stashes: {}", stderr));
+/// A separate handle on `repo` whose new objects go to an in-memory backend,
+/// for merges and trees that are only inspected: nothing it writes reaches
+/// the object database
+fn in_memory_handle(repo: &Repository) -> Result<Repository, String> {
+    let scratch = Repository::open(repo.path()).map_err(|e| e.message().to_string())?;
+    {
+        let odb = scratch.odb().map_err(|e| e.message().to_string())?;
+        odb.add_new_mempack_backend(1000)
+            .map_err(|e| e.message().to_string())?;
     }
+    Ok(scratch)
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut stashes = Vec::new();
+/// Files the working tree would see change if HEAD were combined with
+/// `other`: a diff between HEAD and the three-way merge of the two, with the
+/// paths the merge cannot resolve reported as "conflicted". Merged objects go
+/// to an in-memory handle, so nothing is written.
+fn incoming_file_changes(repo: &Repository, other: &str) -> Result<Vec<FileStatus>, String> {
+    let scratch = in_memory_handle(repo)?;
 
-    for (index, line) in stdout.lines().enumerate() {
-        let parts: Vec<&str> = line.split('|').collect();
-        if parts.len() >= 3 {
-            let id = parts[0].to_string(); // e.g., "stash@{0}"
-            let message = parts[1].to_string(); // e.g., "WIP on main: abc1234 commit message"
-            let timestamp: i64 = parts[2].parse().unwrap_or(0);
+    let head = scratch
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(|e| e.message().to_string())?;
+    let other = scratch
+        .revparse_single(other)
+        .and_then(|o| o.peel_to_commit())
+        .map_err(|_| format!("'{}' does not name a branch or commit", other))?;
+    let merged = scratch
+        .merge_commits(&head, &other, None)
+        .map_err(|e| e.message().to_string())?;
 
-            // Extract branch name from message (format: "WIP on branch: ..." or "On branch: ...")
-            let branch = extract_branch_from_stash_message(&message);
+    let mut conflicted: Vec<String> = merged
+        .conflicts()
+        .map_err(|e| e.message().to_string())?
+        .flatten()
+        .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    conflicted.dedup();
 
-            stashes.push(StashInfo {
-                index,
-                id,
-                message,
-                branch,
-                timestamp,
-            });
-        }
-    }
+    let head_tree = head.tree().map_err(|e| e.message().to_string())?;
+    let mut diff = scratch
+        .diff_tree_to_index(Some(&head_tree), Some(&merged), None)
+        .map_err(|e| e.message().to_string())?;
+    diff.find_similar(None)
+        .map_err(|e| e.message().to_string())?;
 
-    Ok(stashes)
+    let mut files: Vec<FileStatus> = diff
+        .deltas()
+        .filter_map(|delta| {
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())?;
+            let status = match delta.status() {
+                git2::Delta::Added => "new",
+                git2::Delta::Deleted => "deleted",
+                git2::Delta::Modified | git2::Delta::Typechange => "modified",
+                git2::Delta::Renamed => "renamed",
+                git2::Delta::Copied => "copied",
+                _ => return None,
+            };
+            (!conflicted.contains(&path)).then(|| FileStatus {
+                path,
+                status: status.to_string(),
+                staged: false,
+                conflict: None,
+                old_path: None,
+            })
+        })
+        .collect();
+    files.extend(conflicted.into_iter().map(|path| FileStatus {
+        path,
+        status: "conflicted".to_string(),
+        staged: false,
+        conflict: None,
+        old_path: None,
+    }));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
 }
 
-fn extract_branch_from_stash_message(message: &str) -> String {
-    // Stash messages typically look like:
-    // "WIP on main: abc1234 commit message"
-    // "On main: custom message"
-    // "index on main: abc1234 commit message"
-    if let Some(rest) = message.strip_prefix("WIP on ") {
-        if let Some(colon_pos) = rest.find(':') {
-            return rest[..colon_pos].to_string();
-        }
-    }
-    if let Some(rest) = message.strip_prefix("On ") {
-        if let Some(colon_pos) = rest.find(':') {
-            return rest[..colon_pos].to_string();
-        }
-    }
-    if let Some(rest) = message.strip_prefix("index on ") {
-        if let Some(colon_pos) = rest.find(':') {
-            return rest[..colon_pos].to_string();
-        }
-    }
-    String::new()
+/// Files in the working tree that merging `source_branch` into HEAD would
+/// modify, so open editors can be warned before the merge runs
+pub fn get_merge_changed_files(
+    repo: &Repository,
+    source_branch: &str,
+) -> Result<Vec<FileStatus>, String> {
+    incoming_file_changes(repo, source_branch)
 }
 
-pub fn git_stash_save(
+/// Perform a git merge operation
+pub fn git_merge(
     repo_path: &str,
-    message: Option<&str>,
-    include_untracked: bool,
-    keep_index: bool,
+    source_branch: &str,
+    merge_type: &str,
 ) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let mut cmd = Command::new("git");
-    cmd.arg("-C").arg(repo_path).arg("stash").arg("push");
-
-    if include_untracked {
-        cmd.arg("-u");
-    }
-
-    if keep_index {
-        cmd.arg("--keep-index");
-    }
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path).arg("merge");
 
-    if let Some(msg) = message {
-        if !msg.trim().is_empty() {
-            cmd.arg("-m").arg(msg);
+    match merge_type {
+        "no-ff" => {
+            cmd.arg("--no-ff");
+        }
+        "squash" => {
+            cmd.arg("--squash");
         }
+        // "default" - no extra flags, allows fast-forward
+        _ => {}
     }
 
+    cmd.arg(source_branch);
+
     let output = cmd
         .output()
-        .map_err(|e| format!("Failed to save stash: {}", e))?;
+        .map_err(|e| format!("Failed to execute merge: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        // Check if there are no changes to stash
-        if stderr.contains("No local changes to save")
-            || stdout.contains("No local changes to save")
+        // Check for conflicts
+        if stdout.contains("CONFLICT")
+            || stderr.contains("CONFLICT")
+            || stdout.contains("Automatic merge failed")
+            || stderr.contains("Automatic merge failed")
         {
+            // Extract conflicting files
+            let mut conflicting_files = extract_conflicting_files(&stdout);
+            conflicting_files.extend(extract_conflicting_files(&stderr));
+
             return Ok(GitOperationResult {
                 success: false,
-                message: "No local changes to save".to_string(),
+                message: format!(
+                    "Merge conflicts detected. Resolve conflicts and commit.\n{}{}",
+                    stdout.trim(),
+                    if stderr.is_empty() {
+                        String::new()
+                    } else {
+                        format!("\n{}", stderr.trim())
+                    }
+                ),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("no_changes".to_string()),
-                conflicting_files: None,
+                error_type: Some("merge_conflicts".to_string()),
+                conflicting_files: Some(conflicting_files),
             });
         }
+
         return Ok(create_error_result(&stderr, &stdout));
     }
 
-    Ok(create_success_result(
-        "Stash saved successfully".to_string(),
-    ))
-}
-
-pub fn git_stash_apply(repo_path: &str, stash_index: usize) -> Result<GitOperationResult, String> {
-    use std::process::Command;
+    // For squash merges, remind user to commit
+    if merge_type == "squash" {
+        return Ok(GitOperationResult {
+            success: true,
+            message: format!(
+                "Squash merge completed. Changes are staged but not committed.\n{}",
+                stdout.trim()
+            ),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: None,
+            conflicting_files: None,
+        });
+    }
 
-    let stash_ref = format!("stash@{{{}}}", stash_index);
+    Ok(create_success_result(format!(
+        "Merge completed successfully.\n{}",
+        stdout.trim()
+    )))
+}
 
-    let output = Command::new("git")
+/// Abort an in-progress merge
+pub fn git_merge_abort(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
         .arg("-C")
         .arg(repo_path)
-        .arg("stash")
-        .arg("apply")
-        .arg(&stash_ref)
+        .arg("merge")
+        .arg("--abort")
         .output()
-        .map_err(|e| format!("Failed to apply stash: {}", e))?;
+        .map_err(|e| format!("Failed to abort merge: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        // Check for conflicts
-        if stderr.contains("CONFLICT") || stdout.contains("CONFLICT") {
+        if stderr.contains("no merge to abort") || stderr.contains("There is no merge to abort") {
             return Ok(GitOperationResult {
                 success: false,
-                message: format!(
-                    "Stash applied with conflicts. Resolve conflicts and commit.\n{}",
-                    stderr.trim()
-                ),
+                message: "No merge in progress to abort.".to_string(),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("conflicts".to_string()),
+                error_type: Some("no_merge_in_progress".to_string()),
                 conflicting_files: None,
             });
         }
@@ -2507,60 +6244,66 @@ pub fn git_stash_apply(repo_path: &str, stash_index: usize) -> Result<GitOperati
     }
 
     Ok(create_success_result(
-        "Stash applied successfully".to_string(),
+        "Merge aborted successfully.".to_string(),
     ))
 }
 
-pub fn git_stash_pop(repo_path: &str, stash_index: usize) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let stash_ref = format!("stash@{{{}}}", stash_index);
-
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("stash")
-        .arg("pop")
-        .arg(&stash_ref)
-        .output()
-        .map_err(|e| format!("Failed to pop stash: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+/// Message git prepared for the merge in progress (MERGE_MSG), without the
+/// comment lines, or None when no merge is in progress
+pub fn get_merge_message(repo: &Repository) -> Result<Option<String>, String> {
+    if repo.state() != git2::RepositoryState::Merge {
+        return Ok(None);
+    }
+    match repo.message() {
+        Ok(message) => git2::message_prettify(message, Some(b'#'))
+            .map(Some)
+            .map_err(|e| e.message().to_string()),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.message().to_string()),
+    }
+}
 
-    if !output.status.success() {
-        // Check for conflicts
-        if stderr.contains("CONFLICT") || stdout.contains("CONFLICT") {
-            return Ok(GitOperationResult {
-                success: false,
-                message: format!("Stash popped with conflicts. Resolve conflicts and commit. The stash was not dropped.\n{}", stderr.trim()),
-                requires_ssh_verification: None,
-                requires_credential: None,
-                error_type: Some("conflicts".to_string()),
-                conflicting_files: None,
-            });
-        }
-        return Ok(create_error_result(&stderr, &stdout));
+/// Commit a merge after resolving its conflicts, with `message` or, when
+/// None, the message git prepared
+pub fn git_merge_continue(
+    repo_path: &str,
+    message: Option<&str>,
+) -> Result<GitOperationResult, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.message().to_string())?;
+    if repo.state() != git2::RepositoryState::Merge {
+        return Ok(GitOperationResult {
+            success: false,
+            message: "No merge in progress.".to_string(),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: Some("no_merge_in_progress".to_string()),
+            conflicting_files: None,
+        });
+    }
+    let conflicting_files = unmerged_files(repo_path);
+    if !conflicting_files.is_empty() {
+        return Ok(GitOperationResult {
+            success: false,
+            message: "There are still unresolved conflicts.".to_string(),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: Some("merge_conflicts".to_string()),
+            conflicting_files: Some(conflicting_files),
+        });
     }
 
-    Ok(create_success_result(
-        "Stash popped successfully".to_string(),
-    ))
-}
-
-pub fn git_stash_drop(repo_path: &str, stash_index: usize) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let stash_ref = format!("stash@{{{}}}", stash_index);
-
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("stash")
-        .arg("drop")
-        .arg(&stash_ref)
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path).arg("commit");
+    match message {
+        Some(message) => cmd.arg("-m").arg(message),
+        // Without an editor git keeps the commented "# Conflicts:" list of
+        // the prepared message; strip it like the editor would
+        None => cmd.args(["--no-edit", "--cleanup=strip"]),
+    };
+    let output = cmd
+        .env("GIT_TERMINAL_PROMPT", "0")
         .output()
-        .map_err(|e| format!("Failed to drop stash: {}", e))?;
+        .map_err(|e| format!("Failed to execute git commit: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -2569,378 +6312,483 @@ pub fn git_stash_drop(repo_path: &str, stash_index: usize) -> Result<GitOperatio
         return Ok(create_error_result(&stderr, &stdout));
     }
 
-    Ok(create_success_result(format!(
-        "Stash {} dropped",
-        stash_ref
-    )))
+    Ok(create_success_result(
+        "Merge committed successfully.".to_string(),
+    ))
 }
 
 // ============================================================================
-// Image Content Functions
+// REBASE OPERATIONS
 // ============================================================================
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ImageContent {
-    pub base64: String,
-    pub mime_type: String,
-    pub file_size: u64,
+pub struct RebasePreview {
+    pub source_branch: String,
+    pub target_branch: String,
+    pub commits_to_rebase: usize,
 }
 
-/// Get MIME type from file extension
-fn get_mime_type(file_path: &str) -> String {
-    let lower = file_path.to_lowercase();
-    if lower.ends_with(".png") {
-        "image/png".to_string()
-    } else if lower.ends_with(".jpg") || lower.ends_with(".jpeg") {
-        "image/jpeg".to_string()
-    } else if lower.ends_with(".gif") {
-        "image/gif".to_string()
-    } else if lower.ends_with(".bmp") {
-        "image/bmp".to_string()
-    } else if lower.ends_with(".webp") {
-        "image/webp".to_string()
-    } else if lower.ends_with(".svg") {
-        "image/svg+xml".to_string()
-    } else if lower.ends_with(".ico") {
-        "image/x-icon".to_string()
-    } else {
-        "application/octet-stream".to_string()
-    }
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RebaseOptions {
+    pub preserve_merges: bool,
+    pub autostash: bool,
 }
 
-/// Get current image content from working directory as base64
-pub fn get_image_content(repo: &Repository, file_path: &str) -> Result<ImageContent, String> {
-    use base64::{engine::general_purpose::STANDARD, Engine as _};
-
-    let workdir = repo.workdir().ok_or("No working directory")?;
-    let full_path = workdir.join(file_path);
+/// Get a preview of the rebase operation
+pub fn get_rebase_preview(repo_path: &str, target_branch: &str) -> Result<RebasePreview, String> {
+    // Get current branch name
+    let output = git_command()
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(repo_path)
+        .output()
+        .map_err(|e| format!("Failed to get current branch: {}", e))?;
 
-    if !full_path.exists() {
-        return Err(format!("File does not exist: {}", file_path));
+    if !output.status.success() {
+        return Err("Failed to get current branch".to_string());
     }
 
-    let content = std::fs::read(&full_path).map_err(|e| format!("Failed to read file: {}", e))?;
-
-    let file_size = content.len() as u64;
-    let base64_content = STANDARD.encode(&content);
-    let mime_type = get_mime_type(file_path);
-
-    Ok(ImageContent {
-        base64: base64_content,
-        mime_type,
-        file_size,
-    })
-}
+    let source_branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
-/// Get image content from HEAD commit as base64
-pub fn get_image_from_head(repo: &Repository, file_path: &str) -> Result<ImageContent, String> {
-    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    // Get merge base
+    let output = git_command()
+        .args(["merge-base", "HEAD", target_branch])
+        .current_dir(repo_path)
+        .output()
+        .map_err(|e| format!("Failed to get merge base: {}", e))?;
 
-    let head = repo.head().map_err(|e| e.message().to_string())?;
-    let tree = head.peel_to_tree().map_err(|e| e.message().to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to find common ancestor with '{}'",
+            target_branch
+        ));
+    }
 
-    let entry = tree
-        .get_path(std::path::Path::new(file_path))
-        .map_err(|e| format!("File not found in HEAD: {}", e.message()))?;
+    let merge_base = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
-    let blob = repo
-        .find_blob(entry.id())
-        .map_err(|e| format!("Failed to get blob: {}", e.message()))?;
+    // Count commits to rebase (commits in current branch that are not in target)
+    let output = git_command()
+        .args(["rev-list", "--count", &format!("{}..HEAD", merge_base)])
+        .current_dir(repo_path)
+        .output()
+        .map_err(|e| format!("Failed to count commits: {}", e))?;
 
-    let content = blob.content();
-    let file_size = content.len() as u64;
-    let base64_content = STANDARD.encode(content);
-    let mime_type = get_mime_type(file_path);
+    let commits_to_rebase = if output.status.success() {
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<usize>()
+            .unwrap_or(0)
+    } else {
+        0
+    };
 
-    Ok(ImageContent {
-        base64: base64_content,
-        mime_type,
-        file_size,
+    Ok(RebasePreview {
+        source_branch,
+        target_branch: target_branch.to_string(),
+        commits_to_rebase,
     })
 }
 
-/// Get image content from index (staged) as base64
-pub fn get_image_from_index(repo: &Repository, file_path: &str) -> Result<ImageContent, String> {
-    use base64::{engine::general_purpose::STANDARD, Engine as _};
-
-    let index = repo.index().map_err(|e| e.message().to_string())?;
-
-    let entry = index
-        .get_path(std::path::Path::new(file_path), 0)
-        .ok_or_else(|| format!("File not found in index: {}", file_path))?;
-
-    let blob = repo
-        .find_blob(entry.id)
-        .map_err(|e| format!("Failed to get blob: {}", e.message()))?;
-
-    let content = blob.content();
-    let file_size = content.len() as u64;
-    let base64_content = STANDARD.encode(content);
-    let mime_type = get_mime_type(file_path);
-
-    Ok(ImageContent {
-        base64: base64_content,
-        mime_type,
-        file_size,
-    })
+/// Files in the working tree that rebasing HEAD onto `target_branch` would
+/// modify. Replaying the branch commits onto the target ends, barring
+/// conflicts, at the same tree as merging the two, so the merge result is
+/// compared against HEAD.
+pub fn get_rebase_changed_files(
+    repo: &Repository,
+    target_branch: &str,
+) -> Result<Vec<FileStatus>, String> {
+    incoming_file_changes(repo, target_branch)
 }
 
-// ============================================================================
-// Merge Functions
-// ============================================================================
+/// Execute a rebase operation
+pub fn git_rebase(
+    repo_path: &str,
+    target_branch: &str,
+    options: RebaseOptions,
+) -> Result<GitOperationResult, String> {
+    let mut args = vec!["rebase".to_string()];
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct MergePreview {
-    pub source_branch: String,
-    pub target_branch: String,
-    pub commits_ahead: usize,
-    pub can_fast_forward: bool,
-    pub has_conflicts: bool,
-    pub conflicting_files: Vec<String>,
-}
+    if options.preserve_merges {
+        args.push("--rebase-merges".to_string());
+    }
 
-/// Get a preview of what a merge would look like without actually performing it
-pub fn get_merge_preview(repo_path: &str, source_branch: &str) -> Result<MergePreview, String> {
-    use std::process::Command;
+    if options.autostash {
+        args.push("--autostash".to_string());
+    }
 
-    // Get current branch name
-    let head_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("rev-parse")
-        .arg("--abbrev-ref")
-        .arg("HEAD")
+    args.push(target_branch.to_string());
+
+    let output = git_command()
+        .args(&args)
+        .current_dir(repo_path)
         .output()
-        .map_err(|e| format!("Failed to get current branch: {}", e))?;
+        .map_err(|e| format!("Failed to execute git rebase: {}", e))?;
 
-    let target_branch = String::from_utf8_lossy(&head_output.stdout)
-        .trim()
-        .to_string();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    // Get merge base (common ancestor)
-    let merge_base_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("merge-base")
-        .arg("HEAD")
-        .arg(source_branch)
-        .output()
-        .map_err(|e| format!("Failed to find merge base: {}", e))?;
+    if !output.status.success() {
+        // Check for conflicts
+        if stderr.contains("CONFLICT")
+            || stderr.contains("conflict")
+            || stdout.contains("CONFLICT")
+            || stdout.contains("could not apply")
+        {
+            return Ok(GitOperationResult {
+                success: false,
+                message: format!("Rebase conflicts detected. Please resolve conflicts and run 'git rebase --continue'."),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("rebase_conflicts".to_string()),
+                conflicting_files: Some(unmerged_files(repo_path)),
+            });
+        }
 
-    if !merge_base_output.status.success() {
-        return Err(format!(
-            "Cannot find common ancestor between HEAD and '{}'. Are they related?",
-            source_branch
-        ));
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    // Check if rebase resulted in "Already up to date" or similar
+    if stdout.contains("is up to date") || stdout.contains("Already applied") {
+        return Ok(GitOperationResult {
+            success: true,
+            message: "Already up to date, nothing to rebase.".to_string(),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: None,
+            conflicting_files: None,
+        });
     }
 
-    let merge_base = String::from_utf8_lossy(&merge_base_output.stdout)
-        .trim()
-        .to_string();
+    Ok(create_success_result(format!(
+        "Rebase onto '{}' completed successfully.",
+        target_branch
+    )))
+}
 
-    // Count commits ahead (commits in source_branch not in HEAD)
-    let ahead_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("rev-list")
-        .arg("--count")
-        .arg(format!("{}..{}", merge_base, source_branch))
+/// Files left with conflicts in the index by a merge, rebase or cherry-pick
+pub(crate) fn unmerged_files(repo_path: &str) -> Vec<String> {
+    git_command()
+        .args(["diff", "--name-only", "--diff-filter=U"])
+        .current_dir(repo_path)
         .output()
-        .map_err(|e| format!("Failed to count commits: {}", e))?;
-
-    let commits_ahead: usize = String::from_utf8_lossy(&ahead_output.stdout)
-        .trim()
-        .parse()
-        .unwrap_or(0);
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-    // Check if can fast-forward (HEAD is at merge base)
-    let head_sha_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("rev-parse")
-        .arg("HEAD")
+/// Abort a rebase in progress
+pub fn git_rebase_abort(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
+        .args(["rebase", "--abort"])
+        .current_dir(repo_path)
         .output()
-        .map_err(|e| format!("Failed to get HEAD: {}", e))?;
+        .map_err(|e| format!("Failed to execute git rebase --abort: {}", e))?;
 
-    let head_sha = String::from_utf8_lossy(&head_sha_output.stdout)
-        .trim()
-        .to_string();
-    let can_fast_forward = head_sha == merge_base;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    // Check for conflicts using git merge-tree (doesn't modify working directory)
-    let source_sha_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("rev-parse")
-        .arg(source_branch)
-        .output()
-        .map_err(|e| format!("Failed to get source branch SHA: {}", e))?;
+    if !output.status.success() {
+        // Check if there's no rebase in progress
+        if stderr.contains("No rebase in progress") || stderr.contains("no rebase in progress") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: "No rebase in progress to abort.".to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("no_rebase_in_progress".to_string()),
+                conflicting_files: None,
+            });
+        }
+        return Ok(create_error_result(&stderr, &stdout));
+    }
 
-    let source_sha = String::from_utf8_lossy(&source_sha_output.stdout)
-        .trim()
-        .to_string();
+    Ok(create_success_result(
+        "Rebase aborted successfully.".to_string(),
+    ))
+}
 
-    // Use git merge-tree to detect conflicts without modifying working tree
-    let merge_tree_output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("merge-tree")
-        .arg(&merge_base)
-        .arg(&head_sha)
-        .arg(&source_sha)
+/// Continue a rebase after resolving conflicts
+pub fn git_rebase_continue(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
+        .args(["rebase", "--continue"])
+        .current_dir(repo_path)
+        .env("GIT_EDITOR", "true") // Skip editor for commit messages
         .output()
-        .map_err(|e| format!("Failed to run merge-tree: {}", e))?;
+        .map_err(|e| format!("Failed to execute git rebase --continue: {}", e))?;
 
-    let merge_tree_result = String::from_utf8_lossy(&merge_tree_output.stdout).to_string();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    // Parse conflicts from merge-tree output
-    let mut conflicting_files = Vec::new();
-    let has_conflicts = merge_tree_result.contains("<<<<<<<")
-        || merge_tree_result.contains("changed in both")
-        || merge_tree_result.contains("added in both");
+    if !output.status.success() {
+        // Check if there are still conflicts
+        if stderr.contains("CONFLICT") || stderr.contains("conflict") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: "There are still unresolved conflicts.".to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("rebase_conflicts".to_string()),
+                conflicting_files: None,
+            });
+        }
 
-    if has_conflicts {
-        // Extract file paths from merge-tree output
-        // When there's a conflict, merge-tree outputs markers followed by file info
-        let mut in_conflict_section = false;
-        for line in merge_tree_result.lines() {
-            if line.contains("changed in both") || line.contains("added in both") {
-                in_conflict_section = true;
-                continue;
-            }
-            if in_conflict_section && line.starts_with("  ") {
-                // Lines with file info have format: "  base   100644 <sha> <path>"
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 4 {
-                    let path = parts[3..].join(" ");
-                    if !path.is_empty() && !conflicting_files.contains(&path) {
-                        conflicting_files.push(path);
-                    }
-                }
-            }
-            if line.is_empty() {
-                in_conflict_section = false;
-            }
+        // Check if there's no rebase in progress
+        if stderr.contains("No rebase in progress") || stderr.contains("no rebase in progress") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: "No rebase in progress.".to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("no_rebase_in_progress".to_string()),
+                conflicting_files: None,
+            });
         }
+
+        return Ok(create_error_result(&stderr, &stdout));
     }
 
-    Ok(MergePreview {
-        source_branch: source_branch.to_string(),
-        target_branch,
-        commits_ahead,
-        can_fast_forward,
-        has_conflicts,
-        conflicting_files,
-    })
+    Ok(create_success_result(
+        "Rebase continued successfully.".to_string(),
+    ))
 }
 
-/// Perform a git merge operation
-pub fn git_merge(
+/// Apply the changes of `commit_id` on top of the current branch. With
+/// `no_commit` they are only staged, to be edited or combined before committing.
+pub fn git_cherry_pick(
     repo_path: &str,
-    source_branch: &str,
-    merge_type: &str,
+    commit_id: &str,
+    no_commit: bool,
 ) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let mut cmd = Command::new("git");
-    cmd.arg("-C").arg(repo_path).arg("merge");
-
-    match merge_type {
-        "no-ff" => {
-            cmd.arg("--no-ff");
-        }
-        "squash" => {
-            cmd.arg("--squash");
-        }
-        // "default" - no extra flags, allows fast-forward
-        _ => {}
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path).arg("cherry-pick");
+    if no_commit {
+        cmd.arg("--no-commit");
     }
-
-    cmd.arg(source_branch);
+    cmd.arg(commit_id);
 
     let output = cmd
         .output()
-        .map_err(|e| format!("Failed to execute merge: {}", e))?;
+        .map_err(|e| format!("Failed to execute git cherry-pick: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let short_id = commit_id.get(..7).unwrap_or(commit_id);
 
     if !output.status.success() {
-        // Check for conflicts
-        if stdout.contains("CONFLICT")
-            || stderr.contains("CONFLICT")
-            || stdout.contains("Automatic merge failed")
-            || stderr.contains("Automatic merge failed")
-        {
-            // Extract conflicting files
-            let mut conflicting_files = extract_conflicting_files(&stdout);
-            conflicting_files.extend(extract_conflicting_files(&stderr));
+        let stopped_at = format!("'{}'", short_id);
+        return Ok(cherry_pick_failure(
+            repo_path,
+            &stdout,
+            &stderr,
+            &stopped_at,
+        ));
+    }
 
-            return Ok(GitOperationResult {
-                success: false,
-                message: format!(
-                    "Merge conflicts detected. Resolve conflicts and commit.\n{}{}",
-                    stdout.trim(),
-                    if stderr.is_empty() {
-                        String::new()
-                    } else {
-                        format!("\n{}", stderr.trim())
-                    }
-                ),
-                requires_ssh_verification: None,
-                requires_credential: None,
-                error_type: Some("merge_conflicts".to_string()),
-                conflicting_files: Some(conflicting_files),
-            });
-        }
+    if no_commit {
+        return Ok(create_success_result(format!(
+            "Changes of '{}' applied and staged but not committed.",
+            short_id
+        )));
+    }
+    Ok(create_success_result(format!(
+        "Commit '{}' cherry-picked successfully.",
+        short_id
+    )))
+}
 
-        return Ok(create_error_result(&stderr, &stdout));
+/// Result of a cherry-pick that stopped at `stopped_at` (the commit it was
+/// applying, as shown to the user)
+fn cherry_pick_failure(
+    repo_path: &str,
+    stdout: &str,
+    stderr: &str,
+    stopped_at: &str,
+) -> GitOperationResult {
+    let conflicting_files = unmerged_files(repo_path);
+    if !conflicting_files.is_empty() {
+        return GitOperationResult {
+            success: false,
+            message: format!(
+                "Cherry-pick of {} stopped on conflicts. Resolve them, then continue or abort the cherry-pick.",
+                stopped_at
+            ),
+            requires_ssh_verification: None,
+            requires_credential: None,
+            error_type: Some("cherry_pick_conflicts".to_string()),
+            conflicting_files: Some(conflicting_files),
+        };
     }
 
-    // For squash merges, remind user to commit
-    if merge_type == "squash" {
-        return Ok(GitOperationResult {
-            success: true,
+    // The changes are already on the branch, git stops with nothing to commit
+    if stderr.contains("now empty") || stdout.contains("nothing to commit") {
+        return GitOperationResult {
+            success: false,
             message: format!(
-                "Squash merge completed. Changes are staged but not committed.\n{}",
-                stdout.trim()
+                "The changes of {} are already on this branch. Abort the cherry-pick to leave it.",
+                stopped_at
             ),
             requires_ssh_verification: None,
             requires_credential: None,
-            error_type: None,
+            error_type: Some("cherry_pick_empty".to_string()),
             conflicting_files: None,
+        };
+    }
+
+    create_error_result(stderr, stdout)
+}
+
+/// Commits to cherry-pick for `specs`, oldest first. Each spec is a commit or
+/// a range `A..B`: the commits of B that are not in A, like `git cherry-pick A..B`.
+fn resolve_cherry_pick_commits(
+    repo: &Repository,
+    specs: &[String],
+) -> Result<Vec<git2::Oid>, String> {
+    let mut commits = Vec::new();
+    let mut seen = HashSet::new();
+    for spec in specs {
+        let oids: Vec<git2::Oid> = if spec.contains("..") {
+            let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+            revwalk
+                .push_range(spec)
+                .map_err(|e| format!("Cannot resolve '{}': {}", spec, e.message()))?;
+            revwalk
+                .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+                .map_err(|e| e.message().to_string())?;
+            revwalk
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.message().to_string())?
+        } else {
+            vec![resolve_commit(repo, spec)?.id()]
+        };
+        for oid in oids {
+            if !seen.insert(oid) {
+                continue;
+            }
+            let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+            if commit.parent_count() > 1 {
+                return Err(format!(
+                    "'{}' is a merge commit; cherry-pick the commits it merged instead",
+                    &oid.to_string()[..7]
+                ));
+            }
+            commits.push(oid);
+        }
+    }
+    Ok(commits)
+}
+
+/// Cherry-pick a list of commits and ranges (`A..B`) in order, reporting
+/// progress (stage "cherry_pick") after each commit. The commits are handed to a single git
+/// cherry-pick, so a conflict stops the sequence where git_cherry_pick_continue
+/// picks up the remaining commits and git_cherry_pick_abort undoes all of them.
+pub fn git_cherry_pick_range(
+    repo_path: &str,
+    specs: &[String],
+    mut on_progress: impl FnMut(GitProgress),
+) -> Result<GitOperationResult, String> {
+    use std::process::Stdio;
+
+    let repo = open_repository(repo_path)?;
+    let commits = resolve_cherry_pick_commits(&repo, specs)?;
+    if commits.is_empty() {
+        return Err("No commits to cherry-pick".to_string());
+    }
+    let total = commits.len() as u64;
+    let report = |on_progress: &mut dyn FnMut(GitProgress), picked: u64| {
+        on_progress(GitProgress {
+            stage: "cherry_pick".to_string(),
+            percent: Some((picked * 100 / total) as u32),
+            current: Some(picked),
+            total: Some(total),
+            bytes: None,
+        })
+    };
+
+    let mut child = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("cherry-pick")
+        .args(commits.iter().map(|oid| oid.to_string()))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git cherry-pick: {}", e))?;
+
+    // Drain stderr on its own thread: a full pipe would block git while we are
+    // still reading stdout
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = std::io::Read::read_to_end(&mut stderr, &mut buffer);
+            buffer
+        })
+    });
+
+    // Git prints "[branch abc1234] subject" for every commit it creates
+    report(&mut on_progress, 0);
+    let mut picked = 0;
+    let mut messages = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        progress::read_lines(stdout, |line| {
+            if line.starts_with('[') {
+                picked += 1;
+                report(&mut on_progress, picked);
+            }
+            messages.push(line.to_string());
         });
     }
 
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for git cherry-pick: {}", e))?;
+    let stdout = messages.join("\n");
+    let stderr = stderr_reader
+        .and_then(|reader| reader.join().ok())
+        .map(|buffer| String::from_utf8_lossy(&buffer).to_string())
+        .unwrap_or_default();
+
+    if !status.success() {
+        let stopped_at = commits
+            .get(picked as usize)
+            .map(|oid| format!("'{}' ({} of {})", &oid.to_string()[..7], picked + 1, total))
+            .unwrap_or_else(|| "the range".to_string());
+        return Ok(cherry_pick_failure(
+            repo_path,
+            &stdout,
+            &stderr,
+            &stopped_at,
+        ));
+    }
+
     Ok(create_success_result(format!(
-        "Merge completed successfully.\n{}",
-        stdout.trim()
+        "{} commit{} cherry-picked successfully.",
+        total,
+        if total == 1 { "" } else { "s" }
     )))
 }
 
-/// Abort an in-progress merge
-pub fn git_merge_abort(repo_path: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("merge")
-        .arg("--abort")
+/// Abort a cherry-pick in progress, going back to the commit it started from
+pub fn git_cherry_pick_abort(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
+        .args(["cherry-pick", "--abort"])
+        .current_dir(repo_path)
         .output()
-        .map_err(|e| format!("Failed to abort merge: {}", e))?;
+        .map_err(|e| format!("Failed to execute git cherry-pick --abort: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        if stderr.contains("no merge to abort") || stderr.contains("There is no merge to abort") {
+        if stderr.contains("no cherry-pick or revert in progress") {
             return Ok(GitOperationResult {
                 success: false,
-                message: "No merge in progress to abort.".to_string(),
+                message: "No cherry-pick in progress to abort.".to_string(),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("no_merge_in_progress".to_string()),
+                error_type: Some("no_cherry_pick_in_progress".to_string()),
                 conflicting_files: None,
             });
         }
@@ -2948,187 +6796,177 @@ pub fn git_merge_abort(repo_path: &str) -> Result<GitOperationResult, String> {
     }
 
     Ok(create_success_result(
-        "Merge aborted successfully.".to_string(),
+        "Cherry-pick aborted successfully.".to_string(),
     ))
 }
 
-// ============================================================================
-// REBASE OPERATIONS
-// ============================================================================
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct RebasePreview {
-    pub source_branch: String,
-    pub target_branch: String,
-    pub commits_to_rebase: usize,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct RebaseOptions {
-    pub preserve_merges: bool,
-    pub autostash: bool,
-}
-
-/// Get a preview of the rebase operation
-pub fn get_rebase_preview(repo_path: &str, target_branch: &str) -> Result<RebasePreview, String> {
-    use std::process::Command;
-
-    // Get current branch name
-    let output = Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+/// Commit a cherry-pick after resolving its conflicts, keeping the original
+/// message
+pub fn git_cherry_pick_continue(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
+        .args(["cherry-pick", "--continue"])
         .current_dir(repo_path)
+        .env("GIT_EDITOR", "true") // Skip editor for commit messages
         .output()
-        .map_err(|e| format!("Failed to get current branch: {}", e))?;
-
-    if !output.status.success() {
-        return Err("Failed to get current branch".to_string());
-    }
-
-    let source_branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        .map_err(|e| format!("Failed to execute git cherry-pick --continue: {}", e))?;
 
-    // Get merge base
-    let output = Command::new("git")
-        .args(["merge-base", "HEAD", target_branch])
-        .current_dir(repo_path)
-        .output()
-        .map_err(|e| format!("Failed to get merge base: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        return Err(format!(
-            "Failed to find common ancestor with '{}'",
-            target_branch
-        ));
-    }
-
-    let merge_base = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let conflicting_files = unmerged_files(repo_path);
+        if !conflicting_files.is_empty() {
+            return Ok(GitOperationResult {
+                success: false,
+                message: "There are still unresolved conflicts.".to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("cherry_pick_conflicts".to_string()),
+                conflicting_files: Some(conflicting_files),
+            });
+        }
 
-    // Count commits to rebase (commits in current branch that are not in target)
-    let output = Command::new("git")
-        .args(["rev-list", "--count", &format!("{}..HEAD", merge_base)])
-        .current_dir(repo_path)
-        .output()
-        .map_err(|e| format!("Failed to count commits: {}", e))?;
+        if stderr.contains("no cherry-pick or revert in progress") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: "No cherry-pick in progress.".to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("no_cherry_pick_in_progress".to_string()),
+                conflicting_files: None,
+            });
+        }
 
-    let commits_to_rebase = if output.status.success() {
-        String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .parse::<usize>()
-            .unwrap_or(0)
-    } else {
-        0
-    };
+        return Ok(create_error_result(&stderr, &stdout));
+    }
 
-    Ok(RebasePreview {
-        source_branch,
-        target_branch: target_branch.to_string(),
-        commits_to_rebase,
-    })
+    Ok(create_success_result(
+        "Cherry-pick continued successfully.".to_string(),
+    ))
 }
 
-/// Execute a rebase operation
-pub fn git_rebase(
+/// Create a commit undoing `commit_id` on the current branch, the safe way to
+/// undo a commit that is already pushed. Reverting a merge commit needs
+/// `mainline`, the 1-based parent whose side is kept. With `no_commit` the
+/// changes are only staged.
+pub fn git_revert(
     repo_path: &str,
-    target_branch: &str,
-    options: RebaseOptions,
+    commit_id: &str,
+    mainline: Option<u32>,
+    no_commit: bool,
 ) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let mut args = vec!["rebase".to_string()];
-
-    if options.preserve_merges {
-        args.push("--rebase-merges".to_string());
+    let repo = open_repository(repo_path)?;
+    let commit = resolve_commit(&repo, commit_id)?;
+    let short_id = &commit.id().to_string()[..7];
+    let parent_count = commit.parent_count() as u32;
+    match mainline {
+        None if parent_count > 1 => {
+            return Ok(GitOperationResult {
+                success: false,
+                message: format!(
+                    "'{}' is a merge commit; choose the parent (1 to {}) whose side to keep.",
+                    short_id, parent_count
+                ),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("revert_needs_mainline".to_string()),
+                conflicting_files: None,
+            });
+        }
+        Some(_) if parent_count < 2 => {
+            return Err(format!("'{}' is not a merge commit", short_id));
+        }
+        Some(parent) if parent == 0 || parent > parent_count => {
+            return Err(format!(
+                "'{}' has {} parents; parent {} does not exist",
+                short_id, parent_count, parent
+            ));
+        }
+        _ => {}
     }
 
-    if options.autostash {
-        args.push("--autostash".to_string());
+    let mut cmd = git_command();
+    cmd.arg("-C").arg(repo_path).args(["revert", "--no-edit"]);
+    if let Some(parent) = mainline {
+        cmd.arg("-m").arg(parent.to_string());
     }
+    if no_commit {
+        cmd.arg("--no-commit");
+    }
+    cmd.arg(commit.id().to_string());
 
-    args.push(target_branch.to_string());
-
-    let output = Command::new("git")
-        .args(&args)
-        .current_dir(repo_path)
+    let output = cmd
         .output()
-        .map_err(|e| format!("Failed to execute git rebase: {}", e))?;
+        .map_err(|e| format!("Failed to execute git revert: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        // Check for conflicts
-        if stderr.contains("CONFLICT")
-            || stderr.contains("conflict")
-            || stdout.contains("CONFLICT")
-            || stdout.contains("could not apply")
-        {
-            // Get list of conflicting files
-            let status_output = Command::new("git")
-                .args(["diff", "--name-only", "--diff-filter=U"])
-                .current_dir(repo_path)
-                .output();
-
-            let conflicting_files = if let Ok(status) = status_output {
-                String::from_utf8_lossy(&status.stdout)
-                    .lines()
-                    .map(|s| s.to_string())
-                    .collect()
-            } else {
-                vec![]
-            };
-
+        let conflicting_files = unmerged_files(repo_path);
+        if !conflicting_files.is_empty() {
             return Ok(GitOperationResult {
                 success: false,
-                message: format!("Rebase conflicts detected. Please resolve conflicts and run 'git rebase --continue'."),
+                message: format!(
+                    "Revert of '{}' stopped on conflicts. Resolve them, then continue or abort the revert.",
+                    short_id
+                ),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("rebase_conflicts".to_string()),
+                error_type: Some("revert_conflicts".to_string()),
                 conflicting_files: Some(conflicting_files),
             });
         }
 
+        // The changes were already undone, git stops with nothing to commit
+        if stdout.contains("nothing to commit") {
+            return Ok(GitOperationResult {
+                success: false,
+                message: format!(
+                    "The changes of '{}' are not on this branch anymore. Abort the revert to leave it.",
+                    short_id
+                ),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: Some("revert_empty".to_string()),
+                conflicting_files: None,
+            });
+        }
+
         return Ok(create_error_result(&stderr, &stdout));
     }
 
-    // Check if rebase resulted in "Already up to date" or similar
-    if stdout.contains("is up to date") || stdout.contains("Already applied") {
-        return Ok(GitOperationResult {
-            success: true,
-            message: "Already up to date, nothing to rebase.".to_string(),
-            requires_ssh_verification: None,
-            requires_credential: None,
-            error_type: None,
-            conflicting_files: None,
-        });
+    if no_commit {
+        return Ok(create_success_result(format!(
+            "Changes of '{}' reverted and staged but not committed.",
+            short_id
+        )));
     }
-
     Ok(create_success_result(format!(
-        "Rebase onto '{}' completed successfully.",
-        target_branch
+        "Commit '{}' reverted successfully.",
+        short_id
     )))
 }
 
-/// Abort a rebase in progress
-pub fn git_rebase_abort(repo_path: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let output = Command::new("git")
-        .args(["rebase", "--abort"])
+/// Abort a revert in progress, going back to the commit it started from
+pub fn git_revert_abort(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
+        .args(["revert", "--abort"])
         .current_dir(repo_path)
         .output()
-        .map_err(|e| format!("Failed to execute git rebase --abort: {}", e))?;
+        .map_err(|e| format!("Failed to execute git revert --abort: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        // Check if there's no rebase in progress
-        if stderr.contains("No rebase in progress") || stderr.contains("no rebase in progress") {
+        if stderr.contains("no cherry-pick or revert in progress") {
             return Ok(GitOperationResult {
                 success: false,
-                message: "No rebase in progress to abort.".to_string(),
+                message: "No revert in progress to abort.".to_string(),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("no_rebase_in_progress".to_string()),
+                error_type: Some("no_revert_in_progress".to_string()),
                 conflicting_files: None,
             });
         }
@@ -3136,45 +6974,43 @@ pub fn git_rebase_abort(repo_path: &str) -> Result<GitOperationResult, String> {
     }
 
     Ok(create_success_result(
-        "Rebase aborted successfully.".to_string(),
+        "Revert aborted successfully.".to_string(),
     ))
 }
 
-/// Continue a rebase after resolving conflicts
-pub fn git_rebase_continue(repo_path: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
-    let output = Command::new("git")
-        .args(["rebase", "--continue"])
+/// Commit a revert after resolving its conflicts, keeping the generated
+/// message
+pub fn git_revert_continue(repo_path: &str) -> Result<GitOperationResult, String> {
+    let output = git_command()
+        .args(["revert", "--continue"])
         .current_dir(repo_path)
         .env("GIT_EDITOR", "true") // Skip editor for commit messages
         .output()
-        .map_err(|e| format!("Failed to execute git rebase --continue: {}", e))?;
+        .map_err(|e| format!("Failed to execute git revert --continue: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
     if !output.status.success() {
-        // Check if there are still conflicts
-        if stderr.contains("CONFLICT") || stderr.contains("conflict") {
+        let conflicting_files = unmerged_files(repo_path);
+        if !conflicting_files.is_empty() {
             return Ok(GitOperationResult {
                 success: false,
                 message: "There are still unresolved conflicts.".to_string(),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("rebase_conflicts".to_string()),
-                conflicting_files: None,
+                error_type: Some("revert_conflicts".to_string()),
+                conflicting_files: Some(conflicting_files),
             });
         }
 
-        // Check if there's no rebase in progress
-        if stderr.contains("No rebase in progress") || stderr.contains("no rebase in progress") {
+        if stderr.contains("no cherry-pick or revert in progress") {
             return Ok(GitOperationResult {
                 success: false,
-                message: "No rebase in progress.".to_string(),
+                message: "No revert in progress.".to_string(),
                 requires_ssh_verification: None,
                 requires_credential: None,
-                error_type: Some("no_rebase_in_progress".to_string()),
+                error_type: Some("no_revert_in_progress".to_string()),
                 conflicting_files: None,
             });
         }
@@ -3183,10 +7019,151 @@ pub fn git_rebase_continue(repo_path: &str) -> Result<GitOperationResult, String
     }
 
     Ok(create_success_result(
-        "Rebase continued successfully.".to_string(),
+        "Revert continued successfully.".to_string(),
     ))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetMode {
+    Soft,  // move the branch, keep the index and the work tree
+    Mixed, // also reset the index, changes stay in the work tree
+    Hard,  // also reset the work tree, discarding tracked changes
+}
+
+impl ResetMode {
+    fn option(self) -> &'static str {
+        match self {
+            ResetMode::Soft => "--soft",
+            ResetMode::Mixed => "--mixed",
+            ResetMode::Hard => "--hard",
+        }
+    }
+}
+
+/// Commits listed at most in a ResetPreview
+const RESET_PREVIEW_LIMIT: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResetPreview {
+    pub target: CommitInfo,
+    pub mode: ResetMode,
+    pub lost_count: usize, // commits of the branch that are not in the target
+    pub lost_commits: Vec<CommitInfo>, // newest first, at most RESET_PREVIEW_LIMIT
+    pub lost_elsewhere: usize, // of those, commits still on another branch or a remote
+    pub discards_changes: bool, // hard reset with local changes to tracked files
+    pub discarded_files: Vec<String>,
+    pub unstaged_files: Vec<String>, // staged changes a mixed reset moves back to the work tree
+}
+
+/// What resetting the current branch to `commit_id` with `mode` would do: the
+/// commits it would take off the branch and the local changes it would
+/// discard or unstage, for the confirmation shown before git_reset. Nothing
+/// in the repository is modified.
+pub fn reset_preview(
+    repo: &Repository,
+    commit_id: &str,
+    mode: ResetMode,
+) -> Result<ResetPreview, String> {
+    let target = resolve_commit(repo, commit_id)?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| e.message().to_string())?;
+    let (lost, lost_count) =
+        commits_between_capped(repo, head.id(), [target.id()], RESET_PREVIEW_LIMIT)?;
+
+    // Commits reachable from another branch or a remote are not really lost
+    let head_ref = repo.head().ok().and_then(|h| h.name().map(String::from));
+    let other_tips: Vec<git2::Oid> = repo
+        .branches(None)
+        .map_err(|e| e.message().to_string())?
+        .flatten()
+        .filter(|(branch, _)| branch.get().name().map(String::from) != head_ref)
+        .filter_map(|(branch, _)| branch.get().target())
+        .collect();
+    let lost_elsewhere = if lost_count == 0 || other_tips.is_empty() {
+        0
+    } else {
+        let hidden = other_tips.into_iter().chain([target.id()]);
+        let (_, only_here) = commits_between_capped(repo, head.id(), hidden, 0)?;
+        lost_count - only_here
+    };
+
+    let mut discarded_files = Vec::new();
+    let mut unstaged_files = Vec::new();
+    if mode != ResetMode::Soft {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false);
+        let statuses = repo
+            .statuses(Some(&mut opts))
+            .map_err(|e| e.message().to_string())?;
+        let staged = Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE;
+        for entry in statuses.iter() {
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            if mode == ResetMode::Hard {
+                discarded_files.push(path.to_string());
+            } else if entry.status().intersects(staged) {
+                unstaged_files.push(path.to_string());
+            }
+        }
+    }
+
+    Ok(ResetPreview {
+        target: commit_info(&target),
+        mode,
+        lost_count,
+        lost_commits: lost
+            .iter()
+            .filter_map(|oid| repo.find_commit(*oid).ok())
+            .map(|commit| commit_info(&commit))
+            .collect(),
+        lost_elsewhere,
+        discards_changes: !discarded_files.is_empty(),
+        discarded_files,
+        unstaged_files,
+    })
+}
+
+/// Reset the current branch to `commit_id`
+pub fn git_reset(
+    repo_path: &str,
+    commit_id: &str,
+    mode: ResetMode,
+) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    let target = resolve_commit(&repo, commit_id)?;
+    let short_id = &target.id().to_string()[..7];
+
+    let output = git_command()
+        .arg("-C")
+        .arg(repo_path)
+        .arg("reset")
+        .arg(mode.option())
+        .arg(target.id().to_string())
+        .output()
+        .map_err(|e| format!("Failed to execute git reset: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        return Ok(create_error_result(&stderr, &stdout));
+    }
+
+    Ok(create_success_result(format!(
+        "Branch reset to '{}' ({}).",
+        short_id,
+        &mode.option()[2..]
+    )))
+}
+
 /// Interactive rebase action type
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -3228,10 +7205,8 @@ pub fn get_interactive_rebase_commits(
     repo_path: &str,
     target_branch: &str,
 ) -> Result<Vec<InteractiveRebaseEntry>, String> {
-    use std::process::Command;
-
     // Get merge base between HEAD and target
-    let merge_base_output = Command::new("git")
+    let merge_base_output = git_command()
         .args(["merge-base", "HEAD", target_branch])
         .current_dir(repo_path)
         .output()
@@ -3246,7 +7221,7 @@ pub fn get_interactive_rebase_commits(
         .to_string();
 
     // Get commits between merge base and HEAD in reverse order (oldest first, like git rebase -i shows)
-    let log_output = Command::new("git")
+    let log_output = git_command()
         .args([
             "log",
             "--reverse",
@@ -3289,7 +7264,6 @@ pub fn git_interactive_rebase(
     entries: Vec<InteractiveRebaseEntry>,
     autostash: bool,
 ) -> Result<GitOperationResult, String> {
-    use std::fs;
     use std::process::Command;
 
     // Create temporary file with rebase todo list
@@ -3306,39 +7280,38 @@ pub fn git_interactive_rebase(
         .collect::<Vec<_>>()
         .join("\n");
 
-    // Create temp file for the todo list
-    let temp_dir = std::env::temp_dir();
-    let todo_file = temp_dir.join(format!("forky_rebase_todo_{}", std::process::id()));
-
-    fs::write(&todo_file, &todo_content)
+    // Scratch file for the todo list, removed when the rebase returns
+    let todo_file = ScratchFile::create("rebase_todo", "", todo_content.as_bytes())
         .map_err(|e| format!("Failed to write rebase todo file: {}", e))?;
 
     // Create a script that will replace the todo file
-    let script_file = temp_dir.join(format!("forky_rebase_editor_{}", std::process::id()));
-
     #[cfg(unix)]
-    {
-        let script_content = format!("#!/bin/sh\ncp \"{}\" \"$1\"\n", todo_file.to_string_lossy());
-        fs::write(&script_file, &script_content)
+    let script_file = {
+        let script_content = format!(
+            "#!/bin/sh\ncp \"{}\" \"$1\"\n",
+            todo_file.path().to_string_lossy()
+        );
+        let script_file = ScratchFile::create("rebase_editor", "", script_content.as_bytes())
             .map_err(|e| format!("Failed to write editor script: {}", e))?;
 
         // Make script executable
         Command::new("chmod")
-            .args(["+x", script_file.to_str().unwrap()])
+            .arg("+x")
+            .arg(script_file.path())
             .output()
             .map_err(|e| format!("Failed to make script executable: {}", e))?;
-    }
+        script_file
+    };
 
     #[cfg(windows)]
-    {
-        let script_file = temp_dir.join(format!("forky_rebase_editor_{}.cmd", std::process::id()));
+    let script_file = {
         let script_content = format!(
             "@echo off\ncopy /Y \"{}\" \"%~1\"\n",
-            todo_file.to_string_lossy().replace("/", "\\")
+            todo_file.path().to_string_lossy().replace("/", "\\")
         );
-        fs::write(&script_file, &script_content)
-            .map_err(|e| format!("Failed to write editor script: {}", e))?;
-    }
+        ScratchFile::create("rebase_editor", ".cmd", script_content.as_bytes())
+            .map_err(|e| format!("Failed to write editor script: {}", e))?
+    };
 
     // Build rebase command
     let mut args = vec!["rebase", "-i"];
@@ -3348,17 +7321,18 @@ pub fn git_interactive_rebase(
     args.push(target_branch);
 
     // Execute rebase with custom GIT_SEQUENCE_EDITOR
-    let output = Command::new("git")
+    let output = git_command()
         .args(&args)
         .current_dir(repo_path)
-        .env("GIT_SEQUENCE_EDITOR", script_file.to_str().unwrap())
+        // Git runs the editor through the shell; the app data dir may have spaces
+        .env(
+            "GIT_SEQUENCE_EDITOR",
+            format!("\"{}\"", script_file.path().to_string_lossy()),
+        )
         .env("GIT_EDITOR", "true") // Skip editor for commit messages
         .output()
         .map_err(|e| format!("Failed to execute git rebase: {}", e))?;
-
-    // Cleanup temp files
-    let _ = fs::remove_file(&todo_file);
-    let _ = fs::remove_file(&script_file);
+    drop((todo_file, script_file));
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -3370,28 +7344,13 @@ pub fn git_interactive_rebase(
             || stdout.contains("CONFLICT")
             || stdout.contains("conflict")
         {
-            // Get conflicting files
-            let status_output = Command::new("git")
-                .args(["diff", "--name-only", "--diff-filter=U"])
-                .current_dir(repo_path)
-                .output();
-
-            let conflicting_files = if let Ok(status) = status_output {
-                String::from_utf8_lossy(&status.stdout)
-                    .lines()
-                    .map(|s| s.to_string())
-                    .collect()
-            } else {
-                vec![]
-            };
-
             return Ok(GitOperationResult {
                 success: false,
                 message: "Rebase conflicts detected. Please resolve conflicts and run 'git rebase --continue'.".to_string(),
                 requires_ssh_verification: None,
                 requires_credential: None,
                 error_type: Some("rebase_conflicts".to_string()),
-                conflicting_files: Some(conflicting_files),
+                conflicting_files: Some(unmerged_files(repo_path)),
             });
         }
 
@@ -3475,6 +7434,187 @@ pub fn get_gitflow_config(repo: &Repository) -> Result<GitFlowConfig, String> {
     })
 }
 
+/// Local branch to use instead of a missing git flow branch: the first of
+/// `candidates` that exists and is not `taken`
+fn existing_branch_among(repo: &Repository, candidates: &[&str], taken: &str) -> Option<String> {
+    candidates
+        .iter()
+        .find(|name| **name != taken && repo.find_branch(name, BranchType::Local).is_ok())
+        .map(|name| name.to_string())
+}
+
+/// Problems that make git flow misbehave: branches that no longer exist (often
+/// `master` renamed to `main` with a stale config) and branch prefixes that are
+/// empty or overlap, so a branch would be taken for two kinds. Empty when git
+/// flow is not initialized or the configuration is sound.
+pub fn validate_gitflow_config(repo: &Repository) -> Result<Vec<GitFlowIssue>, String> {
+    let config = get_gitflow_config(repo)?;
+    if !config.initialized {
+        return Ok(Vec::new());
+    }
+    let mut issues = Vec::new();
+    let branch_exists = |name: &str| repo.find_branch(name, BranchType::Local).is_ok();
+
+    if config.master_branch == config.develop_branch {
+        issues.push(GitFlowIssue {
+            kind: GitFlowIssueKind::SameMasterAndDevelop,
+            message: format!(
+                "The production and development branches are both '{}'",
+                config.master_branch
+            ),
+            key: "gitflow.branch.develop".to_string(),
+            suggested_value: None,
+        });
+    }
+
+    if !branch_exists(&config.master_branch) {
+        let suggested_value = find_default_branch(repo)
+            .and_then(|name| name.split_once('/').map(|(_, branch)| branch.to_string()))
+            .filter(|name| branch_exists(name) && *name != config.develop_branch)
+            .or_else(|| existing_branch_among(repo, &["main", "master"], &config.develop_branch));
+        issues.push(GitFlowIssue {
+            kind: GitFlowIssueKind::MissingMasterBranch,
+            message: format!(
+                "The production branch '{}' does not exist",
+                config.master_branch
+            ),
+            key: "gitflow.branch.master".to_string(),
+            suggested_value,
+        });
+    }
+
+    if !branch_exists(&config.develop_branch) {
+        let on_remote = repo
+            .branches(Some(BranchType::Remote))
+            .map_err(|e| e.message().to_string())?
+            .flatten()
+            .filter_map(|(branch, _)| branch.name().ok().flatten().map(String::from))
+            .find(|name| {
+                name.split_once('/')
+                    .is_some_and(|(_, branch)| branch == config.develop_branch)
+            });
+        let (message, suggested_value) = match on_remote {
+            // Checking it out fixes it; the config is right
+            Some(remote_branch) => (
+                format!(
+                    "The development branch '{}' only exists as '{}'; check it out",
+                    config.develop_branch, remote_branch
+                ),
+                None,
+            ),
+            None => (
+                format!(
+                    "The development branch '{}' does not exist",
+                    config.develop_branch
+                ),
+                existing_branch_among(
+                    repo,
+                    &["develop", "development", "dev"],
+                    &config.master_branch,
+                ),
+            ),
+        };
+        issues.push(GitFlowIssue {
+            kind: GitFlowIssueKind::MissingDevelopBranch,
+            message,
+            key: "gitflow.branch.develop".to_string(),
+            suggested_value,
+        });
+    }
+
+    let prefixes = [
+        ("feature", &config.feature_prefix),
+        ("release", &config.release_prefix),
+        ("hotfix", &config.hotfix_prefix),
+    ];
+    for (i, (kind, prefix)) in prefixes.iter().enumerate() {
+        let key = format!("gitflow.prefix.{}", kind);
+        let default = format!("{}/", kind);
+        // The default, unless another prefix already overlaps it (empty ones are
+        // reported themselves)
+        let suggested_value = Some(default.clone()).filter(|default| {
+            prefixes.iter().all(|(other, other_prefix)| {
+                other == kind
+                    || other_prefix.is_empty()
+                    || !(other_prefix.starts_with(default.as_str())
+                        || default.starts_with(other_prefix.as_str()))
+            })
+        });
+        if prefix.is_empty() {
+            issues.push(GitFlowIssue {
+                kind: GitFlowIssueKind::EmptyPrefix,
+                message: format!("The {} prefix is empty, so every branch matches it", kind),
+                key,
+                suggested_value,
+            });
+            continue;
+        }
+        // Reported on the later of two overlapping prefixes
+        if let Some((other, other_prefix)) = prefixes[..i].iter().find(|(_, other_prefix)| {
+            !other_prefix.is_empty()
+                && (prefix.starts_with(other_prefix.as_str())
+                    || other_prefix.starts_with(prefix.as_str()))
+        }) {
+            issues.push(GitFlowIssue {
+                kind: GitFlowIssueKind::PrefixCollision,
+                message: format!(
+                    "The {} prefix '{}' overlaps the {} prefix '{}'",
+                    kind, prefix, other, other_prefix
+                ),
+                key,
+                suggested_value,
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Apply the suggested fix of every issue validate_gitflow_config finds; the
+/// ones without a suggestion are listed as left to fix by hand
+pub fn repair_gitflow_config(repo_path: &str) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    let issues = validate_gitflow_config(&repo)?;
+    if issues.is_empty() {
+        return Ok(create_success_result(
+            "The Git Flow configuration has no problems".to_string(),
+        ));
+    }
+
+    let mut config = repo
+        .config()
+        .and_then(|config| config.open_level(git2::ConfigLevel::Local))
+        .map_err(|e| format!("Failed to get config: {}", e))?;
+    let mut repaired = Vec::new();
+    let mut remaining = Vec::new();
+    for issue in issues {
+        match &issue.suggested_value {
+            Some(value) => {
+                config
+                    .set_str(&issue.key, value)
+                    .map_err(|e| format!("Failed to set {}: {}", issue.key, e))?;
+                repaired.push(format!("{} set to '{}'", issue.key, value));
+            }
+            None => remaining.push(issue.message),
+        }
+    }
+
+    if repaired.is_empty() {
+        return Ok(create_error_result(
+            &format!(
+                "Nothing could be repaired automatically: {}",
+                remaining.join("; ")
+            ),
+            "",
+        ));
+    }
+    let mut message = repaired.join(", ");
+    if !remaining.is_empty() {
+        message.push_str(&format!(". Still to fix by hand: {}", remaining.join("; ")));
+    }
+    Ok(create_success_result(message))
+}
+
 /// Get current branch flow info (type and name without prefix)
 pub fn get_current_branch_flow_info(repo: &Repository) -> Result<CurrentBranchFlowInfo, String> {
     let head = repo
@@ -3629,7 +7769,7 @@ pub fn git_flow_start(
     let branch_name = format!("{}{}", prefix, name);
 
     // Create and checkout the new branch from base
-    let output = std::process::Command::new("git")
+    let output = git_command()
         .args(["checkout", "-b", &branch_name, &base_branch])
         .current_dir(repo_path)
         .output()
@@ -3648,160 +7788,6 @@ pub fn git_flow_start(
     )))
 }
 
-/// Finish a git flow branch (feature, release, or hotfix)
-pub fn git_flow_finish(
-    repo_path: &str,
-    flow_type: &str,
-    name: &str,
-    delete_branch: bool,
-) -> Result<GitOperationResult, String> {
-    let repo = open_repository(repo_path)?;
-    let config = get_gitflow_config(&repo)?;
-
-    let (prefix, target_branches, create_tag) = match flow_type {
-        "feature" => (
-            config.feature_prefix.clone(),
-            vec![config.develop_branch.clone()],
-            false,
-        ),
-        "release" => (
-            config.release_prefix.clone(),
-            vec![config.master_branch.clone(), config.develop_branch.clone()],
-            true,
-        ),
-        "hotfix" => (
-            config.hotfix_prefix.clone(),
-            vec![config.master_branch.clone(), config.develop_branch.clone()],
-            true,
-        ),
-        _ => {
-            return Ok(create_error_result(
-                &format!("Unknown flow type: {}", flow_type),
-                "",
-            ))
-        }
-    };
-
-    let branch_name = format!("{}{}", prefix, name);
-    let mut messages = Vec::new();
-
-    // Merge into each target branch
-    for target in &target_branches {
-        // Checkout target branch
-        let output = std::process::Command::new("git")
-            .args(["checkout", target])
-            .current_dir(repo_path)
-            .output()
-            .map_err(|e| format!("Failed to execute git checkout: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Ok(create_error_result(
-                &format!("Failed to checkout '{}': {}", target, stderr),
-                "",
-            ));
-        }
-
-        // Merge with --no-ff
-        let merge_message = format!("Merge {} '{}' into {}", flow_type, name, target);
-        let output = std::process::Command::new("git")
-            .args(["merge", "--no-ff", "-m", &merge_message, &branch_name])
-            .current_dir(repo_path)
-            .output()
-            .map_err(|e| format!("Failed to execute git merge: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-            // Check for merge conflicts
-            if stderr.contains("CONFLICT") || stderr.contains("Automatic merge failed") {
-                return Ok(GitOperationResult {
-                    success: false,
-                    message: format!("Merge conflict while merging into '{}'. Please resolve conflicts manually.", target),
-                    requires_ssh_verification: None,
-                    requires_credential: None,
-                    error_type: Some("merge_conflict".to_string()),
-                    conflicting_files: None,
-                });
-            }
-
-            return Ok(create_error_result(
-                &format!("Failed to merge into '{}': {}", target, stderr),
-                "",
-            ));
-        }
-
-        messages.push(format!("Merged into '{}'", target));
-    }
-
-    // Create tag for release/hotfix (on master branch)
-    if create_tag {
-        // Make sure we're on master for tagging
-        let _ = std::process::Command::new("git")
-            .args(["checkout", &config.master_branch])
-            .current_dir(repo_path)
-            .output();
-
-        let tag_message = format!(
-            "{} {}",
-            if flow_type == "release" {
-                "Release"
-            } else {
-                "Hotfix"
-            },
-            name
-        );
-        let output = std::process::Command::new("git")
-            .args(["tag", "-a", name, "-m", &tag_message])
-            .current_dir(repo_path)
-            .output()
-            .map_err(|e| format!("Failed to create tag: {}", e))?;
-
-        if output.status.success() {
-            messages.push(format!("Created tag '{}'", name));
-        } else {
-            // Tag might already exist, not a fatal error
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            if !stderr.contains("already exists") {
-                messages.push(format!("Warning: Could not create tag: {}", stderr));
-            }
-        }
-    }
-
-    // Delete branch if requested
-    if delete_branch {
-        let output = std::process::Command::new("git")
-            .args(["branch", "-d", &branch_name])
-            .current_dir(repo_path)
-            .output()
-            .map_err(|e| format!("Failed to delete branch: {}", e))?;
-
-        if output.status.success() {
-            messages.push(format!("Deleted branch '{}'", branch_name));
-        } else {
-            // Try force delete if normal delete fails
-            let output = std::process::Command::new("git")
-                .args(["branch", "-D", &branch_name])
-                .current_dir(repo_path)
-                .output();
-
-            if let Ok(output) = output {
-                if output.status.success() {
-                    messages.push(format!("Deleted branch '{}' (force)", branch_name));
-                }
-            }
-        }
-    }
-
-    // Checkout back to develop
-    let _ = std::process::Command::new("git")
-        .args(["checkout", &config.develop_branch])
-        .current_dir(repo_path)
-        .output();
-
-    Ok(create_success_result(messages.join(". ")))
-}
-
 // ============================================================================
 // Global Git Identity
 // ============================================================================
@@ -3814,9 +7800,7 @@ pub struct GitIdentity {
 
 /// Read a single global git config entry. Returns None if it is unset.
 fn read_global_config(key: &str) -> Option<String> {
-    use std::process::Command;
-
-    let output = Command::new("git")
+    let output = git_command()
         .args(["config", "--global", "--get", key])
         .env("GIT_TERMINAL_PROMPT", "0")
         .output()
@@ -3842,10 +7826,56 @@ pub fn git_get_global_identity() -> Result<GitIdentity, String> {
     })
 }
 
+/// The identity git uses for commits in a repository, after evaluating every
+/// config level and conditional include (`includeIf "gitdir:~/work/"`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepositoryIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub name_origin: Option<String>, // config file that sets it, e.g. ~/.gitconfig-work
+    pub email_origin: Option<String>,
+}
+
+/// Read a config entry as git resolves it inside `repo_path`, with the file it
+/// comes from. Returns None if it is unset.
+fn read_repository_config(repo_path: &str, key: &str) -> Option<(String, String)> {
+    let output = git_command()
+        .args(["-C", repo_path, "config", "--show-origin", "--get", key])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // "file:/home/me/.gitconfig-work\tJane Doe"; paths with special characters are quoted
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (origin, value) = stdout.trim_end_matches('\n').split_once('\t')?;
+    let origin = origin.trim_matches('"');
+    let origin = origin.strip_prefix("file:").unwrap_or(origin);
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some((value.to_string(), origin.to_string()))
+    }
+}
+
+/// Read the identity used for commits in a repository
+pub fn git_get_repository_identity(repo_path: &str) -> Result<RepositoryIdentity, String> {
+    let (name, name_origin) = read_repository_config(repo_path, "user.name").unzip();
+    let (email, email_origin) = read_repository_config(repo_path, "user.email").unzip();
+    Ok(RepositoryIdentity {
+        name,
+        email,
+        name_origin,
+        email_origin,
+    })
+}
+
 /// Write user.name and user.email at the global level
 pub fn git_set_global_identity(name: &str, email: &str) -> Result<GitOperationResult, String> {
-    use std::process::Command;
-
     let name = name.trim();
     let email = email.trim();
 
@@ -3856,7 +7886,7 @@ pub fn git_set_global_identity(name: &str, email: &str) -> Result<GitOperationRe
         return Ok(create_error_result("Email cannot be empty", ""));
     }
 
-    let set_name = Command::new("git")
+    let set_name = git_command()
         .args(["config", "--global", "user.name", name])
         .env("GIT_TERMINAL_PROMPT", "0")
         .output()
@@ -3868,7 +7898,7 @@ pub fn git_set_global_identity(name: &str, email: &str) -> Result<GitOperationRe
         return Ok(create_error_result(&stderr, &stdout));
     }
 
-    let set_email = Command::new("git")
+    let set_email = git_command()
         .args(["config", "--global", "user.email", email])
         .env("GIT_TERMINAL_PROMPT", "0")
         .output()
@@ -3909,7 +7939,7 @@ pub fn git_fast_forward(
         let remote_ref = format!("{}/{}", remote, branch);
 
         // First fetch the remote branch
-        let fetch_output = std::process::Command::new("git")
+        let fetch_output = git_command()
             .args(["fetch", remote, branch])
             .current_dir(repo_path)
             .output()
@@ -3921,7 +7951,7 @@ pub fn git_fast_forward(
         }
 
         // Then merge with --ff-only
-        let merge_output = std::process::Command::new("git")
+        let merge_output = git_command()
             .args(["merge", "--ff-only", &remote_ref])
             .current_dir(repo_path)
             .output()
@@ -3956,7 +7986,7 @@ pub fn git_fast_forward(
         // For non-current branches, use git fetch remote branch:branch
         let refspec = format!("{}:{}", branch, branch);
 
-        let output = std::process::Command::new("git")
+        let output = git_command()
             .args(["fetch", remote, &refspec])
             .current_dir(repo_path)
             .output()
@@ -3989,3 +8019,565 @@ pub fn git_fast_forward(
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_file, git, init_repo};
+    use std::path::Path;
+
+    fn read(dir: &Path, file: &str) -> String {
+        std::fs::read_to_string(dir.join(file)).unwrap()
+    }
+
+    /// Content of `file` in the index of `repo`
+    fn staged_content(repo: &Repository, file: &str) -> String {
+        let index = repo.index().unwrap();
+        let entry = index.get_path(Path::new(file), 0).unwrap();
+        let blob = repo.find_blob(entry.id).unwrap();
+        String::from_utf8(blob.content().to_vec()).unwrap()
+    }
+
+    /// A hunk of a diff as the frontend sends it back for a hunk operation
+    fn hunk_data(hunk: &DiffHunk) -> HunkData {
+        HunkData {
+            old_start: hunk.old_start,
+            old_lines: hunk.old_lines,
+            new_start: hunk.new_start,
+            new_lines: hunk.new_lines,
+            lines: hunk
+                .lines
+                .iter()
+                .map(|line| HunkLineData {
+                    content: line.content.clone(),
+                    line_type: line.line_type.clone(),
+                })
+                .collect(),
+            old_path: None,
+            new_path: None,
+            old_mode: None,
+            new_mode: None,
+        }
+    }
+
+    /// Twenty numbered lines, with `changes` replacing some of them
+    fn numbered_lines(changes: &[(usize, &str)]) -> String {
+        (1..=20)
+            .map(|n| match changes.iter().find(|(line, _)| *line == n) {
+                Some((_, text)) => format!("{}\n", text),
+                None => format!("{}\n", n),
+            })
+            .collect()
+    }
+
+    fn head_summary(dir: &Path) -> String {
+        let repo = Repository::open(dir).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        head.summary().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn test_revert_commits_the_inverse_change() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "one\n", "one");
+        commit_file(path, "a.txt", "two\n", "two");
+
+        let result = git_revert(path.to_str().unwrap(), "HEAD", None, false).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(read(path, "a.txt"), "one\n");
+        assert_eq!(head_summary(path), "Revert \"two\"");
+    }
+
+    #[test]
+    fn test_conflicting_revert_can_be_aborted_or_continued() {
+        let dir = init_repo();
+        let path = dir.path();
+        let repo_path = path.to_str().unwrap();
+        commit_file(path, "a.txt", "one\n", "one");
+        commit_file(path, "a.txt", "two\n", "two");
+        commit_file(path, "a.txt", "three\n", "three");
+
+        let result = git_revert(repo_path, "HEAD~1", None, false).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error_type.as_deref(), Some("revert_conflicts"));
+        assert_eq!(result.conflicting_files, Some(vec!["a.txt".to_string()]));
+
+        let result = git_revert_abort(repo_path).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(read(path, "a.txt"), "three\n");
+        assert_eq!(head_summary(path), "three");
+
+        git_revert(repo_path, "HEAD~1", None, false).unwrap();
+        let result = git_revert_continue(repo_path).unwrap();
+        assert_eq!(result.error_type.as_deref(), Some("revert_conflicts"));
+
+        std::fs::write(path.join("a.txt"), "resolved\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        let result = git_revert_continue(repo_path).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(head_summary(path), "Revert \"two\"");
+        let repo = Repository::open(path).unwrap();
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn test_reset_preview_lists_what_the_reset_takes_off_the_branch() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "one\n", "one");
+        commit_file(path, "a.txt", "two\n", "two");
+        git(path, &["branch", "keep"]);
+        commit_file(path, "a.txt", "three\n", "three");
+        std::fs::write(path.join("a.txt"), "changed\n").unwrap();
+
+        let repo = Repository::open(path).unwrap();
+        let preview = reset_preview(&repo, "HEAD~2", ResetMode::Hard).unwrap();
+        assert_eq!(preview.lost_count, 2);
+        let messages: Vec<&str> = preview
+            .lost_commits
+            .iter()
+            .map(|commit| commit.message.trim())
+            .collect();
+        assert_eq!(messages, ["three", "two"]);
+        assert_eq!(preview.lost_elsewhere, 1);
+        assert!(preview.discards_changes);
+        assert_eq!(preview.discarded_files, ["a.txt"]);
+
+        git(path, &["add", "a.txt"]);
+        let preview = reset_preview(&repo, "HEAD~2", ResetMode::Mixed).unwrap();
+        assert!(!preview.discards_changes);
+        assert_eq!(preview.unstaged_files, ["a.txt"]);
+
+        let result = git_reset(path.to_str().unwrap(), "HEAD~2", ResetMode::Hard).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(head_summary(path), "one");
+        assert_eq!(read(path, "a.txt"), "one\n");
+    }
+
+    #[test]
+    fn test_reset_preview_caps_the_listed_commits() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "base\n", "base");
+        let repo = Repository::open(path).unwrap();
+        let signature = repo.signature().unwrap();
+        let total = RESET_PREVIEW_LIMIT + 5;
+        for n in 0..total {
+            let parent = repo.head().unwrap().peel_to_commit().unwrap();
+            let tree = parent.tree().unwrap();
+            let message = format!("commit {}", n);
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &message,
+                &tree,
+                &[&parent],
+            )
+            .unwrap();
+        }
+
+        let preview = reset_preview(&repo, ":/base", ResetMode::Soft).unwrap();
+        assert_eq!(preview.lost_count, total);
+        assert_eq!(preview.lost_commits.len(), RESET_PREVIEW_LIMIT);
+        assert_eq!(preview.lost_elsewhere, 0);
+    }
+
+    /// A repository with "line 1" to "line 10" in a.txt and the diff that
+    /// changes lines 2 and 9 of it, in two hunks
+    fn repo_with_patch() -> (tempfile::TempDir, String) {
+        let dir = init_repo();
+        let path = dir.path();
+        let lines: Vec<String> = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        commit_file(path, "a.txt", &lines.concat(), "lines");
+
+        let mut changed = lines.clone();
+        changed[1] = "line two\n".to_string();
+        changed[8] = "line nine\n".to_string();
+        std::fs::write(path.join("a.txt"), changed.concat()).unwrap();
+        let output = git_command()
+            .args(["diff", "-U1"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        git(path, &["checkout", "--", "a.txt"]);
+        (dir, String::from_utf8(output.stdout).unwrap())
+    }
+
+    #[test]
+    fn test_apply_working_diff_applies_cleanly() {
+        let (dir, patch) = repo_with_patch();
+        let result = apply_working_diff(dir.path().to_str().unwrap(), &patch, false).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(result.applied_files, ["a.txt"]);
+        assert!(read(dir.path(), "a.txt").contains("line nine"));
+    }
+
+    #[test]
+    fn test_apply_working_diff_rejects_hunks_that_do_not_apply() {
+        let (dir, patch) = repo_with_patch();
+        let path = dir.path();
+        let content = read(path, "a.txt").replace("line 9\n", "line 9 edited\n");
+        std::fs::write(path.join("a.txt"), content).unwrap();
+
+        let result = apply_working_diff(path.to_str().unwrap(), &patch, false).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.rejected_files.len(), 1);
+        assert_eq!(result.rejected_files[0].path, "a.txt");
+        assert_eq!(result.rejected_files[0].hunks, [2]);
+        assert!(read(path, "a.txt").contains("line two"));
+        assert!(path.join("a.txt.rej").exists());
+    }
+
+    #[test]
+    fn test_apply_working_diff_refuses_a_patch_that_does_not_fit() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "a\n", "a");
+        std::fs::write(path.join("b.txt"), "b\n").unwrap();
+        git(path, &["add", "-N", "b.txt"]);
+        let output = git_command()
+            .args(["diff"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        let patch = String::from_utf8(output.stdout).unwrap();
+
+        // b.txt already exists, so the patch creating it is refused as a whole
+        let result = apply_working_diff(path.to_str().unwrap(), &patch, false).unwrap();
+        assert!(!result.success);
+        assert!(result.applied_files.is_empty());
+        assert!(result.rejected_files.is_empty());
+    }
+    #[test]
+    fn test_merge_continue_commits_without_the_conflicts_comment() {
+        let dir = init_repo();
+        let path = dir.path();
+        let repo_path = path.to_str().unwrap();
+        commit_file(path, "a.txt", "base\n", "base");
+        git(path, &["checkout", "-q", "-b", "other"]);
+        commit_file(path, "a.txt", "other\n", "other");
+        git(path, &["checkout", "-q", "main"]);
+        commit_file(path, "a.txt", "main\n", "main");
+        let merge = git_command()
+            .args(["merge", "other"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        assert!(!merge.status.success());
+
+        let result = git_merge_continue(repo_path, None).unwrap();
+        assert_eq!(result.error_type.as_deref(), Some("merge_conflicts"));
+
+        std::fs::write(path.join("a.txt"), "resolved\n").unwrap();
+        git(path, &["add", "a.txt"]);
+        let result = git_merge_continue(repo_path, None).unwrap();
+        assert!(result.success, "{}", result.message);
+        let repo = Repository::open(path).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_count(), 2);
+        assert_eq!(head.message(), Some("Merge branch 'other'\n"));
+    }
+
+    #[test]
+    fn test_unstage_files_unstages_staged_renames_whole() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "first file\n", "a");
+        commit_file(path, "b.txt", "second file\n", "b");
+        git(path, &["mv", "a.txt", "c.txt"]);
+        git(path, &["mv", "b.txt", "d.txt"]);
+        std::fs::write(path.join("e.txt"), "new\n").unwrap();
+        git(path, &["add", "e.txt"]);
+
+        let repo = Repository::open(path).unwrap();
+        let (_, staged) = get_file_status_separated(&repo).unwrap();
+        let renamed: Vec<_> = staged
+            .iter()
+            .filter_map(|f| f.old_path.as_deref())
+            .collect();
+        assert_eq!(renamed, ["a.txt", "b.txt"]);
+
+        let paths: Vec<String> = staged.iter().map(|f| f.path.clone()).collect();
+        unstage_files(&repo, &paths).unwrap();
+        let (_, staged) = get_file_status_separated(&repo).unwrap();
+        assert!(staged.is_empty(), "{:?}", staged);
+    }
+
+    #[test]
+    fn test_unstage_directory_before_the_first_commit() {
+        let dir = init_repo();
+        let path = dir.path();
+        std::fs::create_dir(path.join("src")).unwrap();
+        std::fs::write(path.join("src/a.txt"), "a\n").unwrap();
+        std::fs::write(path.join("src/b.txt"), "b\n").unwrap();
+        std::fs::write(path.join("c.txt"), "c\n").unwrap();
+        git(path, &["add", "."]);
+
+        let repo = Repository::open(path).unwrap();
+        let mut unstaged = unstage_directory(&repo, "src").unwrap();
+        unstaged.sort();
+        assert_eq!(unstaged, ["src/a.txt", "src/b.txt"]);
+        let (_, staged) = get_file_status_separated(&repo).unwrap();
+        let staged: Vec<_> = staged.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(staged, ["c.txt"]);
+        assert_eq!(read(path, "src/a.txt"), "a\n");
+    }
+
+    #[test]
+    fn test_unstage_directory_takes_the_rename_source_along() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", "first file\n", "a");
+        std::fs::create_dir(path.join("src")).unwrap();
+        git(path, &["mv", "a.txt", "src/a.txt"]);
+
+        let repo = Repository::open(path).unwrap();
+        assert_eq!(unstage_directory(&repo, "src").unwrap(), ["src/a.txt"]);
+        let (_, staged) = get_file_status_separated(&repo).unwrap();
+        assert!(staged.is_empty(), "{:?}", staged);
+    }
+
+    #[test]
+    fn test_stage_and_unstage_one_hunk_of_several() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", &numbered_lines(&[]), "a");
+        let both = numbered_lines(&[(2, "two"), (18, "eighteen")]);
+        std::fs::write(path.join("a.txt"), &both).unwrap();
+
+        let repo = Repository::open(path).unwrap();
+        let diff = get_working_diff(&repo, "a.txt", false).unwrap();
+        assert_eq!(diff.hunks.len(), 2);
+        stage_hunk(&repo, "a.txt", hunk_data(&diff.hunks[1])).unwrap();
+        assert_eq!(
+            staged_content(&repo, "a.txt"),
+            numbered_lines(&[(18, "eighteen")])
+        );
+        let diff = get_working_diff(&repo, "a.txt", false).unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        assert_eq!(diff.hunks[0].old_start, 1);
+
+        git(path, &["add", "a.txt"]);
+        let repo = Repository::open(path).unwrap();
+        let diff = get_working_diff(&repo, "a.txt", true).unwrap();
+        assert_eq!(diff.hunks.len(), 2);
+        unstage_hunk(&repo, "a.txt", hunk_data(&diff.hunks[0])).unwrap();
+        assert_eq!(
+            staged_content(&repo, "a.txt"),
+            numbered_lines(&[(18, "eighteen")])
+        );
+        assert_eq!(read(path, "a.txt"), both);
+    }
+
+    #[test]
+    fn test_stage_and_unstage_a_hunk_without_a_final_newline() {
+        let dir = init_repo();
+        let path = dir.path();
+        let original = numbered_lines(&[]);
+        commit_file(path, "a.txt", original.trim_end(), "a");
+        let both = numbered_lines(&[(2, "two"), (20, "twenty")]);
+        std::fs::write(path.join("a.txt"), both.trim_end()).unwrap();
+
+        let repo = Repository::open(path).unwrap();
+        let diff = get_working_diff(&repo, "a.txt", false).unwrap();
+        assert_eq!(diff.hunks.len(), 2);
+        stage_hunk(&repo, "a.txt", hunk_data(&diff.hunks[1])).unwrap();
+        let last_only = numbered_lines(&[(20, "twenty")]);
+        assert_eq!(staged_content(&repo, "a.txt"), last_only.trim_end());
+
+        stage_hunk(&repo, "a.txt", hunk_data(&diff.hunks[0])).unwrap();
+        assert_eq!(staged_content(&repo, "a.txt"), both.trim_end());
+        let diff = get_working_diff(&repo, "a.txt", true).unwrap();
+        unstage_hunk(&repo, "a.txt", hunk_data(&diff.hunks[1])).unwrap();
+        let first_only = numbered_lines(&[(2, "two")]);
+        assert_eq!(staged_content(&repo, "a.txt"), first_only.trim_end());
+    }
+
+    #[test]
+    fn test_stage_a_hunk_of_a_renamed_file() {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.txt", &numbered_lines(&[]), "a");
+        std::fs::remove_file(path.join("a.txt")).unwrap();
+        let both = numbered_lines(&[(2, "two"), (18, "eighteen")]);
+        std::fs::write(path.join("b.txt"), &both).unwrap();
+
+        let repo = Repository::open(path).unwrap();
+        let line = |line_type: &str, content: &str| HunkLineData {
+            content: format!("{}\n", content),
+            line_type: line_type.to_string(),
+        };
+        let hunk = HunkData {
+            old_start: 1,
+            old_lines: 5,
+            new_start: 1,
+            new_lines: 5,
+            lines: vec![
+                line("context", "1"),
+                line("delete", "2"),
+                line("add", "two"),
+                line("context", "3"),
+                line("context", "4"),
+                line("context", "5"),
+            ],
+            old_path: Some("a.txt".to_string()),
+            new_path: Some("b.txt".to_string()),
+            old_mode: None,
+            new_mode: None,
+        };
+        let old_id = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("a.txt"), 0)
+            .unwrap()
+            .id
+            .to_string();
+        let preview = preview_hunk_patch(&repo, "b.txt", hunk.clone(), "stage").unwrap();
+        assert!(preview.can_apply, "{}", preview.check_output);
+        assert!(
+            preview.patch.contains("similarity index 9"),
+            "{}",
+            preview.patch
+        );
+        assert!(
+            preview.patch.contains(&format!("index {}..", &old_id[..7])),
+            "{}",
+            preview.patch
+        );
+
+        stage_hunk(&repo, "b.txt", hunk).unwrap();
+        let index = repo.index().unwrap();
+        assert!(index.get_path(Path::new("a.txt"), 0).is_none());
+        assert_eq!(
+            staged_content(&repo, "b.txt"),
+            numbered_lines(&[(2, "two")])
+        );
+    }
+
+    #[cfg(unix)] // the execute bit
+    #[test]
+    fn test_stage_and_unstage_a_hunk_with_a_mode_change() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "a.sh", &numbered_lines(&[]), "a");
+        let both = numbered_lines(&[(2, "two"), (18, "eighteen")]);
+        std::fs::write(path.join("a.sh"), &both).unwrap();
+        let permissions = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(path.join("a.sh"), permissions).unwrap();
+        let with_modes = |hunk: &DiffHunk| HunkData {
+            old_mode: Some(0o100644),
+            new_mode: Some(0o100755),
+            ..hunk_data(hunk)
+        };
+        let staged_mode = |repo: &Repository| {
+            let index = repo.index().unwrap();
+            index.get_path(Path::new("a.sh"), 0).unwrap().mode
+        };
+
+        let repo = Repository::open(path).unwrap();
+        let diff = get_working_diff(&repo, "a.sh", false).unwrap();
+        assert_eq!(diff.hunks.len(), 2);
+        stage_hunk(&repo, "a.sh", with_modes(&diff.hunks[1])).unwrap();
+        assert_eq!(staged_mode(&repo), 0o100755);
+        assert_eq!(
+            staged_content(&repo, "a.sh"),
+            numbered_lines(&[(18, "eighteen")])
+        );
+
+        let diff = get_working_diff(&repo, "a.sh", true).unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        unstage_hunk(&repo, "a.sh", with_modes(&diff.hunks[0])).unwrap();
+        assert_eq!(staged_mode(&repo), 0o100644);
+        assert_eq!(staged_content(&repo, "a.sh"), numbered_lines(&[]));
+    }
+
+    #[cfg(unix)] // the converter is a shell command
+    #[test]
+    fn test_hunks_of_a_textconv_diff_are_refused() {
+        let dir = init_repo();
+        let path = dir.path();
+        git(path, &["config", "diff.upper.textconv", "sed s/a/A/"]);
+        commit_file(path, ".gitattributes", "*.txt diff=upper\n", "attributes");
+        commit_file(path, "a.txt", "one\ntwo\n", "a");
+        std::fs::write(path.join("a.txt"), "one\nthree\n").unwrap();
+
+        let repo = Repository::open(path).unwrap();
+        let diff = get_working_diff(&repo, "a.txt", false).unwrap();
+        assert_eq!(diff.diff_driver.as_deref(), Some("upper"));
+        let hunk = hunk_data(&diff.hunks[0]);
+        let error = stage_hunk(&repo, "a.txt", hunk.clone()).unwrap_err();
+        assert!(error.contains("'upper' diff driver"), "{}", error);
+        assert!(discard_hunk(path.to_str().unwrap(), "a.txt", hunk.clone()).is_err());
+        let preview = preview_hunk_patch(&repo, "a.txt", hunk, "stage").unwrap();
+        assert!(!preview.can_apply);
+        assert_eq!(read(path, "a.txt"), "one\nthree\n");
+        git(path, &["diff", "--cached", "--quiet"]);
+    }
+
+    #[test]
+    fn test_hunks_of_a_converted_notebook_are_refused() {
+        let notebook = |source: &str| {
+            format!(
+                r#"{{"cells": [{{"cell_type": "code", "execution_count": 1, "metadata": {{}},
+"outputs": [], "source": "{}"}}], "metadata": {{}}, "nbformat": 4, "nbformat_minor": 5}}
+"#,
+                source
+            )
+        };
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "nb.ipynb", &notebook("print(1)"), "notebook");
+        std::fs::write(path.join("nb.ipynb"), notebook("print(2)")).unwrap();
+
+        let repo = Repository::open(path).unwrap();
+        let diff = get_working_diff(&repo, "nb.ipynb", false).unwrap();
+        assert_eq!(diff.diff_driver.as_deref(), Some("notebook"));
+        let error = stage_hunk(&repo, "nb.ipynb", hunk_data(&diff.hunks[0])).unwrap_err();
+        assert!(error.contains("'notebook' diff driver"), "{}", error);
+        assert_eq!(read(path, "nb.ipynb"), notebook("print(2)"));
+        git(path, &["diff", "--cached", "--quiet"]);
+
+        git(path, &["add", "nb.ipynb"]);
+        let repo = Repository::open(path).unwrap();
+        let staged = get_working_diff(&repo, "nb.ipynb", true).unwrap();
+        assert!(unstage_hunk(&repo, "nb.ipynb", hunk_data(&staged.hunks[0])).is_err());
+
+        // Without the converter the JSON itself is diffed, and its hunks apply
+        git(path, &["reset", "-q"]);
+        git(path, &["config", "forky.notebookDiff", "false"]);
+        let raw = get_working_diff(&repo, "nb.ipynb", false).unwrap();
+        assert!(raw.diff_driver.is_none());
+        stage_hunk(&repo, "nb.ipynb", hunk_data(&raw.hunks[0])).unwrap();
+        git(path, &["diff", "--quiet"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mode_change_of_a_filtered_file_is_told_from_an_edit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, ".gitattributes", "*.sh text eol=crlf\n", "attributes");
+        // Committed with LF, checked out with CRLF
+        commit_file(path, "run.sh", "echo hi\n", "script");
+        std::fs::remove_file(path.join("run.sh")).unwrap();
+        git(path, &["checkout", "--", "run.sh"]);
+        assert_eq!(read(path, "run.sh"), "echo hi\r\n");
+
+        let script = path.join("run.sh");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let repo = Repository::open(path).unwrap();
+        let (unstaged, _) = get_file_status_separated(&repo).unwrap();
+        assert_eq!(unstaged.len(), 1);
+        assert_eq!(unstaged[0].status, "mode_changed");
+
+        std::fs::write(&script, "echo bye\r\n").unwrap();
+        let (unstaged, _) = get_file_status_separated(&repo).unwrap();
+        assert_eq!(unstaged[0].status, "modified");
+    }
+}
@@ -0,0 +1,238 @@
+//! Scratch files Forky hands to git and to external tools.
+//!
+//! The interactive rebase writes its todo list and an editor script, and
+//! textconv drivers read their input from a file. These used to go straight
+//! into the system temp dir, named after the process id: a crash or an early
+//! return left them behind, and two instances could overwrite each other's
+//! files. They now live in a scratch directory in the app data dir, one
+//! subdirectory per instance, as `ScratchFile`s removed when dropped. Files
+//! left over by a crash are removed at startup and by `clean_scratch`.
+
+use crate::locks::RwLockExt;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+/// Directory (in the app data dir) holding the scratch files
+pub const SCRATCH_DIR: &str = "scratch";
+
+/// Age after which a scratch file is assumed to be left over: no operation
+/// keeps one that long, so removing it cannot break another instance
+pub const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Smallest age `clean` accepts: a scratch file younger than this may still
+/// be in use by another instance
+const MIN_STALE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Prefixes of the files older versions wrote to the system temp dir
+const LEGACY_TEMP_PREFIXES: &[&str] = &[
+    "forky_textconv_",
+    "forky_rebase_todo_",
+    "forky_rebase_editor_",
+];
+
+/// Scratch root, set at startup; the system temp dir is used until then
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScratchCleanup {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+}
+
+/// Use `root` for the scratch files from now on
+pub fn set_root(root: PathBuf) {
+    *ROOT.write_recover("scratch root") = Some(root);
+}
+
+fn root() -> PathBuf {
+    ROOT.read_recover("scratch root")
+        .clone()
+        .unwrap_or_else(|| std::env::temp_dir().join("forky-scratch"))
+}
+
+/// This instance's directory in the scratch root
+fn instance_dir() -> PathBuf {
+    root().join(std::process::id().to_string())
+}
+
+/// A file in this instance's scratch directory, removed when dropped
+#[derive(Debug)]
+pub struct ScratchFile {
+    path: PathBuf,
+}
+
+impl ScratchFile {
+    /// Write `content` to a new scratch file named after `name`, keeping
+    /// `extension` (e.g. ".cmd") since some tools dispatch on it
+    pub fn create(name: &str, extension: &str, content: &[u8]) -> Result<Self, String> {
+        let dir = instance_dir();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(format!(
+            "{}_{}{}",
+            name,
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            extension
+        ));
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(ScratchFile { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn is_stale(metadata: &std::fs::Metadata, max_age: Duration) -> bool {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= max_age)
+}
+
+fn remove_stale_file(
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    max_age: Duration,
+    cleanup: &mut ScratchCleanup,
+) {
+    if is_stale(metadata, max_age) && std::fs::remove_file(path).is_ok() {
+        cleanup.removed_files += 1;
+        cleanup.freed_bytes += metadata.len();
+    }
+}
+
+/// Remove the files under `dir` older than `max_age`, then the directories
+/// left empty. Nothing under `keep` is touched.
+fn clean_dir(dir: &Path, max_age: Duration, keep: &Path, cleanup: &mut ScratchCleanup) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path == keep {
+            continue;
+        }
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            clean_dir(&path, max_age, keep, cleanup);
+            // Fails, as wanted, while the directory still holds files
+            let _ = std::fs::remove_dir(&path);
+        } else {
+            remove_stale_file(&path, &metadata, max_age, cleanup);
+        }
+    }
+}
+
+/// Remove the files older versions left in `temp_dir`, older than `max_age`.
+/// Only files directly in it with one of the legacy names are looked at.
+fn clean_legacy(temp_dir: &Path, max_age: Duration, cleanup: &mut ScratchCleanup) {
+    let Ok(entries) = std::fs::read_dir(temp_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !LEGACY_TEMP_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            continue;
+        }
+        let path = entry.path();
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            if metadata.is_file() {
+                remove_stale_file(&path, &metadata, max_age, cleanup);
+            }
+        }
+    }
+}
+
+/// Remove the scratch files older than `max_age` (at least MIN_STALE_AFTER),
+/// left over by crashed or closed instances, and those older versions left in
+/// the system temp dir. This instance's own directory is left alone.
+pub fn clean(max_age: Duration) -> ScratchCleanup {
+    let mut cleanup = ScratchCleanup::default();
+    let max_age = max_age.max(MIN_STALE_AFTER);
+    clean_dir(&root(), max_age, &instance_dir(), &mut cleanup);
+    clean_legacy(&std::env::temp_dir(), max_age, &mut cleanup);
+    cleanup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_file_is_removed_when_dropped() {
+        let first = ScratchFile::create("test_scratch", ".txt", b"todo").unwrap();
+        let second = ScratchFile::create("test_scratch", ".txt", b"todo").unwrap();
+        assert_ne!(first.path(), second.path());
+        assert_eq!(std::fs::read(first.path()).unwrap(), b"todo");
+        assert!(first.path().to_string_lossy().ends_with(".txt"));
+
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().exists());
+    }
+
+    #[test]
+    fn test_clean_dir_keeps_recent_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old_instance = dir.path().join("1");
+        std::fs::create_dir(&old_instance).unwrap();
+        std::fs::write(old_instance.join("todo_0"), "pick abc").unwrap();
+        std::fs::write(dir.path().join("recent"), "x").unwrap();
+
+        let mut cleanup = ScratchCleanup::default();
+        clean_dir(dir.path(), STALE_AFTER, Path::new(""), &mut cleanup);
+        assert_eq!(cleanup, ScratchCleanup::default());
+        assert!(old_instance.join("todo_0").exists());
+
+        clean_dir(dir.path(), Duration::ZERO, Path::new(""), &mut cleanup);
+        assert_eq!(cleanup.removed_files, 2);
+        assert_eq!(cleanup.freed_bytes, 9);
+        assert!(!old_instance.exists());
+    }
+    #[test]
+    fn test_clean_skips_the_kept_directory_and_unknown_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let keep = dir.path().join("2");
+        std::fs::create_dir(&keep).unwrap();
+        std::fs::write(keep.join("todo_0"), "pick abc").unwrap();
+
+        let mut cleanup = ScratchCleanup::default();
+        clean_dir(dir.path(), Duration::ZERO, &keep, &mut cleanup);
+        assert_eq!(cleanup, ScratchCleanup::default());
+        assert!(keep.join("todo_0").exists());
+
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("forky_rebase_todo_42"), "pick").unwrap();
+        std::fs::write(temp.path().join("forky_notes"), "keep").unwrap();
+        let legacy_dir = temp.path().join("forky_textconv_dir");
+        std::fs::create_dir(&legacy_dir).unwrap();
+        std::fs::write(legacy_dir.join("file"), "keep").unwrap();
+
+        clean_legacy(temp.path(), Duration::ZERO, &mut cleanup);
+        assert_eq!(cleanup.removed_files, 1);
+        assert!(!temp.path().join("forky_rebase_todo_42").exists());
+        assert!(temp.path().join("forky_notes").exists());
+        assert!(legacy_dir.join("file").exists());
+    }
+}
@@ -0,0 +1,636 @@
+//! Stacked branches.
+//!
+//! A stack is a chain of branches each built on the previous one, e.g. one
+//! branch per pull request of a larger change. A branch's parent is the local
+//! branch it tracks (`git branch --track child parent`), which stays known when
+//! the parent is rewritten, otherwise the nearest local branch it contains.
+//! The repository's main line is the base of the stacks and has no parent.
+//!
+//! When a parent gets new commits (or is amended or rebased), restacking
+//! rebases each branch above it onto its parent's new tip, parents first. A
+//! rebase that stops on conflicts saves the progress in the git dir, so the
+//! restack can be continued once they are resolved, or aborted, which puts the
+//! branches already restacked back where they were.
+
+use crate::git::environment::git_command;
+use crate::git::repository::{
+    create_error_result, create_success_result, find_default_branch, open_repository,
+    unmerged_files, GitOperationResult,
+};
+use crate::json_store;
+use git2::{BranchType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// File (in the git dir) holding the state of an interrupted restack
+pub const RESTACK_STATE_FILE: &str = "forky-restack.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StackBranch {
+    pub name: String,
+    pub parent: Option<String>,    // None for the base of the stack
+    pub linked_by: Option<String>, // "upstream" or "merge_base"
+    pub commit: String,
+    pub ahead: usize,        // commits not in the parent
+    pub needs_restack: bool, // the parent has commits the branch is not based on
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestackStep {
+    pub branch: String,
+    pub onto: String,         // parent branch it is rebased onto
+    pub base: String,         // commit the branch was built on, excluded from the rebase
+    pub original_tip: String, // commit of the branch before the restack
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestackState {
+    pub branch: String,               // branch whose stack is restacked
+    pub steps: Vec<RestackStep>,      // parents first
+    pub done: usize,                  // steps finished so far
+    pub start_branch: Option<String>, // checked out when the restack started
+    pub messages: Vec<String>,        // steps done so far
+}
+
+fn state_path(repo: &Repository) -> PathBuf {
+    repo.path().join(RESTACK_STATE_FILE)
+}
+
+/// The restack waiting to be continued or aborted, if any
+pub fn get_restack_state(repo: &Repository) -> Option<RestackState> {
+    json_store::read(&state_path(repo)).ok().flatten()
+}
+
+fn save_state(repo: &Repository, state: &RestackState) -> Result<(), String> {
+    json_store::write(&state_path(repo), state)
+}
+
+fn clear_state(repo: &Repository) {
+    let _ = std::fs::remove_file(state_path(repo));
+}
+
+fn branch_tip(repo: &Repository, branch: &str) -> Option<Oid> {
+    repo.find_branch(branch, BranchType::Local)
+        .ok()?
+        .get()
+        .target()
+}
+
+fn run_git(repo_path: &str, args: &[&str]) -> Result<std::process::Output, String> {
+    git_command()
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .map_err(|e| format!("Failed to execute git {}: {}", args[0], e))
+}
+
+/// Whether `commit` contains `ancestor`
+fn contains(repo: &Repository, commit: Oid, ancestor: Oid) -> bool {
+    commit == ancestor || repo.graph_descendant_of(commit, ancestor).unwrap_or(false)
+}
+
+/// The local branches and their tips, by name
+fn local_branches(repo: &Repository) -> Result<Vec<(String, Oid)>, String> {
+    let mut branches: Vec<(String, Oid)> = repo
+        .branches(Some(BranchType::Local))
+        .map_err(|e| e.message().to_string())?
+        .flatten()
+        .filter_map(|(branch, _)| {
+            let name = branch.name().ok().flatten()?.to_string();
+            Some((name, branch.get().target()?))
+        })
+        .collect();
+    branches.sort();
+    Ok(branches)
+}
+
+/// The local branch `name` tracks, if it tracks one
+fn upstream_parent(repo: &Repository, name: &str) -> Option<String> {
+    let config = repo.config().ok()?;
+    if config.get_string(&format!("branch.{}.remote", name)).ok()? != "." {
+        return None;
+    }
+    let merge = config.get_string(&format!("branch.{}.merge", name)).ok()?;
+    let parent = merge.strip_prefix("refs/heads/")?;
+    (parent != name && repo.find_branch(parent, BranchType::Local).is_ok())
+        .then(|| parent.to_string())
+}
+
+/// The local branch whose tip is the nearest ancestor of `tip`. Branches at
+/// the same commit are not parents of each other.
+fn nearest_contained_branch(
+    repo: &Repository,
+    name: &str,
+    tip: Oid,
+    branches: &[(String, Oid)],
+) -> Option<String> {
+    branches
+        .iter()
+        .filter(|(other, other_tip)| {
+            other != name && *other_tip != tip && contains(repo, tip, *other_tip)
+        })
+        .min_by_key(|(_, other_tip)| {
+            repo.graph_ahead_behind(tip, *other_tip)
+                .map(|(ahead, _)| ahead)
+                .unwrap_or(usize::MAX)
+        })
+        .map(|(other, _)| other.clone())
+}
+
+/// Parent of `name` and how it was found; the main line has none
+fn parent_of(
+    repo: &Repository,
+    name: &str,
+    tip: Oid,
+    main_line: Option<&str>,
+    branches: &[(String, Oid)],
+) -> Option<(String, &'static str)> {
+    if let Some(parent) = upstream_parent(repo, name) {
+        return Some((parent, "upstream"));
+    }
+    if main_line == Some(name) {
+        return None;
+    }
+    nearest_contained_branch(repo, name, tip, branches).map(|parent| (parent, "merge_base"))
+}
+
+/// The stack of `branch`: its base first, then each branch down to `branch`,
+/// then every branch built on it, each after its parent. Branches built on
+/// its ancestors but not on it are left out.
+pub fn get_branch_stack(repo: &Repository, branch: &str) -> Result<Vec<StackBranch>, String> {
+    let branches = local_branches(repo)?;
+    let index_of: HashMap<&str, usize> = branches
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.as_str(), i))
+        .collect();
+    let Some(&start) = index_of.get(branch) else {
+        return Err(format!("Branch '{}' not found", branch));
+    };
+    // "origin/main" is the main line "main"
+    let main_line = find_default_branch(repo).map(|name| {
+        let is_local = index_of.contains_key(name.as_str());
+        match name.split_once('/') {
+            Some((_, local)) if !is_local => local.to_string(),
+            _ => name,
+        }
+    });
+
+    // The parent of each branch, computed once
+    let parents: Vec<Option<(String, &'static str)>> = branches
+        .iter()
+        .map(|(name, tip)| parent_of(repo, name, *tip, main_line.as_deref(), &branches))
+        .collect();
+    let parent_index: Vec<Option<usize>> = parents
+        .iter()
+        .map(|parent| {
+            parent
+                .as_ref()
+                .and_then(|(parent, _)| index_of.get(parent.as_str()).copied())
+        })
+        .collect();
+    let mut children = vec![Vec::new(); branches.len()];
+    for (child, parent) in parent_index.iter().enumerate() {
+        if let Some(parent) = parent {
+            children[*parent].push(child);
+        }
+    }
+
+    // Up to the base, stopping at a cycle of upstreams
+    let mut order = vec![start];
+    let mut seen = HashSet::from([start]);
+    while let Some(parent) = parent_index[order[order.len() - 1]] {
+        if !seen.insert(parent) {
+            break;
+        }
+        order.push(parent);
+    }
+    order.reverse();
+    let base = order[0];
+
+    // Then down to every branch built on `branch`
+    let mut next = order.len() - 1;
+    while next < order.len() {
+        for &child in &children[order[next]] {
+            if seen.insert(child) {
+                order.push(child);
+            }
+        }
+        next += 1;
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|i| {
+            let (name, tip) = &branches[i];
+            let parent = if i == base { None } else { parents[i].clone() };
+            let parent_tip = parent
+                .as_ref()
+                .and_then(|(parent, _)| branch_tip(repo, parent));
+            StackBranch {
+                name: name.clone(),
+                commit: tip.to_string(),
+                ahead: parent_tip
+                    .and_then(|parent_tip| repo.graph_ahead_behind(*tip, parent_tip).ok())
+                    .map(|(ahead, _)| ahead)
+                    .unwrap_or(0),
+                needs_restack: parent_tip
+                    .is_some_and(|parent_tip| !contains(repo, *tip, parent_tip)),
+                linked_by: parent.as_ref().map(|(_, linked_by)| linked_by.to_string()),
+                parent: parent.map(|(parent, _)| parent),
+            }
+        })
+        .collect())
+}
+
+/// Commit `branch` was built on in `parent`: where it forked from the parent
+/// before the parent was rewritten, as the parent's reflog tells, otherwise
+/// their merge base
+fn fork_point(repo_path: &str, parent: &str, branch: &str) -> Result<String, String> {
+    for args in [
+        ["merge-base", "--fork-point", parent, branch].as_slice(),
+        ["merge-base", parent, branch].as_slice(),
+    ] {
+        let output = run_git(repo_path, args)?;
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+        }
+    }
+    Err(format!(
+        "'{}' and '{}' have no commit in common",
+        branch, parent
+    ))
+}
+
+/// Rebase every branch of the stack `branch` belongs to onto its parent,
+/// parents first
+pub fn restack_branches(repo_path: &str, branch: &str) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    if let Some(state) = get_restack_state(&repo) {
+        return Ok(create_error_result(
+            &format!(
+                "Restacking '{}' was interrupted; continue or abort it first",
+                state.branch
+            ),
+            "",
+        ));
+    }
+
+    let mut steps = Vec::new();
+    for stacked in get_branch_stack(&repo, branch)? {
+        let Some(parent) = stacked.parent else {
+            continue;
+        };
+        steps.push(RestackStep {
+            base: fork_point(repo_path, &parent, &stacked.name)?,
+            branch: stacked.name,
+            onto: parent,
+            original_tip: stacked.commit,
+        });
+    }
+    if steps.is_empty() {
+        return Ok(create_success_result(format!(
+            "No branch is stacked on '{}'",
+            branch
+        )));
+    }
+
+    let state = RestackState {
+        branch: branch.to_string(),
+        steps,
+        done: 0,
+        start_branch: repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| head.shorthand().map(String::from)),
+        messages: Vec::new(),
+    };
+    run_restack(repo_path, &repo, state)
+}
+
+/// Run the steps of `state` that are left. A rebase that stops saves the
+/// state for restack_continue and restack_abort.
+fn run_restack(
+    repo_path: &str,
+    repo: &Repository,
+    mut state: RestackState,
+) -> Result<GitOperationResult, String> {
+    while state.done < state.steps.len() {
+        let step = state.steps[state.done].clone();
+        let (Some(tip), Some(onto_tip)) =
+            (branch_tip(repo, &step.branch), branch_tip(repo, &step.onto))
+        else {
+            // Deleted meanwhile
+            state.messages.push(format!("Skipped '{}'", step.branch));
+            state.done += 1;
+            continue;
+        };
+        if contains(repo, tip, onto_tip) {
+            state.done += 1;
+            continue;
+        }
+
+        let output = run_git(
+            repo_path,
+            &["rebase", "--onto", &step.onto, &step.base, &step.branch],
+        )?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+            if matches!(
+                repo.state(),
+                git2::RepositoryState::RebaseMerge | git2::RepositoryState::RebaseInteractive
+            ) {
+                save_state(repo, &state)?;
+                return Ok(GitOperationResult {
+                    success: false,
+                    message: format!(
+                        "Conflicts rebasing '{}' onto '{}' (step {} of {}). Resolve them, then continue the restack (or abort it).",
+                        step.branch,
+                        step.onto,
+                        state.done + 1,
+                        state.steps.len()
+                    ),
+                    requires_ssh_verification: None,
+                    requires_credential: None,
+                    error_type: Some("rebase_conflicts".to_string()),
+                    conflicting_files: Some(unmerged_files(repo_path)),
+                });
+            }
+
+            // Nothing to undo yet when the first rebase fails
+            if state.done > 0 {
+                save_state(repo, &state)?;
+            }
+            return Ok(create_error_result(
+                &format!("Failed to rebase '{}': {}", step.branch, stderr),
+                &stdout,
+            ));
+        }
+
+        state
+            .messages
+            .push(format!("Rebased '{}' onto '{}'", step.branch, step.onto));
+        state.done += 1;
+    }
+
+    if let Some(start_branch) = &state.start_branch {
+        let _ = run_git(repo_path, &["checkout", start_branch]);
+    }
+    clear_state(repo);
+    if state.messages.is_empty() {
+        return Ok(create_success_result(format!(
+            "The stack of '{}' is up to date",
+            state.branch
+        )));
+    }
+    Ok(create_success_result(state.messages.join(". ")))
+}
+
+/// Resume an interrupted restack: continue the rebase whose conflicts were
+/// resolved (or accept one the user finished), then run the steps left
+pub fn restack_continue(repo_path: &str) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    let Some(mut state) = get_restack_state(&repo) else {
+        return Ok(create_error_result("No restack to continue", ""));
+    };
+    // The state file is ours, but it may have been edited or truncated
+    let Some(step) = state.steps.get(state.done).cloned() else {
+        return Err("The saved restack state is invalid; abort it instead".to_string());
+    };
+
+    if matches!(
+        repo.state(),
+        git2::RepositoryState::RebaseMerge | git2::RepositoryState::RebaseInteractive
+    ) {
+        let output = git_command()
+            .args(["rebase", "--continue"])
+            .current_dir(repo_path)
+            .env("GIT_EDITOR", "true")
+            .output()
+            .map_err(|e| format!("Failed to execute git rebase --continue: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            if repo.state() != git2::RepositoryState::Clean {
+                return Ok(GitOperationResult {
+                    success: false,
+                    message: format!(
+                        "There are still conflicts rebasing '{}' onto '{}'; resolve and stage them first",
+                        step.branch, step.onto
+                    ),
+                    requires_ssh_verification: None,
+                    requires_credential: None,
+                    error_type: Some("rebase_conflicts".to_string()),
+                    conflicting_files: Some(unmerged_files(repo_path)),
+                });
+            }
+            return Ok(create_error_result(&stderr, &stdout));
+        }
+    }
+    // A rebase that did not complete is retried
+    if let (Some(tip), Some(onto_tip)) = (
+        branch_tip(&repo, &step.branch),
+        branch_tip(&repo, &step.onto),
+    ) {
+        if contains(&repo, tip, onto_tip) {
+            state
+                .messages
+                .push(format!("Rebased '{}' onto '{}'", step.branch, step.onto));
+            state.done += 1;
+        }
+    }
+
+    run_restack(repo_path, &repo, state)
+}
+
+/// Give up an interrupted restack: abort the rebase in progress, put the
+/// branches already restacked back on their previous commits and check out the
+/// branch the restack started from
+pub fn restack_abort(repo_path: &str) -> Result<GitOperationResult, String> {
+    let repo = open_repository(repo_path)?;
+    let Some(state) = get_restack_state(&repo) else {
+        return Ok(create_error_result("No restack to abort", ""));
+    };
+
+    if repo.state() != git2::RepositoryState::Clean {
+        let output = run_git(repo_path, &["rebase", "--abort"])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            return Ok(create_error_result(&stderr, &stdout));
+        }
+    }
+
+    let checked_out = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(String::from));
+    let mut restored = Vec::new();
+    for step in state.steps.iter().take(state.done) {
+        if branch_tip(&repo, &step.branch)
+            .map(|oid| oid.to_string())
+            .as_ref()
+            == Some(&step.original_tip)
+        {
+            continue;
+        }
+        if checked_out.as_deref() == Some(step.branch.as_str()) {
+            // Keeps local changes, and refuses rather than lose them
+            let output = run_git(repo_path, &["reset", "--keep", &step.original_tip])?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                return Ok(create_error_result(
+                    &format!("Failed to reset '{}': {}", step.branch, stderr),
+                    "",
+                ));
+            }
+        } else {
+            let oid = Oid::from_str(&step.original_tip).map_err(|e| e.message().to_string())?;
+            repo.reference(
+                &format!("refs/heads/{}", step.branch),
+                oid,
+                true,
+                "restack: abort",
+            )
+            .map_err(|e| format!("Failed to reset '{}': {}", step.branch, e.message()))?;
+        }
+        restored.push(step.branch.clone());
+    }
+
+    if let Some(start_branch) = &state.start_branch {
+        let _ = run_git(repo_path, &["checkout", start_branch]);
+    }
+    clear_state(&repo);
+
+    let mut message = format!("Aborted restacking '{}'", state.branch);
+    if !restored.is_empty() {
+        message.push_str(&format!(
+            "; {} put back on the previous commit",
+            restored.join(", ")
+        ));
+    }
+    Ok(create_success_result(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_file, git, init_repo};
+
+    /// main <- first <- second, with `first` amended after `second` was
+    /// created on it, and `second` tracking `first`
+    fn amended_stack() -> tempfile::TempDir {
+        let dir = init_repo();
+        let path = dir.path();
+        commit_file(path, "base.txt", "base\n", "base.txt");
+        git(path, &["checkout", "-q", "-b", "first"]);
+        commit_file(path, "first.txt", "first\n", "first.txt");
+        git(
+            path,
+            &["checkout", "-q", "-b", "second", "--track", "first"],
+        );
+        commit_file(path, "second.txt", "second\n", "second.txt");
+        git(path, &["checkout", "-q", "first"]);
+        std::fs::write(path.join("first.txt"), "first, amended\n").unwrap();
+        git(path, &["commit", "-qa", "--amend", "-m", "first"]);
+        dir
+    }
+
+    #[test]
+    fn test_stack_follows_upstreams_and_ancestry() {
+        let dir = amended_stack();
+        let repo = Repository::open(dir.path()).unwrap();
+
+        let stack = get_branch_stack(&repo, "second").unwrap();
+        let names: Vec<&str> = stack.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["main", "first", "second"]);
+        assert_eq!(stack[1].linked_by.as_deref(), Some("merge_base"));
+        assert_eq!(stack[2].linked_by.as_deref(), Some("upstream"));
+        assert!(!stack[1].needs_restack);
+        assert!(stack[2].needs_restack);
+        assert_eq!(stack[2].ahead, 2); // the old commit of `first` too
+    }
+
+    #[test]
+    fn test_stack_leaves_out_branches_beside_the_branch() {
+        let dir = amended_stack();
+        let path = dir.path();
+        git(
+            path,
+            &["checkout", "-q", "-b", "beside", "--track", "first"],
+        );
+        commit_file(path, "beside.txt", "beside\n", "beside.txt");
+        let repo = Repository::open(path).unwrap();
+
+        let names = |branch: &str| -> Vec<String> {
+            get_branch_stack(&repo, branch)
+                .unwrap()
+                .into_iter()
+                .map(|b| b.name)
+                .collect()
+        };
+        assert_eq!(names("second"), ["main", "first", "second"]);
+        assert_eq!(names("beside"), ["main", "first", "beside"]);
+        assert_eq!(names("first"), ["main", "first", "beside", "second"]);
+    }
+
+    #[test]
+    fn test_restack_rebases_onto_the_rewritten_parent() {
+        let dir = amended_stack();
+        let path = dir.path().to_str().unwrap();
+        let repo = Repository::open(path).unwrap();
+
+        let result = restack_branches(path, "first").unwrap();
+        assert!(result.success, "{}", result.message);
+        let second = branch_tip(&repo, "second").unwrap();
+        let first = branch_tip(&repo, "first").unwrap();
+        assert_eq!(
+            repo.find_commit(second).unwrap().parent_id(0).unwrap(),
+            first
+        );
+        assert_eq!(repo.head().unwrap().shorthand(), Some("first"));
+        assert!(get_branch_stack(&repo, "second")
+            .unwrap()
+            .iter()
+            .all(|branch| !branch.needs_restack));
+    }
+
+    #[test]
+    fn test_restack_conflict_continue_and_abort() {
+        let dir = amended_stack();
+        let path = dir.path().to_str().unwrap();
+        let repo = Repository::open(path).unwrap();
+        // `second` now also edits first.txt, which the amend changed
+        git(dir.path(), &["checkout", "-q", "second"]);
+        commit_file(dir.path(), "first.txt", "first, from second\n", "first.txt");
+        let second_before = branch_tip(&repo, "second").unwrap();
+
+        let result = restack_branches(path, "second").unwrap();
+        assert_eq!(result.error_type.as_deref(), Some("rebase_conflicts"));
+        assert_eq!(
+            result.conflicting_files,
+            Some(vec!["first.txt".to_string()])
+        );
+        assert_eq!(get_restack_state(&repo).unwrap().done, 1); // `first` needed nothing
+        assert!(!restack_continue(path).unwrap().success);
+
+        let result = restack_abort(path).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(branch_tip(&repo, "second").unwrap(), second_before);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert!(get_restack_state(&repo).is_none());
+
+        restack_branches(path, "second").unwrap();
+        std::fs::write(dir.path().join("first.txt"), "resolved\n").unwrap();
+        git(dir.path(), &["add", "first.txt"]);
+        let result = restack_continue(path).unwrap();
+        assert!(result.success, "{}", result.message);
+        assert!(get_restack_state(&repo).is_none());
+        assert_eq!(repo.head().unwrap().shorthand(), Some("second"));
+    }
+}
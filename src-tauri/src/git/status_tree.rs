@@ -0,0 +1,192 @@
+//! File status as a directory tree.
+//!
+//! With thousands of changed files the changes list is only usable with
+//! collapsible folders, and grouping the paths and counting what changed
+//! under each folder is cheaper here than in the frontend on every refresh.
+//! Each side of the status (unstaged and staged) becomes a tree of
+//! directories holding their files, with counts covering every file below.
+
+use crate::git::repository::{self, FileStatus};
+use git2::Repository;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatusCounts {
+    pub total: usize,
+    pub added: usize,    // new and untracked files
+    pub modified: usize, // content or mode changes
+    pub deleted: usize,
+    pub renamed: usize,
+    pub conflicted: usize,
+}
+
+impl StatusCounts {
+    fn add(&mut self, status: &str) {
+        self.total += 1;
+        match status {
+            "new" | "untracked" => self.added += 1,
+            "deleted" => self.deleted += 1,
+            "renamed" | "copied" => self.renamed += 1,
+            "conflicted" => self.conflicted += 1,
+            _ => self.modified += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &StatusCounts) {
+        self.total += other.total;
+        self.added += other.added;
+        self.modified += other.modified;
+        self.deleted += other.deleted;
+        self.renamed += other.renamed;
+        self.conflicted += other.conflicted;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatusTreeNode {
+    pub name: String, // "" for the root; "src/git" for compacted directories
+    pub path: String, // from the repository root, "" for the root
+    pub directories: Vec<StatusTreeNode>, // by name
+    pub files: Vec<FileStatus>, // directly in this directory, by path
+    pub counts: StatusCounts, // every file below, subdirectories included
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStatusTree {
+    pub unstaged: StatusTreeNode,
+    pub staged: StatusTreeNode,
+}
+
+#[derive(Default)]
+struct Builder {
+    directories: BTreeMap<String, Builder>,
+    files: Vec<FileStatus>,
+}
+
+impl Builder {
+    fn insert(&mut self, file: FileStatus) {
+        let mut node = self;
+        let mut components: Vec<&str> = file.path.split('/').collect();
+        components.pop();
+        for component in components {
+            node = node.directories.entry(component.to_string()).or_default();
+        }
+        node.files.push(file);
+    }
+
+    fn build(self, name: String, path: String, compact: bool) -> StatusTreeNode {
+        // A directory holding nothing but one directory is shown as one level
+        if compact && !path.is_empty() && self.files.is_empty() && self.directories.len() == 1 {
+            let (child_name, child) = self.directories.into_iter().next().unwrap_or_default();
+            return child.build(
+                format!("{}/{}", name, child_name),
+                format!("{}/{}", path, child_name),
+                compact,
+            );
+        }
+
+        let mut counts = StatusCounts::default();
+        let directories: Vec<StatusTreeNode> = self
+            .directories
+            .into_iter()
+            .map(|(child_name, child)| {
+                let child_path = if path.is_empty() {
+                    child_name.clone()
+                } else {
+                    format!("{}/{}", path, child_name)
+                };
+                let child = child.build(child_name, child_path, compact);
+                counts.merge(&child.counts);
+                child
+            })
+            .collect();
+        let mut files = self.files;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        for file in &files {
+            counts.add(&file.status);
+        }
+
+        StatusTreeNode {
+            name,
+            path,
+            directories,
+            files,
+            counts,
+        }
+    }
+}
+
+/// Tree of `files` by directory. With `compact`, chains of directories
+/// holding a single directory each are merged into one node.
+pub fn build_tree(files: Vec<FileStatus>, compact: bool) -> StatusTreeNode {
+    let mut root = Builder::default();
+    for file in files {
+        root.insert(file);
+    }
+    root.build(String::new(), String::new(), compact)
+}
+
+/// The unstaged and staged files of the repository, each as a tree
+pub fn get_file_status_tree(repo: &Repository, compact: bool) -> Result<FileStatusTree, String> {
+    let (unstaged, staged) = repository::get_file_status_separated(repo)?;
+    Ok(FileStatusTree {
+        unstaged: build_tree(unstaged, compact),
+        staged: build_tree(staged, compact),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, status: &str) -> FileStatus {
+        FileStatus {
+            path: path.to_string(),
+            status: status.to_string(),
+            staged: false,
+            conflict: None,
+            old_path: None,
+        }
+    }
+
+    #[test]
+    fn test_tree_counts_every_file_below() {
+        let tree = build_tree(
+            vec![
+                file("src/git/repository.rs", "modified"),
+                file("src/git/stack.rs", "untracked"),
+                file("src/lib.rs", "deleted"),
+                file("README.md", "conflicted"),
+            ],
+            false,
+        );
+        assert_eq!(tree.counts.total, 4);
+        assert_eq!(tree.files.len(), 1);
+        let src = &tree.directories[0];
+        assert_eq!((src.name.as_str(), src.path.as_str()), ("src", "src"));
+        assert_eq!(src.counts.total, 3);
+        assert_eq!(src.counts.deleted, 1);
+        let git = &src.directories[0];
+        assert_eq!(git.path, "src/git");
+        assert_eq!((git.counts.added, git.counts.modified), (1, 1));
+    }
+
+    #[test]
+    fn test_compact_merges_single_directory_chains() {
+        let tree = build_tree(
+            vec![
+                file("src/main/java/App.java", "modified"),
+                file("src/main/java/util/Io.java", "new"),
+            ],
+            true,
+        );
+        assert_eq!(tree.directories.len(), 1);
+        let java = &tree.directories[0];
+        assert_eq!(java.name, "src/main/java");
+        assert_eq!(java.path, "src/main/java");
+        assert_eq!(java.files.len(), 1);
+        assert_eq!(java.directories[0].path, "src/main/java/util");
+        assert_eq!(java.counts.total, 2);
+    }
+}
@@ -0,0 +1,51 @@
+//! Helpers shared by the tests that build repositories with the git CLI.
+
+use crate::git::environment::git_command;
+use git2::{Oid, Repository, Signature};
+use std::path::Path;
+
+/// Run git in `dir`, failing the test when it fails
+pub fn git(dir: &Path, args: &[&str]) {
+    let output = git_command().args(args).current_dir(dir).output().unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?}: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// An empty repository on branch main with a committer configured
+pub fn init_repo() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q", "-b", "main"]);
+    git(dir.path(), &["config", "user.name", "Test"]);
+    git(dir.path(), &["config", "user.email", "test@example.com"]);
+    dir
+}
+
+/// Write `content` to `file` and commit it with `message`
+pub fn commit_file(dir: &Path, file: &str, content: &str, message: &str) {
+    std::fs::write(dir.join(file), content).unwrap();
+    git(dir, &["add", file]);
+    git(dir, &["commit", "-qm", message]);
+}
+
+/// Commit the index of `repo` on HEAD with `message`, as the first commit
+/// when HEAD is unborn
+pub fn commit_index(repo: &Repository, message: &str) -> Oid {
+    let signature = Signature::now("Test", "test@example.com").unwrap();
+    let tree_id = repo.index().unwrap().write_tree().unwrap();
+    let tree = repo.find_tree(tree_id).unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap()
+}
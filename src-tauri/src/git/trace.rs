@@ -0,0 +1,195 @@
+//! Opt-in performance trace of the backend commands.
+//!
+//! When enabled (with `set_performance_trace`, or with `FORKY_TRACE=1` in the
+//! environment), every command run through `run_blocking` is timed: each run
+//! is written to the log and added to per-command totals, which
+//! `get_performance_report` returns. It helps tell which operations (status,
+//! diff, history) are slow on a given repository. Disabled, it costs one
+//! atomic load per command.
+
+use crate::locks::LockExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Environment variable enabling the trace from startup
+pub const TRACE_ENV_VAR: &str = "FORKY_TRACE";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+struct Totals {
+    calls: u64,
+    failures: u64,
+    total: Duration,
+    max: Duration,
+    last: Duration,
+}
+
+struct Trace {
+    started_at: Option<i64>, // unix timestamp the current totals start from
+    commands: Option<HashMap<String, Totals>>,
+}
+
+static TRACE: Mutex<Trace> = Mutex::new(Trace {
+    started_at: None,
+    commands: None,
+});
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandTiming {
+    pub command: String,
+    pub calls: u64,
+    pub failures: u64,
+    pub total_ms: f64,
+    pub average_ms: f64,
+    pub max_ms: f64,
+    pub last_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceReport {
+    pub enabled: bool,
+    pub started_at: Option<i64>,
+    pub commands: Vec<CommandTiming>, // most total time first
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Turn the trace on or off; turning it on starts new totals
+pub fn set_enabled(enabled: bool) {
+    if enabled && !is_enabled() {
+        reset();
+    }
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Enable the trace when `FORKY_TRACE` is set to anything but "0"; called once
+/// at startup
+pub fn init_from_env() {
+    if std::env::var(TRACE_ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0") {
+        set_enabled(true);
+    }
+}
+
+/// Forget the recorded timings
+pub fn reset() {
+    let mut trace = TRACE.lock_recover("performance trace");
+    trace.started_at = Some(chrono::Utc::now().timestamp());
+    trace.commands = Some(HashMap::new());
+}
+
+/// Name of the function a task closure was written in, e.g. "get_commits" for
+/// the closure a `get_commits` command hands to `run_blocking`
+pub fn task_name<F>() -> &'static str {
+    let name = std::any::type_name::<F>();
+    name.rsplit("::")
+        .find(|segment| !segment.starts_with('{'))
+        .unwrap_or(name)
+}
+
+/// Record one run of `command`
+pub fn record(command: &str, elapsed: Duration, failed: bool) {
+    eprintln!(
+        "[trace] {} took {:.1} ms{}",
+        command,
+        elapsed.as_secs_f64() * 1000.0,
+        if failed { " (failed)" } else { "" }
+    );
+    let mut trace = TRACE.lock_recover("performance trace");
+    let totals = trace
+        .commands
+        .get_or_insert_with(HashMap::new)
+        .entry(command.to_string())
+        .or_insert(Totals {
+            calls: 0,
+            failures: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+            last: Duration::ZERO,
+        });
+    totals.calls += 1;
+    totals.failures += u64::from(failed);
+    totals.total += elapsed;
+    totals.max = totals.max.max(elapsed);
+    totals.last = elapsed;
+}
+
+/// Run `task`, timing it as `command` when the trace is enabled
+pub fn measure<T>(command: &str, task: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    if !is_enabled() {
+        return task();
+    }
+    let start = Instant::now();
+    let result = task();
+    record(command, start.elapsed(), result.is_err());
+    result
+}
+
+pub fn get_performance_report() -> PerformanceReport {
+    let trace = TRACE.lock_recover("performance trace");
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut commands: Vec<CommandTiming> = trace
+        .commands
+        .iter()
+        .flatten()
+        .map(|(command, totals)| CommandTiming {
+            command: command.clone(),
+            calls: totals.calls,
+            failures: totals.failures,
+            total_ms: ms(totals.total),
+            average_ms: ms(totals.total) / totals.calls.max(1) as f64,
+            max_ms: ms(totals.max),
+            last_ms: ms(totals.last),
+        })
+        .collect();
+    commands.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    PerformanceReport {
+        enabled: is_enabled(),
+        started_at: trace.started_at,
+        commands,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_of<F: FnOnce() -> u32>(_: F) -> &'static str {
+        task_name::<F>()
+    }
+
+    fn get_status_like_command() -> &'static str {
+        name_of(|| 1)
+    }
+
+    #[test]
+    fn test_task_name_is_the_enclosing_function() {
+        assert_eq!(get_status_like_command(), "get_status_like_command");
+    }
+
+    #[test]
+    fn test_measure_records_only_when_enabled() {
+        measure("trace_test_disabled", || Ok(())).unwrap();
+        set_enabled(true);
+        measure("trace_test_command", || Ok(())).unwrap();
+        let _ = measure::<()>("trace_test_command", || Err("boom".to_string()));
+        let report = get_performance_report();
+        set_enabled(false);
+
+        assert!(report.enabled);
+        assert!(!report
+            .commands
+            .iter()
+            .any(|timing| timing.command == "trace_test_disabled"));
+        let timing = report
+            .commands
+            .iter()
+            .find(|timing| timing.command == "trace_test_command")
+            .unwrap();
+        assert_eq!((timing.calls, timing.failures), (2, 1));
+    }
+}
@@ -14,6 +14,7 @@
 // Allow dead code until all Tauri commands have been migrated.
 #![allow(dead_code)]
 
+use crate::git::cache::{CachedRepository, REPO_CACHE};
 use git2::Repository;
 use std::path::{Path, PathBuf};
 
@@ -44,24 +45,81 @@ pub fn validate_repo_path_string(repo_path: &str) -> Result<PathBuf, String> {
 /// the additional sanity check that `.git/HEAD` exists (rejects bare repos or
 /// corrupted directories early with a clear error message).
 ///
+/// Handles for the watched repository come from [`REPO_CACHE`] instead of being
+/// reopened on every call; the returned guard derefs to `Repository` and hands
+/// the handle back to the cache when dropped.
+///
 /// # Security
 /// Prevents path traversal: the caller may pass any `String` from the frontend;
 /// `canonicalize` resolves `..` and symlinks so the final path is always absolute
 /// and normalized before `git2` opens it.
-pub fn open_validated_repo(repo_path: &str) -> Result<Repository, String> {
+pub fn open_validated_repo(repo_path: &str) -> Result<CachedRepository<'static>, String> {
     let canonical = validate_repo_path_string(repo_path)?;
-    let repo = Repository::open(&canonical)
-        .map_err(|e| format!("Not a git repository at '{}': {}", canonical.display(), e))?;
-    // Sanity check: a repository without .git/HEAD is either bare or corrupted.
-    // Rejecting bare repos here avoids surprising behavior in commands that
-    // assume a working tree exists (status, diff, checkout...).
-    if !repo.path().join("HEAD").exists() {
+    REPO_CACHE.get(&canonical, || {
+        let repo = Repository::open(&canonical)
+            .map_err(|e| format!("Not a git repository at '{}': {}", canonical.display(), e))?;
+        // Sanity check: a repository without .git/HEAD is either bare or corrupted.
+        // Rejecting bare repos here avoids surprising behavior in commands that
+        // assume a working tree exists (status, diff, checkout...).
+        if !repo.path().join("HEAD").exists() {
+            return Err(format!(
+                "Invalid repository: missing .git/HEAD at '{}'",
+                canonical.display()
+            ));
+        }
+        Ok(repo)
+    })
+}
+
+/// Whether the user may create files in `dir`, asked with `access(2)`, which
+/// also reports read-only mounts, instead of writing to it. Only a permission
+/// or read-only filesystem error counts: any other failure is left for the
+/// operation itself to report.
+#[cfg(unix)]
+fn is_dir_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return true;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string that outlives the call
+    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+        return true;
+    }
+    !matches!(
+        std::io::Error::last_os_error().kind(),
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// Whether files can be created in `dir`, from its read-only attribute
+#[cfg(not(unix))]
+fn is_dir_writable(dir: &Path) -> bool {
+    std::fs::metadata(dir).map_or(true, |metadata| !metadata.permissions().readonly())
+}
+
+/// Whether the repository cannot be changed: its git dir or working tree is on
+/// read-only media (a mounted disk image, a network share without write
+/// access) or not writable by the user.
+pub fn is_read_only(repo: &Repository) -> bool {
+    !is_dir_writable(repo.path()) || repo.workdir().is_some_and(|dir| !is_dir_writable(dir))
+}
+
+/// Refuse to start a command that changes the repository at `repo_path` when
+/// it is read-only, rather than failing halfway through.
+///
+/// # Errors
+/// - Everything [`open_validated_repo`] rejects.
+/// - If the repository is read-only.
+pub fn ensure_writable(repo_path: &str) -> Result<(), String> {
+    let repo = open_validated_repo(repo_path)?;
+    if is_read_only(&repo) {
         return Err(format!(
-            "Invalid repository: missing .git/HEAD at '{}'",
-            canonical.display()
+            "The repository at '{}' is read-only; it can be browsed but not changed",
+            repo_path
         ));
     }
-    Ok(repo)
+    Ok(())
 }
 
 /// Same as [`validate_repo_path_string`] but accepts an already-canonical `Path`.
@@ -172,6 +230,26 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_ensure_writable_rejects_read_only_repo() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, canonical) = temp_repo();
+        let repo_path = canonical.to_string_lossy().to_string();
+        assert!(ensure_writable(&repo_path).is_ok());
+
+        let git_dir = canonical.join(".git");
+        fs::set_permissions(&git_dir, fs::Permissions::from_mode(0o555)).expect("chmod");
+        // Permissions do not bind root; nothing to check then
+        if !is_dir_writable(&git_dir) {
+            let err = ensure_writable(&repo_path).expect_err("should error");
+            assert!(err.contains("read-only"), "got: {err}");
+        }
+        fs::set_permissions(&git_dir, fs::Permissions::from_mode(0o755)).expect("chmod");
+        assert!(ensure_writable(&repo_path).is_ok());
+    }
+
     #[test]
     fn test_validate_repo_path_accepts_path() {
         let (_dir, canonical) = temp_repo();
@@ -0,0 +1,241 @@
+//! Verification command run before push.
+//!
+//! A repository can name a command (`npm test`, `cargo test`) that Forky runs
+//! in the work tree before pushing, as an alternative to a pre-push hook that
+//! does not depend on hook frameworks being installed. The output is streamed
+//! line by line and a failing command stops the push. The settings live in the
+//! repository config as `forky.verifyCommand` and `forky.verifyBeforePush`.
+
+use crate::git::environment::{self, git_command};
+use crate::git::progress;
+use crate::git::repository::GitOperationResult;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::mpsc;
+
+/// Lines of output kept for the result, the rest is only streamed
+const OUTPUT_TAIL_LINES: usize = 40;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerificationSettings {
+    pub command: String, // run through the shell in the work tree
+    pub before_push: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationResult {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>, // None when killed by a signal
+    pub output: String,         // last lines of stdout and stderr, interleaved
+}
+
+pub fn get_verification_settings(repo: &Repository) -> Result<VerificationSettings, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("Failed to read config: {}", e))?;
+    Ok(VerificationSettings {
+        command: config
+            .get_string("forky.verifyCommand")
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        before_push: config.get_bool("forky.verifyBeforePush").unwrap_or(false),
+    })
+}
+
+/// Store the settings in the repository config; an empty command removes it
+pub fn set_verification_settings(
+    repo: &Repository,
+    settings: &VerificationSettings,
+) -> Result<(), String> {
+    let command = settings.command.trim();
+    if settings.before_push && command.is_empty() {
+        return Err("Set a verification command to run before push".to_string());
+    }
+
+    // Written with git itself, which escapes the quotes and backslashes
+    // commands tend to contain
+    let config = |args: &[&str]| -> Result<(), String> {
+        let output = git_command()
+            .arg("--git-dir")
+            .arg(repo.path())
+            .args(["config", "--local"])
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to execute git config: {}", e))?;
+        // Exit code 5: unsetting a key that is not set
+        if output.status.success() || (args[0] == "--unset" && output.status.code() == Some(5)) {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(format!("Failed to update config: {}", stderr.trim()))
+        }
+    };
+    if command.is_empty() {
+        config(&["--unset", "forky.verifyCommand"])?;
+    } else {
+        config(&["forky.verifyCommand", command])?;
+    }
+    config(&["forky.verifyBeforePush", &settings.before_push.to_string()])
+}
+
+/// Run `command` in the work tree of `repo_path` with the environment git
+/// commands get, calling `on_line` for every line it prints
+pub fn run_verification(
+    repo_path: &str,
+    command: &str,
+    mut on_line: impl FnMut(&str),
+) -> Result<VerificationResult, String> {
+    let mut child = environment::shell_command(command)
+        .current_dir(repo_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run '{}': {}", command, e))?;
+
+    // stdout and stderr are read on their own threads so neither pipe fills up
+    let (sender, receiver) = mpsc::channel::<String>();
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn std::io::Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn std::io::Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|reader| {
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            progress::read_lines(reader, |line| {
+                let _ = sender.send(line.to_string());
+            })
+        })
+    })
+    .collect();
+    drop(sender);
+
+    let mut tail = VecDeque::with_capacity(OUTPUT_TAIL_LINES);
+    for line in receiver {
+        on_line(&line);
+        if tail.len() == OUTPUT_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for '{}': {}", command, e))?;
+
+    Ok(VerificationResult {
+        command: command.to_string(),
+        success: status.success(),
+        exit_code: status.code(),
+        output: Vec::from(tail).join("\n"),
+    })
+}
+
+/// Run the configured command when verification before push is enabled; None
+/// when it is not
+pub fn verify_before_push(
+    repo_path: &str,
+    on_line: impl FnMut(&str),
+) -> Result<Option<VerificationResult>, String> {
+    let repo =
+        Repository::open(repo_path).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let settings = get_verification_settings(&repo)?;
+    if !settings.before_push || settings.command.is_empty() {
+        return Ok(None);
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or("Cannot run a verification command in a bare repository")?;
+    run_verification(&workdir.to_string_lossy(), &settings.command, on_line).map(Some)
+}
+
+/// The result to return instead of pushing when the verification command is
+/// enabled for pushes and fails
+pub fn blocked_push(
+    repo_path: &str,
+    on_line: impl FnMut(&str),
+) -> Result<Option<GitOperationResult>, String> {
+    Ok(verify_before_push(repo_path, on_line)?
+        .filter(|result| !result.success)
+        .map(|result| failure_result(&result)))
+}
+
+/// The result a push stopped by a failed verification returns
+pub fn failure_result(result: &VerificationResult) -> GitOperationResult {
+    let status = match result.exit_code {
+        Some(code) => format!("exited with code {}", code),
+        None => "was interrupted".to_string(),
+    };
+    let mut message = format!(
+        "Push cancelled: the verification command '{}' {}",
+        result.command, status
+    );
+    if !result.output.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&result.output);
+    }
+    GitOperationResult {
+        success: false,
+        message,
+        requires_ssh_verification: None,
+        requires_credential: None,
+        error_type: Some("verification_failed".to_string()),
+        conflicting_files: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)] // the command uses sh syntax
+    fn test_settings_round_trip_and_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let repo_path = dir.path().to_string_lossy().to_string();
+        assert!(verify_before_push(&repo_path, |_| {}).unwrap().is_none());
+
+        let enabled_without_command = VerificationSettings {
+            command: " ".to_string(),
+            before_push: true,
+        };
+        assert!(set_verification_settings(&repo, &enabled_without_command).is_err());
+
+        let settings = VerificationSettings {
+            command: "echo checking && echo \"broken\" >&2 && exit 3".to_string(),
+            before_push: true,
+        };
+        set_verification_settings(&repo, &settings).unwrap();
+        assert_eq!(get_verification_settings(&repo).unwrap(), settings);
+
+        let mut lines = Vec::new();
+        let result = verify_before_push(&repo_path, |line| lines.push(line.to_string()))
+            .unwrap()
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.exit_code, Some(3));
+        lines.sort();
+        assert_eq!(lines, ["broken", "checking"]);
+
+        set_verification_settings(&repo, &VerificationSettings::default()).unwrap();
+        assert_eq!(
+            get_verification_settings(&repo).unwrap(),
+            VerificationSettings::default()
+        );
+    }
+}
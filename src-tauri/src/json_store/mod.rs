@@ -0,0 +1,77 @@
+//! JSON files written atomically.
+//!
+//! Settings, the recent repositories and workspaces, the operation history and
+//! the state of interrupted multi-step operations are all small files that
+//! are rewritten whole. Each write goes to a temporary file next to the target
+//! that is then renamed over it, so a crash or a full disk never leaves a
+//! truncated file behind.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// `path` with ".tmp" appended, in the same directory so the rename is atomic
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
+
+/// Read `path` as JSON; None when the file does not exist
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write `value` to `path` as pretty-printed JSON
+pub fn write<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+    write_content(path, &content)
+}
+
+/// Replace `path` with `content`, creating its directory if needed
+pub fn write_content(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let tmp = tmp_path(path);
+    std::fs::write(&tmp, content)
+        .map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("Failed to write {}: {}", path.display(), e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_write_then_read_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/store.json");
+        assert_eq!(read::<BTreeMap<String, u32>>(&path).unwrap(), None);
+
+        let value = BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)]);
+        write(&path, &value).unwrap();
+        assert_eq!(read(&path).unwrap(), Some(value));
+        assert!(!tmp_path(&path).exists());
+    }
+
+    #[test]
+    fn test_unparsable_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+        std::fs::write(&path, "{ truncated").unwrap();
+        assert!(read::<BTreeMap<String, u32>>(&path).is_err());
+    }
+}
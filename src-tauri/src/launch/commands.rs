@@ -0,0 +1,11 @@
+use super::{OpenRequest, PendingOpens};
+use crate::locks::LockExt;
+use tauri::State;
+
+/// Take the repositories waiting to be opened (from the command line, another
+/// launch of Forky or a `forky://` link), emptying the queue
+#[tauri::command]
+pub fn take_pending_opens(state: State<'_, PendingOpens>) -> Result<Vec<OpenRequest>, String> {
+    let mut pending = state.0.lock_recover("pending repositories");
+    Ok(std::mem::take(&mut *pending))
+}
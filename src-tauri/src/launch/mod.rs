@@ -0,0 +1,250 @@
+pub mod commands;
+
+use crate::git::repository;
+use crate::locks::LockExt;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Scheme of repository links (`forky://open?path=...&commit=...`)
+const DEEP_LINK_SCHEME: &str = "forky://";
+
+/// A repository to open, optionally showing a commit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenRequest {
+    pub path: String,
+    pub commit: Option<String>, // full commit id, from a `forky://open` link
+}
+
+/// Repositories requested on the command line (`forky /path/to/repo`), by an
+/// OS "Open with" action or by a `forky://` link, waiting for the frontend
+#[derive(Default)]
+pub struct PendingOpens(Mutex<Vec<OpenRequest>>);
+
+/// Decode `%XX` escapes in a `file://` URL path
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Extract the paths to open from a process's arguments. The first argument is
+/// the executable and flags are ignored; `file://` URLs (passed by some file
+/// managers) are accepted and relative paths are resolved against `cwd`.
+/// `forky://` links are left to the deep link plugin.
+pub fn paths_from_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !arg.is_empty() && !arg.starts_with('-'))
+        .filter(|arg| !arg.starts_with(DEEP_LINK_SCHEME))
+        .map(|arg| match arg.strip_prefix("file://") {
+            Some(url_path) => {
+                let decoded = percent_decode(url_path);
+                // file:///C:/repo names the Windows path C:/repo
+                match decoded.strip_prefix('/') {
+                    Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => {
+                        PathBuf::from(rest)
+                    }
+                    _ => PathBuf::from(decoded),
+                }
+            }
+            None => PathBuf::from(arg),
+        })
+        .map(|path| {
+            if path.is_relative() {
+                cwd.join(path)
+            } else {
+                path
+            }
+        })
+        .collect()
+}
+
+/// Folders to open for the given paths: existing directories as-is and the
+/// parent folder of files, so "Open with" works on a file inside a repository
+fn folders_to_open(paths: Vec<PathBuf>) -> Vec<String> {
+    let mut folders: Vec<String> = Vec::new();
+    for path in paths {
+        let folder = if path.is_file() {
+            path.parent().map(Path::to_path_buf)
+        } else if path.is_dir() {
+            Some(path)
+        } else {
+            None
+        };
+        let Some(folder) = folder.and_then(|f| dunce::canonicalize(f).ok()) else {
+            continue;
+        };
+        let folder = folder.to_string_lossy().to_string();
+        if !folders.contains(&folder) {
+            folders.push(folder);
+        }
+    }
+    folders
+}
+
+/// Parse a `forky://open?path=...&commit=...` link into the path and the
+/// (unresolved) commit. The path must be absolute.
+pub fn parse_deep_link(url: &str) -> Option<(PathBuf, Option<String>)> {
+    let rest = url.strip_prefix(DEEP_LINK_SCHEME)?;
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if action.trim_end_matches('/') != "open" {
+        return None;
+    }
+
+    let mut path = None;
+    let mut commit = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(&value.replace('+', " "));
+        match key {
+            "path" if !value.is_empty() => path = Some(PathBuf::from(value)),
+            "commit" if !value.is_empty() => commit = Some(value),
+            _ => {}
+        }
+    }
+
+    path.filter(|p| p.is_absolute()).map(|p| (p, commit))
+}
+
+/// Full id of `revision` in the repository at `path`, if it names a commit
+fn resolve_commit(path: &str, revision: &str) -> Option<String> {
+    let repo = repository::open_repository(path).ok()?;
+    let commit = repo.revparse_single(revision).ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// Add requests to the queue and tell the frontend with an
+/// `open-repositories-requested` event. The frontend also drains the queue on
+/// startup, so requests from the first launch are not lost before it listens.
+fn queue(app_handle: &AppHandle, requests: Vec<OpenRequest>) {
+    if requests.is_empty() {
+        return;
+    }
+
+    let state = app_handle.state::<PendingOpens>();
+    {
+        let mut pending = state.0.lock_recover("pending repositories");
+        for request in requests {
+            if !pending.contains(&request) {
+                pending.push(request);
+            }
+        }
+    }
+    if let Err(e) = app_handle.emit("open-repositories-requested", ()) {
+        eprintln!("Failed to emit open repositories event: {}", e);
+    }
+}
+
+/// Queue the repositories named in a process's arguments
+pub fn request_open(app_handle: &AppHandle, args: &[String], cwd: &Path) {
+    let requests = folders_to_open(paths_from_args(args, cwd))
+        .into_iter()
+        .map(|path| OpenRequest { path, commit: None })
+        .collect();
+    queue(app_handle, requests);
+}
+
+/// Queue the repositories (and commits) named by `forky://open` links. A
+/// commit that cannot be found is dropped and the repository opened anyway.
+pub fn open_deep_links(app_handle: &AppHandle, urls: &[String]) {
+    let requests = urls
+        .iter()
+        .filter_map(|url| parse_deep_link(url))
+        .filter_map(|(path, commit)| {
+            let path = folders_to_open(vec![path]).pop()?;
+            let commit = commit.and_then(|revision| resolve_commit(&path, &revision));
+            Some(OpenRequest { path, commit })
+        })
+        .collect();
+    queue(app_handle, requests);
+}
+
+/// Bring the main window to the front
+pub fn focus_main_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Called in the running instance when Forky is launched again: bring the
+/// window to the front and open whatever the new process was asked to open
+pub fn handle_second_instance(app_handle: &AppHandle, args: Vec<String>, cwd: String) {
+    focus_main_window(app_handle);
+    request_open(app_handle, &args, Path::new(&cwd));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_paths_from_args_skips_executable_and_flags() {
+        let cwd = Path::new("/home/user");
+        let paths = paths_from_args(
+            &args(&["forky", "--flag", "projects/app", "/srv/repo", ""]),
+            cwd,
+        );
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/home/user/projects/app"),
+                PathBuf::from("/srv/repo")
+            ]
+        );
+        assert!(paths_from_args(&args(&["forky"]), cwd).is_empty());
+    }
+
+    #[test]
+    fn test_file_urls_are_decoded() {
+        let paths = paths_from_args(
+            &args(&["forky", "file:///home/user/My%20Repo"]),
+            Path::new("/"),
+        );
+        assert_eq!(paths, vec![PathBuf::from("/home/user/My Repo")]);
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("a%2Fb%zz"), "a/b%zz");
+    }
+
+    #[test]
+    fn test_parse_deep_link() {
+        assert_eq!(
+            parse_deep_link("forky://open?path=%2Fsrv%2Fmy%20repo&commit=abc123"),
+            Some((PathBuf::from("/srv/my repo"), Some("abc123".to_string())))
+        );
+        assert_eq!(
+            parse_deep_link("forky://open/?path=/srv/repo"),
+            Some((PathBuf::from("/srv/repo"), None))
+        );
+        // Relative paths, other actions and missing paths are rejected
+        assert_eq!(parse_deep_link("forky://open?path=repo"), None);
+        assert_eq!(parse_deep_link("forky://clone?path=/srv/repo"), None);
+        assert_eq!(parse_deep_link("forky://open?commit=abc123"), None);
+        assert!(
+            paths_from_args(&args(&["forky", "forky://open?path=/a"]), Path::new("/")).is_empty()
+        );
+    }
+}
@@ -1,18 +1,36 @@
+mod forge;
 mod git;
+mod json_store;
+mod launch;
+mod locks;
+mod menu;
+mod recent;
 mod system;
 mod watcher;
+mod workspace;
 
+use forge::commands as forge_commands;
 use git::commands::{self as git_commands};
+use launch::commands as launch_commands;
+use launch::PendingOpens;
+use menu::commands as menu_commands;
+use recent::commands as recent_commands;
 use system::commands as system_commands;
 #[cfg(not(target_os = "linux"))]
 use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::{Emitter, Manager};
 use watcher::commands as watcher_commands;
 use watcher::WatcherState;
+use workspace::commands as workspace_commands;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before doing any work
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            launch::handle_second_instance(app, args, cwd);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
@@ -55,6 +73,7 @@ pub fn run() {
                     true,
                     &[
                         &open_repo,
+                        &recent::menu::create(app.handle())?,
                         &PredefinedMenuItem::separator(app)?,
                         &PredefinedMenuItem::close_window(app, Some("Close Window"))?,
                     ],
@@ -75,6 +94,8 @@ pub fn run() {
                     ],
                 )?;
 
+                let (repository_menu, branch_menu) = menu::create(app.handle())?;
+
                 let window_menu = Submenu::with_items(
                     app,
                     "Window",
@@ -88,11 +109,23 @@ pub fn run() {
                 )?;
 
                 #[cfg(target_os = "macos")]
-                let menu =
-                    Menu::with_items(app, &[&app_menu, &file_menu, &edit_menu, &window_menu])?;
+                let menu = Menu::with_items(
+                    app,
+                    &[
+                        &app_menu,
+                        &file_menu,
+                        &edit_menu,
+                        &repository_menu,
+                        &branch_menu,
+                        &window_menu,
+                    ],
+                )?;
 
                 #[cfg(not(target_os = "macos"))]
-                let menu = Menu::with_items(app, &[&file_menu, &edit_menu, &window_menu])?;
+                let menu = Menu::with_items(
+                    app,
+                    &[&file_menu, &edit_menu, &repository_menu, &branch_menu, &window_menu],
+                )?;
 
                 app.set_menu(menu)?;
             }
@@ -112,46 +145,148 @@ pub fn run() {
                 });
             }
 
-            // On Linux, use frameless window with custom titlebar
-            #[cfg(target_os = "linux")]
+            // On Linux and Windows, use frameless window with custom titlebar
+            // (on Windows its maximize button opens the snap layouts via decorum)
+            #[cfg(any(target_os = "linux", target_os = "windows"))]
             {
                 use tauri::WebviewWindow;
                 let main_window: WebviewWindow = app.get_webview_window("main").unwrap();
                 main_window.set_decorations(false).unwrap();
             }
 
+            // PATH entries and variables for git (and the hooks it runs)
+            git_commands::load_git_environment(app.handle());
+
+            // Scratch files of this instance, and cleanup of stale ones
+            git_commands::init_scratch(app.handle());
+
+            // Opt-in timing of the backend commands
+            git::trace::init_from_env();
+
+            // Repositories passed on the command line (`forky /path/to/repo`)
+            let args: Vec<String> = std::env::args().collect();
+            let cwd = std::env::current_dir().unwrap_or_default();
+            launch::request_open(app.handle(), &args, &cwd);
+
+            // forky://open?path=...&commit=... links, both the one that launched
+            // the app and those received while it runs
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+
+                // Installers register the scheme; this covers running from a
+                // bare binary or an AppImage
+                #[cfg(any(windows, target_os = "linux"))]
+                if let Err(e) = app.deep_link().register_all() {
+                    eprintln!("Failed to register forky:// links: {}", e);
+                }
+
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    let urls: Vec<String> = event.urls().iter().map(|u| u.to_string()).collect();
+                    launch::focus_main_window(&handle);
+                    launch::open_deep_links(&handle, &urls);
+                });
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    let urls: Vec<String> = urls.iter().map(|u| u.to_string()).collect();
+                    launch::open_deep_links(app.handle(), &urls);
+                }
+            }
+
             Ok(())
         })
         .manage(WatcherState::default())
+        .manage(PendingOpens::default())
         .invoke_handler(tauri::generate_handler![
             git_commands::open_repository,
+            git_commands::git_clone,
             git_commands::get_branches,
             git_commands::get_branch_heads,
+            git_commands::get_ref_decorations,
             git_commands::get_commits,
+            git_commands::get_commit_count,
+            git_commands::get_history_stats,
             git_commands::get_file_status,
+            git_commands::get_focused_paths,
+            git_commands::set_focused_paths,
             git_commands::get_file_status_separated,
+            git_commands::get_file_status_tree,
+            git_commands::get_flagged_files,
+            git_commands::get_conflicts,
+            git_commands::get_conflict_versions,
+            git_commands::resolve_conflict_take,
+            git_commands::mark_conflict_resolved,
+            git_commands::set_index_flag,
+            git_commands::clear_index_flags,
             git_commands::get_tags,
+            git_commands::get_branch_commit_set,
+            git_commands::get_default_branch,
+            git_commands::set_default_branch_local,
+            git_commands::get_recent_branches,
+            git_commands::search_refs,
+            git_commands::search_commits_by_content,
+            git_commands::get_branches_containing,
+            git_commands::get_tag_containment,
+            git_commands::git_describe,
             git_commands::get_remotes,
+            git_commands::get_default_remote,
+            git_commands::set_default_remote,
+            git_commands::get_remote_tokens,
+            git_commands::set_remote_token,
+            git_commands::remove_remote_token,
             git_commands::get_repository_info,
+            git_commands::get_repository_state,
+            git_commands::refresh,
+            git_commands::get_multi_repo_summary,
+            git_commands::list_available_actions,
             git_commands::get_working_diff,
+            git_commands::get_working_diffstat,
+            git_commands::export_working_diff,
+            git_commands::export_history,
+            git_commands::get_file_three_way,
             git_commands::get_commit_diff,
             git_commands::get_commit_files,
+            git_commands::get_refs_diff,
+            git_commands::get_refs_file_diff,
             git_commands::stage_file,
+            git_commands::stage_mode_change,
             git_commands::unstage_file,
+            git_commands::unstage_files,
+            git_commands::stage_directory,
+            git_commands::unstage_directory,
             git_commands::discard_file,
             git_commands::git_pull,
             git_commands::git_push,
             git_commands::git_fetch,
             git_commands::git_fetch_with_options,
+            git_commands::retry_with_credential,
             git_commands::git_pull_with_options,
             git_commands::git_push_with_options,
+            git_commands::scan_ssh_host_keys,
             git_commands::add_ssh_known_host,
             git_commands::git_commit,
+            git_commands::get_operation_history,
+            git_commands::undo_last_operation,
+            git_commands::get_hook_framework_status,
+            git_commands::get_branch_issue_key,
+            git_commands::get_issue_patterns,
+            git_commands::set_issue_patterns,
+            git_commands::lint_commit_message,
+            git_commands::get_commit_lint_rules,
+            git_commands::set_commit_lint_rules,
+            git_commands::get_verification_settings,
+            git_commands::set_verification_settings,
+            git_commands::run_verification_command,
+            git_commands::export_onboarding_bundle,
+            git_commands::import_onboarding_bundle,
             git_commands::get_last_commit_message,
             git_commands::git_add_remote,
             git_commands::git_test_remote_connection,
             git_commands::git_checkout,
             git_commands::git_checkout_with_stash,
+            git_commands::checkout_preflight,
+            git_commands::rewrite_preflight,
+            git_commands::get_previous_branch,
+            git_commands::git_checkout_previous,
             git_commands::git_checkout_track,
             git_commands::git_create_branch,
             git_commands::git_create_tag,
@@ -162,37 +297,95 @@ pub fn run() {
             git_commands::git_stash_apply,
             git_commands::git_stash_pop,
             git_commands::git_stash_drop,
+            git_commands::export_stash,
             git_commands::git_checkout_with_stash,
             git_commands::get_image_content,
             git_commands::get_image_from_head,
             git_commands::get_image_from_index,
+            git_commands::get_media_diff_info,
             git_commands::stage_hunk,
             git_commands::unstage_hunk,
             git_commands::discard_hunk,
+            git_commands::preview_hunk_patch,
+            git_commands::apply_patch_file,
+            git_commands::apply_working_diff,
             git_commands::get_merge_preview,
+            git_commands::get_merge_changed_files,
             git_commands::git_merge,
             git_commands::git_merge_abort,
+            git_commands::get_merge_message,
+            git_commands::git_merge_continue,
             git_commands::get_rebase_preview,
+            git_commands::get_rebase_changed_files,
             git_commands::git_rebase,
             git_commands::git_rebase_abort,
             git_commands::git_rebase_continue,
+            git_commands::git_cherry_pick,
+            git_commands::git_cherry_pick_range,
+            git_commands::git_cherry_pick_abort,
+            git_commands::git_cherry_pick_continue,
+            git_commands::git_revert,
+            git_commands::git_revert_abort,
+            git_commands::git_revert_continue,
+            git_commands::reset_preview,
+            git_commands::git_reset,
             git_commands::get_interactive_rebase_commits,
             git_commands::git_interactive_rebase,
             git_commands::get_gitflow_config,
+            git_commands::validate_gitflow_config,
+            git_commands::repair_gitflow_config,
             git_commands::get_current_branch_flow_info,
             git_commands::git_flow_init,
             git_commands::git_flow_start,
             git_commands::git_flow_finish,
+            git_commands::get_flow_finish_state,
+            git_commands::git_flow_finish_continue,
+            git_commands::git_flow_finish_abort,
+            git_commands::get_branch_stack,
+            git_commands::restack_branches,
+            git_commands::get_restack_state,
+            git_commands::restack_continue,
+            git_commands::restack_abort,
             git_commands::git_get_global_identity,
+            git_commands::git_get_repository_identity,
             git_commands::git_set_global_identity,
+            git_commands::get_performance_report,
+            git_commands::set_performance_trace,
+            git_commands::reset_performance_report,
+            git_commands::clean_scratch,
+            git_commands::get_git_environment,
+            git_commands::set_git_environment,
             git_commands::git_fast_forward,
+            forge_commands::list_pull_requests,
+            forge_commands::checkout_pull_request,
+            forge_commands::create_pull_request,
+            forge_commands::open_remote_url,
+            forge_commands::get_permalink,
+            forge_commands::get_check_status,
             system_commands::get_system_theme,
             system_commands::open_in_terminal,
             system_commands::check_git_installed,
+            system_commands::get_git_capabilities,
             system_commands::pick_folder,
+            system_commands::get_shell_integration_status,
+            system_commands::install_shell_integration,
+            system_commands::uninstall_shell_integration,
+            system_commands::run_diagnostics,
             watcher_commands::start_file_watcher,
             watcher_commands::stop_file_watcher,
             watcher_commands::get_watched_repo_path,
+            menu_commands::update_menu_state,
+            recent_commands::get_recent_repositories,
+            recent_commands::add_recent_repository,
+            recent_commands::remove_recent_repository,
+            recent_commands::set_recent_repository_pinned,
+            launch_commands::take_pending_opens,
+            workspace_commands::get_workspaces,
+            workspace_commands::save_workspace,
+            workspace_commands::rename_workspace,
+            workspace_commands::delete_workspace,
+            workspace_commands::fetch_workspace,
+            workspace_commands::pull_workspace,
         ])
         .on_menu_event(|app, event| {
             if event.id() == "open_repository" {
@@ -200,6 +393,22 @@ pub fn run() {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.emit("menu-open-repository", ());
                 }
+            } else if let Some(path) = event
+                .id()
+                .as_ref()
+                .strip_prefix(recent::menu::OPEN_RECENT_PREFIX)
+            {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.emit("menu-open-recent", path);
+                }
+            } else if let Some(action) = event.id().as_ref().strip_prefix(menu::GIT_ACTION_PREFIX) {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.emit("menu-git-action", action);
+                }
+            } else if event.id() == recent::menu::CLEAR_RECENT_ID {
+                if let Err(e) = recent::update_recent(app, |store| store.clear_unpinned()) {
+                    eprintln!("Failed to clear recent repositories: {}", e);
+                }
             } else if event.id() == "about_forky" {
                 // Emit event to frontend to open the About modal
                 if let Some(window) = app.get_webview_window("main") {
@@ -207,6 +416,22 @@ pub fn run() {
                 }
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(|window, event| {
+            // Files or folders dropped from the OS: patches to apply, repositories to open
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                git_commands::handle_dropped_paths(window.app_handle(), paths);
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            // Folders opened from Finder ("Open With" > Forky, dropped on the Dock icon)
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                let mut args = vec![String::new()];
+                args.extend(urls.iter().map(|url| url.to_string()));
+                launch::focus_main_window(_app);
+                launch::request_open(_app, &args, std::path::Path::new("/"));
+            }
+        });
 }
@@ -0,0 +1,98 @@
+//! Locking that survives panics.
+//!
+//! A command that panics while holding a mutex (or the write side of a
+//! read-write lock) poisons it, and from then on every `lock()` fails: one panic used to leave the file watcher, the recent
+//! repositories and workspace stores or the launch queue unusable until
+//! Forky was restarted. The state behind these locks is replaced in single
+//! assignments, so a panic cannot leave it half-updated; it is taken over as
+//! it is and the poison is cleared.
+
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+pub trait LockExt<T> {
+    /// Lock, recovering the mutex when a panic poisoned it; `name` says what
+    /// was recovered in the log
+    fn lock_recover(&self, name: &str) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_recover(&self, name: &str) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            eprintln!("Recovered the {} lock after a panic", name);
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
+
+pub trait RwLockExt<T> {
+    /// Read, recovering the lock when a panic poisoned it
+    fn read_recover(&self, name: &str) -> RwLockReadGuard<'_, T>;
+
+    /// Write, recovering the lock when a panic poisoned it
+    fn write_recover(&self, name: &str) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RwLockExt<T> for RwLock<T> {
+    fn read_recover(&self, name: &str) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(|poisoned| {
+            eprintln!("Recovered the {} lock after a panic", name);
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
+    fn write_recover(&self, name: &str) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(|poisoned| {
+            eprintln!("Recovered the {} lock after a panic", name);
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
+
+/// Message of a caught panic payload
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_recover_clears_poison() {
+        let mutex = Mutex::new(vec![1]);
+        let result = std::panic::catch_unwind(|| {
+            let mut guard = mutex.lock().unwrap();
+            guard.push(2);
+            panic!("boom");
+        });
+        assert_eq!(panic_message(&*result.unwrap_err()), "boom");
+        assert!(mutex.is_poisoned());
+
+        assert_eq!(*mutex.lock_recover("test"), [1, 2]);
+        assert!(!mutex.is_poisoned());
+        assert!(mutex.lock().is_ok());
+    }
+
+    #[test]
+    fn test_rw_lock_recover_clears_poison() {
+        let lock = RwLock::new(1);
+        let _ = std::panic::catch_unwind(|| {
+            let mut guard = lock.write().unwrap();
+            *guard = 2;
+            panic!("boom");
+        });
+        assert!(lock.is_poisoned());
+
+        assert_eq!(*lock.read_recover("test"), 2);
+        assert!(!lock.is_poisoned());
+        *lock.write_recover("test") = 3;
+        assert_eq!(*lock.read().unwrap(), 3);
+    }
+}
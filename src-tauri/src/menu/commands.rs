@@ -0,0 +1,29 @@
+use tauri::AppHandle;
+
+use crate::git::actions;
+use crate::git::commands::run_blocking;
+use crate::git::repository;
+use crate::git::validation::open_validated_repo;
+
+/// Enable or disable the Repository/Branch menu items for the active repository
+/// (`None` when no repository is open)
+#[tauri::command]
+pub async fn update_menu_state(
+    app_handle: AppHandle,
+    repo_path: Option<String>,
+) -> Result<(), String> {
+    let available = match repo_path {
+        Some(repo_path) => {
+            run_blocking(move || {
+                let repo = open_validated_repo(&repo_path)?;
+                let context = repository::get_action_context(&repo)?;
+                Ok(actions::available_actions(&context))
+            })
+            .await?
+        }
+        None => Vec::new(),
+    };
+
+    super::apply(&app_handle, &available);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Repository and Branch menus of the native menu bar.
+//!
+//! Each item is tied to an action of the command palette catalog
+//! ([`crate::git::actions`]); clicking it emits `menu-git-action` with the action
+//! id, and items are enabled or disabled from the catalog whenever the frontend
+//! reports the active repository through `update_menu_state`.
+
+pub mod commands;
+
+/// Menu item ids are `git_action:<action id>`
+pub const GIT_ACTION_PREFIX: &str = "git_action:";
+
+#[cfg(not(target_os = "linux"))]
+mod native {
+    use super::GIT_ACTION_PREFIX;
+    use crate::git::actions::AvailableAction;
+    use tauri::menu::{MenuItem, Submenu};
+    use tauri::{AppHandle, Manager, Wry};
+
+    struct GitMenuItem {
+        action: &'static str, // id in the actions catalog
+        label: &'static str,
+        accelerator: &'static str,
+    }
+
+    const REPOSITORY_ITEMS: &[GitMenuItem] = &[
+        GitMenuItem {
+            action: "git_fetch",
+            label: "Fetch...",
+            accelerator: "CmdOrCtrl+Shift+F",
+        },
+        GitMenuItem {
+            action: "git_pull",
+            label: "Pull...",
+            accelerator: "CmdOrCtrl+Shift+L",
+        },
+        GitMenuItem {
+            action: "git_push",
+            label: "Push...",
+            accelerator: "CmdOrCtrl+Shift+U",
+        },
+        GitMenuItem {
+            action: "git_stash_save",
+            label: "Stash Changes...",
+            accelerator: "CmdOrCtrl+Shift+S",
+        },
+        GitMenuItem {
+            action: "open_in_terminal",
+            label: "Open in Terminal",
+            accelerator: "CmdOrCtrl+Alt+T",
+        },
+    ];
+
+    const BRANCH_ITEMS: &[GitMenuItem] = &[GitMenuItem {
+        action: "git_create_branch",
+        label: "New Branch...",
+        accelerator: "CmdOrCtrl+Shift+N",
+    }];
+
+    /// Handles to the git menu items, by action id, so they can be enabled later
+    pub struct GitMenuState(pub Vec<(&'static str, MenuItem<Wry>)>);
+
+    fn build_items(
+        app_handle: &AppHandle,
+        items: &[GitMenuItem],
+        state: &mut Vec<(&'static str, MenuItem<Wry>)>,
+    ) -> tauri::Result<Vec<MenuItem<Wry>>> {
+        items
+            .iter()
+            .map(|item| {
+                // Disabled until the frontend reports an open repository
+                let menu_item = MenuItem::with_id(
+                    app_handle,
+                    format!("{}{}", GIT_ACTION_PREFIX, item.action),
+                    item.label,
+                    false,
+                    Some(item.accelerator),
+                )?;
+                state.push((item.action, menu_item.clone()));
+                Ok(menu_item)
+            })
+            .collect()
+    }
+
+    /// Build the Repository and Branch menus
+    pub fn create(app_handle: &AppHandle) -> tauri::Result<(Submenu<Wry>, Submenu<Wry>)> {
+        let mut state = Vec::new();
+
+        let repository_menu = Submenu::with_id(app_handle, "repository", "Repository", true)?;
+        for item in build_items(app_handle, REPOSITORY_ITEMS, &mut state)? {
+            repository_menu.append(&item)?;
+        }
+
+        let branch_menu = Submenu::with_id(app_handle, "branch", "Branch", true)?;
+        for item in build_items(app_handle, BRANCH_ITEMS, &mut state)? {
+            branch_menu.append(&item)?;
+        }
+
+        app_handle.manage(GitMenuState(state));
+        Ok((repository_menu, branch_menu))
+    }
+
+    /// Enable the items whose action is available; with no actions (no open
+    /// repository) everything is disabled
+    pub fn apply(app_handle: &AppHandle, actions: &[AvailableAction]) {
+        let Some(state) = app_handle.try_state::<GitMenuState>() else {
+            return;
+        };
+        for (action, item) in &state.0 {
+            let enabled = actions.iter().any(|a| a.id == *action && a.enabled);
+            if let Err(e) = item.set_enabled(enabled) {
+                eprintln!("Failed to update menu item {}: {}", action, e);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub use native::{apply, create};
+
+/// No native menu on Linux
+#[cfg(target_os = "linux")]
+pub fn apply(_app_handle: &tauri::AppHandle, _actions: &[crate::git::actions::AvailableAction]) {}
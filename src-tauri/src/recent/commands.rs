@@ -0,0 +1,47 @@
+use tauri::AppHandle;
+
+use super::{load_recent, update_recent, RecentRepository};
+use crate::git::repository;
+
+/// Get the recent repositories, pinned first, then most recently opened
+#[tauri::command]
+pub fn get_recent_repositories(app_handle: AppHandle) -> Result<Vec<RecentRepository>, String> {
+    load_recent(&app_handle)
+}
+
+/// Add a repository to the recent list (or mark it as just opened)
+#[tauri::command]
+pub fn add_recent_repository(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<Vec<RecentRepository>, String> {
+    let repo = repository::open_repository(&path)?;
+    let info = repository::get_repository_info(&repo)?;
+    let now = chrono::Utc::now().timestamp();
+    update_recent(&app_handle, |store| {
+        store.touch(&info.path, &info.name, now)
+    })
+}
+
+/// Remove a repository from the recent list
+#[tauri::command]
+pub fn remove_recent_repository(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<Vec<RecentRepository>, String> {
+    update_recent(&app_handle, |store| {
+        store.remove(&path);
+    })
+}
+
+/// Pin or unpin a recent repository
+#[tauri::command]
+pub fn set_recent_repository_pinned(
+    app_handle: AppHandle,
+    path: String,
+    pinned: bool,
+) -> Result<Vec<RecentRepository>, String> {
+    update_recent(&app_handle, |store| {
+        store.set_pinned(&path, pinned);
+    })
+}
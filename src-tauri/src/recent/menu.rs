@@ -0,0 +1,66 @@
+//! The File > Open Recent submenu. Menu item ids are `open_recent:<path>`.
+
+use super::RecentRepository;
+use tauri::AppHandle;
+
+pub const OPEN_RECENT_PREFIX: &str = "open_recent:";
+pub const CLEAR_RECENT_ID: &str = "clear_recent";
+
+#[cfg(not(target_os = "linux"))]
+pub struct RecentMenuState(pub tauri::menu::Submenu<tauri::Wry>);
+
+/// Build the submenu and keep a handle to it so it can be rebuilt later
+#[cfg(not(target_os = "linux"))]
+pub fn create(app_handle: &AppHandle) -> tauri::Result<tauri::menu::Submenu<tauri::Wry>> {
+    use tauri::Manager;
+
+    let submenu = tauri::menu::Submenu::with_id(app_handle, "open_recent", "Open Recent", true)?;
+    let repositories = super::load_recent(app_handle).unwrap_or_default();
+    fill(app_handle, &submenu, &repositories)?;
+    app_handle.manage(RecentMenuState(submenu.clone()));
+    Ok(submenu)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn fill(
+    app_handle: &AppHandle,
+    submenu: &tauri::menu::Submenu<tauri::Wry>,
+    repositories: &[RecentRepository],
+) -> tauri::Result<()> {
+    use tauri::menu::{MenuItem, PredefinedMenuItem};
+
+    for item in submenu.items()? {
+        submenu.remove(&item)?;
+    }
+
+    for repository in repositories {
+        let id = format!("{}{}", OPEN_RECENT_PREFIX, repository.path);
+        let item = MenuItem::with_id(app_handle, id, &repository.name, true, None::<&str>)?;
+        submenu.append(&item)?;
+    }
+
+    submenu.append(&PredefinedMenuItem::separator(app_handle)?)?;
+    let clear = MenuItem::with_id(
+        app_handle,
+        CLEAR_RECENT_ID,
+        "Clear Recent",
+        !repositories.is_empty(),
+        None::<&str>,
+    )?;
+    submenu.append(&clear)
+}
+
+/// Rebuild the submenu after the list changed (no-op where there is no native menu)
+#[cfg(not(target_os = "linux"))]
+pub fn refresh(app_handle: &AppHandle, repositories: &[RecentRepository]) {
+    use tauri::Manager;
+
+    if let Some(state) = app_handle.try_state::<RecentMenuState>() {
+        if let Err(e) = fill(app_handle, &state.0, repositories) {
+            eprintln!("Failed to rebuild Open Recent menu: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn refresh(_app_handle: &AppHandle, _repositories: &[RecentRepository]) {}
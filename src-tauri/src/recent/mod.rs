@@ -0,0 +1,191 @@
+pub mod commands;
+pub mod menu;
+
+use crate::git::repository::RepositoryInfo;
+use crate::json_store;
+use crate::locks::LockExt;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// File (in the app data dir) where recently opened repositories are persisted
+const RECENT_FILE: &str = "recent_repositories.json";
+
+/// How many repositories are remembered
+const MAX_RECENT: usize = 20;
+
+/// Serializes reads and writes of the recent repositories file
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentRepository {
+    pub path: String,
+    pub name: String,
+    #[serde(default)]
+    pub last_opened: i64, // unix timestamp (seconds)
+    #[serde(default)]
+    pub pinned: bool, // pinned repositories are listed first and never dropped
+}
+
+/// Contents of the recent repositories file: pinned first, then most recently opened
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecentStore {
+    #[serde(default)]
+    pub repositories: Vec<RecentRepository>,
+}
+
+impl RecentStore {
+    /// Record that a repository was opened at `now`, keeping its pinned flag
+    pub fn touch(&mut self, path: &str, name: &str, now: i64) {
+        let pinned = self.repositories.iter().any(|r| r.path == path && r.pinned);
+        self.repositories.retain(|r| r.path != path);
+        self.repositories.push(RecentRepository {
+            path: path.to_string(),
+            name: name.to_string(),
+            last_opened: now,
+            pinned,
+        });
+        self.normalize();
+    }
+
+    /// Remove a repository; returns whether it was in the list
+    pub fn remove(&mut self, path: &str) -> bool {
+        let before = self.repositories.len();
+        self.repositories.retain(|r| r.path != path);
+        self.repositories.len() != before
+    }
+
+    /// Pin or unpin a repository; returns whether it was in the list
+    pub fn set_pinned(&mut self, path: &str, pinned: bool) -> bool {
+        let Some(repository) = self.repositories.iter_mut().find(|r| r.path == path) else {
+            return false;
+        };
+        repository.pinned = pinned;
+        self.normalize();
+        true
+    }
+
+    /// Forget every repository that is not pinned
+    pub fn clear_unpinned(&mut self) {
+        self.repositories.retain(|r| r.pinned);
+    }
+
+    /// Sort pinned first, then by last opened, and cap the unpinned entries
+    fn normalize(&mut self) {
+        self.repositories.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then(b.last_opened.cmp(&a.last_opened))
+        });
+
+        let mut unpinned = 0;
+        self.repositories.retain(|r| {
+            if r.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= MAX_RECENT
+        });
+    }
+}
+
+fn store_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    Ok(dir.join(RECENT_FILE))
+}
+
+fn read_store(path: &Path) -> Result<RecentStore, String> {
+    Ok(json_store::read(path)?.unwrap_or_default())
+}
+
+fn write_store(path: &Path, store: &RecentStore) -> Result<(), String> {
+    json_store::write(path, store)
+}
+
+/// Load the recent repositories, pinned first, then most recently opened
+pub fn load_recent(app_handle: &AppHandle) -> Result<Vec<RecentRepository>, String> {
+    let path = store_path(app_handle)?;
+    let _guard = STORE_LOCK.lock_recover("recent repositories");
+    Ok(read_store(&path)?.repositories)
+}
+
+/// Load the store, apply `update`, save it, rebuild the Open Recent menu and
+/// notify the frontend with `recent-repositories-changed`
+pub fn update_recent(
+    app_handle: &AppHandle,
+    update: impl FnOnce(&mut RecentStore),
+) -> Result<Vec<RecentRepository>, String> {
+    let path = store_path(app_handle)?;
+    let repositories = {
+        let _guard = STORE_LOCK.lock_recover("recent repositories");
+        let mut store = read_store(&path)?;
+        update(&mut store);
+        write_store(&path, &store)?;
+        store.repositories
+    };
+
+    menu::refresh(app_handle, &repositories);
+    if let Err(e) = app_handle.emit("recent-repositories-changed", &repositories) {
+        eprintln!("Failed to emit recent repositories event: {}", e);
+    }
+    Ok(repositories)
+}
+
+/// Record that a repository was opened
+pub fn record_opened(app_handle: &AppHandle, info: &RepositoryInfo) {
+    let now = chrono::Utc::now().timestamp();
+    if let Err(e) = update_recent(app_handle, |store| store.touch(&info.path, &info.name, now)) {
+        eprintln!("Failed to record recent repository: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(store: &RecentStore) -> Vec<&str> {
+        store.repositories.iter().map(|r| r.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pinned_first_then_most_recent() {
+        let mut store = RecentStore::default();
+        store.touch("/a", "a", 1);
+        store.touch("/b", "b", 2);
+        store.touch("/c", "c", 3);
+        assert_eq!(paths(&store), vec!["/c", "/b", "/a"]);
+
+        assert!(store.set_pinned("/a", true));
+        store.touch("/b", "b", 4);
+        assert_eq!(paths(&store), vec!["/a", "/b", "/c"]);
+
+        // Reopening keeps the pin; clearing keeps pinned entries
+        store.touch("/a", "a", 5);
+        assert!(store.repositories[0].pinned);
+        store.clear_unpinned();
+        assert_eq!(paths(&store), vec!["/a"]);
+        assert!(!store.set_pinned("/missing", true));
+    }
+
+    #[test]
+    fn test_cap_only_drops_unpinned() {
+        let mut store = RecentStore::default();
+        store.touch("/pinned", "pinned", 0);
+        store.set_pinned("/pinned", true);
+        for i in 0..MAX_RECENT + 5 {
+            store.touch(&format!("/repo{}", i), "repo", i as i64 + 1);
+        }
+        assert_eq!(store.repositories.len(), MAX_RECENT + 1);
+        assert_eq!(store.repositories[0].path, "/pinned");
+        assert_eq!(
+            store.repositories[1].path,
+            format!("/repo{}", MAX_RECENT + 4)
+        );
+        assert!(store.remove("/pinned"));
+        assert!(!store.remove("/pinned"));
+    }
+}
@@ -1,3 +1,8 @@
+use super::diagnostics::{self, Diagnostics};
+use super::shell_integration::{self, ShellIntegrationStatus};
+use crate::git::capabilities::{self, GitCapabilities};
+use crate::git::commands::run_blocking;
+use crate::git::environment::git_command;
 use std::process::Command;
 use tauri_plugin_dialog::DialogExt;
 
@@ -13,10 +18,24 @@ pub struct GitStatus {
     pub version: Option<String>,
 }
 
-/// Checks if Git is installed on the system
+/// Checks if Git is installed on the system; the version found selects the
+/// commands used for features newer versions added
 #[tauri::command]
 pub fn check_git_installed() -> GitStatus {
-    match Command::new("git").arg("--version").output() {
+    let status = git_status();
+    capabilities::set_version(status.version.as_deref());
+    status
+}
+
+/// Features of the installed git Forky uses, with a warning for each one it
+/// is too old for
+#[tauri::command]
+pub fn get_git_capabilities() -> GitCapabilities {
+    capabilities::get_capabilities()
+}
+
+fn git_status() -> GitStatus {
+    match git_command().arg("--version").output() {
         Ok(output) => {
             if output.status.success() {
                 let version_output = String::from_utf8_lossy(&output.stdout);
@@ -45,13 +64,12 @@ pub fn check_git_installed() -> GitStatus {
     }
 }
 
-/// Opens a terminal emulator in the specified directory
-#[tauri::command]
-pub fn open_in_terminal(path: String) -> Result<(), String> {
+/// Common terminal emulators (in order of preference), with the arguments
+/// that open them in `path`
+fn terminal_candidates(path: &str) -> Vec<(&'static str, Vec<String>)> {
     // Pre-compute the shell command for xterm-style terminals
     let shell_cmd = format!("cd '{}' && exec $SHELL", path);
 
-    // List of common terminal emulators to try (in order of preference)
     let terminals: Vec<(&str, Vec<&str>)> = vec![
         // Modern terminals
         ("kitty", vec!["--directory", &path]),
@@ -73,18 +91,42 @@ pub fn open_in_terminal(path: String) -> Result<(), String> {
         ("x-terminal-emulator", vec!["-e", &shell_cmd]),
     ];
 
-    for (terminal, args) in terminals.iter() {
+    terminals
+        .into_iter()
+        .map(|(terminal, args)| (terminal, args.into_iter().map(String::from).collect()))
+        .collect()
+}
+
+/// Whether `program` can be found on PATH
+fn is_installed(program: &str) -> bool {
+    Command::new("which")
+        .arg(program)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// The first installed terminal emulator that `open_in_terminal` would use
+pub fn detect_terminal() -> Option<String> {
+    terminal_candidates("")
+        .into_iter()
+        .map(|(terminal, _)| terminal)
+        .find(|terminal| is_installed(terminal))
+        .map(String::from)
+}
+
+/// Opens a terminal emulator in the specified directory
+#[tauri::command]
+pub fn open_in_terminal(path: String) -> Result<(), String> {
+    for (terminal, args) in terminal_candidates(&path).iter() {
         // Check if terminal exists
-        if let Ok(output) = Command::new("which").arg(terminal).output() {
-            if output.status.success() {
-                // Terminal found, try to spawn it
-                match Command::new(terminal).args(args.clone()).spawn() {
-                    Ok(_) => return Ok(()),
-                    Err(e) => {
-                        // Log error but continue to next terminal
-                        eprintln!("Failed to spawn {}: {}", terminal, e);
-                        continue;
-                    }
+        if is_installed(terminal) {
+            // Terminal found, try to spawn it
+            match Command::new(terminal).args(args).spawn() {
+                Ok(_) => return Ok(()),
+                Err(e) => {
+                    // Log error but continue to next terminal
+                    eprintln!("Failed to spawn {}: {}", terminal, e);
+                    continue;
                 }
             }
         }
@@ -201,3 +243,28 @@ pub async fn pick_folder(app: tauri::AppHandle) -> Result<Option<String>, String
         Err(_) => Err("Dialog was cancelled or failed".to_string()),
     }
 }
+
+/// Reports whether the `forky` command and the "Open in Forky" folder entry are installed
+#[tauri::command]
+pub fn get_shell_integration_status() -> Result<ShellIntegrationStatus, String> {
+    shell_integration::status()
+}
+
+/// Installs the `forky` command on PATH and the "Open in Forky" folder entry
+#[tauri::command]
+pub fn install_shell_integration() -> Result<ShellIntegrationStatus, String> {
+    shell_integration::install()
+}
+
+/// Removes what `install_shell_integration` installed
+#[tauri::command]
+pub fn uninstall_shell_integration() -> Result<ShellIntegrationStatus, String> {
+    shell_integration::uninstall()
+}
+
+/// Health report of the environment: git, libgit2, git-flow, ssh, credential
+/// helpers, editor and terminal
+#[tauri::command]
+pub async fn run_diagnostics() -> Result<Diagnostics, String> {
+    run_blocking(|| Ok(diagnostics::run_diagnostics())).await
+}
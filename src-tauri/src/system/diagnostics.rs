@@ -0,0 +1,140 @@
+//! Environment health report for the About/Settings screens and support
+//! bundles: which tools Forky relies on are installed, and how git is set up.
+
+use super::commands::{check_git_installed, detect_terminal, GitStatus};
+use git2::Config;
+use serde::Serialize;
+use std::process::Command;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub git: GitStatus,
+    pub libgit2_version: String,
+    pub git_flow_version: Option<String>, // the git-flow extension; Forky's Git Flow works without it
+    pub ssh_version: Option<String>,
+    pub credential_helpers: Vec<String>, // in the order git tries them
+    pub editor: Option<String>,
+    pub terminal: Option<String>,
+    pub os: String,
+}
+
+/// First non-empty line a program prints for its version, or None if it is not
+/// installed. `ssh -V` writes to stderr, so both streams are read.
+fn program_version(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(String::from)
+}
+
+/// Configured `credential.helper` values. An empty value clears the helpers
+/// listed before it, as it does for git.
+fn credential_helpers(config: &Config) -> Vec<String> {
+    let mut helpers: Vec<String> = Vec::new();
+    if let Ok(mut entries) = config.multivar("credential.helper", None) {
+        while let Some(Ok(entry)) = entries.next() {
+            match entry.value() {
+                Some("") => helpers.clear(),
+                Some(helper) if !helpers.iter().any(|h| h == helper) => {
+                    helpers.push(helper.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+    helpers
+}
+
+/// The editor git would open for commit messages: `GIT_EDITOR`, `core.editor`,
+/// `VISUAL`, then `EDITOR`
+fn git_editor(config: &Config, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    env("GIT_EDITOR")
+        .or_else(|| config.get_string("core.editor").ok())
+        .or_else(|| env("VISUAL"))
+        .or_else(|| env("EDITOR"))
+        .filter(|editor| !editor.trim().is_empty())
+}
+
+/// Collect the health report. Runs a few external programs, so call it off the
+/// IPC thread.
+pub fn run_diagnostics() -> Diagnostics {
+    let (major, minor, patch) = git2::Version::get().libgit2_version();
+    // The user's global/system configuration; unreadable config reports nothing
+    let config = Config::open_default().and_then(|mut c| c.snapshot());
+
+    Diagnostics {
+        git: check_git_installed(),
+        libgit2_version: format!("{}.{}.{}", major, minor, patch),
+        git_flow_version: program_version("git", &["flow", "version"]),
+        ssh_version: program_version("ssh", &["-V"]),
+        credential_helpers: config.as_ref().map(credential_helpers).unwrap_or_default(),
+        editor: config
+            .as_ref()
+            .ok()
+            .and_then(|c| git_editor(c, |name| std::env::var(name).ok())),
+        terminal: detect_terminal(),
+        os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_from(content: &str) -> (tempfile::TempDir, Config) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gitconfig");
+        std::fs::write(&path, content).unwrap();
+        let config = Config::open(&path).unwrap();
+        (dir, config)
+    }
+
+    #[test]
+    fn test_credential_helpers_honour_reset() {
+        let (_dir, config) = config_from(
+            "[credential]\n\
+             \thelper = store\n\
+             \thelper =\n\
+             \thelper = cache --timeout=600\n\
+             \thelper = osxkeychain\n",
+        );
+        assert_eq!(
+            credential_helpers(&config),
+            vec!["cache --timeout=600", "osxkeychain"]
+        );
+    }
+
+    #[test]
+    fn test_git_editor_precedence() {
+        let (_dir, config) = config_from("[core]\n\teditor = nano\n");
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            git_editor(&config, env(&[("GIT_EDITOR", "vim"), ("EDITOR", "ed")])),
+            Some("vim".to_string())
+        );
+        assert_eq!(
+            git_editor(&config, env(&[("EDITOR", "ed")])),
+            Some("nano".to_string())
+        );
+        let (_dir, empty) = config_from("");
+        assert_eq!(
+            git_editor(&empty, env(&[("VISUAL", "code --wait"), ("EDITOR", "ed")])),
+            Some("code --wait".to_string())
+        );
+    }
+}
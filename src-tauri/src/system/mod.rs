@@ -1 +1,3 @@
 pub mod commands;
+pub mod diagnostics;
+pub mod shell_integration;
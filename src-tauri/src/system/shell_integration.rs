@@ -0,0 +1,389 @@
+//! Installation of the `forky` command line shim and the "Open in Forky"
+//! entry of the OS file manager.
+//!
+//! - Linux: `~/.local/bin/forky` and a `.desktop` entry handling folders, so
+//!   file managers list Forky under "Open With".
+//! - macOS: `~/.local/bin/forky`; Finder's "Open With" for folders comes from
+//!   the document types declared in the app bundle (Info.plist).
+//! - Windows: `forky.cmd` in `%LOCALAPPDATA%\Forky\bin` (added to the user
+//!   PATH) and Explorer context-menu entries under `HKCU\Software\Classes`.
+//!
+//! Every file written carries [`MARKER`], so uninstalling never removes a file
+//! Forky did not create.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Written into every installed file
+const MARKER: &str = "Installed by Forky";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShellIntegrationStatus {
+    pub cli_path: String,
+    pub cli_installed: bool,
+    pub cli_on_path: bool, // false: the shim's folder still has to be added to PATH
+    pub context_menu_installed: bool,
+    pub context_menu_supported: bool, // false where the entry comes with the app bundle
+}
+
+/// The executable the shim and menu entries launch. For an AppImage this is the
+/// image itself, not the binary inside its temporary mount.
+fn app_executable() -> Result<PathBuf, String> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe().map_err(|e| format!("Failed to locate the Forky executable: {}", e))
+}
+
+#[cfg(unix)]
+fn home_dir() -> Result<PathBuf, String> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| "Failed to locate the home directory".to_string())
+}
+
+/// Whether `dir` is one of the entries of a PATH-style variable
+fn dir_on_path(dir: &Path, path_var: &std::ffi::OsStr) -> bool {
+    std::env::split_paths(path_var).any(|entry| entry == dir)
+}
+
+/// Whether `path` exists and was written by Forky
+fn is_ours(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|content| content.contains(MARKER))
+}
+
+/// Write `content` to `path`, refusing to replace a file Forky did not create
+fn write_owned(path: &Path, content: &str) -> Result<(), String> {
+    if path.exists() && !is_ours(path) {
+        return Err(format!(
+            "{} already exists and was not created by Forky",
+            path.display()
+        ));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Remove `path` if Forky created it
+fn remove_owned(path: &Path) -> Result<(), String> {
+    if is_ours(path) {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// The `forky` shell script: opens the given folders, or the current one
+#[cfg(unix)]
+fn shim_script(executable: &Path) -> String {
+    let quoted = executable.to_string_lossy().replace('\'', r"'\''");
+    format!(
+        "#!/bin/sh\n\
+         # {MARKER}: opens the given folders (default: the current one) in Forky\n\
+         if [ $# -eq 0 ]; then set -- \"$PWD\"; fi\n\
+         nohup '{quoted}' \"$@\" >/dev/null 2>&1 &\n"
+    )
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    pub fn cli_path() -> Result<PathBuf, String> {
+        Ok(home_dir()?.join(".local").join("bin").join("forky"))
+    }
+
+    pub fn install_cli(executable: &Path) -> Result<(), String> {
+        let path = cli_path()?;
+        write_owned(&path, &shim_script(executable))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))
+    }
+
+    pub fn uninstall_cli() -> Result<(), String> {
+        remove_owned(&cli_path()?)
+    }
+
+    pub fn cli_on_path() -> bool {
+        let (Ok(path), Some(path_var)) = (cli_path(), std::env::var_os("PATH")) else {
+            return false;
+        };
+        path.parent().is_some_and(|dir| dir_on_path(dir, &path_var))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod context_menu {
+    use super::*;
+
+    fn desktop_entry_path() -> Result<PathBuf, String> {
+        Ok(home_dir()?
+            .join(".local/share/applications")
+            .join("forky-open-folder.desktop"))
+    }
+
+    pub fn supported() -> bool {
+        true
+    }
+
+    pub fn installed() -> bool {
+        desktop_entry_path().is_ok_and(|path| is_ours(&path))
+    }
+
+    pub fn install(executable: &Path) -> Result<(), String> {
+        let exec = executable.to_string_lossy().replace('"', "\\\"");
+        let entry = format!(
+            "[Desktop Entry]\n\
+             # {MARKER}\n\
+             Type=Application\n\
+             Name=Open in Forky\n\
+             Exec=\"{exec}\" %F\n\
+             Icon=forky\n\
+             MimeType=inode/directory;\n\
+             NoDisplay=true\n\
+             Terminal=false\n"
+        );
+        write_owned(&desktop_entry_path()?, &entry)
+    }
+
+    pub fn uninstall() -> Result<(), String> {
+        remove_owned(&desktop_entry_path()?)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod context_menu {
+    use super::*;
+
+    // Finder lists Forky under "Open With" for folders through the document
+    // types in the bundle's Info.plist; there is nothing to install
+    pub fn supported() -> bool {
+        false
+    }
+
+    pub fn installed() -> bool {
+        true
+    }
+
+    pub fn install(_executable: &Path) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn uninstall() -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    const ENVIRONMENT_KEY: &str = r"HKCU\Environment";
+
+    fn bin_dir() -> Result<PathBuf, String> {
+        let local = std::env::var_os("LOCALAPPDATA")
+            .ok_or_else(|| "Failed to locate %LOCALAPPDATA%".to_string())?;
+        Ok(PathBuf::from(local).join("Forky").join("bin"))
+    }
+
+    pub fn cli_path() -> Result<PathBuf, String> {
+        Ok(bin_dir()?.join("forky.cmd"))
+    }
+
+    /// The user PATH as stored in the registry (not the inherited process PATH)
+    fn user_path() -> String {
+        let Ok(output) = Command::new("reg")
+            .args(["query", ENVIRONMENT_KEY, "/v", "Path"])
+            .output()
+        else {
+            return String::new();
+        };
+        // "    Path    REG_EXPAND_SZ    C:\...;C:\..."
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| {
+                let line = line.trim();
+                line.strip_prefix("Path")
+                    .and_then(|rest| rest.trim_start().split_once("REG_"))
+                    .and_then(|(_, rest)| rest.split_once(char::is_whitespace))
+                    .map(|(_, value)| value.trim().to_string())
+            })
+            .unwrap_or_default()
+    }
+
+    fn set_user_path(value: &str) -> Result<(), String> {
+        let output = Command::new("reg")
+            .args(["add", ENVIRONMENT_KEY, "/v", "Path", "/t", "REG_EXPAND_SZ"])
+            .args(["/d", value, "/f"])
+            .output()
+            .map_err(|e| format!("Failed to update PATH: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to update PATH: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn install_cli(executable: &Path) -> Result<(), String> {
+        let script = format!(
+            "@echo off\r\n\
+             rem {MARKER}: opens the given folders (default: the current one) in Forky\r\n\
+             if \"%~1\"==\"\" (start \"\" \"{exe}\" \"%CD%\") else (start \"\" \"{exe}\" %*)\r\n",
+            exe = executable.display()
+        );
+        write_owned(&cli_path()?, &script)?;
+
+        // Only new terminals see the change
+        let dir = bin_dir()?;
+        let path = user_path();
+        if !dir_on_path(&dir, path.as_ref()) {
+            let dir = dir.to_string_lossy();
+            let updated = if path.is_empty() {
+                dir.to_string()
+            } else {
+                format!("{};{}", path.trim_end_matches(';'), dir)
+            };
+            set_user_path(&updated)?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall_cli() -> Result<(), String> {
+        remove_owned(&cli_path()?)?;
+        let dir = bin_dir()?;
+        let path = user_path();
+        if dir_on_path(&dir, path.as_ref()) {
+            let remaining: Vec<&str> = path
+                .split(';')
+                .filter(|entry| !entry.is_empty() && Path::new(entry) != dir)
+                .collect();
+            set_user_path(&remaining.join(";"))?;
+        }
+        Ok(())
+    }
+
+    pub fn cli_on_path() -> bool {
+        bin_dir().is_ok_and(|dir| dir_on_path(&dir, user_path().as_ref()))
+    }
+}
+
+#[cfg(windows)]
+mod context_menu {
+    use super::*;
+    use std::process::Command;
+
+    /// Folder entries, and the entry shown when right-clicking inside a folder
+    const KEYS: &[&str] = &[
+        r"HKCU\Software\Classes\Directory\shell\Forky",
+        r"HKCU\Software\Classes\Directory\Background\shell\Forky",
+    ];
+
+    fn reg(args: &[&str]) -> Result<(), String> {
+        let output = Command::new("reg")
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run reg: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to update the context menu: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn supported() -> bool {
+        true
+    }
+
+    pub fn installed() -> bool {
+        Command::new("reg")
+            .args(["query", KEYS[0], "/v", MARKER])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    pub fn install(executable: &Path) -> Result<(), String> {
+        let exe = executable.to_string_lossy();
+        let command = format!("\"{}\" \"%V\"", exe);
+        for key in KEYS {
+            let command_key = format!(r"{}\command", key);
+            reg(&["add", key, "/ve", "/d", "Open in Forky", "/f"])?;
+            reg(&["add", key, "/v", "Icon", "/d", &exe, "/f"])?;
+            reg(&["add", key, "/v", MARKER, "/d", "1", "/f"])?;
+            reg(&["add", &command_key, "/ve", "/d", &command, "/f"])?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall() -> Result<(), String> {
+        if installed() {
+            for key in KEYS {
+                reg(&["delete", key, "/f"])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What is currently installed
+pub fn status() -> Result<ShellIntegrationStatus, String> {
+    let cli_path = platform::cli_path()?;
+    Ok(ShellIntegrationStatus {
+        cli_installed: is_ours(&cli_path),
+        cli_path: cli_path.to_string_lossy().to_string(),
+        cli_on_path: platform::cli_on_path(),
+        context_menu_installed: context_menu::installed(),
+        context_menu_supported: context_menu::supported(),
+    })
+}
+
+/// Install the `forky` shim and the "Open in Forky" entry
+pub fn install() -> Result<ShellIntegrationStatus, String> {
+    let executable = app_executable()?;
+    platform::install_cli(&executable)?;
+    context_menu::install(&executable)?;
+    status()
+}
+
+/// Remove everything [`install`] created
+pub fn uninstall() -> Result<ShellIntegrationStatus, String> {
+    platform::uninstall_cli()?;
+    context_menu::uninstall()?;
+    status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_on_path() {
+        let path_var = std::env::join_paths(["/usr/bin", "/home/user/.local/bin"]).unwrap();
+        assert!(dir_on_path(Path::new("/home/user/.local/bin"), &path_var));
+        assert!(!dir_on_path(Path::new("/home/user/bin"), &path_var));
+    }
+
+    #[test]
+    fn test_only_owned_files_are_replaced_or_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bin").join("forky");
+
+        write_owned(&path, &format!("# {}\n", MARKER)).unwrap();
+        assert!(is_ours(&path));
+        remove_owned(&path).unwrap();
+        assert!(!path.exists());
+
+        std::fs::write(&path, "#!/bin/sh\necho someone else's forky\n").unwrap();
+        assert!(write_owned(&path, MARKER).is_err());
+        remove_owned(&path).unwrap();
+        assert!(path.exists());
+    }
+}
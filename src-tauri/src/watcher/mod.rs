@@ -3,8 +3,11 @@ pub mod commands;
 use notify_debouncer_mini::{
     new_debouncer, notify::RecommendedWatcher, DebouncedEventKind, Debouncer,
 };
+use crate::git::cache::REPO_CACHE;
+use crate::git::repository::{get_focused_paths, get_repository_state, RepositoryState};
+use crate::locks::LockExt;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
@@ -23,6 +26,15 @@ pub struct BranchChangeEvent {
     pub timestamp: u64,
 }
 
+/// Event payload sent to frontend when a merge, rebase, cherry-pick, revert or
+/// bisect starts, advances or ends, from Forky or from another tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationChangeEvent {
+    pub repo_path: String,
+    pub timestamp: u64,
+    pub state: RepositoryState,
+}
+
 /// State for the file watcher
 pub struct WatcherState {
     pub debouncer: Mutex<Option<Debouncer<RecommendedWatcher>>>,
@@ -53,44 +65,133 @@ const IGNORED_PATHS: &[&str] = &[
     ".turbo",
 ];
 
+/// Directories inside the git dir to ignore. A linked worktree's git dir is not
+/// named .git (it lives under the main repository's .git/worktrees), so these
+/// are matched against the actual git dir rather than by name.
+const IGNORED_GIT_DIR_PATHS: &[&str] = &["objects", "logs", "hooks", "refs"];
+
 /// Check if a path should be ignored
-fn should_ignore_path(path: &Path) -> bool {
+fn should_ignore_path(path: &Path, git_dir: Option<&Path>) -> bool {
     let path_str = path.to_string_lossy();
     IGNORED_PATHS
         .iter()
         .any(|ignored| path_str.contains(ignored))
+        || git_dir.is_some_and(|git_dir| {
+            IGNORED_GIT_DIR_PATHS
+                .iter()
+                .any(|ignored| path.starts_with(git_dir.join(ignored)))
+        })
+}
+
+/// Check if a path is the HEAD file of the git dir (indicates branch change)
+fn is_git_head_file(path: &Path, git_dir: Option<&Path>) -> bool {
+    path.ends_with(".git/HEAD")
+        || path.ends_with(".git\\HEAD")
+        || git_dir.is_some_and(|git_dir| path == git_dir.join("HEAD"))
 }
 
-/// Check if a path is the .git/HEAD file (indicates branch change)
-fn is_git_head_file(path: &Path) -> bool {
-    path.ends_with(".git/HEAD") || path.ends_with(".git\\HEAD")
+/// Files and directories git keeps in the git dir while an operation is in
+/// progress
+const OPERATION_STATE_PATHS: &[&str] = &[
+    "MERGE_HEAD",
+    "CHERRY_PICK_HEAD",
+    "REVERT_HEAD",
+    "REBASE_HEAD",
+    "BISECT_LOG",
+    "rebase-merge",
+    "rebase-apply",
+    "sequencer",
+];
+
+/// Check if a path is (or is inside) one of the operation state paths of the
+/// git dir
+fn is_operation_state_path(path: &Path, git_dir: Option<&Path>) -> bool {
+    git_dir.is_some_and(|git_dir| {
+        OPERATION_STATE_PATHS
+            .iter()
+            .any(|name| path.starts_with(git_dir.join(name)))
+    })
+}
+
+/// The git dir of the repository at `repo_path`: its .git directory, or for a
+/// linked worktree the directory its .git file points to, which holds that
+/// worktree's HEAD and index
+fn git_dir(repo_path: &str) -> Option<PathBuf> {
+    let repo = git2::Repository::open(repo_path).ok()?;
+    Some(dunce::canonicalize(repo.path()).unwrap_or_else(|_| repo.path().to_path_buf()))
+}
+
+/// Directories to watch for a repository: the whole working tree, or only the
+/// focused paths when the repository has any, plus the git dir when it is not
+/// already covered (focused paths, linked worktrees)
+fn watch_targets(repo_path: &str, git_dir: Option<&Path>) -> Vec<PathBuf> {
+    let workdir = dunce::canonicalize(repo_path).unwrap_or_else(|_| PathBuf::from(repo_path));
+    let focused = git2::Repository::open(repo_path)
+        .ok()
+        .and_then(|repo| get_focused_paths(&repo).ok())
+        .unwrap_or_default();
+
+    let mut targets: Vec<PathBuf> = if focused.is_empty() {
+        vec![workdir]
+    } else {
+        focused
+            .iter()
+            .map(|path| workdir.join(path))
+            .filter(|path| path.exists())
+            .collect()
+    };
+    if let Some(git_dir) = git_dir {
+        if !targets.iter().any(|target| git_dir.starts_with(target)) {
+            targets.insert(0, git_dir.to_path_buf());
+        }
+    }
+    targets
 }
 
-/// Start watching a repository path for file changes
+/// Start watching a repository path for file changes, replacing the current
+/// watcher. The new watcher is set up before any lock is taken, since
+/// registering a large tree takes a while and commands reading the watched
+/// path should not wait for it; if it fails, the current one keeps running.
 pub fn start_watching(app_handle: AppHandle, repo_path: String) -> Result<(), String> {
     let watcher_state = app_handle.state::<WatcherState>();
 
-    // Stop any existing watcher first
-    stop_watching_internal(&watcher_state)?;
-
     let app_handle_clone = app_handle.clone();
     let repo_path_clone = repo_path.clone();
+    let git_dir = git_dir(&repo_path);
+    let watched_git_dir = git_dir.clone();
+
+    // State last reported, so that only actual changes are emitted
+    let operation_key = |state: &RepositoryState| {
+        (
+            state.state.clone(),
+            state.rebase_step,
+            state.sequence_remaining,
+        )
+    };
+    let mut last_operation_state = git2::Repository::open(&repo_path)
+        .ok()
+        .and_then(|repo| get_repository_state(&repo).ok())
+        .map(|state| operation_key(&state));
 
     // Create debouncer with 500ms delay
-    let debouncer = new_debouncer(
+    let mut debouncer = new_debouncer(
         Duration::from_millis(500),
         move |result: Result<Vec<notify_debouncer_mini::DebouncedEvent>, notify::Error>| {
             match result {
                 Ok(events) => {
+                    // Cached repository handles may be stale after any change
+                    REPO_CACHE.invalidate();
+
                     let timestamp = std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
                         .unwrap_or_default()
                         .as_secs();
 
-                    // Check for branch changes (.git/HEAD)
-                    let has_branch_change = events
-                        .iter()
-                        .any(|e| e.kind == DebouncedEventKind::Any && is_git_head_file(&e.path));
+                    // Check for branch changes (HEAD in the git dir)
+                    let git_dir = watched_git_dir.as_deref();
+                    let has_branch_change = events.iter().any(|e| {
+                        e.kind == DebouncedEventKind::Any && is_git_head_file(&e.path, git_dir)
+                    });
 
                     if has_branch_change {
                         let branch_event = BranchChangeEvent {
@@ -103,13 +204,44 @@ pub fn start_watching(app_handle: AppHandle, repo_path: String) -> Result<(), St
                         }
                     }
 
+                    // Check for operations starting, advancing or ending
+                    let has_operation_change = events.iter().any(|e| {
+                        e.kind == DebouncedEventKind::Any
+                            && is_operation_state_path(&e.path, git_dir)
+                    });
+
+                    if has_operation_change {
+                        let state = git2::Repository::open(&repo_path_clone)
+                            .map_err(|e| e.message().to_string())
+                            .and_then(|repo| get_repository_state(&repo));
+                        match state {
+                            Ok(state) => {
+                                let key = operation_key(&state);
+                                if last_operation_state.as_ref() != Some(&key) {
+                                    last_operation_state = Some(key);
+                                    let operation_event = OperationChangeEvent {
+                                        repo_path: repo_path_clone.clone(),
+                                        timestamp,
+                                        state,
+                                    };
+                                    if let Err(e) = app_handle_clone
+                                        .emit("repo-operation-changed", operation_event)
+                                    {
+                                        eprintln!("Failed to emit operation change event: {}", e);
+                                    }
+                                }
+                            }
+                            Err(e) => eprintln!("Failed to read repository state: {}", e),
+                        }
+                    }
+
                     // Filter out ignored paths for file changes
                     let relevant_events: Vec<_> = events
                         .iter()
                         .filter(|e| {
                             e.kind == DebouncedEventKind::Any
-                                && !should_ignore_path(&e.path)
-                                && !is_git_head_file(&e.path)
+                                && !should_ignore_path(&e.path, git_dir)
+                                && !is_git_head_file(&e.path, git_dir)
                         })
                         .collect();
 
@@ -133,38 +265,24 @@ pub fn start_watching(app_handle: AppHandle, repo_path: String) -> Result<(), St
     )
     .map_err(|e| format!("Failed to create debouncer: {}", e))?;
 
-    // Store the debouncer and path
-    {
-        let mut debouncer_guard = watcher_state
-            .debouncer
-            .lock()
-            .map_err(|e| format!("Failed to lock debouncer: {}", e))?;
-        *debouncer_guard = Some(debouncer);
-    }
-
-    {
-        let mut path_guard = watcher_state
-            .watched_path
-            .lock()
-            .map_err(|e| format!("Failed to lock watched_path: {}", e))?;
-        *path_guard = Some(repo_path.clone());
-    }
-
     // Start watching the repository path
-    {
-        let mut debouncer_guard = watcher_state
-            .debouncer
-            .lock()
-            .map_err(|e| format!("Failed to lock debouncer: {}", e))?;
-
-        if let Some(ref mut debouncer) = *debouncer_guard {
-            debouncer
-                .watcher()
-                .watch(Path::new(&repo_path), notify::RecursiveMode::Recursive)
-                .map_err(|e| format!("Failed to watch path: {}", e))?;
-        }
+    for target in watch_targets(&repo_path, git_dir.as_deref()) {
+        debouncer
+            .watcher()
+            .watch(&target, notify::RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch path: {}", e))?;
     }
 
+    // Swap in the debouncer and path; the previous debouncer is stopped after
+    // the locks are released
+    let previous = watcher_state
+        .debouncer
+        .lock_recover("file watcher")
+        .replace(debouncer);
+    *watcher_state.watched_path.lock_recover("watched path") = Some(repo_path.clone());
+    REPO_CACHE.watch(Path::new(&repo_path));
+    drop(previous);
+
     Ok(())
 }
 
@@ -175,19 +293,12 @@ pub fn stop_watching(app_handle: &AppHandle) -> Result<(), String> {
 }
 
 fn stop_watching_internal(watcher_state: &WatcherState) -> Result<(), String> {
-    let mut debouncer_guard = watcher_state
-        .debouncer
-        .lock()
-        .map_err(|e| format!("Failed to lock debouncer: {}", e))?;
-
-    // Drop the debouncer to stop watching
-    *debouncer_guard = None;
+    let previous = watcher_state.debouncer.lock_recover("file watcher").take();
+    *watcher_state.watched_path.lock_recover("watched path") = None;
+    REPO_CACHE.unwatch();
 
-    let mut path_guard = watcher_state
-        .watched_path
-        .lock()
-        .map_err(|e| format!("Failed to lock watched_path: {}", e))?;
-    *path_guard = None;
+    // Drop the debouncer to stop watching, without holding the lock
+    drop(previous);
 
     Ok(())
 }
@@ -195,6 +306,6 @@ fn stop_watching_internal(watcher_state: &WatcherState) -> Result<(), String> {
 /// Get the currently watched path
 pub fn get_watched_path(app_handle: &AppHandle) -> Option<String> {
     let watcher_state = app_handle.state::<WatcherState>();
-    let guard = watcher_state.watched_path.lock().ok()?;
+    let guard = watcher_state.watched_path.lock_recover("watched path");
     guard.clone()
 }
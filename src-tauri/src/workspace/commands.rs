@@ -0,0 +1,93 @@
+use tauri::AppHandle;
+
+use super::{
+    load_workspaces, run_on_workspace, update_workspaces, Workspace, WorkspaceOperationResult,
+};
+use crate::git::commands::run_blocking;
+use crate::git::network;
+use crate::git::repository::GitOperationResult;
+use crate::git::validation::{ensure_writable, validate_repo_path_string};
+
+/// Get all saved workspaces
+#[tauri::command]
+pub fn get_workspaces(app_handle: AppHandle) -> Result<Vec<Workspace>, String> {
+    load_workspaces(&app_handle)
+}
+
+/// Create a workspace, or replace the repositories of an existing one
+#[tauri::command]
+pub fn save_workspace(
+    app_handle: AppHandle,
+    name: String,
+    repositories: Vec<String>,
+) -> Result<Vec<Workspace>, String> {
+    let repositories = repositories
+        .iter()
+        .map(|path| validate_repo_path_string(path).map(|p| p.to_string_lossy().to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    update_workspaces(&app_handle, |store| store.upsert(&name, repositories))
+}
+
+/// Rename a workspace
+#[tauri::command]
+pub fn rename_workspace(
+    app_handle: AppHandle,
+    name: String,
+    new_name: String,
+) -> Result<Vec<Workspace>, String> {
+    update_workspaces(&app_handle, |store| store.rename(&name, &new_name))
+}
+
+/// Delete a workspace (its repositories are left untouched)
+#[tauri::command]
+pub fn delete_workspace(app_handle: AppHandle, name: String) -> Result<Vec<Workspace>, String> {
+    update_workspaces(&app_handle, |store| {
+        if store.remove(&name) {
+            Ok(())
+        } else {
+            Err(format!("Workspace '{}' not found", name))
+        }
+    })
+}
+
+async fn run_workspace_operation(
+    app_handle: AppHandle,
+    name: String,
+    operation: fn(&str) -> Result<GitOperationResult, String>,
+) -> Result<WorkspaceOperationResult, String> {
+    let workspace = load_workspaces(&app_handle)?
+        .into_iter()
+        .find(|w| w.name == name.trim())
+        .ok_or_else(|| format!("Workspace '{}' not found", name))?;
+
+    run_blocking(move || Ok(run_on_workspace(&workspace, operation))).await
+}
+
+/// Fetch every repository of a workspace in parallel; read-only repositories are
+/// reported as failed
+#[tauri::command]
+pub async fn fetch_workspace(
+    app_handle: AppHandle,
+    name: String,
+) -> Result<WorkspaceOperationResult, String> {
+    run_workspace_operation(app_handle, name, |path| {
+        ensure_writable(path)?;
+        network::fetch(path, |_| {}).map(|r| r.result)
+    })
+    .await
+}
+
+/// Pull every repository of a workspace in parallel; read-only repositories are
+/// reported as failed
+#[tauri::command]
+pub async fn pull_workspace(
+    app_handle: AppHandle,
+    name: String,
+) -> Result<WorkspaceOperationResult, String> {
+    run_workspace_operation(app_handle, name, |path| {
+        ensure_writable(path)?;
+        network::pull(path, |_| {}).map(|r| r.result)
+    })
+    .await
+}
@@ -0,0 +1,258 @@
+pub mod commands;
+
+use crate::git::repository::GitOperationResult;
+use crate::json_store;
+use crate::locks::LockExt;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// File (in the app data dir) where workspaces are persisted
+const WORKSPACES_FILE: &str = "workspaces.json";
+
+/// Serializes reads and writes of the workspaces file
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A named group of repositories that are usually worked on together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub repositories: Vec<String>, // canonical repository paths
+}
+
+/// Contents of the workspaces file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceStore {
+    #[serde(default)]
+    pub workspaces: Vec<Workspace>,
+}
+
+/// `name` without surrounding whitespace, refused when nothing is left
+fn workspace_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    Ok(name)
+}
+
+impl WorkspaceStore {
+    /// Create or replace the workspace with the same name. Repository paths are
+    /// de-duplicated, keeping their first position.
+    pub fn upsert(&mut self, name: &str, repositories: Vec<String>) -> Result<(), String> {
+        let name = workspace_name(name)?;
+
+        let mut unique: Vec<String> = Vec::with_capacity(repositories.len());
+        for path in repositories {
+            if !unique.contains(&path) {
+                unique.push(path);
+            }
+        }
+
+        let workspace = Workspace {
+            name: name.to_string(),
+            repositories: unique,
+        };
+        match self.workspaces.iter_mut().find(|w| w.name == name) {
+            Some(existing) => *existing = workspace,
+            None => self.workspaces.push(workspace),
+        }
+        Ok(())
+    }
+
+    /// Rename a workspace, keeping its repositories and position
+    pub fn rename(&mut self, name: &str, new_name: &str) -> Result<(), String> {
+        let name = name.trim();
+        let new_name = workspace_name(new_name)?;
+        if new_name != name && self.workspaces.iter().any(|w| w.name == new_name) {
+            return Err(format!("Workspace '{}' already exists", new_name));
+        }
+        let workspace = self
+            .workspaces
+            .iter_mut()
+            .find(|w| w.name == name)
+            .ok_or_else(|| format!("Workspace '{}' not found", name))?;
+        workspace.name = new_name.to_string();
+        Ok(())
+    }
+
+    /// Remove a workspace by name; returns whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        let before = self.workspaces.len();
+        self.workspaces.retain(|w| w.name != name);
+        self.workspaces.len() != before
+    }
+}
+
+/// Result of running an operation on one repository of a workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceRepoResult {
+    pub repo_path: String,
+    pub result: GitOperationResult,
+}
+
+/// Aggregated result of running an operation on every repository of a workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceOperationResult {
+    pub workspace: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<WorkspaceRepoResult>, // in workspace order
+}
+
+fn store_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    Ok(dir.join(WORKSPACES_FILE))
+}
+
+fn read_store(path: &Path) -> Result<WorkspaceStore, String> {
+    Ok(json_store::read(path)?.unwrap_or_default())
+}
+
+fn write_store(path: &Path, store: &WorkspaceStore) -> Result<(), String> {
+    json_store::write(path, store)
+}
+
+/// Load all workspaces (empty if none have been saved yet)
+pub fn load_workspaces(app_handle: &AppHandle) -> Result<Vec<Workspace>, String> {
+    let path = store_path(app_handle)?;
+    let _guard = STORE_LOCK.lock_recover("workspaces");
+    Ok(read_store(&path)?.workspaces)
+}
+
+/// Load the store, apply `update` and save it back, returning the new workspace list
+pub fn update_workspaces<T>(
+    app_handle: &AppHandle,
+    update: impl FnOnce(&mut WorkspaceStore) -> Result<T, String>,
+) -> Result<Vec<Workspace>, String> {
+    let path = store_path(app_handle)?;
+    let _guard = STORE_LOCK.lock_recover("workspaces");
+    let mut store = read_store(&path)?;
+    update(&mut store)?;
+    write_store(&path, &store)?;
+    Ok(store.workspaces)
+}
+
+fn failed_result(message: String) -> GitOperationResult {
+    GitOperationResult {
+        success: false,
+        message,
+        requires_ssh_verification: None,
+        requires_credential: None,
+        error_type: Some("git_error".to_string()),
+        conflicting_files: None,
+    }
+}
+
+/// Run `operation` on every repository of the workspace in parallel and
+/// aggregate the results. A repository that fails does not stop the others.
+pub fn run_on_workspace(
+    workspace: &Workspace,
+    operation: fn(&str) -> Result<GitOperationResult, String>,
+) -> WorkspaceOperationResult {
+    let results: Vec<WorkspaceRepoResult> = std::thread::scope(|scope| {
+        let handles: Vec<_> = workspace
+            .repositories
+            .iter()
+            .map(|repo_path| scope.spawn(move || operation(repo_path)))
+            .collect();
+
+        handles
+            .into_iter()
+            .zip(&workspace.repositories)
+            .map(|(handle, repo_path)| {
+                let result = match handle.join() {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(e)) => failed_result(e),
+                    Err(_) => failed_result("Operation panicked".to_string()),
+                };
+                WorkspaceRepoResult {
+                    repo_path: repo_path.clone(),
+                    result,
+                }
+            })
+            .collect()
+    });
+
+    let succeeded = results.iter().filter(|r| r.result.success).count();
+    WorkspaceOperationResult {
+        workspace: workspace.name.clone(),
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_replaces_and_dedupes() {
+        let mut store = WorkspaceStore::default();
+        store
+            .upsert("services", vec!["/a".into(), "/b".into(), "/a".into()])
+            .unwrap();
+        assert_eq!(store.workspaces[0].repositories, vec!["/a", "/b"]);
+
+        store.upsert(" services ", vec!["/c".into()]).unwrap();
+        assert_eq!(store.workspaces.len(), 1);
+        assert_eq!(store.workspaces[0].repositories, vec!["/c"]);
+
+        assert!(store.upsert("  ", vec![]).is_err());
+        assert!(store.remove(" services"));
+        assert!(!store.remove("services"));
+    }
+
+    #[test]
+    fn test_rename_trims_and_rejects_empty_or_taken_names() {
+        let mut store = WorkspaceStore::default();
+        store.upsert("services", vec!["/a".into()]).unwrap();
+        store.upsert("tools", vec!["/b".into()]).unwrap();
+
+        assert!(store.rename("services", " \t ").is_err());
+        assert!(store.rename("services", "tools").is_err());
+        assert!(store.rename("missing", "other").is_err());
+
+        store.rename("services", "  backend  ").unwrap();
+        assert_eq!(store.workspaces[0].name, "backend");
+        assert_eq!(store.workspaces[0].repositories, vec!["/a"]);
+    }
+
+    #[test]
+    fn test_run_on_workspace_aggregates_results() {
+        fn operation(repo_path: &str) -> Result<GitOperationResult, String> {
+            if repo_path == "/broken" {
+                return Err("not a repository".to_string());
+            }
+            Ok(GitOperationResult {
+                success: true,
+                message: repo_path.to_string(),
+                requires_ssh_verification: None,
+                requires_credential: None,
+                error_type: None,
+                conflicting_files: None,
+            })
+        }
+
+        let workspace = Workspace {
+            name: "services".into(),
+            repositories: vec!["/a".into(), "/broken".into(), "/b".into()],
+        };
+        let summary = run_on_workspace(&workspace, operation);
+        assert_eq!(summary.succeeded, 2);
+        assert_eq!(summary.failed, 1);
+        let order: Vec<_> = summary
+            .results
+            .iter()
+            .map(|r| r.repo_path.as_str())
+            .collect();
+        assert_eq!(order, vec!["/a", "/broken", "/b"]);
+        assert_eq!(summary.results[1].result.message, "not a repository");
+    }
+}
@@ -151,6 +151,8 @@ import {
   gitFlowInitOperation,
   gitFlowStartOperation,
   gitFlowFinishOperation,
+  gitFlowFinishContinueOperation,
+  gitFlowFinishAbortOperation,
   createTagOperation,
 } from './hooks/git';
 import { getBranchRemote } from './utils/branchUtils';
@@ -158,6 +160,9 @@ import type {
   BranchInfo,
   ViewMode,
   GitOperationResult,
+  NetworkResult,
+  VerificationOutputEvent,
+  OperationChangeEvent,
   GitOptionsStorage,
   StashInfo,
   MergePreview,
@@ -166,7 +171,11 @@ import type {
   InteractiveRebaseEntry,
   GitFlowConfig,
   CurrentBranchFlowInfo,
+  FlowFinishState,
   GitFlowType,
+  OpenRequest,
+  CheckoutPreflight,
+  RepoRefresh,
 } from './types/git';
 import './styles/global.css';
 import './App.css';
@@ -190,6 +199,8 @@ function App() {
           return t('alerts.connectionTimeout');
         case 'host_not_found':
           return t('alerts.hostNotFound');
+        case 'verification_failed':
+          return t('alerts.verificationFailed');
         case 'git_error':
           return t('alerts.gitError');
         default:
@@ -306,6 +317,7 @@ function App() {
   const [checkoutConflictModalOpen, setCheckoutConflictModalOpen] = useState(false);
   const [checkoutConflictBranch, setCheckoutConflictBranch] = useState('');
   const [checkoutConflictFiles, setCheckoutConflictFiles] = useState<string[]>([]);
+  const [checkoutStashConflicts, setCheckoutStashConflicts] = useState<string[]>([]);
   const [isCheckoutWithStashLoading, setIsCheckoutWithStashLoading] = useState(false);
 
   // Git not installed modal state
@@ -336,16 +348,22 @@ function App() {
   const [currentBranchFlowInfo, setCurrentBranchFlowInfo] = useState<CurrentBranchFlowInfo | null>(
     null
   );
+  // A finish stopped by a conflict, waiting to be continued or aborted
+  const [flowFinishState, setFlowFinishState] = useState<FlowFinishState | null>(null);
   const [gitFlowStartModalOpen, setGitFlowStartModalOpen] = useState(false);
   const [gitFlowStartType, setGitFlowStartType] = useState<GitFlowType>('feature');
   const [gitFlowFinishModalOpen, setGitFlowFinishModalOpen] = useState(false);
   const [gitFlowInitModalOpen, setGitFlowInitModalOpen] = useState(false);
+  // Default branch of origin, proposed as the production branch
+  const [remoteDefaultBranch, setRemoteDefaultBranch] = useState<string | null>(null);
 
   // Create Branch modal state (from Branch dropdown)
   const [createBranchModalOpen, setCreateBranchModalOpen] = useState(false);
 
   // Local changes refresh key - increment to force reload
   const [localChangesRefreshKey, setLocalChangesRefreshKey] = useState(0);
+  // Version of each repository from its last refresh call, by path
+  const refreshVersions = useRef<Record<string, number>>({});
 
   // Divergent branches modal state
   const [divergentBranchesModalOpen, setDivergentBranchesModalOpen] = useState(false);
@@ -401,6 +419,7 @@ function App() {
     try {
       const result = await invoke<GitOperationResult>('add_ssh_known_host', {
         host: sshVerification.hostInfo.host,
+        fingerprint: sshVerification.hostInfo.fingerprint,
       });
 
       if (result.success) {
@@ -439,6 +458,25 @@ function App() {
       // Don't checkout if already on this branch
       if (activeTab.currentBranch === branchName) return;
 
+      // Ask before switching when local changes would block the checkout, instead
+      // of waiting for it to fail
+      try {
+        const preflight = await invoke<CheckoutPreflight>('checkout_preflight', {
+          repoPath: activeTab.path,
+          branchName,
+        });
+        if (preflight.will_conflict) {
+          setCheckoutConflictBranch(branchName);
+          setCheckoutConflictFiles(preflight.blocking_files);
+          setCheckoutStashConflicts(preflight.autostash_conflicts);
+          setCheckoutConflictModalOpen(true);
+          return;
+        }
+      } catch (error) {
+        // The checkout below reports the problem
+        console.error('Checkout preflight failed:', error);
+      }
+
       const command = `git checkout ${branchName}`;
       startOperation('Checkout', branchName);
 
@@ -470,6 +508,7 @@ function App() {
           // Show checkout conflict modal with option to stash and switch
           setCheckoutConflictBranch(branchName);
           setCheckoutConflictFiles(result.conflicting_files || []);
+          setCheckoutStashConflicts([]);
           setCheckoutConflictModalOpen(true);
         } else {
           // Check if checkout failed due to uncommitted changes
@@ -511,6 +550,35 @@ function App() {
     ]
   );
 
+  // Switch back to the previously checked-out branch (`git checkout -`)
+  const handleCheckoutPrevious = useCallback(async () => {
+    if (!activeTab?.path || isGitLoading) return;
+
+    try {
+      const previous = await invoke<string | null>('get_previous_branch', {
+        repoPath: activeTab.path,
+      });
+      if (previous) {
+        await handleBranchChange(previous);
+      }
+    } catch (error) {
+      console.error('Error resolving previous branch:', error);
+    }
+  }, [activeTab?.path, isGitLoading, handleBranchChange]);
+
+  // Keyboard shortcut: Cmd/Ctrl+Shift+B switches to the previous branch
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if ((e.metaKey || e.ctrlKey) && e.shiftKey && e.key.toLowerCase() === 'b') {
+        e.preventDefault();
+        handleCheckoutPrevious();
+      }
+    };
+
+    document.addEventListener('keydown', handleKeyDown);
+    return () => document.removeEventListener('keydown', handleKeyDown);
+  }, [handleCheckoutPrevious]);
+
   // Handle checkout with stash (from conflict modal)
   const handleCheckoutWithStash = useCallback(
     async (restoreChanges: boolean) => {
@@ -578,6 +646,7 @@ function App() {
         setCheckoutConflictModalOpen(false);
         setCheckoutConflictBranch('');
         setCheckoutConflictFiles([]);
+        setCheckoutStashConflicts([]);
       }
     },
     [
@@ -1075,17 +1144,23 @@ function App() {
       setGitFlowConfig(config);
 
       if (config.initialized) {
-        const flowInfo = await invoke<CurrentBranchFlowInfo>('get_current_branch_flow_info', {
-          repoPath: activeTab.path,
-        });
+        const [flowInfo, finishState] = await Promise.all([
+          invoke<CurrentBranchFlowInfo>('get_current_branch_flow_info', {
+            repoPath: activeTab.path,
+          }),
+          invoke<FlowFinishState | null>('get_flow_finish_state', { repoPath: activeTab.path }),
+        ]);
         setCurrentBranchFlowInfo(flowInfo);
+        setFlowFinishState(finishState);
       } else {
         setCurrentBranchFlowInfo(null);
+        setFlowFinishState(null);
       }
     } catch (error) {
       console.error('Error loading git flow config:', error);
       setGitFlowConfig(null);
       setCurrentBranchFlowInfo(null);
+      setFlowFinishState(null);
     }
   }, [activeTab?.path]);
 
@@ -1128,9 +1203,20 @@ function App() {
   }, [currentBranchFlowInfo]);
 
   // Handle Git Flow Init (open modal)
-  const handleInitGitFlow = useCallback(() => {
+  const handleInitGitFlow = useCallback(async () => {
     setGitFlowInitModalOpen(true);
-  }, []);
+    if (!activeTab?.path) return;
+    try {
+      const branch = await invoke<string | null>('get_default_branch', {
+        repoPath: activeTab.path,
+        remote: 'origin',
+      });
+      setRemoteDefaultBranch(branch);
+    } catch {
+      // No origin, or it could not be reached
+      setRemoteDefaultBranch(null);
+    }
+  }, [activeTab?.path]);
 
   // Handle Git Flow Init (actual initialization)
   const handleGitFlowInit = useCallback(
@@ -1217,12 +1303,40 @@ function App() {
             await loadGitFlowConfig();
             setGitFlowFinishModalOpen(false);
           },
+          // A conflict leaves the finish to continue or abort
+          onError: loadGitFlowConfig,
         }
       );
     },
     [executeOperation, currentBranchFlowInfo, loadGitFlowConfig]
   );
 
+  const handleContinueFinish = useCallback(async () => {
+    if (!flowFinishState) return;
+    await executeOperation(
+      'git_flow_finish_continue',
+      {},
+      {
+        ...gitFlowFinishContinueOperation(flowFinishState.branch),
+        onSuccess: loadGitFlowConfig,
+        onError: loadGitFlowConfig,
+      }
+    );
+  }, [executeOperation, flowFinishState, loadGitFlowConfig]);
+
+  const handleAbortFinish = useCallback(async () => {
+    if (!flowFinishState) return;
+    await executeOperation(
+      'git_flow_finish_abort',
+      {},
+      {
+        ...gitFlowFinishAbortOperation(flowFinishState.branch),
+        onSuccess: loadGitFlowConfig,
+        onError: loadGitFlowConfig,
+      }
+    );
+  }, [executeOperation, flowFinishState, loadGitFlowConfig]);
+
   // Handle creating branch from dropdown (wrapper for handleCreateBranch)
   const handleCreateBranchFromModal = useCallback(
     async (branchName: string, startPoint: string, checkout: boolean) => {
@@ -1247,8 +1361,9 @@ function App() {
 
       const doFetch = async () => {
         try {
-          const result = await invoke<GitOperationResult>('git_fetch_with_options', {
+          const { result } = await invoke<NetworkResult>('git_fetch_with_options', {
             repoPath: activeTab.path,
+            operationId: crypto.randomUUID(),
             remote: options.all ? null : options.remote,
             all: options.all,
           });
@@ -1323,8 +1438,9 @@ function App() {
 
       const doPull = async () => {
         try {
-          const result = await invoke<GitOperationResult>('git_pull_with_options', {
+          const { result } = await invoke<NetworkResult>('git_pull_with_options', {
             repoPath: activeTab.path,
+            operationId: crypto.randomUUID(),
             remote: options.remote,
             branch: options.branch,
             rebase: options.rebase,
@@ -1415,8 +1531,9 @@ function App() {
 
       const doPush = async () => {
         try {
-          const result = await invoke<GitOperationResult>('git_push_with_options', {
+          const { result } = await invoke<NetworkResult>('git_push_with_options', {
             repoPath: activeTab.path,
+            operationId: crypto.randomUUID(),
             branch: options.branch,
             remote: options.remote,
             remoteBranch: options.remoteBranch,
@@ -1644,16 +1761,31 @@ function App() {
           const matchingTab = state.tabs.find((tab) => tab.path === event.payload.repo_path);
 
           if (matchingTab) {
-            // Check actual file status to determine if there are pending changes
-            // This avoids false positives from .git directory changes (e.g., after fetch)
+            // One call for the status, branches, tags and stashes; when the repository
+            // has not changed (e.g. the event came from an ignored file) nothing else is
+            // reloaded
             try {
-              const result = await invoke<{ unstaged: unknown[]; staged: unknown[] }>(
-                'get_file_status_separated'
-              );
-              const hasChanges = result.unstaged.length > 0 || result.staged.length > 0;
-              setTabHasPendingChanges(matchingTab.id, hasChanges);
+              const result = await invoke<RepoRefresh>('refresh', {
+                repoPath: matchingTab.path,
+                sinceVersion: refreshVersions.current[matchingTab.path] ?? null,
+              });
+              refreshVersions.current[matchingTab.path] = result.version;
+              if (!result.changed) {
+                return;
+              }
+              const unstaged = result.unstaged ?? [];
+              const staged = result.staged ?? [];
+              setTabHasPendingChanges(matchingTab.id, unstaged.length > 0 || staged.length > 0);
+              updateTabState(matchingTab.id, {
+                branches: result.branches ?? [],
+                tags: result.tags ?? [],
+                stashes: result.stashes ?? [],
+              });
+              if (result.info?.current_branch) {
+                setTabCurrentBranch(matchingTab.id, result.info.current_branch);
+              }
             } catch (error) {
-              console.error('Error checking file status:', error);
+              console.error('Error refreshing repository:', error);
             }
 
             // If this is the active tab, also refresh the local changes view
@@ -1672,7 +1804,7 @@ function App() {
         unlisten();
       }
     };
-  }, [setTabHasPendingChanges]);
+  }, [setTabHasPendingChanges, updateTabState, setTabCurrentBranch]);
 
   // Branch watcher: listen for branch change events (when .git/HEAD changes)
   useEffect(() => {
@@ -1704,6 +1836,55 @@ function App() {
     };
   }, [refreshActiveTab]);
 
+  // Operation watcher: merges, rebases and cherry-picks started or ended
+  // outside Forky change the actions available, so refresh right away
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    const setupListener = async () => {
+      unlisten = await listen<OperationChangeEvent>('repo-operation-changed', async (event) => {
+        const state = useRepositoryStore.getState();
+        const matchingTab = state.tabs.find((tab) => tab.path === event.payload.repo_path);
+
+        if (matchingTab && matchingTab.id === state.activeTabId) {
+          await refreshActiveTab();
+          setLocalChangesRefreshKey((k) => k + 1);
+        }
+      });
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [refreshActiveTab]);
+
+  // Verification command run before push: show its output as the push status
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    const setupListener = async () => {
+      unlisten = await listen<VerificationOutputEvent>('verification-output', (event) => {
+        const state = useRepositoryStore.getState();
+        const activeTab = state.tabs.find((tab) => tab.id === state.activeTabId);
+        if (activeTab?.path === event.payload.repo_path) {
+          useGitOperationStore.getState().setStatusMessage(event.payload.line);
+        }
+      });
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+
   // Menu event: listen for "Open Repository" from native menu
   useEffect(() => {
     let unlisten: (() => void) | undefined;
@@ -1723,6 +1904,113 @@ function App() {
     };
   }, [handleOpenRepo]);
 
+  // Menu event: a repository picked from File > Open Recent
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    const setupListener = async () => {
+      unlisten = await listen<string>('menu-open-recent', (event) => {
+        openRepository(event.payload);
+      });
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [openRepository]);
+
+  // Repositories passed on the command line, via "Open with" or by a
+  // forky://open link, including those requested before this listener was registered
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    const openPending = async () => {
+      try {
+        const requests = await invoke<OpenRequest[]>('take_pending_opens');
+        for (const request of requests) {
+          const opened = await openRepository(request.path);
+          const tabId = useRepositoryStore.getState().activeTabId;
+          if (opened && request.commit && tabId) {
+            updateTabState(tabId, { viewMode: 'all-commits', selectedCommitId: request.commit });
+          }
+        }
+      } catch (e) {
+        console.error('Error opening requested repositories:', e);
+      }
+    };
+
+    const setupListener = async () => {
+      unlisten = await listen('open-repositories-requested', () => {
+        openPending();
+      });
+      openPending();
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [openRepository, updateTabState]);
+
+  // Menu event: Repository/Branch menu items (ids from list_available_actions)
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    const setupListener = async () => {
+      unlisten = await listen<string>('menu-git-action', (event) => {
+        switch (event.payload) {
+          case 'git_fetch':
+            openFetchModal();
+            break;
+          case 'git_pull':
+            handleOpenPullModal();
+            break;
+          case 'git_push':
+            handleOpenPushModal();
+            break;
+          case 'git_stash_save':
+            handleOpenSaveStashModal();
+            break;
+          case 'git_create_branch':
+            handleNewBranch();
+            break;
+          case 'open_in_terminal':
+            if (activeTab?.path) {
+              invoke('open_in_terminal', { path: activeTab.path }).catch(console.error);
+            }
+            break;
+        }
+      });
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [
+    activeTab?.path,
+    openFetchModal,
+    handleOpenPullModal,
+    handleOpenPushModal,
+    handleOpenSaveStashModal,
+    handleNewBranch,
+  ]);
+
+  // Keep the native Repository/Branch menus enabled according to the active repository
+  useEffect(() => {
+    invoke('update_menu_state', { repoPath: activeTab?.path ?? null }).catch(console.error);
+  }, [activeTab?.path, activeTab?.currentBranch, activeTab?.hasPendingChanges]);
+
   // Menu event: listen for "About Forky" from native menu
   useEffect(() => {
     let unlisten: (() => void) | undefined;
@@ -1816,6 +2104,7 @@ function App() {
           onOpenRepo={handleOpenRepo}
           repoName={activeTab?.name}
           repoPath={activeTab?.path}
+          readOnly={activeTab?.readOnly}
           currentBranch={activeTab?.currentBranch ?? undefined}
           branches={activeTabState?.branches ?? []}
           stashes={stashes}
@@ -1837,11 +2126,14 @@ function App() {
           onOpenSettings={() => openSettings()}
           gitFlowConfig={gitFlowConfig}
           currentBranchFlowInfo={currentBranchFlowInfo}
+          flowFinishState={flowFinishState}
           onNewBranch={handleNewBranch}
           onStartFeature={handleStartFeature}
           onStartRelease={handleStartRelease}
           onStartHotfix={handleStartHotfix}
           onFinishBranch={handleFinishBranch}
+          onContinueFinish={handleContinueFinish}
+          onAbortFinish={handleAbortFinish}
           onInitGitFlow={handleInitGitFlow}
         />
       </TitleBar>
@@ -2112,10 +2404,12 @@ function App() {
               setCheckoutConflictModalOpen(false);
               setCheckoutConflictBranch('');
               setCheckoutConflictFiles([]);
+              setCheckoutStashConflicts([]);
             }}
             onConfirm={handleCheckoutWithStash}
             targetBranch={checkoutConflictBranch}
             conflictingFiles={checkoutConflictFiles}
+            stashConflicts={checkoutStashConflicts}
             isLoading={isCheckoutWithStashLoading}
           />
         )}
@@ -2189,7 +2483,7 @@ function App() {
             isOpen={true}
             onClose={() => setGitFlowInitModalOpen(false)}
             onInit={handleGitFlowInit}
-            defaultMasterBranch={activeTab?.currentBranch || 'main'}
+            defaultMasterBranch={remoteDefaultBranch || activeTab?.currentBranch || 'main'}
           />
         )}
       </Suspense>
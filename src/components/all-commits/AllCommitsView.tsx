@@ -4,7 +4,14 @@ import { useTranslation } from 'react-i18next';
 import { invoke } from '@tauri-apps/api/core';
 import type { CommitGraphHandle } from '../commit-graph';
 import { CommitGraph } from '../commit-graph';
-import type { CommitInfo, BranchHead, FileStatus, DiffInfo } from '../../types/git';
+import type {
+  CommitInfo,
+  RefDecorations,
+  CommitFiles,
+  DiffStatSummary,
+  FileDiffStat,
+  DiffInfo,
+} from '../../types/git';
 import { Resizer } from '../resizer/Resizer';
 import './AllCommitsView.css';
 
@@ -18,9 +25,10 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
   ({ repoPath, selectedCommitId, onCommitSelect }) => {
     const { t } = useTranslation();
     const [commits, setCommits] = useState<CommitInfo[]>([]);
-    const [branchHeads, setBranchHeads] = useState<BranchHead[]>([]);
-    const [commitFiles, setCommitFiles] = useState<FileStatus[]>([]);
-    const [selectedFile, setSelectedFile] = useState<FileStatus | null>(null);
+    const [decorations, setDecorations] = useState<RefDecorations>({});
+    const [commitFiles, setCommitFiles] = useState<FileDiffStat[]>([]);
+    const [commitSummary, setCommitSummary] = useState<DiffStatSummary | null>(null);
+    const [selectedFile, setSelectedFile] = useState<FileDiffStat | null>(null);
     const [diffInfo, setDiffInfo] = useState<DiffInfo | null>(null);
     const [isLoadingFiles, setIsLoadingFiles] = useState(false);
     const [isLoadingDiff, setIsLoadingDiff] = useState(false);
@@ -52,44 +60,53 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
       [repoPath]
     );
 
-    const loadBranchHeads = useCallback(async () => {
+    const loadDecorations = useCallback(async () => {
       try {
-        const result = await invoke<BranchHead[]>('get_branch_heads', { repoPath });
-        setBranchHeads(result);
+        const result = await invoke<RefDecorations>('get_ref_decorations', { repoPath });
+        setDecorations(result);
       } catch (error) {
-        console.error('Error loading branch heads:', error);
+        console.error('Error loading ref decorations:', error);
       }
     }, [repoPath]);
 
     useEffect(() => {
       loadCommits();
-      loadBranchHeads();
-    }, [loadCommits, loadBranchHeads, repoPath]);
+      loadDecorations();
+    }, [loadCommits, loadDecorations, repoPath]);
 
     const loadMore = useCallback(async () => {
       if (!hasMore) return;
-      const newLimit = commits.length + 100;
+      const last = commits[commits.length - 1];
       try {
-        const result = await invoke<CommitInfo[]>('get_commits', { repoPath, limit: newLimit });
-        setCommits(result);
-        setHasMore(result.length >= newLimit);
+        // Continue after the last loaded commit instead of walking from the start
+        const result = await invoke<CommitInfo[]>('get_commits', {
+          repoPath,
+          limit: 100,
+          cursor: last?.id,
+        });
+        setCommits((prev) => [...prev, ...result]);
+        setHasMore(result.length >= 100);
       } catch (error) {
+        // The history was rewritten under the cursor, start over
         console.error('Error loading more commits:', error);
+        loadCommits(commits.length + 100);
       }
-    }, [repoPath, commits.length, hasMore]);
+    }, [repoPath, commits, hasMore, loadCommits]);
 
     const loadCommitFiles = useCallback(
       async (commit: CommitInfo) => {
         setIsLoadingFiles(true);
         try {
-          const files = await invoke<FileStatus[]>('get_commit_files', {
+          const { files, summary } = await invoke<CommitFiles>('get_commit_files', {
             repoPath,
             commitId: commit.id,
           });
           setCommitFiles(files);
+          setCommitSummary(summary);
         } catch (error) {
           console.error('Error loading commit files:', error);
           setCommitFiles([]);
+          setCommitSummary(null);
         } finally {
           setIsLoadingFiles(false);
         }
@@ -98,7 +115,7 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
     );
 
     const loadDiff = useCallback(
-      async (commit: CommitInfo, file: FileStatus) => {
+      async (commit: CommitInfo, file: FileDiffStat) => {
         setIsLoadingDiff(true);
         try {
           const diff = await invoke<DiffInfo>('get_commit_diff', {
@@ -140,7 +157,7 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
       }
     }, [selectedCommitId]);
 
-    const handleFileSelect = (file: FileStatus) => {
+    const handleFileSelect = (file: FileDiffStat) => {
       setSelectedFile(file);
       if (selectedCommit) {
         loadDiff(selectedCommit, file);
@@ -293,7 +310,7 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
             <CommitGraph
               ref={graphRef}
               commits={commits}
-              branchHeads={branchHeads}
+              decorations={decorations}
               selectedCommitId={selectedCommitId}
               onCommitClick={handleCommitClick}
             />
@@ -332,6 +349,12 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
                 <div className="files-panel" style={{ width: filePanelWidth }}>
                   <div className="files-header">
                     <span className="files-title">{t('commits.changedFiles')}</span>
+                    {commitSummary && (
+                      <span className="files-stat">
+                        <span className="stat-insertions">+{commitSummary.insertions}</span>
+                        <span className="stat-deletions">−{commitSummary.deletions}</span>
+                      </span>
+                    )}
                     <span className="files-count">{commitFiles.length}</span>
                   </div>
                   <div className="files-list">
@@ -356,6 +379,14 @@ export const AllCommitsView: FC<AllCommitsViewProps> = memo(
                               <span className="file-name">{getFileName(file.path)}</span>
                               <span className="file-dir">{getFileDir(file.path)}</span>
                             </span>
+                            {file.binary ? (
+                              <span className="file-stat binary">{t('commits.binaryStat')}</span>
+                            ) : (
+                              <span className="file-stat">
+                                <span className="stat-insertions">+{file.insertions}</span>
+                                <span className="stat-deletions">−{file.deletions}</span>
+                              </span>
+                            )}
                           </div>
                         );
                       })
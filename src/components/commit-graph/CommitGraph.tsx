@@ -8,13 +8,13 @@ import {
   useImperativeHandle,
 } from 'react';
 import { useTranslation } from 'react-i18next';
-import type { CommitInfo, BranchHead } from '../../types/git';
+import type { CommitInfo, RefDecorations } from '../../types/git';
 import { calculateGraphLayout, getLaneColor } from './graphUtils';
 import './CommitGraph.css';
 
 interface CommitGraphProps {
   commits: CommitInfo[];
-  branchHeads: BranchHead[];
+  decorations: RefDecorations;
   selectedCommitId?: string | null;
   onCommitClick?: (commit: CommitInfo) => void;
   onCommitDoubleClick?: (commit: CommitInfo) => void;
@@ -33,7 +33,7 @@ const VISIBLE_BUFFER = 15;
 const LANE_PADDING = 16; // Extra padding after the node
 
 export const CommitGraph = forwardRef<CommitGraphHandle, CommitGraphProps>(
-  ({ commits, branchHeads, selectedCommitId, onCommitClick, onCommitDoubleClick }, ref) => {
+  ({ commits, decorations, selectedCommitId, onCommitClick, onCommitDoubleClick }, ref) => {
     const { t } = useTranslation();
     const scrollRef = useRef<HTMLDivElement>(null);
     const [scrollTop, setScrollTop] = useState(0);
@@ -70,8 +70,8 @@ export const CommitGraph = forwardRef<CommitGraphHandle, CommitGraphProps>(
 
     // Calculate graph layout
     const graphData = useMemo(() => {
-      return calculateGraphLayout(commits, branchHeads);
-    }, [commits, branchHeads]);
+      return calculateGraphLayout(commits, decorations);
+    }, [commits, decorations]);
 
     // Calculate dimensions
     const maxGraphWidth = Math.max(
@@ -257,13 +257,13 @@ export const CommitGraph = forwardRef<CommitGraphHandle, CommitGraphProps>(
                   {/* Graph space - width based on this commit's lane */}
                   <div className="row-graph" style={{ width: rowGraphWidth }} />
 
-                  {/* Branch labels */}
+                  {/* Ref labels */}
                   <div className="row-labels">
                     {node.branchLabels.map((label) => (
                       <span
-                        key={label.name}
-                        className={`branch-label ${label.isHead ? 'is-head' : ''}`}
-                        style={{ backgroundColor: label.color }}
+                        key={`${label.kind}:${label.name}`}
+                        className={`branch-label ${label.kind} ${label.isHead ? 'is-head' : ''}`}
+                        style={label.color ? { backgroundColor: label.color } : undefined}
                       >
                         {label.name}
                       </span>
@@ -1,4 +1,4 @@
-import type { CommitInfo, BranchHead } from '../../types/git';
+import type { CommitInfo, RefDecorations, RefKind } from '../../types/git';
 
 export interface GraphNode {
   commit: CommitInfo;
@@ -18,8 +18,9 @@ export interface ParentConnection {
 
 export interface BranchLabel {
   name: string;
+  kind: RefKind;
   isHead: boolean;
-  color: string;
+  color: string | null; // only local branches get a color, other refs are styled by kind
 }
 
 export interface GraphData {
@@ -40,7 +41,10 @@ const BRANCH_COLORS = [
   '#17a2b8', // teal
 ];
 
-export function calculateGraphLayout(commits: CommitInfo[], branchHeads: BranchHead[]): GraphData {
+export function calculateGraphLayout(
+  commits: CommitInfo[],
+  decorations: RefDecorations
+): GraphData {
   if (commits.length === 0) {
     return { nodes: [], maxLane: 0 };
   }
@@ -52,19 +56,16 @@ export function calculateGraphLayout(commits: CommitInfo[], branchHeads: BranchH
   // Track active lanes (which commit SHA is currently in each lane)
   const activeLanes: (string | null)[] = [];
 
-  // Map branch heads to their commits for labels
-  const branchHeadsMap = new Map<string, BranchHead[]>();
-  branchHeads.forEach((branch) => {
-    const existing = branchHeadsMap.get(branch.commit_sha) || [];
-    existing.push(branch);
-    branchHeadsMap.set(branch.commit_sha, existing);
-  });
-
-  // Assign color to each branch
+  // Assign color to each local branch, in name order
   const branchColors = new Map<string, string>();
-  branchHeads.forEach((branch, index) => {
-    branchColors.set(branch.name, BRANCH_COLORS[index % BRANCH_COLORS.length]);
-  });
+  Object.values(decorations)
+    .flat()
+    .filter((ref) => ref.kind === 'local_branch')
+    .map((ref) => ref.name)
+    .sort()
+    .forEach((name, index) => {
+      branchColors.set(name, BRANCH_COLORS[index % BRANCH_COLORS.length]);
+    });
 
   // First pass: create nodes and assign rows
   commits.forEach((commit, index) => {
@@ -119,12 +120,14 @@ export function calculateGraphLayout(commits: CommitInfo[], branchHeads: BranchH
       }
     }
 
-    // Get branch labels for this commit
-    const commitBranches = branchHeadsMap.get(commit.id) || [];
-    const branchLabels: BranchLabel[] = commitBranches.map((b) => ({
-      name: b.name,
-      isHead: b.is_head,
-      color: branchColors.get(b.name) || BRANCH_COLORS[0],
+    // Get ref labels for this commit
+    const commitRefs = decorations[commit.id] || [];
+    const branchLabels: BranchLabel[] = commitRefs.map((ref) => ({
+      name: ref.name,
+      kind: ref.kind,
+      isHead: ref.is_head,
+      color:
+        ref.kind === 'local_branch' ? branchColors.get(ref.name) || BRANCH_COLORS[0] : null,
     }));
 
     // Calculate the maximum active lane at this row
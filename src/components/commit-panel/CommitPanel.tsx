@@ -1,7 +1,8 @@
 import type { FC } from 'react';
-import { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback, useRef } from 'react';
 import { useTranslation } from 'react-i18next';
-import { Check } from 'lucide-react';
+import { AlertTriangle, Check } from 'lucide-react';
+import type { CommitLintWarning } from '../../types/git';
 import './CommitPanel.css';
 
 interface CommitPanelProps {
@@ -10,6 +11,10 @@ interface CommitPanelProps {
   isLoading: boolean;
   lastCommitMessage?: { subject: string; body: string } | null;
   onAmendChange: (amend: boolean) => void;
+  hookWarnings?: string[]; // hook frameworks that will not run on commit
+  rewriteWarning?: string | null; // the commit to amend is already on a remote
+  subjectPrefix?: string; // ticket reference pre-filled in an empty subject
+  onLintMessage?: (message: string) => Promise<CommitLintWarning[]>;
 }
 
 export const CommitPanel: FC<CommitPanelProps> = ({
@@ -18,6 +23,10 @@ export const CommitPanel: FC<CommitPanelProps> = ({
   isLoading,
   lastCommitMessage,
   onAmendChange,
+  hookWarnings = [],
+  rewriteWarning = null,
+  subjectPrefix = '',
+  onLintMessage,
 }) => {
   const { t } = useTranslation();
   const [subject, setSubject] = useState('');
@@ -31,11 +40,45 @@ export const CommitPanel: FC<CommitPanelProps> = ({
       setDescription(lastCommitMessage.body);
     } else if (!amend) {
       // Clear fields when amend is toggled off
-      setSubject('');
+      setSubject(subjectPrefix);
       setDescription('');
     }
+    // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [amend, lastCommitMessage]);
 
+  // Pre-fill the ticket reference while nothing has been typed yet
+  const previousPrefix = useRef(subjectPrefix);
+  useEffect(() => {
+    setSubject((current) =>
+      current === '' || current === previousPrefix.current ? subjectPrefix : current
+    );
+    previousPrefix.current = subjectPrefix;
+  }, [subjectPrefix]);
+
+  // Lint the message as it is typed, once typing pauses
+  const [lintWarnings, setLintWarnings] = useState<CommitLintWarning[]>([]);
+  useEffect(() => {
+    const message = description.trim() ? `${subject}\n\n${description}` : subject;
+    if (!onLintMessage || !subject.trim()) {
+      setLintWarnings([]);
+      return;
+    }
+    let cancelled = false;
+    const timer = setTimeout(() => {
+      onLintMessage(message)
+        .then((warnings) => {
+          if (!cancelled) setLintWarnings(warnings);
+        })
+        .catch(() => {
+          if (!cancelled) setLintWarnings([]);
+        });
+    }, 400);
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+    };
+  }, [subject, description, onLintMessage]);
+
   const handleAmendChange = useCallback(
     (checked: boolean) => {
       setAmend(checked);
@@ -44,17 +87,20 @@ export const CommitPanel: FC<CommitPanelProps> = ({
     [onAmendChange]
   );
 
+  // The pre-filled ticket reference alone is not a message
+  const hasSubject = subject.trim() !== '' && subject.trim() !== subjectPrefix.trim();
+
   const handleCommit = useCallback(async () => {
-    if (!subject.trim()) return;
+    if (!hasSubject) return;
     if (stagedCount === 0 && !amend) return;
 
     await onCommit(subject.trim(), description.trim(), amend);
 
     // Clear form after successful commit
-    setSubject('');
+    setSubject(subjectPrefix);
     setDescription('');
     setAmend(false);
-  }, [subject, description, amend, stagedCount, onCommit]);
+  }, [subject, hasSubject, description, amend, stagedCount, subjectPrefix, onCommit]);
 
   const handleKeyDown = useCallback(
     (e: React.KeyboardEvent) => {
@@ -67,7 +113,7 @@ export const CommitPanel: FC<CommitPanelProps> = ({
     [handleCommit]
   );
 
-  const isCommitDisabled = !subject.trim() || (stagedCount === 0 && !amend) || isLoading;
+  const isCommitDisabled = !hasSubject || (stagedCount === 0 && !amend) || isLoading;
 
   const buttonText = amend ? t('localChanges.amendLastCommit') : t('localChanges.commit');
 
@@ -93,6 +139,34 @@ export const CommitPanel: FC<CommitPanelProps> = ({
         disabled={isLoading}
         rows={3}
       />
+      {hookWarnings.length > 0 && (
+        <div className="commit-hook-warnings" role="status">
+          <AlertTriangle size={14} />
+          <ul>
+            {hookWarnings.map((warning) => (
+              <li key={warning}>{warning}</li>
+            ))}
+          </ul>
+        </div>
+      )}
+      {amend && rewriteWarning && (
+        <div className="commit-hook-warnings" role="alert">
+          <AlertTriangle size={14} />
+          <ul>
+            <li>{rewriteWarning}</li>
+          </ul>
+        </div>
+      )}
+      {lintWarnings.length > 0 && (
+        <div className="commit-hook-warnings commit-lint-warnings" role="status">
+          <AlertTriangle size={14} />
+          <ul>
+            {lintWarnings.map((warning) => (
+              <li key={`${warning.rule}-${warning.line}`}>{warning.message}</li>
+            ))}
+          </ul>
+        </div>
+      )}
       <div className="commit-footer">
         <label className="commit-amend-label">
           <input
@@ -11,13 +11,22 @@ interface CheckoutConflictModalProps {
   onConfirm: (restoreChanges: boolean) => void;
   targetBranch: string;
   conflictingFiles: string[];
+  stashConflicts?: string[]; // files that would conflict when the stash is restored
   isLoading?: boolean;
 }
 
 export const CheckoutConflictModal: FC<CheckoutConflictModalProps> = memo(
-  ({ isOpen, onClose, onConfirm, targetBranch, conflictingFiles, isLoading = false }) => {
+  ({
+    isOpen,
+    onClose,
+    onConfirm,
+    targetBranch,
+    conflictingFiles,
+    stashConflicts = [],
+    isLoading = false,
+  }) => {
     const { t } = useTranslation();
-    const [restoreChanges, setRestoreChanges] = useState(true);
+    const [restoreChanges, setRestoreChanges] = useState(stashConflicts.length === 0);
 
     const handleConfirm = () => {
       onConfirm(restoreChanges);
@@ -67,6 +76,14 @@ export const CheckoutConflictModal: FC<CheckoutConflictModalProps> = memo(
                   ? t('modals.checkoutConflict.restoreHint')
                   : t('modals.checkoutConflict.noRestoreHint')}
               </p>
+              {stashConflicts.length > 0 && (
+                <p className="option-hint option-hint-warning">
+                  {t('modals.checkoutConflict.restoreWillConflict', {
+                    count: stashConflicts.length,
+                    files: stashConflicts.join(', '),
+                  })}
+                </p>
+              )}
             </div>
           </div>
         </ModalBody>
@@ -1,8 +1,10 @@
 import type { FC } from 'react';
-import { memo } from 'react';
+import { memo, useEffect, useState } from 'react';
 import { useTranslation } from 'react-i18next';
+import { invoke } from '@tauri-apps/api/core';
 import { ShieldAlert } from 'lucide-react';
 import { Modal, ModalHeader, ModalBody, ModalFooter } from '../modal';
+import type { ScannedHostKey } from '../../types/git';
 import './GitModals.css';
 
 export interface SshHostInfo {
@@ -23,9 +25,55 @@ interface SshHostVerificationModalProps {
 export const SshHostVerificationModal: FC<SshHostVerificationModalProps> = memo(
   ({ isOpen, onClose, onAccept, onReject, hostInfo, isLoading = false }) => {
     const { t } = useTranslation();
+    const [scannedKeys, setScannedKeys] = useState<ScannedHostKey[] | null>(null);
+    const [scanError, setScanError] = useState<string | null>(null);
+
+    // Scan the keys the host presents, to check the one seen while connecting
+    // against them and against the fingerprints the host publishes
+    const host = hostInfo?.host;
+    useEffect(() => {
+      if (!host) return;
+      let cancelled = false;
+      setScannedKeys(null);
+      setScanError(null);
+      invoke<ScannedHostKey[]>('scan_ssh_host_keys', { host })
+        .then((keys) => {
+          if (!cancelled) setScannedKeys(keys);
+        })
+        .catch((err) => {
+          if (!cancelled) setScanError(String(err));
+        });
+      return () => {
+        cancelled = true;
+      };
+    }, [host]);
 
     if (!hostInfo) return null;
 
+    const seenKey = scannedKeys?.find((key) => key.fingerprint === hostInfo.fingerprint);
+    // Only a key the host still presents, and that does not contradict the
+    // published fingerprints, can be trusted
+    const canTrust = !!seenKey && seenKey.published !== false;
+
+    let status: { className: string; text: string } | null = null;
+    if (scanError) {
+      status = { className: 'ssh-key-status-error', text: scanError };
+    } else if (!scannedKeys) {
+      status = { className: '', text: t('modals.sshVerification.checking') };
+    } else if (!seenKey) {
+      status = { className: 'ssh-key-status-error', text: t('modals.sshVerification.keyChanged') };
+    } else if (seenKey.published === true) {
+      status = {
+        className: 'ssh-key-status-ok',
+        text: t('modals.sshVerification.matchesPublished', { host: hostInfo.host }),
+      };
+    } else if (seenKey.published === false) {
+      status = {
+        className: 'ssh-key-status-error',
+        text: t('modals.sshVerification.doesNotMatchPublished', { host: hostInfo.host }),
+      };
+    }
+
     const handleReject = () => {
       onReject();
       onClose();
@@ -45,7 +93,20 @@ export const SshHostVerificationModal: FC<SshHostVerificationModalProps> = memo(
                 {t('modals.sshVerification.fingerprint', { keyType: hostInfo.keyType })}
               </div>
               <code className="ssh-fingerprint-value">{hostInfo.fingerprint}</code>
+              {status && <div className={`ssh-key-status ${status.className}`}>{status.text}</div>}
             </div>
+            {scannedKeys && scannedKeys.length > 0 && (
+              <div className="ssh-fingerprint-box">
+                <div className="ssh-fingerprint-label">
+                  {t('modals.sshVerification.scannedKeys')}
+                </div>
+                {scannedKeys.map((key) => (
+                  <code key={key.fingerprint} className="ssh-fingerprint-value">
+                    {key.key_type} {key.fingerprint}
+                  </code>
+                ))}
+              </div>
+            )}
             <p className="ssh-verification-warning">{t('modals.sshVerification.confirmConnect')}</p>
           </div>
         </ModalBody>
@@ -53,7 +114,7 @@ export const SshHostVerificationModal: FC<SshHostVerificationModalProps> = memo(
           <button className="btn-cancel" onClick={handleReject} disabled={isLoading}>
             {t('modals.sshVerification.noCancel')}
           </button>
-          <button className="btn-primary" onClick={onAccept} disabled={isLoading}>
+          <button className="btn-primary" onClick={onAccept} disabled={isLoading || !canTrust}>
             {isLoading ? t('modals.sshVerification.adding') : t('modals.sshVerification.yesTrust')}
           </button>
         </ModalFooter>
@@ -5,10 +5,17 @@ import { invoke } from '@tauri-apps/api/core';
 import type {
   FileStatus,
   FileStatusSeparated,
+  FileDiffStat,
+  FlaggedFile,
   DiffInfo,
   CommitMessage,
+  RewritePreflight,
   DiffHunk,
   HunkData,
+  HookFrameworkStatus,
+  IssueKey,
+  CommitLintWarning,
+  WorkingDiffStat,
 } from '../../types/git';
 import { Resizer } from '../resizer/Resizer';
 import { CommitPanel } from '../commit-panel';
@@ -37,6 +44,9 @@ interface LocalChangesViewProps {
   onRefreshRepository?: () => void;
 }
 
+// Lines of a new file loaded per page (NEW_FILE_PAGE_LINES in the backend)
+const NEW_FILE_PAGE_LINES = 2000;
+
 interface ContextMenuState {
   visible: boolean;
   x: number;
@@ -50,8 +60,14 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
     // Git operation store
     const { startOperation, completeOperation, addLogEntry } = useGitOperationStore();
     const [unstaged, setUnstaged] = useState<FileStatus[]>([]);
+    const [flagged, setFlagged] = useState<FlaggedFile[]>([]);
     const [staged, setStaged] = useState<FileStatus[]>([]);
     const [selectedFile, setSelectedFile] = useState<FileStatus | null>(null);
+    // Line counts by path, for the badges of the file lists
+    const [lineStats, setLineStats] = useState<{
+      staged: Record<string, FileDiffStat>;
+      unstaged: Record<string, FileDiffStat>;
+    }>({ staged: {}, unstaged: {} });
     const [diffInfo, setDiffInfo] = useState<DiffInfo | null>(null);
     const [isLoadingDiff, setIsLoadingDiff] = useState(false);
     const [sidebarWidth, setSidebarWidth] = useState(300);
@@ -78,6 +94,43 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
 
     // Commit panel state
     const [lastCommitMessage, setLastCommitMessage] = useState<CommitMessage | null>(null);
+    const [hookWarnings, setHookWarnings] = useState<string[]>([]);
+    const [rewriteWarning, setRewriteWarning] = useState<string | null>(null);
+
+    // Hook frameworks (husky, pre-commit) that would silently not run from here
+    useEffect(() => {
+      let cancelled = false;
+      invoke<HookFrameworkStatus[]>('get_hook_framework_status', { repoPath })
+        .then((frameworks) => {
+          if (!cancelled) setHookWarnings(frameworks.flatMap((f) => f.warnings));
+        })
+        .catch(() => {
+          if (!cancelled) setHookWarnings([]);
+        });
+      return () => {
+        cancelled = true;
+      };
+    }, [repoPath]);
+    // Ticket reference from the branch name, pre-filled in the commit subject
+    const [issuePrefix, setIssuePrefix] = useState('');
+    useEffect(() => {
+      let cancelled = false;
+      invoke<IssueKey | null>('get_branch_issue_key', { repoPath })
+        .then((issue) => {
+          if (!cancelled) setIssuePrefix(issue?.prefix ?? '');
+        })
+        .catch(() => {
+          if (!cancelled) setIssuePrefix('');
+        });
+      return () => {
+        cancelled = true;
+      };
+    }, [repoPath, refreshKey]);
+    const lintCommitMessage = useCallback(
+      (message: string) =>
+        invoke<CommitLintWarning[]>('lint_commit_message', { repoPath, message }),
+      [repoPath]
+    );
     const [isCommitLoading, setIsCommitLoading] = useState(false);
 
     // Missing Git identity confirmation modal
@@ -102,6 +155,7 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
         const result = await invoke<FileStatusSeparated>('get_file_status_separated', { repoPath });
         setUnstaged(result.unstaged);
         setStaged(result.staged);
+        setFlagged(result.flagged);
 
         // Update the store with file statuses and pending changes indicator
         const { activeTabId } = useRepositoryStore.getState();
@@ -116,6 +170,16 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
       } catch (error) {
         console.error('Error loading file status:', error);
       }
+
+      try {
+        const stats = await invoke<WorkingDiffStat>('get_working_diffstat', { repoPath });
+        const byPath = (files: FileDiffStat[]) =>
+          Object.fromEntries(files.map((file) => [file.path, file]));
+        setLineStats({ staged: byPath(stats.staged), unstaged: byPath(stats.unstaged) });
+      } catch (error) {
+        console.error('Error loading line counts:', error);
+        setLineStats({ staged: {}, unstaged: {} });
+      }
     }, [repoPath, setTabHasPendingChanges, updateTabState]);
 
     useEffect(() => {
@@ -214,11 +278,48 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
       [loadImageContent]
     );
 
+    // Append the next page of a long new file to the diff shown
+    const loadMoreLines = async () => {
+      if (!selectedFile || !diffInfo?.page) return;
+      const { page } = diffInfo;
+      try {
+        const next = await invoke<DiffInfo>('get_working_diff', {
+          repoPath,
+          filePath: selectedFile.path,
+          staged: selectedFile.staged,
+          fileStatus: selectedFile.status,
+          lineRange: { start: page.start + page.count, count: NEW_FILE_PAGE_LINES },
+        });
+        const [hunk] = diffInfo.hunks;
+        const [nextHunk] = next.hunks;
+        if (!hunk || !nextHunk || !next.page) return;
+        const count = page.count + next.page.count;
+        setDiffInfo({
+          ...diffInfo,
+          hunks: [
+            { ...hunk, new_lines: count, lines: [...hunk.lines, ...nextHunk.lines] },
+          ],
+          page: { ...page, count, total_lines: next.page.total_lines },
+        });
+      } catch (error) {
+        console.error('Error loading more lines:', error);
+      }
+    };
+
     const handleFileSelect = (file: FileStatus) => {
       setSelectedFile(file);
       loadDiff(file);
     };
 
+    const handleClearIndexFlags = async (file: FlaggedFile) => {
+      try {
+        await invoke('clear_index_flags', { repoPath, paths: [file.path] });
+        await loadFileStatus();
+      } catch (error) {
+        console.error('Error clearing index flags:', error);
+      }
+    };
+
     const handleStageFile = async (file: FileStatus) => {
       try {
         await invoke('stage_file', { repoPath, filePath: file.path });
@@ -260,9 +361,7 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
 
     const handleUnstageAll = async () => {
       try {
-        for (const file of staged) {
-          await invoke('unstage_file', { repoPath, filePath: file.path });
-        }
+        await invoke('unstage_files', { repoPath, filePaths: staged.map((file) => file.path) });
         await loadFileStatus();
       } catch (error) {
         console.error('Error unstaging all files:', error);
@@ -397,8 +496,19 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
           console.error('Error loading last commit message:', error);
           setLastCommitMessage(null);
         }
+        // Amending a commit that is already pushed needs a force push afterwards
+        try {
+          const preflight = await invoke<RewritePreflight>('rewrite_preflight', {
+            repoPath,
+            operation: { kind: 'amend' },
+          });
+          setRewriteWarning(preflight.warning);
+        } catch {
+          setRewriteWarning(null);
+        }
       } else {
         setLastCommitMessage(null);
+        setRewriteWarning(null);
       }
     }, []);
 
@@ -520,10 +630,14 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
           return { icon: 'A', color: 'var(--accent-green)', label: t('fileStatus.added') };
         case 'modified':
           return { icon: 'M', color: 'var(--accent-yellow)', label: t('fileStatus.modified') };
+        case 'mode_changed':
+          return { icon: 'M', color: 'var(--accent-yellow)', label: t('fileStatus.modeChanged') };
         case 'deleted':
           return { icon: 'D', color: 'var(--accent-red)', label: t('fileStatus.deleted') };
         case 'renamed':
           return { icon: 'R', color: 'var(--accent-blue)', label: t('fileStatus.renamed') };
+        case 'conflicted':
+          return { icon: 'C', color: 'var(--accent-red)', label: t('fileStatus.conflicted') };
         default:
           return { icon: '?', color: 'var(--text-secondary)', label: t('fileStatus.unknown') };
       }
@@ -602,6 +716,7 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
               const { icon, color } = getStatusIcon(file.status);
               const isSelected =
                 selectedFile?.path === file.path && selectedFile?.staged === file.staged;
+              const stat = (isStaged ? lineStats.staged : lineStats.unstaged)[file.path];
               return (
                 <div
                   key={`${file.path}-${file.staged}`}
@@ -612,10 +727,19 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
                   <span className="file-status-icon" style={{ color }}>
                     {icon}
                   </span>
-                  <span className="file-info">
+                  <span
+                    className="file-info"
+                    title={file.old_path ? `${file.old_path} → ${file.path}` : undefined}
+                  >
                     <span className="file-name">{getFileName(file.path)}</span>
                     <span className="file-dir">{getFileDir(file.path)}</span>
                   </span>
+                  {stat && !stat.binary && (
+                    <span className="file-line-stat">
+                      <span className="stat-insertions">+{stat.insertions}</span>
+                      <span className="stat-deletions">−{stat.deletions}</span>
+                    </span>
+                  )}
                   <button
                     className="action-btn"
                     onClick={(e) => {
@@ -634,6 +758,46 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
       </div>
     );
 
+    // Files git no longer compares with the work tree, so their changes are missing above
+    const renderFlaggedFiles = () => (
+      <div className="file-panel flagged-files-panel">
+        <div className="file-panel-header">
+          <span className="panel-title">{t('localChanges.hiddenFromStatus')}</span>
+          <span className="file-count">{flagged.length}</span>
+        </div>
+        <div className="file-list">
+          {flagged.map((file) => (
+            <div key={file.path} className="file-item">
+              <span className="file-info">
+                <span className="file-name">{getFileName(file.path)}</span>
+                <span className="file-dir">
+                  {[
+                    file.assume_unchanged && t('localChanges.assumeUnchanged'),
+                    file.skip_worktree && t('localChanges.skipWorktree'),
+                    file.worktree_status &&
+                      t(
+                        file.worktree_status === 'deleted'
+                          ? 'localChanges.hiddenDeletion'
+                          : 'localChanges.hiddenModification'
+                      ),
+                  ]
+                    .filter(Boolean)
+                    .join(' · ')}
+                </span>
+              </span>
+              <button
+                className="action-btn"
+                onClick={() => handleClearIndexFlags(file)}
+                title={t('localChanges.clearIndexFlags')}
+              >
+                ×
+              </button>
+            </div>
+          ))}
+        </div>
+      </div>
+    );
+
     const renderBinaryViewer = () => {
       if (!diffInfo || !selectedFile) return null;
 
@@ -769,11 +933,24 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
 
       const isStaged = selectedFile.staged;
       const isUntracked = selectedFile.status === 'untracked' || selectedFile.status === 'new';
+      // A page of a new file cannot be staged on its own, and the lines of a converted
+      // or textconv diff no longer match the bytes in the file
+      const hunkActionsAllowed = !diffInfo.page && !diffInfo.encoding && !diffInfo.diff_driver;
 
       return (
         <div className="diff-content">
           <div className="diff-file-header">
             <span className="diff-file-path">{selectedFile.path}</span>
+            {diffInfo.old_mode !== null && diffInfo.new_mode !== null && (
+              <span className="diff-file-mode">
+                {diffInfo.old_mode.toString(8)} → {diffInfo.new_mode.toString(8)}
+              </span>
+            )}
+            {diffInfo.encoding && (
+              <span className="diff-file-encoding" title={t('localChanges.convertedFromEncoding')}>
+                {diffInfo.encoding}
+              </span>
+            )}
             <span className="diff-file-status">{getStatusIcon(selectedFile.status).label}</span>
           </div>
           <div className="diff-hunks">
@@ -788,7 +965,7 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
                   <span className="hunk-info">
                     @@ -{hunk.old_start},{hunk.old_lines} +{hunk.new_start},{hunk.new_lines} @@
                   </span>
-                  {hoveredHunkIndex === hunkIndex && (
+                  {hoveredHunkIndex === hunkIndex && hunkActionsAllowed && (
                     <div className="hunk-actions">
                       {isStaged ? (
                         // Staged file: only show Unstage button
@@ -846,6 +1023,29 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
                 </div>
               </div>
             ))}
+            {diffInfo.page && (
+              <div className="diff-page-footer">
+                <span>
+                  {t('localChanges.showingLines', {
+                    shown: diffInfo.page.start + diffInfo.page.count,
+                    total: diffInfo.page.total_lines,
+                  })}
+                </span>
+                {diffInfo.page.start + diffInfo.page.count < diffInfo.page.total_lines && (
+                  <button className="hunk-action-btn" onClick={loadMoreLines}>
+                    {t('localChanges.loadMoreLines')}
+                  </button>
+                )}
+                {!isStaged && (
+                  <button
+                    className="hunk-action-btn stage"
+                    onClick={() => handleStageFile(selectedFile)}
+                  >
+                    {t('localChanges.stageEntireFile')}
+                  </button>
+                )}
+              </div>
+            )}
           </div>
         </div>
       );
@@ -951,6 +1151,7 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
             handleUnstageFile,
             handleUnstageAll
           )}
+          {flagged.length > 0 && renderFlaggedFiles()}
         </div>
         <Resizer direction="horizontal" onMouseDown={handleResizeStart} isResizing={isResizing} />
         <div className="diff-panel">
@@ -962,6 +1163,10 @@ export const LocalChangesView: FC<LocalChangesViewProps> = memo(
               isLoading={isCommitLoading}
               lastCommitMessage={lastCommitMessage}
               onAmendChange={handleAmendChange}
+              hookWarnings={hookWarnings}
+              rewriteWarning={rewriteWarning}
+              subjectPrefix={issuePrefix}
+              onLintMessage={lintCommitMessage}
             />
           )}
         </div>
@@ -1,6 +1,7 @@
 import type { FC } from 'react';
 import { useState, useRef, useEffect } from 'react';
-import { List, ChevronDown, X } from 'lucide-react';
+import { useTranslation } from 'react-i18next';
+import { List, ChevronDown, X, Lock } from 'lucide-react';
 import type { BranchInfo } from '../../types/git';
 import { BranchSelector } from '../branch-selector';
 import './RepositoryInfoBox.css';
@@ -16,6 +17,7 @@ export interface GitOperationState {
 
 interface RepositoryInfoBoxProps {
   repoName?: string;
+  readOnly?: boolean; // read-only media or no write permission
   currentBranch?: string;
   branches: BranchInfo[];
   onBranchChange: (branchName: string) => void;
@@ -27,12 +29,14 @@ const Spinner = () => <div className="repo-info-spinner" />;
 
 export const RepositoryInfoBox: FC<RepositoryInfoBoxProps> = ({
   repoName,
+  readOnly,
   currentBranch,
   branches,
   onBranchChange,
   gitOperation,
   onDismissOperation,
 }) => {
+  const { t } = useTranslation();
   const [isBranchSelectorOpen, setIsBranchSelectorOpen] = useState(false);
   const containerRef = useRef<HTMLDivElement>(null);
 
@@ -100,7 +104,14 @@ export const RepositoryInfoBox: FC<RepositoryInfoBoxProps> = ({
       {/* Normal state - repo and branch info (vertical layout) */}
       {!showOperation && (
         <div className="repo-info-content">
-          <span className="repo-info-name">{repoName}</span>
+          <span className="repo-info-name">
+            {readOnly && (
+              <span className="repo-info-read-only" title={t('toolbar.readOnlyRepository')}>
+                <Lock size={11} />
+              </span>
+            )}
+            {repoName}
+          </span>
           {currentBranch && (
             <button className="repo-info-branch" onClick={handleBranchClick}>
               <span className="branch-text">{currentBranch}</span>
@@ -2,8 +2,14 @@ import type { FC } from 'react';
 import { useEffect, useState, useMemo } from 'react';
 import { useTranslation } from 'react-i18next';
 import { invoke } from '@tauri-apps/api/core';
-import type { GitIdentity, GitOperationResult } from '../../../types/git';
+import type {
+  GitEnvironment,
+  GitIdentity,
+  GitOperationResult,
+  RepositoryIdentity,
+} from '../../../types/git';
 import { useUIStore } from '../../../stores/uiStore';
+import { useRepositoryStore } from '../../../stores/repositoryStore';
 
 interface GitPanelProps {
   onClose: () => void;
@@ -11,6 +17,36 @@ interface GitPanelProps {
 
 const EMAIL_RE = /^[^\s@]+@[^\s@]+\.[^\s@]+$/;
 
+// One PATH entry per line
+function parsePathEntries(text: string): string[] {
+  return text
+    .split('\n')
+    .map((line) => line.trim())
+    .filter((line) => line.length > 0);
+}
+
+// One NAME=value per line
+function parseVariables(text: string): Record<string, string> {
+  const variables: Record<string, string> = {};
+  for (const line of text.split('\n')) {
+    const trimmed = line.trim();
+    if (!trimmed) continue;
+    const eq = trimmed.indexOf('=');
+    if (eq <= 0) {
+      variables[trimmed] = '';
+    } else {
+      variables[trimmed.slice(0, eq).trim()] = trimmed.slice(eq + 1);
+    }
+  }
+  return variables;
+}
+
+function formatVariables(variables: Record<string, string>): string {
+  return Object.entries(variables)
+    .map(([key, value]) => `${key}=${value}`)
+    .join('\n');
+}
+
 function getInitials(name: string): string {
   const trimmed = name.trim();
   if (!trimmed) return '?';
@@ -31,15 +67,44 @@ export const GitPanel: FC<GitPanelProps> = ({ onClose }) => {
   const [saving, setSaving] = useState(false);
   const [nameError, setNameError] = useState<string | null>(null);
   const [emailError, setEmailError] = useState<string | null>(null);
+  const activeRepoPath = useRepositoryStore((state) => state.getActiveTab()?.path ?? null);
+  const [repoIdentity, setRepoIdentity] = useState<RepositoryIdentity | null>(null);
+  const [pathEntries, setPathEntries] = useState('');
+  const [variables, setVariables] = useState('');
+
+  // The identity commits in the active repository will use, which can differ from
+  // the global one through includeIf (e.g. a work identity for ~/work/)
+  useEffect(() => {
+    if (!activeRepoPath) {
+      setRepoIdentity(null);
+      return;
+    }
+    let cancelled = false;
+    invoke<RepositoryIdentity>('git_get_repository_identity', { repoPath: activeRepoPath })
+      .then((identity) => {
+        if (!cancelled) setRepoIdentity(identity);
+      })
+      .catch(() => {
+        if (!cancelled) setRepoIdentity(null);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [activeRepoPath]);
 
   useEffect(() => {
     let cancelled = false;
     (async () => {
       try {
-        const identity = await invoke<GitIdentity>('git_get_global_identity');
+        const [identity, environment] = await Promise.all([
+          invoke<GitIdentity>('git_get_global_identity'),
+          invoke<GitEnvironment>('get_git_environment'),
+        ]);
         if (cancelled) return;
         setName(identity.name ?? '');
         setEmail(identity.email ?? '');
+        setPathEntries(environment.path_entries.join('\n'));
+        setVariables(formatVariables(environment.variables));
       } catch (err) {
         if (!cancelled) {
           addAlert('error', t('settings.git.loadFailed'), String(err));
@@ -76,6 +141,12 @@ export const GitPanel: FC<GitPanelProps> = ({ onClose }) => {
     if (!validate() || saving) return;
     setSaving(true);
     try {
+      await invoke<GitEnvironment>('set_git_environment', {
+        settings: {
+          path_entries: parsePathEntries(pathEntries),
+          variables: parseVariables(variables),
+        },
+      });
       const result = await invoke<GitOperationResult>('git_set_global_identity', {
         name: name.trim(),
         email: email.trim(),
@@ -137,6 +208,63 @@ export const GitPanel: FC<GitPanelProps> = ({ onClose }) => {
         </div>
       </section>
 
+      {repoIdentity && (
+        <section className="settings-section">
+          <h3 className="settings-section-title">{t('settings.git.repositoryIdentity')}</h3>
+          <dl className="settings-repo-identity">
+            <dt>{t('settings.git.name')}</dt>
+            <dd>
+              {repoIdentity.name ?? t('settings.git.notSet')}
+              {repoIdentity.name_origin && (
+                <span className="settings-repo-identity-origin">
+                  {t('settings.git.configOrigin', { origin: repoIdentity.name_origin })}
+                </span>
+              )}
+            </dd>
+            <dt>{t('settings.git.email')}</dt>
+            <dd>
+              {repoIdentity.email ?? t('settings.git.notSet')}
+              {repoIdentity.email_origin && (
+                <span className="settings-repo-identity-origin">
+                  {t('settings.git.configOrigin', { origin: repoIdentity.email_origin })}
+                </span>
+              )}
+            </dd>
+          </dl>
+        </section>
+      )}
+
+      <section className="settings-section">
+        <h3 className="settings-section-title">{t('settings.git.hookEnvironment')}</h3>
+        <p className="settings-section-description">
+          {t('settings.git.hookEnvironmentDescription')}
+        </p>
+        <div className="settings-field">
+          <label className="settings-field-label">{t('settings.git.pathEntries')}</label>
+          <textarea
+            className="settings-input settings-textarea"
+            placeholder={t('settings.git.pathEntriesPlaceholder')}
+            value={pathEntries}
+            disabled={loading}
+            rows={3}
+            spellCheck={false}
+            onChange={(e) => setPathEntries(e.target.value)}
+          />
+        </div>
+        <div className="settings-field">
+          <label className="settings-field-label">{t('settings.git.variables')}</label>
+          <textarea
+            className="settings-input settings-textarea"
+            placeholder={t('settings.git.variablesPlaceholder')}
+            value={variables}
+            disabled={loading}
+            rows={3}
+            spellCheck={false}
+            onChange={(e) => setVariables(e.target.value)}
+          />
+        </div>
+      </section>
+
       <footer className="settings-panel-footer">
         <button type="button" className="settings-btn-cancel" onClick={onClose}>
           {t('common.cancel')}
@@ -26,13 +26,18 @@ const BranchCounter: FC<BranchCounterProps> = memo(({ branch }) => {
   if (ahead === null || behind === null) return null;
   if (ahead === 0 && behind === 0) return null;
 
+  // A shallow clone misses the fork point, so the counts run down to the cut
+  const prefix = branch.ahead_behind_approximate ? '~' : '';
+  const note = branch.ahead_behind_approximate ? ' (approximate: shallow history)' : '';
+
   return (
     <span className="branch-counters">
       {ahead > 0 && (
         <span
           className="branch-counter ahead"
-          title={`${ahead} commit${ahead !== 1 ? 's' : ''} ahead of upstream`}
+          title={`${ahead} commit${ahead !== 1 ? 's' : ''} ahead of upstream${note}`}
         >
+          {prefix}
           {ahead}
           <ArrowUp size={10} />
         </span>
@@ -40,8 +45,9 @@ const BranchCounter: FC<BranchCounterProps> = memo(({ branch }) => {
       {behind > 0 && (
         <span
           className="branch-counter behind"
-          title={`${behind} commit${behind !== 1 ? 's' : ''} behind upstream`}
+          title={`${behind} commit${behind !== 1 ? 's' : ''} behind upstream${note}`}
         >
+          {prefix}
           {behind}
           <ArrowDown size={10} />
         </span>
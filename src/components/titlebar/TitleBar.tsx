@@ -1,11 +1,13 @@
 import type { FC } from 'react';
 import { useEffect, useState, useCallback } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { X, Minus, Square, Copy } from 'lucide-react';
 import './TitleBar.css';
 
-// Detect macOS using navigator
+// Detect macOS and Windows using navigator
 const isMacOS = navigator.platform.toLowerCase().includes('mac');
+const isWindows = navigator.platform.toLowerCase().includes('win');
 
 interface TitleBarProps {
   children?: React.ReactNode;
@@ -54,6 +56,16 @@ export const TitleBar: FC<TitleBarProps> = ({ children }) => {
     }
   }, [appWindow]);
 
+  // Windows 11 snap layouts, shown when hovering the maximize button
+  const handleMaximizeHover = async () => {
+    if (!isWindows) return;
+    try {
+      await invoke('plugin:decorum|show_snap_overlay');
+    } catch (e) {
+      console.error('Error showing snap layouts:', e);
+    }
+  };
+
   const handleClose = async () => {
     try {
       await appWindow.close();
@@ -98,7 +110,10 @@ export const TitleBar: FC<TitleBarProps> = ({ children }) => {
   );
 
   return (
-    <div className={`titlebar ${isMacOS ? 'macos' : ''}`} onMouseDown={handleMouseDown}>
+    <div
+      className={`titlebar ${isMacOS ? 'macos' : ''} ${isWindows ? 'windows' : ''}`}
+      onMouseDown={handleMouseDown}
+    >
       {/* On macOS, native window controls are used via titleBarStyle: overlay */}
       {!isMacOS && (
         <div className="titlebar-controls">
@@ -111,6 +126,7 @@ export const TitleBar: FC<TitleBarProps> = ({ children }) => {
           <button
             className="titlebar-btn maximize"
             onClick={handleMaximize}
+            onMouseEnter={handleMaximizeHover}
             title={isMaximized ? 'Restore' : 'Maximize'}
           >
             {isMaximized ? (
@@ -1,18 +1,30 @@
 import type { FC } from 'react';
 import { useEffect, useRef, memo } from 'react';
 import { useTranslation } from 'react-i18next';
-import { GitBranch, Plus, Play, Flag, AlertTriangle, CheckCircle } from 'lucide-react';
-import type { GitFlowConfig, CurrentBranchFlowInfo } from '../../types/git';
+import {
+  GitBranch,
+  Plus,
+  Play,
+  Flag,
+  AlertTriangle,
+  CheckCircle,
+  RotateCcw,
+  XCircle,
+} from 'lucide-react';
+import type { GitFlowConfig, CurrentBranchFlowInfo, FlowFinishState } from '../../types/git';
 import './BranchDropdown.css';
 
 interface BranchDropdownProps {
   gitFlowConfig: GitFlowConfig | null;
   currentBranchFlowInfo: CurrentBranchFlowInfo | null;
+  flowFinishState: FlowFinishState | null;
   onNewBranch: () => void;
   onStartFeature: () => void;
   onStartRelease: () => void;
   onStartHotfix: () => void;
   onFinishBranch: () => void;
+  onContinueFinish: () => void;
+  onAbortFinish: () => void;
   onClose: () => void;
 }
 
@@ -20,11 +32,14 @@ export const BranchDropdown: FC<BranchDropdownProps> = memo(
   ({
     gitFlowConfig,
     currentBranchFlowInfo,
+    flowFinishState,
     onNewBranch,
     onStartFeature,
     onStartRelease,
     onStartHotfix,
     onFinishBranch,
+    onContinueFinish,
+    onAbortFinish,
     onClose,
   }) => {
     const { t } = useTranslation();
@@ -131,8 +146,37 @@ export const BranchDropdown: FC<BranchDropdownProps> = memo(
               <span>{t('branchDropdown.startHotfix')}</span>
             </div>
 
+            {/* Interrupted finish - continue once the conflicts are resolved, or undo it */}
+            {flowFinishState && (
+              <>
+                <div className="branch-dropdown-separator-small" />
+                <div
+                  className="branch-dropdown-item finish"
+                  onMouseDown={(e) => {
+                    e.stopPropagation();
+                    handleItemClick(onContinueFinish);
+                  }}
+                >
+                  <RotateCcw size={14} className="branch-dropdown-icon" />
+                  <span>
+                    {t('branchDropdown.continueFinish', { branch: flowFinishState.branch })}
+                  </span>
+                </div>
+                <div
+                  className="branch-dropdown-item"
+                  onMouseDown={(e) => {
+                    e.stopPropagation();
+                    handleItemClick(onAbortFinish);
+                  }}
+                >
+                  <XCircle size={14} className="branch-dropdown-icon" />
+                  <span>{t('branchDropdown.abortFinish', { branch: flowFinishState.branch })}</span>
+                </div>
+              </>
+            )}
+
             {/* Finish Branch - only show if on a git flow branch */}
-            {canFinish && (
+            {canFinish && !flowFinishState && (
               <>
                 <div className="branch-dropdown-separator-small" />
                 <div
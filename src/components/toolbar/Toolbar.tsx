@@ -31,13 +31,20 @@ import { RepositoryInfoBox } from '../repository-info-box';
 import { StashDropdown } from './StashDropdown';
 import { MergeDropdown } from './MergeDropdown';
 import { BranchDropdown } from './BranchDropdown';
-import type { BranchInfo, StashInfo, GitFlowConfig, CurrentBranchFlowInfo } from '../../types/git';
+import type {
+  BranchInfo,
+  StashInfo,
+  GitFlowConfig,
+  CurrentBranchFlowInfo,
+  FlowFinishState,
+} from '../../types/git';
 import './Toolbar.css';
 
 interface ToolbarProps {
   onOpenRepo: () => void;
   repoName?: string;
   repoPath?: string;
+  readOnly?: boolean;
   currentBranch?: string;
   branches?: BranchInfo[];
   stashes?: StashInfo[];
@@ -54,11 +61,14 @@ interface ToolbarProps {
   // Branch dropdown props
   gitFlowConfig?: GitFlowConfig | null;
   currentBranchFlowInfo?: CurrentBranchFlowInfo | null;
+  flowFinishState?: FlowFinishState | null;
   onNewBranch?: () => void;
   onStartFeature?: () => void;
   onStartRelease?: () => void;
   onStartHotfix?: () => void;
   onFinishBranch?: () => void;
+  onContinueFinish?: () => void;
+  onAbortFinish?: () => void;
   onInitGitFlow?: () => void;
   isLoading?: boolean;
   gitOperation?: GitOperationState | null;
@@ -75,6 +85,7 @@ export const Toolbar: FC<ToolbarProps> = memo(
     onOpenRepo,
     repoName,
     repoPath,
+    readOnly,
     currentBranch,
     branches = [],
     stashes = [],
@@ -90,11 +101,14 @@ export const Toolbar: FC<ToolbarProps> = memo(
     onMergeSelect,
     gitFlowConfig,
     currentBranchFlowInfo,
+    flowFinishState,
     onNewBranch,
     onStartFeature,
     onStartRelease,
     onStartHotfix,
     onFinishBranch,
+    onContinueFinish,
+    onAbortFinish,
     onInitGitFlow,
     isLoading = false,
     gitOperation,
@@ -213,6 +227,7 @@ export const Toolbar: FC<ToolbarProps> = memo(
         <div className="toolbar-center">
           <RepositoryInfoBox
             repoName={repoName}
+            readOnly={readOnly}
             currentBranch={currentBranch}
             branches={branches}
             onBranchChange={onBranchChange || (() => {})}
@@ -237,6 +252,7 @@ export const Toolbar: FC<ToolbarProps> = memo(
               <BranchDropdown
                 gitFlowConfig={gitFlowConfig ?? null}
                 currentBranchFlowInfo={currentBranchFlowInfo ?? null}
+                flowFinishState={flowFinishState ?? null}
                 onNewBranch={() => {
                   onNewBranch?.();
                   setBranchDropdownOpen(false);
@@ -257,6 +273,14 @@ export const Toolbar: FC<ToolbarProps> = memo(
                   onFinishBranch?.();
                   setBranchDropdownOpen(false);
                 }}
+                onContinueFinish={() => {
+                  onContinueFinish?.();
+                  setBranchDropdownOpen(false);
+                }}
+                onAbortFinish={() => {
+                  onAbortFinish?.();
+                  setBranchDropdownOpen(false);
+                }}
                 onClose={() => setBranchDropdownOpen(false)}
               />
             )}
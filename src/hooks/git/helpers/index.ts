@@ -23,5 +23,7 @@ export {
   gitFlowInitOperation,
   gitFlowStartOperation,
   gitFlowFinishOperation,
+  gitFlowFinishContinueOperation,
+  gitFlowFinishAbortOperation,
   createTagOperation,
 } from './workflowHelpers';
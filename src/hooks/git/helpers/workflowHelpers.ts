@@ -121,6 +121,30 @@ export const gitFlowFinishOperation = (
   errorAlertKey: 'alerts.gitFlowFinishFailed',
 });
 
+/**
+ * Creates config for resuming a Git Flow finish stopped by a conflict
+ */
+export const gitFlowFinishContinueOperation = (branch: string): GitOperationConfig => ({
+  operationType: 'Merge',
+  operationTarget: `Finish '${branch}'`,
+  logDescription: `Continue finishing '${branch}'`,
+  command: 'git commit --no-edit',
+  successAlertKey: 'alerts.gitFlowFinishSuccess',
+  errorAlertKey: 'alerts.gitFlowFinishFailed',
+});
+
+/**
+ * Creates config for abandoning a Git Flow finish stopped by a conflict
+ */
+export const gitFlowFinishAbortOperation = (branch: string): GitOperationConfig => ({
+  operationType: 'Merge',
+  operationTarget: `Finish '${branch}'`,
+  logDescription: `Abort finishing '${branch}'`,
+  command: 'git merge --abort',
+  successAlertKey: 'alerts.gitFlowFinishAborted',
+  errorAlertKey: 'alerts.gitFlowFinishFailed',
+});
+
 /**
  * Creates config for creating a tag
  */
@@ -137,6 +137,7 @@ export function useRepositoryTabs() {
           validTabs.push({
             ...tab,
             currentBranch: info.current_branch,
+            readOnly: info.read_only,
           });
           // Pass persisted tab data when loading
           const tabData = persistedState.tabData[tab.id];
@@ -182,12 +183,20 @@ export function useRepositoryTabs() {
 
         const info = await invoke<RepositoryInfo>('open_repository', { path });
 
+        // A subfolder resolves to its repository root, which may already be open
+        const existingRoot = tabs.find((t) => t.path === info.path);
+        if (existingRoot) {
+          setActiveTabId(existingRoot.id);
+          return true;
+        }
+
         const newTab: RepositoryTab = {
           id: `${Date.now()}-${Math.random().toString(36).substr(2, 9)}`,
           path: info.path,
           name: info.name,
           currentBranch: info.current_branch,
           hasPendingChanges: false,
+          readOnly: info.read_only,
         };
 
         setTabs((prev) => [...prev, newTab]);
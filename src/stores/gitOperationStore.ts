@@ -26,6 +26,7 @@ interface GitOperationStore {
   // Actions
   startOperation: (type: OperationType, target?: string) => void;
   completeOperation: (result: GitOperationResult) => void;
+  setStatusMessage: (message: string) => void;
   clearOperation: () => void;
   addLogEntry: (
     repoPath: string,
@@ -101,6 +102,14 @@ export const useGitOperationStore = create<GitOperationStore>()(
         }
       },
 
+      // Replace the status line of the running operation (e.g. output of the
+      // verification command run before a push)
+      setStatusMessage: (message) => {
+        const current = get().currentOperation;
+        if (!current?.isActive) return;
+        set({ currentOperation: { ...current, statusMessage: message } });
+      },
+
       clearOperation: () => {
         if (dismissTimeout) {
           clearTimeout(dismissTimeout);
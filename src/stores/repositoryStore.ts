@@ -97,12 +97,20 @@ export const useRepositoryStore = create<RepositoryStore>()(
           try {
             const info = await invoke<RepositoryInfo>('open_repository', { path });
 
+            // A subfolder resolves to its repository root, which may already be open
+            const existingRoot = get().tabs.find((t) => t.path === info.path);
+            if (existingRoot) {
+              await get().selectTab(existingRoot.id);
+              return true;
+            }
+
             const newTab: RepositoryTab = {
               id: `${Date.now()}-${Math.random().toString(36).substr(2, 9)}`,
               path: info.path,
               name: info.name,
               currentBranch: info.current_branch,
               hasPendingChanges: false,
+              readOnly: info.read_only,
             };
 
             set((state) => ({
@@ -360,6 +368,7 @@ export const useRepositoryStore = create<RepositoryStore>()(
                 ...tab,
                 currentBranch: info.current_branch,
                 hasPendingChanges: false,
+                readOnly: info.read_only,
               });
 
               // Get persisted tab data if available
@@ -8,6 +8,46 @@ export interface CommitInfo {
   parent_ids: string[];
 }
 
+// Part of the history get_commits walks; omitted fields mean all local branches and paths
+export interface CommitScope {
+  reference?: string; // branch, tag or commit to walk instead of the local branches
+  paths?: string[]; // pathspecs; only commits touching them (e.g. a monorepo package)
+}
+
+// Optional filters of get_commits; omitted fields match everything
+export interface CommitFilter {
+  message?: string; // substring of the message, case-insensitive
+  author?: string; // substring of the author name or email, case-insensitive
+  since?: number; // unix timestamp (seconds) of the commit time, inclusive
+  until?: number; // unix timestamp (seconds) of the commit time, inclusive
+}
+
+// Result of get_commit_count
+export interface CommitCount {
+  count: number;
+  is_estimate: boolean; // counting stopped early; there are at least `count`
+  history_limited: boolean; // the history reaches a shallow or grafted cut; older commits are missing
+}
+
+// Commit found by search_commits_by_content (git log -S / -G)
+export interface ContentSearchMatch {
+  commit: CommitInfo;
+  files: string[]; // files where the term was added or removed
+}
+
+export interface HistoryTip {
+  name: string;
+  commit_sha: string;
+  position: number | null;
+}
+
+export interface HistoryStats {
+  total_count: number;
+  is_estimate: boolean; // "at least total_count" commits
+  history_limited: boolean; // the history reaches a shallow or grafted cut
+  tips: HistoryTip[];
+}
+
 export interface BranchInfo {
   name: string;
   is_head: boolean;
@@ -15,6 +55,7 @@ export interface BranchInfo {
   upstream: string | null;
   ahead: number | null;
   behind: number | null;
+  ahead_behind_approximate: boolean; // counted in a shallow history that misses the fork point
 }
 
 export interface BranchHead {
@@ -23,11 +64,118 @@ export interface BranchHead {
   is_head: boolean;
 }
 
+// Lines added and removed in one file of a commit
+export interface FileDiffStat {
+  path: string;
+  status: string;
+  insertions: number;
+  deletions: number;
+  binary: boolean; // no line counts for binary files
+}
+
+export interface DiffStatSummary {
+  files_changed: number;
+  insertions: number;
+  deletions: number;
+}
+
+// Files changed in a commit (get_commit_files)
+export interface CommitFiles {
+  files: FileDiffStat[];
+  summary: DiffStatSummary;
+}
+
+// Line counts of the staged and unstaged changes (get_working_diffstat)
+export interface WorkingDiffStat {
+  staged: FileDiffStat[];
+  unstaged: FileDiffStat[]; // untracked files count all their lines as added
+  staged_summary: DiffStatSummary;
+  unstaged_summary: DiffStatSummary;
+}
+
+export interface WorkingPatch {
+  summary: DiffStatSummary;
+  path: string | null; // file the patch was written to
+  patch: string | null; // the patch text when it was not written to a file
+}
+
+export interface RejectedFile {
+  path: string;
+  hunks: number[]; // 1-based numbers of the hunks left in `<path>.rej`
+}
+
+export interface PatchApplyResult {
+  success: boolean; // every file applied without conflicts or rejects
+  message: string;
+  applied_files: string[];
+  conflicted_files: string[]; // merged with conflict markers (three-way)
+  rejected_files: RejectedFile[];
+}
+
+export type HistoryFormat = 'dot' | 'json' | 'svg';
+
+export interface HistoryExport {
+  format: HistoryFormat;
+  commit_count: number;
+  truncated: boolean;
+  path: string | null; // file the export was written to
+  content: string | null; // the export itself when it was not written to a file
+}
+
+// How get_refs_diff compares two refs: the two trees (base..target), or the
+// merge base against target (base...target), as a pull request shows it
+export type RefsDiffMode = 'two_dot' | 'three_dot';
+
+// Files that differ between two branches or commits (get_refs_diff)
+export interface RefsDiff {
+  base_sha: string;
+  target_sha: string;
+  merge_base: string | null; // the commit compared against target in three_dot mode
+  files: FileStatus[]; // status of each file in target relative to the base
+}
+
+export type RefKind = 'head' | 'local_branch' | 'remote_branch' | 'tag' | 'stash';
+
+export interface RefDecoration {
+  name: string; // short name: "main", "origin/main", "v1.0", "stash@{0}"
+  kind: RefKind;
+  is_head: boolean;
+}
+
+// Refs pointing at each commit, keyed by commit SHA (get_ref_decorations)
+export type RefDecorations = Record<string, RefDecoration[]>;
+
 export interface TagInfo {
   name: string;
   commit_sha: string;
 }
 
+export interface BranchCommitSet {
+  branch: string;
+  base: string | null;
+  commits: string[];
+}
+
+export interface RefContainment {
+  commit_sha: string;
+  local_branches: string[];
+  remote_branches: string[];
+  tags: string[];
+}
+
+export interface RecentBranch {
+  name: string;
+  last_checkout: number; // unix timestamp
+}
+
+export interface RefMatch {
+  name: string;
+  ref_type: 'local' | 'remote' | 'tag';
+  commit_sha: string;
+  score: number;
+  matched_indices: number[];
+}
+
 export interface StashInfo {
   index: number;
   id: string;
@@ -38,8 +186,10 @@ export interface StashInfo {
 
 export interface FileStatus {
   path: string;
-  status: string;
+  status: string; // "mode_changed" when only the mode differs, "conflicted" when unmerged
   staged: boolean;
+  conflict: ConflictKind | null; // set when conflicted: which side modified or deleted it
+  old_path: string | null; // path before a rename, set when status is "renamed"
 }
 
 export interface RepositoryInfo {
@@ -48,6 +198,24 @@ export interface RepositoryInfo {
   current_branch: string | null;
   is_bare: boolean;
   is_empty: boolean;
+  read_only: boolean; // read-only media or no write permission; changes are refused
+}
+
+export interface RepositoryState {
+  state: string; // "clean", "merge", "rebase", "rebase_interactive", "cherry_pick", ...
+  head: string | null;
+  orig_head: string | null;
+  merge_heads: string[];
+  rebase_head: string | null;
+  rebase_onto: string | null;
+  cherry_pick_head: string | null;
+  revert_head: string | null;
+  rebase_branch: string | null; // branch being rebased, null when it was detached
+  rebase_step: number | null; // 1-based number of the commit being applied
+  rebase_total: number | null;
+  sequence_remaining: number | null; // commits left, the current one included, in a cherry-pick or revert sequence
+  bisect_bad: string | null;
+  bisect_good: string[];
 }
 
 export interface DiffHunk {
@@ -65,6 +233,11 @@ export interface HunkData {
   new_start: number;
   new_lines: number;
   lines: HunkLineData[];
+  // Renames and mode changes (e.g. 0o100755); omit when unchanged
+  old_path?: string;
+  new_path?: string;
+  old_mode?: number;
+  new_mode?: number;
 }
 
 export interface HunkLineData {
@@ -72,11 +245,18 @@ export interface HunkLineData {
   line_type: string;
 }
 
+export interface HunkPatchPreview {
+  patch: string;
+  can_apply: boolean;
+  check_output: string;
+}
+
 export interface DiffLine {
   content: string;
   line_type: 'add' | 'delete' | 'context';
   old_line_no: number | null;
   new_line_no: number | null;
+  eol_only: boolean; // changed only in line endings / trailing whitespace
 }
 
 export interface DiffInfo {
@@ -87,6 +267,41 @@ export interface DiffInfo {
   is_binary: boolean;
   binary_type: string | null; // "image", "pdf", "other"
   file_size: number | null;
+  page: DiffPage | null; // set when only some lines of a new file are included
+  encoding: string | null; // set when the text was converted to UTF-8 from this encoding
+  old_mode: number | null; // set when the file mode changed, e.g. 0o100644
+  new_mode: number | null;
+  old_path: string | null; // path before a rename, set for renamed files
+}
+
+// Lines of a new file requested from get_working_diff (0-based start)
+export interface LineRange {
+  start: number;
+  count: number;
+}
+
+// Part of a new file a paged DiffInfo holds
+export interface DiffPage {
+  start: number;
+  count: number;
+  total_lines: number;
+}
+
+// One version of a file in a FileThreeWay
+export interface FileVersion {
+  exists: boolean;
+  content: string | null; // null when the file is missing or binary
+  is_binary: boolean;
+  size: number | null;
+}
+
+// HEAD, index and working tree versions of a file (get_file_three_way)
+export interface FileThreeWay {
+  file_path: string;
+  head: FileVersion;
+  index: FileVersion;
+  workdir: FileVersion;
+  partially_staged: boolean; // the index differs from both HEAD and the working tree
 }
 
 export interface RepositoryTab {
@@ -95,11 +310,94 @@ export interface RepositoryTab {
   name: string;
   currentBranch: string | null;
   hasPendingChanges: boolean;
+  readOnly?: boolean; // RepositoryInfo.read_only when the tab was opened
+}
+
+export type IndexFlag = 'assume_unchanged' | 'skip_worktree';
+
+// Index entry git no longer compares with the work tree
+export interface FlaggedFile {
+  path: string;
+  assume_unchanged: boolean;
+  skip_worktree: boolean;
+  worktree_status: 'modified' | 'deleted' | null; // the change the status is not showing
 }
 
 export interface FileStatusSeparated {
   unstaged: FileStatus[];
   staged: FileStatus[];
+  flagged: FlaggedFile[];
+}
+
+export interface StatusCounts {
+  total: number;
+  added: number; // new and untracked files
+  modified: number; // content or mode changes
+  deleted: number;
+  renamed: number;
+  conflicted: number;
+}
+
+// A directory of get_file_status_tree; counts cover every file below it
+export interface StatusTreeNode {
+  name: string; // "" for the root; "src/git" for compacted directories
+  path: string; // from the repository root, "" for the root
+  directories: StatusTreeNode[];
+  files: FileStatus[]; // directly in this directory
+  counts: StatusCounts;
+}
+
+export interface FileStatusTree {
+  unstaged: StatusTreeNode;
+  staged: StatusTreeNode;
+}
+
+// Everything the main window shows, from refresh; only `version` and
+// `changed: false` when the version is still the one passed in
+export interface RepoRefresh {
+  version: number;
+  changed: boolean;
+  info: RepositoryInfo | null;
+  branches: BranchInfo[] | null;
+  tags: TagInfo[] | null;
+  stashes: StashInfo[] | null;
+  unstaged: FileStatus[] | null;
+  staged: FileStatus[] | null;
+  flagged: FlaggedFile[] | null;
+}
+
+export type ConflictKind =
+  | 'both_modified'
+  | 'both_added'
+  | 'deleted_by_us'
+  | 'deleted_by_them'
+  | 'added_by_us'
+  | 'added_by_them'
+  | 'both_deleted';
+
+// During a rebase 'ours' is the branch being rebased onto
+export type ConflictSide = 'ours' | 'theirs';
+
+export interface ConflictStage {
+  id: string; // blob id
+  mode: number;
+}
+
+export interface ConflictedFile {
+  path: string;
+  kind: ConflictKind;
+  ancestor: ConflictStage | null; // stage 1
+  ours: ConflictStage | null; // stage 2
+  theirs: ConflictStage | null; // stage 3
+}
+
+export interface ConflictVersions {
+  file_path: string;
+  kind: ConflictKind;
+  ancestor: FileVersion;
+  ours: FileVersion;
+  theirs: FileVersion;
+  merged: FileVersion; // the working tree file, with conflict markers
 }
 
 export type ViewMode = 'local-changes' | 'all-commits';
@@ -286,6 +584,64 @@ export interface GitFlowConfig {
   version_tag_prefix: string;
 }
 
+export type GitFlowIssueKind =
+  | 'missing_master_branch'
+  | 'missing_develop_branch'
+  | 'same_master_and_develop'
+  | 'empty_prefix'
+  | 'prefix_collision';
+
+// A git flow finish interrupted by a conflict (get_flow_finish_state), to be
+// continued with git_flow_finish_continue or undone with git_flow_finish_abort
+export interface FlowFinishState {
+  flow_type: string; // "feature", "release" or "hotfix"
+  name: string; // without prefix; also the tag of a release or hotfix
+  branch: string; // e.g. "hotfix/1.2.1"
+  targets: string[]; // branches it is merged into, in order
+  original_tips: (string | null)[]; // commit of each target before the finish
+  merged: number; // targets merged so far
+  create_tag: boolean;
+  delete_branch: boolean;
+  master_branch: string;
+  develop_branch: string;
+  start_branch: string | null; // checked out when the finish started
+  messages: string[]; // steps done so far
+}
+
+// A branch of a stack, from get_branch_stack (base first, parents before children)
+export interface StackBranch {
+  name: string;
+  parent: string | null; // null for the base of the stack
+  linked_by: 'upstream' | 'merge_base' | null;
+  commit: string;
+  ahead: number; // commits not in the parent
+  needs_restack: boolean; // the parent has commits the branch is not based on
+}
+
+export interface RestackStep {
+  branch: string;
+  onto: string; // parent branch it is rebased onto
+  base: string; // commit the branch was built on, excluded from the rebase
+  original_tip: string; // commit of the branch before the restack
+}
+
+// A restack interrupted by conflicts, to continue or abort
+export interface RestackState {
+  branch: string; // branch whose stack is restacked
+  steps: RestackStep[]; // parents first
+  done: number; // steps finished so far
+  start_branch: string | null; // checked out when the restack started
+  messages: string[]; // steps done so far
+}
+
+// A problem found by validate_gitflow_config
+export interface GitFlowIssue {
+  kind: GitFlowIssueKind;
+  message: string;
+  key: string; // gitflow.* key at fault
+  suggested_value: string | null; // what repair_gitflow_config writes; null when it cannot be fixed automatically
+}
+
 export type GitFlowBranchType = 'Feature' | 'Release' | 'Hotfix' | 'Master' | 'Develop' | 'Other';
 
 export interface CurrentBranchFlowInfo {
@@ -294,3 +650,424 @@ export interface CurrentBranchFlowInfo {
 }
 
 export type GitFlowType = 'feature' | 'release' | 'hotfix';
+
+export interface RepositorySummary {
+  path: string;
+  name: string;
+  branch: string | null;
+  upstream: string | null;
+  changed_files: number;
+  conflicted_files: number;
+  ahead: number | null;
+  behind: number | null;
+  ahead_behind_approximate: boolean;
+  state: string;
+  error: string | null;
+}
+
+// Payload of the `files-dropped` event
+export interface DroppedPaths {
+  patches: string[];
+  repositories: RepositoryInfo[];
+  rejected: string[];
+}
+
+// Command palette
+export interface AvailableAction {
+  id: string; // Tauri command that performs the action
+  label: string;
+  category: string;
+  enabled: boolean;
+  reason: string | null;
+}
+
+// Clone types
+export interface GitProgress {
+  stage:
+    | 'counting'
+    | 'compressing'
+    | 'receiving'
+    | 'resolving'
+    | 'checkout'
+    | 'writing'
+    | 'cherry_pick';
+  percent: number | null;
+  current: number | null;
+  total: number | null;
+  bytes: number | null;
+}
+
+// Payload of the `git-progress` event (fetch, pull, push, cherry-pick of a range)
+export interface GitProgressEvent extends GitProgress {
+  operation_id: string;
+  operation: 'fetch' | 'pull' | 'push' | 'cherry_pick';
+  repo_path: string;
+}
+
+// Command run in the work tree before push (forky.verifyCommand)
+export interface VerificationSettings {
+  command: string;
+  before_push: boolean;
+}
+
+export interface VerificationResult {
+  command: string;
+  success: boolean;
+  exit_code: number | null; // null when killed by a signal
+  output: string; // last lines of the output
+}
+
+// Payload of the `verification-output` event, one per line printed
+export interface VerificationOutputEvent {
+  operation_id: string;
+  repo_path: string;
+  line: string;
+}
+
+// Payload of the `repo-operation-changed` event, sent when a merge, rebase,
+// cherry-pick, revert or bisect starts, advances or ends
+export interface OperationChangeEvent {
+  repo_path: string;
+  timestamp: number;
+  state: RepositoryState;
+}
+
+// A reference moved by git_fetch / git_pull / git_push or a recorded operation
+// (null id: created or deleted)
+export interface RefUpdate {
+  name: string;
+  old_id: string | null;
+  new_id: string | null;
+}
+
+// Operation performed through Forky, from get_operation_history
+export type OperationKind =
+  | 'commit'
+  | 'merge'
+  | 'rebase'
+  | 'delete_branch'
+  | 'cherry_pick'
+  | 'revert'
+  | 'reset'
+  | 'stash_drop';
+
+export interface DroppedStash {
+  id: string;
+  message: string;
+}
+
+export interface OperationRecord {
+  kind: OperationKind;
+  summary: string;
+  timestamp: number; // unix timestamp (seconds)
+  head: string | null; // commit HEAD pointed to afterwards
+  refs: RefUpdate[]; // local branches (and a detached HEAD) it moved
+  head_ref: string | null; // branch HEAD was on before, null when detached
+  index_tree: string | null; // tree of the index before, null when it had conflicts
+  stash: DroppedStash | null;
+  undone: boolean; // put back by undo_last_operation
+}
+
+export interface NetworkResult {
+  result: GitOperationResult;
+  updated_refs: RefUpdate[];
+}
+
+export interface CloneProgressEvent extends GitProgress {
+  url: string;
+  path: string;
+}
+
+export interface CloneResult {
+  result: GitOperationResult;
+  repository: RepositoryInfo | null;
+}
+
+// Recent repositories (pinned first, then most recently opened)
+export interface RecentRepository {
+  path: string;
+  name: string;
+  last_opened: number; // unix timestamp (seconds)
+  pinned: boolean;
+}
+
+// Workspace types
+export interface Workspace {
+  name: string;
+  repositories: string[];
+}
+
+export interface WorkspaceRepoResult {
+  repo_path: string;
+  result: GitOperationResult;
+}
+
+export interface WorkspaceOperationResult {
+  workspace: string;
+  succeeded: number;
+  failed: number;
+  results: WorkspaceRepoResult[];
+}
+
+// A repository to open from the command line or a forky://open link
+export interface OpenRequest {
+  path: string;
+  commit: string | null;
+}
+
+export interface ShellIntegrationStatus {
+  cli_path: string;
+  cli_installed: boolean;
+  cli_on_path: boolean; // false: the shim's folder still has to be added to PATH
+  context_menu_installed: boolean;
+  context_menu_supported: boolean; // false where the entry comes with the app bundle (macOS)
+}
+
+// Environment health report from run_diagnostics
+export interface Diagnostics {
+  git: { installed: boolean; version: string | null };
+  libgit2_version: string;
+  git_flow_version: string | null; // the git-flow extension; Forky's Git Flow works without it
+  ssh_version: string | null;
+  credential_helpers: string[];
+  editor: string | null;
+  terminal: string | null;
+  os: string;
+}
+
+// Features of the installed git, from get_git_capabilities; older versions
+// fall back to git checkout
+export interface GitCapabilities {
+  version: string | null; // null when git could not be run
+  switch: boolean; // git switch, 2.23+
+  restore: boolean; // git restore, 2.23+
+  sparse_checkout: boolean; // git sparse-checkout, 2.25+
+  warnings: string[]; // one per missing feature, with the version needed
+}
+
+// Credential typed by the user to retry an operation that returned credential_required
+export interface SuppliedCredential {
+  username: string | null;
+  password: string | null;
+  passphrase: string | null; // for an encrypted SSH key
+  save: boolean; // store the username/password with the git credential helper
+}
+
+// Operation to retry with a credential, with the options of the dialog it was
+// started from (null for the plain git_fetch, git_pull and git_push)
+export type NetworkOperation =
+  | { kind: 'fetch'; options: { remote: string | null; all: boolean } | null }
+  | {
+      kind: 'pull';
+      options: { remote: string; branch: string; rebase: boolean; autostash: boolean } | null;
+    }
+  | {
+      kind: 'push';
+      options: {
+        branch: string;
+        remote: string;
+        remote_branch: string;
+        push_tags: boolean;
+        force_with_lease: boolean;
+      } | null;
+    };
+
+// Identity used for commits in a repository, after conditional includes
+export interface RepositoryIdentity {
+  name: string | null;
+  email: string | null;
+  name_origin: string | null; // config file that sets it
+  email_origin: string | null;
+}
+
+// A host key returned by scan_ssh_host_keys
+export interface ScannedHostKey {
+  key_type: string;
+  fingerprint: string;
+  published: boolean | null; // matches the host's published fingerprints; null if it publishes none
+}
+
+// Access token status of a remote (the token itself stays in the OS keychain)
+export interface RemoteTokenInfo {
+  remote: string;
+  url: string;
+  https: boolean; // tokens can only be attached to HTTP(S) remotes
+  has_token: boolean;
+  username: string | null;
+}
+
+export interface CheckoutPreflight {
+  branch_name: string;
+  is_current: boolean;
+  staged_files: number;
+  unstaged_files: number;
+  untracked_files: number;
+  stash_count: number;
+  will_conflict: boolean; // a plain checkout would refuse to switch
+  blocking_files: string[]; // local changes a plain checkout would overwrite
+  autostash_clean: boolean | null; // null when there is nothing to stash
+  autostash_conflicts: string[]; // files that would conflict when restoring the stash
+}
+
+export type RewriteOperation =
+  | { kind: 'amend' }
+  | { kind: 'rebase'; onto: string }
+  | { kind: 'force_push'; branch: string; remote: string; remote_branch: string };
+
+export interface RewritePreflight {
+  published_count: number;
+  published_commits: CommitInfo[]; // newest first, at most 100
+  remote_refs: string[]; // remote branches holding them, e.g. "origin/main"
+  warning: string | null; // null when no published commit is affected
+}
+
+export type ResetMode = 'soft' | 'mixed' | 'hard';
+
+export interface ResetPreview {
+  target: CommitInfo;
+  mode: ResetMode;
+  lost_count: number; // commits of the branch that are not in the target
+  lost_commits: CommitInfo[]; // newest first, at most 100
+  lost_elsewhere: number; // of those, commits still on another branch or a remote
+  discards_changes: boolean; // hard reset with local changes to tracked files
+  discarded_files: string[];
+  unstaged_files: string[]; // staged changes a mixed reset moves back to the work tree
+}
+
+export interface PullRequest {
+  number: number;
+  title: string;
+  author: string;
+  head_branch: string;
+  head_repository: string | null; // "owner/name"; null if the fork was deleted
+  base_branch: string;
+  draft: boolean;
+  url: string;
+  updated_at: string;
+  local_branch: string | null; // the branch it was checked out into, if it exists
+}
+
+export type HostingProvider = 'github' | 'gitlab' | 'bitbucket';
+
+// Page of a repository's web interface opened by open_remote_url
+export type RemotePageKind = 'repository' | 'branch' | 'commit' | 'file';
+
+export interface CreatePullRequestOptions {
+  title?: string;
+  body?: string;
+  base_branch?: string; // the target repository's default branch when omitted
+  draft?: boolean;
+}
+
+export interface CreatedPullRequest {
+  provider: HostingProvider;
+  url: string;
+  created: boolean; // false: url is the provider's "new pull request" page, opened in the browser
+  number: number | null;
+  head_branch: string;
+  base_branch: string | null;
+}
+
+export type CheckState = 'pass' | 'fail' | 'pending' | 'neutral';
+
+// One GitHub check run / commit status or GitLab pipeline job
+export interface Check {
+  name: string;
+  state: CheckState;
+  url: string | null;
+}
+
+// CI status of a branch's head commit, from get_check_status
+export interface CheckStatus {
+  commit: string;
+  provider: HostingProvider;
+  state: CheckState | null; // null when no checks ran on the commit
+  checks: Check[];
+}
+
+// Extra environment for the git commands Forky runs (and the hooks they run)
+export interface GitEnvironment {
+  path_entries: string[]; // prepended to PATH, in order
+  variables: Record<string, string>;
+}
+
+// Hook framework (husky, pre-commit) configured in a repository
+export interface HookFrameworkStatus {
+  framework: 'husky' | 'pre-commit';
+  config_path: string; // relative to the work tree
+  hooks_installed: boolean;
+  runner: string; // program the hooks need
+  runner_path: string | null; // null when it is not on the PATH git gets
+  warnings: string[];
+}
+
+// Commit message lint rules, stored under forky.commitLint.* in the git config
+export interface CommitLintRules {
+  subject_max_length: number; // 0 disables the check
+  body_max_line_length: number; // 0 disables the check
+  imperative_subject: boolean;
+  subject_period: boolean;
+  blank_line_after_subject: boolean;
+  trailing_whitespace: boolean;
+}
+
+export interface CommitLintWarning {
+  rule: keyof CommitLintRules;
+  line: number; // 1-based line of the message
+  message: string;
+}
+
+// Issue key found in the current branch name (get_branch_issue_key)
+export interface IssueKey {
+  key: string; // "PROJ-42", or "#456" for a bare number
+  branch: string;
+  prefix: string; // commit message prefix, e.g. "PROJ-42: "
+}
+
+export interface BundleRemote {
+  name: string;
+  url: string; // without credentials
+  push_url?: string | null;
+}
+
+// Shareable repository setup (export_onboarding_bundle / import_onboarding_bundle);
+// every section but the version is optional
+export interface OnboardingBundle {
+  version: number;
+  remotes?: BundleRemote[];
+  gitflow?: GitFlowConfig | null;
+  issue_patterns?: string[] | null; // null for the default patterns
+  issue_prefix?: string | null;
+  commit_lint?: CommitLintRules | null;
+  verification?: VerificationSettings | null;
+}
+
+export interface BundleImportSummary {
+  added_remotes: string[];
+  updated_remotes: string[];
+  applied: string[]; // settings written, to show the user
+  skipped_commands: string[]; // commands not imported, to confirm before allowing them
+}
+
+// Timings of one backend command since the trace was enabled
+export interface CommandTiming {
+  command: string; // e.g. "get_file_status_separated"
+  calls: number;
+  failures: number;
+  total_ms: number;
+  average_ms: number;
+  max_ms: number;
+  last_ms: number;
+}
+
+// Result of get_performance_report (opt-in, see set_performance_trace)
+export interface PerformanceReport {
+  enabled: boolean;
+  started_at: number | null; // unix timestamp the totals start from
+  commands: CommandTiming[]; // most total time first
+}
+
+export interface ScratchCleanup {
+  removed_files: number;
+  freed_bytes: number;
+}